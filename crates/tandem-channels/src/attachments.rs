@@ -0,0 +1,199 @@
+//! File delivery helpers.
+//!
+//! Run artifacts (`report.md`, `diff.patch`, …) are uploaded through
+//! [`Channel::send_file`](crate::traits::Channel::send_file). The dispatcher
+//! falls back to a link into the server's artifact store when a file exceeds
+//! the platform's upload limit or the upload fails.
+//!
+//! Uploads are `multipart/form-data`; [`MultipartForm`] builds those bodies so
+//! adapters can send them with a plain `reqwest` body.
+
+use std::time::Duration;
+
+use crate::traits::OutboundFile;
+
+/// Request timeout for uploads, which can be much slower than messages.
+pub const UPLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+/// Longest caption sent with an upload (Telegram's limit).
+pub const MAX_CAPTION_CHARS: usize = 1_024;
+
+/// Content type for a file name, by extension.
+pub fn content_type_for(file_name: &str) -> &'static str {
+    let ext = file_name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "md" | "markdown" => "text/markdown; charset=utf-8",
+        "txt" | "log" => "text/plain; charset=utf-8",
+        "patch" | "diff" => "text/x-diff; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "html" | "htm" => "text/html; charset=utf-8",
+        "json" => "application/json",
+        "yaml" | "yml" => "application/yaml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        _ => "application/octet-stream",
+    }
+}
+
+/// File name for an artifact: the last segment of its URI, else its label,
+/// else `artifact`.
+pub fn artifact_file_name(uri: &str, label: Option<&str>) -> String {
+    let path = uri.split(['?', '#']).next().unwrap_or_default();
+    let path = path.split_once("://").map(|(_, rest)| rest).unwrap_or(path);
+    path.rsplit(['/', '\\'])
+        .next()
+        .filter(|name| !name.is_empty() && name.contains('.'))
+        .or(label.filter(|label| !label.trim().is_empty()))
+        .unwrap_or("artifact")
+        .to_string()
+}
+
+/// Human-readable size, e.g. `12.5 MB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// Caption cut to [`MAX_CAPTION_CHARS`].
+pub fn caption_text(file: &OutboundFile) -> Option<String> {
+    let caption = file.caption.as_deref()?.trim();
+    if caption.is_empty() {
+        return None;
+    }
+    Some(caption.chars().take(MAX_CAPTION_CHARS).collect())
+}
+
+/// A `multipart/form-data` request body.
+pub struct MultipartForm {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl Default for MultipartForm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MultipartForm {
+    pub fn new() -> Self {
+        Self {
+            boundary: format!("tandem-{}", uuid::Uuid::new_v4().simple()),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn text(mut self, name: &str, value: &str) -> Self {
+        self.part_header(name, None, None);
+        self.body.extend_from_slice(value.as_bytes());
+        self.body.extend_from_slice(b"\r\n");
+        self
+    }
+
+    pub fn file(mut self, name: &str, file: &OutboundFile) -> Self {
+        self.part_header(name, Some(&file.file_name), Some(&file.content_type));
+        self.body.extend_from_slice(&file.bytes);
+        self.body.extend_from_slice(b"\r\n");
+        self
+    }
+
+    /// `(content type header, body)`.
+    pub fn finish(mut self) -> (String, Vec<u8>) {
+        self.body
+            .extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        (
+            format!("multipart/form-data; boundary={}", self.boundary),
+            self.body,
+        )
+    }
+
+    fn part_header(&mut self, name: &str, file_name: Option<&str>, content_type: Option<&str>) {
+        let mut header = format!(
+            "--{}\r\nContent-Disposition: form-data; name=\"{}\"",
+            self.boundary,
+            quote_param(name)
+        );
+        if let Some(file_name) = file_name {
+            header.push_str(&format!("; filename=\"{}\"", quote_param(file_name)));
+        }
+        header.push_str("\r\n");
+        if let Some(content_type) = content_type {
+            header.push_str(&format!("Content-Type: {content_type}\r\n"));
+        }
+        header.push_str("\r\n");
+        self.body.extend_from_slice(header.as_bytes());
+    }
+}
+
+fn quote_param(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| if c == '"' { '\'' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_types_and_sizes() {
+        assert_eq!(
+            artifact_file_name("file://reports/out/report.md", None),
+            "report.md"
+        );
+        assert_eq!(
+            artifact_file_name("https://example.com/a/diff.patch?x=1", None),
+            "diff.patch"
+        );
+        assert_eq!(
+            artifact_file_name("memory://run-1", Some("summary")),
+            "summary"
+        );
+        assert_eq!(content_type_for("diff.PATCH"), "text/x-diff; charset=utf-8");
+        assert_eq!(content_type_for("blob"), "application/octet-stream");
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(52 * 1024 * 1024), "52.0 MB");
+    }
+
+    #[test]
+    fn multipart_body_has_text_and_file_parts() {
+        let file = OutboundFile {
+            file_name: "re\"port.md".to_string(),
+            content_type: "text/markdown".to_string(),
+            bytes: b"# Hi".to_vec(),
+            caption: None,
+        };
+        let (content_type, body) = MultipartForm::new()
+            .text("chat_id", "42")
+            .file("document", &file)
+            .finish();
+        let boundary = content_type
+            .strip_prefix("multipart/form-data; boundary=")
+            .unwrap();
+        let body = String::from_utf8(body).unwrap();
+        assert!(body.starts_with(&format!("--{boundary}\r\n")));
+        assert!(body.contains("name=\"chat_id\"\r\n\r\n42\r\n"));
+        assert!(
+            body.contains("filename=\"re'port.md\"\r\nContent-Type: text/markdown\r\n\r\n# Hi\r\n")
+        );
+        assert!(body.ends_with(&format!("--{boundary}--\r\n")));
+    }
+}
@@ -2,9 +2,13 @@
 //!
 //! Config is loaded in priority order: environment variables > `config.json`.
 //! Calling `ChannelsConfig::from_env()` reads the relevant `TANDEM_*` env vars
-//! and returns `Err` only if *no* channels are configured.
+//! and returns `Err` if *no* channels are configured or a timezone or
+//! quiet-hours window can't be parsed.
 
-use anyhow::bail;
+use anyhow::{anyhow, bail};
+
+use crate::delivery::QuietHours;
+use crate::locale::LocaleSettings;
 
 /// Top-level channels configuration.
 #[derive(Debug, Clone, Default)]
@@ -18,6 +22,8 @@ pub struct ChannelsConfig {
     pub api_token: String,
     /// Default policy for tool execution coming from channel commands
     pub tool_policy: ChannelToolPolicy,
+    /// Language and display timezone for user-facing replies.
+    pub locale: LocaleSettings,
 }
 
 #[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
@@ -36,6 +42,8 @@ pub struct TelegramConfig {
     pub allowed_users: Vec<String>,
     /// Only respond when the bot is @-mentioned (useful in group chats).
     pub mention_only: bool,
+    /// Hold routine outputs and other proactive messages during this window.
+    pub quiet_hours: Option<QuietHours>,
 }
 
 #[derive(Debug, Clone)]
@@ -47,6 +55,10 @@ pub struct DiscordConfig {
     pub allowed_users: Vec<String>,
     /// Only respond to messages that @-mention the bot.
     pub mention_only: bool,
+    /// Hold routine outputs and other proactive messages during this window.
+    pub quiet_hours: Option<QuietHours>,
+    /// Earlier messages to import when the bot joins a conversation; `0` = off.
+    pub history_import: usize,
 }
 
 #[derive(Debug, Clone)]
@@ -56,6 +68,10 @@ pub struct SlackConfig {
     pub channel_id: String,
     /// `["*"]` = allow everyone.
     pub allowed_users: Vec<String>,
+    /// Hold routine outputs and other proactive messages during this window.
+    pub quiet_hours: Option<QuietHours>,
+    /// Earlier messages to import when the bot joins a conversation; `0` = off.
+    pub history_import: usize,
 }
 
 /// Parse a comma-separated allowed_users string into a Vec.
@@ -78,8 +94,33 @@ pub fn is_user_allowed(user: &str, allowed_users: &[String]) -> bool {
     allowed_users.iter().any(|a| a == "*" || a == user)
 }
 
+/// Read `TANDEM_{PREFIX}_HISTORY_IMPORT`, the number of earlier messages
+/// to import as context; unset or invalid means off.
+fn history_import_from_env(prefix: &str) -> usize {
+    std::env::var(format!("TANDEM_{prefix}_HISTORY_IMPORT"))
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+/// Read `TANDEM_{PREFIX}_QUIET_HOURS` (`HH:MM-HH:MM`) and its optional
+/// `TANDEM_{PREFIX}_QUIET_HOURS_TZ` companion.
+fn quiet_hours_from_env(prefix: &str) -> anyhow::Result<Option<QuietHours>> {
+    let Ok(raw) = std::env::var(format!("TANDEM_{prefix}_QUIET_HOURS")) else {
+        return Ok(None);
+    };
+    let timezone = std::env::var(format!("TANDEM_{prefix}_QUIET_HOURS_TZ"))
+        .ok()
+        .map(|tz| tz.trim().to_string())
+        .filter(|tz| !tz.is_empty());
+    QuietHours::parse(&raw, timezone)
+        .map(Some)
+        .map_err(|err| anyhow!("TANDEM_{prefix}_QUIET_HOURS: {err}"))
+}
+
 impl ChannelsConfig {
-    /// Build from environment variables. Returns `Err` if no channels are configured.
+    /// Build from environment variables. Returns `Err` if no channels are
+    /// configured or a timezone or quiet-hours value is invalid.
     pub fn from_env() -> anyhow::Result<Self> {
         let server_base_url = std::env::var("TANDEM_SERVER_URL")
             .unwrap_or_else(|_| "http://127.0.0.1:39731".to_string());
@@ -91,9 +132,13 @@ impl ChannelsConfig {
             _ => ChannelToolPolicy::RequireApproval,
         };
 
-        let telegram = Self::telegram_from_env();
-        let discord = Self::discord_from_env();
-        let slack = Self::slack_from_env();
+        let telegram = Self::telegram_from_env()?;
+        let discord = Self::discord_from_env()?;
+        let slack = Self::slack_from_env()?;
+        let locale = LocaleSettings::from_env();
+        locale
+            .validate()
+            .map_err(|err| anyhow!("TANDEM_TIMEZONE: {err}"))?;
 
         if telegram.is_none() && discord.is_none() && slack.is_none() {
             bail!(
@@ -109,13 +154,14 @@ impl ChannelsConfig {
             server_base_url,
             api_token,
             tool_policy,
+            locale,
         })
     }
 
-    fn telegram_from_env() -> Option<TelegramConfig> {
-        let bot_token = std::env::var("TANDEM_TELEGRAM_BOT_TOKEN").ok()?;
+    fn telegram_from_env() -> anyhow::Result<Option<TelegramConfig>> {
+        let bot_token = std::env::var("TANDEM_TELEGRAM_BOT_TOKEN").unwrap_or_default();
         if bot_token.trim().is_empty() {
-            return None;
+            return Ok(None);
         }
         let allowed_users = std::env::var("TANDEM_TELEGRAM_ALLOWED_USERS")
             .map(|s| parse_allowed_users(&s))
@@ -123,17 +169,18 @@ impl ChannelsConfig {
         let mention_only = std::env::var("TANDEM_TELEGRAM_MENTION_ONLY")
             .map(|v| v == "1" || v.to_lowercase() == "true")
             .unwrap_or(false);
-        Some(TelegramConfig {
+        Ok(Some(TelegramConfig {
             bot_token,
             allowed_users,
             mention_only,
-        })
+            quiet_hours: quiet_hours_from_env("TELEGRAM")?,
+        }))
     }
 
-    fn discord_from_env() -> Option<DiscordConfig> {
-        let bot_token = std::env::var("TANDEM_DISCORD_BOT_TOKEN").ok()?;
+    fn discord_from_env() -> anyhow::Result<Option<DiscordConfig>> {
+        let bot_token = std::env::var("TANDEM_DISCORD_BOT_TOKEN").unwrap_or_default();
         if bot_token.trim().is_empty() {
-            return None;
+            return Ok(None);
         }
         let guild_id = std::env::var("TANDEM_DISCORD_GUILD_ID").ok();
         let allowed_users = std::env::var("TANDEM_DISCORD_ALLOWED_USERS")
@@ -142,31 +189,35 @@ impl ChannelsConfig {
         let mention_only = std::env::var("TANDEM_DISCORD_MENTION_ONLY")
             .map(|v| v == "1" || v.to_lowercase() == "true")
             .unwrap_or(true); // default true for Discord — avoids bots fighting each other
-        Some(DiscordConfig {
+        Ok(Some(DiscordConfig {
             bot_token,
             guild_id,
             allowed_users,
             mention_only,
-        })
+            quiet_hours: quiet_hours_from_env("DISCORD")?,
+            history_import: history_import_from_env("DISCORD"),
+        }))
     }
 
-    fn slack_from_env() -> Option<SlackConfig> {
-        let bot_token = std::env::var("TANDEM_SLACK_BOT_TOKEN").ok()?;
+    fn slack_from_env() -> anyhow::Result<Option<SlackConfig>> {
+        let bot_token = std::env::var("TANDEM_SLACK_BOT_TOKEN").unwrap_or_default();
         if bot_token.trim().is_empty() {
-            return None;
+            return Ok(None);
         }
-        let channel_id = std::env::var("TANDEM_SLACK_CHANNEL_ID").ok()?;
+        let channel_id = std::env::var("TANDEM_SLACK_CHANNEL_ID").unwrap_or_default();
         if channel_id.trim().is_empty() {
-            return None;
+            return Ok(None);
         }
         let allowed_users = std::env::var("TANDEM_SLACK_ALLOWED_USERS")
             .map(|s| parse_allowed_users(&s))
             .unwrap_or_else(|_| vec!["*".to_string()]);
-        Some(SlackConfig {
+        Ok(Some(SlackConfig {
             bot_token,
             channel_id,
             allowed_users,
-        })
+            quiet_hours: quiet_hours_from_env("SLACK")?,
+            history_import: history_import_from_env("SLACK"),
+        }))
     }
 }
 
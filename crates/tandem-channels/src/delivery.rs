@@ -0,0 +1,459 @@
+//! Proactive channel delivery with quiet hours.
+//!
+//! Replies to a sender go out immediately, but messages Tandem pushes on its
+//! own (routine outputs, approval notices) pass through a [`DeliveryQueue`].
+//! Each channel may configure a [`QuietHours`] window; non-urgent deliveries
+//! that land inside it are held and flushed when the window ends. Every
+//! delivery gets a [`DeliveryStatus`] that records whether and until when it
+//! was deferred.
+//!
+//! Pending and recent deliveries are persisted next to the session map as
+//! `channel_deliveries.json` so deferred messages survive a restart.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use chrono::{DateTime, Local, TimeZone, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::locale::TimezoneSpec;
+use crate::traits::{Channel, SendMessage};
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+/// Finished deliveries kept for status lookups.
+const MAX_FINISHED_DELIVERIES: usize = 200;
+
+/// A daily do-not-disturb window, e.g. `22:00`–`07:00`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    /// Window start, `HH:MM`.
+    pub start: String,
+    /// Window end, `HH:MM`. May be earlier than `start` for overnight windows.
+    pub end: String,
+    /// `UTC`, `local` or a fixed offset. Defaults to the locale timezone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+impl QuietHours {
+    /// Parse the `HH:MM-HH:MM` form used by the `TANDEM_*_QUIET_HOURS` env vars.
+    pub fn parse(raw: &str, timezone: Option<String>) -> Result<Self, String> {
+        let (start, end) = raw
+            .trim()
+            .split_once('-')
+            .ok_or_else(|| format!("quiet hours `{}` must look like `22:00-07:00`", raw.trim()))?;
+        let quiet = Self {
+            start: start.trim().to_string(),
+            end: end.trim().to_string(),
+            timezone,
+        };
+        quiet.validate()?;
+        Ok(quiet)
+    }
+
+    /// Check both clocks and, when set, the timezone.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.minutes().is_none() {
+            return Err(format!(
+                "quiet hours `{}-{}` must use HH:MM clocks",
+                self.start, self.end
+            ));
+        }
+        if let Some(timezone) = self.timezone.as_deref() {
+            TimezoneSpec::validate(timezone)?;
+        }
+        Ok(())
+    }
+
+    fn minutes(&self) -> Option<(u32, u32)> {
+        Some((parse_clock(&self.start)?, parse_clock(&self.end)?))
+    }
+
+    fn zone(&self, fallback: &TimezoneSpec) -> TimezoneSpec {
+        self.timezone
+            .as_deref()
+            .and_then(TimezoneSpec::parse)
+            .unwrap_or_else(|| fallback.clone())
+    }
+
+    /// When `now_ms` falls inside the window, the epoch-ms time it ends.
+    pub fn deferred_until(&self, now_ms: u64, fallback_zone: &TimezoneSpec) -> Option<u64> {
+        let (start, end) = self.minutes()?;
+        if start == end {
+            return None;
+        }
+        let utc = DateTime::<Utc>::from_timestamp_millis(now_ms as i64)?;
+        let (minute, second) = match self.zone(fallback_zone) {
+            TimezoneSpec::Utc => (utc.hour() * 60 + utc.minute(), utc.second()),
+            TimezoneSpec::Local => {
+                let local = Local.from_utc_datetime(&utc.naive_utc());
+                (local.hour() * 60 + local.minute(), local.second())
+            }
+            TimezoneSpec::Fixed(offset) => {
+                let shifted = offset.from_utc_datetime(&utc.naive_utc());
+                (shifted.hour() * 60 + shifted.minute(), shifted.second())
+            }
+        };
+        let quiet = if start < end {
+            (start..end).contains(&minute)
+        } else {
+            minute >= start || minute < end
+        };
+        if !quiet {
+            return None;
+        }
+        let minutes_left = (end + MINUTES_PER_DAY - minute) % MINUTES_PER_DAY;
+        let minute_floor_ms = now_ms - (second as u64) * 1000 - now_ms % 1000;
+        Some(minute_floor_ms + minutes_left as u64 * 60_000)
+    }
+}
+
+fn parse_clock(raw: &str) -> Option<u32> {
+    let (hours, minutes) = raw.trim().split_once(':')?;
+    let hours = hours.parse::<u32>().ok()?;
+    let minutes = minutes.parse::<u32>().ok()?;
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    Some(hours * 60 + minutes)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryState {
+    /// Held for quiet hours; sent once `deferred_until_ms` passes.
+    Deferred,
+    Delivered,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeliveryStatus {
+    pub delivery_id: String,
+    pub channel: String,
+    pub recipient: String,
+    /// What produced the message, e.g. `routine.output` or `routine.approval`.
+    pub source: String,
+    pub urgent: bool,
+    pub state: DeliveryState,
+    pub created_at_ms: u64,
+    /// Set when the delivery was held for quiet hours; kept after it is sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deferred_until_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delivered_at_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DeliveryRecord {
+    status: DeliveryStatus,
+    /// Message body, kept only while the delivery is deferred.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    /// Formatted platform payload of a deferred delivery.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    payload: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// Outbound deliveries for all channels, honouring each channel's quiet hours.
+pub struct DeliveryQueue {
+    path: PathBuf,
+    quiet_hours: HashMap<String, QuietHours>,
+    fallback_zone: TimezoneSpec,
+    records: Mutex<Vec<DeliveryRecord>>,
+}
+
+impl DeliveryQueue {
+    pub async fn load(
+        path: PathBuf,
+        quiet_hours: HashMap<String, QuietHours>,
+        fallback_zone: TimezoneSpec,
+    ) -> Self {
+        let records = tokio::fs::read(&path)
+            .await
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            quiet_hours,
+            fallback_zone,
+            records: Mutex::new(records),
+        }
+    }
+
+    /// Send `message` now, or hold it when the channel is in quiet hours and
+    /// the delivery is not urgent.
+    pub async fn deliver(
+        &self,
+        channel: &dyn Channel,
+        message: SendMessage,
+        source: &str,
+        urgent: bool,
+        now_ms: u64,
+    ) -> DeliveryStatus {
+        let deferred_until_ms = if urgent {
+            None
+        } else {
+            self.quiet_hours
+                .get(channel.name())
+                .and_then(|quiet| quiet.deferred_until(now_ms, &self.fallback_zone))
+        };
+        let mut status = DeliveryStatus {
+            delivery_id: format!("delivery-{}", uuid::Uuid::new_v4()),
+            channel: channel.name().to_string(),
+            recipient: message.recipient.clone(),
+            source: source.to_string(),
+            urgent,
+            state: DeliveryState::Deferred,
+            created_at_ms: now_ms,
+            deferred_until_ms,
+            delivered_at_ms: None,
+            error: None,
+        };
+        let (content, payload) = if deferred_until_ms.is_some() {
+            (Some(message.content), message.payload)
+        } else {
+            finish(&mut status, channel.send(&message).await, now_ms);
+            (None, None)
+        };
+        self.records.lock().await.push(DeliveryRecord {
+            status: status.clone(),
+            content,
+            payload,
+        });
+        self.persist().await;
+        status
+    }
+
+    /// Whether a non-urgent delivery to `channel` would be held right now.
+    pub fn in_quiet_hours(&self, channel: &str, now_ms: u64) -> bool {
+        self.quiet_hours
+            .get(channel)
+            .and_then(|quiet| quiet.deferred_until(now_ms, &self.fallback_zone))
+            .is_some()
+    }
+
+    /// Send every deferred delivery whose quiet-hours window has ended.
+    pub async fn flush_due(&self, channels: &[Arc<dyn Channel>], now_ms: u64) -> usize {
+        let due = {
+            let records = self.records.lock().await;
+            records
+                .iter()
+                .filter(|r| r.status.state == DeliveryState::Deferred)
+                .filter(|r| r.status.deferred_until_ms.unwrap_or(0) <= now_ms)
+                .map(|r| {
+                    (
+                        r.status.clone(),
+                        r.content.clone().unwrap_or_default(),
+                        r.payload.clone(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        if due.is_empty() {
+            return 0;
+        }
+        let mut sent = Vec::with_capacity(due.len());
+        for (mut status, content, payload) in due {
+            let result = match channels.iter().find(|c| c.name() == status.channel) {
+                Some(channel) => {
+                    channel
+                        .send(&SendMessage {
+                            content,
+                            recipient: status.recipient.clone(),
+                            payload,
+                        })
+                        .await
+                }
+                None => Err(anyhow::anyhow!(
+                    "channel `{}` is not running",
+                    status.channel
+                )),
+            };
+            finish(&mut status, result, now_ms);
+            sent.push(status);
+        }
+        let flushed = sent.len();
+        {
+            let mut records = self.records.lock().await;
+            for status in sent {
+                if let Some(record) = records
+                    .iter_mut()
+                    .find(|r| r.status.delivery_id == status.delivery_id)
+                {
+                    record.status = status;
+                    record.content = None;
+                    record.payload = None;
+                }
+            }
+        }
+        self.persist().await;
+        flushed
+    }
+
+    pub async fn statuses(&self) -> Vec<DeliveryStatus> {
+        self.records
+            .lock()
+            .await
+            .iter()
+            .map(|r| r.status.clone())
+            .collect()
+    }
+
+    async fn persist(&self) {
+        let payload = {
+            let mut records = self.records.lock().await;
+            let finished = records
+                .iter()
+                .filter(|r| r.status.state != DeliveryState::Deferred)
+                .count();
+            let mut excess = finished.saturating_sub(MAX_FINISHED_DELIVERIES);
+            records.retain(|r| {
+                if excess > 0 && r.status.state != DeliveryState::Deferred {
+                    excess -= 1;
+                    return false;
+                }
+                true
+            });
+            serde_json::to_vec_pretty(&*records)
+        };
+        let Ok(payload) = payload else {
+            return;
+        };
+        let _ = crate::dispatcher::write_state_file(&self.path, &payload).await;
+    }
+}
+
+fn finish(status: &mut DeliveryStatus, result: anyhow::Result<()>, now_ms: u64) {
+    match result {
+        Ok(()) => {
+            status.state = DeliveryState::Delivered;
+            status.delivered_at_ms = Some(now_ms);
+        }
+        Err(e) => {
+            status.state = DeliveryState::Failed;
+            status.error = Some(e.to_string());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::traits::ChannelMessage;
+
+    const HOUR_MS: u64 = 3_600_000;
+
+    struct RecordingChannel {
+        sent: std::sync::Mutex<Vec<String>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Channel for RecordingChannel {
+        fn name(&self) -> &str {
+            "telegram"
+        }
+
+        async fn send(&self, message: &SendMessage) -> anyhow::Result<()> {
+            self.sent.lock().unwrap().push(message.content.clone());
+            Ok(())
+        }
+
+        async fn listen(
+            &self,
+            _tx: tokio::sync::mpsc::Sender<ChannelMessage>,
+        ) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn overnight_window_defers_until_its_end() {
+        let quiet = QuietHours::parse("22:00-07:00", None).unwrap();
+        // 1970-01-02 03:00:30 UTC
+        let night = 24 * HOUR_MS + 3 * HOUR_MS + 30_000;
+        assert_eq!(
+            quiet.deferred_until(night, &TimezoneSpec::Utc),
+            Some(24 * HOUR_MS + 7 * HOUR_MS)
+        );
+        assert_eq!(quiet.deferred_until(12 * HOUR_MS, &TimezoneSpec::Utc), None);
+        // 23:00 UTC is 01:00 at +02:00, still quiet until 07:00 local (05:00 UTC).
+        let zoned = QuietHours::parse("22:00-07:00", Some("+02:00".to_string())).unwrap();
+        assert_eq!(
+            zoned.deferred_until(23 * HOUR_MS, &TimezoneSpec::Utc),
+            Some(29 * HOUR_MS)
+        );
+        assert!(QuietHours::parse("25:00-07:00", None).is_err());
+        assert!(QuietHours::parse("22:00", None).is_err());
+        let err =
+            QuietHours::parse("22:00-07:00", Some("America/New_York".to_string())).unwrap_err();
+        assert!(err.contains("America/New_York"));
+    }
+
+    #[tokio::test]
+    async fn quiet_hours_hold_non_urgent_deliveries() {
+        let path = std::env::temp_dir().join(format!(
+            "tandem-channel-deliveries-{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        let quiet = HashMap::from([(
+            "telegram".to_string(),
+            QuietHours::parse("22:00-07:00", None).unwrap(),
+        )]);
+        let queue = DeliveryQueue::load(path.clone(), quiet, TimezoneSpec::Utc).await;
+        let channel = Arc::new(RecordingChannel {
+            sent: std::sync::Mutex::new(Vec::new()),
+        });
+        let message = |content: &str| SendMessage {
+            content: content.to_string(),
+            recipient: "42".to_string(),
+            payload: None,
+        };
+        let night = 3 * HOUR_MS;
+
+        let deferred = queue
+            .deliver(
+                channel.as_ref(),
+                message("report"),
+                "routine.output",
+                false,
+                night,
+            )
+            .await;
+        assert_eq!(deferred.state, DeliveryState::Deferred);
+        assert_eq!(deferred.deferred_until_ms, Some(7 * HOUR_MS));
+        let urgent = queue
+            .deliver(
+                channel.as_ref(),
+                message("alert"),
+                "routine.approval",
+                true,
+                night,
+            )
+            .await;
+        assert_eq!(urgent.state, DeliveryState::Delivered);
+        assert_eq!(*channel.sent.lock().unwrap(), vec!["alert"]);
+
+        let channels = vec![channel.clone() as Arc<dyn Channel>];
+        assert_eq!(queue.flush_due(&channels, 6 * HOUR_MS).await, 0);
+        // Deferred deliveries survive a restart.
+        let reloaded = DeliveryQueue::load(path.clone(), HashMap::new(), TimezoneSpec::Utc).await;
+        assert_eq!(reloaded.flush_due(&channels, 7 * HOUR_MS).await, 1);
+        assert_eq!(*channel.sent.lock().unwrap(), vec!["alert", "report"]);
+        let status = reloaded
+            .statuses()
+            .await
+            .into_iter()
+            .find(|s| s.delivery_id == deferred.delivery_id)
+            .unwrap();
+        assert_eq!(status.state, DeliveryState::Delivered);
+        assert_eq!(status.deferred_until_ms, Some(7 * HOUR_MS));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -1,7 +1,8 @@
 //! Discord channel adapter for Tandem.
 //!
 //! Connects to the Discord Gateway WebSocket, sends an Identify payload,
-//! maintains a heartbeat loop, and dispatches `MESSAGE_CREATE` events.
+//! maintains a heartbeat loop, and dispatches `MESSAGE_CREATE` and
+//! `MESSAGE_REACTION_ADD` events.
 //! Messages are split into 2000-character chunks (Unicode-aware) to comply
 //! with Discord's limit.
 
@@ -16,12 +17,17 @@ use tokio_tungstenite::tungstenite::Message;
 use tracing::{info, warn};
 use uuid::Uuid;
 
+use crate::attachments::{caption_text, MultipartForm, UPLOAD_TIMEOUT};
 use crate::config::{is_user_allowed, DiscordConfig};
-use crate::traits::{Channel, ChannelMessage, SendMessage};
+use crate::traits::{Channel, ChannelMessage, HistoryMessage, OutboundFile, Reaction, SendMessage};
 
 /// Discord's maximum message length for regular messages.
 const DISCORD_MAX_MESSAGE_LENGTH: usize = 2000;
 const DISCORD_API: &str = "https://discord.com/api/v10";
+/// Attachment limit for bot uploads to servers without boosts.
+const DISCORD_MAX_UPLOAD_BYTES: u64 = 10 * 1024 * 1024;
+/// Page size cap of `GET /channels/{id}/messages`.
+const DISCORD_MAX_HISTORY_MESSAGES: usize = 100;
 
 // ---------------------------------------------------------------------------
 // Message splitting
@@ -160,6 +166,7 @@ pub struct DiscordChannel {
     guild_id: Option<String>,
     allowed_users: Vec<String>,
     mention_only: bool,
+    history_import: usize,
     /// Typing indicator handle — single per-channel (Discord typing is per channel).
     typing_handle: Mutex<Option<tokio::task::JoinHandle<()>>>,
 }
@@ -171,6 +178,7 @@ impl DiscordChannel {
             guild_id: config.guild_id,
             allowed_users: config.allowed_users,
             mention_only: config.mention_only,
+            history_import: config.history_import,
             typing_handle: Mutex::new(None),
         }
     }
@@ -185,16 +193,28 @@ impl DiscordChannel {
     fn auth_header(&self) -> String {
         format!("Bot {}", self.bot_token)
     }
-}
 
-#[async_trait]
-impl Channel for DiscordChannel {
-    fn name(&self) -> &str {
-        "discord"
-    }
-
-    async fn send(&self, message: &SendMessage) -> anyhow::Result<()> {
+    /// Post `message`, chunked to Discord's limit, and return the ids of the
+    /// posted messages.
+    async fn post_messages(&self, message: &SendMessage) -> anyhow::Result<Vec<String>> {
         let client = self.http_client();
+        let mut ids = Vec::new();
+        if let Some(payload) = &message.payload {
+            let url = format!("{DISCORD_API}/channels/{}/messages", message.recipient);
+            let resp = client
+                .post(&url)
+                .header("Authorization", self.auth_header())
+                .json(payload)
+                .send()
+                .await?;
+            if !resp.status().is_success() {
+                let status = resp.status();
+                let err = resp.text().await.unwrap_or_default();
+                anyhow::bail!("Discord send failed ({status}): {err}");
+            }
+            ids.extend(posted_message_id(resp).await);
+            return Ok(ids);
+        }
         let chunks = split_message(&message.content);
 
         for (i, chunk) in chunks.iter().enumerate() {
@@ -211,15 +231,161 @@ impl Channel for DiscordChannel {
                 let err = resp.text().await.unwrap_or_default();
                 anyhow::bail!("Discord send failed ({status}): {err}");
             }
+            ids.extend(posted_message_id(resp).await);
 
             // Small inter-chunk delay to avoid rate limiting
             if i < chunks.len() - 1 {
                 tokio::time::sleep(Duration::from_millis(500)).await;
             }
         }
+        Ok(ids)
+    }
+}
+
+/// Id of the message in a successful `POST /channels/{id}/messages` response.
+async fn posted_message_id(resp: reqwest::Response) -> Option<String> {
+    let body = resp.json::<serde_json::Value>().await.ok()?;
+    body["id"].as_str().map(str::to_string)
+}
+
+/// Build a reaction event from a `MESSAGE_REACTION_ADD` payload, skipping the
+/// bot's own reactions.
+fn reaction_message(d: &serde_json::Value, bot_user_id: &str) -> Option<ChannelMessage> {
+    let user_id = d["user_id"].as_str().filter(|id| !id.is_empty())?;
+    if user_id == bot_user_id || d["member"]["user"]["bot"].as_bool().unwrap_or(false) {
+        return None;
+    }
+    let emoji = d["emoji"]["name"].as_str().filter(|e| !e.is_empty())?;
+    let message_id = d["message_id"].as_str().filter(|id| !id.is_empty())?;
+    let channel_id = d["channel_id"].as_str().unwrap_or(user_id);
+    Some(ChannelMessage {
+        id: format!("discord_reaction_{message_id}"),
+        sender: user_id.to_string(),
+        reply_target: channel_id.to_string(),
+        content: String::new(),
+        channel: "discord".to_string(),
+        timestamp: chrono::Utc::now(),
+        attachment: None,
+        user_id: None,
+        reaction: Some(Reaction {
+            emoji: emoji.to_string(),
+            message_id: message_id.to_string(),
+        }),
+        locale: None,
+    })
+}
+
+/// Messages from a `GET /channels/{id}/messages` response (newest first),
+/// oldest first and without empty ones.
+fn history_messages(body: &serde_json::Value) -> Vec<HistoryMessage> {
+    let Some(messages) = body.as_array() else {
+        return Vec::new();
+    };
+    messages
+        .iter()
+        .rev()
+        .filter_map(|m| {
+            let text = m["content"].as_str().map(str::trim).unwrap_or_default();
+            if text.is_empty() {
+                return None;
+            }
+            let author = m["author"]["global_name"]
+                .as_str()
+                .or_else(|| m["author"]["username"].as_str())
+                .unwrap_or("unknown");
+            Some(HistoryMessage {
+                author: author.to_string(),
+                text: text.to_string(),
+                timestamp: m["timestamp"].as_str().map(String::from),
+            })
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Channel for DiscordChannel {
+    fn name(&self) -> &str {
+        "discord"
+    }
+
+    async fn send(&self, message: &SendMessage) -> anyhow::Result<()> {
+        self.post_messages(message).await.map(|_| ())
+    }
+
+    async fn send_reply(&self, message: &SendMessage) -> anyhow::Result<Vec<String>> {
+        self.post_messages(message).await
+    }
+
+    fn max_upload_bytes(&self) -> u64 {
+        DISCORD_MAX_UPLOAD_BYTES
+    }
+
+    async fn send_file(&self, recipient: &str, file: &OutboundFile) -> anyhow::Result<()> {
+        let payload = json!({
+            "content": caption_text(file).unwrap_or_default(),
+            "allowed_mentions": { "parse": [] },
+        });
+        let (content_type, body) = MultipartForm::new()
+            .text("payload_json", &payload.to_string())
+            .file("files[0]", file)
+            .finish();
+        let resp = self
+            .http_client()
+            .post(format!("{DISCORD_API}/channels/{recipient}/messages"))
+            .header("Authorization", self.auth_header())
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .timeout(UPLOAD_TIMEOUT)
+            .body(body)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let err = resp.text().await.unwrap_or_default();
+            anyhow::bail!("Discord upload failed ({status}): {err}");
+        }
         Ok(())
     }
 
+    fn history_import_limit(&self) -> usize {
+        self.history_import.min(DISCORD_MAX_HISTORY_MESSAGES)
+    }
+
+    /// Needs the Read Message History permission in the channel or thread;
+    /// without it Discord answers 403 and nothing is imported.
+    async fn fetch_history(
+        &self,
+        message: &ChannelMessage,
+        limit: usize,
+    ) -> anyhow::Result<Vec<HistoryMessage>> {
+        let Some(message_id) = message.id.strip_prefix("discord_") else {
+            return Ok(Vec::new());
+        };
+        let limit = limit.min(DISCORD_MAX_HISTORY_MESSAGES);
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let resp = self
+            .http_client()
+            .get(format!(
+                "{DISCORD_API}/channels/{}/messages",
+                message.reply_target
+            ))
+            .header("Authorization", self.auth_header())
+            .query(&[
+                ("before", message_id.to_string()),
+                ("limit", limit.to_string()),
+            ])
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let err = resp.text().await.unwrap_or_default();
+            anyhow::bail!("Discord history fetch failed ({status}): {err}");
+        }
+        let body: serde_json::Value = resp.json().await?;
+        Ok(history_messages(&body))
+    }
+
     #[allow(clippy::too_many_lines)]
     async fn listen(&self, tx: mpsc::Sender<ChannelMessage>) -> anyhow::Result<()> {
         let bot_user_id = bot_user_id_from_token(&self.bot_token).unwrap_or_default();
@@ -258,12 +424,13 @@ impl Channel for DiscordChannel {
             .unwrap_or(41_250);
 
         // Send Identify (op 2)
-        // Intents: 37377 = GUILDS | GUILD_MESSAGES | MESSAGE_CONTENT | DIRECT_MESSAGES
+        // Intents: 46593 = GUILDS | GUILD_MESSAGES | GUILD_MESSAGE_REACTIONS |
+        // DIRECT_MESSAGES | DIRECT_MESSAGE_REACTIONS | MESSAGE_CONTENT
         let identify = json!({
             "op": 2,
             "d": {
                 "token": self.bot_token,
-                "intents": 37377,
+                "intents": 46593,
                 "properties": {
                     "os": "linux",
                     "browser": "tandem",
@@ -336,6 +503,21 @@ impl Channel for DiscordChannel {
                     }
 
                     let t = event.get("t").and_then(|t| t.as_str()).unwrap_or("");
+                    if t == "MESSAGE_REACTION_ADD" {
+                        let Some(reaction) = event
+                            .get("d")
+                            .and_then(|d| reaction_message(d, &bot_user_id))
+                        else {
+                            continue;
+                        };
+                        if !is_user_allowed(&reaction.sender, &self.allowed_users) {
+                            continue;
+                        }
+                        if tx.send(reaction).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
                     if t != "MESSAGE_CREATE" {
                         continue;
                     }
@@ -394,6 +576,9 @@ impl Channel for DiscordChannel {
                         channel: "discord".to_string(),
                         timestamp: chrono::Utc::now(),
                         attachment: None,
+                        user_id: None,
+                        reaction: None,
+                        locale: None,
                     };
 
                     if tx.send(channel_msg).await.is_err() {
@@ -452,12 +637,58 @@ impl Channel for DiscordChannel {
 mod tests {
     use super::*;
 
+    #[test]
+    fn reaction_events_skip_bot_and_carry_emoji() {
+        let event = json!({
+            "user_id": "42",
+            "channel_id": "c1",
+            "message_id": "m9",
+            "emoji": {"id": null, "name": "👍"},
+        });
+        let msg = reaction_message(&event, "bot").expect("reaction");
+        let reaction = msg.reaction.as_ref().expect("reaction");
+        assert_eq!(reaction.emoji, "👍");
+        assert_eq!(reaction.message_id, "m9");
+        assert_eq!(msg.reply_target, "c1");
+        assert!(msg.content.is_empty());
+        assert!(reaction_message(&event, "42").is_none());
+    }
+
+    #[test]
+    fn history_is_oldest_first_and_skips_empty_messages() {
+        let body = json!([
+            {"content": "second", "author": {"username": "ben"}, "timestamp": "2026-01-01T10:01:00Z"},
+            {"content": "", "author": {"username": "bot"}},
+            {"content": "first", "author": {"username": "ana", "global_name": "Ana"}}
+        ]);
+        let history = history_messages(&body);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].author, "Ana");
+        assert_eq!(history[0].text, "first");
+        assert_eq!(
+            history[1].timestamp.as_deref(),
+            Some("2026-01-01T10:01:00Z")
+        );
+        assert!(history_messages(&json!({"message": "Missing Access"})).is_empty());
+    }
+
+    #[test]
+    fn history_import_limit_is_capped() {
+        let ch = DiscordChannel {
+            history_import: 500,
+            ..make_channel()
+        };
+        assert_eq!(ch.history_import_limit(), DISCORD_MAX_HISTORY_MESSAGES);
+        assert_eq!(make_channel().history_import_limit(), 0);
+    }
+
     fn make_channel() -> DiscordChannel {
         DiscordChannel {
             bot_token: "fake".into(),
             guild_id: None,
             allowed_users: vec![],
             mention_only: false,
+            history_import: 0,
             typing_handle: Mutex::new(None),
         }
     }
@@ -1,7 +1,8 @@
 //! Session dispatcher — routes incoming channel messages to Tandem sessions.
 //!
 //! Each unique `{channel_name}:{sender_id}` pair maps to one persistent Tandem
-//! session. The mapping is durably persisted under Tandem's app-data state dir
+//! session. Senders linked to a Tandem user (see [`crate::identity`]) share a
+//! single `user:{user_id}` mapping across platforms instead. The mapping is durably persisted under Tandem's app-data state dir
 //! (for example `~/.local/share/tandem/data/channel_sessions.json` on Linux)
 //! and reloaded on startup.
 //!
@@ -14,28 +15,88 @@
 //! | Get session    | `GET  /session/{id}`                 |
 //! | Update session | `PUT  /session/{id}`                 |
 //! | Prompt (sync)  | `POST /session/{id}/prompt_sync`     |
+//! | Feedback       | `POST /session/{id}/message/{message_id}/feedback` |
 //!
 //! ## Slash commands
 //!
 //! `/new [name]`, `/sessions`, `/resume <query>`, `/rename <name>`,
 //! `/status`, `/run`, `/cancel`, `/todos`, `/requests`, `/answer <id> <text>`,
 //! `/providers`, `/models [provider]`, `/model <model_id>`, `/approve <tool_call_id>`,
-//! `/deny <tool_call_id>`, `/help`
-
-use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::Arc;
+//! `/deny <tool_call_id>`, `/approve_run <run_id>`, `/deny_run <run_id> [reason]`,
+//! `/language [code|auto]`, `/quota`, `/help`
+//!
+//! ## Routine approvals and outputs
+//!
+//! Designated routine approvers are notified on every channel their Tandem
+//! user is linked to when a run needs approval (or is escalated to them), by
+//! following `routine.approval.notify` events on `GET /event`.
+//!
+//! Completed routine runs whose output targets include
+//! `channel://{channel}/{recipient}` have their final report sent there, and
+//! failed runs send the failure reason. Append `?urgent=true` to a target to
+//! bypass quiet hours, `?digest=true` for a shortened report, `?template=name`
+//! to pick a template set and `?format=plain` to skip rich formatting (see
+//! [`crate::format`]). `?attach=true` (or a comma-separated list of file
+//! names) uploads the run's file artifacts; files over the platform limit are
+//! sent as expiring Web UI links (see `?secure=true` below), never as links to
+//! the engine's own address.
+//! Artifacts whose metadata carries `deliver_to` targets (see the server's
+//! `channel_send_file` tool) are sent as soon as they are added.
+//!
+//! `?secure=true` keeps a run's content off the platform: the target gets a
+//! short notice with an expiring Web UI link to the report (from
+//! `POST /routines/runs/{id}/secure-links`) and one link per artifact instead
+//! of uploads.
+//!
+//! Server event rules with a `notify_channel` action publish `rule.notify`
+//! events; their text is sent to the rule's `channel://` target. Routine
+//! notification policies publish `routine.run.notify` the same way.
+//!
+//! ## Quiet hours
+//!
+//! These proactive messages go through a [`DeliveryQueue`]: during a channel's
+//! configured quiet hours they are held and flushed when the window ends.
+//! Escalated approval notices and urgent targets are sent immediately.
+//!
+//! ## Conversation history
+//!
+//! Discord and Slack can be configured with `history_import` (or
+//! `TANDEM_{DISCORD,SLACK}_HISTORY_IMPORT`) = N. The first time a
+//! conversation reaches a session, its last N earlier messages are fetched
+//! with the bot's own permissions and summarized into the session via
+//! `POST /session/{id}/context/import`.
+//!
+//! ## Reactions
+//!
+//! Thumbs-up/down style reactions (see [`reaction_rating`]) are recorded as
+//! feedback on the assistant message whose reply was reacted to. Replies are
+//! tracked in memory (the most recent [`MAX_TRACKED_REPLIES`]); reactions to
+//! older replies, and other emoji, are ignored.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 use tokio::sync::{mpsc, Mutex};
 use tokio::task::JoinSet;
 use tracing::{error, info, warn};
 
+use crate::attachments::{artifact_file_name, content_type_for, format_size};
 use crate::config::ChannelsConfig;
+use crate::delivery::{DeliveryQueue, QuietHours};
 use crate::discord::DiscordChannel;
+use crate::format::{MessageKind, MessageTemplates};
+use crate::identity::IdentityDirectory;
+use crate::language::{
+    language_name, language_preferences, normalize_language_tag, response_language_directive,
+    LanguageSource,
+};
+use crate::locale::{LocaleSettings, MessageKey};
 use crate::slack::SlackChannel;
 use crate::telegram::TelegramChannel;
-use crate::traits::{Channel, ChannelMessage, SendMessage};
+use crate::traits::{Channel, ChannelMessage, OutboundFile, Reaction, SendMessage};
 
 // ---------------------------------------------------------------------------
 // Auth helper
@@ -48,6 +109,17 @@ fn add_auth(rb: reqwest::RequestBuilder, token: &str) -> reqwest::RequestBuilder
     rb.header("x-tandem-token", token).bearer_auth(token)
 }
 
+/// Header the server uses to attribute runs to a per-user quota.
+const USER_HEADER: &str = "x-tandem-user-id";
+
+/// Quota principal for a message: the linked Tandem user when known,
+/// otherwise the platform-scoped `{channel}:{sender}`.
+fn quota_user(msg: &ChannelMessage) -> String {
+    msg.user_id
+        .clone()
+        .unwrap_or_else(|| format!("{}:{}", msg.channel, msg.sender))
+}
+
 // ---------------------------------------------------------------------------
 // Session map + persistence
 // ---------------------------------------------------------------------------
@@ -59,13 +131,20 @@ pub struct SessionRecord {
     pub last_seen_at_ms: u64,
     pub channel: String,
     pub sender: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// `{channel}:{conversation}` keys whose history was already imported
+    /// into this session (or could not be).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub imported_conversations: Vec<String>,
 }
 
-/// `{channel_name}:{sender_id}` → Tandem `SessionRecord`
+/// `{channel_name}:{sender_id}` (or `user:{user_id}` once linked) → Tandem `SessionRecord`
 pub type SessionMap = Arc<Mutex<HashMap<String, SessionRecord>>>;
 
-fn persistence_path() -> PathBuf {
-    let base = std::env::var("TANDEM_STATE_DIR")
+/// Directory holding the channel dispatcher's persisted state.
+pub(crate) fn channel_state_dir() -> PathBuf {
+    std::env::var("TANDEM_STATE_DIR")
         .map(PathBuf::from)
         .unwrap_or_else(|_| {
             if let Some(data_dir) = dirs::data_dir() {
@@ -74,8 +153,64 @@ fn persistence_path() -> PathBuf {
             dirs::home_dir()
                 .map(|home| home.join(".tandem").join("data"))
                 .unwrap_or_else(|| PathBuf::from(".tandem"))
-        });
-    base.join("channel_sessions.json")
+        })
+}
+
+/// Replace `path` with `bytes` via a sibling temp file and a rename, so a
+/// crash mid-write leaves the previous state file intact.
+pub(crate) async fn write_state_file(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "state".to_string());
+    let seq = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let tmp = path.with_file_name(format!(".{name}.{}-{seq}.partial", std::process::id()));
+    let result = async {
+        tokio::fs::write(&tmp, bytes).await?;
+        tokio::fs::rename(&tmp, path).await
+    }
+    .await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&tmp).await;
+    }
+    result
+}
+
+fn persistence_path() -> PathBuf {
+    channel_state_dir().join("channel_sessions.json")
+}
+
+/// Session map key for a message: the linked Tandem user when known,
+/// otherwise the platform-scoped `{channel}:{sender}`.
+fn session_key(msg: &ChannelMessage) -> String {
+    match msg.user_id.as_deref() {
+        Some(user_id) => format!("user:{user_id}"),
+        None => format!("{}:{}", msg.channel, msg.sender),
+    }
+}
+
+/// When a sender is newly linked to a Tandem user, carry their existing
+/// platform session over to the user-level key so affinity is preserved.
+async fn adopt_platform_session(msg: &ChannelMessage, session_map: &SessionMap, path: &Path) {
+    if msg.user_id.is_none() {
+        return;
+    }
+    let user_key = session_key(msg);
+    let platform_key = format!("{}:{}", msg.channel, msg.sender);
+    let mut guard = session_map.lock().await;
+    if guard.contains_key(&user_key) {
+        return;
+    }
+    let Some(mut record) = guard.get(&platform_key).cloned() else {
+        return;
+    };
+    record.user_id = msg.user_id.clone();
+    guard.insert(user_key, record);
+    save_session_map_to(path, &guard).await;
 }
 
 /// Load the session map from disk. Returns an empty map if the file doesn't
@@ -109,6 +244,8 @@ async fn load_session_map() -> HashMap<String, SessionRecord> {
                     last_seen_at_ms: now,
                     channel,
                     sender,
+                    user_id: None,
+                    imported_conversations: Vec::new(),
                 },
             );
         }
@@ -120,12 +257,12 @@ async fn load_session_map() -> HashMap<String, SessionRecord> {
 
 /// Persist the session map to disk. Silently ignores I/O errors.
 async fn save_session_map(map: &HashMap<String, SessionRecord>) {
-    let path = persistence_path();
-    if let Some(parent) = path.parent() {
-        let _ = tokio::fs::create_dir_all(parent).await;
-    }
+    save_session_map_to(&persistence_path(), map).await;
+}
+
+async fn save_session_map_to(path: &Path, map: &HashMap<String, SessionRecord>) {
     if let Ok(json) = serde_json::to_vec_pretty(map) {
-        let _ = tokio::fs::write(&path, json).await;
+        let _ = write_state_file(path, &json).await;
     }
 }
 
@@ -135,22 +272,50 @@ async fn save_session_map(map: &HashMap<String, SessionRecord>) {
 
 #[derive(Debug)]
 enum SlashCommand {
-    New { name: Option<String> },
+    New {
+        name: Option<String>,
+    },
     ListSessions,
-    Resume { query: String },
-    Rename { name: String },
+    Resume {
+        query: String,
+    },
+    Rename {
+        name: String,
+    },
     Status,
     Run,
     Cancel,
     Todos,
     Requests,
-    Answer { question_id: String, answer: String },
+    Answer {
+        question_id: String,
+        answer: String,
+    },
     Providers,
-    Models { provider: Option<String> },
-    Model { model_id: String },
+    Models {
+        provider: Option<String>,
+    },
+    Model {
+        model_id: String,
+    },
     Help,
-    Approve { tool_call_id: String },
-    Deny { tool_call_id: String },
+    Approve {
+        tool_call_id: String,
+    },
+    Deny {
+        tool_call_id: String,
+    },
+    ApproveRun {
+        run_id: String,
+    },
+    DenyRun {
+        run_id: String,
+        reason: Option<String>,
+    },
+    Quota,
+    Language {
+        tag: Option<String>,
+    },
 }
 
 fn parse_slash_command(content: &str) -> Option<SlashCommand> {
@@ -223,9 +388,48 @@ fn parse_slash_command(content: &str) -> Option<SlashCommand> {
         }
         return None;
     }
+    if trimmed == "/language" || trimmed == "/lang" {
+        return Some(SlashCommand::Language { tag: None });
+    }
+    if let Some(tag) = trimmed
+        .strip_prefix("/language ")
+        .or_else(|| trimmed.strip_prefix("/lang "))
+    {
+        return Some(SlashCommand::Language {
+            tag: Some(tag.trim().to_string()),
+        });
+    }
+    if trimmed == "/quota" || trimmed == "/usage" {
+        return Some(SlashCommand::Quota);
+    }
     if trimmed == "/help" || trimmed == "/?" {
         return Some(SlashCommand::Help);
     }
+    if let Some(run_id) = trimmed.strip_prefix("/approve_run ") {
+        let run_id = run_id.trim();
+        if !run_id.is_empty() {
+            return Some(SlashCommand::ApproveRun {
+                run_id: run_id.to_string(),
+            });
+        }
+        return None;
+    }
+    if let Some(rest) = trimmed.strip_prefix("/deny_run ") {
+        let mut parts = rest.trim().splitn(2, ' ');
+        let run_id = parts.next().unwrap_or_default().trim();
+        let reason = parts
+            .next()
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(String::from);
+        if !run_id.is_empty() {
+            return Some(SlashCommand::DenyRun {
+                run_id: run_id.to_string(),
+                reason,
+            });
+        }
+        return None;
+    }
     if let Some(id) = trimmed.strip_prefix("/approve ") {
         return Some(SlashCommand::Approve {
             tool_call_id: id.trim().to_string(),
@@ -252,39 +456,979 @@ pub async fn start_channel_listeners(config: ChannelsConfig) -> JoinSet<()> {
         initial_map.len()
     );
 
+    let identities = Arc::new(IdentityDirectory::load().await);
+    info!(
+        "tandem-channels: loaded {} linked channel identities",
+        identities.users.len()
+    );
+
+    let mut quiet_hours: HashMap<String, QuietHours> = HashMap::new();
+    for (name, window) in [
+        ("telegram", config.telegram.as_ref().map(|c| &c.quiet_hours)),
+        ("discord", config.discord.as_ref().map(|c| &c.quiet_hours)),
+        ("slack", config.slack.as_ref().map(|c| &c.quiet_hours)),
+    ] {
+        if let Some(Some(window)) = window {
+            quiet_hours.insert(name.to_string(), window.clone());
+        }
+    }
+    let deliveries = Arc::new(
+        DeliveryQueue::load(
+            channel_state_dir().join("channel_deliveries.json"),
+            quiet_hours,
+            config.locale.zone(),
+        )
+        .await,
+    );
+
     let session_map: SessionMap = Arc::new(Mutex::new(initial_map));
     let mut set = JoinSet::new();
+    let mut approval_channels: Vec<Arc<dyn Channel>> = Vec::new();
 
-    if let Some(tg) = config.telegram {
+    if let Some(mut tg) = config.telegram {
+        tg.allowed_users = identities.expand_allowed_users("telegram", &tg.allowed_users);
         let channel = Arc::new(TelegramChannel::new(tg));
         let map = session_map.clone();
         let base_url = config.server_base_url.clone();
         let api_token = config.api_token.clone();
-        set.spawn(supervise(channel, base_url, api_token, map));
+        let locale = config.locale.clone();
+        let ids = identities.clone();
+        approval_channels.push(channel.clone() as Arc<dyn Channel>);
+        set.spawn(supervise(channel, base_url, api_token, map, locale, ids));
         info!("tandem-channels: Telegram listener started");
     }
 
-    if let Some(dc) = config.discord {
+    if let Some(mut dc) = config.discord {
+        dc.allowed_users = identities.expand_allowed_users("discord", &dc.allowed_users);
         let channel = Arc::new(DiscordChannel::new(dc));
         let map = session_map.clone();
         let base_url = config.server_base_url.clone();
         let api_token = config.api_token.clone();
-        set.spawn(supervise(channel, base_url, api_token, map));
+        let locale = config.locale.clone();
+        let ids = identities.clone();
+        approval_channels.push(channel.clone() as Arc<dyn Channel>);
+        set.spawn(supervise(channel, base_url, api_token, map, locale, ids));
         info!("tandem-channels: Discord listener started");
     }
 
-    if let Some(sl) = config.slack {
+    if let Some(mut sl) = config.slack {
+        sl.allowed_users = identities.expand_allowed_users("slack", &sl.allowed_users);
         let channel = Arc::new(SlackChannel::new(sl));
         let map = session_map.clone();
         let base_url = config.server_base_url.clone();
         let api_token = config.api_token.clone();
-        set.spawn(supervise(channel, base_url, api_token, map));
+        let locale = config.locale.clone();
+        let ids = identities.clone();
+        approval_channels.push(channel.clone() as Arc<dyn Channel>);
+        set.spawn(supervise(channel, base_url, api_token, map, locale, ids));
         info!("tandem-channels: Slack listener started");
     }
 
+    if !approval_channels.is_empty() {
+        set.spawn(flush_deferred_deliveries(
+            approval_channels.clone(),
+            deliveries.clone(),
+        ));
+        set.spawn(watch_routine_events(
+            approval_channels,
+            config.server_base_url.clone(),
+            config.api_token.clone(),
+            identities,
+            deliveries,
+            Arc::new(MessageTemplates::load().await),
+        ));
+    }
+
     set
 }
 
+// ---------------------------------------------------------------------------
+// Routine approval notifications and output delivery
+// ---------------------------------------------------------------------------
+
+/// How often held deliveries are checked against their quiet-hours window.
+const DEFERRED_FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Output target scheme for delivering a routine's final report to a channel.
+const CHANNEL_TARGET_PREFIX: &str = "channel://";
+
+/// Largest artifact downloaded for upload; bigger ones are sent as links.
+const MAX_ATTACHMENT_FETCH_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Follow the server event stream and forward routine approval requests,
+/// channel-targeted routine outputs and routine failures. Reconnects with
+/// exponential backoff.
+async fn watch_routine_events(
+    channels: Vec<Arc<dyn Channel>>,
+    base_url: String,
+    api_token: String,
+    identities: Arc<IdentityDirectory>,
+    deliveries: Arc<DeliveryQueue>,
+    templates: Arc<MessageTemplates>,
+) {
+    let mut backoff_secs: u64 = 1;
+    loop {
+        let outbound = Outbound {
+            channels: &channels,
+            deliveries: &deliveries,
+            templates: &templates,
+        };
+        match follow_routine_events(&outbound, &base_url, &api_token, &identities).await {
+            Ok(()) => backoff_secs = 1,
+            Err(e) => warn!("routine event watcher: {e}"),
+        }
+        tokio::time::sleep(Duration::from_secs(backoff_secs)).await;
+        backoff_secs = (backoff_secs * 2).min(60);
+    }
+}
+
+/// Send deliveries held for quiet hours once their window has ended.
+async fn flush_deferred_deliveries(
+    channels: Vec<Arc<dyn Channel>>,
+    deliveries: Arc<DeliveryQueue>,
+) {
+    loop {
+        let flushed = deliveries.flush_due(&channels, now_ms()).await;
+        if flushed > 0 {
+            info!("tandem-channels: flushed {flushed} deliveries held for quiet hours");
+        }
+        tokio::time::sleep(DEFERRED_FLUSH_INTERVAL).await;
+    }
+}
+
+/// Running channels plus what proactive messages need to reach them.
+struct Outbound<'a> {
+    channels: &'a [Arc<dyn Channel>],
+    deliveries: &'a DeliveryQueue,
+    templates: &'a MessageTemplates,
+}
+
+impl Outbound<'_> {
+    /// Format `kind` for `channel` and hand it to the delivery queue.
+    #[allow(clippy::too_many_arguments)]
+    async fn deliver(
+        &self,
+        channel: &dyn Channel,
+        recipient: &str,
+        kind: MessageKind,
+        target: Option<&ChannelTarget>,
+        vars: &[(&str, String)],
+        source: &str,
+        urgent: bool,
+    ) {
+        let mut formatted = self.templates.render(
+            channel.name(),
+            kind,
+            target.and_then(|t| t.template.as_deref()),
+            vars,
+        );
+        if target.is_some_and(|t| t.plain) {
+            formatted.payload.clear();
+        }
+        let status = self
+            .deliveries
+            .deliver(
+                channel,
+                formatted.into_send(recipient),
+                source,
+                urgent,
+                now_ms(),
+            )
+            .await;
+        if let Some(e) = status.error {
+            warn!("{source} to {}:{recipient} failed: {e}", channel.name());
+        }
+    }
+}
+
+async fn follow_routine_events(
+    outbound: &Outbound<'_>,
+    base_url: &str,
+    api_token: &str,
+    identities: &IdentityDirectory,
+) -> anyhow::Result<()> {
+    use futures_util::StreamExt;
+
+    let client = reqwest::Client::new();
+    let resp = add_auth(client.get(format!("{base_url}/event")), api_token)
+        .header("Accept", "text/event-stream")
+        .send()
+        .await?;
+    if !resp.status().is_success() {
+        anyhow::bail!("event stream failed ({})", resp.status());
+    }
+    let mut body_stream = resp.bytes_stream();
+    let mut line_buf = String::new();
+    while let Some(chunk) = body_stream.next().await {
+        line_buf.push_str(&String::from_utf8_lossy(&chunk?));
+        while let Some(pos) = line_buf.find('\n') {
+            let raw = line_buf[..pos].trim_end_matches('\r').to_string();
+            line_buf = line_buf[pos + 1..].to_string();
+            let Some(data) = raw.strip_prefix("data:").map(str::trim) else {
+                continue;
+            };
+            let Ok(evt) = serde_json::from_str::<serde_json::Value>(data) else {
+                continue;
+            };
+            let Some(props) = evt.get("properties") else {
+                continue;
+            };
+            match evt.get("type").and_then(|v| v.as_str()) {
+                Some("routine.approval.notify") => {
+                    notify_approvers(outbound, identities, props).await
+                }
+                Some("routine.run.completed") => {
+                    deliver_routine_output(outbound, props, &client, base_url, api_token).await
+                }
+                Some("routine.run.failed") => {
+                    deliver_routine_failure(outbound, props, &client, base_url, api_token).await
+                }
+                Some("routine.run.artifact_added") => {
+                    deliver_requested_artifact(outbound, props, &client, base_url, api_token).await
+                }
+                Some("rule.notify") => deliver_rule_notice(outbound, props).await,
+                Some("routine.run.notify") => deliver_routine_alert(outbound, props).await,
+                _ => {}
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn notify_approvers(
+    outbound: &Outbound<'_>,
+    identities: &IdentityDirectory,
+    props: &serde_json::Value,
+) {
+    let vars = approval_vars(props);
+    let escalated = props
+        .get("escalated")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let approvers = props
+        .get("approvers")
+        .and_then(|v| v.as_array())
+        .map(|list| {
+            list.iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    for channel in outbound.channels {
+        for recipient in approval_recipients(identities, &approvers, channel.name()) {
+            outbound
+                .deliver(
+                    channel.as_ref(),
+                    &recipient,
+                    MessageKind::Approval,
+                    None,
+                    &vars,
+                    "routine.approval",
+                    escalated,
+                )
+                .await;
+        }
+    }
+}
+
+/// A parsed `channel://{channel}/{recipient}` output target.
+///
+/// Query options: `urgent=true` bypasses quiet hours, `template={name}` picks
+/// a template set from `channel_templates.json`, `format=plain` disables rich
+/// formatting, `digest=true` sends a shortened report, `attach=true` (or
+/// `attach=report.md,diff.patch`) uploads the run's file artifacts and
+/// `secure=true` sends expiring links in place of any content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ChannelTarget {
+    channel: String,
+    recipient: String,
+    urgent: bool,
+    template: Option<String>,
+    plain: bool,
+    digest: bool,
+    /// Artifact file names or labels to upload; empty means all of them.
+    attach: Option<Vec<String>>,
+    secure: bool,
+}
+
+fn parse_channel_target(target: &str) -> Option<ChannelTarget> {
+    let rest = target.trim().strip_prefix(CHANNEL_TARGET_PREFIX)?;
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let (channel, recipient) = path.split_once('/')?;
+    if channel.is_empty() || recipient.is_empty() {
+        return None;
+    }
+    let mut parsed = ChannelTarget {
+        channel: channel.to_ascii_lowercase(),
+        recipient: recipient.to_string(),
+        urgent: false,
+        template: None,
+        plain: false,
+        digest: false,
+        attach: None,
+        secure: false,
+    };
+    for pair in query.split('&') {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, "true"));
+        let enabled = value == "true" || value == "1";
+        match key {
+            "urgent" => parsed.urgent = enabled,
+            "digest" => parsed.digest = enabled,
+            "secure" => parsed.secure = enabled,
+            "format" => parsed.plain = value.eq_ignore_ascii_case("plain"),
+            "template" if !value.is_empty() => parsed.template = Some(value.to_string()),
+            "attach" => {
+                parsed.attach = match value {
+                    "false" | "0" => None,
+                    "true" | "1" | "all" => Some(Vec::new()),
+                    names => Some(
+                        names
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|name| !name.is_empty())
+                            .map(str::to_string)
+                            .collect(),
+                    ),
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(parsed)
+}
+
+/// A routine run artifact as returned by `GET /routines/runs/{id}`.
+#[derive(Debug, Clone, serde::Deserialize)]
+struct RunArtifact {
+    artifact_id: String,
+    uri: String,
+    kind: String,
+    #[serde(default)]
+    label: Option<String>,
+    #[serde(default)]
+    metadata: Option<serde_json::Value>,
+}
+
+impl RunArtifact {
+    fn file_name(&self) -> String {
+        artifact_file_name(&self.uri, self.label.as_deref())
+    }
+
+    fn metadata_str(&self, key: &str) -> Option<&str> {
+        self.metadata.as_ref()?.get(key)?.as_str()
+    }
+
+    /// Targets named in `metadata.deliver_to` (a string or a list), set when
+    /// a tool or client asks for the artifact to be sent right away.
+    fn deliver_to(&self) -> Vec<ChannelTarget> {
+        match self.metadata.as_ref().and_then(|m| m.get("deliver_to")) {
+            Some(serde_json::Value::String(target)) => {
+                parse_channel_target(target).into_iter().collect()
+            }
+            Some(serde_json::Value::Array(list)) => list
+                .iter()
+                .filter_map(|v| v.as_str())
+                .filter_map(parse_channel_target)
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Whether `target`'s `attach` option selects this artifact.
+    fn wanted_by(&self, target: &ChannelTarget) -> bool {
+        if self.kind == "output_target" {
+            return false;
+        }
+        let Some(names) = &target.attach else {
+            return false;
+        };
+        let file_name = self.file_name();
+        names.is_empty()
+            || names.iter().any(|name| {
+                name.eq_ignore_ascii_case(&file_name)
+                    || self
+                        .label
+                        .as_deref()
+                        .is_some_and(|label| name.eq_ignore_ascii_case(label))
+            })
+    }
+}
+
+/// `channel://` output targets and artifacts of a routine run.
+#[derive(Debug, Default)]
+struct RunOutputs {
+    targets: Vec<ChannelTarget>,
+    artifacts: Vec<RunArtifact>,
+}
+
+async fn fetch_run_outputs(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_token: &str,
+    run_id: &str,
+) -> RunOutputs {
+    let run = match add_auth(
+        client.get(format!("{base_url}/routines/runs/{run_id}")),
+        api_token,
+    )
+    .send()
+    .await
+    {
+        Ok(resp) if resp.status().is_success() => {
+            resp.json::<serde_json::Value>().await.unwrap_or_default()
+        }
+        Ok(resp) => {
+            warn!("routine run {run_id} lookup failed ({})", resp.status());
+            return RunOutputs::default();
+        }
+        Err(e) => {
+            warn!("routine run {run_id} lookup failed: {e}");
+            return RunOutputs::default();
+        }
+    };
+    RunOutputs {
+        targets: run["run"]["output_targets"]
+            .as_array()
+            .map(|list| {
+                list.iter()
+                    .filter_map(|v| v.as_str())
+                    .filter_map(parse_channel_target)
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default(),
+        artifacts: serde_json::from_value(run["run"]["artifacts"].clone()).unwrap_or_default(),
+    }
+}
+
+/// Send one formatted message per target, on the target's channel.
+async fn deliver_to_targets(
+    outbound: &Outbound<'_>,
+    targets: &[ChannelTarget],
+    run_id: &str,
+    source: &str,
+    message_for: impl Fn(&ChannelTarget) -> (MessageKind, Vec<(&'static str, String)>),
+) {
+    for target in targets {
+        let Some(channel) = outbound
+            .channels
+            .iter()
+            .find(|c| c.name() == target.channel)
+        else {
+            warn!(
+                "routine run {run_id}: channel `{}` is not running",
+                target.channel
+            );
+            continue;
+        };
+        let (kind, vars) = message_for(target);
+        outbound
+            .deliver(
+                channel.as_ref(),
+                &target.recipient,
+                kind,
+                Some(target),
+                &vars,
+                source,
+                target.urgent,
+            )
+            .await;
+    }
+}
+
+/// Send a completed run's final report to its `channel://` output targets.
+async fn deliver_routine_output(
+    outbound: &Outbound<'_>,
+    props: &serde_json::Value,
+    client: &reqwest::Client,
+    base_url: &str,
+    api_token: &str,
+) {
+    if props
+        .get("deliverySuppressed")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+    {
+        return;
+    }
+    let field = |key: &str| props.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+    let (run_id, routine_id, session_id) = (field("runID"), field("routineID"), field("sessionID"));
+    if run_id.is_empty() || session_id.is_empty() {
+        return;
+    }
+    let outputs = fetch_run_outputs(client, base_url, api_token, run_id).await;
+    if outputs.targets.is_empty() {
+        return;
+    }
+    let report = match fetch_latest_assistant_message(client, base_url, api_token, session_id).await
+    {
+        Ok(report) => report,
+        Err(e) => {
+            warn!("routine run {run_id} output fetch failed: {e}");
+            None
+        }
+    };
+    let changed = match props.get("changed").and_then(|v| v.as_bool()) {
+        Some(true) => "changed",
+        Some(false) => "unchanged",
+        None => "first run",
+    };
+    let fetch = ArtifactFetch {
+        client,
+        base_url,
+        api_token,
+        run_id,
+        routine_id,
+    };
+    let (secure, plain): (Vec<_>, Vec<_>) = outputs.targets.iter().cloned().partition(|t| t.secure);
+    if !secure.is_empty() {
+        let (link, expires) = fetch.secure_link_vars(None).await;
+        deliver_to_targets(outbound, &secure, run_id, "routine.output", |_| {
+            let vars = vec![
+                ("routine_id", routine_id.to_string()),
+                ("run_id", run_id.to_string()),
+                ("changed", changed.to_string()),
+                ("file_name", String::new()),
+                ("link", link.clone()),
+                ("expires", expires.clone()),
+            ];
+            (MessageKind::SecureLink, vars)
+        })
+        .await;
+    }
+    if let Some(report) = report.filter(|_| !plain.is_empty()) {
+        deliver_to_targets(outbound, &plain, run_id, "routine.output", |target| {
+            let mut vars = vec![
+                ("routine_id", routine_id.to_string()),
+                ("run_id", run_id.to_string()),
+                ("changed", changed.to_string()),
+            ];
+            if target.digest {
+                vars.push(("summary", crate::format::digest_summary(&report)));
+                (MessageKind::RoutineDigest, vars)
+            } else {
+                vars.push(("report", report.clone()));
+                (MessageKind::RunResult, vars)
+            }
+        })
+        .await;
+    }
+    for target in outputs.targets.iter().filter(|t| t.attach.is_some()) {
+        let Some(channel) = outbound
+            .channels
+            .iter()
+            .find(|c| c.name() == target.channel)
+        else {
+            continue;
+        };
+        for artifact in outputs.artifacts.iter().filter(|a| a.wanted_by(target)) {
+            deliver_artifact(outbound, channel.as_ref(), target, artifact, &fetch).await;
+        }
+    }
+}
+
+/// Send an artifact whose metadata names `deliver_to` targets, e.g. one
+/// added by the `channel_send_file` tool.
+async fn deliver_requested_artifact(
+    outbound: &Outbound<'_>,
+    props: &serde_json::Value,
+    client: &reqwest::Client,
+    base_url: &str,
+    api_token: &str,
+) {
+    let Some(run_id) = props.get("runID").and_then(|v| v.as_str()) else {
+        return;
+    };
+    let routine_id = props
+        .get("routineID")
+        .and_then(|v| v.as_str())
+        .unwrap_or_default();
+    let Ok(artifact) = serde_json::from_value::<RunArtifact>(props["artifact"].clone()) else {
+        return;
+    };
+    let targets = artifact.deliver_to();
+    if targets.is_empty() {
+        return;
+    }
+    let fetch = ArtifactFetch {
+        client,
+        base_url,
+        api_token,
+        run_id,
+        routine_id,
+    };
+    for target in &targets {
+        let Some(channel) = outbound
+            .channels
+            .iter()
+            .find(|c| c.name() == target.channel)
+        else {
+            warn!(
+                "routine run {run_id}: channel `{}` is not running",
+                target.channel
+            );
+            continue;
+        };
+        deliver_artifact(outbound, channel.as_ref(), target, &artifact, &fetch).await;
+    }
+}
+
+/// Where to download a run's artifacts from.
+struct ArtifactFetch<'a> {
+    client: &'a reqwest::Client,
+    base_url: &'a str,
+    api_token: &'a str,
+    run_id: &'a str,
+    routine_id: &'a str,
+}
+
+enum ArtifactBytes {
+    Fits(Vec<u8>),
+    /// Larger than the limit; carries the size when the server reported it.
+    TooLarge(Option<u64>),
+}
+
+impl ArtifactFetch<'_> {
+    fn url(&self, artifact: &RunArtifact) -> String {
+        format!(
+            "{}/routines/runs/{}/artifacts/{}/content",
+            self.base_url, self.run_id, artifact.artifact_id
+        )
+    }
+
+    /// An expiring Web UI link to the run's report, or to one artifact. The
+    /// URL comes from the server, which builds it on the Web UI's public
+    /// address rather than the engine address this dispatcher talks to.
+    async fn secure_link(&self, artifact_id: Option<&str>) -> anyhow::Result<(String, u64)> {
+        let resp = add_auth(
+            self.client.post(format!(
+                "{}/routines/runs/{}/secure-links",
+                self.base_url, self.run_id
+            )),
+            self.api_token,
+        )
+        .json(&serde_json::json!({ "artifact_id": artifact_id }))
+        .send()
+        .await?;
+        if !resp.status().is_success() {
+            anyhow::bail!("secure link request failed ({})", resp.status());
+        }
+        let body = resp.json::<serde_json::Value>().await?;
+        let url = body["url"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("secure link response has no url"))?;
+        let expires_at_ms = body["link"]["expiresAtMs"].as_u64().unwrap_or_default();
+        Ok((url.to_string(), expires_at_ms))
+    }
+
+    /// `link` and `expires` template values; both empty when no link could
+    /// be made, so the notice goes out without one.
+    async fn secure_link_vars(&self, artifact_id: Option<&str>) -> (String, String) {
+        match self.secure_link(artifact_id).await {
+            Ok((url, expires_at_ms)) => {
+                let expires = chrono::DateTime::from_timestamp_millis(expires_at_ms as i64)
+                    .map(|at| format!("Expires {}", at.format("%Y-%m-%d %H:%M UTC")))
+                    .unwrap_or_default();
+                (url, expires)
+            }
+            Err(e) => {
+                warn!("routine run {}: {e}", self.run_id);
+                (String::new(), String::new())
+            }
+        }
+    }
+
+    /// Download an artifact, giving up once it exceeds `limit` bytes.
+    async fn bytes(&self, artifact: &RunArtifact, limit: u64) -> anyhow::Result<ArtifactBytes> {
+        use futures_util::StreamExt;
+
+        let resp = add_auth(self.client.get(self.url(artifact)), self.api_token)
+            .send()
+            .await?;
+        if resp.status() == reqwest::StatusCode::PAYLOAD_TOO_LARGE {
+            let size = resp
+                .json::<serde_json::Value>()
+                .await
+                .ok()
+                .and_then(|body| body.get("sizeBytes").and_then(|v| v.as_u64()));
+            return Ok(ArtifactBytes::TooLarge(size));
+        }
+        if !resp.status().is_success() {
+            anyhow::bail!("artifact download failed ({})", resp.status());
+        }
+        if let Some(len) = resp.content_length().filter(|len| *len > limit) {
+            return Ok(ArtifactBytes::TooLarge(Some(len)));
+        }
+        let mut bytes = Vec::new();
+        let mut stream = resp.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            bytes.extend_from_slice(&chunk?);
+            if bytes.len() as u64 > limit {
+                return Ok(ArtifactBytes::TooLarge(None));
+            }
+        }
+        Ok(ArtifactBytes::Fits(bytes))
+    }
+}
+
+/// Upload an artifact to a target, or send a link to it in the artifact store
+/// when it is too large, the upload fails, the adapter cannot upload files, or
+/// the channel is in quiet hours (the link is then held like any message).
+async fn deliver_artifact(
+    outbound: &Outbound<'_>,
+    channel: &dyn Channel,
+    target: &ChannelTarget,
+    artifact: &RunArtifact,
+    fetch: &ArtifactFetch<'_>,
+) {
+    let run_id = fetch.run_id;
+    let file_name = artifact.file_name();
+    if target.secure {
+        let (link, expires) = fetch.secure_link_vars(Some(&artifact.artifact_id)).await;
+        let vars = vec![
+            ("routine_id", fetch.routine_id.to_string()),
+            ("run_id", run_id.to_string()),
+            ("file_name", file_name),
+            ("link", link),
+            ("expires", expires),
+        ];
+        outbound
+            .deliver(
+                channel,
+                &target.recipient,
+                MessageKind::SecureLink,
+                Some(target),
+                &vars,
+                "routine.attachment",
+                target.urgent,
+            )
+            .await;
+        return;
+    }
+    let caption = artifact.metadata_str("caption").map(str::to_string);
+    let limit = channel.max_upload_bytes().min(MAX_ATTACHMENT_FETCH_BYTES);
+    let held = !target.urgent && outbound.deliveries.in_quiet_hours(channel.name(), now_ms());
+    let mut size = None;
+    if limit > 0 && !held {
+        match fetch.bytes(artifact, limit).await {
+            Ok(ArtifactBytes::Fits(bytes)) => {
+                let file = OutboundFile {
+                    content_type: content_type_for(&file_name).to_string(),
+                    file_name: file_name.clone(),
+                    bytes,
+                    caption: caption.clone(),
+                };
+                match channel.send_file(&target.recipient, &file).await {
+                    Ok(()) => return,
+                    Err(e) => warn!(
+                        "routine run {run_id}: uploading {file_name} to {}:{} failed: {e}",
+                        channel.name(),
+                        target.recipient
+                    ),
+                }
+            }
+            Ok(ArtifactBytes::TooLarge(len)) => size = len,
+            Err(e) => warn!("routine run {run_id}: fetching {file_name} failed: {e}"),
+        }
+    }
+    let mut content = format!("📎 {file_name}");
+    if let Some(size) = size {
+        content.push_str(&format!(" ({})", format_size(size)));
+    }
+    match fetch.secure_link(Some(&artifact.artifact_id)).await {
+        Ok((link, _)) => content.push_str(&format!(": {link}")),
+        Err(e) => {
+            warn!("routine run {run_id}: no link for {file_name}: {e}");
+            content.push_str(&format!(
+                " could not be uploaded; open run {run_id} in Tandem to download it."
+            ));
+        }
+    }
+    if let Some(caption) = caption {
+        content.push_str(&format!("\n{caption}"));
+    }
+    let status = outbound
+        .deliveries
+        .deliver(
+            channel,
+            SendMessage {
+                content,
+                recipient: target.recipient.clone(),
+                payload: None,
+            },
+            "routine.attachment",
+            target.urgent,
+            now_ms(),
+        )
+        .await;
+    if let Some(e) = status.error {
+        warn!(
+            "routine run {run_id}: attachment link to {}:{} failed: {e}",
+            channel.name(),
+            target.recipient
+        );
+    }
+}
+
+/// Tell a failed run's `channel://` output targets why it failed.
+async fn deliver_routine_failure(
+    outbound: &Outbound<'_>,
+    props: &serde_json::Value,
+    client: &reqwest::Client,
+    base_url: &str,
+    api_token: &str,
+) {
+    let field = |key: &str| props.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+    let (run_id, routine_id) = (field("runID"), field("routineID"));
+    if run_id.is_empty() {
+        return;
+    }
+    let targets = fetch_run_outputs(client, base_url, api_token, run_id)
+        .await
+        .targets;
+    let vars = vec![
+        ("routine_id", routine_id.to_string()),
+        ("run_id", run_id.to_string()),
+        ("reason", field("reason").to_string()),
+    ];
+    deliver_to_targets(outbound, &targets, run_id, "routine.error", |_| {
+        (MessageKind::Error, vars.clone())
+    })
+    .await;
+}
+
+/// Send an event rule's `notify_channel` text to its target.
+async fn deliver_rule_notice(outbound: &Outbound<'_>, props: &serde_json::Value) {
+    let field = |key: &str| props.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+    let Some(target) = parse_channel_target(field("target")) else {
+        warn!("rule {}: invalid notify target", field("ruleID"));
+        return;
+    };
+    let Some(channel) = outbound
+        .channels
+        .iter()
+        .find(|c| c.name() == target.channel)
+    else {
+        warn!(
+            "rule {}: channel `{}` is not running",
+            field("ruleID"),
+            target.channel
+        );
+        return;
+    };
+    let vars = vec![
+        ("rule_id", field("ruleID").to_string()),
+        ("event_type", field("eventType").to_string()),
+        ("text", field("text").to_string()),
+    ];
+    outbound
+        .deliver(
+            channel.as_ref(),
+            &target.recipient,
+            MessageKind::RuleNotice,
+            Some(&target),
+            &vars,
+            "rule.notify",
+            target.urgent,
+        )
+        .await;
+}
+
+async fn deliver_routine_alert(outbound: &Outbound<'_>, props: &serde_json::Value) {
+    let field = |key: &str| props.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+    let Some(target) = parse_channel_target(field("target")) else {
+        warn!(
+            "routine {}: invalid notification target",
+            field("routineID")
+        );
+        return;
+    };
+    let Some(channel) = outbound
+        .channels
+        .iter()
+        .find(|c| c.name() == target.channel)
+    else {
+        warn!(
+            "routine {}: channel `{}` is not running",
+            field("routineID"),
+            target.channel
+        );
+        return;
+    };
+    let icon = match field("kind") {
+        "failure" => "❌",
+        "recovery" => "✅",
+        _ => "📋",
+    };
+    let vars = vec![
+        ("icon", icon.to_string()),
+        ("routine_id", field("routineID").to_string()),
+        ("run_id", field("runID").to_string()),
+        ("kind", field("kind").to_string()),
+        ("text", field("text").to_string()),
+    ];
+    outbound
+        .deliver(
+            channel.as_ref(),
+            &target.recipient,
+            MessageKind::RoutineAlert,
+            Some(&target),
+            &vars,
+            "routine.run.notify",
+            target.urgent,
+        )
+        .await;
+}
+
+/// Platform senders on `channel` linked to any of `approvers`.
+fn approval_recipients(
+    identities: &IdentityDirectory,
+    approvers: &[String],
+    channel: &str,
+) -> Vec<String> {
+    let mut out = Vec::new();
+    for approver in approvers {
+        let Some(user) = identities.users.get(approver) else {
+            continue;
+        };
+        for identity in user.identities.iter().filter(|i| i.channel == channel) {
+            if !out.contains(&identity.sender) {
+                out.push(identity.sender.clone());
+            }
+        }
+    }
+    out
+}
+
+/// Template variables for a `routine.approval.notify` event.
+fn approval_vars(props: &serde_json::Value) -> Vec<(&'static str, String)> {
+    let field = |key: &str| props.get(key).and_then(|v| v.as_str()).unwrap_or("?");
+    let escalated = props
+        .get("escalated")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    vec![
+        ("icon", if escalated { "⏫" } else { "🔔" }.to_string()),
+        ("routine_id", field("routineID").to_string()),
+        ("run_id", field("runID").to_string()),
+        (
+            "escalation",
+            if escalated {
+                "Escalated: the original approvers did not respond.".to_string()
+            } else {
+                String::new()
+            },
+        ),
+        (
+            "reason",
+            props
+                .get("reason")
+                .and_then(|v| v.as_str())
+                .map(|reason| format!("Reason: {reason}"))
+                .unwrap_or_default(),
+        ),
+    ]
+}
+
 // ---------------------------------------------------------------------------
 // Supervisor
 // ---------------------------------------------------------------------------
@@ -295,6 +1439,8 @@ async fn supervise(
     base_url: String,
     api_token: String,
     session_map: SessionMap,
+    locale: LocaleSettings,
+    identities: Arc<IdentityDirectory>,
 ) {
     let mut backoff_secs: u64 = 1;
     loop {
@@ -312,8 +1458,10 @@ async fn supervise(
             let base = base_url.clone();
             let tok = api_token.clone();
             let map = session_map.clone();
+            let loc = locale.clone();
+            let ids = identities.clone();
             tokio::spawn(async move {
-                process_channel_message(msg, ch, &base, &tok, &map).await;
+                process_channel_message(msg, ch, &base, &tok, &map, &loc, &ids).await;
             });
         }
 
@@ -340,20 +1488,41 @@ async fn supervise(
 /// Process a single incoming channel message: handle slash commands or forward
 /// to the Tandem session HTTP API.
 async fn process_channel_message(
-    msg: ChannelMessage,
+    mut msg: ChannelMessage,
     channel: Arc<dyn Channel>,
     base_url: &str,
     api_token: &str,
     session_map: &SessionMap,
+    locale: &LocaleSettings,
+    identities: &IdentityDirectory,
 ) {
+    msg.user_id = identities
+        .resolve(&msg.channel, &msg.sender)
+        .map(|user| user.user_id.clone());
+    if let Some(user_id) = msg.user_id.as_deref() {
+        info!(
+            "tandem-channels: {}:{} resolved to user {}",
+            msg.channel, msg.sender, user_id
+        );
+        adopt_platform_session(&msg, session_map, &persistence_path()).await;
+    }
+
+    // --- Reaction → message feedback ---
+    if let Some(reaction) = msg.reaction.as_ref() {
+        record_reaction_feedback(&msg, reaction, base_url, api_token).await;
+        return;
+    }
+
     // --- Slash command intercept ---
     if msg.content.starts_with('/') {
         if let Some(cmd) = parse_slash_command(&msg.content) {
-            let response = handle_slash_command(cmd, &msg, base_url, api_token, session_map).await;
+            let response =
+                handle_slash_command(cmd, &msg, base_url, api_token, session_map, locale).await;
             let _ = channel
                 .send(&SendMessage {
                     content: response,
                     recipient: msg.reply_target.clone(),
+                    payload: None,
                 })
                 .await;
             return;
@@ -361,7 +1530,7 @@ async fn process_channel_message(
     }
 
     // --- Normal message → Tandem session ---
-    let map_key = format!("{}:{}", msg.channel, msg.sender);
+    let map_key = session_key(&msg);
     let session_id = get_or_create_session(&map_key, &msg, base_url, api_token, session_map).await;
 
     let session_id = match session_id {
@@ -372,27 +1541,276 @@ async fn process_channel_message(
         }
     };
 
+    import_conversation_history(
+        channel.as_ref(),
+        &msg,
+        &map_key,
+        &session_id,
+        base_url,
+        api_token,
+        session_map,
+    )
+    .await;
+
+    let language = language_preferences()
+        .observe(&map_key, &msg.content, msg.locale.as_deref(), now_ms())
+        .await;
+
     let _ = channel.start_typing(&msg.reply_target).await;
-    let response = run_in_session(&session_id, &msg.content, base_url, api_token).await;
+    let response = run_in_session(
+        &session_id,
+        &msg.content,
+        &quota_user(&msg),
+        language.as_deref(),
+        base_url,
+        api_token,
+    )
+    .await;
     let _ = channel.stop_typing(&msg.reply_target).await;
 
-    let reply = response.unwrap_or_else(|e| format!("⚠️ Error: {e}"));
-    let _ = channel
-        .send(&SendMessage {
+    let (reply, message_id) = match response {
+        Ok(reply) => (reply.text, reply.message_id),
+        Err(e) => (
+            format!("⚠️ {}: {e}", locale.text(MessageKey::ErrorPrefix)),
+            None,
+        ),
+    };
+    let sent = channel
+        .send_reply(&SendMessage {
             content: reply,
-            recipient: msg.reply_target,
+            recipient: msg.reply_target.clone(),
+            payload: None,
         })
         .await;
+    if let (Ok(platform_ids), Some(message_id)) = (sent, message_id) {
+        let mut replies = reply_index().lock().await;
+        for platform_id in platform_ids {
+            replies.record(
+                ReplyKey::new(&msg.channel, &msg.reply_target, &platform_id),
+                TrackedReply {
+                    session_id: session_id.clone(),
+                    message_id: message_id.clone(),
+                },
+            );
+        }
+    }
+}
+
+/// Platform replies tracked for reaction feedback.
+pub const MAX_TRACKED_REPLIES: usize = 4_096;
+
+/// `(channel, conversation, platform message id)` of a posted reply.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ReplyKey(String, String, String);
+
+impl ReplyKey {
+    fn new(channel: &str, conversation: &str, platform_id: &str) -> Self {
+        Self(
+            channel.to_string(),
+            conversation.to_string(),
+            platform_id.to_string(),
+        )
+    }
+}
+
+/// The Tandem assistant message a platform reply relayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TrackedReply {
+    session_id: String,
+    message_id: String,
+}
+
+/// Recent replies by platform id, oldest evicted first.
+#[derive(Default)]
+struct ReplyIndex {
+    replies: HashMap<ReplyKey, TrackedReply>,
+    order: VecDeque<ReplyKey>,
+}
+
+impl ReplyIndex {
+    fn record(&mut self, key: ReplyKey, reply: TrackedReply) {
+        if self.replies.insert(key.clone(), reply).is_none() {
+            self.order.push_back(key);
+        }
+        while self.order.len() > MAX_TRACKED_REPLIES {
+            if let Some(oldest) = self.order.pop_front() {
+                self.replies.remove(&oldest);
+            }
+        }
+    }
+
+    fn get(&self, key: &ReplyKey) -> Option<&TrackedReply> {
+        self.replies.get(key)
+    }
+}
+
+fn reply_index() -> &'static Mutex<ReplyIndex> {
+    static INDEX: OnceLock<Mutex<ReplyIndex>> = OnceLock::new();
+    INDEX.get_or_init(|| Mutex::new(ReplyIndex::default()))
+}
+
+/// Feedback rating for a reaction emoji (or Slack-style shortcode).
+pub fn reaction_rating(emoji: &str) -> Option<&'static str> {
+    // Drop skin-tone modifiers and variation selectors.
+    let base = emoji
+        .trim_matches(':')
+        .chars()
+        .filter(|c| !matches!(*c as u32, 0x1F3FB..=0x1F3FF | 0xFE0F))
+        .collect::<String>();
+    match base.as_str() {
+        "👍" | "❤" | "🎉" | "✅" | "💯" | "+1" | "thumbsup" | "heart" => Some("up"),
+        "👎" | "❌" | "-1" | "thumbsdown" | "x" => Some("down"),
+        _ => None,
+    }
+}
+
+async fn record_reaction_feedback(
+    msg: &ChannelMessage,
+    reaction: &Reaction,
+    base_url: &str,
+    api_token: &str,
+) {
+    let Some(rating) = reaction_rating(&reaction.emoji) else {
+        return;
+    };
+    let key = ReplyKey::new(&msg.channel, &msg.reply_target, &reaction.message_id);
+    let Some(TrackedReply {
+        session_id,
+        message_id,
+    }) = reply_index().lock().await.get(&key).cloned()
+    else {
+        info!(
+            "tandem-channels: ignoring {} reaction from {} to an untracked message",
+            msg.channel, msg.sender
+        );
+        return;
+    };
+    let submitted_by = msg
+        .user_id
+        .clone()
+        .unwrap_or_else(|| format!("{}:{}", msg.channel, msg.sender));
+    let body = serde_json::json!({
+        "rating": rating,
+        "source": format!("channel:{}", msg.channel),
+        "submitted_by": submitted_by,
+    });
+    let client = reqwest::Client::new();
+    let resp = add_auth(
+        client.post(format!(
+            "{base_url}/session/{session_id}/message/{message_id}/feedback"
+        )),
+        api_token,
+    )
+    .json(&body)
+    .send()
+    .await;
+    match resp {
+        Ok(resp) if resp.status().is_success() => {}
+        Ok(resp) => warn!(
+            "tandem-channels: reaction feedback rejected ({})",
+            resp.status()
+        ),
+        Err(e) => warn!("tandem-channels: reaction feedback failed: {e}"),
+    }
 }
 
 // ---------------------------------------------------------------------------
 // Session management helpers
 // ---------------------------------------------------------------------------
 
-fn build_channel_session_create_body(title: &str) -> serde_json::Value {
+/// Conversations remembered per session record as already imported.
+const MAX_IMPORTED_CONVERSATIONS: usize = 32;
+
+/// The first time a conversation (channel or thread) reaches a session, seed
+/// the session with the conversation's earlier messages via
+/// `POST /session/{id}/context/import`. Best effort: a conversation whose
+/// history the bot may not read is marked as handled and not retried.
+async fn import_conversation_history(
+    channel: &dyn Channel,
+    msg: &ChannelMessage,
+    map_key: &str,
+    session_id: &str,
+    base_url: &str,
+    api_token: &str,
+    session_map: &SessionMap,
+) {
+    let limit = channel.history_import_limit();
+    if limit == 0 {
+        return;
+    }
+    let conversation = format!("{}:{}", msg.channel, msg.reply_target);
+    {
+        let guard = session_map.lock().await;
+        match guard.get(map_key) {
+            Some(record)
+                if record.session_id == session_id
+                    && !record.imported_conversations.contains(&conversation) => {}
+            _ => return,
+        }
+    }
+    let history = match channel.fetch_history(msg, limit).await {
+        Ok(history) => history,
+        Err(e) => {
+            warn!("tandem-channels: no history import for {conversation}: {e}");
+            mark_conversation_imported(map_key, &conversation, session_map).await;
+            return;
+        }
+    };
+    if !history.is_empty() {
+        let body = serde_json::json!({
+            "source": msg.channel,
+            "conversation": msg.reply_target,
+            "messages": history,
+        });
+        let resp = add_auth(
+            reqwest::Client::new().post(format!("{base_url}/session/{session_id}/context/import")),
+            api_token,
+        )
+        .json(&body)
+        .send()
+        .await;
+        match resp {
+            Ok(resp) if resp.status().is_success() => {
+                info!(
+                    "tandem-channels: imported {} earlier messages from {conversation} into session {session_id}",
+                    history.len()
+                );
+            }
+            Ok(resp) => {
+                warn!(
+                    "tandem-channels: history import rejected ({})",
+                    resp.status()
+                );
+                return;
+            }
+            Err(e) => {
+                warn!("tandem-channels: history import failed: {e}");
+                return;
+            }
+        }
+    }
+    mark_conversation_imported(map_key, &conversation, session_map).await;
+}
+
+async fn mark_conversation_imported(map_key: &str, conversation: &str, session_map: &SessionMap) {
+    let mut guard = session_map.lock().await;
+    let Some(record) = guard.get_mut(map_key) else {
+        return;
+    };
+    record.imported_conversations.push(conversation.to_string());
+    let overflow = record
+        .imported_conversations
+        .len()
+        .saturating_sub(MAX_IMPORTED_CONVERSATIONS);
+    record.imported_conversations.drain(..overflow);
+    save_session_map(&guard).await;
+}
+
+fn build_channel_session_create_body(title: &str, channel: &str) -> serde_json::Value {
     serde_json::json!({
         "title": title,
         "directory": ".",
+        "tags": ["channel", format!("channel:{channel}")],
         "permission": [
             { "permission": "ls", "pattern": "*", "action": "allow" },
             { "permission": "list", "pattern": "*", "action": "allow" },
@@ -433,7 +1851,7 @@ async fn get_or_create_session(
 
     let client = reqwest::Client::new();
     let title = format!("{} — {}", msg.channel, msg.sender);
-    let body = build_channel_session_create_body(&title);
+    let body = build_channel_session_create_body(&title, &msg.channel);
 
     let resp = add_auth(client.post(format!("{base_url}/session")), api_token)
         .json(&body)
@@ -474,6 +1892,8 @@ async fn get_or_create_session(
             last_seen_at_ms: now,
             channel: msg.channel.clone(),
             sender: msg.sender.clone(),
+            user_id: msg.user_id.clone(),
+            imported_conversations: Vec::new(),
         },
     );
     save_session_map(&guard).await;
@@ -486,12 +1906,21 @@ async fn get_or_create_session(
 ///
 /// Falls back to an error string if the initial fire fails or the stream
 /// never completes within `timeout_secs`.
+/// Text relayed for a finished run, and the id of the session's latest
+/// assistant message it came from.
+struct RunReply {
+    text: String,
+    message_id: Option<String>,
+}
+
 async fn run_in_session(
     session_id: &str,
     content: &str,
+    user: &str,
+    language: Option<&str>,
     base_url: &str,
     api_token: &str,
-) -> anyhow::Result<String> {
+) -> anyhow::Result<RunReply> {
     let timeout_secs: u64 = std::env::var("TANDEM_CHANNEL_MAX_WAIT_SECONDS")
         .ok()
         .and_then(|v| v.parse().ok())
@@ -501,9 +1930,12 @@ async fn run_in_session(
         .timeout(Duration::from_secs(timeout_secs + 30))
         .build()?;
 
-    let body = serde_json::json!({
+    let mut body = serde_json::json!({
         "parts": [{ "type": "text", "text": content }]
     });
+    if let Some(tag) = language {
+        body["system"] = serde_json::Value::String(response_language_directive(tag));
+    }
 
     // Request run metadata so we can bind SSE to this specific run.
     let resp = add_auth(
@@ -512,10 +1944,20 @@ async fn run_in_session(
         )),
         api_token,
     )
+    .header(USER_HEADER, user)
     .json(&body)
     .send()
     .await?;
 
+    if resp.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        let err = resp
+            .json::<serde_json::Value>()
+            .await
+            .ok()
+            .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(String::from))
+            .unwrap_or_else(|| "quota exhausted".to_string());
+        anyhow::bail!("{err} — use /quota to check your remaining usage");
+    }
     if !resp.status().is_success() {
         let status = resp.status();
         let err = resp.text().await.unwrap_or_default();
@@ -630,16 +2072,18 @@ async fn run_in_session(
         }
     }
 
-    if content_buf.is_empty() {
-        if let Ok(Some(fallback)) =
-            fetch_latest_assistant_message(&client, base_url, api_token, session_id).await
-        {
-            return Ok(fallback);
-        }
-        return Ok("(no response)".to_string());
-    }
-
-    Ok(content_buf)
+    let messages = fetch_session_messages(&client, base_url, api_token, session_id)
+        .await
+        .unwrap_or_default();
+    let text = if content_buf.is_empty() {
+        latest_assistant_text(&messages).unwrap_or_else(|| "(no response)".to_string())
+    } else {
+        content_buf
+    };
+    Ok(RunReply {
+        text,
+        message_id: latest_assistant_message_id(&messages),
+    })
 }
 
 /// Fallback for channel delivery: if the SSE stream did not emit text deltas,
@@ -650,6 +2094,16 @@ async fn fetch_latest_assistant_message(
     api_token: &str,
     session_id: &str,
 ) -> anyhow::Result<Option<String>> {
+    let messages = fetch_session_messages(client, base_url, api_token, session_id).await?;
+    Ok(latest_assistant_text(&messages))
+}
+
+async fn fetch_session_messages(
+    client: &reqwest::Client,
+    base_url: &str,
+    api_token: &str,
+    session_id: &str,
+) -> anyhow::Result<Vec<serde_json::Value>> {
     let url = format!("{base_url}/session/{session_id}/message");
     let resp = add_auth(client.get(&url), api_token).send().await?;
     if !resp.status().is_success() {
@@ -659,17 +2113,30 @@ async fn fetch_latest_assistant_message(
     }
 
     let messages: serde_json::Value = resp.json().await?;
-    let Some(items) = messages.as_array() else {
-        return Ok(None);
-    };
+    Ok(messages.as_array().cloned().unwrap_or_default())
+}
+
+fn is_assistant(msg: &serde_json::Value) -> bool {
+    msg.get("info")
+        .and_then(|info| info.get("role"))
+        .and_then(|v| v.as_str())
+        == Some("assistant")
+}
+
+/// Id of the last assistant message in a `GET /session/{id}/message` list.
+fn latest_assistant_message_id(items: &[serde_json::Value]) -> Option<String> {
+    items
+        .iter()
+        .rev()
+        .find(|msg| is_assistant(msg))
+        .and_then(|msg| msg.get("info")?.get("id")?.as_str())
+        .map(str::to_string)
+}
 
+/// Text of the last assistant message with any text in it.
+fn latest_assistant_text(items: &[serde_json::Value]) -> Option<String> {
     for msg in items.iter().rev() {
-        let role = msg
-            .get("info")
-            .and_then(|info| info.get("role"))
-            .and_then(|v| v.as_str())
-            .unwrap_or("");
-        if role != "assistant" {
+        if !is_assistant(msg) {
             continue;
         }
 
@@ -693,11 +2160,11 @@ async fn fetch_latest_assistant_message(
         }
 
         if !text.trim().is_empty() {
-            return Ok(Some(text));
+            return Some(text);
         }
     }
 
-    Ok(None)
+    None
 }
 
 /// Send an approve or deny decision to the tandem-server tool approval endpoint.
@@ -732,11 +2199,12 @@ async fn handle_slash_command(
     base_url: &str,
     api_token: &str,
     session_map: &SessionMap,
+    locale: &LocaleSettings,
 ) -> String {
     match cmd {
-        SlashCommand::Help => help_text(),
+        SlashCommand::Help => help_text(locale),
         SlashCommand::ListSessions => {
-            list_sessions_text(base_url, api_token, &msg.channel, &msg.sender).await
+            list_sessions_text(base_url, api_token, &msg.channel, &msg.sender, locale).await
         }
         SlashCommand::New { name } => {
             new_session_text(name, msg, base_url, api_token, session_map).await
@@ -744,8 +2212,8 @@ async fn handle_slash_command(
         SlashCommand::Resume { query } => {
             resume_session_text(query, msg, base_url, api_token, session_map).await
         }
-        SlashCommand::Status => status_text(msg, base_url, api_token, session_map).await,
-        SlashCommand::Run => run_status_text(msg, base_url, api_token, session_map).await,
+        SlashCommand::Status => status_text(msg, base_url, api_token, session_map, locale).await,
+        SlashCommand::Run => run_status_text(msg, base_url, api_token, session_map, locale).await,
         SlashCommand::Cancel => cancel_run_text(msg, base_url, api_token, session_map).await,
         SlashCommand::Todos => todos_text(msg, base_url, api_token, session_map).await,
         SlashCommand::Requests => requests_text(msg, base_url, api_token, session_map).await,
@@ -754,13 +2222,21 @@ async fn handle_slash_command(
             answer,
         } => answer_question_text(question_id, answer, msg, base_url, api_token, session_map).await,
         SlashCommand::Providers => providers_text(base_url, api_token).await,
+        SlashCommand::Quota => quota_text(msg, base_url, api_token).await,
+        SlashCommand::Language { tag } => language_text(tag, msg).await,
+        SlashCommand::ApproveRun { run_id } => {
+            routine_run_decision_text(&run_id, true, None, msg, base_url, api_token).await
+        }
+        SlashCommand::DenyRun { run_id, reason } => {
+            routine_run_decision_text(&run_id, false, reason, msg, base_url, api_token).await
+        }
         SlashCommand::Models { provider } => models_text(provider, base_url, api_token).await,
         SlashCommand::Model { model_id } => set_model_text(model_id, base_url, api_token).await,
         SlashCommand::Rename { name } => {
             rename_session_text(name, msg, base_url, api_token, session_map).await
         }
         SlashCommand::Approve { tool_call_id } => {
-            let map_key = format!("{}:{}", msg.channel, msg.sender);
+            let map_key = session_key(msg);
             let session_id = {
                 let guard = session_map.lock().await;
                 guard.get(&map_key).map(|r| r.session_id.clone())
@@ -777,7 +2253,7 @@ async fn handle_slash_command(
             }
         }
         SlashCommand::Deny { tool_call_id } => {
-            let map_key = format!("{}:{}", msg.channel, msg.sender);
+            let map_key = session_key(msg);
             let session_id = {
                 let guard = session_map.lock().await;
                 guard.get(&map_key).map(|r| r.session_id.clone())
@@ -800,8 +2276,15 @@ async fn handle_slash_command(
 // Individual slash command implementations
 // ---------------------------------------------------------------------------
 
-fn help_text() -> String {
-    "🤖 *Tandem Commands*\n\
+fn help_text(locale: &LocaleSettings) -> String {
+    format!(
+        "🤖 *{}*\n{}",
+        locale.text(MessageKey::CommandsTitle),
+        HELP_COMMANDS
+    )
+}
+
+const HELP_COMMANDS: &str = "\
     /new [name] — start a fresh session\n\
     /sessions — list your recent sessions\n\
     /resume <id or name> — switch to a previous session\n\
@@ -817,12 +2300,14 @@ fn help_text() -> String {
     /model <model_id> — set model for current default provider\n\
     /approve <tool_call_id> — approve a pending tool call\n\
     /deny <tool_call_id> — deny a pending tool call\n\
-    /help — show this message"
-        .to_string()
-}
+    /approve_run <run_id> — approve a pending routine run\n\
+    /deny_run <run_id> [reason] — deny a pending routine run\n\
+    /language [code|auto] — show or set the reply language\n\
+    /quota — show your remaining daily quota\n\
+    /help — show this message";
 
 async fn active_session_id(msg: &ChannelMessage, session_map: &SessionMap) -> Option<String> {
-    let map_key = format!("{}:{}", msg.channel, msg.sender);
+    let map_key = session_key(msg);
     session_map
         .lock()
         .await
@@ -835,6 +2320,7 @@ async fn list_sessions_text(
     api_token: &str,
     channel: &str,
     sender: &str,
+    locale: &LocaleSettings,
 ) -> String {
     let client = reqwest::Client::new();
     let source_title_prefix = format!("{channel} — {sender}");
@@ -843,10 +2329,10 @@ async fn list_sessions_text(
         .send()
         .await
     else {
-        return "⚠️ Could not reach Tandem server.".to_string();
+        return format!("⚠️ {}", locale.text(MessageKey::ServerUnreachable));
     };
     let Ok(json) = resp.json::<serde_json::Value>().await else {
-        return "⚠️ Unexpected server response.".to_string();
+        return format!("⚠️ {}", locale.text(MessageKey::UnexpectedResponse));
     };
 
     let sessions = json.as_array().cloned().unwrap_or_default();
@@ -872,20 +2358,28 @@ async fn list_sessions_text(
                 .and_then(|m| m.as_array())
                 .map(|a| a.len())
                 .unwrap_or(0);
+            let updated = session_updated_ms(s)
+                .map(|ms| format!(" · {}", locale.format_timestamp_ms(ms)))
+                .unwrap_or_default();
             format!(
-                "{}. `{}` — {} ({} msgs)",
+                "{}. `{}` — {} ({} msgs){}",
                 i + 1,
                 &id[..8.min(id.len())],
                 title,
-                msg_count
+                msg_count,
+                updated
             )
         })
         .collect();
 
     if matching.is_empty() {
-        "📋 No previous sessions found.".to_string()
+        format!("📋 {}", locale.text(MessageKey::NoSessionsFound))
     } else {
-        format!("📋 Your sessions:\n{}", matching.join("\n"))
+        format!(
+            "📋 {}\n{}",
+            locale.text(MessageKey::YourSessions),
+            matching.join("\n")
+        )
     }
 }
 
@@ -896,12 +2390,12 @@ async fn new_session_text(
     api_token: &str,
     session_map: &SessionMap,
 ) -> String {
-    let map_key = format!("{}:{}", msg.channel, msg.sender);
+    let map_key = session_key(msg);
     let display_name = name
         .clone()
         .unwrap_or_else(|| format!("{} — {}", msg.channel, msg.sender));
     let client = reqwest::Client::new();
-    let body = build_channel_session_create_body(&display_name);
+    let body = build_channel_session_create_body(&display_name, &msg.channel);
 
     let Ok(resp) = add_auth(client.post(format!("{base_url}/session")), api_token)
         .json(&body)
@@ -932,6 +2426,8 @@ async fn new_session_text(
             last_seen_at_ms: now,
             channel: msg.channel.clone(),
             sender: msg.sender.clone(),
+            user_id: msg.user_id.clone(),
+            imported_conversations: Vec::new(),
         },
     );
     save_session_map(&guard).await;
@@ -950,7 +2446,7 @@ async fn resume_session_text(
     api_token: &str,
     session_map: &SessionMap,
 ) -> String {
-    let map_key = format!("{}:{}", msg.channel, msg.sender);
+    let map_key = session_key(msg);
     let source_prefix = format!("{} — {}", msg.channel, msg.sender);
     let client = reqwest::Client::new();
 
@@ -1005,6 +2501,8 @@ async fn resume_session_text(
                     last_seen_at_ms: now,
                     channel: msg.channel.clone(),
                     sender: msg.sender.clone(),
+                    user_id: msg.user_id.clone(),
+                    imported_conversations: Vec::new(),
                 },
             );
             save_session_map(&guard).await;
@@ -1027,15 +2525,16 @@ async fn status_text(
     base_url: &str,
     api_token: &str,
     session_map: &SessionMap,
+    locale: &LocaleSettings,
 ) -> String {
-    let map_key = format!("{}:{}", msg.channel, msg.sender);
+    let map_key = session_key(msg);
     let session_id = session_map
         .lock()
         .await
         .get(&map_key)
         .map(|r| r.session_id.clone());
     let Some(sid) = session_id else {
-        return "ℹ️ No active session. Send a message to start one, or use /new.".to_string();
+        return format!("ℹ️ {}", locale.text(MessageKey::NoActiveSession));
     };
 
     let client = reqwest::Client::new();
@@ -1059,14 +2558,33 @@ async fn status_text(
         .map(|a| a.len())
         .unwrap_or(0);
 
+    let updated = session_updated_ms(&json)
+        .map(|ms| {
+            format!(
+                " | {} {}",
+                locale.text(MessageKey::LastUpdated),
+                locale.format_timestamp_ms(ms)
+            )
+        })
+        .unwrap_or_default();
+
     format!(
-        "ℹ️ Session: \"{}\" (`{}`) | {} messages",
+        "ℹ️ Session: \"{}\" (`{}`) | {} messages{}",
         title,
         &sid[..8.min(sid.len())],
-        msgs
+        msgs,
+        updated
     )
 }
 
+/// `time.updated` from a wire session, in epoch milliseconds.
+fn session_updated_ms(session: &serde_json::Value) -> Option<u64> {
+    session
+        .get("time")
+        .and_then(|t| t.get("updated"))
+        .and_then(|v| v.as_u64())
+}
+
 async fn rename_session_text(
     name: String,
     msg: &ChannelMessage,
@@ -1074,7 +2592,7 @@ async fn rename_session_text(
     api_token: &str,
     session_map: &SessionMap,
 ) -> String {
-    let map_key = format!("{}:{}", msg.channel, msg.sender);
+    let map_key = session_key(msg);
     let session_id = session_map
         .lock()
         .await
@@ -1102,9 +2620,10 @@ async fn run_status_text(
     base_url: &str,
     api_token: &str,
     session_map: &SessionMap,
+    locale: &LocaleSettings,
 ) -> String {
     let Some(sid) = active_session_id(msg, session_map).await else {
-        return "ℹ️ No active session. Send a message to start one, or use /new.".to_string();
+        return format!("ℹ️ {}", locale.text(MessageKey::NoActiveSession));
     };
 
     let client = reqwest::Client::new();
@@ -1125,7 +2644,7 @@ async fn run_status_text(
         .cloned()
         .unwrap_or(serde_json::Value::Null);
     if active.is_null() {
-        return "ℹ️ No active run.".to_string();
+        return format!("ℹ️ {}", locale.text(MessageKey::NoActiveRun));
     }
 
     let run_id = active
@@ -1133,10 +2652,16 @@ async fn run_status_text(
         .or_else(|| active.get("runID"))
         .and_then(|v| v.as_str())
         .unwrap_or("unknown");
+    let started = active
+        .get("startedAtMs")
+        .and_then(|v| v.as_u64())
+        .map(|ms| format!(" since {}", locale.format_timestamp_ms(ms)))
+        .unwrap_or_default();
     format!(
-        "🏃 Active run: `{}` on session `{}`",
+        "🏃 Active run: `{}` on session `{}`{}",
         &run_id[..8.min(run_id.len())],
-        &sid[..8.min(sid.len())]
+        &sid[..8.min(sid.len())],
+        started
     )
 }
 
@@ -1362,6 +2887,129 @@ async fn answer_question_text(
     }
 }
 
+async fn quota_text(msg: &ChannelMessage, base_url: &str, api_token: &str) -> String {
+    let client = reqwest::Client::new();
+    let Ok(resp) = add_auth(client.get(format!("{base_url}/quota")), api_token)
+        .header(USER_HEADER, quota_user(msg))
+        .send()
+        .await
+    else {
+        return "⚠️ Could not reach server.".to_string();
+    };
+    let Ok(json) = resp.json::<serde_json::Value>().await else {
+        return "⚠️ Unexpected server response.".to_string();
+    };
+    let Some(quota) = json.get("quota") else {
+        return "⚠️ Unexpected server response.".to_string();
+    };
+    format_quota(quota)
+}
+
+pub(crate) fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+async fn language_text(tag: Option<String>, msg: &ChannelMessage) -> String {
+    let prefs = language_preferences();
+    let key = session_key(msg);
+    match tag.as_deref() {
+        None => match prefs.get(&key) {
+            Some(pref) => {
+                let how = match pref.source {
+                    LanguageSource::Explicit => "set by you",
+                    LanguageSource::Detected => "detected from your messages",
+                    LanguageSource::Platform => "from your app language",
+                };
+                format!(
+                    "🌐 Replying in {} ({}), {how}. Use /language <code> to change or /language auto to detect.",
+                    language_name(&pref.tag),
+                    pref.tag
+                )
+            }
+            None => {
+                "🌐 No reply language yet — it will be detected from your messages.".to_string()
+            }
+        },
+        Some("auto") => {
+            prefs.reset(&key).await;
+            "🌐 Reply language will be detected from your messages.".to_string()
+        }
+        Some(raw) => match normalize_language_tag(raw) {
+            Some(tag) => {
+                prefs.set_explicit(&key, tag, now_ms()).await;
+                format!("🌐 Replies will be in {} ({tag}).", language_name(tag))
+            }
+            None => format!("⚠️ Unknown language `{raw}`. Try a code such as `de`, `fr` or `ja`."),
+        },
+    }
+}
+
+async fn routine_run_decision_text(
+    run_id: &str,
+    approve: bool,
+    reason: Option<String>,
+    msg: &ChannelMessage,
+    base_url: &str,
+    api_token: &str,
+) -> String {
+    let action = if approve { "approve" } else { "deny" };
+    let client = reqwest::Client::new();
+    let body = serde_json::json!({
+        "approver": quota_user(msg),
+        "reason": reason,
+    });
+    let resp = add_auth(
+        client.post(format!("{base_url}/routines/runs/{run_id}/{action}")),
+        api_token,
+    )
+    .json(&body)
+    .send()
+    .await;
+    match resp {
+        Ok(r) if r.status().is_success() => {
+            if approve {
+                format!("✅ Approved routine run `{run_id}`.")
+            } else {
+                format!("🚫 Denied routine run `{run_id}`.")
+            }
+        }
+        Ok(r) if r.status() == reqwest::StatusCode::FORBIDDEN => {
+            format!("⚠️ You are not a designated approver for run `{run_id}`.")
+        }
+        Ok(r) if r.status() == reqwest::StatusCode::CONFLICT => {
+            format!("⚠️ Run `{run_id}` is no longer waiting for approval.")
+        }
+        Ok(r) if r.status() == reqwest::StatusCode::NOT_FOUND => {
+            format!("⚠️ Routine run `{run_id}` not found.")
+        }
+        Ok(r) => format!("⚠️ Could not {action} run (HTTP {}).", r.status()),
+        Err(e) => format!("⚠️ Could not {action} run: {e}"),
+    }
+}
+
+fn format_quota(quota: &serde_json::Value) -> String {
+    let used = |key: &str| quota.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+    let line = |label: &str, used_key: &str, limit_key: &str, remaining_key: &str| match quota
+        .get(limit_key)
+        .and_then(|v| v.as_u64())
+    {
+        Some(limit) => format!(
+            "• {label}: {} / {limit} used ({} left)",
+            used(used_key),
+            used(remaining_key)
+        ),
+        None => format!("• {label}: {} used (unlimited)", used(used_key)),
+    };
+    format!(
+        "📊 Today's usage:\n{}\n{}",
+        line("Runs", "runsUsed", "runsLimit", "runsRemaining"),
+        line("Tokens", "tokensUsed", "tokensLimit", "tokensRemaining"),
+    )
+}
+
 async fn providers_text(base_url: &str, api_token: &str) -> String {
     let client = reqwest::Client::new();
     let Ok(resp) = add_auth(client.get(format!("{base_url}/provider")), api_token)
@@ -1706,6 +3354,162 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parse_language_command() {
+        assert!(matches!(
+            parse_slash_command("/language"),
+            Some(SlashCommand::Language { tag: None })
+        ));
+        assert!(matches!(
+            parse_slash_command("/lang de"),
+            Some(SlashCommand::Language { tag: Some(ref t) }) if t == "de"
+        ));
+    }
+
+    #[test]
+    fn parse_quota_aliases() {
+        assert!(matches!(
+            parse_slash_command("/quota"),
+            Some(SlashCommand::Quota)
+        ));
+        assert!(matches!(
+            parse_slash_command("/usage"),
+            Some(SlashCommand::Quota)
+        ));
+    }
+
+    #[test]
+    fn format_quota_shows_limits_and_unlimited() {
+        let text = format_quota(&serde_json::json!({
+            "runsUsed": 3,
+            "runsLimit": 10,
+            "runsRemaining": 7,
+            "tokensUsed": 1200,
+        }));
+        assert!(text.contains("Runs: 3 / 10 used (7 left)"));
+        assert!(text.contains("Tokens: 1200 used (unlimited)"));
+    }
+
+    #[test]
+    fn parse_routine_run_decisions() {
+        match parse_slash_command("/approve_run routine-run-1") {
+            Some(SlashCommand::ApproveRun { run_id }) => assert_eq!(run_id, "routine-run-1"),
+            other => panic!("expected ApproveRun, got {other:?}"),
+        }
+        match parse_slash_command("/deny_run routine-run-1 not today") {
+            Some(SlashCommand::DenyRun { run_id, reason }) => {
+                assert_eq!(run_id, "routine-run-1");
+                assert_eq!(reason.as_deref(), Some("not today"));
+            }
+            other => panic!("expected DenyRun, got {other:?}"),
+        }
+        assert!(parse_slash_command("/approve_run ").is_none());
+    }
+
+    #[test]
+    fn approval_recipients_follow_linked_identities() {
+        let mut dir = IdentityDirectory::default();
+        dir.upsert_user(
+            "alice",
+            None,
+            vec![
+                crate::identity::ChannelIdentity::new("telegram", "111"),
+                crate::identity::ChannelIdentity::new("slack", "U1"),
+            ],
+        )
+        .expect("upsert");
+        let approvers = vec!["alice".to_string(), "unknown".to_string()];
+        assert_eq!(
+            approval_recipients(&dir, &approvers, "telegram"),
+            vec!["111".to_string()]
+        );
+        assert!(approval_recipients(&dir, &approvers, "discord").is_empty());
+        let text = MessageTemplates::default()
+            .render(
+                "telegram",
+                MessageKind::Approval,
+                None,
+                &approval_vars(&serde_json::json!({
+                    "routineID": "nightly",
+                    "runID": "run-9",
+                    "escalated": true,
+                })),
+            )
+            .text;
+        assert!(text.contains("Escalated"));
+        assert!(text.contains("/approve_run run-9"));
+    }
+
+    #[test]
+    fn parse_channel_output_targets() {
+        let plain = parse_channel_target("channel://telegram/12345").unwrap();
+        assert_eq!(
+            (
+                plain.channel.as_str(),
+                plain.recipient.as_str(),
+                plain.urgent
+            ),
+            ("telegram", "12345", false)
+        );
+        let urgent = parse_channel_target("channel://Slack/C0123?urgent=true").unwrap();
+        assert_eq!(
+            (
+                urgent.channel.as_str(),
+                urgent.recipient.as_str(),
+                urgent.urgent
+            ),
+            ("slack", "C0123", true)
+        );
+        let styled =
+            parse_channel_target("channel://discord/99?template=terse&format=plain&digest=1")
+                .unwrap();
+        assert_eq!(styled.template.as_deref(), Some("terse"));
+        assert!(styled.plain && styled.digest && !styled.urgent);
+        assert_eq!(styled.attach, None);
+        assert!(!styled.secure);
+        assert!(
+            parse_channel_target("channel://slack/C1?secure=true&attach=true")
+                .unwrap()
+                .secure
+        );
+        assert_eq!(parse_channel_target("file://reports/out.md"), None);
+        assert_eq!(parse_channel_target("channel://telegram/"), None);
+    }
+
+    #[test]
+    fn artifacts_are_selected_by_attach_option_and_deliver_to() {
+        let artifact = |uri: &str, kind: &str, metadata: serde_json::Value| RunArtifact {
+            artifact_id: "artifact-1".to_string(),
+            uri: uri.to_string(),
+            kind: kind.to_string(),
+            label: None,
+            metadata: Some(metadata),
+        };
+        let report = artifact("file://out/report.md", "file", serde_json::json!({}));
+        let target = |raw: &str| parse_channel_target(raw).unwrap();
+
+        assert!(report.wanted_by(&target("channel://slack/C1?attach=true")));
+        assert!(report.wanted_by(&target("channel://slack/C1?attach=diff.patch,REPORT.md")));
+        assert!(!report.wanted_by(&target("channel://slack/C1?attach=diff.patch")));
+        assert!(!report.wanted_by(&target("channel://slack/C1")));
+        let configured = artifact(
+            "channel://slack/C1?attach=true",
+            "output_target",
+            serde_json::json!({}),
+        );
+        assert!(!configured.wanted_by(&target("channel://slack/C1?attach=true")));
+
+        let requested = artifact(
+            "/ws/diff.patch",
+            "file",
+            serde_json::json!({ "deliver_to": ["channel://telegram/42", "file://x"] }),
+        );
+        let targets = requested.deliver_to();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].recipient, "42");
+        assert_eq!(requested.file_name(), "diff.patch");
+    }
+
     #[test]
     fn parse_unknown_returns_none() {
         assert!(parse_slash_command("/unknown").is_none());
@@ -1731,6 +3535,8 @@ mod tests {
             last_seen_at_ms: 2000,
             channel: "telegram".to_string(),
             sender: "user1".to_string(),
+            user_id: None,
+            imported_conversations: Vec::new(),
         };
         let serialized = serde_json::to_string(&record).unwrap();
         let deserialized: SessionRecord = serde_json::from_str(&serialized).unwrap();
@@ -1740,4 +3546,133 @@ mod tests {
         assert_eq!(deserialized.channel, "telegram");
         assert_eq!(deserialized.sender, "user1");
     }
+
+    #[test]
+    fn session_record_without_user_id_still_parses() {
+        let raw = r#"{"session_id":"s1","created_at_ms":1,"last_seen_at_ms":2,"channel":"slack","sender":"U1"}"#;
+        let record: SessionRecord = serde_json::from_str(raw).unwrap();
+        assert!(record.user_id.is_none());
+        assert!(record.imported_conversations.is_empty());
+    }
+
+    // ── Identity-aware session keys ───────────────────────────────────────
+
+    fn channel_message(channel: &str, sender: &str, user_id: Option<&str>) -> ChannelMessage {
+        ChannelMessage {
+            id: "m1".to_string(),
+            sender: sender.to_string(),
+            reply_target: "chat".to_string(),
+            content: "hello".to_string(),
+            channel: channel.to_string(),
+            timestamp: chrono::Utc::now(),
+            attachment: None,
+            user_id: user_id.map(ToString::to_string),
+            reaction: None,
+            locale: None,
+        }
+    }
+
+    #[test]
+    fn reactions_resolve_to_the_reply_they_target() {
+        let mut index = ReplyIndex::default();
+        let reply = |id: &str| TrackedReply {
+            session_id: "s1".to_string(),
+            message_id: id.to_string(),
+        };
+        index.record(ReplyKey::new("discord", "c1", "p1"), reply("m1"));
+        index.record(ReplyKey::new("discord", "c1", "p2"), reply("m2"));
+        assert_eq!(
+            index.get(&ReplyKey::new("discord", "c1", "p1")),
+            Some(&reply("m1"))
+        );
+        assert_eq!(index.get(&ReplyKey::new("telegram", "c1", "p1")), None);
+        for n in 0..MAX_TRACKED_REPLIES {
+            index.record(ReplyKey::new("discord", "c2", &n.to_string()), reply("m3"));
+        }
+        assert_eq!(index.get(&ReplyKey::new("discord", "c1", "p1")), None);
+        assert_eq!(index.order.len(), MAX_TRACKED_REPLIES);
+    }
+
+    #[test]
+    fn latest_assistant_message_id_skips_user_messages() {
+        let messages = vec![
+            serde_json::json!({"info": {"id": "a1", "role": "assistant"}, "parts": []}),
+            serde_json::json!({"info": {"id": "a2", "role": "assistant"}, "parts": []}),
+            serde_json::json!({"info": {"id": "u1", "role": "user"}, "parts": []}),
+        ];
+        assert_eq!(
+            latest_assistant_message_id(&messages).as_deref(),
+            Some("a2")
+        );
+        assert_eq!(latest_assistant_message_id(&messages[2..]), None);
+    }
+
+    #[test]
+    fn reaction_rating_maps_thumbs_and_ignores_other_emoji() {
+        assert_eq!(reaction_rating("👍"), Some("up"));
+        assert_eq!(reaction_rating("👍🏽"), Some("up"));
+        assert_eq!(reaction_rating("❤️"), Some("up"));
+        assert_eq!(reaction_rating(":thumbsdown:"), Some("down"));
+        assert_eq!(reaction_rating("👀"), None);
+    }
+
+    #[test]
+    fn session_key_prefers_linked_user() {
+        assert_eq!(
+            session_key(&channel_message("telegram", "123", None)),
+            "telegram:123"
+        );
+        assert_eq!(
+            session_key(&channel_message("telegram", "123", Some("evan"))),
+            "user:evan"
+        );
+    }
+
+    #[tokio::test]
+    async fn linked_sender_adopts_existing_platform_session() {
+        let map: SessionMap = Arc::new(Mutex::new(HashMap::new()));
+        map.lock().await.insert(
+            "discord:987".to_string(),
+            SessionRecord {
+                session_id: "s-discord".to_string(),
+                created_at_ms: 1,
+                last_seen_at_ms: 1,
+                channel: "discord".to_string(),
+                sender: "987".to_string(),
+                user_id: None,
+                imported_conversations: Vec::new(),
+            },
+        );
+        let path = std::env::temp_dir()
+            .join(format!("tandem-channels-test-{}", uuid::Uuid::new_v4()))
+            .join("channel_sessions.json");
+
+        adopt_platform_session(
+            &channel_message("discord", "987", Some("evan")),
+            &map,
+            &path,
+        )
+        .await;
+
+        let guard = map.lock().await;
+        let adopted = guard.get("user:evan").expect("adopted record");
+        assert_eq!(adopted.session_id, "s-discord");
+        assert_eq!(adopted.user_id.as_deref(), Some("evan"));
+        assert!(path.exists());
+    }
+
+    #[tokio::test]
+    async fn state_files_are_replaced_without_leftover_temp_files() {
+        let dir =
+            std::env::temp_dir().join(format!("tandem-channels-test-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("channel_languages.json");
+        write_state_file(&path, b"{\"a\":1}")
+            .await
+            .expect("first write");
+        write_state_file(&path, b"{}").await.expect("second write");
+        assert_eq!(std::fs::read(&path).expect("read"), b"{}");
+        let entries = std::fs::read_dir(&dir).expect("dir").count();
+        assert_eq!(entries, 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,580 @@
+//! Platform formatting for proactive channel messages.
+//!
+//! Run results, routine digests, secure-link notices, approval notices,
+//! routine failures, routine alerts and event-rule notices are rendered from a [`MessageTemplate`] into a [`FormattedMessage`]: Slack Block
+//! Kit blocks, a Discord embed, or Telegram MarkdownV2. Templates are plain
+//! text with `{name}` placeholders; the rendered text is escaped for the target
+//! platform, so report content cannot inject mentions, links or broken markup.
+//!
+//! The built-in templates can be overridden in `channel_templates.json` (next
+//! to the session map). The file holds named template sets; `default` applies
+//! everywhere, and an output target picks another set with
+//! `channel://{channel}/{recipient}?template={name}`:
+//!
+//! ```json
+//! {
+//!   "default": { "error": { "title": "🚨 {routine_id} failed", "body": "{reason}" } },
+//!   "terse": { "run_result": { "title": "{routine_id}", "body": "{report}" } }
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::traits::SendMessage;
+
+const DEFAULT_SET: &str = "default";
+const SLACK_HEADER_MAX_CHARS: usize = 150;
+const SLACK_SECTION_MAX_CHARS: usize = 3_000;
+const SLACK_MAX_BLOCKS: usize = 50;
+const DISCORD_TITLE_MAX_CHARS: usize = 256;
+const DISCORD_DESCRIPTION_MAX_CHARS: usize = 4_096;
+const DISCORD_FOOTER_MAX_CHARS: usize = 2_048;
+/// Discord also caps the combined text of all fields in an embed.
+const DISCORD_EMBED_MAX_CHARS: usize = 6_000;
+const TELEGRAM_MAX_CHARS: usize = 4_096;
+const TELEGRAM_SPECIAL_CHARS: &str = "_*[]()~`>#+-=|{}.!\\";
+const DIGEST_MAX_LINES: usize = 8;
+const DIGEST_MAX_CHARS: usize = 600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageKind {
+    /// A completed routine run's final report.
+    RunResult,
+    /// A shortened run report, for targets that opt in with `?digest=true`.
+    RoutineDigest,
+    Approval,
+    /// A routine run that failed.
+    Error,
+    /// Text sent by an event rule's `notify_channel` action.
+    RuleNotice,
+    /// A routine's failure, recovery or completion notification.
+    RoutineAlert,
+    /// An expiring link sent in place of a run's report or an artifact, for
+    /// targets that opt in with `?secure=true`.
+    SecureLink,
+}
+
+impl MessageKind {
+    fn builtin_template(self) -> MessageTemplate {
+        let (title, body, footer) = match self {
+            Self::RunResult => ("📋 Routine {routine_id}", "{report}", "Run {run_id}"),
+            Self::RoutineDigest => (
+                "📋 Routine {routine_id} digest",
+                "{summary}",
+                "Run {run_id} · {changed}",
+            ),
+            Self::Approval => (
+                "{icon} Routine {routine_id} needs approval",
+                "Run {run_id}.\n{escalation}\n{reason}\nReply /approve_run {run_id} or /deny_run {run_id} [reason].",
+                "",
+            ),
+            Self::Error => ("❌ Routine {routine_id} failed", "{reason}", "Run {run_id}"),
+            Self::RuleNotice => ("🔔 {rule_id}", "{text}", "{event_type}"),
+            Self::RoutineAlert => ("{icon} Routine {routine_id}", "{text}", "Run {run_id}"),
+            Self::SecureLink => (
+                "🔒 Routine {routine_id}",
+                "The result is not posted here.\n{file_name}\n{link}\n{expires}",
+                "Run {run_id}",
+            ),
+        };
+        MessageTemplate {
+            title: title.to_string(),
+            body: body.to_string(),
+            footer: (!footer.is_empty()).then(|| footer.to_string()),
+            color: None,
+        }
+    }
+
+    fn default_color(self) -> u32 {
+        match self {
+            Self::RunResult => 0x3b82f6,
+            Self::RoutineDigest => 0x6366f1,
+            Self::Approval => 0xf59e0b,
+            Self::Error => 0xef4444,
+            Self::RuleNotice => 0x10b981,
+            Self::RoutineAlert => 0xf97316,
+            Self::SecureLink => 0x64748b,
+        }
+    }
+}
+
+/// Plain-text template with `{name}` placeholders. Template lines whose
+/// placeholders all render empty are dropped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageTemplate {
+    pub title: String,
+    pub body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footer: Option<String>,
+    /// Discord embed colour; defaults per message kind.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<u32>,
+}
+
+/// A message rendered for one platform.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FormattedMessage {
+    /// Plain-text rendering, used by platforms without a rich form.
+    pub text: String,
+    /// Platform request fields (Slack `blocks`, Discord `embeds`, Telegram
+    /// MarkdownV2 `text`); empty when the message goes out as plain text.
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    pub payload: Map<String, Value>,
+}
+
+impl FormattedMessage {
+    pub fn into_send(self, recipient: impl Into<String>) -> SendMessage {
+        SendMessage {
+            content: self.text,
+            recipient: recipient.into(),
+            payload: (!self.payload.is_empty()).then_some(self.payload),
+        }
+    }
+}
+
+/// Template sets by name, loaded from `channel_templates.json`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MessageTemplates {
+    sets: HashMap<String, HashMap<MessageKind, MessageTemplate>>,
+}
+
+impl MessageTemplates {
+    pub fn path() -> PathBuf {
+        crate::dispatcher::channel_state_dir().join("channel_templates.json")
+    }
+
+    /// Load the template overrides. A missing file yields the built-ins; an
+    /// unreadable one is logged and ignored.
+    pub async fn load() -> Self {
+        Self::load_from(&Self::path()).await
+    }
+
+    pub async fn load_from(path: &Path) -> Self {
+        let Ok(bytes) = tokio::fs::read(path).await else {
+            return Self::default();
+        };
+        serde_json::from_slice(&bytes).unwrap_or_else(|e| {
+            tracing::warn!("ignoring invalid {}: {e}", path.display());
+            Self::default()
+        })
+    }
+
+    /// The template for `kind` from `set`, falling back to the `default` set
+    /// and then the built-in template.
+    pub fn template(&self, set: Option<&str>, kind: MessageKind) -> MessageTemplate {
+        set.into_iter()
+            .chain([DEFAULT_SET])
+            .find_map(|name| self.sets.get(name)?.get(&kind).cloned())
+            .unwrap_or_else(|| kind.builtin_template())
+    }
+
+    /// Render `kind` for `channel` (`slack`, `discord`, `telegram`; anything
+    /// else gets plain text only).
+    pub fn render(
+        &self,
+        channel: &str,
+        kind: MessageKind,
+        set: Option<&str>,
+        vars: &[(&str, String)],
+    ) -> FormattedMessage {
+        let template = self.template(set, kind);
+        let parts = Rendered {
+            title: fill(&template.title, vars),
+            body: fill(&template.body, vars),
+            footer: template
+                .footer
+                .as_deref()
+                .map(|footer| fill(footer, vars))
+                .filter(|footer| !footer.is_empty()),
+        };
+        let payload = match channel {
+            "slack" => slack_payload(&parts),
+            "discord" => discord_payload(&parts, template.color.unwrap_or(kind.default_color())),
+            "telegram" => telegram_payload(&parts),
+            _ => Map::new(),
+        };
+        FormattedMessage {
+            text: parts.plain_text(),
+            payload,
+        }
+    }
+}
+
+struct Rendered {
+    title: String,
+    body: String,
+    footer: Option<String>,
+}
+
+impl Rendered {
+    fn plain_text(&self) -> String {
+        [Some(&self.title), Some(&self.body), self.footer.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter(|part| !part.is_empty())
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// Substitute `{name}` placeholders in one pass; unknown names are kept.
+fn fill(template: &str, vars: &[(&str, String)]) -> String {
+    let mut lines = Vec::new();
+    for line in template.lines() {
+        let mut out = String::with_capacity(line.len());
+        let mut rest = line;
+        while let Some(open) = rest.find('{') {
+            out.push_str(&rest[..open]);
+            let after = &rest[open + 1..];
+            let value = after.find('}').and_then(|close| {
+                let name = &after[..close];
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| (value, close))
+            });
+            match value {
+                Some((value, close)) => {
+                    out.push_str(value);
+                    rest = &after[close + 1..];
+                }
+                None => {
+                    out.push('{');
+                    rest = after;
+                }
+            }
+        }
+        out.push_str(rest);
+        if line.trim().is_empty() || !out.trim().is_empty() {
+            lines.push(out);
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// Escape text for Slack `mrkdwn` so it cannot form links or mentions.
+pub fn escape_slack(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Neutralise Discord mass mentions and `<@…>`/`<#…>` references.
+pub fn escape_discord(text: &str) -> String {
+    text.replace("@everyone", "@\u{200b}everyone")
+        .replace("@here", "@\u{200b}here")
+        .replace("<@", "<\u{200b}@")
+        .replace("<#", "<\u{200b}#")
+}
+
+/// Escape every character Telegram MarkdownV2 treats as markup.
+pub fn escape_telegram_markdown_v2(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if TELEGRAM_SPECIAL_CHARS.contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn slack_payload(parts: &Rendered) -> Map<String, Value> {
+    let mut blocks = vec![json!({
+        "type": "header",
+        "text": {
+            "type": "plain_text",
+            "text": truncate_chars(&parts.title, SLACK_HEADER_MAX_CHARS),
+            "emoji": true,
+        },
+    })];
+    let body = escape_slack(&parts.body);
+    let section_budget = SLACK_MAX_BLOCKS - 2;
+    for chunk in split_escaped(&body, SLACK_SECTION_MAX_CHARS)
+        .into_iter()
+        .take(section_budget)
+    {
+        blocks.push(json!({
+            "type": "section",
+            "text": { "type": "mrkdwn", "text": chunk },
+        }));
+    }
+    if let Some(footer) = &parts.footer {
+        blocks.push(json!({
+            "type": "context",
+            "elements": [{ "type": "mrkdwn", "text": escape_slack(footer) }],
+        }));
+    }
+    let mut payload = Map::new();
+    payload.insert(
+        "text".to_string(),
+        Value::String(escape_slack(&parts.plain_text())),
+    );
+    payload.insert("blocks".to_string(), Value::Array(blocks));
+    payload
+}
+
+fn discord_payload(parts: &Rendered, color: u32) -> Map<String, Value> {
+    let title = truncate_chars(&escape_discord(&parts.title), DISCORD_TITLE_MAX_CHARS);
+    let footer = parts
+        .footer
+        .as_ref()
+        .map(|footer| truncate_chars(&escape_discord(footer), DISCORD_FOOTER_MAX_CHARS));
+    // The description gets whatever the title and footer leave of the total.
+    let used = title.chars().count() + footer.as_ref().map_or(0, |f| f.chars().count());
+    let description_max = DISCORD_DESCRIPTION_MAX_CHARS.min(DISCORD_EMBED_MAX_CHARS - used);
+    let mut embed = json!({
+        "title": title,
+        "color": color,
+    });
+    if !parts.body.is_empty() {
+        embed["description"] = Value::String(truncate_chars(
+            &escape_discord(&parts.body),
+            description_max,
+        ));
+    }
+    if let Some(footer) = footer {
+        embed["footer"] = json!({ "text": footer });
+    }
+    let mut payload = Map::new();
+    payload.insert("embeds".to_string(), json!([embed]));
+    payload.insert("allowed_mentions".to_string(), json!({ "parse": [] }));
+    payload
+}
+
+/// MarkdownV2 rendering, or nothing when it would exceed one message (the
+/// plain text is then chunked as usual).
+fn telegram_payload(parts: &Rendered) -> Map<String, Value> {
+    let mut text = format!("*{}*", escape_telegram_markdown_v2(&parts.title));
+    if !parts.body.is_empty() {
+        text.push_str("\n\n");
+        text.push_str(&escape_telegram_markdown_v2(&parts.body));
+    }
+    if let Some(footer) = &parts.footer {
+        text.push_str("\n\n_");
+        text.push_str(&escape_telegram_markdown_v2(footer));
+        text.push('_');
+    }
+    let mut payload = Map::new();
+    if text.chars().count() > TELEGRAM_MAX_CHARS {
+        return payload;
+    }
+    payload.insert("text".to_string(), Value::String(text));
+    payload.insert(
+        "parse_mode".to_string(),
+        Value::String("MarkdownV2".to_string()),
+    );
+    payload
+}
+
+/// First lines of a report, for digests.
+pub fn digest_summary(report: &str) -> String {
+    let lines = report
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+    let mut summary = lines
+        .iter()
+        .take(DIGEST_MAX_LINES)
+        .copied()
+        .collect::<Vec<_>>()
+        .join("\n");
+    if lines.len() > DIGEST_MAX_LINES {
+        summary.push_str("\n…");
+    }
+    truncate_chars(&summary, DIGEST_MAX_CHARS)
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let mut out = text.chars().take(max - 1).collect::<String>();
+    out.push('…');
+    out
+}
+
+/// Split escaped text into chunks of at most `max` chars, preferring line
+/// breaks and never cutting through an `&…;` entity.
+fn split_escaped(text: &str, max: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        if rest.chars().count() <= max {
+            chunks.push(rest.to_string());
+            break;
+        }
+        let limit = rest
+            .char_indices()
+            .nth(max)
+            .map(|(idx, _)| idx)
+            .unwrap_or(rest.len());
+        let window = &rest[..limit];
+        let mut cut = window.rfind('\n').filter(|idx| *idx > 0).unwrap_or(limit);
+        if let Some(amp) = window[..cut].rfind('&') {
+            if !window[amp..cut].contains(';') && cut - amp < 6 {
+                cut = amp;
+            }
+        }
+        chunks.push(rest[..cut].trim_end().to_string());
+        rest = rest[cut..].trim_start();
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> Vec<(&'static str, String)> {
+        vec![
+            ("routine_id", "nightly".to_string()),
+            ("run_id", "run-1".to_string()),
+            (
+                "report",
+                "Done. <@U123> see *notes* & @everyone".to_string(),
+            ),
+        ]
+    }
+
+    #[test]
+    fn discord_embeds_stay_within_the_total_limit() {
+        let parts = Rendered {
+            title: "t".repeat(400),
+            body: "b".repeat(5_000),
+            footer: Some("f".repeat(3_000)),
+        };
+        let payload = discord_payload(&parts, 0);
+        let embed = &payload["embeds"][0];
+        let len = |value: &Value| value.as_str().unwrap().chars().count();
+        let total =
+            len(&embed["title"]) + len(&embed["description"]) + len(&embed["footer"]["text"]);
+        assert_eq!(len(&embed["title"]), DISCORD_TITLE_MAX_CHARS);
+        assert_eq!(len(&embed["footer"]["text"]), DISCORD_FOOTER_MAX_CHARS);
+        assert_eq!(total, DISCORD_EMBED_MAX_CHARS);
+    }
+
+    #[test]
+    fn renders_escaped_platform_payloads() {
+        let templates = MessageTemplates::default();
+
+        let slack = templates.render("slack", MessageKind::RunResult, None, &vars());
+        let section = &slack.payload["blocks"][1]["text"]["text"];
+        assert_eq!(
+            section,
+            &json!("Done. &lt;@U123&gt; see *notes* &amp; @everyone")
+        );
+        assert_eq!(slack.payload["blocks"][0]["type"], json!("header"));
+
+        let discord = templates.render("discord", MessageKind::RunResult, None, &vars());
+        let description = discord.payload["embeds"][0]["description"]
+            .as_str()
+            .unwrap();
+        assert!(!description.contains("<@U123"));
+        assert!(!description.contains("@everyone"));
+        assert_eq!(discord.payload["allowed_mentions"], json!({ "parse": [] }));
+
+        let telegram = templates.render("telegram", MessageKind::RunResult, None, &vars());
+        assert_eq!(telegram.payload["parse_mode"], json!("MarkdownV2"));
+        assert!(telegram.payload["text"]
+            .as_str()
+            .unwrap()
+            .contains("Done\\. <@U123\\> see \\*notes\\*"));
+
+        let plain = templates.render("matrix", MessageKind::RunResult, None, &vars());
+        assert!(plain.payload.is_empty());
+        assert_eq!(
+            plain.text,
+            "📋 Routine nightly\n\nDone. <@U123> see *notes* & @everyone\n\nRun run-1"
+        );
+    }
+
+    #[test]
+    fn named_sets_override_defaults_and_empty_lines_drop() {
+        let templates: MessageTemplates = serde_json::from_value(json!({
+            "default": { "error": { "title": "{routine_id} broke", "body": "{reason}" } },
+            "terse": { "run_result": { "title": "{routine_id}", "body": "{report}", "color": 1 } }
+        }))
+        .unwrap();
+        let error = templates.render(
+            "matrix",
+            MessageKind::Error,
+            Some("terse"),
+            &[
+                ("routine_id", "nightly".to_string()),
+                ("reason", "boom".to_string()),
+            ],
+        );
+        assert_eq!(error.text, "nightly broke\n\nboom");
+        assert_eq!(
+            templates
+                .template(Some("terse"), MessageKind::RunResult)
+                .color,
+            Some(1)
+        );
+
+        let approval = templates.render(
+            "matrix",
+            MessageKind::Approval,
+            None,
+            &[
+                ("icon", "🔔".to_string()),
+                ("routine_id", "nightly".to_string()),
+                ("run_id", "run-9".to_string()),
+                ("escalation", String::new()),
+                ("reason", String::new()),
+            ],
+        );
+        assert_eq!(
+            approval.text,
+            "🔔 Routine nightly needs approval\n\nRun run-9.\nReply /approve_run run-9 or /deny_run run-9 [reason]."
+        );
+    }
+
+    #[test]
+    fn secure_link_notice_drops_missing_link_lines() {
+        let templates = MessageTemplates::default();
+        let vars = |link: &str, expires: &str| {
+            vec![
+                ("routine_id", "rotation".to_string()),
+                ("run_id", "run-1".to_string()),
+                ("file_name", String::new()),
+                ("link", link.to_string()),
+                ("expires", expires.to_string()),
+            ]
+        };
+        let notice = templates.render(
+            "matrix",
+            MessageKind::SecureLink,
+            None,
+            &vars(
+                "https://tandem/admin/share/abc",
+                "Expires 2026-01-02 03:04 UTC",
+            ),
+        );
+        assert_eq!(
+            notice.text,
+            "🔒 Routine rotation\n\nThe result is not posted here.\nhttps://tandem/admin/share/abc\nExpires 2026-01-02 03:04 UTC\n\nRun run-1"
+        );
+        let unlinked = templates.render("matrix", MessageKind::SecureLink, None, &vars("", ""));
+        assert_eq!(
+            unlinked.text,
+            "🔒 Routine rotation\n\nThe result is not posted here.\n\nRun run-1"
+        );
+    }
+
+    #[test]
+    fn long_slack_bodies_split_without_cutting_entities() {
+        let body = format!("{}&amp;tail", "a".repeat(2_996));
+        let chunks = split_escaped(&body, SLACK_SECTION_MAX_CHARS);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks[1].starts_with("&amp;"));
+    }
+}
@@ -0,0 +1,274 @@
+//! Cross-channel identity mapping.
+//!
+//! A Tandem user owns any number of platform identities (`telegram:12345`,
+//! `discord:98765`, `slack:U0XXXX`). Once linked, the dispatcher keys session
+//! affinity by the Tandem user instead of the platform account, and allowlist
+//! entries of the form `user:<user_id>` admit every linked identity.
+//!
+//! The directory is persisted next to the channel session map as
+//! `channel_identities.json`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, MutexGuard};
+
+/// Allowlist entry prefix that refers to a Tandem user rather than a platform ID.
+pub const USER_ALLOWLIST_PREFIX: &str = "user:";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ChannelIdentity {
+    pub channel: String,
+    pub sender: String,
+}
+
+impl ChannelIdentity {
+    pub fn new(channel: impl Into<String>, sender: impl Into<String>) -> Self {
+        Self {
+            channel: channel.into().trim().to_ascii_lowercase(),
+            sender: sender.into().trim().to_string(),
+        }
+    }
+
+    /// `{channel}:{sender}` — the platform-scoped key used before linking.
+    pub fn key(&self) -> String {
+        format!("{}:{}", self.channel, self.sender)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TandemUser {
+    pub user_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub identities: Vec<ChannelIdentity>,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum IdentityError {
+    InvalidUserId(String),
+    AlreadyLinked { identity: String, user_id: String },
+}
+
+impl std::fmt::Display for IdentityError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidUserId(user_id) => write!(f, "invalid user id `{user_id}`"),
+            Self::AlreadyLinked { identity, user_id } => {
+                write!(
+                    f,
+                    "identity `{identity}` is already linked to user `{user_id}`"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for IdentityError {}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IdentityDirectory {
+    #[serde(default)]
+    pub users: HashMap<String, TandemUser>,
+}
+
+fn valid_user_id(user_id: &str) -> bool {
+    !user_id.is_empty()
+        && user_id.len() <= 128
+        && user_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@'))
+}
+
+impl IdentityDirectory {
+    /// Path of the persisted directory under Tandem's state dir.
+    pub fn path() -> PathBuf {
+        crate::dispatcher::channel_state_dir().join("channel_identities.json")
+    }
+
+    /// Load the persisted directory. Missing or unreadable files yield an
+    /// empty directory.
+    pub async fn load() -> Self {
+        Self::load_from(&Self::path()).await
+    }
+
+    pub async fn load_from(path: &Path) -> Self {
+        let Ok(bytes) = tokio::fs::read(path).await else {
+            return Self::default();
+        };
+        serde_json::from_slice(&bytes).unwrap_or_default()
+    }
+
+    /// Hold this across a load/modify/save cycle so concurrent edits do not
+    /// overwrite each other.
+    pub async fn write_lock() -> MutexGuard<'static, ()> {
+        static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
+        LOCK.get_or_init(|| Mutex::new(())).lock().await
+    }
+
+    pub async fn save_to(&self, path: &Path) -> anyhow::Result<()> {
+        crate::dispatcher::write_state_file(path, &serde_json::to_vec_pretty(self)?).await?;
+        Ok(())
+    }
+
+    /// Tandem user owning `channel`/`sender`, if linked.
+    pub fn resolve(&self, channel: &str, sender: &str) -> Option<&TandemUser> {
+        let wanted = ChannelIdentity::new(channel, sender);
+        self.users
+            .values()
+            .find(|user| user.identities.iter().any(|identity| identity == &wanted))
+    }
+
+    /// Replace a user's identity set (creating the user if needed). Fails if
+    /// any identity already belongs to a different user.
+    pub fn upsert_user(
+        &mut self,
+        user_id: &str,
+        display_name: Option<String>,
+        identities: Vec<ChannelIdentity>,
+    ) -> Result<&TandemUser, IdentityError> {
+        let user_id = user_id.trim();
+        if !valid_user_id(user_id) {
+            return Err(IdentityError::InvalidUserId(user_id.to_string()));
+        }
+        let mut normalized = Vec::with_capacity(identities.len());
+        for identity in identities {
+            let identity = ChannelIdentity::new(identity.channel, identity.sender);
+            if identity.channel.is_empty() || identity.sender.is_empty() {
+                continue;
+            }
+            if let Some(owner) = self.resolve(&identity.channel, &identity.sender) {
+                if owner.user_id != user_id {
+                    return Err(IdentityError::AlreadyLinked {
+                        identity: identity.key(),
+                        user_id: owner.user_id.clone(),
+                    });
+                }
+            }
+            if !normalized.contains(&identity) {
+                normalized.push(identity);
+            }
+        }
+        let now = crate::dispatcher::now_ms();
+        let user = self
+            .users
+            .entry(user_id.to_string())
+            .or_insert_with(|| TandemUser {
+                user_id: user_id.to_string(),
+                display_name: None,
+                identities: Vec::new(),
+                created_at_ms: now,
+                updated_at_ms: now,
+            });
+        if display_name.is_some() {
+            user.display_name = display_name;
+        }
+        user.identities = normalized;
+        user.updated_at_ms = now;
+        Ok(user)
+    }
+
+    pub fn remove_user(&mut self, user_id: &str) -> Option<TandemUser> {
+        self.users.remove(user_id)
+    }
+
+    /// Expand `user:<id>` allowlist entries into the platform senders linked
+    /// for `channel`. Other entries pass through unchanged.
+    pub fn expand_allowed_users(&self, channel: &str, allowed_users: &[String]) -> Vec<String> {
+        let channel = channel.to_ascii_lowercase();
+        let mut out = Vec::with_capacity(allowed_users.len());
+        for entry in allowed_users {
+            let Some(user_id) = entry.strip_prefix(USER_ALLOWLIST_PREFIX) else {
+                out.push(entry.clone());
+                continue;
+            };
+            let Some(user) = self.users.get(user_id.trim()) else {
+                continue;
+            };
+            for identity in user.identities.iter().filter(|i| i.channel == channel) {
+                if !out.contains(&identity.sender) {
+                    out.push(identity.sender.clone());
+                }
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory() -> IdentityDirectory {
+        let mut dir = IdentityDirectory::default();
+        dir.upsert_user(
+            "evan",
+            Some("Evan".to_string()),
+            vec![
+                ChannelIdentity::new("telegram", "12345"),
+                ChannelIdentity::new("Discord", "98765"),
+            ],
+        )
+        .expect("upsert");
+        dir
+    }
+
+    #[test]
+    fn resolves_linked_identities_across_channels() {
+        let dir = directory();
+        assert_eq!(
+            dir.resolve("telegram", "12345").map(|u| u.user_id.as_str()),
+            Some("evan")
+        );
+        assert_eq!(
+            dir.resolve("discord", "98765").map(|u| u.user_id.as_str()),
+            Some("evan")
+        );
+        assert!(dir.resolve("slack", "12345").is_none());
+    }
+
+    #[test]
+    fn identity_cannot_belong_to_two_users() {
+        let mut dir = directory();
+        let err = dir
+            .upsert_user(
+                "mallory",
+                None,
+                vec![ChannelIdentity::new("telegram", "12345")],
+            )
+            .expect_err("conflict");
+        assert_eq!(
+            err,
+            IdentityError::AlreadyLinked {
+                identity: "telegram:12345".to_string(),
+                user_id: "evan".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_user_ids() {
+        let mut dir = IdentityDirectory::default();
+        assert!(dir.upsert_user("has space", None, Vec::new()).is_err());
+        assert!(dir.upsert_user("", None, Vec::new()).is_err());
+    }
+
+    #[test]
+    fn expands_user_allowlist_entries_per_channel() {
+        let dir = directory();
+        let allowed = vec!["user:evan".to_string(), "@alice".to_string()];
+        assert_eq!(
+            dir.expand_allowed_users("telegram", &allowed),
+            vec!["12345".to_string(), "@alice".to_string()]
+        );
+        assert_eq!(
+            dir.expand_allowed_users("slack", &allowed),
+            vec!["@alice".to_string()]
+        );
+    }
+}
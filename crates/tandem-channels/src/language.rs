@@ -0,0 +1,389 @@
+//! Per-sender response language for channel conversations.
+//!
+//! Each session key (see [`crate::dispatcher`]) gets a language preference.
+//! It is detected from what the sender writes, falls back to the platform
+//! locale (e.g. Telegram's `language_code`), and can be pinned with
+//! `/language <code>`. The dispatcher turns the preference into a
+//! response-language directive sent with every prompt.
+//!
+//! Preferences are persisted next to the session map as
+//! `channel_languages.json`.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+
+use serde::{Deserialize, Serialize};
+
+/// `(tag, English name, endonym)` for languages we can name in a directive.
+const LANGUAGES: &[(&str, &str, &str)] = &[
+    ("en", "English", "english"),
+    ("de", "German", "deutsch"),
+    ("fr", "French", "français"),
+    ("es", "Spanish", "español"),
+    ("it", "Italian", "italiano"),
+    ("pt", "Portuguese", "português"),
+    ("nl", "Dutch", "nederlands"),
+    ("pl", "Polish", "polski"),
+    ("tr", "Turkish", "türkçe"),
+    ("ru", "Russian", "русский"),
+    ("uk", "Ukrainian", "українська"),
+    ("ar", "Arabic", "العربية"),
+    ("he", "Hebrew", "עברית"),
+    ("el", "Greek", "ελληνικά"),
+    ("hi", "Hindi", "हिन्दी"),
+    ("th", "Thai", "ไทย"),
+    ("ja", "Japanese", "日本語"),
+    ("ko", "Korean", "한국어"),
+    ("zh", "Chinese", "中文"),
+];
+
+/// Function words that identify Latin-script languages.
+const STOPWORDS: &[(&str, &[&str])] = &[
+    (
+        "en",
+        &[
+            "the", "and", "is", "are", "you", "what", "how", "this", "that", "with", "for", "can",
+            "please", "my", "to", "of", "it", "i", "do", "not",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "das", "und", "ist", "ich", "nicht", "ein", "eine", "mit", "wie", "was",
+            "bitte", "kannst", "du", "mir", "zu", "auf", "für", "sie", "es", "den", "dem",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "la", "les", "et", "est", "je", "vous", "une", "un", "pour", "avec", "que",
+            "pas", "des", "du", "sur", "peux", "moi", "tu", "ce", "merci",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "la", "los", "las", "y", "es", "que", "por", "para", "con", "una", "un", "yo",
+            "puedes", "qué", "cómo", "gracias", "del", "mi", "lo", "no",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "lo", "gli", "e", "è", "che", "per", "con", "una", "un", "sono", "puoi", "come",
+            "grazie", "della", "non", "mi", "di",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "os", "as", "e", "é", "que", "para", "com", "uma", "um", "você", "não", "obrigado",
+            "como", "do", "da", "em", "meu",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "een", "en", "is", "ik", "niet", "je", "van", "met", "wat", "hoe",
+            "alsjeblieft", "kun", "mij", "op", "dat",
+        ],
+    ),
+];
+
+const MIN_DETECT_WORDS: usize = 3;
+const MIN_SCRIPT_LETTERS: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LanguageSource {
+    /// Set with `/language`; never overridden by detection.
+    Explicit,
+    Detected,
+    Platform,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LanguagePreference {
+    pub tag: String,
+    pub source: LanguageSource,
+    pub updated_at_ms: u64,
+}
+
+/// Map a code or language name (`de`, `de-AT`, `German`, `deutsch`) to a
+/// supported tag.
+pub fn normalize_language_tag(raw: &str) -> Option<&'static str> {
+    let lowered = raw.trim().to_lowercase().replace('_', "-");
+    let primary = lowered.split('-').next().unwrap_or_default();
+    LANGUAGES
+        .iter()
+        .find(|(tag, name, endonym)| {
+            *tag == primary || name.to_lowercase() == lowered || *endonym == lowered
+        })
+        .map(|(tag, _, _)| *tag)
+}
+
+pub fn language_name(tag: &str) -> &str {
+    LANGUAGES
+        .iter()
+        .find(|(t, _, _)| *t == tag)
+        .map(|(_, name, _)| *name)
+        .unwrap_or(tag)
+}
+
+/// System-prompt directive asking the model to answer in `tag`.
+pub fn response_language_directive(tag: &str) -> String {
+    format!(
+        "Respond in {} ({tag}) unless the user explicitly asks for a different language. Keep code, commands and identifiers unchanged.",
+        language_name(tag)
+    )
+}
+
+fn script_language(c: char) -> Option<&'static str> {
+    Some(match c as u32 {
+        0x3040..=0x30FF => "ja",
+        0xAC00..=0xD7AF | 0x1100..=0x11FF => "ko",
+        0x4E00..=0x9FFF => "zh",
+        0x0400..=0x04FF => "ru",
+        0x0600..=0x06FF => "ar",
+        0x0590..=0x05FF => "he",
+        0x0370..=0x03FF => "el",
+        0x0900..=0x097F => "hi",
+        0x0E00..=0x0E7F => "th",
+        _ => return None,
+    })
+}
+
+/// Best-effort language of `text`, or `None` when it is too short or
+/// ambiguous (commands, code, "ok thanks").
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let mut scripts: HashMap<&'static str, usize> = HashMap::new();
+    let mut latin = 0usize;
+    for c in text.chars().filter(|c| c.is_alphabetic()) {
+        match script_language(c) {
+            Some(lang) => *scripts.entry(lang).or_default() += 1,
+            None => latin += 1,
+        }
+    }
+    // Any kana marks Japanese even though most characters may be Han.
+    if scripts.get("ja").copied().unwrap_or(0) >= MIN_SCRIPT_LETTERS {
+        return Some("ja");
+    }
+    if let Some((lang, count)) = scripts.iter().max_by_key(|(_, count)| **count) {
+        if *count >= MIN_SCRIPT_LETTERS && *count >= latin {
+            return Some(if *lang == "ru" && text.contains(['ї', 'є', 'і']) {
+                "uk"
+            } else {
+                lang
+            });
+        }
+    }
+
+    let words = text
+        .split(|c: char| !c.is_alphabetic())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>();
+    if words.len() < MIN_DETECT_WORDS {
+        return None;
+    }
+    let mut scores = STOPWORDS
+        .iter()
+        .map(|(lang, stopwords)| {
+            let hits = words
+                .iter()
+                .filter(|w| stopwords.contains(&w.as_str()))
+                .count();
+            (*lang, hits)
+        })
+        .collect::<Vec<_>>();
+    scores.sort_by_key(|(_, hits)| std::cmp::Reverse(*hits));
+    match scores.as_slice() {
+        [(lang, best), (_, second), ..] if *best >= 2 && best > second => Some(*lang),
+        _ => None,
+    }
+}
+
+/// Language preferences keyed by dispatcher session key.
+pub struct LanguagePreferences {
+    path: PathBuf,
+    prefs: Mutex<HashMap<String, LanguagePreference>>,
+}
+
+impl LanguagePreferences {
+    pub fn new(path: PathBuf) -> Self {
+        let prefs = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            prefs: Mutex::new(prefs),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<LanguagePreference> {
+        self.prefs.lock().ok()?.get(key).cloned()
+    }
+
+    /// Update the preference from an incoming message and return the language
+    /// replies should use. Explicit choices always win; a confident detection
+    /// replaces an earlier detection or platform hint.
+    pub async fn observe(
+        &self,
+        key: &str,
+        text: &str,
+        platform_locale: Option<&str>,
+        now_ms: u64,
+    ) -> Option<String> {
+        let candidate = detect_language(text)
+            .map(|tag| (tag, LanguageSource::Detected))
+            .or_else(|| {
+                platform_locale
+                    .and_then(normalize_language_tag)
+                    .map(|tag| (tag, LanguageSource::Platform))
+            });
+        let (current, changed) = {
+            let mut prefs = self.prefs.lock().ok()?;
+            let existing = prefs.get(key).cloned();
+            let replace = match (&existing, candidate) {
+                (_, None) => false,
+                (Some(pref), _) if pref.source == LanguageSource::Explicit => false,
+                (None, Some(_)) => true,
+                (Some(pref), Some((tag, source))) => {
+                    pref.tag != tag
+                        && (source == LanguageSource::Detected
+                            || pref.source == LanguageSource::Platform)
+                }
+            };
+            if let (true, Some((tag, source))) = (replace, candidate) {
+                prefs.insert(
+                    key.to_string(),
+                    LanguagePreference {
+                        tag: tag.to_string(),
+                        source,
+                        updated_at_ms: now_ms,
+                    },
+                );
+            }
+            (prefs.get(key).map(|p| p.tag.clone()), replace)
+        };
+        if changed {
+            self.persist().await;
+        }
+        current
+    }
+
+    pub async fn set_explicit(&self, key: &str, tag: &str, now_ms: u64) {
+        if let Ok(mut prefs) = self.prefs.lock() {
+            prefs.insert(
+                key.to_string(),
+                LanguagePreference {
+                    tag: tag.to_string(),
+                    source: LanguageSource::Explicit,
+                    updated_at_ms: now_ms,
+                },
+            );
+        }
+        self.persist().await;
+    }
+
+    /// Forget the preference so detection takes over again.
+    pub async fn reset(&self, key: &str) {
+        if let Ok(mut prefs) = self.prefs.lock() {
+            prefs.remove(key);
+        }
+        self.persist().await;
+    }
+
+    async fn persist(&self) {
+        let Some(payload) = self
+            .prefs
+            .lock()
+            .ok()
+            .and_then(|prefs| serde_json::to_string_pretty(&*prefs).ok())
+        else {
+            return;
+        };
+        let _ = crate::dispatcher::write_state_file(&self.path, payload.as_bytes()).await;
+    }
+}
+
+/// Process-wide preference store used by the dispatcher.
+pub fn language_preferences() -> &'static LanguagePreferences {
+    static PREFS: OnceLock<LanguagePreferences> = OnceLock::new();
+    PREFS.get_or_init(|| {
+        LanguagePreferences::new(
+            crate::dispatcher::channel_state_dir().join("channel_languages.json"),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_scripts_and_latin_languages() {
+        assert_eq!(
+            detect_language("Kannst du mir bitte die Datei zeigen?"),
+            Some("de")
+        );
+        assert_eq!(
+            detect_language("Can you show me the file please?"),
+            Some("en")
+        );
+        assert_eq!(
+            detect_language("¿Puedes mostrarme el archivo, por favor?"),
+            Some("es")
+        );
+        assert_eq!(detect_language("ファイルを見せてください"), Some("ja"));
+        assert_eq!(detect_language("покажи мне файл"), Some("ru"));
+        assert_eq!(detect_language("ok thanks"), None);
+        assert_eq!(detect_language("cargo build --release"), None);
+        assert_eq!(normalize_language_tag("de_AT"), Some("de"));
+        assert_eq!(normalize_language_tag("German"), Some("de"));
+        assert_eq!(normalize_language_tag("klingon"), None);
+    }
+
+    #[tokio::test]
+    async fn explicit_choice_beats_detection_until_reset() {
+        let path = std::env::temp_dir().join(format!(
+            "tandem-channel-languages-{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        let prefs = LanguagePreferences::new(path.clone());
+        assert_eq!(
+            prefs.observe("telegram:1", "hi", Some("de"), 1).await.as_deref(),
+            Some("de")
+        );
+        assert_eq!(
+            prefs
+                .observe("telegram:1", "Can you show me the logs please", None, 2)
+                .await
+                .as_deref(),
+            Some("en")
+        );
+        // A platform hint does not undo a detection.
+        assert_eq!(
+            prefs.observe("telegram:1", "ok", Some("de"), 3).await.as_deref(),
+            Some("en")
+        );
+        prefs.set_explicit("telegram:1", "fr", 4).await;
+        assert_eq!(
+            prefs
+                .observe("telegram:1", "Can you show me the logs please", None, 5)
+                .await
+                .as_deref(),
+            Some("fr")
+        );
+        let reloaded = LanguagePreferences::new(path.clone());
+        assert_eq!(
+            reloaded.get("telegram:1").map(|p| p.source),
+            Some(LanguageSource::Explicit)
+        );
+        reloaded.reset("telegram:1").await;
+        assert!(reloaded.get("telegram:1").is_none());
+        let _ = std::fs::remove_file(path);
+    }
+}
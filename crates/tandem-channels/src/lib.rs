@@ -17,9 +17,15 @@
 //! }
 //! ```
 
+pub mod attachments;
 pub mod config;
+pub mod delivery;
 pub mod discord;
 pub mod dispatcher;
+pub mod format;
+pub mod identity;
+pub mod language;
+pub mod locale;
 pub mod slack;
 pub mod telegram;
 pub mod traits;
@@ -0,0 +1,417 @@
+//! Locale and timezone-aware formatting for user-facing channel output.
+//!
+//! Settings are loaded in the same priority order as the rest of the channel
+//! config: `TANDEM_LOCALE` / `TANDEM_TIMEZONE` env vars override the `locale`
+//! block of `config.json`. Timestamps are rendered in the configured zone and
+//! short status strings come from a small built-in message catalog.
+//!
+//! Timezones accept `UTC`, `local` (the host zone, DST-aware) and fixed
+//! offsets such as `+05:30`, `UTC-8` or `GMT+1`. Named IANA zones are not
+//! resolved here, so [`LocaleSettings::validate`] rejects them (and any other
+//! unrecognised value) rather than letting output silently shift to UTC.
+
+use chrono::{DateTime, FixedOffset, Local, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Server-level locale and timezone preferences.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocaleSettings {
+    /// BCP-47-ish tag, e.g. `en`, `de-DE`, `zh-CN`.
+    #[serde(default = "default_locale_tag")]
+    pub locale: String,
+    /// `UTC`, `local`, or a fixed offset like `+02:00`.
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+}
+
+impl Default for LocaleSettings {
+    fn default() -> Self {
+        Self {
+            locale: default_locale_tag(),
+            timezone: default_timezone(),
+        }
+    }
+}
+
+fn default_locale_tag() -> String {
+    "en".to_string()
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+impl LocaleSettings {
+    /// Apply `TANDEM_LOCALE` / `TANDEM_TIMEZONE` on top of `self`.
+    pub fn with_env_overrides(mut self) -> Self {
+        if let Ok(locale) = std::env::var("TANDEM_LOCALE") {
+            if !locale.trim().is_empty() {
+                self.locale = locale.trim().to_string();
+            }
+        }
+        if let Ok(timezone) = std::env::var("TANDEM_TIMEZONE") {
+            if !timezone.trim().is_empty() {
+                self.timezone = timezone.trim().to_string();
+            }
+        }
+        self
+    }
+
+    pub fn from_env() -> Self {
+        Self::default().with_env_overrides()
+    }
+
+    pub fn language(&self) -> Locale {
+        Locale::parse(&self.locale)
+    }
+
+    /// Check that `timezone` is a zone this module can resolve.
+    pub fn validate(&self) -> Result<(), String> {
+        TimezoneSpec::validate(&self.timezone).map(|_| ())
+    }
+
+    /// The configured zone. Settings that failed [`Self::validate`] render
+    /// in UTC.
+    pub fn zone(&self) -> TimezoneSpec {
+        TimezoneSpec::parse(&self.timezone).unwrap_or(TimezoneSpec::Utc)
+    }
+
+    /// Look up a catalog string in the configured language.
+    pub fn text(&self, key: MessageKey) -> &'static str {
+        message(self.language(), key)
+    }
+
+    /// Render an epoch-millisecond timestamp for humans.
+    pub fn format_timestamp_ms(&self, ms: u64) -> String {
+        format_timestamp_ms(ms, self.language(), &self.zone())
+    }
+}
+
+/// Languages with a built-in message catalog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Locale {
+    En,
+    Es,
+    Fr,
+    De,
+    Ja,
+    ZhCn,
+}
+
+impl Locale {
+    pub const ALL: [Locale; 6] = [
+        Locale::En,
+        Locale::Es,
+        Locale::Fr,
+        Locale::De,
+        Locale::Ja,
+        Locale::ZhCn,
+    ];
+
+    /// Resolve a locale tag, falling back to English for unknown languages.
+    pub fn parse(raw: &str) -> Self {
+        let normalized = raw.trim().to_ascii_lowercase().replace('_', "-");
+        let language = normalized.split('-').next().unwrap_or("");
+        match language {
+            "es" => Locale::Es,
+            "fr" => Locale::Fr,
+            "de" => Locale::De,
+            "ja" => Locale::Ja,
+            "zh" => Locale::ZhCn,
+            _ => Locale::En,
+        }
+    }
+
+    pub fn tag(self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Es => "es",
+            Locale::Fr => "fr",
+            Locale::De => "de",
+            Locale::Ja => "ja",
+            Locale::ZhCn => "zh-CN",
+        }
+    }
+
+    fn datetime_pattern(self) -> &'static str {
+        match self {
+            Locale::En => "%b %-d, %Y %H:%M",
+            Locale::Es | Locale::Fr => "%d/%m/%Y %H:%M",
+            Locale::De => "%d.%m.%Y %H:%M",
+            Locale::Ja | Locale::ZhCn => "%Y-%m-%d %H:%M",
+        }
+    }
+}
+
+/// A resolved display timezone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimezoneSpec {
+    Utc,
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl TimezoneSpec {
+    /// Parse `UTC`, `local`, or a fixed offset. Returns `None` for anything
+    /// else (including IANA names, which need a tz database).
+    pub fn parse(raw: &str) -> Option<Self> {
+        let trimmed = raw.trim();
+        let lower = trimmed.to_ascii_lowercase();
+        match lower.as_str() {
+            "" | "utc" | "z" | "gmt" | "etc/utc" | "etc/gmt" => return Some(TimezoneSpec::Utc),
+            "local" => return Some(TimezoneSpec::Local),
+            _ => {}
+        }
+        let offset = lower
+            .strip_prefix("utc")
+            .or_else(|| lower.strip_prefix("gmt"))
+            .unwrap_or(&lower);
+        parse_fixed_offset(offset).map(TimezoneSpec::Fixed)
+    }
+
+    /// Like [`Self::parse`], but with an error naming the rejected zone.
+    pub fn validate(raw: &str) -> Result<Self, String> {
+        Self::parse(raw).ok_or_else(|| {
+            format!(
+                "unknown timezone `{}`: expected `UTC`, `local` or a fixed offset such as `+05:30`",
+                raw.trim()
+            )
+        })
+    }
+}
+
+fn parse_fixed_offset(raw: &str) -> Option<FixedOffset> {
+    let (sign, rest) = match raw.chars().next()? {
+        '+' => (1, &raw[1..]),
+        '-' => (-1, &raw[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((h, m)) => (h.parse::<i32>().ok()?, m.parse::<i32>().ok()?),
+        None if rest.len() == 4 => (rest[..2].parse().ok()?, rest[2..].parse().ok()?),
+        None => (rest.parse::<i32>().ok()?, 0),
+    };
+    if !(0..=14).contains(&hours) || !(0..60).contains(&minutes) {
+        return None;
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Format `ms` since the epoch with the locale's date pattern and a zone suffix.
+pub fn format_timestamp_ms(ms: u64, locale: Locale, zone: &TimezoneSpec) -> String {
+    let Some(utc) = DateTime::<Utc>::from_timestamp_millis(ms as i64) else {
+        return ms.to_string();
+    };
+    let pattern = locale.datetime_pattern();
+    match zone {
+        TimezoneSpec::Utc => format!("{} UTC", utc.format(pattern)),
+        TimezoneSpec::Local => {
+            let local = Local.from_utc_datetime(&utc.naive_utc());
+            format!("{} {}", local.format(pattern), local.format("%:z"))
+        }
+        TimezoneSpec::Fixed(offset) => {
+            let shifted = offset.from_utc_datetime(&utc.naive_utc());
+            format!("{} UTC{}", shifted.format(pattern), shifted.format("%:z"))
+        }
+    }
+}
+
+/// Keys for user-facing strings emitted by channel adapters and the Web UI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageKey {
+    CommandsTitle,
+    NoActiveSession,
+    ServerUnreachable,
+    UnexpectedResponse,
+    NoSessionsFound,
+    YourSessions,
+    LastUpdated,
+    NoActiveRun,
+    ErrorPrefix,
+}
+
+impl MessageKey {
+    pub const ALL: [MessageKey; 9] = [
+        MessageKey::CommandsTitle,
+        MessageKey::NoActiveSession,
+        MessageKey::ServerUnreachable,
+        MessageKey::UnexpectedResponse,
+        MessageKey::NoSessionsFound,
+        MessageKey::YourSessions,
+        MessageKey::LastUpdated,
+        MessageKey::NoActiveRun,
+        MessageKey::ErrorPrefix,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MessageKey::CommandsTitle => "commands_title",
+            MessageKey::NoActiveSession => "no_active_session",
+            MessageKey::ServerUnreachable => "server_unreachable",
+            MessageKey::UnexpectedResponse => "unexpected_response",
+            MessageKey::NoSessionsFound => "no_sessions_found",
+            MessageKey::YourSessions => "your_sessions",
+            MessageKey::LastUpdated => "last_updated",
+            MessageKey::NoActiveRun => "no_active_run",
+            MessageKey::ErrorPrefix => "error_prefix",
+        }
+    }
+}
+
+/// Catalog lookup. Every key has an entry for every [`Locale`].
+pub fn message(locale: Locale, key: MessageKey) -> &'static str {
+    use MessageKey::*;
+    match (locale, key) {
+        (Locale::En, CommandsTitle) => "Tandem Commands",
+        (Locale::En, NoActiveSession) => {
+            "No active session. Send a message to start one, or use /new."
+        }
+        (Locale::En, ServerUnreachable) => "Could not reach Tandem server.",
+        (Locale::En, UnexpectedResponse) => "Unexpected server response.",
+        (Locale::En, NoSessionsFound) => "No previous sessions found.",
+        (Locale::En, YourSessions) => "Your sessions:",
+        (Locale::En, LastUpdated) => "last updated",
+        (Locale::En, NoActiveRun) => "No active run.",
+        (Locale::En, ErrorPrefix) => "Error",
+
+        (Locale::Es, CommandsTitle) => "Comandos de Tandem",
+        (Locale::Es, NoActiveSession) => {
+            "No hay sesión activa. Envía un mensaje para iniciar una, o usa /new."
+        }
+        (Locale::Es, ServerUnreachable) => "No se pudo conectar con el servidor de Tandem.",
+        (Locale::Es, UnexpectedResponse) => "Respuesta inesperada del servidor.",
+        (Locale::Es, NoSessionsFound) => "No se encontraron sesiones anteriores.",
+        (Locale::Es, YourSessions) => "Tus sesiones:",
+        (Locale::Es, LastUpdated) => "última actualización",
+        (Locale::Es, NoActiveRun) => "No hay ninguna ejecución activa.",
+        (Locale::Es, ErrorPrefix) => "Error",
+
+        (Locale::Fr, CommandsTitle) => "Commandes Tandem",
+        (Locale::Fr, NoActiveSession) => {
+            "Aucune session active. Envoyez un message pour en démarrer une, ou utilisez /new."
+        }
+        (Locale::Fr, ServerUnreachable) => "Impossible de joindre le serveur Tandem.",
+        (Locale::Fr, UnexpectedResponse) => "Réponse inattendue du serveur.",
+        (Locale::Fr, NoSessionsFound) => "Aucune session précédente trouvée.",
+        (Locale::Fr, YourSessions) => "Vos sessions :",
+        (Locale::Fr, LastUpdated) => "dernière mise à jour",
+        (Locale::Fr, NoActiveRun) => "Aucune exécution en cours.",
+        (Locale::Fr, ErrorPrefix) => "Erreur",
+
+        (Locale::De, CommandsTitle) => "Tandem-Befehle",
+        (Locale::De, NoActiveSession) => {
+            "Keine aktive Sitzung. Sende eine Nachricht, um eine zu starten, oder nutze /new."
+        }
+        (Locale::De, ServerUnreachable) => "Tandem-Server nicht erreichbar.",
+        (Locale::De, UnexpectedResponse) => "Unerwartete Serverantwort.",
+        (Locale::De, NoSessionsFound) => "Keine früheren Sitzungen gefunden.",
+        (Locale::De, YourSessions) => "Deine Sitzungen:",
+        (Locale::De, LastUpdated) => "zuletzt aktualisiert",
+        (Locale::De, NoActiveRun) => "Kein aktiver Lauf.",
+        (Locale::De, ErrorPrefix) => "Fehler",
+
+        (Locale::Ja, CommandsTitle) => "Tandem コマンド",
+        (Locale::Ja, NoActiveSession) => {
+            "アクティブなセッションがありません。メッセージを送るか /new で開始してください。"
+        }
+        (Locale::Ja, ServerUnreachable) => "Tandem サーバーに接続できませんでした。",
+        (Locale::Ja, UnexpectedResponse) => "サーバーから予期しない応答がありました。",
+        (Locale::Ja, NoSessionsFound) => "以前のセッションは見つかりませんでした。",
+        (Locale::Ja, YourSessions) => "あなたのセッション:",
+        (Locale::Ja, LastUpdated) => "最終更新",
+        (Locale::Ja, NoActiveRun) => "実行中のランはありません。",
+        (Locale::Ja, ErrorPrefix) => "エラー",
+
+        (Locale::ZhCn, CommandsTitle) => "Tandem 命令",
+        (Locale::ZhCn, NoActiveSession) => "没有活动会话。发送消息即可开始，或使用 /new。",
+        (Locale::ZhCn, ServerUnreachable) => "无法连接到 Tandem 服务器。",
+        (Locale::ZhCn, UnexpectedResponse) => "服务器返回了意外的响应。",
+        (Locale::ZhCn, NoSessionsFound) => "未找到以前的会话。",
+        (Locale::ZhCn, YourSessions) => "你的会话：",
+        (Locale::ZhCn, LastUpdated) => "最后更新",
+        (Locale::ZhCn, NoActiveRun) => "没有正在进行的运行。",
+        (Locale::ZhCn, ErrorPrefix) => "错误",
+    }
+}
+
+/// Full catalog for one locale as `key -> text`, for clients such as the Web UI.
+pub fn catalog(locale: Locale) -> serde_json::Map<String, serde_json::Value> {
+    MessageKey::ALL
+        .iter()
+        .map(|key| {
+            (
+                key.as_str().to_string(),
+                serde_json::Value::String(message(locale, *key).to_string()),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_locale_tags() {
+        assert_eq!(Locale::parse("de-DE"), Locale::De);
+        assert_eq!(Locale::parse("zh_CN"), Locale::ZhCn);
+        assert_eq!(Locale::parse("pt-BR"), Locale::En);
+    }
+
+    #[test]
+    fn parse_timezone_specs() {
+        assert_eq!(TimezoneSpec::parse("UTC"), Some(TimezoneSpec::Utc));
+        assert_eq!(TimezoneSpec::parse("local"), Some(TimezoneSpec::Local));
+        assert_eq!(
+            TimezoneSpec::parse("UTC+05:30"),
+            Some(TimezoneSpec::Fixed(
+                FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap()
+            ))
+        );
+        assert_eq!(
+            TimezoneSpec::parse("-0800"),
+            Some(TimezoneSpec::Fixed(
+                FixedOffset::west_opt(8 * 3600).unwrap()
+            ))
+        );
+        assert_eq!(TimezoneSpec::parse("Europe/Berlin"), None);
+    }
+
+    #[test]
+    fn format_timestamp_respects_locale_and_offset() {
+        // 2024-03-01T12:34:00Z
+        let ms = 1_709_296_440_000;
+        assert_eq!(
+            format_timestamp_ms(ms, Locale::En, &TimezoneSpec::Utc),
+            "Mar 1, 2024 12:34 UTC"
+        );
+        let plus_two = TimezoneSpec::parse("+02:00").unwrap();
+        assert_eq!(
+            format_timestamp_ms(ms, Locale::De, &plus_two),
+            "01.03.2024 14:34 UTC+02:00"
+        );
+    }
+
+    #[test]
+    fn unknown_timezones_fail_validation() {
+        let settings = LocaleSettings {
+            locale: "fr".to_string(),
+            timezone: "Mars/Olympus".to_string(),
+        };
+        let err = settings.validate().unwrap_err();
+        assert!(err.contains("Mars/Olympus"));
+        assert!(TimezoneSpec::validate("Europe/Berlin").is_err());
+        assert!(LocaleSettings::default().validate().is_ok());
+        assert_eq!(settings.zone(), TimezoneSpec::Utc);
+        assert_eq!(settings.text(MessageKey::ErrorPrefix), "Erreur");
+    }
+
+    #[test]
+    fn catalog_covers_every_key() {
+        for locale in Locale::ALL {
+            assert_eq!(catalog(locale).len(), MessageKey::ALL.len());
+        }
+    }
+}
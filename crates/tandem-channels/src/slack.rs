@@ -10,16 +10,22 @@ use std::time::Duration;
 use tokio::sync::mpsc;
 use tracing::{info, warn};
 
+use crate::attachments::{caption_text, UPLOAD_TIMEOUT};
 use crate::config::{is_user_allowed, SlackConfig};
-use crate::traits::{Channel, ChannelMessage, SendMessage};
+use crate::traits::{Channel, ChannelMessage, HistoryMessage, OutboundFile, SendMessage};
 
 const SLACK_API: &str = "https://slack.com/api";
 const POLL_INTERVAL_SECS: u64 = 3;
+/// Upload cap; Slack accepts up to 1 GB but files are buffered in memory.
+const SLACK_MAX_UPLOAD_BYTES: u64 = 100 * 1024 * 1024;
+/// Largest history page requested from `conversations.history`.
+const SLACK_MAX_HISTORY_MESSAGES: usize = 100;
 
 pub struct SlackChannel {
     bot_token: String,
     channel_id: String,
     allowed_users: Vec<String>,
+    history_import: usize,
 }
 
 impl SlackChannel {
@@ -28,6 +34,7 @@ impl SlackChannel {
             bot_token: config.bot_token,
             channel_id: config.channel_id,
             allowed_users: config.allowed_users,
+            history_import: config.history_import,
         }
     }
 
@@ -55,6 +62,64 @@ impl SlackChannel {
             .and_then(|u| u.as_str())
             .map(String::from)
     }
+
+    /// Call a Web API method and return its body, failing on `"ok": false`.
+    async fn api_result(
+        &self,
+        method: &str,
+        request: reqwest::RequestBuilder,
+    ) -> anyhow::Result<serde_json::Value> {
+        let resp = request.bearer_auth(&self.bot_token).send().await?;
+        let status = resp.status();
+        let body_text = resp.text().await.unwrap_or_default();
+        if !status.is_success() {
+            anyhow::bail!("Slack {method} failed ({status}): {body_text}");
+        }
+        let parsed: serde_json::Value = serde_json::from_str(&body_text).unwrap_or_default();
+        if parsed.get("ok") != Some(&serde_json::Value::Bool(true)) {
+            let err = parsed
+                .get("error")
+                .and_then(|e| e.as_str())
+                .unwrap_or("unknown");
+            anyhow::bail!("Slack {method} error: {err}");
+        }
+        Ok(parsed)
+    }
+}
+
+/// Messages from a `conversations.history` response (newest first), oldest
+/// first and without empty ones or join/leave notices.
+fn history_messages(body: &serde_json::Value) -> Vec<HistoryMessage> {
+    let Some(messages) = body.get("messages").and_then(|m| m.as_array()) else {
+        return Vec::new();
+    };
+    messages
+        .iter()
+        .rev()
+        .filter(|m| {
+            !matches!(
+                m.get("subtype").and_then(|s| s.as_str()),
+                Some("channel_join" | "channel_leave")
+            )
+        })
+        .filter_map(|m| {
+            let text = m.get("text").and_then(|t| t.as_str()).map(str::trim)?;
+            if text.is_empty() {
+                return None;
+            }
+            let author = m
+                .get("user")
+                .or_else(|| m.get("username"))
+                .or_else(|| m.get("bot_id"))
+                .and_then(|u| u.as_str())
+                .unwrap_or("unknown");
+            Some(HistoryMessage {
+                author: author.to_string(),
+                text: text.to_string(),
+                timestamp: m.get("ts").and_then(|t| t.as_str()).map(String::from),
+            })
+        })
+        .collect()
 }
 
 #[async_trait]
@@ -64,10 +129,13 @@ impl Channel for SlackChannel {
     }
 
     async fn send(&self, message: &SendMessage) -> anyhow::Result<()> {
-        let body = serde_json::json!({
+        let mut body = serde_json::json!({
             "channel": message.recipient,
             "text": message.content,
         });
+        if let (Some(payload), Some(fields)) = (&message.payload, body.as_object_mut()) {
+            fields.extend(payload.clone());
+        }
 
         let resp = self
             .http_client()
@@ -97,6 +165,93 @@ impl Channel for SlackChannel {
         Ok(())
     }
 
+    fn max_upload_bytes(&self) -> u64 {
+        SLACK_MAX_UPLOAD_BYTES
+    }
+
+    /// External upload flow: reserve an upload URL, send the bytes there, then
+    /// share the file into the channel.
+    async fn send_file(&self, recipient: &str, file: &OutboundFile) -> anyhow::Result<()> {
+        let client = self.http_client();
+        let reserved = self
+            .api_result(
+                "files.getUploadURLExternal",
+                client
+                    .get(format!("{SLACK_API}/files.getUploadURLExternal"))
+                    .query(&[
+                        ("filename", file.file_name.clone()),
+                        ("length", file.bytes.len().to_string()),
+                    ]),
+            )
+            .await?;
+        let (Some(upload_url), Some(file_id)) = (
+            reserved.get("upload_url").and_then(|v| v.as_str()),
+            reserved.get("file_id").and_then(|v| v.as_str()),
+        ) else {
+            anyhow::bail!("Slack files.getUploadURLExternal returned no upload URL");
+        };
+        let resp = client
+            .post(upload_url)
+            .header(reqwest::header::CONTENT_TYPE, file.content_type.as_str())
+            .timeout(UPLOAD_TIMEOUT)
+            .body(file.bytes.clone())
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Slack file upload failed ({})", resp.status());
+        }
+        let mut complete = serde_json::json!({
+            "files": [{ "id": file_id, "title": file.file_name }],
+            "channel_id": recipient,
+        });
+        if let Some(caption) = caption_text(file) {
+            complete["initial_comment"] = serde_json::Value::String(caption);
+        }
+        self.api_result(
+            "files.completeUploadExternal",
+            client
+                .post(format!("{SLACK_API}/files.completeUploadExternal"))
+                .json(&complete),
+        )
+        .await?;
+        Ok(())
+    }
+
+    fn history_import_limit(&self) -> usize {
+        self.history_import.min(SLACK_MAX_HISTORY_MESSAGES)
+    }
+
+    /// Needs the `channels:history` (or `groups:history`) scope; Slack's
+    /// `missing_scope` / `not_in_channel` errors fail the import.
+    async fn fetch_history(
+        &self,
+        message: &ChannelMessage,
+        limit: usize,
+    ) -> anyhow::Result<Vec<HistoryMessage>> {
+        let prefix = format!("slack_{}_", message.reply_target);
+        let Some(ts) = message.id.strip_prefix(&prefix) else {
+            return Ok(Vec::new());
+        };
+        let limit = limit.min(SLACK_MAX_HISTORY_MESSAGES);
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let body = self
+            .api_result(
+                "conversations.history",
+                self.http_client()
+                    .get(format!("{SLACK_API}/conversations.history"))
+                    .query(&[
+                        ("channel", message.reply_target.clone()),
+                        ("latest", ts.to_string()),
+                        ("inclusive", "false".to_string()),
+                        ("limit", limit.to_string()),
+                    ]),
+            )
+            .await?;
+        Ok(history_messages(&body))
+    }
+
     async fn listen(&self, tx: mpsc::Sender<ChannelMessage>) -> anyhow::Result<()> {
         let bot_user_id = self.get_bot_user_id().await.unwrap_or_default();
         let mut last_ts = String::new();
@@ -187,6 +342,9 @@ impl Channel for SlackChannel {
                     channel: "slack".to_string(),
                     timestamp: chrono::Utc::now(),
                     attachment: None,
+                    user_id: None,
+                    reaction: None,
+                    locale: None,
                 };
 
                 if tx.send(channel_msg).await.is_err() {
@@ -216,6 +374,7 @@ mod tests {
             bot_token: "xoxb-fake".into(),
             channel_id: "C0FAKE".into(),
             allowed_users: vec![],
+            history_import: 0,
         }
     }
 
@@ -269,6 +428,23 @@ mod tests {
         assert!(!is_user_allowed("u111", &ch.allowed_users));
     }
 
+    #[test]
+    fn history_is_oldest_first_without_join_notices() {
+        let body = serde_json::json!({
+            "ok": true,
+            "messages": [
+                {"user": "U2", "text": "second", "ts": "1000.000002"},
+                {"user": "U3", "subtype": "channel_join", "text": "<@U3> has joined", "ts": "1000.000001"},
+                {"bot_id": "B1", "text": "first", "ts": "1000.000000"}
+            ]
+        });
+        let history = history_messages(&body);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].author, "B1");
+        assert_eq!(history[1].text, "second");
+        assert_eq!(history[1].timestamp.as_deref(), Some("1000.000002"));
+    }
+
     #[test]
     fn message_id_format() {
         let ts = "1234567890.123456";
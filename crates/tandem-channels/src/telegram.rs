@@ -15,11 +15,14 @@ use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use tracing::{debug, error, warn};
 
+use crate::attachments::{caption_text, MultipartForm, UPLOAD_TIMEOUT};
 use crate::config::{is_user_allowed, TelegramConfig};
-use crate::traits::{Channel, ChannelMessage, SendMessage};
+use crate::traits::{Channel, ChannelMessage, OutboundFile, Reaction, SendMessage};
 
 const MAX_MESSAGE_LEN: usize = 4096;
 const TELEGRAM_API: &str = "https://api.telegram.org/bot";
+/// Bot API upload limit for `sendDocument`.
+const MAX_UPLOAD_BYTES: u64 = 50 * 1024 * 1024;
 
 /// Split a long message into ≤4096-character chunks.
 pub fn split_message(text: &str) -> Vec<String> {
@@ -68,15 +71,29 @@ impl TelegramChannel {
     fn api_url(&self, method: &str) -> String {
         format!("{}{}/{}", TELEGRAM_API, self.bot_token, method)
     }
-}
-
-#[async_trait]
-impl Channel for TelegramChannel {
-    fn name(&self) -> &str {
-        "telegram"
-    }
 
-    async fn send(&self, message: &SendMessage) -> anyhow::Result<()> {
+    /// Post `message` with `sendMessage`, chunked to Telegram's limit, and
+    /// return the ids of the posted messages.
+    async fn post_messages(&self, message: &SendMessage) -> anyhow::Result<Vec<String>> {
+        let mut ids = Vec::new();
+        if let Some(payload) = &message.payload {
+            let mut body = serde_json::json!({ "chat_id": message.recipient });
+            if let Some(fields) = body.as_object_mut() {
+                fields.extend(payload.clone());
+            }
+            let resp = self
+                .client
+                .post(self.api_url("sendMessage"))
+                .json(&body)
+                .send()
+                .await?;
+            if !resp.status().is_success() {
+                let text = resp.text().await.unwrap_or_default();
+                anyhow::bail!("telegram sendMessage failed: {text}");
+            }
+            ids.extend(sent_message_id(resp).await);
+            return Ok(ids);
+        }
         for chunk in split_message(&message.content) {
             let body = serde_json::json!({
                 "chat_id": message.recipient,
@@ -92,7 +109,89 @@ impl Channel for TelegramChannel {
             if !resp.status().is_success() {
                 let text = resp.text().await.unwrap_or_default();
                 error!("telegram sendMessage failed: {text}");
+                continue;
             }
+            ids.extend(sent_message_id(resp).await);
+        }
+        Ok(ids)
+    }
+}
+
+/// `message_id` of the message in a successful `sendMessage` response.
+async fn sent_message_id(resp: reqwest::Response) -> Option<String> {
+    let body = resp.json::<Value>().await.ok()?;
+    body["result"]["message_id"]
+        .as_i64()
+        .map(|id| id.to_string())
+}
+
+/// Build a reaction event from a `message_reaction` update. Only newly added
+/// emoji reactions count; removals arrive with an empty `new_reaction`.
+fn reaction_message(update_id: i64, reaction: &Value) -> Option<ChannelMessage> {
+    let emoji = reaction["new_reaction"]
+        .as_array()?
+        .iter()
+        .find(|r| r["type"] == "emoji")?["emoji"]
+        .as_str()?;
+    let message_id = reaction["message_id"].as_i64()?;
+    let user = &reaction["user"];
+    let sender = user["username"]
+        .as_str()
+        .map(|u| format!("@{u}"))
+        .or_else(|| user["id"].as_i64().map(|id| id.to_string()))?;
+    Some(ChannelMessage {
+        id: format!("reaction_{update_id}"),
+        sender,
+        reply_target: reaction["chat"]["id"].as_i64().unwrap_or(0).to_string(),
+        content: String::new(),
+        channel: "telegram".to_string(),
+        timestamp: chrono::Utc::now(),
+        attachment: None,
+        user_id: None,
+        reaction: Some(Reaction {
+            emoji: emoji.to_string(),
+            message_id: message_id.to_string(),
+        }),
+        locale: None,
+    })
+}
+
+#[async_trait]
+impl Channel for TelegramChannel {
+    fn name(&self) -> &str {
+        "telegram"
+    }
+
+    async fn send(&self, message: &SendMessage) -> anyhow::Result<()> {
+        self.post_messages(message).await.map(|_| ())
+    }
+
+    async fn send_reply(&self, message: &SendMessage) -> anyhow::Result<Vec<String>> {
+        self.post_messages(message).await
+    }
+
+    fn max_upload_bytes(&self) -> u64 {
+        MAX_UPLOAD_BYTES
+    }
+
+    async fn send_file(&self, recipient: &str, file: &OutboundFile) -> anyhow::Result<()> {
+        let mut form = MultipartForm::new().text("chat_id", recipient);
+        if let Some(caption) = caption_text(file) {
+            form = form.text("caption", &caption);
+        }
+        let (content_type, body) = form.file("document", file).finish();
+        let resp = self
+            .client
+            .post(self.api_url("sendDocument"))
+            .timeout(UPLOAD_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body)
+            .send()
+            .await?;
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            anyhow::bail!("telegram sendDocument failed ({status}): {text}");
         }
         Ok(())
     }
@@ -106,7 +205,7 @@ impl Channel for TelegramChannel {
                 .query(&[
                     ("timeout", "25"),
                     ("offset", &offset.to_string()),
-                    ("allowed_updates", r#"["message"]"#),
+                    ("allowed_updates", r#"["message","message_reaction"]"#),
                 ])
                 .send()
                 .await;
@@ -155,6 +254,22 @@ impl Channel for TelegramChannel {
                 let update_id = update["update_id"].as_i64().unwrap_or(0);
                 offset = offset.max(update_id + 1);
 
+                if let Some(reaction) = update.get("message_reaction") {
+                    let Some(channel_msg) = reaction_message(update_id, reaction) else {
+                        continue;
+                    };
+                    let numeric_id = reaction["user"]["id"].as_i64().map(|id| id.to_string());
+                    let allowed = self.allowed_users.iter().any(|a| a == "*")
+                        || [Some(channel_msg.sender.as_str()), numeric_id.as_deref()]
+                            .iter()
+                            .flatten()
+                            .any(|candidate| is_user_allowed(candidate, &self.allowed_users));
+                    if allowed && tx.send(channel_msg).await.is_err() {
+                        return Ok(());
+                    }
+                    continue;
+                }
+
                 let msg = match update.get("message") {
                     Some(m) => m,
                     None => continue,
@@ -222,6 +337,9 @@ impl Channel for TelegramChannel {
                     channel: "telegram".to_string(),
                     timestamp: chrono::Utc::now(),
                     attachment: None,
+                    user_id: None,
+                    reaction: None,
+                    locale: msg["from"]["language_code"].as_str().map(str::to_string),
                 };
 
                 if tx.send(channel_msg).await.is_err() {
@@ -258,6 +376,24 @@ impl Channel for TelegramChannel {
 mod tests {
     use super::*;
 
+    #[test]
+    fn reaction_updates_use_first_emoji() {
+        let update = serde_json::json!({
+            "chat": {"id": 7},
+            "message_id": 3,
+            "user": {"id": 99, "username": "sam"},
+            "new_reaction": [{"type": "emoji", "emoji": "👎"}],
+        });
+        let msg = reaction_message(5, &update).expect("reaction");
+        assert_eq!(msg.sender, "@sam");
+        assert_eq!(msg.reply_target, "7");
+        let reaction = msg.reaction.as_ref().expect("reaction");
+        assert_eq!(reaction.emoji, "👎");
+        assert_eq!(reaction.message_id, "3");
+        let removed = serde_json::json!({"user": {"id": 99}, "new_reaction": []});
+        assert!(reaction_message(6, &removed).is_none());
+    }
+
     #[test]
     fn test_split_short_message() {
         let msg = "Hello, world!";
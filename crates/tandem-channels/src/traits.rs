@@ -21,6 +21,37 @@ pub struct ChannelMessage {
     pub timestamp: DateTime<Utc>,
     /// Optional raw attachment description (file name, URL, etc.)
     pub attachment: Option<String>,
+    /// Linked Tandem user, resolved by the dispatcher. Adapters leave this `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// Set for reaction events. `content` is empty for these; the dispatcher
+    /// records them as feedback on the reacted-to reply.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reaction: Option<Reaction>,
+    /// Sender's platform language setting, when the platform reports one
+    /// (e.g. Telegram `language_code`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<String>,
+}
+
+/// An emoji reaction to an earlier message.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Reaction {
+    pub emoji: String,
+    /// Platform id of the message that was reacted to, within the
+    /// conversation named by the event's `reply_target`.
+    pub message_id: String,
+}
+
+/// An earlier message from a conversation, fetched for context import.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HistoryMessage {
+    /// Display name or platform id of the author.
+    pub author: String,
+    pub text: String,
+    /// Platform timestamp as reported (ISO 8601 for Discord, `ts` for Slack).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
 }
 
 /// A message to send back to the external channel.
@@ -30,6 +61,20 @@ pub struct SendMessage {
     pub content: String,
     /// Destination (chat_id, channel_id, user_id, etc. — platform-specific).
     pub recipient: String,
+    /// Platform request fields from [`crate::format`] (Slack `blocks`, Discord
+    /// `embeds`, Telegram MarkdownV2 `text`). Adapters send `content` as plain
+    /// text when this is `None`.
+    pub payload: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// A file to upload to an external channel.
+#[derive(Debug, Clone)]
+pub struct OutboundFile {
+    pub file_name: String,
+    pub content_type: String,
+    pub bytes: Vec<u8>,
+    /// Short text shown with the file.
+    pub caption: Option<String>,
 }
 
 /// All external channel adapters implement this trait.
@@ -41,6 +86,13 @@ pub trait Channel: Send + Sync {
     /// Send a message to the given recipient.
     async fn send(&self, message: &SendMessage) -> anyhow::Result<()>;
 
+    /// Send a message and return the platform ids of the messages posted, so
+    /// reactions to them can be traced back. Adapters that do not deliver
+    /// reactions keep the default, which reports no ids.
+    async fn send_reply(&self, message: &SendMessage) -> anyhow::Result<Vec<String>> {
+        self.send(message).await.map(|()| Vec::new())
+    }
+
     /// Listen for incoming messages and forward them through `tx`.
     ///
     /// This method should run until the sender is dropped or an unrecoverable
@@ -64,6 +116,34 @@ pub trait Channel: Send + Sync {
         Ok(())
     }
 
+    /// Largest file [`Channel::send_file`] accepts, in bytes. `0` means the
+    /// adapter cannot upload files and callers should send a link instead.
+    fn max_upload_bytes(&self) -> u64 {
+        0
+    }
+
+    /// How many earlier messages to import as context when a conversation
+    /// is first seen in a session. `0` disables history import.
+    fn history_import_limit(&self) -> usize {
+        0
+    }
+
+    /// Up to `limit` messages posted before `message` in the same
+    /// conversation, oldest first. Fails when the bot may not read the
+    /// conversation's history.
+    async fn fetch_history(
+        &self,
+        _message: &ChannelMessage,
+        _limit: usize,
+    ) -> anyhow::Result<Vec<HistoryMessage>> {
+        Ok(Vec::new())
+    }
+
+    /// Upload a file to the recipient.
+    async fn send_file(&self, _recipient: &str, _file: &OutboundFile) -> anyhow::Result<()> {
+        anyhow::bail!("{} does not support file uploads", self.name())
+    }
+
     /// `true` if the platform supports in-place message editing for streaming
     /// partial responses. Used to enable draft-update mode in the dispatcher.
     fn supports_draft_updates(&self) -> bool {
@@ -26,6 +26,10 @@ pub struct AgentDefinition {
     pub tools: Option<Vec<String>>,
     #[serde(default)]
     pub skills: Option<Vec<String>>,
+    /// Generation preset used when neither the request nor the session
+    /// names one.
+    #[serde(default)]
+    pub preset: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -35,33 +39,40 @@ struct AgentFrontmatter {
     hidden: Option<bool>,
     tools: Option<Vec<String>>,
     skills: Option<Vec<String>>,
+    preset: Option<String>,
 }
 
 #[derive(Clone)]
 pub struct AgentRegistry {
     agents: Arc<RwLock<HashMap<String, AgentDefinition>>>,
     default_agent: String,
+    agents_dir: PathBuf,
 }
 
 impl AgentRegistry {
     pub async fn new(workspace_root: impl Into<PathBuf>) -> anyhow::Result<Self> {
-        let mut by_name = HashMap::new();
-        for agent in default_agents() {
-            by_name.insert(agent.name.clone(), agent);
-        }
-
         let root: PathBuf = workspace_root.into();
-        let custom = load_custom_agents(root.join(".tandem").join("agent")).await?;
-        for agent in custom {
-            by_name.insert(agent.name.clone(), agent);
-        }
-
+        let agents_dir = root.join(".tandem").join("agent");
+        let by_name = load_agents(&agents_dir).await?;
         Ok(Self {
             agents: Arc::new(RwLock::new(by_name)),
             default_agent: "build".to_string(),
+            agents_dir,
         })
     }
 
+    /// Directory custom agent profiles (`*.md`) are loaded from.
+    pub fn agents_dir(&self) -> &Path {
+        &self.agents_dir
+    }
+
+    /// Re-read custom agents after profiles were added, changed or removed.
+    pub async fn reload(&self) -> anyhow::Result<()> {
+        let by_name = load_agents(&self.agents_dir).await?;
+        *self.agents.write().await = by_name;
+        Ok(())
+    }
+
     pub async fn list(&self) -> Vec<AgentDefinition> {
         let mut agents = self
             .agents
@@ -88,6 +99,7 @@ impl AgentRegistry {
                 system_prompt: None,
                 tools: None,
                 skills: None,
+                preset: None,
             })
     }
 }
@@ -109,6 +121,7 @@ tool permissions are denied."
             ),
             tools: None,
             skills: None,
+            preset: None,
         },
         AgentDefinition {
             name: "plan".to_string(),
@@ -123,6 +136,7 @@ After receiving answers, continue planning and update todos."
             ),
             tools: None,
             skills: None,
+            preset: None,
         },
         AgentDefinition {
             name: "explore".to_string(),
@@ -137,6 +151,7 @@ Only ask for clarification after an initial workspace pass if results are insuff
             ),
             tools: None,
             skills: None,
+            preset: None,
         },
         AgentDefinition {
             name: "general".to_string(),
@@ -151,6 +166,7 @@ Avoid asking broad context questions before attempting local inspection."
             ),
             tools: None,
             skills: None,
+            preset: None,
         },
         AgentDefinition {
             name: "compaction".to_string(),
@@ -161,6 +177,7 @@ Avoid asking broad context questions before attempting local inspection."
             ),
             tools: Some(vec![]),
             skills: Some(vec![]),
+            preset: None,
         },
         AgentDefinition {
             name: "title".to_string(),
@@ -169,6 +186,7 @@ Avoid asking broad context questions before attempting local inspection."
             system_prompt: Some("You generate concise, descriptive session titles.".to_string()),
             tools: Some(vec![]),
             skills: Some(vec![]),
+            preset: None,
         },
         AgentDefinition {
             name: "summary".to_string(),
@@ -177,10 +195,22 @@ Avoid asking broad context questions before attempting local inspection."
             system_prompt: Some("You produce factual summaries of session content.".to_string()),
             tools: Some(vec![]),
             skills: Some(vec![]),
+            preset: None,
         },
     ]
 }
 
+async fn load_agents(agents_dir: &Path) -> anyhow::Result<HashMap<String, AgentDefinition>> {
+    let mut by_name = HashMap::new();
+    for agent in default_agents() {
+        by_name.insert(agent.name.clone(), agent);
+    }
+    for agent in load_custom_agents(agents_dir.to_path_buf()).await? {
+        by_name.insert(agent.name.clone(), agent);
+    }
+    Ok(by_name)
+}
+
 async fn load_custom_agents(dir: PathBuf) -> anyhow::Result<Vec<AgentDefinition>> {
     let mut out = Vec::new();
     let mut entries = match fs::read_dir(&dir).await {
@@ -226,5 +256,6 @@ fn parse_agent_markdown(raw: &str, path: &Path) -> Option<AgentDefinition> {
         system_prompt: if body.is_empty() { None } else { Some(body) },
         tools: parsed.tools,
         skills: parsed.skills,
+        preset: parsed.preset,
     })
 }
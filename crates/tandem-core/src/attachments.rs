@@ -0,0 +1,515 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use uuid::Uuid;
+
+use tandem_types::MessagePartInput;
+
+/// Default upper bound for a single uploaded attachment.
+pub const DEFAULT_MAX_ATTACHMENT_BYTES: u64 = 25 * 1024 * 1024;
+/// Text attachments are inlined into the prompt up to this many characters.
+const MAX_INLINE_ATTACHMENT_CHARS: usize = 50_000;
+/// Bytes read to fill [`MAX_INLINE_ATTACHMENT_CHARS`] (UTF-8 is at most four
+/// bytes per character).
+const MAX_INLINE_ATTACHMENT_BYTES: u64 = MAX_INLINE_ATTACHMENT_CHARS as u64 * 4;
+const SNIFF_BYTES: usize = 4 * 1024;
+const EICAR_MARKER: &[u8] = b"EICAR-STANDARD-ANTIVIRUS-TEST-FILE";
+const BLOCKED_EXTENSIONS: &[&str] = &[
+    "exe", "dll", "scr", "com", "bat", "cmd", "msi", "vbs", "jar", "ps1",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AttachmentRecord {
+    #[serde(rename = "artifactID")]
+    pub artifact_id: String,
+    #[serde(rename = "sessionID")]
+    pub session_id: String,
+    pub filename: String,
+    pub mime: String,
+    #[serde(rename = "sizeBytes")]
+    pub size_bytes: u64,
+    pub sha256: String,
+    #[serde(rename = "createdAtMs")]
+    pub created_at_ms: u64,
+    /// Object storage key once the blob has been moved off local disk.
+    #[serde(rename = "objectKey", default, skip_serializing_if = "Option::is_none")]
+    pub object_key: Option<String>,
+}
+
+impl AttachmentRecord {
+    /// Message part referencing this attachment.
+    pub fn message_part(&self) -> MessagePartInput {
+        MessagePartInput::Artifact {
+            artifact_id: self.artifact_id.clone(),
+            mime: self.mime.clone(),
+            filename: Some(self.filename.clone()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AttachmentError {
+    TooLarge { limit: u64 },
+    Rejected(String),
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for AttachmentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooLarge { limit } => write!(f, "attachment exceeds {limit} bytes"),
+            Self::Rejected(reason) => write!(f, "attachment rejected: {reason}"),
+            Self::Io(err) => write!(f, "attachment io error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for AttachmentError {}
+
+impl From<std::io::Error> for AttachmentError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Content checks run before an upload is accepted. `TANDEM_ATTACHMENT_SCAN_CMD`
+/// optionally names an external scanner (e.g. `clamdscan --no-summary`) that
+/// is invoked with the file path and must exit 0.
+async fn scan_attachment(path: &Path, filename: &str, head: &[u8]) -> Result<(), String> {
+    let ext = Path::new(filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    if BLOCKED_EXTENSIONS.contains(&ext.as_str()) {
+        return Err(format!("executable file type `.{ext}` is not allowed"));
+    }
+    if head.starts_with(b"MZ") || head.starts_with(b"\x7fELF") {
+        return Err("executable content is not allowed".to_string());
+    }
+    if head
+        .windows(EICAR_MARKER.len())
+        .any(|window| window == EICAR_MARKER)
+    {
+        return Err("malware signature detected (EICAR test file)".to_string());
+    }
+    let Ok(command) = std::env::var("TANDEM_ATTACHMENT_SCAN_CMD") else {
+        return Ok(());
+    };
+    let mut words = command.split_whitespace();
+    let Some(program) = words.next() else {
+        return Ok(());
+    };
+    let status = tokio::process::Command::new(program)
+        .args(words)
+        .arg(path)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .await
+        .map_err(|err| format!("virus scanner failed to start: {err}"))?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err("virus scanner flagged the file".to_string())
+    }
+}
+
+/// Whether attachments of this type are inlined into prompts (and so must
+/// stay readable from local disk).
+pub fn is_inline_text(mime: &str) -> bool {
+    let mime = mime.split(';').next().unwrap_or_default().trim();
+    mime.starts_with("text/")
+        || matches!(
+            mime,
+            "application/json"
+                | "application/xml"
+                | "application/yaml"
+                | "application/x-yaml"
+                | "application/javascript"
+                | "application/toml"
+                | "application/sql"
+        )
+}
+
+/// Session attachments stored under `<storage>/attachments/<artifact_id>/`.
+#[derive(Debug, Clone)]
+pub struct AttachmentStore {
+    root: PathBuf,
+}
+
+impl AttachmentStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn valid_id(artifact_id: &str) -> bool {
+        !artifact_id.is_empty()
+            && artifact_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    }
+
+    pub fn blob_path(&self, artifact_id: &str) -> Option<PathBuf> {
+        Self::valid_id(artifact_id).then(|| self.root.join(artifact_id).join("blob"))
+    }
+
+    /// Start a streamed upload; chunks are written to disk as they arrive.
+    pub async fn begin(
+        &self,
+        session_id: &str,
+        filename: &str,
+        mime: &str,
+        max_bytes: u64,
+    ) -> Result<AttachmentWriter, AttachmentError> {
+        fs::create_dir_all(&self.root).await?;
+        let artifact_id = format!("att-{}", Uuid::new_v4());
+        let tmp_path = self.root.join(format!(".{artifact_id}.upload"));
+        let file = fs::File::create(&tmp_path).await?;
+        let filename = Path::new(filename)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .filter(|n| !n.is_empty())
+            .unwrap_or_else(|| "attachment".to_string());
+        Ok(AttachmentWriter {
+            root: self.root.clone(),
+            file,
+            tmp: TempUpload(Some(tmp_path)),
+            hasher: Sha256::new(),
+            head: Vec::new(),
+            size: 0,
+            max_bytes,
+            record: AttachmentRecord {
+                artifact_id,
+                session_id: session_id.to_string(),
+                filename,
+                mime: if mime.trim().is_empty() {
+                    "application/octet-stream".to_string()
+                } else {
+                    mime.trim().to_string()
+                },
+                size_bytes: 0,
+                sha256: String::new(),
+                created_at_ms: chrono::Utc::now().timestamp_millis().max(0) as u64,
+                object_key: None,
+            },
+        })
+    }
+
+    pub async fn get(&self, artifact_id: &str) -> Option<AttachmentRecord> {
+        if !Self::valid_id(artifact_id) {
+            return None;
+        }
+        let raw = fs::read_to_string(self.root.join(artifact_id).join("meta.json"))
+            .await
+            .ok()?;
+        serde_json::from_str(&raw).ok()
+    }
+
+    /// Record that the blob now lives in object storage under `object_key`
+    /// and drop the local copy.
+    pub async fn set_object_key(
+        &self,
+        artifact_id: &str,
+        object_key: &str,
+    ) -> std::io::Result<AttachmentRecord> {
+        let mut record = self.get(artifact_id).await.ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::NotFound, "attachment not found")
+        })?;
+        record.object_key = Some(object_key.to_string());
+        let dir = self.root.join(artifact_id);
+        let meta = serde_json::to_vec_pretty(&record).map_err(std::io::Error::other)?;
+        fs::write(dir.join("meta.json"), meta).await?;
+        if let Err(err) = fs::remove_file(dir.join("blob")).await {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                return Err(err);
+            }
+        }
+        Ok(record)
+    }
+
+    pub async fn delete(&self, artifact_id: &str) -> bool {
+        Self::valid_id(artifact_id)
+            && fs::remove_dir_all(self.root.join(artifact_id))
+                .await
+                .is_ok()
+    }
+
+    /// Prompt text for an attachment of `session_id`: text content is inlined
+    /// (bounded), anything else is described so the model knows it exists.
+    /// Another session's attachment is reported as unavailable.
+    pub async fn prompt_text(&self, session_id: &str, artifact_id: &str) -> String {
+        let Some(record) = self
+            .get(artifact_id)
+            .await
+            .filter(|record| record.session_id == session_id)
+        else {
+            return format!("[attachment id={artifact_id} unavailable]");
+        };
+        let header = format!(
+            "[attachment name={} mime={} size={} id={}]",
+            record.filename, record.mime, record.size_bytes, record.artifact_id
+        );
+        if !is_inline_text(&record.mime) {
+            return format!("{header}\n(binary content not included)");
+        }
+        let Some(path) = self.blob_path(artifact_id) else {
+            return header;
+        };
+        match read_prefix(&path, MAX_INLINE_ATTACHMENT_BYTES).await {
+            Ok((bytes, size)) => {
+                let text = String::from_utf8_lossy(&bytes);
+                let mut body = text
+                    .chars()
+                    .take(MAX_INLINE_ATTACHMENT_CHARS)
+                    .collect::<String>();
+                if size > bytes.len() as u64 || text.chars().count() > MAX_INLINE_ATTACHMENT_CHARS {
+                    body.push_str("\n…[truncated]");
+                }
+                format!("{header}\n```\n{body}\n```")
+            }
+            Err(_) => format!("{header}\n(content unavailable)"),
+        }
+    }
+}
+
+/// Up to `limit` bytes from the start of `path`, and the file's full size.
+async fn read_prefix(path: &Path, limit: u64) -> std::io::Result<(Vec<u8>, u64)> {
+    let file = fs::File::open(path).await?;
+    let size = file.metadata().await?.len();
+    let mut bytes = Vec::with_capacity(size.min(limit) as usize);
+    file.take(limit).read_to_end(&mut bytes).await?;
+    Ok((bytes, size))
+}
+
+/// Removes an unfinished upload's temporary file when dropped, so uploads
+/// abandoned on any error path (or a dropped request) do not leak.
+struct TempUpload(Option<PathBuf>);
+
+impl TempUpload {
+    fn path(&self) -> &Path {
+        self.0.as_deref().unwrap_or(Path::new(""))
+    }
+
+    /// The file was moved into place; leave it alone.
+    fn keep(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for TempUpload {
+    fn drop(&mut self) {
+        if let Some(path) = self.0.take() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Text sent to the provider for a user message part of `session_id`.
+pub async fn render_message_part_input(
+    part: &MessagePartInput,
+    session_id: &str,
+    attachments: &AttachmentStore,
+) -> String {
+    match part {
+        MessagePartInput::Text { text } => text.clone(),
+        MessagePartInput::File {
+            mime,
+            filename,
+            url,
+        } => format!(
+            "[file mime={} name={} url={}]",
+            mime,
+            filename.clone().unwrap_or_else(|| "unknown".to_string()),
+            url
+        ),
+        MessagePartInput::Artifact { artifact_id, .. } => {
+            attachments.prompt_text(session_id, artifact_id).await
+        }
+    }
+}
+
+/// Provider-facing text for a full set of input parts.
+pub async fn render_message_parts(
+    parts: &[MessagePartInput],
+    session_id: &str,
+    attachments: &AttachmentStore,
+) -> String {
+    let mut rendered = Vec::with_capacity(parts.len());
+    for part in parts {
+        rendered.push(render_message_part_input(part, session_id, attachments).await);
+    }
+    rendered.join("\n")
+}
+
+pub struct AttachmentWriter {
+    root: PathBuf,
+    // Declared before `tmp` so the handle is closed before the file goes.
+    file: fs::File,
+    tmp: TempUpload,
+    hasher: Sha256,
+    head: Vec<u8>,
+    size: u64,
+    max_bytes: u64,
+    record: AttachmentRecord,
+}
+
+impl AttachmentWriter {
+    pub fn artifact_id(&self) -> &str {
+        &self.record.artifact_id
+    }
+
+    pub async fn write_chunk(&mut self, chunk: &[u8]) -> Result<(), AttachmentError> {
+        self.size += chunk.len() as u64;
+        if self.size > self.max_bytes {
+            return Err(AttachmentError::TooLarge {
+                limit: self.max_bytes,
+            });
+        }
+        if self.head.len() < SNIFF_BYTES {
+            let take = (SNIFF_BYTES - self.head.len()).min(chunk.len());
+            self.head.extend_from_slice(&chunk[..take]);
+        }
+        self.hasher.update(chunk);
+        self.file.write_all(chunk).await?;
+        Ok(())
+    }
+
+    /// Scan and commit the upload. Nothing is left behind on failure.
+    pub async fn finish(mut self) -> Result<AttachmentRecord, AttachmentError> {
+        let result = self.commit().await;
+        if result.is_err() {
+            let _ = fs::remove_dir_all(self.root.join(&self.record.artifact_id)).await;
+        }
+        result
+    }
+
+    async fn commit(&mut self) -> Result<AttachmentRecord, AttachmentError> {
+        self.file.flush().await?;
+        self.file.sync_all().await?;
+        scan_attachment(self.tmp.path(), &self.record.filename, &self.head)
+            .await
+            .map_err(AttachmentError::Rejected)?;
+        let dir = self.root.join(&self.record.artifact_id);
+        fs::create_dir_all(&dir).await?;
+        fs::rename(self.tmp.path(), dir.join("blob")).await?;
+        self.tmp.keep();
+        self.record.size_bytes = self.size;
+        self.record.sha256 = format!("{:x}", self.hasher.clone().finalize());
+        let meta = serde_json::to_vec_pretty(&self.record).map_err(std::io::Error::other)?;
+        fs::write(dir.join("meta.json"), meta).await?;
+        Ok(self.record.clone())
+    }
+
+    /// Discard the upload; dropping the writer does the same.
+    pub async fn abort(self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> (tempfile::TempDir, AttachmentStore) {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let store = AttachmentStore::new(dir.path().join("attachments"));
+        (dir, store)
+    }
+
+    #[tokio::test]
+    async fn streamed_text_upload_is_inlined_for_providers() {
+        let (_dir, store) = store();
+        let mut writer = store
+            .begin("s1", "../notes.md", "text/markdown", 1024)
+            .await
+            .expect("begin");
+        writer.write_chunk(b"# Notes\n").await.expect("chunk");
+        writer.write_chunk(b"ship it\n").await.expect("chunk");
+        let record = writer.finish().await.expect("finish");
+        assert_eq!(record.filename, "notes.md");
+        assert_eq!(record.size_bytes, 16);
+        assert_eq!(store.get(&record.artifact_id).await, Some(record.clone()));
+
+        let text = render_message_part_input(&record.message_part(), "s1", &store).await;
+        assert!(text.starts_with("[attachment name=notes.md mime=text/markdown"));
+        assert!(text.contains("ship it"));
+
+        let foreign = render_message_part_input(&record.message_part(), "s2", &store).await;
+        assert_eq!(
+            foreign,
+            format!("[attachment id={} unavailable]", record.artifact_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn large_text_is_read_only_up_to_the_inline_limit() {
+        let (_dir, store) = store();
+        let mut writer = store
+            .begin("s1", "big.txt", "text/plain", u64::MAX)
+            .await
+            .expect("begin");
+        let chunk = vec![b'a'; 64 * 1024];
+        for _ in 0..8 {
+            writer.write_chunk(&chunk).await.expect("chunk");
+        }
+        let record = writer.finish().await.expect("finish");
+        let text = store.prompt_text("s1", &record.artifact_id).await;
+        assert!(text.ends_with("…[truncated]\n```"));
+        assert!(text.len() < MAX_INLINE_ATTACHMENT_CHARS + 200);
+    }
+
+    #[tokio::test]
+    async fn abandoned_uploads_leave_no_temp_files() {
+        let (_dir, store) = store();
+        let mut writer = store
+            .begin("s1", "notes.txt", "text/plain", 1024)
+            .await
+            .expect("begin");
+        writer.write_chunk(b"half an upl").await.expect("chunk");
+        drop(writer);
+        let mut writer = store
+            .begin("s1", "setup.exe", "application/octet-stream", 1024)
+            .await
+            .expect("begin");
+        writer.write_chunk(b"MZ").await.expect("chunk");
+        assert!(writer.finish().await.is_err());
+        let mut entries = fs::read_dir(&store.root).await.expect("read dir");
+        assert!(entries.next_entry().await.expect("entry").is_none());
+    }
+
+    #[tokio::test]
+    async fn oversized_and_flagged_uploads_are_rejected() {
+        let (_dir, store) = store();
+        let mut writer = store
+            .begin("s1", "big.txt", "text/plain", 4)
+            .await
+            .expect("begin");
+        assert!(matches!(
+            writer.write_chunk(b"12345").await,
+            Err(AttachmentError::TooLarge { limit: 4 })
+        ));
+        writer.abort().await;
+
+        let mut writer = store
+            .begin("s1", "eicar.txt", "text/plain", 1024)
+            .await
+            .expect("begin");
+        writer
+            .write_chunk(b"X5O!P%@AP[4\\PZX54(P^)7CC)7}$EICAR-STANDARD-ANTIVIRUS-TEST-FILE!$H+H*")
+            .await
+            .expect("chunk");
+        assert!(matches!(
+            writer.finish().await,
+            Err(AttachmentError::Rejected(_))
+        ));
+
+        let writer = store
+            .begin("s1", "setup.exe", "application/octet-stream", 1024)
+            .await
+            .expect("begin");
+        assert!(writer.finish().await.is_err());
+        assert!(store.get("../etc").await.is_none());
+    }
+}
@@ -0,0 +1,293 @@
+//! Development-only fault injection for exercising retry, supervision and
+//! rollback paths.
+//!
+//! Chaos is off unless enabled via `TANDEM_CHAOS` (e.g.
+//! `provider_stream=0.2,tool_timeout=0.1,tool_timeout_ms=5000,persistence=0.05,event_lag_ms=250,seed=7`)
+//! or the server's `/global/chaos` endpoint. Release builds ignore both unless
+//! `TANDEM_ENABLE_CHAOS=1` is set.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+const DEFAULT_TOOL_TIMEOUT_MS: u64 = 30_000;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ChaosConfig {
+    pub enabled: bool,
+    /// Probability that a provider stream chunk is replaced by an error.
+    pub provider_stream_failure_rate: f64,
+    /// Probability that a tool call stalls and then fails as timed out.
+    pub tool_timeout_rate: f64,
+    /// How long a timed-out tool call stalls. Defaults to 30s.
+    pub tool_timeout_ms: u64,
+    /// Probability that a storage flush fails before writing.
+    pub persistence_failure_rate: f64,
+    /// Probability that an event is delivered late, and by how much. Every
+    /// event lags by default once `event_lag_ms` is non-zero.
+    pub event_lag_rate: f64,
+    pub event_lag_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            provider_stream_failure_rate: 0.0,
+            tool_timeout_rate: 0.0,
+            tool_timeout_ms: DEFAULT_TOOL_TIMEOUT_MS,
+            persistence_failure_rate: 0.0,
+            event_lag_rate: 1.0,
+            event_lag_ms: 0,
+            seed: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChaosFault {
+    ProviderStream,
+    ToolTimeout,
+    Persistence,
+    EventLag,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChaosStats {
+    pub provider_stream_failures: u64,
+    pub tool_timeouts: u64,
+    pub persistence_failures: u64,
+    pub lagged_events: u64,
+}
+
+/// Whether this build may inject faults at all.
+pub fn chaos_allowed() -> bool {
+    cfg!(debug_assertions)
+        || std::env::var("TANDEM_ENABLE_CHAOS")
+            .map(|v| matches!(v.trim(), "1" | "true" | "yes"))
+            .unwrap_or(false)
+}
+
+impl ChaosConfig {
+    /// Parse `key=value` pairs from `TANDEM_CHAOS`.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut config = Self {
+            enabled: true,
+            ..Self::default()
+        };
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got `{pair}`"))?;
+            let value = value.trim();
+            let rate = || {
+                value
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|r| (0.0..=1.0).contains(r))
+                    .ok_or_else(|| format!("`{key}` must be a probability between 0 and 1"))
+            };
+            let millis = || {
+                value
+                    .parse::<u64>()
+                    .map_err(|_| format!("`{key}` must be a number of milliseconds"))
+            };
+            match key.trim() {
+                "provider_stream" => config.provider_stream_failure_rate = rate()?,
+                "tool_timeout" => config.tool_timeout_rate = rate()?,
+                "tool_timeout_ms" => config.tool_timeout_ms = millis()?,
+                "persistence" => config.persistence_failure_rate = rate()?,
+                "event_lag" => config.event_lag_rate = rate()?,
+                "event_lag_ms" => config.event_lag_ms = millis()?,
+                "seed" => config.seed = Some(millis()?),
+                other => return Err(format!("unknown chaos setting `{other}`")),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, rate) in [
+            (
+                "provider_stream_failure_rate",
+                self.provider_stream_failure_rate,
+            ),
+            ("tool_timeout_rate", self.tool_timeout_rate),
+            ("persistence_failure_rate", self.persistence_failure_rate),
+            ("event_lag_rate", self.event_lag_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(format!("`{name}` must be between 0 and 1"));
+            }
+        }
+        Ok(())
+    }
+
+    fn from_env() -> Self {
+        let Ok(spec) = std::env::var("TANDEM_CHAOS") else {
+            return Self::default();
+        };
+        if !chaos_allowed() {
+            tracing::warn!("TANDEM_CHAOS ignored: set TANDEM_ENABLE_CHAOS=1 in release builds");
+            return Self::default();
+        }
+        match Self::parse(&spec) {
+            Ok(config) => {
+                tracing::warn!("chaos fault injection enabled: {config:?}");
+                config
+            }
+            Err(err) => {
+                tracing::warn!("invalid TANDEM_CHAOS: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    fn rate(&self, fault: ChaosFault) -> f64 {
+        match fault {
+            ChaosFault::ProviderStream => self.provider_stream_failure_rate,
+            ChaosFault::ToolTimeout => self.tool_timeout_rate,
+            ChaosFault::Persistence => self.persistence_failure_rate,
+            ChaosFault::EventLag if self.event_lag_ms > 0 => self.event_lag_rate,
+            ChaosFault::EventLag => 0.0,
+        }
+    }
+}
+
+struct ChaosState {
+    config: RwLock<ChaosConfig>,
+    rng: AtomicU64,
+    counters: [AtomicU64; 4],
+}
+
+fn state() -> &'static ChaosState {
+    static STATE: OnceLock<ChaosState> = OnceLock::new();
+    STATE.get_or_init(|| {
+        let config = ChaosConfig::from_env();
+        let seed = config.seed.unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(1)
+        });
+        ChaosState {
+            config: RwLock::new(config),
+            rng: AtomicU64::new(seed | 1),
+            counters: Default::default(),
+        }
+    })
+}
+
+pub fn chaos_config() -> ChaosConfig {
+    state().config.read().map(|c| c.clone()).unwrap_or_default()
+}
+
+pub fn set_chaos_config(config: ChaosConfig) {
+    let state = state();
+    if let Some(seed) = config.seed {
+        state.rng.store(seed | 1, Ordering::Relaxed);
+    }
+    if let Ok(mut current) = state.config.write() {
+        *current = config;
+    }
+}
+
+pub fn chaos_stats() -> ChaosStats {
+    let c = &state().counters;
+    ChaosStats {
+        provider_stream_failures: c[0].load(Ordering::Relaxed),
+        tool_timeouts: c[1].load(Ordering::Relaxed),
+        persistence_failures: c[2].load(Ordering::Relaxed),
+        lagged_events: c[3].load(Ordering::Relaxed),
+    }
+}
+
+/// xorshift64* step; good enough for fault sampling and reproducible with a seed.
+fn next_unit(state: &ChaosState) -> f64 {
+    let mut x = state.rng.load(Ordering::Relaxed);
+    x ^= x >> 12;
+    x ^= x << 25;
+    x ^= x >> 27;
+    state.rng.store(x, Ordering::Relaxed);
+    (x.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Roll for `fault`; counts the injection when it fires.
+pub fn chaos_inject(fault: ChaosFault) -> bool {
+    let state = state();
+    let rate = match state.config.read() {
+        Ok(config) if config.enabled => config.rate(fault),
+        _ => return false,
+    };
+    if rate <= 0.0 || next_unit(state) >= rate {
+        return false;
+    }
+    let idx = match fault {
+        ChaosFault::ProviderStream => 0,
+        ChaosFault::ToolTimeout => 1,
+        ChaosFault::Persistence => 2,
+        ChaosFault::EventLag => 3,
+    };
+    state.counters[idx].fetch_add(1, Ordering::Relaxed);
+    true
+}
+
+pub fn chaos_tool_timeout() -> Duration {
+    Duration::from_millis(chaos_config().tool_timeout_ms)
+}
+
+pub fn chaos_event_lag() -> Duration {
+    Duration::from_millis(chaos_config().event_lag_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_spec_and_rejects_bad_values() {
+        let config =
+            ChaosConfig::parse("provider_stream=0.5, tool_timeout_ms=100,event_lag_ms=20,seed=3")
+                .expect("parse");
+        assert!(config.enabled);
+        assert_eq!(config.provider_stream_failure_rate, 0.5);
+        assert_eq!(config.tool_timeout_ms, 100);
+        assert_eq!(config.rate(ChaosFault::EventLag), 1.0);
+        assert_eq!(config.seed, Some(3));
+        assert!(ChaosConfig::parse("persistence=2").is_err());
+        assert!(ChaosConfig::parse("gremlins=1").is_err());
+        let invalid = ChaosConfig {
+            tool_timeout_rate: -0.5,
+            ..config
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn endpoint_and_env_configs_share_defaults() {
+        let from_json: ChaosConfig =
+            serde_json::from_value(serde_json::json!({ "enabled": true, "event_lag_ms": 50 }))
+                .expect("json");
+        let from_env = ChaosConfig::parse("event_lag_ms=50").expect("parse");
+        assert_eq!(from_json, from_env);
+        assert_eq!(from_json.tool_timeout_ms, 30_000);
+        assert_eq!(from_json.rate(ChaosFault::EventLag), 1.0);
+        assert_eq!(ChaosConfig::default().rate(ChaosFault::EventLag), 0.0);
+    }
+
+    #[test]
+    fn sampling_is_uniform_enough() {
+        let state = ChaosState {
+            config: RwLock::new(ChaosConfig::default()),
+            rng: AtomicU64::new(42 | 1),
+            counters: Default::default(),
+        };
+        let hits = (0..10_000).filter(|_| next_unit(&state) < 0.25).count();
+        assert!((2_000..3_000).contains(&hits), "hits={hits}");
+    }
+}
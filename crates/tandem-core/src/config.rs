@@ -12,6 +12,8 @@ pub struct ProviderConfig {
     pub api_key: Option<String>,
     pub url: Option<String>,
     pub default_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_idle_connections: Option<usize>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -19,6 +21,10 @@ pub struct AppConfig {
     #[serde(default)]
     pub providers: HashMap<String, ProviderConfig>,
     pub default_provider: Option<String>,
+    #[serde(default)]
+    pub connection_pool: tandem_providers::ConnectionPoolConfig,
+    #[serde(default)]
+    pub generation_presets: HashMap<String, tandem_providers::GenerationPreset>,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -674,6 +680,7 @@ impl From<ProviderConfig> for tandem_providers::ProviderConfig {
             api_key: value.api_key,
             url: value.url,
             default_model: value.default_model,
+            max_idle_connections: value.max_idle_connections,
         }
     }
 }
@@ -687,6 +694,8 @@ impl From<AppConfig> for tandem_providers::AppConfig {
                 .map(|(k, v)| (k, v.into()))
                 .collect(),
             default_provider: value.default_provider,
+            connection_pool: value.connection_pool,
+            generation_presets: value.generation_presets,
         }
     }
 }
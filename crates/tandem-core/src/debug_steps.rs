@@ -0,0 +1,287 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::{oneshot, RwLock};
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use tandem_types::EngineEvent;
+
+use crate::event_bus::EventBus;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DebugStepKind {
+    ProviderCall,
+    ToolExecution,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DebugCommand {
+    /// Run the paused step as planned.
+    Continue,
+    /// Do not run the step; the loop carries on without it.
+    Skip,
+    /// Cancel the whole run.
+    Abort,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DebugPause {
+    #[serde(rename = "pauseID")]
+    pub pause_id: String,
+    #[serde(rename = "sessionID")]
+    pub session_id: String,
+    pub kind: DebugStepKind,
+    pub payload: Value,
+    #[serde(rename = "createdAtMs")]
+    pub created_at_ms: u64,
+}
+
+struct PendingPause {
+    pause: DebugPause,
+    reply: oneshot::Sender<DebugCommand>,
+}
+
+/// Step-by-step debugging for sessions: when enabled, the engine loop pauses
+/// before each provider call and tool execution until a command arrives.
+#[derive(Clone)]
+pub struct DebugStepController {
+    event_bus: EventBus,
+    sessions: Arc<RwLock<HashSet<String>>>,
+    /// Paused steps by pause id; a session may have several when tool calls
+    /// run in parallel.
+    pending: Arc<RwLock<HashMap<String, PendingPause>>>,
+}
+
+impl DebugStepController {
+    pub fn new(event_bus: EventBus) -> Self {
+        Self {
+            event_bus,
+            sessions: Arc::new(RwLock::new(HashSet::new())),
+            pending: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub async fn enable(&self, session_id: &str) {
+        self.sessions.write().await.insert(session_id.to_string());
+    }
+
+    /// Turn debugging off and let every paused step continue.
+    pub async fn disable(&self, session_id: &str) {
+        self.sessions.write().await.remove(session_id);
+        while self
+            .resolve(session_id, None, DebugCommand::Continue)
+            .await
+            .is_some()
+        {}
+    }
+
+    pub async fn is_enabled(&self, session_id: &str) -> bool {
+        self.sessions.read().await.contains(session_id)
+    }
+
+    /// The session's paused steps, oldest first.
+    pub async fn pending(&self, session_id: &str) -> Vec<DebugPause> {
+        let mut pauses = self
+            .pending
+            .read()
+            .await
+            .values()
+            .filter(|p| p.pause.session_id == session_id)
+            .map(|p| p.pause.clone())
+            .collect::<Vec<_>>();
+        pauses.sort_by_key(|p| p.created_at_ms);
+        pauses
+    }
+
+    /// Deliver `command` to the session's paused step `pause_id`, or to its
+    /// oldest paused step when `pause_id` is unset. Returns the resolved
+    /// pause, if any.
+    pub async fn resolve(
+        &self,
+        session_id: &str,
+        pause_id: Option<&str>,
+        command: DebugCommand,
+    ) -> Option<DebugPause> {
+        let pending = {
+            let mut guard = self.pending.write().await;
+            let key = match pause_id {
+                Some(id) => guard
+                    .get(id)
+                    .filter(|p| p.pause.session_id == session_id)
+                    .map(|_| id.to_string())?,
+                None => guard
+                    .values()
+                    .filter(|p| p.pause.session_id == session_id)
+                    .min_by_key(|p| p.pause.created_at_ms)
+                    .map(|p| p.pause.pause_id.clone())?,
+            };
+            guard.remove(&key)?
+        };
+        let _ = pending.reply.send(command);
+        self.event_bus.publish(EngineEvent::new(
+            "debug.step.resumed",
+            json!({
+                "sessionID": session_id,
+                "pauseID": pending.pause.pause_id,
+                "kind": pending.pause.kind,
+                "command": command,
+            }),
+        ));
+        Some(pending.pause)
+    }
+
+    /// Pause before a step if debugging is enabled for the session. Returns
+    /// immediately with `Continue` otherwise; cancellation yields `Abort`.
+    pub async fn checkpoint(
+        &self,
+        session_id: &str,
+        kind: DebugStepKind,
+        payload: Value,
+        cancel: &CancellationToken,
+    ) -> DebugCommand {
+        if !self.is_enabled(session_id).await {
+            return DebugCommand::Continue;
+        }
+        let (tx, rx) = oneshot::channel();
+        let pause = DebugPause {
+            pause_id: format!("pause-{}", Uuid::new_v4()),
+            session_id: session_id.to_string(),
+            kind,
+            payload,
+            created_at_ms: chrono::Utc::now().timestamp_millis().max(0) as u64,
+        };
+        self.pending.write().await.insert(
+            pause.pause_id.clone(),
+            PendingPause {
+                pause: pause.clone(),
+                reply: tx,
+            },
+        );
+        self.event_bus
+            .publish(EngineEvent::new("debug.step.paused", json!(pause)));
+        tokio::select! {
+            _ = cancel.cancelled() => {
+                self.pending.write().await.remove(&pause.pause_id);
+                DebugCommand::Abort
+            }
+            command = rx => command.unwrap_or(DebugCommand::Continue),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn checkpoint_is_noop_when_disabled() {
+        let controller = DebugStepController::new(EventBus::new());
+        let cancel = CancellationToken::new();
+        let command = controller
+            .checkpoint("s1", DebugStepKind::ProviderCall, json!({}), &cancel)
+            .await;
+        assert_eq!(command, DebugCommand::Continue);
+    }
+
+    #[tokio::test]
+    async fn checkpoint_waits_for_command() {
+        let controller = DebugStepController::new(EventBus::new());
+        controller.enable("s1").await;
+        let waiter = controller.clone();
+        let handle = tokio::spawn(async move {
+            let cancel = CancellationToken::new();
+            waiter
+                .checkpoint(
+                    "s1",
+                    DebugStepKind::ToolExecution,
+                    json!({"tool": "read"}),
+                    &cancel,
+                )
+                .await
+        });
+        let pause = loop {
+            if let Some(pause) = controller.pending("s1").await.pop() {
+                break pause;
+            }
+            tokio::task::yield_now().await;
+        };
+        assert_eq!(pause.kind, DebugStepKind::ToolExecution);
+        assert!(controller
+            .resolve("s1", Some("other"), DebugCommand::Skip)
+            .await
+            .is_none());
+        assert!(controller
+            .resolve("s1", Some(&pause.pause_id), DebugCommand::Skip)
+            .await
+            .is_some());
+        assert_eq!(handle.await.expect("join"), DebugCommand::Skip);
+    }
+
+    #[tokio::test]
+    async fn cancellation_aborts_pause() {
+        let controller = DebugStepController::new(EventBus::new());
+        controller.enable("s1").await;
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let command = controller
+            .checkpoint("s1", DebugStepKind::ProviderCall, json!({}), &cancel)
+            .await;
+        assert_eq!(command, DebugCommand::Abort);
+        assert!(controller.pending("s1").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn parallel_pauses_are_resolved_independently() {
+        let controller = DebugStepController::new(EventBus::new());
+        controller.enable("s1").await;
+        let spawn_pause = |tool: &'static str| {
+            let waiter = controller.clone();
+            tokio::spawn(async move {
+                let cancel = CancellationToken::new();
+                waiter
+                    .checkpoint(
+                        "s1",
+                        DebugStepKind::ToolExecution,
+                        json!({ "tool": tool }),
+                        &cancel,
+                    )
+                    .await
+            })
+        };
+        let first = spawn_pause("read");
+        let second = spawn_pause("grep");
+        let pauses = loop {
+            let pauses = controller.pending("s1").await;
+            if pauses.len() == 2 {
+                break pauses;
+            }
+            tokio::task::yield_now().await;
+        };
+        let by_tool = |tool: &str| {
+            pauses
+                .iter()
+                .find(|p| p.payload["tool"] == tool)
+                .map(|p| p.pause_id.clone())
+                .expect("pause")
+        };
+        assert!(controller
+            .resolve("s2", Some(&by_tool("grep")), DebugCommand::Skip)
+            .await
+            .is_none());
+        controller
+            .resolve("s1", Some(&by_tool("grep")), DebugCommand::Skip)
+            .await
+            .expect("grep pause");
+        controller
+            .resolve("s1", Some(&by_tool("read")), DebugCommand::Continue)
+            .await
+            .expect("read pause");
+        assert_eq!(first.await.expect("join"), DebugCommand::Continue);
+        assert_eq!(second.await.expect("join"), DebugCommand::Skip);
+    }
+}
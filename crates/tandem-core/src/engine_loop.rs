@@ -6,11 +6,16 @@ use std::collections::{hash_map::DefaultHasher, HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
 use tandem_observability::{emit_event, ObservabilityEvent, ProcessKind};
-use tandem_providers::{ChatMessage, ProviderRegistry, StreamChunk, TokenUsage};
-use tandem_tools::{validate_tool_schemas, ToolRegistry};
+use tandem_providers::{
+    ChatMessage, GenerationPreset, HedgePolicy, HedgeWinner, ProviderRegistry, StreamChunk,
+    TokenUsage,
+};
+use tandem_tools::{
+    is_timeout_result, validate_tool_schemas, CommandAction, CommandDecision, ToolRegistry,
+};
 use tandem_types::{
-    EngineEvent, HostOs, HostRuntimeContext, Message, MessagePart, MessagePartInput, MessageRole,
-    ModelSpec, PathStyle, SendMessageRequest, ShellFamily,
+    EngineEvent, HostOs, HostRuntimeContext, Message, MessagePart, MessageRole, ModelSpec,
+    PathStyle, SendMessageRequest, ShellFamily, ToolResult,
 };
 use tandem_wire::WireMessagePart;
 use tokio_util::sync::CancellationToken;
@@ -18,16 +23,36 @@ use tracing::Level;
 
 use crate::{
     derive_session_title_from_prompt, title_needs_repair, AgentDefinition, AgentRegistry,
-    CancellationRegistry, EventBus, PermissionAction, PermissionManager, PluginRegistry, Storage,
+    CancellationRegistry, DebugCommand, DebugStepController, DebugStepKind, EventBus,
+    PermissionAction, PermissionManager, PluginRegistry, ProviderLanes, RunPauseController,
+    RunPriority, Storage,
 };
 use tokio::sync::RwLock;
 
 #[derive(Default)]
 struct StreamedToolCall {
+    /// Position of the call in the stream, so calls keep the model's order.
+    order: usize,
     name: String,
     args: String,
 }
 
+/// One tool call from an assistant turn after the loop guards ran: either an
+/// output decided up front (skipped, cached) or a call still to execute.
+enum PlannedToolSlot {
+    Output(String),
+    Call(PlannedToolCall),
+}
+
+struct PlannedToolCall {
+    tool: String,
+    tool_key: String,
+    args: Value,
+    signature: String,
+    signature_count: usize,
+    parallel_safe: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct SpawnAgentToolContext {
     pub session_id: String,
@@ -70,6 +95,10 @@ pub trait ToolPolicyHook: Send + Sync {
     ) -> BoxFuture<'static, anyhow::Result<ToolPolicyDecision>>;
 }
 
+const MAX_SESSION_TOUCHED_PATHS: usize = 16;
+/// Provider calls a single prompt may make before the tool loop stops.
+const MAX_TOOL_ITERATIONS: usize = 25;
+
 #[derive(Clone)]
 pub struct EngineLoop {
     storage: std::sync::Arc<Storage>,
@@ -83,8 +112,18 @@ pub struct EngineLoop {
     host_runtime_context: HostRuntimeContext,
     workspace_overrides: std::sync::Arc<RwLock<HashMap<String, u64>>>,
     session_allowed_tools: std::sync::Arc<RwLock<HashMap<String, Vec<String>>>>,
+    session_priorities: std::sync::Arc<RwLock<HashMap<String, RunPriority>>>,
+    session_runs: std::sync::Arc<RwLock<HashMap<String, String>>>,
+    /// Files each session recently read or changed, newest last; search
+    /// tools use them to default to the packages being worked on.
+    session_touched_paths: std::sync::Arc<RwLock<HashMap<String, Vec<String>>>>,
+    /// Unix ms each skill was last loaded, for relevance ranking.
+    skill_last_used: std::sync::Arc<RwLock<HashMap<String, u64>>>,
+    provider_lanes: ProviderLanes,
     spawn_agent_hook: std::sync::Arc<RwLock<Option<std::sync::Arc<dyn SpawnAgentHook>>>>,
     tool_policy_hook: std::sync::Arc<RwLock<Option<std::sync::Arc<dyn ToolPolicyHook>>>>,
+    debug_steps: DebugStepController,
+    run_pauses: RunPauseController,
 }
 
 impl EngineLoop {
@@ -101,6 +140,8 @@ impl EngineLoop {
         host_runtime_context: HostRuntimeContext,
     ) -> Self {
         Self {
+            debug_steps: DebugStepController::new(event_bus.clone()),
+            run_pauses: RunPauseController::new(storage.clone(), event_bus.clone()),
             storage,
             event_bus,
             providers,
@@ -112,6 +153,11 @@ impl EngineLoop {
             host_runtime_context,
             workspace_overrides: std::sync::Arc::new(RwLock::new(HashMap::new())),
             session_allowed_tools: std::sync::Arc::new(RwLock::new(HashMap::new())),
+            session_priorities: std::sync::Arc::new(RwLock::new(HashMap::new())),
+            session_runs: std::sync::Arc::new(RwLock::new(HashMap::new())),
+            session_touched_paths: std::sync::Arc::new(RwLock::new(HashMap::new())),
+            skill_last_used: std::sync::Arc::new(RwLock::new(HashMap::new())),
+            provider_lanes: ProviderLanes::default(),
             spawn_agent_hook: std::sync::Arc::new(RwLock::new(None)),
             tool_policy_hook: std::sync::Arc::new(RwLock::new(None)),
         }
@@ -125,6 +171,14 @@ impl EngineLoop {
         *self.tool_policy_hook.write().await = Some(hook);
     }
 
+    pub fn debug_steps(&self) -> &DebugStepController {
+        &self.debug_steps
+    }
+
+    pub fn run_pauses(&self) -> &RunPauseController {
+        &self.run_pauses
+    }
+
     pub async fn set_session_allowed_tools(&self, session_id: &str, allowed_tools: Vec<String>) {
         let normalized = allowed_tools
             .into_iter()
@@ -141,6 +195,49 @@ impl EngineLoop {
         self.session_allowed_tools.write().await.remove(session_id);
     }
 
+    /// Provider-lane priority for a session's calls; sessions default to
+    /// interactive.
+    pub async fn set_session_priority(&self, session_id: &str, priority: RunPriority) {
+        self.session_priorities
+            .write()
+            .await
+            .insert(session_id.to_string(), priority);
+    }
+
+    pub async fn clear_session_priority(&self, session_id: &str) {
+        self.session_priorities.write().await.remove(session_id);
+    }
+
+    pub async fn session_priority(&self, session_id: &str) -> RunPriority {
+        self.session_priorities
+            .read()
+            .await
+            .get(session_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Run currently driving a session; tools receive it as `__run_id` so
+    /// their side effects can be traced back to the run.
+    pub async fn set_session_run(&self, session_id: &str, run_id: &str) {
+        self.session_runs
+            .write()
+            .await
+            .insert(session_id.to_string(), run_id.to_string());
+    }
+
+    pub async fn clear_session_run(&self, session_id: &str) {
+        self.session_runs.write().await.remove(session_id);
+    }
+
+    pub async fn session_run(&self, session_id: &str) -> Option<String> {
+        self.session_runs.read().await.get(session_id).cloned()
+    }
+
+    pub fn provider_lanes(&self) -> &ProviderLanes {
+        &self.provider_lanes
+    }
+
     pub async fn grant_workspace_override_for_session(
         &self,
         session_id: &str,
@@ -173,17 +270,38 @@ impl EngineLoop {
         req: SendMessageRequest,
         correlation_id: Option<String>,
     ) -> anyhow::Result<()> {
-        let session_model = self
-            .storage
-            .get_session(&session_id)
-            .await
-            .and_then(|s| s.model);
-        let (provider_id, model_id_value) =
-            resolve_model_route(req.model.as_ref(), session_model.as_ref()).ok_or_else(|| {
-                anyhow::anyhow!(
+        let session = self.storage.get_session(&session_id).await;
+        let session_model = session.as_ref().and_then(|s| s.model.clone());
+        let active_agent = self.agents.get(req.agent.as_deref()).await;
+        let preset_name = req
+            .preset
+            .clone()
+            .or_else(|| session.as_ref().and_then(|s| s.generation_preset.clone()))
+            .or_else(|| active_agent.preset.clone())
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty());
+        let preset =
+            match preset_name.as_deref() {
+                Some(name) => Some(self.providers.generation_preset(name).await.ok_or_else(
+                    || {
+                        anyhow::anyhow!(
+                            "GENERATION_PRESET_NOT_FOUND: no generation preset named `{name}`"
+                        )
+                    },
+                )?),
+                None => None,
+            };
+        let (provider_id, model_id_value) = resolve_model_route(
+            req.model.as_ref(),
+            preset.as_ref().and_then(GenerationPreset::model_route),
+            session_model.as_ref(),
+        )
+        .ok_or_else(|| {
+            anyhow::anyhow!(
                 "MODEL_SELECTION_REQUIRED: explicit provider/model is required for this request."
             )
-            })?;
+        })?;
+        let generation_params = preset.map(|preset| preset.params).unwrap_or_default();
         let correlation_ref = correlation_id.as_deref();
         let model_id = Some(model_id_value.as_str());
         let cancel = self.cancellations.create(&session_id).await;
@@ -208,27 +326,10 @@ impl EngineLoop {
             "session.status",
             json!({"sessionID": session_id, "status":"running"}),
         ));
-        let text = req
-            .parts
-            .iter()
-            .map(|p| match p {
-                MessagePartInput::Text { text } => text.clone(),
-                MessagePartInput::File {
-                    mime,
-                    filename,
-                    url,
-                } => format!(
-                    "[file mime={} name={} url={}]",
-                    mime,
-                    filename.clone().unwrap_or_else(|| "unknown".to_string()),
-                    url
-                ),
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+        let text =
+            crate::render_message_parts(&req.parts, &session_id, &self.storage.attachments()).await;
         self.auto_rename_session_from_user_text(&session_id, &text)
             .await;
-        let active_agent = self.agents.get(req.agent.as_deref()).await;
         let mut user_message_id = self
             .find_recent_matching_user_message_id(&session_id, &text)
             .await;
@@ -265,6 +366,8 @@ impl EngineLoop {
         }
 
         let mut question_tool_used = false;
+        let mut stream_resumes: Vec<Value> = Vec::new();
+        let mut turn_retries: Vec<Value> = Vec::new();
         let completion = if let Some((tool, args)) = parse_tool_invocation(&text) {
             if normalize_tool_name(&tool) == "question" {
                 question_tool_used = true;
@@ -280,6 +383,7 @@ impl EngineLoop {
                     &user_message_id,
                     tool.clone(),
                     args,
+                    &active_agent.name,
                     active_agent.skills.as_deref(),
                     &text,
                     None,
@@ -290,7 +394,8 @@ impl EngineLoop {
             }
         } else {
             let mut completion = String::new();
-            let mut max_iterations = 25usize;
+            let mut max_iterations = MAX_TOOL_ITERATIONS;
+            let mut iteration = 0usize;
             let mut followup_context: Option<String> = None;
             let mut last_tool_outputs: Vec<String> = Vec::new();
             let mut tool_call_counts: HashMap<String, usize> = HashMap::new();
@@ -299,15 +404,31 @@ impl EngineLoop {
             let mut shell_mismatch_signatures: HashSet<String> = HashSet::new();
             let mut websearch_query_blocked = false;
             let mut auto_workspace_probe_attempted = false;
+            let turn_retry = crate::TurnRetryPolicy::from_env();
+            let mut turn_retry_attempt = 0usize;
+            let mut loop_watchdog = crate::ToolLoopWatchdog::from_env();
+            let mut loops_detected = 0usize;
+            let parallel_tool_limit = crate::parallel_tool_limit_from_env();
+            let hedge_policy = HedgePolicy::from_env();
+            let skill_context = self
+                .select_skill_context(&session_id, &user_message_id, &active_agent, &text)
+                .await;
 
             while max_iterations > 0 && !cancel.is_cancelled() {
                 max_iterations -= 1;
+                iteration += 1;
                 let mut messages = load_chat_history(self.storage.clone(), &session_id).await;
                 let mut system_parts =
                     vec![tandem_runtime_system_prompt(&self.host_runtime_context)];
                 if let Some(system) = active_agent.system_prompt.as_ref() {
                     system_parts.push(system.clone());
                 }
+                if let Some(extra) = req.system.as_ref().filter(|s| !s.trim().is_empty()) {
+                    system_parts.push(extra.clone());
+                }
+                if let Some(skills) = skill_context.as_ref() {
+                    system_parts.push(skills.clone());
+                }
                 messages.insert(
                     0,
                     ChatMessage {
@@ -360,16 +481,149 @@ impl EngineLoop {
                     );
                     anyhow::bail!("{detail}");
                 }
-                let stream = self
-                    .providers
-                    .stream_for_provider(
-                        Some(provider_id.as_str()),
-                        Some(model_id_value.as_str()),
-                        messages,
-                        Some(tool_schemas),
-                        cancel.clone(),
+                let debug_command = self
+                    .debug_steps
+                    .checkpoint(
+                        &session_id,
+                        DebugStepKind::ProviderCall,
+                        json!({
+                            "messageID": user_message_id,
+                            "providerID": provider_id,
+                            "modelID": model_id_value,
+                            "iteration": iteration,
+                            "messages": messages
+                                .iter()
+                                .map(|m| json!({
+                                    "role": m.role,
+                                    "content": truncate_text(&m.content, 4_000),
+                                }))
+                                .collect::<Vec<_>>(),
+                            "tools": tool_schemas.iter().map(|t| t.name.clone()).collect::<Vec<_>>(),
+                        }),
+                        &cancel,
                     )
-                    .await
+                    .await;
+                match debug_command {
+                    DebugCommand::Continue => {}
+                    // Skip this provider call only; the next iteration pauses
+                    // again, so the iteration budget bounds repeated skips.
+                    DebugCommand::Skip => continue,
+                    DebugCommand::Abort => {
+                        cancel.cancel();
+                        break;
+                    }
+                }
+                if !self.run_pauses.checkpoint(&session_id, &cancel).await {
+                    break;
+                }
+                let mut provider_call_props = json!({
+                    "sessionID": session_id,
+                    "messageID": user_message_id,
+                    "providerID": provider_id,
+                    "modelID": model_id_value,
+                    "preset": preset_name,
+                    "iteration": iteration,
+                });
+                self.event_bus.publish(EngineEvent::new(
+                    "provider.call.started",
+                    provider_call_props.clone(),
+                ));
+                completion.clear();
+                let mut streamed_tool_calls: HashMap<String, StreamedToolCall> = HashMap::new();
+                let mut provider_usage: Option<TokenUsage> = None;
+                // The route that actually served the turn; a retry or a won
+                // hedge moves it off the session's model.
+                let mut served_route = (provider_id.clone(), model_id_value.clone());
+                let mut resume_attempt = 0usize;
+                let mut pending_overlap: Option<String> = None;
+                'provider_stream: loop {
+                    let attempt_messages = if resume_attempt == 0 {
+                        messages.clone()
+                    } else {
+                        let mut resumed = messages.clone();
+                        resumed.push(ChatMessage {
+                            role: "assistant".to_string(),
+                            content: completion.clone(),
+                        });
+                        resumed.push(ChatMessage {
+                            role: "user".to_string(),
+                            content: crate::stream_continuation_prompt(&completion),
+                        });
+                        resumed
+                    };
+                    let _lane = self
+                        .provider_lanes
+                        .acquire(self.session_priority(&session_id).await)
+                        .await;
+                    let (turn_provider_id, turn_model_id) =
+                        turn_retry.route_for(turn_retry_attempt, &provider_id, &model_id_value);
+                    // Hedge only the first attempt of interactive turns; retries and
+                    // stream resumes already pick their own route.
+                    let interactive =
+                        self.session_priority(&session_id).await == RunPriority::Interactive;
+                    let hedge = hedge_policy.as_ref().filter(|policy| {
+                        interactive
+                            && resume_attempt == 0
+                            && turn_retry_attempt == 0
+                            && !policy.targets(&turn_provider_id, &turn_model_id)
+                    });
+                    let params = generation_params
+                        .with_temperature(turn_retry.temperature_for(turn_retry_attempt));
+                    let stream = match hedge {
+                        Some(policy) => self
+                            .providers
+                            .stream_hedged(
+                                Some(turn_provider_id.as_str()),
+                                Some(turn_model_id.as_str()),
+                                attempt_messages,
+                                Some(tool_schemas.clone()),
+                                &params,
+                                policy,
+                                cancel.clone(),
+                            )
+                            .await
+                            .map(|(stream, outcome)| {
+                                served_route = match outcome.winner {
+                                    HedgeWinner::Primary => {
+                                        (turn_provider_id.clone(), turn_model_id.clone())
+                                    }
+                                    HedgeWinner::Fallback => (
+                                        policy.fallback_provider.clone(),
+                                        policy.fallback_model.clone(),
+                                    ),
+                                };
+                                if outcome.hedged {
+                                    self.event_bus.publish(EngineEvent::new(
+                                        "provider.hedge",
+                                        json!({
+                                            "sessionID": session_id,
+                                            "messageID": user_message_id,
+                                            "providerID": turn_provider_id,
+                                            "modelID": turn_model_id,
+                                            "fallbackProviderID": policy.fallback_provider,
+                                            "fallbackModelID": policy.fallback_model,
+                                            "delayMs": policy.delay_ms,
+                                            "winner": outcome.winner,
+                                            "firstChunkMs": outcome.first_chunk_ms,
+                                        }),
+                                    ));
+                                }
+                                stream
+                            }),
+                        None => {
+                            served_route = (turn_provider_id.clone(), turn_model_id.clone());
+                            self.providers
+                                .stream_for_provider_with_params(
+                                    Some(turn_provider_id.as_str()),
+                                    Some(turn_model_id.as_str()),
+                                    attempt_messages,
+                                    Some(tool_schemas.clone()),
+                                    &params,
+                                    cancel.clone(),
+                                )
+                                .await
+                        }
+                    }
                     .inspect_err(|err| {
                         let error_text = err.to_string();
                         let error_code = provider_error_code(&error_text);
@@ -392,47 +646,26 @@ impl EngineLoop {
                             },
                         );
                     })?;
-                tokio::pin!(stream);
-                completion.clear();
-                let mut streamed_tool_calls: HashMap<String, StreamedToolCall> = HashMap::new();
-                let mut provider_usage: Option<TokenUsage> = None;
-                while let Some(chunk) = stream.next().await {
-                    let chunk = match chunk {
-                        Ok(chunk) => chunk,
-                        Err(err) => {
-                            let error_text = err.to_string();
-                            let error_code = provider_error_code(&error_text);
-                            let detail = truncate_text(&error_text, 500);
-                            emit_event(
-                                Level::ERROR,
-                                ProcessKind::Engine,
-                                ObservabilityEvent {
-                                    event: "provider.call.error",
-                                    component: "engine.loop",
-                                    correlation_id: correlation_ref,
-                                    session_id: Some(&session_id),
-                                    run_id: None,
-                                    message_id: Some(&user_message_id),
-                                    provider_id: Some(provider_id.as_str()),
-                                    model_id,
-                                    status: Some("failed"),
-                                    error_code: Some(error_code),
-                                    detail: Some(&detail),
-                                },
-                            );
-                            return Err(anyhow::anyhow!(
-                                "provider stream chunk error: {error_text}"
-                            ));
-                        }
-                    };
-                    match chunk {
-                        StreamChunk::TextDelta(delta) => {
-                            if completion.is_empty() {
+                    tokio::pin!(stream);
+                    while let Some(chunk) = stream.next().await {
+                        let chunk = chunk.and_then(|chunk| {
+                            if crate::chaos_inject(crate::ChaosFault::ProviderStream) {
+                                Err(anyhow::anyhow!("chaos: injected provider stream failure"))
+                            } else {
+                                Ok(chunk)
+                            }
+                        });
+                        let chunk = match chunk {
+                            Ok(chunk) => chunk,
+                            Err(err) => {
+                                let error_text = err.to_string();
+                                let error_code = provider_error_code(&error_text);
+                                let detail = truncate_text(&error_text, 500);
                                 emit_event(
-                                    Level::INFO,
+                                    Level::ERROR,
                                     ProcessKind::Engine,
                                     ObservabilityEvent {
-                                        event: "provider.call.first_byte",
+                                        event: "provider.call.error",
                                         component: "engine.loop",
                                         correlation_id: correlation_ref,
                                         session_id: Some(&session_id),
@@ -440,91 +673,231 @@ impl EngineLoop {
                                         message_id: Some(&user_message_id),
                                         provider_id: Some(provider_id.as_str()),
                                         model_id,
-                                        status: Some("streaming"),
-                                        error_code: None,
-                                        detail: Some("first text delta"),
+                                        status: Some("failed"),
+                                        error_code: Some(error_code),
+                                        detail: Some(&detail),
                                     },
                                 );
+                                if resume_attempt < crate::max_stream_resumes()
+                                    && streamed_tool_calls.is_empty()
+                                    && !cancel.is_cancelled()
+                                    && crate::is_resumable_stream_error(error_code)
+                                {
+                                    resume_attempt += 1;
+                                    self.flush_resume_overlap(
+                                        &session_id,
+                                        &user_message_id,
+                                        &mut completion,
+                                        &mut pending_overlap,
+                                    );
+                                    let partial_chars = completion.chars().count();
+                                    stream_resumes.push(json!({
+                                        "attempt": resume_attempt,
+                                        "errorCode": error_code,
+                                        "error": detail,
+                                        "partialChars": partial_chars,
+                                    }));
+                                    self.event_bus.publish(EngineEvent::new(
+                                        "provider.stream.resumed",
+                                        json!({
+                                            "sessionID": session_id,
+                                            "messageID": user_message_id,
+                                            "attempt": resume_attempt,
+                                            "errorCode": error_code,
+                                            "partialChars": partial_chars,
+                                        }),
+                                    ));
+                                    if !completion.is_empty() {
+                                        pending_overlap = Some(String::new());
+                                    }
+                                    let delay = crate::stream_resume_backoff(resume_attempt);
+                                    tokio::select! {
+                                        _ = tokio::time::sleep(delay) => {}
+                                        _ = cancel.cancelled() => {
+                                            return Err(anyhow::anyhow!(
+                                                "provider stream chunk error: {error_text}"
+                                            ));
+                                        }
+                                    }
+                                    continue 'provider_stream;
+                                }
+                                return Err(anyhow::anyhow!(
+                                    "provider stream chunk error: {error_text}"
+                                ));
                             }
-                            completion.push_str(&delta);
-                            let delta = truncate_text(&delta, 4_000);
-                            let delta_part =
-                                WireMessagePart::text(&session_id, &user_message_id, delta.clone());
-                            self.event_bus.publish(EngineEvent::new(
-                                "message.part.updated",
-                                json!({"part": delta_part, "delta": delta}),
-                            ));
-                        }
-                        StreamChunk::ReasoningDelta(_reasoning) => {}
-                        StreamChunk::Done {
-                            finish_reason: _,
-                            usage,
-                        } => {
-                            if usage.is_some() {
-                                provider_usage = usage;
+                        };
+                        match chunk {
+                            StreamChunk::TextDelta(delta) => {
+                                if completion.is_empty() {
+                                    emit_event(
+                                        Level::INFO,
+                                        ProcessKind::Engine,
+                                        ObservabilityEvent {
+                                            event: "provider.call.first_byte",
+                                            component: "engine.loop",
+                                            correlation_id: correlation_ref,
+                                            session_id: Some(&session_id),
+                                            run_id: None,
+                                            message_id: Some(&user_message_id),
+                                            provider_id: Some(provider_id.as_str()),
+                                            model_id,
+                                            status: Some("streaming"),
+                                            error_code: None,
+                                            detail: Some("first text delta"),
+                                        },
+                                    );
+                                }
+                                let delta = match pending_overlap.as_mut() {
+                                    Some(buffer) => {
+                                        buffer.push_str(&delta);
+                                        if buffer.chars().count()
+                                            < crate::STREAM_RESUME_OVERLAP_WINDOW
+                                        {
+                                            continue;
+                                        }
+                                        let buffer = pending_overlap.take().unwrap_or_default();
+                                        crate::strip_resume_overlap(&completion, &buffer)
+                                    }
+                                    None => delta,
+                                };
+                                completion.push_str(&delta);
+                                self.publish_text_delta(&session_id, &user_message_id, &delta);
                             }
-                            break;
-                        }
-                        StreamChunk::ToolCallStart { id, name } => {
-                            let entry = streamed_tool_calls.entry(id).or_default();
-                            if entry.name.is_empty() {
-                                entry.name = name;
+                            StreamChunk::ReasoningDelta(_reasoning) => {}
+                            StreamChunk::Done {
+                                finish_reason: _,
+                                usage,
+                            } => {
+                                if usage.is_some() {
+                                    provider_usage = usage;
+                                }
+                                break;
                             }
-                        }
-                        StreamChunk::ToolCallDelta { id, args_delta } => {
-                            let entry = streamed_tool_calls.entry(id.clone()).or_default();
-                            entry.args.push_str(&args_delta);
-                            let tool_name = if entry.name.trim().is_empty() {
-                                "tool".to_string()
-                            } else {
-                                normalize_tool_name(&entry.name)
-                            };
-                            let parsed_preview = if entry.name.trim().is_empty() {
-                                Value::String(truncate_text(&entry.args, 1_000))
-                            } else {
-                                parse_streamed_tool_args(&tool_name, &entry.args)
-                            };
-                            let mut tool_part = WireMessagePart::tool_invocation(
-                                &session_id,
-                                &user_message_id,
-                                tool_name.clone(),
-                                json!({}),
-                            );
-                            tool_part.id = Some(id.clone());
-                            self.event_bus.publish(EngineEvent::new(
-                                "message.part.updated",
-                                json!({
-                                    "part": tool_part,
-                                    "toolCallDelta": {
-                                        "id": id,
-                                        "tool": tool_name,
-                                        "argsDelta": truncate_text(&args_delta, 1_000),
-                                        "parsedArgsPreview": parsed_preview
+                            StreamChunk::ToolCallStart { id, name } => {
+                                let order = streamed_tool_calls.len();
+                                let entry = streamed_tool_calls.entry(id).or_insert_with(|| {
+                                    StreamedToolCall {
+                                        order,
+                                        ..Default::default()
                                     }
-                                }),
-                            ));
+                                });
+                                if entry.name.is_empty() {
+                                    entry.name = name;
+                                }
+                            }
+                            StreamChunk::ToolCallDelta { id, args_delta } => {
+                                let order = streamed_tool_calls.len();
+                                let entry =
+                                    streamed_tool_calls.entry(id.clone()).or_insert_with(|| {
+                                        StreamedToolCall {
+                                            order,
+                                            ..Default::default()
+                                        }
+                                    });
+                                entry.args.push_str(&args_delta);
+                                let tool_name = if entry.name.trim().is_empty() {
+                                    "tool".to_string()
+                                } else {
+                                    normalize_tool_name(&entry.name)
+                                };
+                                let parsed_preview = if entry.name.trim().is_empty() {
+                                    Value::String(truncate_text(&entry.args, 1_000))
+                                } else {
+                                    parse_streamed_tool_args(&tool_name, &entry.args)
+                                };
+                                let mut tool_part = WireMessagePart::tool_invocation(
+                                    &session_id,
+                                    &user_message_id,
+                                    tool_name.clone(),
+                                    json!({}),
+                                );
+                                tool_part.id = Some(id.clone());
+                                self.event_bus.publish(EngineEvent::new(
+                                    "message.part.updated",
+                                    json!({
+                                        "part": tool_part,
+                                        "toolCallDelta": {
+                                            "id": id,
+                                            "tool": tool_name,
+                                            "argsDelta": truncate_text(&args_delta, 1_000),
+                                            "parsedArgsPreview": parsed_preview
+                                        }
+                                    }),
+                                ));
+                            }
+                            StreamChunk::ToolCallEnd { id: _ } => {}
+                        }
+                        if cancel.is_cancelled() {
+                            break;
                         }
-                        StreamChunk::ToolCallEnd { id: _ } => {}
-                    }
-                    if cancel.is_cancelled() {
-                        break;
                     }
+                    break;
                 }
+                self.flush_resume_overlap(
+                    &session_id,
+                    &user_message_id,
+                    &mut completion,
+                    &mut pending_overlap,
+                );
+                provider_call_props["providerID"] = json!(served_route.0);
+                provider_call_props["modelID"] = json!(served_route.1);
+                self.event_bus.publish(EngineEvent::new(
+                    "provider.call.finished",
+                    provider_call_props,
+                ));
 
-                let mut tool_calls = streamed_tool_calls
-                    .into_values()
+                let mut malformed_tool_calls = Vec::new();
+                let mut streamed_in_order = streamed_tool_calls.into_values().collect::<Vec<_>>();
+                streamed_in_order.sort_by_key(|call| call.order);
+                let mut tool_calls = streamed_in_order
+                    .into_iter()
                     .filter_map(|call| {
                         if call.name.trim().is_empty() {
                             return None;
                         }
                         let tool_name = normalize_tool_name(&call.name);
                         let parsed_args = parse_streamed_tool_args(&tool_name, &call.args);
+                        if crate::is_malformed_tool_args(&call.args, &parsed_args) {
+                            malformed_tool_calls.push(tool_name.clone());
+                        }
                         Some((tool_name, parsed_args))
                     })
                     .collect::<Vec<_>>();
                 if tool_calls.is_empty() {
                     tool_calls = parse_tool_invocations_from_response(&completion);
                 }
+                if let Some(issue) = crate::classify_turn_output(
+                    &completion,
+                    tool_calls.len(),
+                    malformed_tool_calls.len(),
+                ) {
+                    if turn_retry_attempt < turn_retry.max_retries && !cancel.is_cancelled() {
+                        turn_retry_attempt += 1;
+                        let (retry_provider_id, retry_model_id) =
+                            turn_retry.route_for(turn_retry_attempt, &provider_id, &model_id_value);
+                        let temperature = turn_retry.temperature_for(turn_retry_attempt);
+                        let retry = json!({
+                            "attempt": turn_retry_attempt,
+                            "reason": issue.code(),
+                            "temperature": temperature,
+                            "providerID": retry_provider_id,
+                            "modelID": retry_model_id,
+                            "malformedTools": malformed_tool_calls,
+                        });
+                        let mut event = retry.clone();
+                        event["sessionID"] = json!(session_id);
+                        event["messageID"] = json!(user_message_id);
+                        self.event_bus
+                            .publish(EngineEvent::new("provider.turn.retry", event));
+                        turn_retries.push(retry);
+                        completion.clear();
+                        // Retries do not count against the tool iteration budget.
+                        max_iterations += 1;
+                        continue;
+                    }
+                } else {
+                    turn_retry_attempt = 0;
+                }
                 if tool_calls.is_empty()
                     && !auto_workspace_probe_attempted
                     && should_force_workspace_probe(&text, &completion)
@@ -533,8 +906,9 @@ impl EngineLoop {
                     tool_calls = vec![("glob".to_string(), json!({ "pattern": "*" }))];
                 }
                 if !tool_calls.is_empty() {
-                    let mut outputs = Vec::new();
+                    let mut slots = Vec::new();
                     let mut executed_productive_tool = false;
+                    let mut loop_detected: Option<(String, usize)> = None;
                     for (tool, args) in tool_calls {
                         if !agent_can_use_tool(&active_agent, &tool) {
                             continue;
@@ -544,30 +918,30 @@ impl EngineLoop {
                             question_tool_used = true;
                         }
                         if websearch_query_blocked && tool_key == "websearch" {
-                            outputs.push(
+                            slots.push(PlannedToolSlot::Output(
                                 "Tool `websearch` call skipped: WEBSEARCH_QUERY_MISSING"
                                     .to_string(),
-                            );
+                            ));
                             continue;
                         }
                         let entry = tool_call_counts.entry(tool_key.clone()).or_insert(0);
                         *entry += 1;
                         let budget = tool_budget_for(&tool_key);
                         if *entry > budget {
-                            outputs.push(format!(
+                            slots.push(PlannedToolSlot::Output(format!(
                                 "Tool `{}` call skipped: per-run guard budget exceeded ({}).",
                                 tool_key, budget
-                            ));
+                            )));
                             continue;
                         }
                         let mut effective_args = args.clone();
                         if tool_key == "todo_write" {
                             effective_args = normalize_todo_write_args(effective_args, &completion);
                             if is_empty_todo_write_args(&effective_args) {
-                                outputs.push(
+                                slots.push(PlannedToolSlot::Output(
                                     "Tool `todo_write` call skipped: empty todo payload."
                                         .to_string(),
-                                );
+                                ));
                                 continue;
                             }
                         }
@@ -580,12 +954,36 @@ impl EngineLoop {
                         if is_shell_tool_name(&tool_key)
                             && shell_mismatch_signatures.contains(&signature)
                         {
-                            outputs.push(
+                            slots.push(PlannedToolSlot::Output(
                                 "Tool `bash` call skipped: previous invocation hit an OS/path mismatch. Use `read`, `glob`, or `grep`."
                                     .to_string(),
-                            );
+                            ));
                             continue;
                         }
+                        let signature_hash = stable_hash(&signature);
+                        let loop_repeats = loop_watchdog.record(&signature_hash);
+                        if loop_watchdog.is_loop(loop_repeats) {
+                            loops_detected += 1;
+                            self.event_bus.publish(EngineEvent::new(
+                                "run.loop_detected",
+                                json!({
+                                    "sessionID": session_id,
+                                    "messageID": user_message_id,
+                                    "runID": self.session_run(&session_id).await,
+                                    "tool": tool_key,
+                                    "signatureHash": signature_hash,
+                                    "repeats": loop_repeats,
+                                    "action": if loops_detected > 1 { "stopped" } else { "corrected" },
+                                }),
+                            ));
+                            loop_detected = Some((tool_key.clone(), loop_repeats));
+                            break;
+                        }
+                        if loop_repeats > 1 {
+                            if let Some(obj) = effective_args.as_object_mut() {
+                                obj.insert("__loop_guard_triggered".to_string(), json!(true));
+                            }
+                        }
                         let mut signature_count = 1usize;
                         if is_read_only_tool(&tool_key)
                             || (tool_key == "batch" && is_read_only_batch_call(&args))
@@ -607,37 +1005,73 @@ impl EngineLoop {
                                         "loop_guard_triggered": true
                                     }),
                                 ));
-                                outputs.push(
+                                slots.push(PlannedToolSlot::Output(
                                     "Tool `websearch` call skipped: WEBSEARCH_LOOP_GUARD"
                                         .to_string(),
-                                );
+                                ));
                                 continue;
                             }
                             if tool_key != "websearch" && *count > 1 {
                                 if let Some(cached) = readonly_tool_cache.get(&signature) {
-                                    outputs.push(cached.clone());
+                                    slots.push(PlannedToolSlot::Output(cached.clone()));
                                 } else {
-                                    outputs.push(format!(
+                                    slots.push(PlannedToolSlot::Output(format!(
                                         "Tool `{}` call skipped: duplicate call signature detected.",
                                         tool_key
-                                    ));
+                                    )));
                                 }
                                 continue;
                             }
                         }
-                        if let Some(output) = self
-                            .execute_tool_with_permission(
-                                &session_id,
-                                &user_message_id,
-                                tool,
-                                effective_args,
-                                active_agent.skills.as_deref(),
-                                &text,
-                                Some(&completion),
-                                cancel.clone(),
-                            )
-                            .await?
-                        {
+                        slots.push(PlannedToolSlot::Call(PlannedToolCall {
+                            parallel_safe: is_read_only_tool(&tool_key)
+                                || (tool_key == "batch" && is_read_only_batch_call(&args)),
+                            tool,
+                            tool_key,
+                            args: effective_args,
+                            signature,
+                            signature_count,
+                        }));
+                    }
+                    let parallel_limit = if self.debug_steps.is_enabled(&session_id).await {
+                        1
+                    } else {
+                        crate::capped_parallel_tool_limit(parallel_tool_limit)
+                    };
+                    let results = self
+                        .execute_tool_batches(
+                            &session_id,
+                            &user_message_id,
+                            &active_agent,
+                            &text,
+                            &completion,
+                            &slots,
+                            parallel_limit,
+                            &cancel,
+                        )
+                        .await;
+                    // Report back in call order; the first failure aborts the run
+                    // just as it did when calls ran one at a time.
+                    let mut results = results.into_iter();
+                    let mut outputs = Vec::new();
+                    for slot in slots {
+                        let call = match slot {
+                            PlannedToolSlot::Output(output) => {
+                                outputs.push(output);
+                                continue;
+                            }
+                            PlannedToolSlot::Call(call) => call,
+                        };
+                        let Some(result) = results.next() else {
+                            break;
+                        };
+                        let PlannedToolCall {
+                            tool_key,
+                            signature,
+                            signature_count,
+                            ..
+                        } = call;
+                        if let Some(output) = result? {
                             let productive =
                                 !(tool_key == "batch" && is_non_productive_batch_output(&output));
                             if output.contains("WEBSEARCH_QUERY_MISSING") {
@@ -659,6 +1093,22 @@ impl EngineLoop {
                             outputs.push(output);
                         }
                     }
+                    if let Some((looping_tool, repeats)) = loop_detected {
+                        loop_watchdog.reset();
+                        if loops_detected > 1 {
+                            completion = format!(
+                                "Stopped after repeated identical `{looping_tool}` calls; the run was not making progress."
+                            );
+                            break;
+                        }
+                        let corrective = crate::loop_corrective_prompt(&looping_tool, repeats);
+                        followup_context = Some(if outputs.is_empty() {
+                            corrective
+                        } else {
+                            format!("{}\n{corrective}", summarize_tool_outputs(&outputs))
+                        });
+                        continue;
+                    }
                     if !outputs.is_empty() {
                         last_tool_outputs = outputs.clone();
                         if executed_productive_tool {
@@ -679,6 +1129,8 @@ impl EngineLoop {
                         json!({
                             "sessionID": session_id,
                             "messageID": user_message_id,
+                            "providerID": served_route.0,
+                            "modelID": served_route.1,
                             "promptTokens": usage.prompt_tokens,
                             "completionTokens": usage.completion_tokens,
                             "totalTokens": usage.total_tokens,
@@ -763,12 +1215,23 @@ impl EngineLoop {
             self.cancellations.remove(&session_id).await;
             return Ok(());
         }
-        let assistant = Message::new(
+        let mut assistant = Message::new(
             MessageRole::Assistant,
             vec![MessagePart::Text {
                 text: completion.clone(),
             }],
         );
+        let mut metadata = Map::new();
+        if !stream_resumes.is_empty() {
+            metadata.insert("stitched".to_string(), json!(true));
+            metadata.insert("streamResumes".to_string(), json!(stream_resumes));
+        }
+        if !turn_retries.is_empty() {
+            metadata.insert("turnRetries".to_string(), json!(turn_retries));
+        }
+        if !metadata.is_empty() {
+            assistant.metadata = Some(Value::Object(metadata));
+        }
         let assistant_message_id = assistant.id.clone();
         self.storage.append_message(&session_id, assistant).await?;
         let final_part = WireMessagePart::text(
@@ -806,6 +1269,121 @@ impl EngineLoop {
             .await
     }
 
+    fn publish_text_delta(&self, session_id: &str, message_id: &str, delta: &str) {
+        if delta.is_empty() {
+            return;
+        }
+        let delta = truncate_text(delta, 4_000);
+        let delta_part = WireMessagePart::text(session_id, message_id, delta.clone());
+        self.event_bus.publish(EngineEvent::new(
+            "message.part.updated",
+            json!({"part": delta_part, "delta": delta}),
+        ));
+    }
+
+    /// Stitch a buffered resumed-stream prefix onto `completion`.
+    fn flush_resume_overlap(
+        &self,
+        session_id: &str,
+        message_id: &str,
+        completion: &mut String,
+        pending_overlap: &mut Option<String>,
+    ) {
+        if let Some(buffer) = pending_overlap.take() {
+            let delta = crate::strip_resume_overlap(completion, &buffer);
+            completion.push_str(&delta);
+            self.publish_text_delta(session_id, message_id, &delta);
+        }
+    }
+
+    /// Run the planned calls batch by batch, read-only batches up to
+    /// `parallel_limit` at a time, and return their results in call order.
+    /// Stops after the first batch with a failed call, so no write or shell
+    /// call runs after a failure.
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_tool_batches(
+        &self,
+        session_id: &str,
+        user_message_id: &str,
+        active_agent: &AgentDefinition,
+        text: &str,
+        completion: &str,
+        slots: &[PlannedToolSlot],
+        parallel_limit: usize,
+        cancel: &CancellationToken,
+    ) -> Vec<anyhow::Result<Option<String>>> {
+        let mut results = Vec::with_capacity(slots.len());
+        let parallel_safe = slots
+            .iter()
+            .map(|slot| matches!(slot, PlannedToolSlot::Call(call) if call.parallel_safe))
+            .collect::<Vec<_>>();
+        for batch in crate::plan_tool_batches(&parallel_safe) {
+            if cancel.is_cancelled() {
+                break;
+            }
+            let calls = slots[batch]
+                .iter()
+                .filter_map(|slot| match slot {
+                    PlannedToolSlot::Call(call) => Some(call),
+                    PlannedToolSlot::Output(_) => None,
+                })
+                .collect::<Vec<_>>();
+            if calls.len() > 1 && parallel_limit > 1 {
+                self.event_bus.publish(EngineEvent::new(
+                    "tool.batch.parallel",
+                    json!({
+                        "sessionID": session_id,
+                        "messageID": user_message_id,
+                        "tools": calls.iter().map(|call| call.tool_key.as_str()).collect::<Vec<_>>(),
+                        "concurrency": parallel_limit.min(calls.len()),
+                    }),
+                ));
+            }
+            // Each future owns its inputs; futures borrowing from a closure
+            // argument are not `Send` for every lifetime.
+            let mut pending = Vec::with_capacity(calls.len());
+            for call in calls {
+                let cancel = cancel.clone();
+                let tool = call.tool.clone();
+                let args = call.args.clone();
+                let skills = active_agent.skills.clone();
+                let agent_name = active_agent.name.clone();
+                let session_id = session_id.to_string();
+                let user_message_id = user_message_id.to_string();
+                let text = text.to_string();
+                let completion = completion.to_string();
+                pending.push(async move {
+                    if cancel.is_cancelled() {
+                        return Ok(None);
+                    }
+                    self.execute_tool_with_permission(
+                        &session_id,
+                        &user_message_id,
+                        tool,
+                        args,
+                        &agent_name,
+                        skills.as_deref(),
+                        &text,
+                        Some(&completion),
+                        cancel,
+                    )
+                    .await
+                });
+            }
+            let batch_results = futures::stream::iter(pending)
+                .buffered(parallel_limit)
+                .collect::<Vec<anyhow::Result<Option<String>>>>()
+                .await;
+            let failed = batch_results.iter().any(|result| result.is_err());
+            results.extend(batch_results);
+            // Only read-only calls share a batch.
+            if failed {
+                break;
+            }
+        }
+        results
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn execute_tool_with_permission(
         &self,
@@ -813,6 +1391,7 @@ impl EngineLoop {
         message_id: &str,
         tool: String,
         args: Value,
+        agent_name: &str,
         equipped_skills: Option<&[String]>,
         latest_user_text: &str,
         latest_assistant_context: Option<&str>,
@@ -895,6 +1474,11 @@ impl EngineLoop {
                 return Ok(Some(format!("Tool `{tool}` is not allowed for this run.")));
             }
         }
+        if is_memory_write_tool(&tool) && self.storage.is_incognito(session_id).await {
+            return Ok(Some(
+                "Memory is off for this incognito session; nothing was stored.".to_string(),
+            ));
+        }
         if let Some(hook) = self.tool_policy_hook.read().await.clone() {
             let decision = hook
                 .evaluate_tool(ToolPolicyContext {
@@ -934,6 +1518,34 @@ impl EngineLoop {
             ));
             return Ok(Some(violation));
         }
+        let command_violation = match extract_shell_command(&args) {
+            Some(command) if is_shell_tool_name(&tool) => {
+                let decision = self.tools.evaluate_command(&command).await;
+                (decision.action != CommandAction::Allow).then_some((command, decision))
+            }
+            _ => None,
+        };
+        if let Some((command, decision)) = command_violation
+            .as_ref()
+            .filter(|(_, decision)| decision.action == CommandAction::Deny)
+        {
+            self.publish_command_policy(
+                session_id, message_id, &tool, command, decision, "blocked",
+            );
+            let reason = format!(
+                "Command blocked by the command policy: {}.",
+                decision.reason.as_deref().unwrap_or("matched a deny rule")
+            );
+            let mut blocked_part =
+                WireMessagePart::tool_result(session_id, message_id, tool.clone(), json!(null));
+            blocked_part.state = Some("failed".to_string());
+            blocked_part.error = Some(reason.clone());
+            self.event_bus.publish(EngineEvent::new(
+                "message.part.updated",
+                json!({"part": blocked_part}),
+            ));
+            return Ok(Some(reason));
+        }
         let rule = self
             .plugins
             .permission_override(&tool)
@@ -944,6 +1556,12 @@ impl EngineLoop {
                 "Permission denied for tool `{tool}` by policy."
             )));
         }
+        // Commands the policy asks about need approval even when the tool is allowed.
+        let rule = if command_violation.is_some() {
+            PermissionAction::Ask
+        } else {
+            rule
+        };
 
         let mut effective_args = args.clone();
         if matches!(rule, PermissionAction::Ask) {
@@ -981,6 +1599,12 @@ impl EngineLoop {
                 return Ok(None);
             }
             let approved = matches!(reply.as_deref(), Some("once" | "always" | "allow"));
+            if let Some((command, decision)) = command_violation.as_ref() {
+                let outcome = if approved { "approved" } else { "rejected" };
+                self.publish_command_policy(
+                    session_id, message_id, &tool, command, decision, outcome,
+                );
+            }
             if !approved {
                 let mut denied_part =
                     WireMessagePart::tool_result(session_id, message_id, tool.clone(), json!(null));
@@ -998,6 +1622,29 @@ impl EngineLoop {
             effective_args = args;
         }
 
+        match self
+            .debug_steps
+            .checkpoint(
+                session_id,
+                DebugStepKind::ToolExecution,
+                json!({
+                    "messageID": message_id,
+                    "tool": tool,
+                    "args": effective_args,
+                }),
+                &cancel,
+            )
+            .await
+        {
+            DebugCommand::Continue => {}
+            DebugCommand::Skip => {
+                return Ok(Some(format!("Tool `{tool}` call skipped by debugger.")));
+            }
+            DebugCommand::Abort => {
+                cancel.cancel();
+                return Ok(None);
+            }
+        }
         let mut args = self.plugins.inject_tool_args(&tool, effective_args).await;
         let tool_context = self.resolve_tool_execution_context(session_id).await;
         if let Some((workspace_root, effective_cwd)) = tool_context.as_ref() {
@@ -1014,6 +1661,25 @@ impl EngineLoop {
                     "__session_id".to_string(),
                     Value::String(session_id.to_string()),
                 );
+                obj.insert(
+                    "__message_id".to_string(),
+                    Value::String(message_id.to_string()),
+                );
+                if let Some(run_id) = self.session_run(session_id).await {
+                    obj.insert("__run_id".to_string(), Value::String(run_id));
+                }
+                obj.insert(
+                    "__path_style".to_string(),
+                    Value::String(
+                        path_style_label(self.host_runtime_context.path_style).to_string(),
+                    ),
+                );
+                if is_scoped_search_tool(&tool) {
+                    let touched = self.session_touched_paths(session_id).await;
+                    if !touched.is_empty() {
+                        obj.insert("__recent_paths".to_string(), json!(touched));
+                    }
+                }
             }
             tracing::info!(
                 "tool execution context session_id={} tool={} workspace_root={} effective_cwd={}",
@@ -1033,6 +1699,9 @@ impl EngineLoop {
             "message.part.updated",
             json!({"part": invoke_part}),
         ));
+        if let (Some(call_id), Some(obj)) = (invoke_part_id.as_ref(), args.as_object_mut()) {
+            obj.insert("__tool_call_id".to_string(), Value::String(call_id.clone()));
+        }
         let args_for_side_events = args.clone();
         if tool == "spawn_agent" {
             let hook = self.spawn_agent_hook.read().await.clone();
@@ -1087,11 +1756,21 @@ impl EngineLoop {
             ));
             return Ok(Some(output.to_string()));
         }
-        let result = match self
-            .tools
-            .execute_with_cancel(&tool, args, cancel.clone())
-            .await
-        {
+        let execution = if crate::chaos_inject(crate::ChaosFault::ToolTimeout) {
+            tokio::select! {
+                _ = tokio::time::sleep(crate::chaos_tool_timeout()) => {}
+                _ = cancel.cancelled() => {}
+            }
+            Err(anyhow::anyhow!("chaos: injected timeout for tool `{tool}`"))
+        } else if tool == "tool_result_read" {
+            Ok(self.read_stored_tool_result(session_id, &args).await)
+        } else {
+            // Commands the policy asks about were approved above.
+            self.tools
+                .execute_approved(&tool, args, cancel.clone(), Some(agent_name))
+                .await
+        };
+        let result = match execution {
             Ok(result) => result,
             Err(err) => {
                 let mut failed_part =
@@ -1118,7 +1797,34 @@ impl EngineLoop {
             tool_context.as_ref().map(|ctx| ctx.1.as_str()),
         )
         .await;
+        self.record_touched_paths(session_id, &tool, &args_for_side_events)
+            .await;
+        if tool == "skill" {
+            if let Some(name) = result.metadata.get("name").and_then(|v| v.as_str()) {
+                let now = chrono::Utc::now().timestamp_millis().max(0) as u64;
+                self.skill_last_used
+                    .write()
+                    .await
+                    .insert(name.to_string(), now);
+            }
+        }
+        let timed_out = is_timeout_result(&result);
+        if timed_out {
+            self.event_bus.publish(EngineEvent::new(
+                "tool.timed_out",
+                json!({
+                    "sessionID": session_id,
+                    "messageID": message_id,
+                    "tool": tool,
+                    "agent": agent_name,
+                    "timeoutMs": result.metadata["timeoutMs"],
+                }),
+            ));
+        }
         let output = self.plugins.transform_tool_output(result.output).await;
+        let output = self
+            .summarize_oversized_tool_output(session_id, message_id, &tool, output)
+            .await;
         let output = truncate_text(&output, 16_000);
         let mut result_part = WireMessagePart::tool_result(
             session_id,
@@ -1127,6 +1833,9 @@ impl EngineLoop {
             json!(output.clone()),
         );
         result_part.id = invoke_part_id;
+        if timed_out {
+            result_part.state = Some("timeout".to_string());
+        }
         self.event_bus.publish(EngineEvent::new(
             "message.part.updated",
             json!({"part": result_part}),
@@ -1137,6 +1846,196 @@ impl EngineLoop {
         )))
     }
 
+    /// Rank the skills relevant to `prompt` and render those that fit the
+    /// skill context budget, publishing the selection for the run.
+    async fn select_skill_context(
+        &self,
+        session_id: &str,
+        message_id: &str,
+        agent: &AgentDefinition,
+        prompt: &str,
+    ) -> Option<String> {
+        let policy = crate::SkillContextPolicy::from_env();
+        if policy.budget_tokens == 0 {
+            return None;
+        }
+        let workspace_root = self
+            .resolve_tool_execution_context(session_id)
+            .await
+            .map(|(root, _)| root);
+        let service = crate::skill_service_for(workspace_root.as_deref());
+        let last_used = self.skill_last_used.read().await.clone();
+        let now = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        let (selection, rendered) = crate::build_skill_context(
+            &service,
+            policy,
+            agent.skills.as_deref(),
+            prompt,
+            &last_used,
+            now,
+        )?;
+        {
+            let mut last_used = self.skill_last_used.write().await;
+            for skill in selection
+                .skills
+                .iter()
+                .filter(|s| s.mode == tandem_skills::SkillLoadMode::Full)
+            {
+                last_used.insert(skill.name.clone(), now);
+            }
+        }
+        self.event_bus.publish(EngineEvent::new(
+            "session.run.skills_selected",
+            json!({
+                "sessionID": session_id,
+                "runID": self.session_run(session_id).await,
+                "messageID": message_id,
+                "agent": agent.name,
+                "selection": selection,
+            }),
+        ));
+        Some(rendered)
+    }
+
+    async fn session_touched_paths(&self, session_id: &str) -> Vec<String> {
+        self.session_touched_paths
+            .read()
+            .await
+            .get(session_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    async fn record_touched_paths(&self, session_id: &str, tool: &str, args: &Value) {
+        if !matches!(tool, "read" | "write" | "edit") {
+            return;
+        }
+        let paths = extract_tool_candidate_paths(tool, args);
+        if paths.is_empty() {
+            return;
+        }
+        let mut touched = self.session_touched_paths.write().await;
+        let entry = touched.entry(session_id.to_string()).or_default();
+        for path in paths {
+            entry.retain(|existing| *existing != path);
+            entry.push(path);
+        }
+        let overflow = entry.len().saturating_sub(MAX_SESSION_TOUCHED_PATHS);
+        entry.drain(..overflow);
+    }
+
+    /// Replace an output above the summary threshold with an LLM summary and a
+    /// handle to the stored full result; the model can page through it with
+    /// `tool_result_read`. Incognito sessions get the summary only, so their
+    /// outputs never reach disk.
+    async fn summarize_oversized_tool_output(
+        &self,
+        session_id: &str,
+        message_id: &str,
+        tool: &str,
+        output: String,
+    ) -> String {
+        let threshold = crate::tool_result_summary_threshold();
+        if threshold == 0
+            || tool == "tool_result_read"
+            || crate::estimate_tokens(&output) <= threshold
+        {
+            return output;
+        }
+        let record = if self.storage.is_incognito(session_id).await {
+            None
+        } else {
+            match self
+                .storage
+                .tool_results()
+                .save(session_id, message_id, tool, &output)
+                .await
+            {
+                Ok(record) => Some(record),
+                Err(err) => {
+                    tracing::warn!("failed to store oversized `{tool}` output: {err}");
+                    return output;
+                }
+            }
+        };
+        let prompt = crate::tool_result_summary_prompt(tool, &output);
+        let (summary, summarizer) = match tokio::time::timeout(
+            std::time::Duration::from_secs(45),
+            self.providers.complete_cheapest(&prompt, None, None),
+        )
+        .await
+        {
+            Ok(Ok(summary)) if !summary.trim().is_empty() => (summary, "llm"),
+            Ok(Err(err)) => {
+                tracing::warn!("tool result summarization failed for `{tool}`: {err}");
+                (crate::fallback_tool_result_summary(&output), "preview")
+            }
+            _ => (crate::fallback_tool_result_summary(&output), "preview"),
+        };
+        self.event_bus.publish(EngineEvent::new(
+            "tool.result.summarized",
+            json!({
+                "sessionID": session_id,
+                "messageID": message_id,
+                "tool": tool,
+                "handle": record.as_ref().map(|record| record.handle.as_str()),
+                "totalLines": output.lines().count(),
+                "estimatedTokens": crate::estimate_tokens(&output),
+                "summarizer": summarizer,
+            }),
+        ));
+        match record {
+            Some(record) => crate::render_summarized_tool_result(&record, &summary),
+            None => crate::render_unstored_tool_result(&output, &summary),
+        }
+    }
+
+    async fn read_stored_tool_result(&self, session_id: &str, args: &Value) -> ToolResult {
+        let handle = args
+            .get("handle")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .unwrap_or_default();
+        let store = self.storage.tool_results();
+        let owned = store
+            .get(handle)
+            .await
+            .is_some_and(|record| record.session_id == session_id);
+        let range = if owned {
+            let start_line = args.get("startLine").and_then(Value::as_u64).unwrap_or(1);
+            let end_line = args.get("endLine").and_then(Value::as_u64);
+            store
+                .read_range(handle, start_line as usize, end_line.map(|n| n as usize))
+                .await
+        } else {
+            None
+        };
+        let Some(range) = range else {
+            return ToolResult {
+                output: format!("No stored tool result with handle `{handle}` in this session."),
+                metadata: json!({"ok": false, "code": "TOOL_RESULT_NOT_FOUND"}),
+            };
+        };
+        let mut output = format!(
+            "[{} lines {}-{} of {}]\n{}",
+            range.handle, range.start_line, range.end_line, range.total_lines, range.text
+        );
+        if range.truncated {
+            output.push_str(&format!("… continue with startLine={}", range.end_line + 1));
+        }
+        ToolResult {
+            output,
+            metadata: json!({
+                "ok": true,
+                "handle": range.handle,
+                "startLine": range.start_line,
+                "endLine": range.end_line,
+                "totalLines": range.total_lines,
+                "truncated": range.truncated,
+            }),
+        }
+    }
+
     async fn find_recent_matching_user_message_id(
         &self,
         session_id: &str,
@@ -1194,6 +2093,30 @@ impl EngineLoop {
         let _ = self.storage.save_session(session).await;
     }
 
+    /// Publish `tool.command_policy` for a command the policy denied or asked
+    /// about; the server records these in its audit log.
+    fn publish_command_policy(
+        &self,
+        session_id: &str,
+        message_id: &str,
+        tool: &str,
+        command: &str,
+        decision: &CommandDecision,
+        outcome: &str,
+    ) {
+        self.event_bus.publish(EngineEvent::new(
+            "tool.command_policy",
+            json!({
+                "sessionID": session_id,
+                "messageID": message_id,
+                "tool": tool,
+                "command": command,
+                "decision": decision,
+                "outcome": outcome,
+            }),
+        ));
+    }
+
     async fn workspace_sandbox_violation(
         &self,
         session_id: &str,
@@ -1228,7 +2151,7 @@ impl EngineLoop {
             } else {
                 workspace_path.join(raw)
             };
-            is_sensitive_path_candidate(&resolved)
+            crate::is_sensitive_path(&resolved)
         }) {
             return Some(format!(
                 "Sandbox blocked `{tool}` path `{sensitive}` (sensitive path policy)."
@@ -1305,6 +2228,10 @@ impl EngineLoop {
                 summarize_tool_outputs(tool_outputs)
             ),
         });
+        let _lane = self
+            .provider_lanes
+            .acquire(self.session_priority(session_id).await)
+            .await;
         let stream = self
             .providers
             .stream_for_provider(provider_hint, model_id, messages, None, cancel.clone())
@@ -1334,6 +2261,7 @@ impl EngineLoop {
 
 fn resolve_model_route(
     request_model: Option<&ModelSpec>,
+    preset_route: Option<(String, String)>,
     session_model: Option<&ModelSpec>,
 ) -> Option<(String, String)> {
     fn normalize(spec: &ModelSpec) -> Option<(String, String)> {
@@ -1347,6 +2275,7 @@ fn resolve_model_route(
 
     request_model
         .and_then(normalize)
+        .or(preset_route)
         .or_else(|| session_model.and_then(normalize))
 }
 
@@ -1421,6 +2350,7 @@ fn normalize_tool_name(name: &str) -> String {
     match normalized.as_str() {
         "todowrite" | "update_todo_list" | "update_todos" => "todo_write".to_string(),
         "run_command" | "shell" | "powershell" | "cmd" => "bash".to_string(),
+        "calculator" | "calculate" | "eval" => "calc".to_string(),
         other => other.to_string(),
     }
 }
@@ -1506,6 +2436,15 @@ fn enforce_skill_scope(
     Ok(out)
 }
 
+/// Search tools that accept a package `scope` and default it from
+/// `__recent_paths`.
+fn is_scoped_search_tool(tool_name: &str) -> bool {
+    matches!(
+        normalize_tool_name(tool_name).as_str(),
+        "glob" | "grep" | "codesearch"
+    )
+}
+
 fn is_read_only_tool(tool_name: &str) -> bool {
     matches!(
         normalize_tool_name(tool_name).as_str(),
@@ -1520,6 +2459,10 @@ fn is_read_only_tool(tool_name: &str) -> bool {
             | "websearch"
             | "webfetch"
             | "webfetch_html"
+            | "tool_result_read"
+            | "secrets_scan"
+            | "workspace_changes"
+            | "calc"
     )
 }
 
@@ -1635,41 +2578,6 @@ fn tool_budget_for(tool_name: &str) -> usize {
     }
 }
 
-fn is_sensitive_path_candidate(path: &Path) -> bool {
-    let lowered = path.to_string_lossy().to_ascii_lowercase();
-    if lowered.contains("/.ssh/")
-        || lowered.ends_with("/.ssh")
-        || lowered.contains("/.gnupg/")
-        || lowered.ends_with("/.gnupg")
-    {
-        return true;
-    }
-    if lowered.contains("/.aws/credentials")
-        || lowered.ends_with("/.npmrc")
-        || lowered.ends_with("/.netrc")
-        || lowered.ends_with("/.pypirc")
-    {
-        return true;
-    }
-    if lowered.contains("id_rsa")
-        || lowered.contains("id_ed25519")
-        || lowered.contains("id_ecdsa")
-        || lowered.contains(".pem")
-        || lowered.contains(".p12")
-        || lowered.contains(".pfx")
-        || lowered.contains(".key")
-    {
-        return true;
-    }
-    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-        let n = name.to_ascii_lowercase();
-        if n == ".env" || n.starts_with(".env.") {
-            return true;
-        }
-    }
-    false
-}
-
 fn shell_command_targets_sensitive_path(command: &str) -> bool {
     let lower = command.to_ascii_lowercase();
     let patterns = [
@@ -1805,6 +2713,10 @@ fn normalize_tool_args(
     }
 }
 
+fn is_memory_write_tool(tool_name: &str) -> bool {
+    tool_name.trim().eq_ignore_ascii_case("memory_store")
+}
+
 fn is_shell_tool_name(tool_name: &str) -> bool {
     matches!(
         tool_name.trim().to_ascii_lowercase().as_str(),
@@ -2565,7 +3477,9 @@ fn tandem_runtime_system_prompt(host: &HostRuntimeContext) -> String {
         "You are operating inside Tandem (Desktop/TUI) as an engine-backed coding assistant.
 Use tool calls to inspect and modify the workspace when needed instead of asking the user
 to manually run basic discovery steps. Permission prompts may occur for some tools; if
-a tool is denied or blocked, explain what was blocked and suggest a concrete next step."
+a tool is denied or blocked, explain what was blocked and suggest a concrete next step.
+For arithmetic, date math and unit conversions, call the `calc` tool rather than working
+out the result yourself."
             .to_string(),
     );
     if host.os == HostOs::Windows {
@@ -3836,6 +4750,32 @@ Call: todowrite(task_id=3, status="in_progress")
         );
     }
 
+    #[test]
+    fn model_route_prefers_request_then_preset_then_session() {
+        let spec = |provider: &str, model: &str| ModelSpec {
+            provider_id: provider.to_string(),
+            model_id: model.to_string(),
+        };
+        let preset = Some(("groq".to_string(), "llama".to_string()));
+        let session = spec("openai", "gpt-4o");
+        assert_eq!(
+            resolve_model_route(
+                Some(&spec("anthropic", "opus")),
+                preset.clone(),
+                Some(&session)
+            ),
+            Some(("anthropic".to_string(), "opus".to_string()))
+        );
+        assert_eq!(
+            resolve_model_route(Some(&spec(" ", "")), preset.clone(), Some(&session)),
+            preset
+        );
+        assert_eq!(
+            resolve_model_route(None, None, Some(&session)),
+            Some(("openai".to_string(), "gpt-4o".to_string()))
+        );
+    }
+
     #[test]
     fn normalizes_todo_write_tasks_alias() {
         let normalized = normalize_todo_write_args(
@@ -4235,6 +5175,7 @@ Call: todowrite(task_id=3, status="in_progress")
     fn normalize_tool_name_strips_default_api_namespace() {
         assert_eq!(normalize_tool_name("default_api:read"), "read");
         assert_eq!(normalize_tool_name("functions.shell"), "bash");
+        assert_eq!(normalize_tool_name("functions.calculator"), "calc");
     }
 
     #[test]
@@ -4292,4 +5233,113 @@ Call: todowrite(task_id=3, status="in_progress")
         assert!(prompt.contains("Shell: powershell"));
         assert!(prompt.contains("Path style: windows"));
     }
+
+    struct RecordingPolicy {
+        evaluated: std::sync::Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl ToolPolicyHook for RecordingPolicy {
+        fn evaluate_tool(
+            &self,
+            ctx: ToolPolicyContext,
+        ) -> BoxFuture<'static, anyhow::Result<ToolPolicyDecision>> {
+            self.evaluated.lock().expect("evaluated").push(format!(
+                "{}:{}",
+                ctx.tool,
+                ctx.args["path"].as_str().unwrap_or("")
+            ));
+            Box::pin(async move {
+                if ctx.args["path"] == "broken.rs" {
+                    anyhow::bail!("policy backend unavailable");
+                }
+                Ok(ToolPolicyDecision {
+                    allowed: false,
+                    reason: Some("denied in test".to_string()),
+                })
+            })
+        }
+    }
+
+    fn planned_call(tool: &str, path: &str) -> PlannedToolSlot {
+        PlannedToolSlot::Call(PlannedToolCall {
+            tool: tool.to_string(),
+            tool_key: tool.to_string(),
+            args: json!({"path": path, "content": "x"}),
+            signature: format!("{tool}:{path}"),
+            signature_count: 1,
+            parallel_safe: is_read_only_tool(tool),
+        })
+    }
+
+    #[tokio::test]
+    async fn failed_parallel_batch_stops_later_calls() {
+        let base = std::env::temp_dir().join(format!("engine-loop-test-{}", Uuid::new_v4()));
+        let storage = std::sync::Arc::new(Storage::new(&base).await.expect("storage"));
+        let bus = EventBus::new();
+        let engine = EngineLoop::new(
+            storage,
+            bus.clone(),
+            ProviderRegistry::new(tandem_providers::AppConfig::default()),
+            PluginRegistry::new(&base).await.expect("plugins"),
+            AgentRegistry::new(&base).await.expect("agents"),
+            PermissionManager::new(bus),
+            ToolRegistry::new(),
+            CancellationRegistry::new(),
+            HostRuntimeContext {
+                os: HostOs::Linux,
+                arch: "x86_64".to_string(),
+                shell_family: ShellFamily::Posix,
+                path_style: PathStyle::Posix,
+            },
+        );
+        let evaluated = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        engine
+            .set_tool_policy_hook(std::sync::Arc::new(RecordingPolicy {
+                evaluated: evaluated.clone(),
+            }))
+            .await;
+        let agent = AgentDefinition {
+            name: "build".to_string(),
+            mode: crate::AgentMode::Primary,
+            hidden: false,
+            system_prompt: None,
+            tools: None,
+            skills: None,
+            preset: None,
+        };
+        let slots = vec![
+            planned_call("read", "ok.rs"),
+            planned_call("read", "broken.rs"),
+            planned_call("write", "out.rs"),
+        ];
+
+        let results = engine
+            .execute_tool_batches(
+                "s1",
+                "m1",
+                &agent,
+                "read both files",
+                "",
+                &slots,
+                4,
+                &CancellationToken::new(),
+            )
+            .await;
+
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
+        let evaluated = evaluated.lock().expect("evaluated").clone();
+        assert_eq!(evaluated.len(), 2);
+        assert!(evaluated.iter().all(|call| call.starts_with("read:")));
+    }
+
+    #[test]
+    fn run_prompt_future_is_send() {
+        fn assert_send<T: Send>(_: &T) {}
+        let _ = |engine: EngineLoop, request: SendMessageRequest| {
+            let future = engine.run_prompt_async("s1".to_string(), request);
+            assert_send(&future);
+        };
+    }
 }
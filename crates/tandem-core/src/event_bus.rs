@@ -17,7 +17,19 @@ impl EventBus {
         self.tx.subscribe()
     }
 
-    pub fn publish(&self, event: EngineEvent) {
+    pub fn publish(&self, mut event: EngineEvent) {
+        crate::stamp_request_id(&mut event);
+        if crate::chaos_inject(crate::ChaosFault::EventLag) {
+            if let Ok(handle) = tokio::runtime::Handle::try_current() {
+                let tx = self.tx.clone();
+                let lag = crate::chaos_event_lag();
+                handle.spawn(async move {
+                    tokio::time::sleep(lag).await;
+                    let _ = tx.send(event);
+                });
+                return;
+            }
+        }
         let _ = self.tx.send(event);
     }
 }
@@ -0,0 +1,196 @@
+//! Feature flags for experimental subsystems.
+//!
+//! Flags are declared in [`FEATURE_FLAGS`] with a default and read from the
+//! `feature_flags` config block:
+//!
+//! ```json
+//! {
+//!   "feature_flags": {
+//!     "flags": { "global_memory": true },
+//!     "tenants": { "acme": { "browser_tool": true } }
+//!   }
+//! }
+//! ```
+//!
+//! A tenant override wins over the server-wide value, which wins over the
+//! default. [`ConfigStore::set_feature_flag`] toggles a flag while the engine
+//! runs: in the runtime layer, or also in the project config when persisted.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::ConfigStore;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct FeatureFlagDefinition {
+    pub key: &'static str,
+    pub description: &'static str,
+    pub default: bool,
+}
+
+pub const FEATURE_FLAGS: &[FeatureFlagDefinition] = &[
+    FeatureFlagDefinition {
+        key: "browser_tool",
+        description: "Browser automation tool for agents",
+        default: false,
+    },
+    FeatureFlagDefinition {
+        key: "global_memory",
+        description: "Memory tools may read and write the global tier without allow_global",
+        default: false,
+    },
+    FeatureFlagDefinition {
+        key: "autonomous_loop",
+        description: "Agents may keep working in a loop without a new prompt",
+        default: false,
+    },
+];
+
+pub fn feature_flag(key: &str) -> Option<&'static FeatureFlagDefinition> {
+    FEATURE_FLAGS.iter().find(|flag| flag.key == key)
+}
+
+/// The `feature_flags` config block.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeatureFlagsConfig {
+    #[serde(default)]
+    pub flags: BTreeMap<String, bool>,
+    #[serde(default)]
+    pub tenants: BTreeMap<String, BTreeMap<String, bool>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureFlagSource {
+    Default,
+    Config,
+    Tenant,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureFlagState {
+    pub key: &'static str,
+    pub description: &'static str,
+    pub enabled: bool,
+    pub default: bool,
+    pub source: FeatureFlagSource,
+}
+
+impl FeatureFlagsConfig {
+    pub fn state(&self, flag: &FeatureFlagDefinition, tenant: Option<&str>) -> FeatureFlagState {
+        let tenant_value = tenant
+            .and_then(|tenant| self.tenants.get(tenant))
+            .and_then(|flags| flags.get(flag.key))
+            .copied();
+        let (enabled, source) = match (tenant_value, self.flags.get(flag.key)) {
+            (Some(enabled), _) => (enabled, FeatureFlagSource::Tenant),
+            (None, Some(enabled)) => (*enabled, FeatureFlagSource::Config),
+            (None, None) => (flag.default, FeatureFlagSource::Default),
+        };
+        FeatureFlagState {
+            key: flag.key,
+            description: flag.description,
+            enabled,
+            default: flag.default,
+            source,
+        }
+    }
+
+    /// Every declared flag as seen by `tenant` (server-wide when `None`).
+    pub fn states(&self, tenant: Option<&str>) -> Vec<FeatureFlagState> {
+        FEATURE_FLAGS
+            .iter()
+            .map(|flag| self.state(flag, tenant))
+            .collect()
+    }
+
+    /// Unknown keys are off.
+    pub fn is_enabled(&self, key: &str, tenant: Option<&str>) -> bool {
+        feature_flag(key).is_some_and(|flag| self.state(flag, tenant).enabled)
+    }
+
+    /// Flag keys that are set in config but not declared.
+    pub fn unknown_keys(&self) -> Vec<&str> {
+        self.flags
+            .keys()
+            .chain(self.tenants.values().flat_map(|flags| flags.keys()))
+            .map(String::as_str)
+            .filter(|key| feature_flag(key).is_none())
+            .collect()
+    }
+}
+
+impl ConfigStore {
+    pub async fn feature_flags(&self) -> FeatureFlagsConfig {
+        self.get_effective_value()
+            .await
+            .get("feature_flags")
+            .cloned()
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    }
+
+    pub async fn feature_flag_enabled(&self, key: &str, tenant: Option<&str>) -> bool {
+        self.feature_flags().await.is_enabled(key, tenant)
+    }
+
+    /// Turn a declared flag on or off, server-wide or for one tenant. The
+    /// change applies at once; with `persist` it is also written to the
+    /// project config so it survives a restart.
+    pub async fn set_feature_flag(
+        &self,
+        key: &str,
+        enabled: bool,
+        tenant: Option<&str>,
+        persist: bool,
+    ) -> anyhow::Result<FeatureFlagState> {
+        let Some(flag) = feature_flag(key) else {
+            anyhow::bail!("unknown feature flag `{key}`");
+        };
+        let value = json!({ key: enabled });
+        let patch = match tenant {
+            Some(tenant) => json!({ "feature_flags": { "tenants": { tenant: value } } }),
+            None => json!({ "feature_flags": { "flags": value } }),
+        };
+        if persist {
+            self.patch_project(patch.clone()).await?;
+        }
+        self.patch_runtime(patch).await?;
+        Ok(self.feature_flags().await.state(flag, tenant))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tenant_override_wins_over_server_value_and_default() {
+        let config: FeatureFlagsConfig = serde_json::from_value(json!({
+            "flags": { "global_memory": true },
+            "tenants": { "acme": { "global_memory": false, "warp_drive": true } },
+        }))
+        .expect("config");
+        assert!(config.is_enabled("global_memory", None));
+        assert!(config.is_enabled("global_memory", Some("other")));
+        assert!(!config.is_enabled("global_memory", Some("acme")));
+        assert!(!config.is_enabled("browser_tool", Some("acme")));
+        assert!(!config.is_enabled("warp_drive", Some("acme")));
+
+        let flag = feature_flag("global_memory").expect("declared");
+        assert_eq!(config.state(flag, None).source, FeatureFlagSource::Config);
+        assert_eq!(
+            config.state(flag, Some("acme")).source,
+            FeatureFlagSource::Tenant
+        );
+        let browser = feature_flag("browser_tool").expect("declared");
+        assert_eq!(
+            config.state(browser, None).source,
+            FeatureFlagSource::Default
+        );
+        assert_eq!(config.unknown_keys(), vec!["warp_drive"]);
+    }
+}
@@ -1,29 +1,55 @@
 pub mod agents;
+pub mod attachments;
 pub mod cancellation;
+pub mod chaos;
 pub mod config;
+pub mod debug_steps;
 pub mod engine_api_token;
 pub mod engine_loop;
 pub mod event_bus;
+pub mod feature_flags;
 pub mod hooks;
+pub mod loop_watchdog;
+pub mod parallel_tools;
 pub mod permission_defaults;
 pub mod permissions;
 pub mod plugins;
+pub mod request_context;
+pub mod run_pause;
+pub mod run_priority;
 pub mod session_title;
+pub mod skill_context;
 pub mod storage;
 pub mod storage_paths;
+pub mod stream_resume;
+pub mod tool_results;
+pub mod turn_retry;
 
 pub const DEFAULT_ENGINE_HOST: &str = "127.0.0.1";
 pub const DEFAULT_ENGINE_PORT: u16 = 39731;
 
 pub use agents::*;
+pub use attachments::*;
 pub use cancellation::*;
+pub use chaos::*;
 pub use config::*;
+pub use debug_steps::*;
 pub use engine_api_token::*;
 pub use engine_loop::*;
 pub use event_bus::*;
+pub use feature_flags::*;
+pub use loop_watchdog::*;
+pub use parallel_tools::*;
 pub use permission_defaults::*;
 pub use permissions::*;
 pub use plugins::*;
+pub use request_context::*;
+pub use run_pause::*;
+pub use run_priority::*;
 pub use session_title::*;
+pub use skill_context::*;
 pub use storage::*;
 pub use storage_paths::*;
+pub use stream_resume::*;
+pub use tool_results::*;
+pub use turn_retry::*;
@@ -0,0 +1,98 @@
+//! Detects a model calling the same tool with the same arguments over and
+//! over, back to back, within one run.
+
+const DEFAULT_LOOP_REPEATS: usize = 3;
+
+/// Tracks how many consecutive tool calls shared the same signature hash.
+#[derive(Debug, Clone)]
+pub struct ToolLoopWatchdog {
+    last: Option<String>,
+    consecutive: usize,
+    max_repeats: usize,
+}
+
+impl Default for ToolLoopWatchdog {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOOP_REPEATS)
+    }
+}
+
+impl ToolLoopWatchdog {
+    pub fn new(max_repeats: usize) -> Self {
+        Self {
+            last: None,
+            consecutive: 0,
+            max_repeats,
+        }
+    }
+
+    /// Watchdog configured by `TANDEM_TOOL_LOOP_REPEATS` (0 disables
+    /// detection).
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("TANDEM_TOOL_LOOP_REPEATS")
+                .ok()
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .unwrap_or(DEFAULT_LOOP_REPEATS),
+        )
+    }
+
+    pub fn max_repeats(&self) -> usize {
+        self.max_repeats
+    }
+
+    /// Record a call and return how many calls in a row, including this one,
+    /// had its signature. A different call resets the count.
+    pub fn record(&mut self, signature_hash: &str) -> usize {
+        if self.last.as_deref() == Some(signature_hash) {
+            self.consecutive += 1;
+        } else {
+            self.last = Some(signature_hash.to_string());
+            self.consecutive = 1;
+        }
+        self.consecutive
+    }
+
+    pub fn is_loop(&self, repeats: usize) -> bool {
+        self.max_repeats > 0 && repeats >= self.max_repeats
+    }
+
+    pub fn reset(&mut self) {
+        self.last = None;
+        self.consecutive = 0;
+    }
+}
+
+/// Message injected after a loop is broken so the model changes course.
+pub fn loop_corrective_prompt(tool: &str, repeats: usize) -> String {
+    format!(
+        "You called `{tool}` with identical arguments {repeats} times in a row and the calls were stopped. \
+Repeating it will not produce new information. Use the results you already have, try a different tool or different arguments, \
+or give your final answer and state what is still unknown."
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_consecutive_repeats_only() {
+        let mut watchdog = ToolLoopWatchdog::new(3);
+        assert_eq!(watchdog.record("a"), 1);
+        assert_eq!(watchdog.record("a"), 2);
+        let repeats = watchdog.record("a");
+        assert_eq!(repeats, 3);
+        assert!(watchdog.is_loop(repeats));
+
+        let mut interleaved = ToolLoopWatchdog::new(3);
+        for hash in ["a", "a", "b", "a", "a", "b", "a"] {
+            let repeats = interleaved.record(hash);
+            assert!(!interleaved.is_loop(repeats));
+        }
+
+        watchdog.reset();
+        assert_eq!(watchdog.record("a"), 1);
+        assert!(!ToolLoopWatchdog::new(0).is_loop(10));
+    }
+}
@@ -0,0 +1,89 @@
+//! Grouping of the tool calls from one assistant turn into batches that may
+//! run concurrently.
+//!
+//! Only calls marked parallel-safe (read-only tools) share a batch; any other
+//! call is a barrier and runs on its own, so a write never races the reads
+//! the model issued around it. Batches run in order and results are reported
+//! back to the model in the order the calls were made.
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const DEFAULT_PARALLEL_TOOL_CALLS: usize = 4;
+const MAX_PARALLEL_TOOL_CALLS: usize = 16;
+
+/// Concurrency limit for one turn's tool calls, from
+/// `TANDEM_PARALLEL_TOOL_CALLS` (0 or 1 keeps execution serial).
+pub fn parallel_tool_limit_from_env() -> usize {
+    std::env::var("TANDEM_PARALLEL_TOOL_CALLS")
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_PARALLEL_TOOL_CALLS)
+        .clamp(1, MAX_PARALLEL_TOOL_CALLS)
+}
+
+/// Cap set by the host resource monitor while the machine is under pressure;
+/// 0 means no cap.
+static HOST_TOOL_PARALLELISM_CAP: AtomicUsize = AtomicUsize::new(0);
+
+/// Cap every turn's tool concurrency at `cap`, or lift the cap with `None`.
+pub fn set_host_tool_parallelism_cap(cap: Option<usize>) {
+    let cap = cap.map(|cap| cap.max(1)).unwrap_or(0);
+    HOST_TOOL_PARALLELISM_CAP.store(cap, Ordering::Relaxed);
+}
+
+pub fn host_tool_parallelism_cap() -> Option<usize> {
+    match HOST_TOOL_PARALLELISM_CAP.load(Ordering::Relaxed) {
+        0 => None,
+        cap => Some(cap),
+    }
+}
+
+/// `limit` lowered to the host cap, if one is set.
+pub fn capped_parallel_tool_limit(limit: usize) -> usize {
+    host_tool_parallelism_cap().map_or(limit, |cap| limit.min(cap))
+}
+
+/// Split calls into consecutive index ranges. Runs of parallel-safe calls
+/// form one range; every other call gets a range of its own.
+pub fn plan_tool_batches(parallel_safe: &[bool]) -> Vec<Range<usize>> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    while start < parallel_safe.len() {
+        let mut end = start + 1;
+        if parallel_safe[start] {
+            while end < parallel_safe.len() && parallel_safe[end] {
+                end += 1;
+            }
+        }
+        batches.push(start..end);
+        start = end;
+    }
+    batches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsafe_calls_are_barriers() {
+        assert_eq!(
+            plan_tool_batches(&[true, true, false, true, false, false, true]),
+            vec![0..2, 2..3, 3..4, 4..5, 5..6, 6..7]
+        );
+        assert_eq!(plan_tool_batches(&[true, true, true]), vec![0..3]);
+        assert!(plan_tool_batches(&[]).is_empty());
+    }
+
+    #[test]
+    fn host_cap_lowers_but_never_raises_the_limit() {
+        set_host_tool_parallelism_cap(Some(2));
+        assert_eq!(capped_parallel_tool_limit(4), 2);
+        assert_eq!(capped_parallel_tool_limit(1), 1);
+        set_host_tool_parallelism_cap(Some(0));
+        assert_eq!(host_tool_parallelism_cap(), Some(1));
+        set_host_tool_parallelism_cap(None);
+        assert_eq!(capped_parallel_tool_limit(4), 4);
+    }
+}
@@ -52,6 +52,14 @@ pub fn build_mode_permission_rules(
         });
     }
 
+    if allows_any(allowed_tools, &["tool_result_read"]) {
+        rules.push(PermissionRuleTemplate {
+            permission: "tool_result_read".to_string(),
+            pattern: "*".to_string(),
+            action: "allow".to_string(),
+        });
+    }
+
     if allows_any(
         allowed_tools,
         &["todowrite", "todo_write", "new_task", "update_todo_list"],
@@ -0,0 +1,96 @@
+//! Request id of the HTTP request a task is working for.
+//!
+//! The server scopes each request (and any run or routine it starts) with
+//! [`with_request_id`]; [`EventBus::publish`](crate::EventBus::publish) then
+//! stamps the id onto every event emitted inside that scope as the event's
+//! top-level `correlationID`, so a failure can be traced from the request through
+//! the engine loop and provider calls. Task-locals don't follow
+//! `tokio::spawn`, so work spawned for a request goes through
+//! [`spawn_in_request`].
+
+use std::future::Future;
+
+use tandem_types::EngineEvent;
+use tokio::task::JoinHandle;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Run `fut` with `request_id` as the current request id.
+pub async fn with_request_id<F: Future>(request_id: impl Into<String>, fut: F) -> F::Output {
+    REQUEST_ID.scope(request_id.into(), fut).await
+}
+
+/// Run `fut` under `request_id` when there is one.
+pub async fn with_optional_request_id<F: Future>(request_id: Option<String>, fut: F) -> F::Output {
+    match request_id {
+        Some(request_id) => with_request_id(request_id, fut).await,
+        None => fut.await,
+    }
+}
+
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Spawn `fut` under the current request id, if any.
+pub fn spawn_in_request<F>(fut: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    tokio::spawn(with_optional_request_id(current_request_id(), fut))
+}
+
+/// Tag an event that doesn't carry a request id yet with the current one.
+pub fn stamp_request_id(event: &mut EngineEvent) {
+    if event.correlation_id.is_none() {
+        event.correlation_id = current_request_id();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn stamps_events_only_inside_a_request_scope() {
+        let mut outside = EngineEvent::new("x", json!({"sessionID": "s1"}));
+        stamp_request_id(&mut outside);
+        assert!(outside.correlation_id.is_none());
+
+        let (inside, explicit) = with_request_id("req-1", async {
+            let mut inside = EngineEvent::new("x", json!({"sessionID": "s1"}));
+            stamp_request_id(&mut inside);
+            let mut explicit = EngineEvent::new("x", json!({}));
+            explicit.correlation_id = Some("req-0".to_string());
+            stamp_request_id(&mut explicit);
+            (inside, explicit)
+        })
+        .await;
+        assert_eq!(inside.correlation_id.as_deref(), Some("req-1"));
+        assert_eq!(inside.properties, json!({"sessionID": "s1"}));
+        assert_eq!(explicit.correlation_id.as_deref(), Some("req-0"));
+        assert_eq!(
+            with_optional_request_id(None, async { current_request_id() }).await,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn spawned_work_keeps_the_request_id() {
+        let spawned = with_request_id("req-2", async {
+            spawn_in_request(async { current_request_id() })
+                .await
+                .expect("join")
+        })
+        .await;
+        assert_eq!(spawned.as_deref(), Some("req-2"));
+        let detached = spawn_in_request(async { current_request_id() })
+            .await
+            .expect("join");
+        assert_eq!(detached, None);
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::{Notify, RwLock};
+use tokio_util::sync::CancellationToken;
+
+use tandem_types::EngineEvent;
+
+use crate::{EventBus, Storage};
+
+/// A pause on a session's run, kept in the session metadata so it survives a
+/// restart.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RunPause {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub requested_at_ms: u64,
+    /// When the run reached a boundary and stopped; unset while the request
+    /// is still waiting for one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub paused_at_ms: Option<u64>,
+}
+
+/// Outcome of [`RunPauseController::resume`].
+#[derive(Debug, Clone)]
+pub struct ResumedPause {
+    pub pause: RunPause,
+    /// Whether a run in this process was waiting on the pause. When it was
+    /// not (the engine restarted while paused), the caller starts a new run.
+    pub run_waiting: bool,
+}
+
+/// Pause and resume for session runs. A requested pause takes effect at the
+/// next safe boundary, before the engine loop's next provider call; the run
+/// then waits until it is resumed or cancelled.
+#[derive(Clone)]
+pub struct RunPauseController {
+    storage: Arc<Storage>,
+    event_bus: EventBus,
+    waiting: Arc<RwLock<HashMap<String, Arc<Notify>>>>,
+}
+
+impl RunPauseController {
+    pub fn new(storage: Arc<Storage>, event_bus: EventBus) -> Self {
+        Self {
+            storage,
+            event_bus,
+            waiting: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub async fn get(&self, session_id: &str) -> Option<RunPause> {
+        self.storage.run_pause(session_id).await
+    }
+
+    /// Whether a run in this process is stopped at a boundary.
+    pub async fn is_waiting(&self, session_id: &str) -> bool {
+        self.waiting.read().await.contains_key(session_id)
+    }
+
+    /// Ask the session's run to pause. An existing pause is returned as is.
+    pub async fn request(
+        &self,
+        session_id: &str,
+        reason: Option<String>,
+    ) -> anyhow::Result<RunPause> {
+        if let Some(existing) = self.get(session_id).await {
+            return Ok(existing);
+        }
+        let pause = RunPause {
+            reason,
+            requested_at_ms: crate::storage::now_ms_u64(),
+            paused_at_ms: None,
+        };
+        self.storage
+            .set_run_pause(session_id, Some(pause.clone()))
+            .await?;
+        self.event_bus.publish(EngineEvent::new(
+            "session.run.pause_requested",
+            json!({
+                "sessionID": session_id,
+                "reason": pause.reason,
+                "requestedAtMs": pause.requested_at_ms,
+            }),
+        ));
+        Ok(pause)
+    }
+
+    /// Lift the session's pause and wake its run if one is waiting.
+    pub async fn resume(&self, session_id: &str) -> anyhow::Result<Option<ResumedPause>> {
+        // Holding `waiting` keeps a checkpoint from pausing on the record
+        // while it is being cleared.
+        let mut waiting = self.waiting.write().await;
+        let Some(pause) = self.get(session_id).await else {
+            return Ok(None);
+        };
+        self.storage.set_run_pause(session_id, None).await?;
+        let waiter = waiting.remove(session_id);
+        drop(waiting);
+        let run_waiting = waiter.is_some();
+        if let Some(waiter) = waiter {
+            waiter.notify_one();
+        }
+        self.event_bus.publish(EngineEvent::new(
+            "session.run.resumed",
+            json!({
+                "sessionID": session_id,
+                "reason": pause.reason,
+                "pausedAtMs": pause.paused_at_ms,
+                "resumedAtMs": crate::storage::now_ms_u64(),
+                "runWaiting": run_waiting,
+            }),
+        ));
+        Ok(Some(ResumedPause { pause, run_waiting }))
+    }
+
+    /// Drop a request the run finished before reaching, so it does not
+    /// pause the session's next run.
+    pub async fn clear_unreached(&self, session_id: &str) {
+        if self
+            .get(session_id)
+            .await
+            .is_some_and(|pause| pause.paused_at_ms.is_none())
+        {
+            let _ = self.storage.set_run_pause(session_id, None).await;
+        }
+    }
+
+    /// Drop requests whose run did not survive a restart. Pauses a run had
+    /// reached are kept for [`resume`](Self::resume).
+    pub async fn clear_unreached_all(&self) {
+        for (session_id, _) in self.storage.run_pauses().await {
+            self.clear_unreached(&session_id).await;
+        }
+    }
+
+    /// Stop here if a pause was requested for the session. Returns `false`
+    /// when the run was cancelled while paused.
+    pub async fn checkpoint(&self, session_id: &str, cancel: &CancellationToken) -> bool {
+        let mut waiting = self.waiting.write().await;
+        let Some(mut pause) = self.get(session_id).await else {
+            return true;
+        };
+        let notify = Arc::new(Notify::new());
+        waiting.insert(session_id.to_string(), notify.clone());
+        pause.paused_at_ms = Some(crate::storage::now_ms_u64());
+        let _ = self
+            .storage
+            .set_run_pause(session_id, Some(pause.clone()))
+            .await;
+        drop(waiting);
+        self.event_bus.publish(EngineEvent::new(
+            "session.run.paused",
+            json!({
+                "sessionID": session_id,
+                "reason": pause.reason,
+                "requestedAtMs": pause.requested_at_ms,
+                "pausedAtMs": pause.paused_at_ms,
+            }),
+        ));
+        tokio::select! {
+            _ = cancel.cancelled() => {
+                self.waiting.write().await.remove(session_id);
+                let _ = self.storage.set_run_pause(session_id, None).await;
+                false
+            }
+            _ = notify.notified() => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn paused_run_waits_for_resume() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let storage = Arc::new(Storage::new(dir.path()).await.expect("storage"));
+        let controller = RunPauseController::new(storage.clone(), EventBus::new());
+        let cancel = CancellationToken::new();
+        assert!(controller.checkpoint("s1", &cancel).await);
+
+        controller
+            .request("s1", Some("cost gate".to_string()))
+            .await
+            .expect("request");
+        let waiter = controller.clone();
+        let handle = tokio::spawn(async move {
+            let cancel = CancellationToken::new();
+            waiter.checkpoint("s1", &cancel).await
+        });
+        while !controller.is_waiting("s1").await {
+            tokio::task::yield_now().await;
+        }
+        let stored = storage.run_pause("s1").await.expect("persisted");
+        assert!(stored.paused_at_ms.is_some());
+
+        let resumed = controller
+            .resume("s1")
+            .await
+            .expect("resume")
+            .expect("pause");
+        assert!(resumed.run_waiting);
+        assert_eq!(resumed.pause.reason.as_deref(), Some("cost gate"));
+        assert!(handle.await.expect("join"));
+        assert!(storage.run_pause("s1").await.is_none());
+
+        controller.request("s1", None).await.expect("request");
+        controller.clear_unreached("s1").await;
+        assert!(controller.get("s1").await.is_none());
+    }
+}
@@ -0,0 +1,304 @@
+//! Priority lanes for provider calls.
+//!
+//! Interactive prompts and background routine runs share the same providers.
+//! Provider calls take a slot from [`ProviderLanes`]; when a concurrency limit
+//! is configured and slots are scarce, interactive callers are admitted first.
+//! A routine caller that has waited longer than the starvation window is
+//! treated as interactive so it cannot be starved indefinitely. Without a
+//! limit every call is admitted immediately.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+const DEFAULT_STARVATION_MS: u64 = 30_000;
+const WAIT_RECHECK: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum RunPriority {
+    /// A user is waiting on the response.
+    #[default]
+    Interactive,
+    /// Scheduled or background work.
+    Routine,
+}
+
+impl RunPriority {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Interactive => "interactive",
+            Self::Routine => "routine",
+        }
+    }
+}
+
+/// How long lower-priority work may be held back
+/// (`TANDEM_PRIORITY_STARVATION_MS`).
+pub fn priority_starvation_window() -> Duration {
+    Duration::from_millis(
+        std::env::var("TANDEM_PRIORITY_STARVATION_MS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(DEFAULT_STARVATION_MS),
+    )
+}
+
+/// Concurrent provider calls allowed before callers queue by priority
+/// (`TANDEM_PROVIDER_CONCURRENCY`); unset or `0` means no limit.
+fn provider_concurrency() -> Option<usize> {
+    std::env::var("TANDEM_PROVIDER_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|v| *v > 0)
+}
+
+/// The `provider_lanes` config block.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ProviderLanesConfig {
+    /// Overrides `TANDEM_PROVIDER_CONCURRENCY`; `0` removes the limit.
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+}
+
+struct Waiter {
+    ticket: u64,
+    priority: RunPriority,
+    enqueued_at: Instant,
+}
+
+struct LaneState {
+    limit: Option<usize>,
+    in_flight: usize,
+    next_ticket: u64,
+    waiters: Vec<Waiter>,
+}
+
+impl LaneState {
+    /// Ticket that should be admitted next: interactive (or starved) waiters
+    /// first, then arrival order.
+    fn head(&self, starvation: Duration) -> Option<u64> {
+        self.waiters
+            .iter()
+            .min_by_key(|w| {
+                let outranked =
+                    w.priority == RunPriority::Routine && w.enqueued_at.elapsed() < starvation;
+                (outranked, w.ticket)
+            })
+            .map(|w| w.ticket)
+    }
+
+    fn has_free_slot(&self) -> bool {
+        self.limit.is_none_or(|limit| self.in_flight < limit)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct ProviderLaneStats {
+    pub limit: Option<usize>,
+    pub in_flight: usize,
+    pub waiting_interactive: usize,
+    pub waiting_routine: usize,
+}
+
+#[derive(Clone)]
+pub struct ProviderLanes {
+    state: Arc<Mutex<LaneState>>,
+    notify: Arc<Notify>,
+    starvation: Duration,
+}
+
+impl Default for ProviderLanes {
+    fn default() -> Self {
+        Self::new(provider_concurrency(), priority_starvation_window())
+    }
+}
+
+impl ProviderLanes {
+    pub fn new(limit: Option<usize>, starvation: Duration) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(LaneState {
+                limit: limit.filter(|limit| *limit > 0),
+                in_flight: 0,
+                next_ticket: 0,
+                waiters: Vec::new(),
+            })),
+            notify: Arc::new(Notify::new()),
+            starvation,
+        }
+    }
+
+    /// Change the concurrency limit; `None` or `0` removes it. Queued
+    /// callers are re-checked against the new limit right away.
+    pub fn set_limit(&self, limit: Option<usize>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.limit = limit.filter(|limit| *limit > 0);
+        }
+        self.notify.notify_waiters();
+    }
+
+    pub fn stats(&self) -> ProviderLaneStats {
+        let Ok(state) = self.state.lock() else {
+            return ProviderLaneStats::default();
+        };
+        let waiting = |p: RunPriority| state.waiters.iter().filter(|w| w.priority == p).count();
+        ProviderLaneStats {
+            limit: state.limit,
+            in_flight: state.in_flight,
+            waiting_interactive: waiting(RunPriority::Interactive),
+            waiting_routine: waiting(RunPriority::Routine),
+        }
+    }
+
+    /// Wait for a provider slot. The slot is released when the permit drops.
+    pub async fn acquire(&self, priority: RunPriority) -> ProviderLanePermit {
+        let ticket = {
+            let mut state = self.state.lock().expect("provider lanes poisoned");
+            let ticket = state.next_ticket;
+            state.next_ticket += 1;
+            state.waiters.push(Waiter {
+                ticket,
+                priority,
+                enqueued_at: Instant::now(),
+            });
+            ticket
+        };
+        // Removes the ticket if the caller gives up while queued.
+        let mut queued = QueuedTicket {
+            lanes: self,
+            ticket: Some(ticket),
+        };
+        loop {
+            let notified = self.notify.notified();
+            {
+                let mut state = self.state.lock().expect("provider lanes poisoned");
+                if state.has_free_slot() && state.head(self.starvation) == Some(ticket) {
+                    state.waiters.retain(|w| w.ticket != ticket);
+                    state.in_flight += 1;
+                    queued.ticket = None;
+                    return ProviderLanePermit {
+                        state: self.state.clone(),
+                        notify: self.notify.clone(),
+                    };
+                }
+            }
+            // Re-check periodically so waiting routine calls age into the
+            // interactive lane even without a release.
+            let _ = tokio::time::timeout(WAIT_RECHECK, notified).await;
+        }
+    }
+}
+
+struct QueuedTicket<'a> {
+    lanes: &'a ProviderLanes,
+    ticket: Option<u64>,
+}
+
+impl Drop for QueuedTicket<'_> {
+    fn drop(&mut self) {
+        if let Some(ticket) = self.ticket {
+            if let Ok(mut state) = self.lanes.state.lock() {
+                state.waiters.retain(|w| w.ticket != ticket);
+            }
+            self.lanes.notify.notify_waiters();
+        }
+    }
+}
+
+pub struct ProviderLanePermit {
+    state: Arc<Mutex<LaneState>>,
+    notify: Arc<Notify>,
+}
+
+impl Drop for ProviderLanePermit {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.state.lock() {
+            state.in_flight = state.in_flight.saturating_sub(1);
+        }
+        self.notify.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn interactive_waiters_are_admitted_before_routine() {
+        let lanes = ProviderLanes::new(Some(1), Duration::from_secs(60));
+        let held = lanes.acquire(RunPriority::Interactive).await;
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let spawn = |priority: RunPriority| {
+            let lanes = lanes.clone();
+            let order = order.clone();
+            tokio::spawn(async move {
+                let _permit = lanes.acquire(priority).await;
+                order.lock().expect("order").push(priority);
+            })
+        };
+        let routine = spawn(RunPriority::Routine);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let interactive = spawn(RunPriority::Interactive);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(lanes.stats().waiting_routine, 1);
+        drop(held);
+        routine.await.expect("routine");
+        interactive.await.expect("interactive");
+        assert_eq!(
+            *order.lock().expect("order"),
+            vec![RunPriority::Interactive, RunPriority::Routine]
+        );
+        assert_eq!(lanes.stats().in_flight, 0);
+    }
+
+    #[tokio::test]
+    async fn starved_routine_waiters_keep_arrival_order() {
+        let lanes = ProviderLanes::new(Some(1), Duration::ZERO);
+        let held = lanes.acquire(RunPriority::Routine).await;
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let spawn = |priority: RunPriority| {
+            let lanes = lanes.clone();
+            let order = order.clone();
+            tokio::spawn(async move {
+                let _permit = lanes.acquire(priority).await;
+                order.lock().expect("order").push(priority);
+            })
+        };
+        let routine = spawn(RunPriority::Routine);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let interactive = spawn(RunPriority::Interactive);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        drop(held);
+        routine.await.expect("routine");
+        interactive.await.expect("interactive");
+        assert_eq!(
+            *order.lock().expect("order"),
+            vec![RunPriority::Routine, RunPriority::Interactive]
+        );
+    }
+
+    #[tokio::test]
+    async fn unlimited_lanes_admit_immediately_until_a_limit_is_set() {
+        let lanes = ProviderLanes::new(None, Duration::from_secs(60));
+        let first = lanes.acquire(RunPriority::Routine).await;
+        let second = lanes.acquire(RunPriority::Routine).await;
+        assert_eq!(lanes.stats().in_flight, 2);
+        assert_eq!(lanes.stats().limit, None);
+
+        lanes.set_limit(Some(2));
+        let queued = {
+            let lanes = lanes.clone();
+            tokio::spawn(async move {
+                let _permit = lanes.acquire(RunPriority::Interactive).await;
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(lanes.stats().waiting_interactive, 1);
+        drop(first);
+        queued.await.expect("queued");
+        drop(second);
+        assert_eq!(lanes.stats().in_flight, 0);
+    }
+}
@@ -0,0 +1,182 @@
+//! Loading the skills relevant to a prompt into the system prompt.
+//!
+//! At the start of a run the engine ranks the installed skills the agent may
+//! use against the user's prompt (see [`tandem_skills::relevance`]) and
+//! injects the best ones within a token budget: the top few in full, the rest
+//! as one-line summaries the model can load with the `skill` tool. The
+//! selection is published as `session.run.skills_selected` so it is recorded
+//! on the run.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use tandem_skills::{rank_skills, select_skills, SkillRankInput, SkillSelection, SkillService};
+
+const DEFAULT_BUDGET_TOKENS: usize = 2_000;
+const DEFAULT_MAX_FULL: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SkillContextPolicy {
+    /// Tokens available for skill context; 0 disables automatic loading.
+    pub budget_tokens: usize,
+    /// Most skills loaded in full.
+    pub max_full: usize,
+}
+
+impl Default for SkillContextPolicy {
+    fn default() -> Self {
+        Self {
+            budget_tokens: DEFAULT_BUDGET_TOKENS,
+            max_full: DEFAULT_MAX_FULL,
+        }
+    }
+}
+
+fn env_parse<T: std::str::FromStr>(key: &str) -> Option<T> {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.trim().parse::<T>().ok())
+}
+
+impl SkillContextPolicy {
+    /// Policy from `TANDEM_SKILL_CONTEXT_TOKENS` and
+    /// `TANDEM_SKILL_CONTEXT_MAX_FULL`.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            budget_tokens: env_parse("TANDEM_SKILL_CONTEXT_TOKENS")
+                .unwrap_or(defaults.budget_tokens),
+            max_full: env_parse("TANDEM_SKILL_CONTEXT_MAX_FULL").unwrap_or(defaults.max_full),
+        }
+    }
+}
+
+/// Whether `name` is usable with the agent's equipped skills (`None`, `*`
+/// or `all` mean every skill).
+fn skill_equipped(name: &str, equipped: Option<&[String]>) -> bool {
+    let Some(equipped) = equipped else {
+        return true;
+    };
+    equipped
+        .iter()
+        .map(|s| s.trim())
+        .any(|s| s == "*" || s.eq_ignore_ascii_case("all") || s == name)
+}
+
+/// Selection and rendered context block for `prompt`; `None` when disabled
+/// or no skill is relevant.
+pub fn build_skill_context(
+    service: &SkillService,
+    policy: SkillContextPolicy,
+    equipped: Option<&[String]>,
+    prompt: &str,
+    last_used_ms: &HashMap<String, u64>,
+    now_ms: u64,
+) -> Option<(SkillSelection, String)> {
+    if policy.budget_tokens == 0 || prompt.trim().is_empty() {
+        return None;
+    }
+    let mut skills = service.list_skills().ok()?;
+    skills.retain(|skill| skill_equipped(&skill.name, equipped));
+    let ranked = rank_skills(
+        &skills,
+        &SkillRankInput {
+            prompt,
+            last_used_ms: Some(last_used_ms),
+            embedding_similarity: None,
+            now_ms,
+        },
+    );
+    if ranked.is_empty() {
+        return None;
+    }
+    let (selection, rendered) = select_skills(
+        &ranked,
+        &skills,
+        policy.budget_tokens,
+        policy.max_full,
+        |name| {
+            service
+                .load_skill(name)
+                .ok()
+                .flatten()
+                .map(|skill| skill.content)
+        },
+    );
+    (!selection.is_empty()).then_some((selection, rendered))
+}
+
+/// Skill service rooted at the session workspace.
+pub fn skill_service_for(workspace_root: Option<&str>) -> SkillService {
+    SkillService::for_workspace(workspace_root.map(PathBuf::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tandem_skills::SkillLoadMode;
+
+    fn write_skill(root: &std::path::Path, name: &str, description: &str, trigger: &str) {
+        let dir = root.join(name);
+        std::fs::create_dir_all(&dir).expect("skill dir");
+        std::fs::write(
+            dir.join("SKILL.md"),
+            format!(
+                "---\nname: {name}\ndescription: {description}\ntriggers:\n  - {trigger}\n---\n\n# {name}\n\nSteps for {description}.\n"
+            ),
+        )
+        .expect("skill file");
+    }
+
+    #[test]
+    fn loads_equipped_relevant_skills_only() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().join("skills");
+        write_skill(
+            &root,
+            "release-notes",
+            "draft release notes",
+            "release notes",
+        );
+        write_skill(&root, "migrations", "review schema migrations", "migration");
+        let service = SkillService::with_roots(None, root, Vec::new());
+        let policy = SkillContextPolicy::default();
+
+        let (selection, rendered) = build_skill_context(
+            &service,
+            policy,
+            None,
+            "Please write the release notes",
+            &HashMap::new(),
+            0,
+        )
+        .expect("context");
+        assert_eq!(selection.skills.len(), 1);
+        assert_eq!(selection.skills[0].name, "release-notes");
+        assert_eq!(selection.skills[0].mode, SkillLoadMode::Full);
+        assert!(rendered.contains("Steps for draft release notes."));
+
+        let equipped = vec!["migrations".to_string()];
+        assert!(build_skill_context(
+            &service,
+            policy,
+            Some(&equipped),
+            "Please write the release notes",
+            &HashMap::new(),
+            0,
+        )
+        .is_none());
+        assert!(build_skill_context(
+            &service,
+            SkillContextPolicy {
+                budget_tokens: 0,
+                ..policy
+            },
+            None,
+            "Please write the release notes",
+            &HashMap::new(),
+            0,
+        )
+        .is_none());
+    }
+}
@@ -1,18 +1,24 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use anyhow::Context;
 use chrono::{TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
 use tokio::fs;
 use tokio::sync::RwLock;
 use tokio::task;
 use uuid::Uuid;
 
-use tandem_types::{Message, MessagePart, MessageRole, Session};
+use tandem_types::{
+    normalize_session_tag, Message, MessagePart, MessageRole, Session, MAX_SESSION_TAGS,
+};
 
-use crate::{derive_session_title_from_prompt, normalize_workspace_path, title_needs_repair};
+use crate::{
+    derive_session_title_from_prompt, normalize_workspace_path, title_needs_repair, RunPause,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct SessionMeta {
@@ -28,6 +34,58 @@ pub struct SessionMeta {
     pub pre_revert: Option<Vec<Message>>,
     #[serde(default)]
     pub todos: Vec<Value>,
+    /// Delta-sync sequence numbers, keyed by message id.
+    #[serde(default)]
+    pub message_seqs: HashMap<String, MessageSeq>,
+    /// Message id -> sequence at which it was removed (e.g. by a revert).
+    #[serde(default)]
+    pub removed_message_seqs: HashMap<String, u64>,
+    /// Highest tombstone sequence dropped from `removed_message_seqs`. Cursors
+    /// older than this can no longer be served incrementally.
+    #[serde(default)]
+    pub tombstone_floor: u64,
+    /// Earlier channel conversations summarized into the session.
+    #[serde(default)]
+    pub context_imports: Vec<ContextImport>,
+    /// Pause requested for, or holding, the session's run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_pause: Option<RunPause>,
+}
+
+/// Record of external conversation history seeded into a session.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ContextImport {
+    /// Platform the history came from, e.g. `discord`.
+    pub source: String,
+    /// Platform conversation (channel or thread) id.
+    pub conversation: String,
+    pub message_count: usize,
+    /// `llm` or `excerpt`.
+    pub summarizer: String,
+    /// Session message holding the summary.
+    #[serde(rename = "messageID")]
+    pub message_id: String,
+    pub imported_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct MessageSeq {
+    pub seq: u64,
+    pub fingerprint: u64,
+}
+
+/// Messages added or changed after a client's cursor.
+#[derive(Debug, Clone, Default)]
+pub struct MessageDelta {
+    /// Cursor to pass as `since_seq` on the next poll.
+    pub seq: u64,
+    /// The cursor was too old to diff against; `messages` is the whole
+    /// transcript and the client should drop what it has.
+    pub reset: bool,
+    /// `(seq, message)` in transcript order.
+    pub messages: Vec<(u64, Message)>,
+    pub removed: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,6 +112,7 @@ pub struct Storage {
     sessions: RwLock<HashMap<String, Session>>,
     metadata: RwLock<HashMap<String, SessionMeta>>,
     question_requests: RwLock<HashMap<String, QuestionRequest>>,
+    message_seq: AtomicU64,
 }
 
 #[derive(Debug, Clone)]
@@ -72,6 +131,7 @@ pub struct SessionRepairStats {
 
 const LEGACY_IMPORT_MARKER_FILE: &str = "legacy_import_marker.json";
 const LEGACY_IMPORT_MARKER_VERSION: u32 = 1;
+const MAX_MESSAGE_TOMBSTONES: usize = 256;
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LegacyTreeCounts {
@@ -160,11 +220,13 @@ impl Storage {
         } else {
             HashMap::new()
         };
+        let last_seq = metadata.values().map(meta_max_seq).max().unwrap_or(0);
         let storage = Self {
             base,
             sessions: RwLock::new(sessions),
             metadata: RwLock::new(metadata),
             question_requests: RwLock::new(question_requests),
+            message_seq: AtomicU64::new(last_seq),
         };
 
         if imported_legacy_sessions {
@@ -177,6 +239,18 @@ impl Storage {
         Ok(storage)
     }
 
+    pub fn base_dir(&self) -> &Path {
+        &self.base
+    }
+
+    pub fn attachments(&self) -> crate::AttachmentStore {
+        crate::AttachmentStore::new(self.base.join("attachments"))
+    }
+
+    pub fn tool_results(&self) -> crate::ToolResultStore {
+        crate::ToolResultStore::new(self.base.join("tool-results"))
+    }
+
     pub async fn list_sessions(&self) -> Vec<Session> {
         self.list_sessions_scoped(SessionListScope::Global).await
     }
@@ -219,6 +293,15 @@ impl Storage {
         self.sessions.read().await.get(id).cloned()
     }
 
+    /// Whether `id` is an incognito session, without cloning it.
+    pub async fn is_incognito(&self, id: &str) -> bool {
+        self.sessions
+            .read()
+            .await
+            .get(id)
+            .is_some_and(|session| session.incognito)
+    }
+
     pub async fn save_session(&self, mut session: Session) -> anyhow::Result<()> {
         if session.workspace_root.is_none() {
             session.workspace_root = normalize_workspace_path(&session.directory);
@@ -346,6 +429,7 @@ impl Storage {
             .retain(|_, request| request.session_id != id);
         if removed {
             self.flush().await?;
+            self.tool_results().delete_session(id).await;
         }
         Ok(removed)
     }
@@ -370,6 +454,43 @@ impl Storage {
         self.flush().await
     }
 
+    /// Messages added or changed in `session_id` after `since_seq`, plus ids
+    /// removed since then. `None` when the session does not exist.
+    pub async fn messages_since(&self, session_id: &str, since_seq: u64) -> Option<MessageDelta> {
+        let sessions = self.sessions.read().await;
+        let session = sessions.get(session_id)?;
+        let meta = {
+            let mut metadata = self.metadata.write().await;
+            let meta = metadata.entry(session_id.to_string()).or_default();
+            self.sync_session_message_seqs(session, meta);
+            meta.clone()
+        };
+        let reset = since_seq > 0 && since_seq < meta.tombstone_floor;
+        let seq_of = |msg: &Message| meta.message_seqs.get(&msg.id).map(|s| s.seq).unwrap_or(0);
+        let messages = session
+            .messages
+            .iter()
+            .filter(|msg| reset || seq_of(msg) > since_seq)
+            .map(|msg| (seq_of(msg), msg.clone()))
+            .collect();
+        let mut removed = if reset {
+            Vec::new()
+        } else {
+            meta.removed_message_seqs
+                .iter()
+                .filter(|(_, seq)| **seq > since_seq)
+                .map(|(id, seq)| (*seq, id.clone()))
+                .collect::<Vec<_>>()
+        };
+        removed.sort();
+        Some(MessageDelta {
+            seq: meta_max_seq(&meta),
+            reset,
+            messages,
+            removed: removed.into_iter().map(|(_, id)| id).collect(),
+        })
+    }
+
     pub async fn fork_session(&self, id: &str) -> anyhow::Result<Option<Session>> {
         let source = {
             let sessions = self.sessions.read().await;
@@ -484,6 +605,52 @@ impl Storage {
         Ok(true)
     }
 
+    /// Add and remove session tags. Entries in `remove` ending in `*` drop
+    /// every tag with that prefix (`run:*`). Invalid tags are ignored.
+    /// Returns the resulting tags, or `None` if the session does not exist.
+    pub async fn update_session_tags(
+        &self,
+        id: &str,
+        add: &[String],
+        remove: &[String],
+    ) -> anyhow::Result<Option<Vec<String>>> {
+        let mut sessions = self.sessions.write().await;
+        let Some(session) = sessions.get_mut(id) else {
+            return Ok(None);
+        };
+        let before = session.tags.clone();
+        for pattern in remove {
+            let pattern = pattern.trim().to_ascii_lowercase();
+            match pattern.strip_suffix('*') {
+                Some(prefix) => session.tags.retain(|tag| !tag.starts_with(prefix)),
+                None => session.tags.retain(|tag| *tag != pattern),
+            }
+        }
+        for tag in add.iter().filter_map(|raw| normalize_session_tag(raw)) {
+            if session.tags.len() >= MAX_SESSION_TAGS {
+                break;
+            }
+            if !session.tags.contains(&tag) {
+                session.tags.push(tag);
+            }
+        }
+        let tags = session.tags.clone();
+        drop(sessions);
+        if tags != before {
+            self.flush().await?;
+        }
+        Ok(Some(tags))
+    }
+
+    /// Replace a session's tags.
+    pub async fn set_session_tags(
+        &self,
+        id: &str,
+        tags: &[String],
+    ) -> anyhow::Result<Option<Vec<String>>> {
+        self.update_session_tags(id, tags, &["*".to_string()]).await
+    }
+
     pub async fn children(&self, parent_id: &str) -> Vec<Session> {
         let child_ids = {
             let metadata = self.metadata.read().await;
@@ -507,11 +674,67 @@ impl Storage {
                 "archived": meta.archived,
                 "shared": meta.shared,
                 "parentID": meta.parent_id,
-                "snapshotCount": meta.snapshots.len()
+                "snapshotCount": meta.snapshots.len(),
+                "contextImports": meta.context_imports,
+                "runPause": meta.run_pause,
             })
         })
     }
 
+    pub async fn context_imports(&self, id: &str) -> Vec<ContextImport> {
+        self.metadata
+            .read()
+            .await
+            .get(id)
+            .map(|meta| meta.context_imports.clone())
+            .unwrap_or_default()
+    }
+
+    pub async fn record_context_import(
+        &self,
+        id: &str,
+        import: ContextImport,
+    ) -> anyhow::Result<()> {
+        let mut metadata = self.metadata.write().await;
+        let meta = metadata
+            .entry(id.to_string())
+            .or_insert_with(SessionMeta::default);
+        meta.context_imports.push(import);
+        drop(metadata);
+        self.flush().await
+    }
+
+    pub async fn run_pause(&self, id: &str) -> Option<RunPause> {
+        self.metadata
+            .read()
+            .await
+            .get(id)
+            .and_then(|meta| meta.run_pause.clone())
+    }
+
+    pub async fn set_run_pause(&self, id: &str, pause: Option<RunPause>) -> anyhow::Result<()> {
+        let mut metadata = self.metadata.write().await;
+        let meta = metadata
+            .entry(id.to_string())
+            .or_insert_with(SessionMeta::default);
+        meta.run_pause = pause;
+        drop(metadata);
+        self.flush().await
+    }
+
+    /// Sessions with a pause on record, oldest request first.
+    pub async fn run_pauses(&self) -> Vec<(String, RunPause)> {
+        let mut out = self
+            .metadata
+            .read()
+            .await
+            .iter()
+            .filter_map(|(id, meta)| Some((id.clone(), meta.run_pause.clone()?)))
+            .collect::<Vec<_>>();
+        out.sort_by_key(|(_, pause)| pause.requested_at_ms);
+        out
+    }
+
     pub async fn session_diff(&self, id: &str) -> Option<Value> {
         let sessions = self.sessions.read().await;
         let current = sessions.get(id)?;
@@ -527,6 +750,34 @@ impl Storage {
         }))
     }
 
+    /// Set `key` in a message's metadata object. Returns `false` when the
+    /// session or message does not exist.
+    pub async fn set_message_metadata(
+        &self,
+        session_id: &str,
+        message_id: &str,
+        key: &str,
+        value: Value,
+    ) -> anyhow::Result<bool> {
+        let mut sessions = self.sessions.write().await;
+        let Some(message) = sessions
+            .get_mut(session_id)
+            .and_then(|session| session.messages.iter_mut().find(|m| m.id == message_id))
+        else {
+            return Ok(false);
+        };
+        let metadata = message.metadata.get_or_insert_with(|| json!({}));
+        if !metadata.is_object() {
+            *metadata = json!({});
+        }
+        if let Some(obj) = metadata.as_object_mut() {
+            obj.insert(key.to_string(), value);
+        }
+        drop(sessions);
+        self.flush().await?;
+        Ok(true)
+    }
+
     pub async fn set_todos(&self, id: &str, todos: Vec<Value>) -> anyhow::Result<()> {
         let mut metadata = self.metadata.write().await;
         let meta = metadata
@@ -637,7 +888,72 @@ impl Storage {
         Ok(Some(updated))
     }
 
+    /// Sync message sequence numbers for every session; run before persisting.
+    async fn sync_message_seqs(&self) {
+        let sessions = self.sessions.read().await;
+        let mut metadata = self.metadata.write().await;
+        for (session_id, session) in sessions.iter() {
+            let meta = metadata
+                .entry(session_id.clone())
+                .or_insert_with(SessionMeta::default);
+            self.sync_session_message_seqs(session, meta);
+        }
+    }
+
+    /// Assign fresh sequence numbers to messages of `session` that are new or
+    /// whose content changed, and tombstone the ones that disappeared.
+    fn sync_session_message_seqs(&self, session: &Session, meta: &mut SessionMeta) {
+        for msg in &session.messages {
+            let fingerprint = message_fingerprint(msg);
+            if meta
+                .message_seqs
+                .get(&msg.id)
+                .is_some_and(|current| current.fingerprint == fingerprint)
+            {
+                continue;
+            }
+            let seq = self.message_seq.fetch_add(1, Ordering::SeqCst) + 1;
+            meta.message_seqs
+                .insert(msg.id.clone(), MessageSeq { seq, fingerprint });
+            meta.removed_message_seqs.remove(&msg.id);
+        }
+        if meta.message_seqs.len() > session.messages.len() {
+            let live = session
+                .messages
+                .iter()
+                .map(|msg| msg.id.as_str())
+                .collect::<std::collections::HashSet<_>>();
+            let gone = meta
+                .message_seqs
+                .keys()
+                .filter(|id| !live.contains(id.as_str()))
+                .cloned()
+                .collect::<Vec<_>>();
+            for id in gone {
+                meta.message_seqs.remove(&id);
+                let seq = self.message_seq.fetch_add(1, Ordering::SeqCst) + 1;
+                meta.removed_message_seqs.insert(id, seq);
+            }
+        }
+        while meta.removed_message_seqs.len() > MAX_MESSAGE_TOMBSTONES {
+            let Some((oldest_id, oldest_seq)) = meta
+                .removed_message_seqs
+                .iter()
+                .min_by_key(|(_, seq)| **seq)
+                .map(|(id, seq)| (id.clone(), *seq))
+            else {
+                break;
+            };
+            meta.removed_message_seqs.remove(&oldest_id);
+            meta.tombstone_floor = meta.tombstone_floor.max(oldest_seq);
+        }
+    }
+
     async fn flush(&self) -> anyhow::Result<()> {
+        self.sync_message_seqs().await;
+        if crate::chaos_inject(crate::ChaosFault::Persistence) {
+            anyhow::bail!("chaos: injected persistence failure");
+        }
         let snapshot = self.sessions.read().await.clone();
         let payload = serde_json::to_string_pretty(&snapshot)?;
         fs::write(self.base.join("sessions.json"), payload).await?;
@@ -708,7 +1024,25 @@ struct LegacyMergeStats {
     parts_recovered: u64,
 }
 
-fn now_ms_u64() -> u64 {
+fn meta_max_seq(meta: &SessionMeta) -> u64 {
+    meta.message_seqs
+        .values()
+        .map(|entry| entry.seq)
+        .chain(meta.removed_message_seqs.values().copied())
+        .max()
+        .unwrap_or(0)
+        .max(meta.tombstone_floor)
+}
+
+fn message_fingerprint(msg: &Message) -> u64 {
+    let bytes = serde_json::to_vec(msg).unwrap_or_default();
+    let digest = Sha256::digest(&bytes);
+    let mut head = [0u8; 8];
+    head.copy_from_slice(&digest[..8]);
+    u64::from_le_bytes(head)
+}
+
+pub(crate) fn now_ms_u64() -> u64 {
     Utc::now().timestamp_millis().max(0) as u64
 }
 
@@ -972,7 +1306,10 @@ fn load_legacy_opencode_sessions(base: &Path) -> anyhow::Result<HashMap<String,
                     time: tandem_types::SessionTime { created, updated },
                     model: None,
                     provider: None,
+                    generation_preset: None,
                     environment: None,
+                    tags: Vec::new(),
+                    incognito: false,
                     messages: load_legacy_session_messages(base, &session_id),
                 },
             );
@@ -1040,6 +1377,7 @@ fn load_legacy_session_messages(base: &Path, session_id: &str) -> Vec<Message> {
                 role: legacy_role_to_message_role(&legacy.role),
                 parts: load_legacy_message_parts(base, &legacy.id),
                 created_at,
+                metadata: None,
             },
         ));
     }
@@ -1202,6 +1540,62 @@ mod tests {
     use super::*;
     use std::fs as stdfs;
 
+    #[tokio::test]
+    async fn messages_since_returns_new_changed_and_removed_messages() {
+        let base = std::env::temp_dir().join(format!("tandem-core-test-{}", Uuid::new_v4()));
+        let storage = Storage::new(&base).await.expect("storage");
+        let session = Session::new(Some("delta".to_string()), Some(".".to_string()));
+        let id = session.id.clone();
+        storage.save_session(session).await.expect("save session");
+        let first = Message::new(
+            MessageRole::User,
+            vec![MessagePart::Text {
+                text: "hello".to_string(),
+            }],
+        );
+        storage
+            .append_message(&id, first.clone())
+            .await
+            .expect("append");
+
+        let full = storage.messages_since(&id, 0).await.expect("delta");
+        assert_eq!(full.messages.len(), 1);
+        let cursor = full.seq;
+        assert!(cursor > 0);
+        let idle = storage.messages_since(&id, cursor).await.expect("delta");
+        assert!(idle.messages.is_empty());
+        assert_eq!(idle.seq, cursor);
+
+        let second = Message::new(
+            MessageRole::Assistant,
+            vec![MessagePart::Text {
+                text: "hi".to_string(),
+            }],
+        );
+        storage
+            .append_message(&id, second.clone())
+            .await
+            .expect("append");
+        let delta = storage.messages_since(&id, cursor).await.expect("delta");
+        assert_eq!(delta.messages.len(), 1);
+        assert_eq!(delta.messages[0].1.id, second.id);
+
+        let mut session = storage.get_session(&id).await.expect("session");
+        session.messages[0].parts.push(MessagePart::Text {
+            text: "edited".to_string(),
+        });
+        storage.save_session(session).await.expect("save");
+        let delta = storage.messages_since(&id, delta.seq).await.expect("delta");
+        assert_eq!(delta.messages.len(), 1);
+        assert_eq!(delta.messages[0].1.id, first.id);
+
+        assert!(storage.revert_session(&id).await.expect("revert"));
+        let delta = storage.messages_since(&id, delta.seq).await.expect("delta");
+        assert_eq!(delta.removed, vec![second.id.clone()]);
+        assert!(!delta.reset);
+        assert!(storage.messages_since("missing", 0).await.is_none());
+    }
+
     #[tokio::test]
     async fn todos_are_normalized_to_wire_shape() {
         let base = std::env::temp_dir().join(format!("tandem-core-test-{}", Uuid::new_v4()));
@@ -1446,4 +1840,40 @@ mod tests {
         let repaired = storage.get_session(&id).await.expect("session");
         assert_eq!(repaired.title, "Explain this bug");
     }
+
+    #[tokio::test]
+    async fn session_tags_are_normalized_and_prefix_removable() {
+        let base = std::env::temp_dir().join(format!("tandem-core-tags-{}", Uuid::new_v4()));
+        let storage = Storage::new(&base).await.expect("storage");
+        let session = Session::new(Some("tags".to_string()), Some(".".to_string()));
+        let id = session.id.clone();
+        storage.save_session(session).await.expect("save");
+
+        let tags = storage
+            .update_session_tags(
+                &id,
+                &[
+                    " Agent:Release-Bot ".to_string(),
+                    "run:failed".to_string(),
+                    "not a tag".to_string(),
+                ],
+                &[],
+            )
+            .await
+            .expect("update")
+            .expect("session");
+        assert_eq!(tags, vec!["agent:release-bot", "run:failed"]);
+
+        let tags = storage
+            .update_session_tags(&id, &["run:completed".to_string()], &["run:*".to_string()])
+            .await
+            .expect("update")
+            .expect("session");
+        assert_eq!(tags, vec!["agent:release-bot", "run:completed"]);
+        assert!(storage
+            .set_session_tags("missing", &[])
+            .await
+            .expect("set")
+            .is_none());
+    }
 }
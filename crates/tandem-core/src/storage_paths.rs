@@ -2,7 +2,9 @@ use anyhow::Context;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::SystemTime;
+
+use crate::storage::now_ms_u64;
 
 const STORAGE_LAYOUT_VERSION: u32 = 1;
 
@@ -63,6 +65,44 @@ pub fn is_within_workspace_root(path: &Path, workspace_root: &Path) -> bool {
     candidate.starts_with(root)
 }
 
+/// Credential files and key material (SSH and GPG dirs, cloud and package
+/// registry credentials, private keys, `.env` files) that tools must not read
+/// or send anywhere.
+pub fn is_sensitive_path(path: &Path) -> bool {
+    let lowered = path.to_string_lossy().to_ascii_lowercase();
+    if lowered.contains("/.ssh/")
+        || lowered.ends_with("/.ssh")
+        || lowered.contains("/.gnupg/")
+        || lowered.ends_with("/.gnupg")
+    {
+        return true;
+    }
+    if lowered.contains("/.aws/credentials")
+        || lowered.ends_with("/.npmrc")
+        || lowered.ends_with("/.netrc")
+        || lowered.ends_with("/.pypirc")
+    {
+        return true;
+    }
+    if lowered.contains("id_rsa")
+        || lowered.contains("id_ed25519")
+        || lowered.contains("id_ecdsa")
+        || lowered.contains(".pem")
+        || lowered.contains(".p12")
+        || lowered.contains(".pfx")
+        || lowered.contains(".key")
+    {
+        return true;
+    }
+    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+        let n = name.to_ascii_lowercase();
+        if n == ".env" || n.starts_with(".env.") {
+            return true;
+        }
+    }
+    false
+}
+
 fn normalize_for_workspace_compare(path: PathBuf) -> PathBuf {
     #[cfg(windows)]
     {
@@ -141,7 +181,7 @@ pub fn migrate_legacy_storage_if_needed(paths: &SharedPaths) -> anyhow::Result<M
         copied: Vec::new(),
         skipped: Vec::new(),
         errors: Vec::new(),
-        timestamp_ms: now_ms(),
+        timestamp_ms: now_ms_u64(),
     };
 
     let canonical_empty = is_dir_effectively_empty(&paths.canonical_root)?;
@@ -233,7 +273,7 @@ pub fn migrate_legacy_storage_if_needed(paths: &SharedPaths) -> anyhow::Result<M
 fn persist_storage_marker(paths: &SharedPaths) -> anyhow::Result<()> {
     let marker = StorageVersionMarker {
         version: STORAGE_LAYOUT_VERSION,
-        timestamp_ms: now_ms(),
+        timestamp_ms: now_ms_u64(),
     };
     write_json(&paths.storage_version_path, &marker)
 }
@@ -341,13 +381,6 @@ fn resolve_opencode_legacy_root() -> Option<PathBuf> {
     candidates.into_iter().find(|path| path.exists())
 }
 
-fn now_ms() -> u64 {
-    match SystemTime::now().duration_since(UNIX_EPOCH) {
-        Ok(dur) => dur.as_millis() as u64,
-        Err(_) => 0,
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
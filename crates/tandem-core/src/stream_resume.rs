@@ -0,0 +1,120 @@
+//! Helpers for resuming a provider stream that dropped mid-response.
+//!
+//! The partial assistant text is kept and the provider is asked to continue
+//! from where it stopped. Models often repeat a few words of the partial text,
+//! so the start of the continuation is buffered and any overlap is trimmed
+//! before it is stitched on.
+
+use std::time::Duration;
+
+/// Characters of a resumed stream buffered before overlap trimming.
+pub const STREAM_RESUME_OVERLAP_WINDOW: usize = 200;
+const DEFAULT_STREAM_RESUME_ATTEMPTS: usize = 2;
+const RESUME_TAIL_CHARS: usize = 400;
+const RESUME_BACKOFF_BASE_MS: u64 = 500;
+const RESUME_BACKOFF_MAX_MS: u64 = 8_000;
+
+/// Resume attempts per provider call (`TANDEM_STREAM_RESUME_ATTEMPTS`, 0
+/// disables resumption).
+pub fn max_stream_resumes() -> usize {
+    std::env::var("TANDEM_STREAM_RESUME_ATTEMPTS")
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_STREAM_RESUME_ATTEMPTS)
+}
+
+/// Delay before resume attempt `attempt` (1-based): doubles from 500ms up to
+/// 8s, and the upper half is randomized so runs that lost their streams to
+/// the same outage don't all reconnect at once.
+pub fn stream_resume_backoff(attempt: usize) -> Duration {
+    backoff_with_jitter(attempt, uuid::Uuid::new_v4().as_u128() as u64)
+}
+
+fn backoff_with_jitter(attempt: usize, random: u64) -> Duration {
+    let doublings = attempt.saturating_sub(1).min(16) as u32;
+    let ceiling = RESUME_BACKOFF_BASE_MS
+        .saturating_mul(1 << doublings)
+        .min(RESUME_BACKOFF_MAX_MS);
+    let floor = ceiling / 2;
+    Duration::from_millis(floor + random % (ceiling - floor + 1))
+}
+
+/// Whether a stream error is worth resuming; request problems will fail the
+/// same way again.
+pub fn is_resumable_stream_error(error_code: &str) -> bool {
+    !matches!(
+        error_code,
+        "TOOL_SCHEMA_INVALID" | "CONTEXT_LENGTH_EXCEEDED" | "AUTHENTICATION_ERROR"
+    )
+}
+
+/// Follow-up user instruction sent after the partial assistant message.
+pub fn stream_continuation_prompt(partial: &str) -> String {
+    let tail_start = partial
+        .char_indices()
+        .rev()
+        .nth(RESUME_TAIL_CHARS.saturating_sub(1))
+        .map(|(idx, _)| idx)
+        .unwrap_or(0);
+    format!(
+        "Your previous response was cut off by a network interruption. Continue exactly from where it stopped, without repeating earlier text or adding a preamble. It ended with:\n\n{}",
+        &partial[tail_start..]
+    )
+}
+
+/// Drop the longest prefix of `continuation` that repeats the end of `partial`.
+pub fn strip_resume_overlap(partial: &str, continuation: &str) -> String {
+    let max = continuation.len().min(partial.len());
+    let best = (1..=max)
+        .rev()
+        .filter(|len| continuation.is_char_boundary(*len))
+        .find(|len| partial.ends_with(&continuation[..*len]))
+        .unwrap_or(0);
+    // Ignore trivial overlaps such as a single space or letter.
+    if best < 4 {
+        return continuation.to_string();
+    }
+    continuation[best..].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trims_repeated_tail_and_keeps_short_overlaps() {
+        let partial = "The migration touches three tables: users, orders";
+        assert_eq!(
+            strip_resume_overlap(partial, "users, orders and invoices."),
+            " and invoices."
+        );
+        assert_eq!(strip_resume_overlap(partial, "s and more"), "s and more");
+        assert_eq!(strip_resume_overlap("", "hello"), "hello");
+    }
+
+    #[test]
+    fn resume_backoff_grows_with_jitter_and_is_capped() {
+        assert_eq!(backoff_with_jitter(1, 0), Duration::from_millis(250));
+        assert_eq!(backoff_with_jitter(1, 250), Duration::from_millis(500));
+        assert_eq!(backoff_with_jitter(2, 0), Duration::from_millis(500));
+        assert_eq!(backoff_with_jitter(3, 0), Duration::from_millis(1_000));
+        assert_eq!(
+            backoff_with_jitter(40, u64::MAX),
+            Duration::from_millis(4_000 + u64::MAX % 4_001)
+        );
+        for attempt in 1..=10 {
+            let delay = stream_resume_backoff(attempt);
+            assert!(delay <= Duration::from_millis(RESUME_BACKOFF_MAX_MS));
+        }
+    }
+
+    #[test]
+    fn continuation_prompt_quotes_only_the_tail() {
+        let partial = format!("{}END", "x".repeat(1_000));
+        let prompt = stream_continuation_prompt(&partial);
+        assert!(prompt.ends_with("END"));
+        assert!(prompt.len() < 600);
+        assert!(!is_resumable_stream_error("AUTHENTICATION_ERROR"));
+        assert!(is_resumable_stream_error("TIMEOUT"));
+    }
+}
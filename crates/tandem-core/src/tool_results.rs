@@ -0,0 +1,361 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use uuid::Uuid;
+
+/// Tool outputs estimated above this many tokens are stored and summarized.
+pub const DEFAULT_TOOL_RESULT_SUMMARY_TOKENS: usize = 4_000;
+/// Upper bound on lines returned by a single `tool_result_read` call.
+pub const MAX_TOOL_RESULT_RANGE_LINES: usize = 400;
+const MAX_TOOL_RESULT_RANGE_CHARS: usize = 16_000;
+/// How much of the full output the summarizer gets to see; longer outputs
+/// are cut to their head and tail before they reach the provider.
+pub const MAX_SUMMARY_INPUT_CHARS: usize = 24_000;
+/// Stored outputs older than this are pruned whenever a new one is saved.
+pub const TOOL_RESULT_RETENTION_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+const FALLBACK_PREVIEW_LINES: usize = 20;
+
+/// Summarization threshold from `TANDEM_TOOL_RESULT_SUMMARY_TOKENS`; `0`
+/// disables summarization.
+pub fn tool_result_summary_threshold() -> usize {
+    std::env::var("TANDEM_TOOL_RESULT_SUMMARY_TOKENS")
+        .ok()
+        .and_then(|raw| raw.trim().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_TOOL_RESULT_SUMMARY_TOKENS)
+}
+
+/// Rough token count (~4 chars per token), good enough for budgeting.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ToolResultRecord {
+    pub handle: String,
+    #[serde(rename = "sessionID")]
+    pub session_id: String,
+    #[serde(rename = "messageID")]
+    pub message_id: String,
+    pub tool: String,
+    #[serde(rename = "totalChars")]
+    pub total_chars: usize,
+    #[serde(rename = "totalLines")]
+    pub total_lines: usize,
+    #[serde(rename = "estimatedTokens")]
+    pub estimated_tokens: usize,
+    pub sha256: String,
+    #[serde(rename = "createdAtMs")]
+    pub created_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ToolResultRange {
+    pub handle: String,
+    /// 1-based, inclusive.
+    #[serde(rename = "startLine")]
+    pub start_line: usize,
+    #[serde(rename = "endLine")]
+    pub end_line: usize,
+    #[serde(rename = "totalLines")]
+    pub total_lines: usize,
+    pub text: String,
+    pub truncated: bool,
+}
+
+/// Full outputs of summarized tool calls, stored under
+/// `<storage>/tool-results/<handle>/` until their session is deleted or they
+/// age past [`TOOL_RESULT_RETENTION_MS`].
+#[derive(Debug, Clone)]
+pub struct ToolResultStore {
+    root: PathBuf,
+}
+
+impl ToolResultStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn valid_handle(handle: &str) -> bool {
+        !handle.is_empty()
+            && handle
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    }
+
+    pub async fn save(
+        &self,
+        session_id: &str,
+        message_id: &str,
+        tool: &str,
+        output: &str,
+    ) -> std::io::Result<ToolResultRecord> {
+        let record = ToolResultRecord {
+            handle: format!("tres-{}", Uuid::new_v4()),
+            session_id: session_id.to_string(),
+            message_id: message_id.to_string(),
+            tool: tool.to_string(),
+            total_chars: output.chars().count(),
+            total_lines: output.lines().count(),
+            estimated_tokens: estimate_tokens(output),
+            sha256: format!("{:x}", Sha256::digest(output.as_bytes())),
+            created_at_ms: chrono::Utc::now().timestamp_millis().max(0) as u64,
+        };
+        let dir = self.root.join(&record.handle);
+        fs::create_dir_all(&dir).await?;
+        fs::write(dir.join("output.txt"), output).await?;
+        let meta = serde_json::to_vec_pretty(&record).map_err(std::io::Error::other)?;
+        fs::write(dir.join("meta.json"), meta).await?;
+        let cutoff = record
+            .created_at_ms
+            .saturating_sub(TOOL_RESULT_RETENTION_MS);
+        self.remove_where(|other| other.created_at_ms < cutoff)
+            .await;
+        Ok(record)
+    }
+
+    /// Remove every stored output of `session_id`.
+    pub async fn delete_session(&self, session_id: &str) -> usize {
+        self.remove_where(|record| record.session_id == session_id)
+            .await
+    }
+
+    async fn remove_where(&self, matches: impl Fn(&ToolResultRecord) -> bool) -> usize {
+        let Ok(mut entries) = fs::read_dir(&self.root).await else {
+            return 0;
+        };
+        let mut removed = 0;
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let Ok(raw) = fs::read_to_string(entry.path().join("meta.json")).await else {
+                continue;
+            };
+            let Ok(record) = serde_json::from_str::<ToolResultRecord>(&raw) else {
+                continue;
+            };
+            if matches(&record) && fs::remove_dir_all(entry.path()).await.is_ok() {
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    pub async fn get(&self, handle: &str) -> Option<ToolResultRecord> {
+        if !Self::valid_handle(handle) {
+            return None;
+        }
+        let raw = fs::read_to_string(self.root.join(handle).join("meta.json"))
+            .await
+            .ok()?;
+        serde_json::from_str(&raw).ok()
+    }
+
+    /// Lines `start_line..=end_line` (1-based) of a stored output, clamped to
+    /// the per-call line and character limits.
+    pub async fn read_range(
+        &self,
+        handle: &str,
+        start_line: usize,
+        end_line: Option<usize>,
+    ) -> Option<ToolResultRange> {
+        if !Self::valid_handle(handle) {
+            return None;
+        }
+        let output = fs::read_to_string(self.root.join(handle).join("output.txt"))
+            .await
+            .ok()?;
+        let lines = output.lines().collect::<Vec<_>>();
+        let total_lines = lines.len();
+        let start = start_line.max(1);
+        let requested_end = end_line
+            .unwrap_or(start + MAX_TOOL_RESULT_RANGE_LINES - 1)
+            .max(start);
+        let end = requested_end
+            .min(start + MAX_TOOL_RESULT_RANGE_LINES - 1)
+            .min(total_lines);
+        let mut text = String::new();
+        let mut last_line = start.saturating_sub(1);
+        let mut truncated = end < requested_end.min(total_lines);
+        for (idx, line) in lines.iter().enumerate().take(end).skip(start - 1) {
+            if text.len() + line.len() + 1 > MAX_TOOL_RESULT_RANGE_CHARS && !text.is_empty() {
+                truncated = true;
+                break;
+            }
+            text.push_str(line);
+            text.push('\n');
+            last_line = idx + 1;
+        }
+        Some(ToolResultRange {
+            handle: handle.to_string(),
+            start_line: start,
+            end_line: last_line,
+            total_lines,
+            text,
+            truncated,
+        })
+    }
+}
+
+/// Prompt asking a model to summarize an oversized tool output.
+pub fn tool_result_summary_prompt(tool: &str, output: &str) -> String {
+    let total_chars = output.chars().count();
+    let (sample, clipped) = if total_chars <= MAX_SUMMARY_INPUT_CHARS {
+        (output.to_string(), "")
+    } else {
+        let head_chars = MAX_SUMMARY_INPUT_CHARS * 3 / 4;
+        let tail_chars = MAX_SUMMARY_INPUT_CHARS - head_chars;
+        let head = output.chars().take(head_chars).collect::<String>();
+        let tail = output
+            .chars()
+            .skip(total_chars - tail_chars)
+            .collect::<String>();
+        (
+            format!(
+                "{head}\n… {} characters omitted …\n{tail}",
+                total_chars - head_chars - tail_chars
+            ),
+            "\n(output clipped for summarization)",
+        )
+    };
+    format!(
+        "Summarize the output of the `{tool}` tool for an agent that cannot see it. \
+Keep concrete facts: file paths, line numbers, identifiers, counts, errors. \
+Note which parts look most relevant and where (by line number) they appear. \
+Reply with at most 20 short bullet points.\n\n<output>\n{sample}\n</output>{clipped}"
+    )
+}
+
+/// Summary used when no provider is available: the head and tail of the output.
+pub fn fallback_tool_result_summary(output: &str) -> String {
+    let lines = output.lines().collect::<Vec<_>>();
+    if lines.len() <= FALLBACK_PREVIEW_LINES * 2 {
+        return lines.join("\n");
+    }
+    format!(
+        "{}\n… {} lines omitted …\n{}",
+        lines[..FALLBACK_PREVIEW_LINES].join("\n"),
+        lines.len() - FALLBACK_PREVIEW_LINES * 2,
+        lines[lines.len() - FALLBACK_PREVIEW_LINES..].join("\n")
+    )
+}
+
+/// Text injected into the conversation in place of the full output.
+pub fn render_summarized_tool_result(record: &ToolResultRecord, summary: &str) -> String {
+    format!(
+        "[output summarized: {} lines, ~{} tokens; full result stored as handle={}]\n{}\n\n\
+Use the `tool_result_read` tool with handle `{}` and startLine/endLine to read exact lines.",
+        record.total_lines,
+        record.estimated_tokens,
+        record.handle,
+        summary.trim(),
+        record.handle
+    )
+}
+
+/// Summary text for an output that was not stored (incognito sessions), so
+/// there is no handle to read it back through.
+pub fn render_unstored_tool_result(output: &str, summary: &str) -> String {
+    format!(
+        "[output summarized: {} lines, ~{} tokens; the full result was not stored]\n{}",
+        output.lines().count(),
+        estimate_tokens(output),
+        summary.trim()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn stored_results_are_read_back_by_line_range() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let store = ToolResultStore::new(dir.path().join("tool-results"));
+        let output = (1..=1000)
+            .map(|n| format!("match {n}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let record = store.save("s1", "m1", "grep", &output).await.expect("save");
+        assert_eq!(record.total_lines, 1000);
+        assert_eq!(store.get(&record.handle).await, Some(record.clone()));
+
+        let range = store
+            .read_range(&record.handle, 10, Some(12))
+            .await
+            .expect("range");
+        assert_eq!(range.text, "match 10\nmatch 11\nmatch 12\n");
+        assert_eq!((range.start_line, range.end_line), (10, 12));
+        assert!(!range.truncated);
+
+        let capped = store
+            .read_range(&record.handle, 1, Some(1000))
+            .await
+            .expect("range");
+        assert_eq!(capped.end_line, MAX_TOOL_RESULT_RANGE_LINES);
+        assert!(capped.truncated);
+        assert!(store.read_range("../etc", 1, None).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn stored_results_are_removed_with_their_session_or_when_stale() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let store = ToolResultStore::new(dir.path().join("tool-results"));
+        let kept = store.save("s1", "m1", "grep", "a").await.expect("save");
+        let other = store.save("s2", "m2", "grep", "b").await.expect("save");
+        assert_eq!(store.delete_session("s2").await, 1);
+        assert!(store.get(&other.handle).await.is_none());
+        assert!(store.get(&kept.handle).await.is_some());
+
+        let stale = ToolResultRecord {
+            created_at_ms: kept.created_at_ms - TOOL_RESULT_RETENTION_MS - 1,
+            ..kept.clone()
+        };
+        let meta = dir
+            .path()
+            .join("tool-results")
+            .join(&kept.handle)
+            .join("meta.json");
+        std::fs::write(&meta, serde_json::to_vec(&stale).expect("json")).expect("write");
+        let fresh = store.save("s1", "m3", "grep", "c").await.expect("save");
+        assert!(store.get(&kept.handle).await.is_none());
+        assert!(store.get(&fresh.handle).await.is_some());
+    }
+
+    #[test]
+    fn summary_prompt_input_is_capped_to_head_and_tail() {
+        let output = format!("HEAD{}TAIL", "x".repeat(MAX_SUMMARY_INPUT_CHARS * 4));
+        let prompt = tool_result_summary_prompt("grep", &output);
+        assert!(prompt.chars().count() < MAX_SUMMARY_INPUT_CHARS + 1_000);
+        assert!(prompt.contains("HEAD"));
+        assert!(prompt.contains("TAIL\n</output>"));
+        assert!(prompt.ends_with("(output clipped for summarization)"));
+        let short = tool_result_summary_prompt("grep", "one line");
+        assert!(short.contains("<output>\none line\n</output>"));
+        assert!(!short.contains("clipped"));
+    }
+
+    #[test]
+    fn summarized_result_points_at_the_handle() {
+        let record = ToolResultRecord {
+            handle: "tres-1".to_string(),
+            session_id: "s1".to_string(),
+            message_id: "m1".to_string(),
+            tool: "webfetch".to_string(),
+            total_chars: 40_000,
+            total_lines: 900,
+            estimated_tokens: 10_000,
+            sha256: String::new(),
+            created_at_ms: 0,
+        };
+        let text = render_summarized_tool_result(&record, "- a page about rust");
+        assert!(text.contains("handle=tres-1"));
+        assert!(text.contains("tool_result_read"));
+        assert_eq!(estimate_tokens("abcdefgh"), 2);
+        let long = (1..=100).map(|n| n.to_string()).collect::<Vec<_>>();
+        let preview = fallback_tool_result_summary(&long.join("\n"));
+        assert!(preview.contains("60 lines omitted"));
+        let unstored = render_unstored_tool_result(&long.join("\n"), "- numbers");
+        assert!(unstored.starts_with("[output summarized: 100 lines"));
+        assert!(!unstored.contains("tool_result_read"));
+    }
+}
@@ -0,0 +1,180 @@
+//! Retry policy for provider turns that come back unusable.
+//!
+//! A turn is retried when the model returns neither text nor tool calls, or
+//! when it emits tool calls whose arguments cannot be parsed. Each retry
+//! raises the sampling temperature a step, and the last retry can switch to a
+//! fallback model.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const DEFAULT_TURN_RETRIES: usize = 2;
+const DEFAULT_RETRY_TEMPERATURE: f32 = 0.4;
+const DEFAULT_TEMPERATURE_STEP: f32 = 0.3;
+const MAX_RETRY_TEMPERATURE: f32 = 1.2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TurnRetryPolicy {
+    /// Retries per turn; 0 disables turn retries.
+    pub max_retries: usize,
+    /// Temperature used for the first retry.
+    pub initial_temperature: f32,
+    /// Added to the temperature on every further retry.
+    pub temperature_step: f32,
+    /// `provider/model` used for the final retry.
+    pub fallback_model: Option<(String, String)>,
+}
+
+impl Default for TurnRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: DEFAULT_TURN_RETRIES,
+            initial_temperature: DEFAULT_RETRY_TEMPERATURE,
+            temperature_step: DEFAULT_TEMPERATURE_STEP,
+            fallback_model: None,
+        }
+    }
+}
+
+fn env_parse<T: std::str::FromStr>(key: &str) -> Option<T> {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.trim().parse::<T>().ok())
+}
+
+impl TurnRetryPolicy {
+    /// Policy from `TANDEM_TURN_RETRY_ATTEMPTS`,
+    /// `TANDEM_TURN_RETRY_TEMPERATURE`, `TANDEM_TURN_RETRY_TEMPERATURE_STEP`
+    /// and `TANDEM_TURN_RETRY_FALLBACK_MODEL` (`provider/model`).
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            max_retries: env_parse("TANDEM_TURN_RETRY_ATTEMPTS").unwrap_or(defaults.max_retries),
+            initial_temperature: env_parse("TANDEM_TURN_RETRY_TEMPERATURE")
+                .unwrap_or(defaults.initial_temperature),
+            temperature_step: env_parse("TANDEM_TURN_RETRY_TEMPERATURE_STEP")
+                .unwrap_or(defaults.temperature_step),
+            fallback_model: std::env::var("TANDEM_TURN_RETRY_FALLBACK_MODEL")
+                .ok()
+                .and_then(|raw| parse_model_route(&raw)),
+        }
+    }
+
+    /// Sampling temperature for `attempt` (0 is the original call, which keeps
+    /// the provider default).
+    pub fn temperature_for(&self, attempt: usize) -> Option<f32> {
+        if attempt == 0 {
+            return None;
+        }
+        let bumped = self.initial_temperature + self.temperature_step * (attempt - 1) as f32;
+        Some(bumped.clamp(0.0, MAX_RETRY_TEMPERATURE))
+    }
+
+    /// Provider and model for `attempt`; the final retry uses the fallback
+    /// model when one is configured.
+    pub fn route_for(&self, attempt: usize, provider_id: &str, model_id: &str) -> (String, String) {
+        match self.fallback_model.as_ref() {
+            Some((provider, model)) if attempt > 0 && attempt == self.max_retries => {
+                (provider.clone(), model.clone())
+            }
+            _ => (provider_id.to_string(), model_id.to_string()),
+        }
+    }
+}
+
+fn parse_model_route(raw: &str) -> Option<(String, String)> {
+    let (provider, model) = raw.trim().split_once('/')?;
+    let (provider, model) = (provider.trim(), model.trim());
+    if provider.is_empty() || model.is_empty() {
+        return None;
+    }
+    Some((provider.to_string(), model.to_string()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnOutputIssue {
+    EmptyOutput,
+    InvalidToolArgs,
+}
+
+impl TurnOutputIssue {
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::EmptyOutput => "EMPTY_OUTPUT",
+            Self::InvalidToolArgs => "INVALID_TOOL_ARGS",
+        }
+    }
+}
+
+/// Raw streamed arguments that neither parse as JSON nor recover into any
+/// arguments.
+pub fn is_malformed_tool_args(raw_args: &str, recovered: &Value) -> bool {
+    let trimmed = raw_args.trim();
+    !trimmed.is_empty()
+        && serde_json::from_str::<Value>(trimmed).is_err()
+        && recovered.as_object().is_some_and(|obj| obj.is_empty())
+}
+
+/// Why a turn's output is unusable, if it is.
+pub fn classify_turn_output(
+    completion: &str,
+    tool_call_count: usize,
+    malformed_tool_calls: usize,
+) -> Option<TurnOutputIssue> {
+    if malformed_tool_calls > 0 {
+        return Some(TurnOutputIssue::InvalidToolArgs);
+    }
+    if completion.trim().is_empty() && tool_call_count == 0 {
+        return Some(TurnOutputIssue::EmptyOutput);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn retries_escalate_temperature_and_switch_model_last() {
+        let policy = TurnRetryPolicy {
+            fallback_model: Some(("openai".to_string(), "gpt-5.2".to_string())),
+            ..TurnRetryPolicy::default()
+        };
+        assert_eq!(policy.temperature_for(0), None);
+        assert_eq!(policy.temperature_for(1), Some(0.4));
+        assert!((policy.temperature_for(2).unwrap() - 0.7).abs() < 1e-6);
+        assert_eq!(policy.temperature_for(10), Some(MAX_RETRY_TEMPERATURE));
+
+        assert_eq!(
+            policy.route_for(1, "ollama", "llama3"),
+            ("ollama".to_string(), "llama3".to_string())
+        );
+        assert_eq!(
+            policy.route_for(2, "ollama", "llama3"),
+            ("openai".to_string(), "gpt-5.2".to_string())
+        );
+        assert_eq!(
+            parse_model_route("openrouter/meta/llama").unwrap().1,
+            "meta/llama"
+        );
+        assert!(parse_model_route("no-slash").is_none());
+    }
+
+    #[test]
+    fn classifies_empty_and_malformed_turns() {
+        assert_eq!(
+            classify_turn_output("  ", 0, 0),
+            Some(TurnOutputIssue::EmptyOutput)
+        );
+        assert_eq!(classify_turn_output("", 1, 0), None);
+        assert_eq!(
+            classify_turn_output("text", 1, 1),
+            Some(TurnOutputIssue::InvalidToolArgs)
+        );
+        assert!(is_malformed_tool_args("{\"path\": ", &json!({})));
+        assert!(!is_malformed_tool_args("", &json!({})));
+        assert!(!is_malformed_tool_args("path=src", &json!({"path": "src"})));
+    }
+}
@@ -3,10 +3,12 @@
 
 use crate::types::{
     ClearFileIndexResult, MemoryChunk, MemoryConfig, MemoryResult, MemoryStats, MemoryTier,
-    ProjectMemoryStats, DEFAULT_EMBEDDING_DIMENSION,
+    ProjectMemoryStats, DEFAULT_EMBEDDING_DIMENSION, DEFAULT_EMBEDDING_MODEL,
 };
 use chrono::{DateTime, Utc};
-use rusqlite::{ffi::sqlite3_auto_extension, params, Connection, OptionalExtension, Row};
+use rusqlite::{
+    ffi::sqlite3_auto_extension, params, Connection, OpenFlags, OptionalExtension, Row,
+};
 use sqlite_vec::sqlite3_vec_init;
 use std::collections::HashSet;
 use std::path::Path;
@@ -14,6 +16,12 @@ use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::Mutex;
 
+const CHUNK_TABLES: [&str; 3] = [
+    "session_memory_chunks",
+    "project_memory_chunks",
+    "global_memory_chunks",
+];
+
 type ProjectIndexStatusRow = (
     Option<String>,
     Option<i64>,
@@ -319,9 +327,29 @@ impl MemoryDatabase {
             [],
         )?;
 
+        // Migrations: embedding model version per chunk (NULL = legacy default
+        // model) and write-time provenance (JSON)
+        for table in CHUNK_TABLES {
+            let cols: HashSet<String> = {
+                let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+                let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
+                rows.collect::<Result<HashSet<_>, _>>()?
+            };
+            for column in ["embedding_model", "provenance"] {
+                if !cols.contains(column) {
+                    conn.execute(&format!("ALTER TABLE {table} ADD COLUMN {column} TEXT"), [])?;
+                }
+            }
+        }
+
         Ok(())
     }
 
+    /// Path of the underlying SQLite file
+    pub fn path(&self) -> &Path {
+        &self.db_path
+    }
+
     /// Validate that sqlite-vec tables are readable.
     /// This catches legacy/corrupted vector blobs early so startup can recover.
     pub async fn validate_vector_tables(&self) -> MemoryResult<()> {
@@ -603,6 +631,22 @@ impl MemoryDatabase {
             params![chunk.id, embedding_json],
         )?;
 
+        if let Some(model) = chunk.embedding_model.as_deref() {
+            conn.execute(
+                &format!(
+                    "UPDATE {} SET embedding_model = ?1 WHERE id = ?2",
+                    chunks_table
+                ),
+                params![model, chunk.id],
+            )?;
+        }
+        if let Some(provenance) = chunk.provenance.as_ref().filter(|p| !p.is_empty()) {
+            conn.execute(
+                &format!("UPDATE {} SET provenance = ?1 WHERE id = ?2", chunks_table),
+                params![serde_json::to_string(provenance)?, chunk.id],
+            )?;
+        }
+
         Ok(())
     }
 
@@ -638,7 +682,7 @@ impl MemoryDatabase {
                 if let Some(sid) = session_id {
                     let sql = format!(
                         "SELECT c.id, c.content, c.session_id, c.project_id, c.source, c.created_at, c.token_count, c.metadata,
-                                v.distance
+                                v.distance, c.embedding_model, c.provenance
                          FROM {} AS v
                          JOIN {} AS c ON v.chunk_id = c.id
                          WHERE c.session_id = ?1 AND v.embedding MATCH ?2 AND k = ?3
@@ -655,7 +699,7 @@ impl MemoryDatabase {
                 } else if let Some(pid) = project_id {
                     let sql = format!(
                         "SELECT c.id, c.content, c.session_id, c.project_id, c.source, c.created_at, c.token_count, c.metadata,
-                                v.distance
+                                v.distance, c.embedding_model, c.provenance
                          FROM {} AS v
                          JOIN {} AS c ON v.chunk_id = c.id
                          WHERE c.project_id = ?1 AND v.embedding MATCH ?2 AND k = ?3
@@ -672,7 +716,7 @@ impl MemoryDatabase {
                 } else {
                     let sql = format!(
                         "SELECT c.id, c.content, c.session_id, c.project_id, c.source, c.created_at, c.token_count, c.metadata,
-                                v.distance
+                                v.distance, c.embedding_model, c.provenance
                          FROM {} AS v
                          JOIN {} AS c ON v.chunk_id = c.id
                          WHERE v.embedding MATCH ?1 AND k = ?2
@@ -693,7 +737,7 @@ impl MemoryDatabase {
                     let sql = format!(
                         "SELECT c.id, c.content, c.session_id, c.project_id, c.source, c.created_at, c.token_count, c.metadata,
                                 c.source_path, c.source_mtime, c.source_size, c.source_hash,
-                                v.distance
+                                v.distance, c.embedding_model, c.provenance
                          FROM {} AS v
                          JOIN {} AS c ON v.chunk_id = c.id
                          WHERE c.project_id = ?1 AND v.embedding MATCH ?2 AND k = ?3
@@ -711,7 +755,7 @@ impl MemoryDatabase {
                     let sql = format!(
                         "SELECT c.id, c.content, c.session_id, c.project_id, c.source, c.created_at, c.token_count, c.metadata,
                                 c.source_path, c.source_mtime, c.source_size, c.source_hash,
-                                v.distance
+                                v.distance, c.embedding_model, c.provenance
                          FROM {} AS v
                          JOIN {} AS c ON v.chunk_id = c.id
                          WHERE v.embedding MATCH ?1 AND k = ?2
@@ -730,7 +774,7 @@ impl MemoryDatabase {
             MemoryTier::Global => {
                 let sql = format!(
                     "SELECT c.id, c.content, NULL as session_id, NULL as project_id, c.source, c.created_at, c.token_count, c.metadata,
-                            v.distance
+                            v.distance, c.embedding_model, c.provenance
                      FROM {} AS v
                      JOIN {} AS c ON v.chunk_id = c.id
                      WHERE v.embedding MATCH ?1 AND k = ?2
@@ -750,6 +794,285 @@ impl MemoryDatabase {
         Ok(results)
     }
 
+    /// Count chunks per tier and embedding model (legacy rows report the default model)
+    pub async fn embedding_model_counts(&self) -> MemoryResult<Vec<(MemoryTier, String, i64)>> {
+        let conn = self.conn.lock().await;
+        let mut counts = Vec::new();
+        for tier in [MemoryTier::Session, MemoryTier::Project, MemoryTier::Global] {
+            let sql = format!(
+                "SELECT COALESCE(embedding_model, ?1) AS model, COUNT(*)
+                 FROM {}_memory_chunks
+                 GROUP BY model
+                 ORDER BY model",
+                tier.table_prefix()
+            );
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt
+                .query_map(params![DEFAULT_EMBEDDING_MODEL], |row| {
+                    Ok((tier, row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            counts.extend(rows);
+        }
+        Ok(counts)
+    }
+
+    /// Count chunks whose vectors were produced by a model other than `model`
+    pub async fn count_stale_embeddings(&self, model: &str) -> MemoryResult<i64> {
+        let conn = self.conn.lock().await;
+        let mut total = 0i64;
+        for table in CHUNK_TABLES {
+            let sql =
+                format!("SELECT COUNT(*) FROM {table} WHERE COALESCE(embedding_model, ?1) != ?2");
+            total += conn.query_row(&sql, params![DEFAULT_EMBEDDING_MODEL, model], |row| {
+                row.get::<_, i64>(0)
+            })?;
+        }
+        Ok(total)
+    }
+
+    /// Next batch of `(tier, chunk_id, content)` still embedded with a model other than `model`
+    pub async fn stale_embedding_batch(
+        &self,
+        model: &str,
+        limit: usize,
+    ) -> MemoryResult<Vec<(MemoryTier, String, String)>> {
+        let conn = self.conn.lock().await;
+        let mut batch = Vec::new();
+        for tier in [MemoryTier::Session, MemoryTier::Project, MemoryTier::Global] {
+            let remaining = limit.saturating_sub(batch.len());
+            if remaining == 0 {
+                break;
+            }
+            let sql = format!(
+                "SELECT id, content FROM {}_memory_chunks
+                 WHERE COALESCE(embedding_model, ?1) != ?2
+                 ORDER BY created_at
+                 LIMIT ?3",
+                tier.table_prefix()
+            );
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt
+                .query_map(
+                    params![DEFAULT_EMBEDDING_MODEL, model, remaining as i64],
+                    |row| Ok((tier, row.get::<_, String>(0)?, row.get::<_, String>(1)?)),
+                )?
+                .collect::<Result<Vec<_>, _>>()?;
+            batch.extend(rows);
+        }
+        Ok(batch)
+    }
+
+    /// Replace a chunk's vector and record the model that produced it
+    pub async fn replace_chunk_embedding(
+        &self,
+        tier: MemoryTier,
+        chunk_id: &str,
+        embedding: &[f32],
+        model: &str,
+    ) -> MemoryResult<()> {
+        let mut conn = self.conn.lock().await;
+        let embedding_json = format!(
+            "[{}]",
+            embedding
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+        let prefix = tier.table_prefix();
+        // All or nothing: a chunk must never be left without a vector, or
+        // with a new vector still tagged with the old model.
+        let tx = conn.transaction()?;
+        tx.execute(
+            &format!("DELETE FROM {prefix}_memory_vectors WHERE chunk_id = ?1"),
+            params![chunk_id],
+        )?;
+        tx.execute(
+            &format!("INSERT INTO {prefix}_memory_vectors (chunk_id, embedding) VALUES (?1, ?2)"),
+            params![chunk_id, embedding_json],
+        )?;
+        tx.execute(
+            &format!("UPDATE {prefix}_memory_chunks SET embedding_model = ?1 WHERE id = ?2"),
+            params![model, chunk_id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Keyword match over chunks not yet re-embedded with `model`. Their vectors
+    /// live in a different embedding space, so vector distance is meaningless
+    /// against a query embedded with the current model.
+    pub async fn search_stale_chunks_lexical(
+        &self,
+        tier: MemoryTier,
+        model: &str,
+        terms: &[String],
+        project_id: Option<&str>,
+        session_id: Option<&str>,
+        limit: usize,
+    ) -> MemoryResult<Vec<(MemoryChunk, usize)>> {
+        if terms.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+        let conn = self.conn.lock().await;
+        let columns = match tier {
+            MemoryTier::Session => {
+                "id, content, session_id, project_id, source, created_at, token_count, metadata,
+                 embedding_model, provenance"
+            }
+            MemoryTier::Project => {
+                "id, content, session_id, project_id, source, created_at, token_count, metadata,
+                 source_path, source_mtime, source_size, source_hash, embedding_model, provenance"
+            }
+            MemoryTier::Global => {
+                "id, content, NULL as session_id, NULL as project_id, source, created_at, token_count, metadata,
+                 embedding_model, provenance"
+            }
+        };
+        let scope = match (tier, session_id, project_id) {
+            (MemoryTier::Session, Some(_), _) => "AND session_id = ?3",
+            (MemoryTier::Session | MemoryTier::Project, _, Some(_)) => "AND project_id = ?3",
+            _ => "AND ?3 IS NULL",
+        };
+        let term_clause = (0..terms.len())
+            .map(|i| format!("LOWER(content) LIKE ?{}", i + 4))
+            .collect::<Vec<_>>()
+            .join(" OR ");
+        let sql = format!(
+            "SELECT {columns} FROM {}_memory_chunks
+             WHERE COALESCE(embedding_model, ?1) != ?2 {scope} AND ({term_clause})
+             ORDER BY created_at DESC
+             LIMIT 200",
+            tier.table_prefix()
+        );
+        let scope_value = match (tier, session_id, project_id) {
+            (MemoryTier::Session, Some(sid), _) => Some(sid.to_string()),
+            (MemoryTier::Session | MemoryTier::Project, _, Some(pid)) => Some(pid.to_string()),
+            _ => None,
+        };
+        let mut values: Vec<Box<dyn rusqlite::ToSql>> = vec![
+            Box::new(DEFAULT_EMBEDDING_MODEL.to_string()),
+            Box::new(model.to_string()),
+            Box::new(scope_value),
+        ];
+        for term in terms {
+            values.push(Box::new(format!("%{}%", term.to_lowercase())));
+        }
+        let mut stmt = conn.prepare(&sql)?;
+        let mut matches = stmt
+            .query_map(
+                rusqlite::params_from_iter(values.iter().map(|value| value.as_ref())),
+                |row| row_to_chunk(row, tier),
+            )?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .map(|chunk| {
+                let content = chunk.content.to_lowercase();
+                let hits = terms
+                    .iter()
+                    .filter(|term| content.contains(&term.to_lowercase()))
+                    .count();
+                (chunk, hits)
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by_key(|(_, hits)| std::cmp::Reverse(*hits));
+        matches.truncate(limit);
+        Ok(matches)
+    }
+
+    /// Look up a chunk by ID in any tier
+    pub async fn get_chunk(&self, chunk_id: &str) -> MemoryResult<Option<MemoryChunk>> {
+        let ids = [chunk_id.to_string()];
+        for tier in [MemoryTier::Session, MemoryTier::Project, MemoryTier::Global] {
+            if let Some(chunk) = self.get_chunks_by_ids(tier, &ids).await?.pop() {
+                return Ok(Some(chunk));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Count embeddings persisted for a tier
+    pub async fn count_tier_vectors(&self, tier: MemoryTier) -> MemoryResult<i64> {
+        let conn = self.conn.lock().await;
+        let sql = format!(
+            "SELECT COUNT(*) FROM {}_memory_vectors",
+            tier.table_prefix()
+        );
+        Ok(conn.query_row(&sql, [], |row| row.get(0))?)
+    }
+
+    /// Load every `(chunk_id, embedding)` pair for a tier, used to build
+    /// in-process vector indexes
+    pub async fn load_tier_vectors(
+        &self,
+        tier: MemoryTier,
+    ) -> MemoryResult<Vec<(String, Vec<f32>)>> {
+        let conn = self.conn.lock().await;
+        let sql = format!(
+            "SELECT chunk_id, embedding FROM {}_memory_vectors",
+            tier.table_prefix()
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map([], |row| {
+                let blob: Vec<u8> = row.get(1)?;
+                let embedding = blob
+                    .chunks_exact(4)
+                    .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .collect::<Vec<_>>();
+                Ok((row.get::<_, String>(0)?, embedding))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// Fetch chunks of a tier by ID, preserving the order of `ids`
+    pub async fn get_chunks_by_ids(
+        &self,
+        tier: MemoryTier,
+        ids: &[String],
+    ) -> MemoryResult<Vec<MemoryChunk>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let conn = self.conn.lock().await;
+        let columns = match tier {
+            MemoryTier::Session => {
+                "id, content, session_id, project_id, source, created_at, token_count, metadata,
+                 embedding_model, provenance"
+            }
+            MemoryTier::Project => {
+                "id, content, session_id, project_id, source, created_at, token_count, metadata,
+                 source_path, source_mtime, source_size, source_hash, embedding_model, provenance"
+            }
+            MemoryTier::Global => {
+                "id, content, NULL as session_id, NULL as project_id, source, created_at, token_count, metadata,
+                 embedding_model, provenance"
+            }
+        };
+        let placeholders = (1..=ids.len())
+            .map(|i| format!("?{i}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let sql = format!(
+            "SELECT {} FROM {}_memory_chunks WHERE id IN ({})",
+            columns,
+            tier.table_prefix(),
+            placeholders
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let mut by_id = stmt
+            .query_map(rusqlite::params_from_iter(ids.iter()), |row| {
+                row_to_chunk(row, tier)
+            })?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .map(|chunk| (chunk.id.clone(), chunk))
+            .collect::<std::collections::HashMap<_, _>>();
+        Ok(ids.iter().filter_map(|id| by_id.remove(id)).collect())
+    }
+
     /// Get chunks by session ID
     pub async fn get_session_chunks(&self, session_id: &str) -> MemoryResult<Vec<MemoryChunk>> {
         let conn = self.conn.lock().await;
@@ -839,6 +1162,8 @@ impl MemoryDatabase {
                     created_at,
                     token_count,
                     metadata,
+                    embedding_model: None,
+                    provenance: None,
                 })
             })?
             .collect::<Result<Vec<_>, _>>()?;
@@ -1116,6 +1441,13 @@ impl MemoryDatabase {
         Ok(())
     }
 
+    /// Run SQLite's `PRAGMA quick_check`. Returns the reported problems; an
+    /// empty list means the file is structurally sound.
+    pub async fn quick_check(&self) -> MemoryResult<Vec<String>> {
+        let conn = self.conn.lock().await;
+        quick_check_conn(&conn)
+    }
+
     /// Vacuum the database to reclaim space
     pub async fn vacuum(&self) -> MemoryResult<()> {
         let conn = self.conn.lock().await;
@@ -1526,6 +1858,15 @@ fn row_to_chunk(row: &Row, tier: MemoryTier) -> Result<MemoryChunk, rusqlite::Er
     let source_mtime = row.get::<_, Option<i64>>("source_mtime").ok().flatten();
     let source_size = row.get::<_, Option<i64>>("source_size").ok().flatten();
     let source_hash = row.get::<_, Option<String>>("source_hash").ok().flatten();
+    let embedding_model = row
+        .get::<_, Option<String>>("embedding_model")
+        .ok()
+        .flatten();
+    let provenance = row
+        .get::<_, Option<String>>("provenance")
+        .ok()
+        .flatten()
+        .and_then(|raw| serde_json::from_str(&raw).ok());
 
     Ok(MemoryChunk {
         id,
@@ -1541,9 +1882,35 @@ fn row_to_chunk(row: &Row, tier: MemoryTier) -> Result<MemoryChunk, rusqlite::Er
         created_at,
         token_count,
         metadata,
+        embedding_model,
+        provenance,
     })
 }
 
+/// [`MemoryDatabase::quick_check`] on a separate read-only connection to the
+/// file at `path`, without running schema setup. Blocking; call it from
+/// `spawn_blocking`.
+pub fn quick_check_path(path: &Path) -> MemoryResult<Vec<String>> {
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    quick_check_conn(&conn)
+}
+
+fn quick_check_conn(conn: &Connection) -> MemoryResult<Vec<String>> {
+    let mut stmt = conn.prepare("PRAGMA quick_check")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+    let mut problems = Vec::new();
+    for row in rows {
+        let row = row?;
+        if row != "ok" {
+            problems.push(row);
+        }
+    }
+    Ok(problems)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1558,10 +1925,13 @@ mod tests {
 
     #[tokio::test]
     async fn test_init_schema() {
-        let (db, _temp) = setup_test_db().await;
+        let (db, temp) = setup_test_db().await;
         // If we get here, schema was initialized successfully
         let stats = db.get_stats().await.unwrap();
         assert_eq!(stats.total_chunks, 0);
+        assert!(db.quick_check().await.unwrap().is_empty());
+        let db_path = temp.path().join("test_memory.db");
+        assert!(quick_check_path(&db_path).unwrap().is_empty());
     }
 
     #[tokio::test]
@@ -1582,6 +1952,8 @@ mod tests {
             created_at: Utc::now(),
             token_count: 10,
             metadata: None,
+            embedding_model: None,
+            provenance: None,
         };
 
         let embedding = vec![0.1f32; DEFAULT_EMBEDDING_DIMENSION];
@@ -1592,6 +1964,194 @@ mod tests {
         assert_eq!(chunks[0].content, "Test content");
     }
 
+    #[tokio::test]
+    async fn test_load_tier_vectors_and_chunks_by_ids() {
+        let (db, _temp) = setup_test_db().await;
+
+        for (id, value) in [("global-1", 0.25f32), ("global-2", 0.5f32)] {
+            let chunk = MemoryChunk {
+                id: id.to_string(),
+                content: format!("content {id}"),
+                tier: MemoryTier::Global,
+                session_id: None,
+                project_id: None,
+                source: "user_message".to_string(),
+                source_path: None,
+                source_mtime: None,
+                source_size: None,
+                source_hash: None,
+                created_at: Utc::now(),
+                token_count: 2,
+                metadata: None,
+                embedding_model: None,
+                provenance: None,
+            };
+            let embedding = vec![value; DEFAULT_EMBEDDING_DIMENSION];
+            db.store_chunk(&chunk, &embedding).await.unwrap();
+        }
+
+        assert_eq!(db.count_tier_vectors(MemoryTier::Global).await.unwrap(), 2);
+        let vectors = db.load_tier_vectors(MemoryTier::Global).await.unwrap();
+        let loaded = vectors
+            .iter()
+            .find(|(id, _)| id == "global-2")
+            .expect("vector");
+        assert_eq!(loaded.1.len(), DEFAULT_EMBEDDING_DIMENSION);
+        assert_eq!(loaded.1[0], 0.5);
+
+        let chunks = db
+            .get_chunks_by_ids(
+                MemoryTier::Global,
+                &[
+                    "global-2".to_string(),
+                    "missing".to_string(),
+                    "global-1".to_string(),
+                ],
+            )
+            .await
+            .unwrap();
+        let ids = chunks.iter().map(|c| c.id.as_str()).collect::<Vec<_>>();
+        assert_eq!(ids, vec!["global-2", "global-1"]);
+    }
+
+    #[tokio::test]
+    async fn test_embedding_model_versions_and_reembedding() {
+        let (db, _temp) = setup_test_db().await;
+
+        for (id, content, model) in [
+            ("legacy-1", "deploy pipeline uses blue green rollout", None),
+            (
+                "new-1",
+                "deploy notes for the new model",
+                Some("bge-small-en-v1.5"),
+            ),
+        ] {
+            let chunk = MemoryChunk {
+                id: id.to_string(),
+                content: content.to_string(),
+                tier: MemoryTier::Project,
+                session_id: None,
+                project_id: Some("project-1".to_string()),
+                source: "user_message".to_string(),
+                source_path: None,
+                source_mtime: None,
+                source_size: None,
+                source_hash: None,
+                created_at: Utc::now(),
+                token_count: 5,
+                metadata: None,
+                embedding_model: model.map(ToString::to_string),
+                provenance: None,
+            };
+            db.store_chunk(&chunk, &vec![0.1f32; DEFAULT_EMBEDDING_DIMENSION])
+                .await
+                .unwrap();
+        }
+
+        let counts = db.embedding_model_counts().await.unwrap();
+        assert!(counts.contains(&(MemoryTier::Project, DEFAULT_EMBEDDING_MODEL.to_string(), 1)));
+        assert!(counts.contains(&(MemoryTier::Project, "bge-small-en-v1.5".to_string(), 1)));
+        assert_eq!(
+            db.count_stale_embeddings("bge-small-en-v1.5")
+                .await
+                .unwrap(),
+            1
+        );
+
+        let stale = db
+            .search_stale_chunks_lexical(
+                MemoryTier::Project,
+                "bge-small-en-v1.5",
+                &["rollout".to_string(), "deploy".to_string()],
+                Some("project-1"),
+                None,
+                5,
+            )
+            .await
+            .unwrap();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].0.id, "legacy-1");
+        assert_eq!(stale[0].1, 2);
+
+        let batch = db
+            .stale_embedding_batch("bge-small-en-v1.5", 10)
+            .await
+            .unwrap();
+        assert_eq!(batch.len(), 1);
+        db.replace_chunk_embedding(
+            MemoryTier::Project,
+            "legacy-1",
+            &vec![0.2f32; DEFAULT_EMBEDDING_DIMENSION],
+            "bge-small-en-v1.5",
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            db.count_stale_embeddings("bge-small-en-v1.5")
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(db.count_tier_vectors(MemoryTier::Project).await.unwrap(), 2);
+        let migrated = db
+            .get_chunks_by_ids(MemoryTier::Project, &["legacy-1".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(
+            migrated[0].embedding_model.as_deref(),
+            Some("bge-small-en-v1.5")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chunk_provenance_round_trips() {
+        let (db, _temp) = setup_test_db().await;
+        let provenance = crate::types::MemoryProvenance {
+            session_id: Some("session-9".to_string()),
+            message_id: Some("msg-3".to_string()),
+            run_id: Some("run-7".to_string()),
+            tool_call_id: Some("call-1".to_string()),
+            tool_name: Some("memory_store".to_string()),
+        };
+        let chunk = MemoryChunk {
+            id: "prov-1".to_string(),
+            content: "remember the staging port is 8443".to_string(),
+            tier: MemoryTier::Project,
+            session_id: Some("session-9".to_string()),
+            project_id: Some("project-1".to_string()),
+            source: "agent_note".to_string(),
+            source_path: None,
+            source_mtime: None,
+            source_size: None,
+            source_hash: None,
+            created_at: Utc::now(),
+            token_count: 6,
+            metadata: None,
+            embedding_model: None,
+            provenance: Some(provenance.clone()),
+        };
+        db.store_chunk(&chunk, &vec![0.3f32; DEFAULT_EMBEDDING_DIMENSION])
+            .await
+            .unwrap();
+
+        let stored = db.get_chunk("prov-1").await.unwrap().expect("chunk");
+        assert_eq!(stored.tier, MemoryTier::Project);
+        assert_eq!(stored.provenance, Some(provenance.clone()));
+
+        let hits = db
+            .search_similar(
+                &vec![0.3f32; DEFAULT_EMBEDDING_DIMENSION],
+                MemoryTier::Project,
+                Some("project-1"),
+                None,
+                1,
+            )
+            .await
+            .unwrap();
+        assert_eq!(hits[0].0.provenance, Some(provenance));
+        assert!(db.get_chunk("missing").await.unwrap().is_none());
+    }
+
     #[tokio::test]
     async fn test_config_crud() {
         let (db, _temp) = setup_test_db().await;
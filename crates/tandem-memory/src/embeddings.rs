@@ -17,21 +17,59 @@ type EmbeddingBackend = TextEmbedding;
 #[cfg(not(feature = "local-embeddings"))]
 type EmbeddingBackend = ();
 
+/// Models that can be selected with `TANDEM_MEMORY_EMBEDDING_MODEL`. All of
+/// them produce vectors of [`DEFAULT_EMBEDDING_DIMENSION`], the width of the
+/// vector tables, so chunks can be re-embedded in place when switching.
+pub const SUPPORTED_EMBEDDING_MODELS: &[&str] = &[
+    "all-MiniLM-L6-v2",
+    "all-MiniLM-L12-v2",
+    "bge-small-en-v1.5",
+    "paraphrase-multilingual-MiniLM-L12-v2",
+    "multilingual-e5-small",
+];
+
+/// Canonical id of a supported model; case and `_`/`-` are not significant.
+pub fn canonical_embedding_model(model_name: &str) -> Option<&'static str> {
+    let wanted = model_name.trim().to_ascii_lowercase().replace('_', "-");
+    SUPPORTED_EMBEDDING_MODELS
+        .iter()
+        .copied()
+        .find(|id| id.to_ascii_lowercase() == wanted)
+}
+
+/// Embedding model selected for new writes and queries.
+pub fn configured_embedding_model() -> String {
+    std::env::var("TANDEM_MEMORY_EMBEDDING_MODEL")
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            canonical_embedding_model(&value)
+                .map(ToString::to_string)
+                .unwrap_or(value)
+        })
+        .unwrap_or_else(|| DEFAULT_EMBEDDING_MODEL.to_string())
+}
+
 /// Embedding service for generating vector representations.
 pub struct EmbeddingService {
     model_name: String,
     dimension: usize,
     model: Option<EmbeddingBackend>,
     disabled_reason: Option<String>,
+    /// Deterministic stand-in vectors so tests can run without model files.
+    #[cfg(test)]
+    fake: bool,
 }
 
 impl EmbeddingService {
-    /// Create a new embedding service with default model.
+    /// Create a new embedding service with the configured model.
+    ///
+    /// `TANDEM_MEMORY_EMBEDDING_MODEL` overrides the default model. Chunks
+    /// embedded with a previous model stay searchable by keyword until the
+    /// re-embedding job migrates them.
     pub fn new() -> Self {
-        Self::with_model(
-            DEFAULT_EMBEDDING_MODEL.to_string(),
-            DEFAULT_EMBEDDING_DIMENSION,
-        )
+        Self::with_model(configured_embedding_model(), DEFAULT_EMBEDDING_DIMENSION)
     }
 
     /// Create with custom model.
@@ -59,9 +97,32 @@ impl EmbeddingService {
             dimension,
             model,
             disabled_reason,
+            #[cfg(test)]
+            fake: false,
         }
     }
 
+    /// Service that derives vectors from a hash of the model name and text.
+    #[cfg(test)]
+    pub(crate) fn fake(model_name: &str) -> Self {
+        Self {
+            model_name: model_name.to_string(),
+            dimension: DEFAULT_EMBEDDING_DIMENSION,
+            model: None,
+            disabled_reason: None,
+            fake: true,
+        }
+    }
+
+    #[cfg(test)]
+    fn fake_embedding(&self, text: &str) -> Vec<f32> {
+        use sha2::{Digest, Sha256};
+        let seed = Sha256::digest(format!("{}\0{text}", self.model_name));
+        (0..self.dimension)
+            .map(|i| f32::from(seed[i % seed.len()]) / 255.0 + (i % 7) as f32 / 100.0)
+            .collect()
+    }
+
     fn init_model(model_name: &str) -> (Option<EmbeddingBackend>, Option<String>) {
         #[cfg(not(feature = "local-embeddings"))]
         {
@@ -83,7 +144,8 @@ impl EmbeddingService {
                     None,
                     Some(format!(
                         "unsupported embedding model id '{}'; supported: {}",
-                        model_name, DEFAULT_EMBEDDING_MODEL
+                        model_name,
+                        SUPPORTED_EMBEDDING_MODELS.join(", ")
                     )),
                 );
             };
@@ -112,8 +174,14 @@ impl EmbeddingService {
 
     #[cfg(feature = "local-embeddings")]
     fn parse_model_id(model_name: &str) -> Option<EmbeddingModel> {
-        match model_name.trim().to_ascii_lowercase().as_str() {
-            "all-minilm-l6-v2" | "all_minilm_l6_v2" => Some(EmbeddingModel::AllMiniLML6V2),
+        match canonical_embedding_model(model_name)? {
+            "all-MiniLM-L6-v2" => Some(EmbeddingModel::AllMiniLML6V2),
+            "all-MiniLM-L12-v2" => Some(EmbeddingModel::AllMiniLML12V2),
+            "bge-small-en-v1.5" => Some(EmbeddingModel::BGESmallENV15),
+            "paraphrase-multilingual-MiniLM-L12-v2" => {
+                Some(EmbeddingModel::ParaphraseMLMiniLML12V2)
+            }
+            "multilingual-e5-small" => Some(EmbeddingModel::MultilingualE5Small),
             _ => None,
         }
     }
@@ -130,6 +198,10 @@ impl EmbeddingService {
 
     /// Returns whether semantic embeddings are currently available.
     pub fn is_available(&self) -> bool {
+        #[cfg(test)]
+        if self.fake {
+            return true;
+        }
         self.model.is_some()
     }
 
@@ -160,6 +232,11 @@ impl EmbeddingService {
 
     /// Generate embeddings for a single text.
     pub async fn embed(&self, text: &str) -> MemoryResult<Vec<f32>> {
+        #[cfg(test)]
+        if self.fake {
+            return Ok(self.fake_embedding(text));
+        }
+
         #[cfg(not(feature = "local-embeddings"))]
         {
             let _ = text;
@@ -185,6 +262,11 @@ impl EmbeddingService {
 
     /// Generate embeddings for multiple texts.
     pub async fn embed_batch(&self, texts: &[String]) -> MemoryResult<Vec<Vec<f32>>> {
+        #[cfg(test)]
+        if self.fake {
+            return Ok(texts.iter().map(|text| self.fake_embedding(text)).collect());
+        }
+
         #[cfg(not(feature = "local-embeddings"))]
         {
             let _ = texts;
@@ -350,6 +432,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn supported_model_ids_are_matched_loosely() {
+        assert_eq!(
+            canonical_embedding_model("ALL_MINILM_L6_V2"),
+            Some(DEFAULT_EMBEDDING_MODEL)
+        );
+        assert_eq!(
+            canonical_embedding_model(" bge-small-en-v1.5 "),
+            Some("bge-small-en-v1.5")
+        );
+        assert_eq!(canonical_embedding_model("bge-large-en-v1.5"), None);
+    }
+
     #[test]
     fn test_cosine_similarity() {
         let a = vec![1.0f32, 0.0, 0.0];
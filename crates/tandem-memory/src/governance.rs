@@ -119,6 +119,8 @@ pub struct MemoryPutResponse {
     pub tier: GovernedMemoryTier,
     pub partition_key: String,
     pub audit_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redaction: Option<crate::redaction::RedactionManifest>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
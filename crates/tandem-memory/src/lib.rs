@@ -3,9 +3,17 @@ pub mod db;
 pub mod embeddings;
 pub mod governance;
 pub mod manager;
+pub mod redaction;
+pub mod reembed;
 pub mod response_cache;
 pub mod types;
+pub mod vector_index;
 
 pub use governance::*;
 pub use manager::MemoryManager;
+pub use redaction::{redact_content, RedactionManifest, RedactionProfile, RedactionRule};
+pub use reembed::{EmbeddingMigrationStatus, ReembedJobState, ReembedOptions, ReembedProgress};
 pub use response_cache::ResponseCache;
+pub use vector_index::{
+    VectorIndexBackend, VectorIndexBenchmark, VectorIndexStats, VectorIndexTierStats,
+};
@@ -4,21 +4,59 @@
 use crate::chunking::{chunk_text_semantic, ChunkingConfig, Tokenizer};
 use crate::db::MemoryDatabase;
 use crate::embeddings::EmbeddingService;
+use crate::redaction::{redact_content, RedactionProfile};
+use crate::reembed::{reembed_progress, EmbeddingMigrationStatus, EmbeddingModelCount};
 use crate::types::{
-    CleanupLogEntry, EmbeddingHealth, MemoryChunk, MemoryConfig, MemoryContext, MemoryResult,
-    MemoryRetrievalMeta, MemorySearchResult, MemoryStats, MemoryTier, StoreMessageRequest,
+    CleanupLogEntry, EmbeddingHealth, MemoryChunk, MemoryConfig, MemoryContext, MemoryError,
+    MemoryProvenance, MemoryResult, MemoryRetrievalMeta, MemorySearchResult, MemoryStats,
+    MemoryTier, StoreMessageRequest,
+};
+use crate::vector_index::{
+    shared_vector_index, HnswIndex, TierVectorIndex, VectorIndexBackend, VectorIndexBenchmark,
+    VectorIndexSet, VectorIndexStats, VectorIndexTierStats, VectorSearchTiming,
 };
 use chrono::Utc;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 use tandem_providers::{MemoryConsolidationConfig, ProviderRegistry};
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, RwLock};
 
 /// High-level memory manager that coordinates database, embeddings, and chunking
 pub struct MemoryManager {
     db: Arc<MemoryDatabase>,
     embedding_service: Arc<Mutex<EmbeddingService>>,
     tokenizer: Tokenizer,
+    vector_index: Arc<RwLock<VectorIndexSet>>,
+    /// Fingerprint key for redaction manifests. Chunks do not keep their
+    /// manifest, so a per-process key is enough.
+    redaction_key: [u8; 32],
+    stale_embeddings: Arc<StaleEmbeddings>,
+}
+
+/// How often search re-counts chunks left on a previous embedding model while
+/// a migration is in progress (possibly in another manager).
+const STALE_RECHECK_MS: u64 = 60_000;
+
+/// Whether chunks from a previous embedding model may remain, so search does
+/// not count them on every query. New writes always use the current model,
+/// so once clear it stays clear.
+#[derive(Default)]
+struct StaleEmbeddings {
+    present: AtomicBool,
+    checked_at_ms: AtomicU64,
+}
+
+impl StaleEmbeddings {
+    fn set(&self, present: bool) {
+        self.present.store(present, Ordering::Relaxed);
+        self.checked_at_ms.store(
+            Utc::now().timestamp_millis().max(0) as u64,
+            Ordering::Relaxed,
+        );
+    }
 }
 
 impl MemoryManager {
@@ -34,21 +72,110 @@ impl MemoryManager {
 
     /// Initialize the memory manager
     pub async fn new(db_path: &Path) -> MemoryResult<Self> {
+        Self::with_embedding_service(db_path, EmbeddingService::new()).await
+    }
+
+    pub(crate) async fn with_embedding_service(
+        db_path: &Path,
+        embedding_service: EmbeddingService,
+    ) -> MemoryResult<Self> {
         let db = Arc::new(MemoryDatabase::new(db_path).await?);
-        let embedding_service = Arc::new(Mutex::new(EmbeddingService::new()));
+        let model = embedding_service.model_name().to_string();
+        let embedding_service = Arc::new(Mutex::new(embedding_service));
         let tokenizer = Tokenizer::new()?;
+        let vector_index = shared_vector_index(db_path);
 
+        let stale_embeddings = Arc::new(StaleEmbeddings::default());
+        match db.count_stale_embeddings(&model).await {
+            Ok(stale) => {
+                stale_embeddings.set(stale > 0);
+                if stale > 0 {
+                    tracing::warn!(
+                        "{} memory chunks were embedded with a model other than {}; \
+                         they are keyword-searchable only until re-embedded",
+                        stale,
+                        model
+                    );
+                }
+            }
+            Err(err) => {
+                stale_embeddings.set(true);
+                tracing::warn!("Failed to check memory embedding versions: {}", err);
+            }
+        }
+
+        let mut redaction_key = [0u8; 32];
+        redaction_key[..16].copy_from_slice(uuid::Uuid::new_v4().as_bytes());
+        redaction_key[16..].copy_from_slice(uuid::Uuid::new_v4().as_bytes());
         Ok(Self {
             db,
             embedding_service,
             tokenizer,
+            vector_index,
+            redaction_key,
+            stale_embeddings,
         })
     }
 
+    /// Embedding model used for new writes and queries
+    pub async fn embedding_model(&self) -> String {
+        self.embedding_service.lock().await.model_name().to_string()
+    }
+
+    /// Per-model chunk counts plus progress of any re-embedding job
+    pub async fn embedding_migration_status(&self) -> MemoryResult<EmbeddingMigrationStatus> {
+        let current_model = self.embedding_model().await;
+        let models = self
+            .db
+            .embedding_model_counts()
+            .await?
+            .into_iter()
+            .map(|(tier, model, chunks)| EmbeddingModelCount {
+                tier,
+                model,
+                chunks,
+            })
+            .collect();
+        Ok(EmbeddingMigrationStatus {
+            stale_chunks: self.db.count_stale_embeddings(&current_model).await?,
+            current_model,
+            models,
+            job: reembed_progress(self.db.path()).await,
+        })
+    }
+
+    /// Re-embed up to `batch_size` chunks still on a previous model.
+    /// Returns the number of migrated chunks; 0 means migration is complete.
+    pub async fn reembed_batch(&self, batch_size: usize) -> MemoryResult<usize> {
+        let model = self.embedding_model().await;
+        let batch = self.db.stale_embedding_batch(&model, batch_size).await?;
+        if batch.is_empty() {
+            self.stale_embeddings.set(false);
+            return Ok(0);
+        }
+        let mut tiers = Vec::new();
+        {
+            let embedding_service = self.embedding_service.lock().await;
+            for (tier, chunk_id, content) in &batch {
+                let embedding = embedding_service.embed(content).await?;
+                self.db
+                    .replace_chunk_embedding(*tier, chunk_id, &embedding, &model)
+                    .await?;
+                if !tiers.contains(tier) {
+                    tiers.push(*tier);
+                }
+            }
+        }
+        // Graph nodes still hold the old vectors.
+        self.invalidate_tier_indexes(&tiers).await;
+        Ok(batch.len())
+    }
+
     /// Store a message in memory
     ///
     /// This will:
-    /// 1. Chunk the message content
+    /// 1. Redact secrets from anything kept beyond the session tier, and
+    ///    chunk the message content
     /// 2. Generate embeddings for each chunk
     /// 3. Store chunks and embeddings in the database
     pub async fn store_message(&self, request: StoreMessageRequest) -> MemoryResult<Vec<String>> {
@@ -74,7 +201,24 @@ impl MemoryManager {
             separator: None,
         };
 
-        let text_chunks = chunk_text_semantic(&request.content, &chunking_config)?;
+        let content = if request.tier == MemoryTier::Session {
+            request.content.clone()
+        } else {
+            let (redacted, manifest) = redact_content(
+                &request.content,
+                &RedactionProfile::secrets_only(),
+                &self.redaction_key,
+            );
+            if manifest.redaction_count() > 0 {
+                tracing::debug!(
+                    "redacted {} span(s) before storing {} memory",
+                    manifest.redaction_count(),
+                    request.tier
+                );
+            }
+            redacted
+        };
+        let text_chunks = chunk_text_semantic(&content, &chunking_config)?;
 
         if text_chunks.is_empty() {
             return Ok(Vec::new());
@@ -104,6 +248,8 @@ impl MemoryManager {
                 created_at: Utc::now(),
                 token_count: text_chunk.token_count as i64,
                 metadata: request.metadata.clone(),
+                embedding_model: Some(embedding_service.model_name().to_string()),
+                provenance: request.provenance.clone(),
             };
 
             // Store in database (retry once after vector-table self-heal).
@@ -136,6 +282,8 @@ impl MemoryManager {
                     return Err(err);
                 }
             }
+            self.index_stored_vector(request.tier, &chunk_id, &embedding)
+                .await;
             chunk_ids.push(chunk_id);
         }
 
@@ -161,8 +309,24 @@ impl MemoryManager {
         // Generate query embedding
         let embedding_service = self.embedding_service.lock().await;
         let query_embedding = embedding_service.embed(query).await?;
+        let current_model = embedding_service.model_name().to_string();
         drop(embedding_service);
 
+        // During an embedding-model migration, vectors from the previous model
+        // are not comparable with the query vector. Those chunks are excluded
+        // from vector results and reached through keyword matching instead.
+        let has_stale = self.has_stale_embeddings(&current_model).await;
+        let vector_limit = if has_stale {
+            effective_limit * 2
+        } else {
+            effective_limit
+        };
+        let stale_terms = if has_stale {
+            keyword_terms(query)
+        } else {
+            Vec::new()
+        };
+
         let mut results = Vec::new();
 
         // Search in specified tier or all tiers
@@ -179,13 +343,12 @@ impl MemoryManager {
 
         for search_tier in tiers_to_search {
             let tier_results = match self
-                .db
-                .search_similar(
+                .search_tier_similar(
                     &query_embedding,
                     search_tier,
                     project_id,
                     session_id,
-                    effective_limit,
+                    vector_limit,
                 )
                 .await
             {
@@ -204,13 +367,12 @@ impl MemoryManager {
                             .unwrap_or(false);
                     if repaired {
                         match self
-                            .db
-                            .search_similar(
+                            .search_tier_similar(
                                 &query_embedding,
                                 search_tier,
                                 project_id,
                                 session_id,
-                                effective_limit,
+                                vector_limit,
                             )
                             .await
                         {
@@ -231,6 +393,9 @@ impl MemoryManager {
             };
 
             for (chunk, distance) in tier_results {
+                if chunk.embedding_model_or_default() != current_model {
+                    continue;
+                }
                 // Convert distance to similarity (cosine similarity)
                 // sqlite-vec returns distance, where lower is more similar
                 // Cosine similarity ranges from -1 to 1, but for normalized vectors it's 0 to 1
@@ -238,6 +403,34 @@ impl MemoryManager {
 
                 results.push(MemorySearchResult { chunk, similarity });
             }
+
+            if !stale_terms.is_empty() {
+                match self
+                    .db
+                    .search_stale_chunks_lexical(
+                        search_tier,
+                        &current_model,
+                        &stale_terms,
+                        project_id,
+                        session_id,
+                        effective_limit as usize,
+                    )
+                    .await
+                {
+                    Ok(matches) => {
+                        for (chunk, hits) in matches {
+                            // Keep keyword matches below strong semantic matches.
+                            let similarity = 0.5 * hits as f64 / stale_terms.len() as f64;
+                            results.push(MemorySearchResult { chunk, similarity });
+                        }
+                    }
+                    Err(err) => tracing::warn!(
+                        "Keyword search over stale-embedding chunks failed for {:?}: {}",
+                        search_tier,
+                        err
+                    ),
+                }
+            }
         }
 
         // Sort by similarity (highest first) and limit results
@@ -247,6 +440,294 @@ impl MemoryManager {
         Ok(results)
     }
 
+    /// Whether chunks from a previous embedding model remain, re-counted at
+    /// most every [`STALE_RECHECK_MS`] while they do.
+    async fn has_stale_embeddings(&self, model: &str) -> bool {
+        if !self.stale_embeddings.present.load(Ordering::Relaxed) {
+            return false;
+        }
+        let now_ms = Utc::now().timestamp_millis().max(0) as u64;
+        let checked_at_ms = self.stale_embeddings.checked_at_ms.load(Ordering::Relaxed);
+        if now_ms.saturating_sub(checked_at_ms) >= STALE_RECHECK_MS {
+            match self.db.count_stale_embeddings(model).await {
+                Ok(stale) => self.stale_embeddings.set(stale > 0),
+                Err(err) => tracing::warn!("Failed to check memory embedding versions: {}", err),
+            }
+        }
+        self.stale_embeddings.present.load(Ordering::Relaxed)
+    }
+
+    /// Search one tier through the configured vector backend, falling back to
+    /// sqlite-vec when the HNSW graph cannot fill the requested page.
+    async fn search_tier_similar(
+        &self,
+        query_embedding: &[f32],
+        tier: MemoryTier,
+        project_id: Option<&str>,
+        session_id: Option<&str>,
+        limit: i64,
+    ) -> MemoryResult<Vec<(MemoryChunk, f64)>> {
+        if self.vector_index.read().await.backend == VectorIndexBackend::Hnsw {
+            if let Some(results) = self
+                .search_tier_hnsw(query_embedding, tier, project_id, session_id, limit)
+                .await?
+            {
+                return Ok(results);
+            }
+        }
+        self.db
+            .search_similar(query_embedding, tier, project_id, session_id, limit)
+            .await
+    }
+
+    async fn search_tier_hnsw(
+        &self,
+        query_embedding: &[f32],
+        tier: MemoryTier,
+        project_id: Option<&str>,
+        session_id: Option<&str>,
+        limit: i64,
+    ) -> MemoryResult<Option<Vec<(MemoryChunk, f64)>>> {
+        self.ensure_tier_index(tier).await?;
+        let limit = limit.max(0) as usize;
+        let filtered = match tier {
+            MemoryTier::Session => session_id.is_some() || project_id.is_some(),
+            MemoryTier::Project => project_id.is_some(),
+            MemoryTier::Global => false,
+        };
+        // Filters are applied after the graph search, so over-fetch to leave
+        // enough candidates once other sessions/projects are dropped.
+        let fetch = if filtered { (limit * 8).max(64) } else { limit };
+
+        let started = Instant::now();
+        let (hits, indexed) = {
+            let set = self.vector_index.read().await;
+            let Some(entry) = set.tiers.get(&tier) else {
+                return Ok(None);
+            };
+            (
+                entry.index.search(query_embedding, fetch),
+                entry.index.len(),
+            )
+        };
+        let ids = hits.iter().map(|(id, _)| id.clone()).collect::<Vec<_>>();
+        let distances = hits.into_iter().collect::<HashMap<_, _>>();
+        let results = self
+            .db
+            .get_chunks_by_ids(tier, &ids)
+            .await?
+            .into_iter()
+            .filter(|chunk| match (tier, session_id, project_id) {
+                (MemoryTier::Session, Some(sid), _) => chunk.session_id.as_deref() == Some(sid),
+                (MemoryTier::Session | MemoryTier::Project, _, Some(pid)) => {
+                    chunk.project_id.as_deref() == Some(pid)
+                }
+                _ => true,
+            })
+            .take(limit)
+            .map(|chunk| {
+                let distance = distances.get(&chunk.id).copied().unwrap_or(f64::MAX);
+                (chunk, distance)
+            })
+            .collect::<Vec<_>>();
+
+        if let Some(entry) = self.vector_index.write().await.tiers.get_mut(&tier) {
+            entry.searches += 1;
+            entry.total_search_us += started.elapsed().as_micros() as u64;
+        }
+
+        if results.len() < limit && ids.len() == fetch && indexed > fetch {
+            return Ok(None);
+        }
+        Ok(Some(results))
+    }
+
+    /// Build the tier's HNSW graph when missing or out of sync with sqlite-vec.
+    async fn ensure_tier_index(&self, tier: MemoryTier) -> MemoryResult<()> {
+        let stored = self.db.count_tier_vectors(tier).await?;
+        let fresh = self
+            .vector_index
+            .read()
+            .await
+            .tiers
+            .get(&tier)
+            .is_some_and(|entry| entry.index.len() as i64 == stored);
+        if !fresh {
+            self.build_tier_index(tier).await?;
+        }
+        Ok(())
+    }
+
+    async fn build_tier_index(&self, tier: MemoryTier) -> MemoryResult<()> {
+        let params = self.vector_index.read().await.params;
+        let started = Instant::now();
+        let vectors = self.db.load_tier_vectors(tier).await?;
+        let index = tokio::task::spawn_blocking(move || {
+            let mut index = HnswIndex::new(params);
+            for (id, vector) in vectors {
+                index.insert(id, vector);
+            }
+            index
+        })
+        .await
+        .map_err(|err| MemoryError::Embedding(format!("vector index build failed: {err}")))?;
+        let build_ms = started.elapsed().as_millis() as u64;
+        tracing::info!(
+            "Built HNSW memory index for {} tier: {} vectors in {}ms",
+            tier,
+            index.len(),
+            build_ms
+        );
+        self.vector_index.write().await.tiers.insert(
+            tier,
+            TierVectorIndex {
+                index,
+                built_at: Utc::now(),
+                build_ms,
+                searches: 0,
+                total_search_us: 0,
+            },
+        );
+        Ok(())
+    }
+
+    async fn index_stored_vector(&self, tier: MemoryTier, chunk_id: &str, embedding: &[f32]) {
+        let mut set = self.vector_index.write().await;
+        if set.backend != VectorIndexBackend::Hnsw {
+            return;
+        }
+        if let Some(entry) = set.tiers.get_mut(&tier) {
+            entry.index.insert(chunk_id.to_string(), embedding.to_vec());
+        }
+    }
+
+    async fn invalidate_tier_indexes(&self, tiers: &[MemoryTier]) {
+        let mut set = self.vector_index.write().await;
+        for tier in tiers {
+            set.tiers.remove(tier);
+        }
+    }
+
+    /// Report the active vector backend and per-tier index state
+    pub async fn vector_index_stats(&self) -> MemoryResult<VectorIndexStats> {
+        let mut tiers = Vec::new();
+        for tier in [MemoryTier::Session, MemoryTier::Project, MemoryTier::Global] {
+            let stored_vectors = self.db.count_tier_vectors(tier).await?;
+            let set = self.vector_index.read().await;
+            let entry = set.tiers.get(&tier);
+            tiers.push(VectorIndexTierStats {
+                tier,
+                stored_vectors,
+                indexed_vectors: entry.map(|entry| entry.index.len()),
+                built_at: entry.map(|entry| entry.built_at),
+                build_ms: entry.map(|entry| entry.build_ms),
+                searches: entry.map(|entry| entry.searches).unwrap_or(0),
+                avg_search_us: entry
+                    .filter(|entry| entry.searches > 0)
+                    .map(|entry| entry.total_search_us as f64 / entry.searches as f64),
+            });
+        }
+        let set = self.vector_index.read().await;
+        Ok(VectorIndexStats {
+            backend: set.backend,
+            params: set.params,
+            tiers,
+        })
+    }
+
+    /// Switch the vector backend and (re)build HNSW graphs for one or all tiers
+    pub async fn rebuild_vector_index(
+        &self,
+        backend: Option<VectorIndexBackend>,
+        tier: Option<MemoryTier>,
+    ) -> MemoryResult<VectorIndexStats> {
+        let backend = {
+            let mut set = self.vector_index.write().await;
+            if let Some(backend) = backend {
+                set.backend = backend;
+            }
+            if set.backend == VectorIndexBackend::SqliteVec {
+                set.tiers.clear();
+            }
+            set.backend
+        };
+        if backend == VectorIndexBackend::Hnsw {
+            let tiers = match tier {
+                Some(tier) => vec![tier],
+                None => vec![MemoryTier::Session, MemoryTier::Project, MemoryTier::Global],
+            };
+            for tier in tiers {
+                self.build_tier_index(tier).await?;
+            }
+        }
+        self.vector_index_stats().await
+    }
+
+    /// Compare HNSW and sqlite-vec latency and recall using stored vectors as queries
+    pub async fn benchmark_vector_index(
+        &self,
+        tier: MemoryTier,
+        queries: usize,
+        k: usize,
+    ) -> MemoryResult<VectorIndexBenchmark> {
+        self.ensure_tier_index(tier).await?;
+        let k = k.max(1);
+        let (samples, vectors) = {
+            let set = self.vector_index.read().await;
+            let Some(entry) = set.tiers.get(&tier) else {
+                return Err(MemoryError::NotFound(format!("{tier} vector index")));
+            };
+            (entry.index.sample_vectors(queries), entry.index.len())
+        };
+
+        let mut hnsw_us = Vec::with_capacity(samples.len());
+        let mut sqlite_us = Vec::with_capacity(samples.len());
+        let mut matched = 0usize;
+        let mut expected = 0usize;
+        for query in &samples {
+            let started = Instant::now();
+            let approximate = {
+                let set = self.vector_index.read().await;
+                set.tiers
+                    .get(&tier)
+                    .map(|entry| entry.index.search(query, k))
+                    .unwrap_or_default()
+            };
+            hnsw_us.push(started.elapsed().as_micros() as u64);
+
+            let started = Instant::now();
+            let exact = self
+                .db
+                .search_similar(query, tier, None, None, k as i64)
+                .await?;
+            sqlite_us.push(started.elapsed().as_micros() as u64);
+
+            let exact_ids = exact
+                .iter()
+                .map(|(chunk, _)| chunk.id.as_str())
+                .collect::<HashSet<_>>();
+            expected += exact_ids.len();
+            matched += approximate
+                .iter()
+                .filter(|(id, _)| exact_ids.contains(id.as_str()))
+                .count();
+        }
+
+        Ok(VectorIndexBenchmark {
+            tier,
+            vectors,
+            queries: samples.len(),
+            k,
+            hnsw: VectorSearchTiming::from_samples(hnsw_us),
+            sqlite_vec: VectorSearchTiming::from_samples(sqlite_us),
+            recall_at_k: if expected == 0 {
+                1.0
+            } else {
+                matched as f64 / expected as f64
+            },
+        })
+    }
+
     /// Retrieve context for a message
     ///
     /// This retrieves relevant chunks from all tiers and formats them
@@ -403,6 +884,7 @@ impl MemoryManager {
     /// Clear session memory
     pub async fn clear_session(&self, session_id: &str) -> MemoryResult<u64> {
         let count = self.db.clear_session_memory(session_id).await?;
+        self.invalidate_tier_indexes(&[MemoryTier::Session]).await;
 
         // Log cleanup
         self.db
@@ -422,6 +904,8 @@ impl MemoryManager {
     /// Clear project memory
     pub async fn clear_project(&self, project_id: &str) -> MemoryResult<u64> {
         let count = self.db.clear_project_memory(project_id).await?;
+        self.invalidate_tier_indexes(&[MemoryTier::Session, MemoryTier::Project])
+            .await;
 
         // Log cleanup
         self.db
@@ -490,6 +974,10 @@ impl MemoryManager {
             total_cleaned += cleaned;
         }
 
+        if total_cleaned > 0 {
+            self.invalidate_tier_indexes(&[MemoryTier::Session]).await;
+        }
+
         // Vacuum if significant cleanup occurred
         if total_cleaned > 100 {
             self.db.vacuum().await?;
@@ -598,12 +1086,13 @@ impl MemoryManager {
         }
 
         // Generate embedding for the summary
-        let embedding = {
+        let (embedding, embedding_model) = {
             let service = self.embedding_service.lock().await;
-            service
+            let embedding = service
                 .embed(&summary_text)
                 .await
-                .map_err(|e| crate::types::MemoryError::Embedding(e.to_string()))?
+                .map_err(|e| crate::types::MemoryError::Embedding(e.to_string()))?;
+            (embedding, service.model_name().to_string())
         };
 
         // Store the summary chunk
@@ -622,6 +1111,11 @@ impl MemoryManager {
             source_size: None,
             source_hash: None,
             metadata: None,
+            embedding_model: Some(embedding_model),
+            provenance: Some(MemoryProvenance {
+                session_id: Some(session_id.to_string()),
+                ..MemoryProvenance::default()
+            }),
         };
 
         self.db.store_chunk(&chunk, &embedding).await?;
@@ -638,6 +1132,24 @@ impl MemoryManager {
 }
 
 /// Create memory manager with default database path
+/// Lowercased, de-duplicated query words used for keyword fallback search.
+fn keyword_terms(query: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for word in query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 3)
+    {
+        let word = word.to_lowercase();
+        if !terms.contains(&word) {
+            terms.push(word);
+        }
+        if terms.len() == 8 {
+            break;
+        }
+    }
+    terms
+}
+
 pub async fn create_memory_manager(app_data_dir: &Path) -> MemoryResult<MemoryManager> {
     let db_path = app_data_dir.join("tandem_memory.db");
     MemoryManager::new(&db_path).await
@@ -675,6 +1187,7 @@ mod tests {
             source_size: None,
             source_hash: None,
             metadata: None,
+            provenance: None,
         };
 
         let chunk_ids = match manager.store_message(request).await {
@@ -705,6 +1218,121 @@ mod tests {
         assert!(results[0].similarity >= 0.0);
     }
 
+    #[tokio::test]
+    async fn chunks_migrate_to_a_new_embedding_model() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("migrate.db");
+        let old = MemoryManager::with_embedding_service(
+            &db_path,
+            EmbeddingService::fake("all-MiniLM-L6-v2"),
+        )
+        .await
+        .unwrap();
+        for content in [
+            "rollout checklist for the billing service",
+            "database failover runbook",
+            "on-call rotation notes",
+        ] {
+            old.store_message(StoreMessageRequest {
+                content: content.to_string(),
+                tier: MemoryTier::Project,
+                session_id: None,
+                project_id: Some("project-1".to_string()),
+                source: "user_message".to_string(),
+                source_path: None,
+                source_mtime: None,
+                source_size: None,
+                source_hash: None,
+                metadata: None,
+                provenance: None,
+            })
+            .await
+            .unwrap();
+        }
+        drop(old);
+
+        let new = MemoryManager::with_embedding_service(
+            &db_path,
+            EmbeddingService::fake("bge-small-en-v1.5"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            new.embedding_migration_status().await.unwrap().stale_chunks,
+            3
+        );
+        // Stale chunks are still found, by keyword.
+        let found = new
+            .search("failover runbook", None, Some("project-1"), None, None)
+            .await
+            .unwrap();
+        assert_eq!(found[0].chunk.content, "database failover runbook");
+
+        assert_eq!(new.reembed_batch(2).await.unwrap(), 2);
+        assert_eq!(new.reembed_batch(2).await.unwrap(), 1);
+        assert_eq!(new.reembed_batch(2).await.unwrap(), 0);
+        let status = new.embedding_migration_status().await.unwrap();
+        assert_eq!(status.stale_chunks, 0);
+        assert!(status
+            .models
+            .iter()
+            .all(|count| count.model == "bge-small-en-v1.5" || count.chunks == 0));
+        assert_eq!(
+            new.db()
+                .count_tier_vectors(MemoryTier::Project)
+                .await
+                .unwrap(),
+            3
+        );
+        let found = new
+            .search(
+                "database failover runbook",
+                None,
+                Some("project-1"),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 3);
+        assert!(found
+            .iter()
+            .all(|result| result.chunk.embedding_model.as_deref() == Some("bge-small-en-v1.5")));
+    }
+
+    #[tokio::test]
+    async fn secrets_are_redacted_before_chunks_are_stored() {
+        let (manager, _temp) = setup_test_manager().await;
+        let request = StoreMessageRequest {
+            content: "Deploy with password = hunter2 to /srv/app".to_string(),
+            tier: MemoryTier::Project,
+            session_id: None,
+            project_id: Some("project-1".to_string()),
+            source: "agent_note".to_string(),
+            source_path: None,
+            source_mtime: None,
+            source_size: None,
+            source_hash: None,
+            metadata: None,
+            provenance: None,
+        };
+        let chunk_ids = match manager.store_message(request).await {
+            Ok(ids) => ids,
+            Err(err) if is_embeddings_disabled(&err) => return,
+            Err(err) => panic!("store_message failed: {err}"),
+        };
+        let chunk = manager
+            .db()
+            .get_chunk(&chunk_ids[0])
+            .await
+            .expect("get chunk")
+            .expect("chunk");
+        assert_eq!(
+            chunk.content,
+            "Deploy with password = [REDACTED:secret] to /srv/app"
+        );
+    }
+
     #[tokio::test]
     async fn test_retrieve_context() {
         let (manager, _temp) = setup_test_manager().await;
@@ -721,6 +1349,7 @@ mod tests {
             source_size: None,
             source_hash: None,
             metadata: None,
+            provenance: None,
         };
         match manager.store_message(request).await {
             Ok(_) => {}
@@ -755,6 +1384,7 @@ mod tests {
             source_size: None,
             source_hash: None,
             metadata: None,
+            provenance: None,
         };
         match manager.store_message(request).await {
             Ok(_) => {}
@@ -0,0 +1,489 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::governance::GovernedMemoryTier;
+
+/// Category of sensitive content removed before memory is persisted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionRule {
+    Secrets,
+    Emails,
+    ExternalPaths,
+}
+
+impl RedactionRule {
+    pub fn placeholder(&self) -> &'static str {
+        match self {
+            Self::Secrets => "[REDACTED:secret]",
+            Self::Emails => "[REDACTED:email]",
+            Self::ExternalPaths => "[REDACTED:path]",
+        }
+    }
+}
+
+/// Redaction settings applied to a memory partition.
+///
+/// Session-tier writes are ephemeral and are left untouched; every other tier
+/// runs through the enabled rules before the record is stored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactionProfile {
+    #[serde(default = "default_profile_name")]
+    pub name: String,
+    #[serde(default = "default_rules")]
+    pub rules: Vec<RedactionRule>,
+    /// Absolute paths under these roots are kept; any other absolute path is
+    /// redacted when `external_paths` is enabled.
+    #[serde(default)]
+    pub workspace_roots: Vec<String>,
+}
+
+fn default_profile_name() -> String {
+    "default".to_string()
+}
+
+fn default_rules() -> Vec<RedactionRule> {
+    vec![
+        RedactionRule::Secrets,
+        RedactionRule::Emails,
+        RedactionRule::ExternalPaths,
+    ]
+}
+
+impl Default for RedactionProfile {
+    fn default() -> Self {
+        Self {
+            name: default_profile_name(),
+            rules: default_rules(),
+            workspace_roots: Vec::new(),
+        }
+    }
+}
+
+impl RedactionProfile {
+    /// Profile for semantic memory chunks, which have no partition profile:
+    /// secrets only, since paths and addresses are often what a note is about.
+    pub fn secrets_only() -> Self {
+        Self {
+            name: "secrets".to_string(),
+            rules: vec![RedactionRule::Secrets],
+            workspace_roots: Vec::new(),
+        }
+    }
+
+    pub fn applies_to(&self, tier: GovernedMemoryTier) -> bool {
+        tier != GovernedMemoryTier::Session && !self.rules.is_empty()
+    }
+
+    fn enabled(&self, rule: RedactionRule) -> bool {
+        self.rules.contains(&rule)
+    }
+
+    fn is_inside_workspace(&self, path: &str) -> bool {
+        self.workspace_roots.iter().any(|root| {
+            let root = root.trim_end_matches(['/', '\\']);
+            !root.is_empty()
+                && path.starts_with(root)
+                && path[root.len()..]
+                    .chars()
+                    .next()
+                    .is_none_or(|c| c == '/' || c == '\\')
+        })
+    }
+}
+
+/// One redacted span. The original value is never stored, only a short keyed
+/// fingerprint so auditors can correlate repeated values across records
+/// without being able to guess short values back from it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactionEntry {
+    pub rule: RedactionRule,
+    pub placeholder: String,
+    pub fingerprint: String,
+    pub original_len: usize,
+}
+
+/// Audit record stored alongside a memory record describing what was removed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RedactionManifest {
+    pub profile: String,
+    #[serde(default)]
+    pub rules: Vec<RedactionRule>,
+    #[serde(default)]
+    pub entries: Vec<RedactionEntry>,
+    /// Keyed digest of the unredacted content.
+    pub original_hmac: String,
+}
+
+impl RedactionManifest {
+    pub fn redaction_count(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+const SECRET_PREFIXES: &[&str] = &[
+    "sk-",
+    "ghp_",
+    "gho_",
+    "ghs_",
+    "github_pat_",
+    "xoxb-",
+    "xoxp-",
+    "glpat-",
+    "AKIA",
+    "AIza",
+];
+
+const SECRET_KEYS: &[&str] = &[
+    "api_key",
+    "apikey",
+    "api-key",
+    "x-api-key",
+    "secret",
+    "token",
+    "password",
+    "passwd",
+    "bearer",
+];
+
+/// Where a secret key name leaves the next token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyState {
+    None,
+    /// A key name such as `password`, still waiting for its `=` or `:`.
+    Named,
+    /// The next token is the key's value.
+    Value,
+}
+
+const TRIM_CHARS: &[char] = &[
+    '"', '\'', '`', '(', ')', '[', ']', '{', '}', '<', '>', ',', ';',
+];
+
+/// Run `content` through the profile's rules and return the redacted text with
+/// its manifest. Fingerprints in the manifest are HMACs under
+/// `fingerprint_key`, which should be a secret kept apart from the manifests.
+pub fn redact_content(
+    content: &str,
+    profile: &RedactionProfile,
+    fingerprint_key: &[u8],
+) -> (String, RedactionManifest) {
+    let mut entries = Vec::new();
+    let mut text = content.to_string();
+    if profile.enabled(RedactionRule::Secrets) {
+        text = redact_pem_blocks(&text, fingerprint_key, &mut entries);
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut key_state = KeyState::None;
+    let mut rest = text.as_str();
+    while !rest.is_empty() {
+        let ws_len = rest.len() - rest.trim_start().len();
+        out.push_str(&rest[..ws_len]);
+        rest = &rest[ws_len..];
+        let token_len = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if token_len == 0 {
+            break;
+        }
+        let token = &rest[..token_len];
+        rest = &rest[token_len..];
+
+        let (rewritten, next_state) =
+            redact_token(token, key_state, profile, fingerprint_key, &mut entries);
+        key_state = next_state;
+        out.push_str(&rewritten);
+    }
+
+    let manifest = RedactionManifest {
+        profile: profile.name.clone(),
+        rules: profile.rules.clone(),
+        entries,
+        original_hmac: fingerprint(fingerprint_key, content),
+    };
+    (out, manifest)
+}
+
+fn redact_token(
+    token: &str,
+    key_state: KeyState,
+    profile: &RedactionProfile,
+    key: &[u8],
+    entries: &mut Vec<RedactionEntry>,
+) -> (String, KeyState) {
+    let secrets = profile.enabled(RedactionRule::Secrets);
+    // `password = hunter2`: a lone separator after a key name.
+    if secrets && key_state == KeyState::Named && matches!(token, "=" | ":" | ":=" | "=>") {
+        return (token.to_string(), KeyState::Value);
+    }
+    let start = token.len() - token.trim_start_matches(TRIM_CHARS).len();
+    let core = token[start..].trim_end_matches(TRIM_CHARS);
+    let core = core.trim_end_matches(['.', ':']);
+    if core.is_empty() {
+        return (token.to_string(), KeyState::None);
+    }
+    let end = start + core.len();
+    let (prefix, suffix) = (&token[..start], &token[end..]);
+
+    if secrets {
+        if key_state == KeyState::Value && !is_secret_key(core) {
+            let placeholder = record(entries, RedactionRule::Secrets, key, core);
+            return (format!("{prefix}{placeholder}{suffix}"), KeyState::None);
+        }
+        // `password =hunter2`: the separator opens the value token.
+        let separator = if key_state == KeyState::Named && core.starts_with(['=', ':']) {
+            Some(0)
+        } else {
+            core.find(['=', ':'])
+                .filter(|idx| is_secret_key(&core[..*idx]))
+        };
+        if let Some(idx) = separator {
+            let value = &core[idx + 1..];
+            let value_start = value.len() - value.trim_start_matches(TRIM_CHARS).len();
+            let value = value[value_start..].trim_end_matches(TRIM_CHARS);
+            if !value.is_empty() {
+                let placeholder = record(entries, RedactionRule::Secrets, key, value);
+                let value_offset = start + idx + 1 + value_start;
+                return (
+                    format!(
+                        "{}{}{}",
+                        &token[..value_offset],
+                        placeholder,
+                        &token[value_offset + value.len()..]
+                    ),
+                    KeyState::None,
+                );
+            }
+        }
+        if (is_secret_key(core) && token.trim_end_matches(TRIM_CHARS).ends_with([':', '=']))
+            || core.eq_ignore_ascii_case("bearer")
+        {
+            return (token.to_string(), KeyState::Value);
+        }
+        if is_secret_key(core) {
+            return (token.to_string(), KeyState::Named);
+        }
+        if looks_like_secret(core) {
+            let placeholder = record(entries, RedactionRule::Secrets, key, core);
+            return (format!("{prefix}{placeholder}{suffix}"), KeyState::None);
+        }
+    }
+
+    if profile.enabled(RedactionRule::Emails) && looks_like_email(core) {
+        let placeholder = record(entries, RedactionRule::Emails, key, core);
+        return (format!("{prefix}{placeholder}{suffix}"), KeyState::None);
+    }
+
+    if profile.enabled(RedactionRule::ExternalPaths)
+        && looks_like_absolute_path(core)
+        && !profile.is_inside_workspace(core)
+    {
+        let placeholder = record(entries, RedactionRule::ExternalPaths, key, core);
+        return (format!("{prefix}{placeholder}{suffix}"), KeyState::None);
+    }
+
+    (token.to_string(), KeyState::None)
+}
+
+fn redact_pem_blocks(text: &str, key: &[u8], entries: &mut Vec<RedactionEntry>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(begin) = rest.find("-----BEGIN ") {
+        let Some(end_marker) = rest[begin..].find("-----END ") else {
+            break;
+        };
+        let end_marker = begin + end_marker;
+        let Some(close) = rest[end_marker + 9..].find("-----") else {
+            break;
+        };
+        let end = end_marker + 9 + close + 5;
+        let block = &rest[begin..end];
+        out.push_str(&rest[..begin]);
+        out.push_str(&record(entries, RedactionRule::Secrets, key, block));
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn record(
+    entries: &mut Vec<RedactionEntry>,
+    rule: RedactionRule,
+    key: &[u8],
+    original: &str,
+) -> String {
+    let placeholder = rule.placeholder().to_string();
+    entries.push(RedactionEntry {
+        rule,
+        placeholder: placeholder.clone(),
+        fingerprint: fingerprint(key, original)[..12].to_string(),
+        original_len: original.len(),
+    });
+    placeholder
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let key = key
+        .trim_matches(TRIM_CHARS)
+        .trim_end_matches([':', '='])
+        .to_ascii_lowercase();
+    !key.is_empty()
+        && SECRET_KEYS
+            .iter()
+            .any(|marker| key == *marker || key.ends_with(&format!("_{marker}")))
+}
+
+fn looks_like_secret(token: &str) -> bool {
+    SECRET_PREFIXES
+        .iter()
+        .any(|prefix| token.starts_with(prefix) && token.len() >= prefix.len() + 12)
+        && token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+}
+
+fn looks_like_email(token: &str) -> bool {
+    let Some((local, domain)) = token.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && local
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '%' | '+' | '-'))
+        && domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+fn looks_like_absolute_path(token: &str) -> bool {
+    let unix = (token.starts_with('/') || token.starts_with("~/"))
+        && token.trim_start_matches('~').matches('/').count() >= 2;
+    let bytes = token.as_bytes();
+    let windows = bytes.len() > 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/');
+    (unix || windows) && !token.contains("://")
+}
+
+/// Hex HMAC-SHA256 of `input` under `key` (RFC 2104).
+fn fingerprint(key: &[u8], input: &str) -> String {
+    const BLOCK: usize = 64;
+    let mut block_key = [0u8; BLOCK];
+    if key.len() > BLOCK {
+        block_key[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block_key[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block_key.iter().map(|k| k ^ byte).collect::<Vec<_>>();
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(input.as_bytes())
+        .finalize();
+    let digest = Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize();
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"test-fingerprint-key";
+
+    #[test]
+    fn fingerprints_are_keyed_hmacs() {
+        assert_eq!(
+            fingerprint(b"Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        let profile = RedactionProfile::default();
+        let (_, a) = redact_content("password=hunter2", &profile, b"key-a");
+        let (_, b) = redact_content("password=hunter2", &profile, b"key-b");
+        assert_ne!(a.entries[0].fingerprint, b.entries[0].fingerprint);
+    }
+
+    #[test]
+    fn redacts_values_separated_by_spaced_operators() {
+        let profile = RedactionProfile::default();
+        for input in [
+            "password = hunter2",
+            "password =hunter2",
+            "password= hunter2",
+            "password : hunter2",
+        ] {
+            let (output, manifest) = redact_content(input, &profile, KEY);
+            assert!(!output.contains("hunter2"), "{input} -> {output}");
+            assert_eq!(manifest.redaction_count(), 1, "{input}");
+        }
+        let (output, manifest) = redact_content("the password is rotated weekly", &profile, KEY);
+        assert_eq!(output, "the password is rotated weekly");
+        assert!(manifest.entries.is_empty());
+    }
+
+    #[test]
+    fn redacts_secrets_emails_and_external_paths() {
+        let profile = RedactionProfile {
+            workspace_roots: vec!["/home/dev/project".to_string()],
+            ..RedactionProfile::default()
+        };
+        let input = "Use api_key=abc123 and mail ops@example.com. \
+                     Config at /etc/app/config.toml, code in /home/dev/project/src/lib.rs";
+        let (output, manifest) = redact_content(input, &profile, KEY);
+        assert_eq!(
+            output,
+            "Use api_key=[REDACTED:secret] and mail [REDACTED:email]. \
+             Config at [REDACTED:path], code in /home/dev/project/src/lib.rs"
+        );
+        let rules = manifest
+            .entries
+            .iter()
+            .map(|entry| entry.rule)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rules,
+            vec![
+                RedactionRule::Secrets,
+                RedactionRule::Emails,
+                RedactionRule::ExternalPaths
+            ]
+        );
+        assert_eq!(manifest.original_hmac, fingerprint(KEY, input));
+        assert!(!serde_json::to_string(&manifest)
+            .expect("manifest json")
+            .contains("abc123"));
+    }
+
+    #[test]
+    fn redacts_prefixed_tokens_bearer_values_and_pem_blocks() {
+        let profile = RedactionProfile::default();
+        let input = concat!(
+            "Authorization: Bearer eyJhbGciOi\n",
+            "key ghp_0123456789abcdef0123\n",
+            "-----BEGIN",
+            " PRIVATE KEY-----\nMIIB\n-----END PRIVATE KEY-----"
+        );
+        let (output, manifest) = redact_content(input, &profile, KEY);
+        assert_eq!(
+            output,
+            "Authorization: Bearer [REDACTED:secret]\nkey [REDACTED:secret]\n[REDACTED:secret]"
+        );
+        assert_eq!(manifest.redaction_count(), 3);
+    }
+
+    #[test]
+    fn disabled_rules_and_session_tier_are_skipped() {
+        let profile = RedactionProfile {
+            rules: vec![RedactionRule::Secrets],
+            ..RedactionProfile::default()
+        };
+        let (output, manifest) =
+            redact_content("ping ops@example.com about /var/log", &profile, KEY);
+        assert_eq!(output, "ping ops@example.com about /var/log");
+        assert!(manifest.entries.is_empty());
+        assert!(!profile.applies_to(GovernedMemoryTier::Session));
+        assert!(profile.applies_to(GovernedMemoryTier::Project));
+    }
+}
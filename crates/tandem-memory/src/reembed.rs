@@ -0,0 +1,330 @@
+//! Embedding model migration.
+//!
+//! Every chunk records the model that produced its vector. When the configured
+//! model changes, chunks from the previous model are migrated by a throttled
+//! background job that re-embeds them in small batches. Until a chunk is
+//! migrated, search reaches it through keyword matching instead of vector
+//! distance (see `MemoryManager::search`).
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use crate::manager::MemoryManager;
+use crate::types::{MemoryError, MemoryResult, MemoryTier};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReembedOptions {
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Pause between batches so migration does not starve interactive search.
+    #[serde(default = "default_throttle_ms")]
+    pub throttle_ms: u64,
+}
+
+fn default_batch_size() -> usize {
+    32
+}
+
+fn default_throttle_ms() -> u64 {
+    250
+}
+
+impl Default for ReembedOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: default_batch_size(),
+            throttle_ms: default_throttle_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReembedJobState {
+    Running,
+    Completed,
+    Cancelled,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReembedProgress {
+    pub job_id: String,
+    pub target_model: String,
+    pub state: ReembedJobState,
+    /// Stale chunks when the job started.
+    pub total: i64,
+    pub processed: i64,
+    pub batch_size: usize,
+    pub throttle_ms: u64,
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+impl ReembedProgress {
+    pub fn percent(&self) -> f64 {
+        if self.total <= 0 {
+            100.0
+        } else {
+            (self.processed as f64 / self.total as f64 * 100.0).min(100.0)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingModelCount {
+    pub tier: MemoryTier,
+    pub model: String,
+    pub chunks: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmbeddingMigrationStatus {
+    pub current_model: String,
+    pub stale_chunks: i64,
+    pub models: Vec<EmbeddingModelCount>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job: Option<ReembedProgress>,
+}
+
+#[derive(Clone)]
+struct ReembedJobHandle {
+    progress: Arc<RwLock<ReembedProgress>>,
+    cancel: Arc<AtomicBool>,
+    /// Set while the job owns its database; checked and set under [`JOBS`]
+    /// so two starts cannot both win.
+    running: Arc<AtomicBool>,
+}
+
+static JOBS: Lazy<std::sync::Mutex<HashMap<PathBuf, ReembedJobHandle>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+fn job_handle(db_path: &Path) -> Option<ReembedJobHandle> {
+    JOBS.lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .get(db_path)
+        .cloned()
+}
+
+/// Latest progress of the re-embedding job for `db_path`, if one has run.
+pub async fn reembed_progress(db_path: &Path) -> Option<ReembedProgress> {
+    match job_handle(db_path) {
+        Some(handle) => Some(handle.progress.read().await.clone()),
+        None => None,
+    }
+}
+
+/// Register a new running job for `db_path`, unless one is already running.
+fn claim_job(db_path: &Path, progress: ReembedProgress) -> MemoryResult<ReembedJobHandle> {
+    let mut jobs = JOBS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(existing) = jobs.get(db_path) {
+        if existing.running.load(Ordering::SeqCst) {
+            let job_id = existing
+                .progress
+                .try_read()
+                .map(|progress| progress.job_id.clone())
+                .unwrap_or_default();
+            return Err(MemoryError::InvalidConfig(format!(
+                "re-embedding job {job_id} is already running"
+            )));
+        }
+    }
+    let handle = ReembedJobHandle {
+        progress: Arc::new(RwLock::new(progress)),
+        cancel: Arc::new(AtomicBool::new(false)),
+        running: Arc::new(AtomicBool::new(true)),
+    };
+    jobs.insert(db_path.to_path_buf(), handle.clone());
+    Ok(handle)
+}
+
+/// Request cancellation of a running job. Returns false when nothing is running.
+pub async fn cancel_reembed_job(db_path: &Path) -> bool {
+    let Some(handle) = job_handle(db_path) else {
+        return false;
+    };
+    if handle.progress.read().await.state != ReembedJobState::Running {
+        return false;
+    }
+    handle.cancel.store(true, Ordering::SeqCst);
+    true
+}
+
+/// Start re-embedding stale chunks of the database at `db_path` in the
+/// background. `on_progress` is invoked after every batch and when the job
+/// finishes.
+pub async fn start_reembed_job<F>(
+    db_path: &Path,
+    options: ReembedOptions,
+    on_progress: F,
+) -> MemoryResult<ReembedProgress>
+where
+    F: Fn(&ReembedProgress) + Send + Sync + 'static,
+{
+    let now = Utc::now();
+    let options = ReembedOptions {
+        batch_size: options.batch_size.clamp(1, 512),
+        throttle_ms: options.throttle_ms,
+    };
+    let handle = claim_job(
+        db_path,
+        ReembedProgress {
+            job_id: uuid::Uuid::new_v4().to_string(),
+            target_model: crate::embeddings::configured_embedding_model(),
+            state: ReembedJobState::Running,
+            total: 0,
+            processed: 0,
+            batch_size: options.batch_size,
+            throttle_ms: options.throttle_ms,
+            started_at: now,
+            updated_at: now,
+            finished_at: None,
+            last_error: None,
+        },
+    )?;
+
+    let setup = async {
+        let manager = MemoryManager::new(db_path).await?;
+        let target_model = manager.embedding_model().await;
+        let total = manager.db().count_stale_embeddings(&target_model).await?;
+        Ok::<_, MemoryError>((manager, target_model, total))
+    };
+    let (manager, target_model, total) = match setup.await {
+        Ok(setup) => setup,
+        Err(err) => {
+            finish(
+                &handle,
+                ReembedJobState::Failed,
+                Some(err.to_string()),
+                &on_progress,
+            )
+            .await;
+            return Err(err);
+        }
+    };
+    let progress = {
+        let mut progress = handle.progress.write().await;
+        progress.target_model = target_model;
+        progress.total = total;
+        progress.clone()
+    };
+
+    tokio::spawn(async move {
+        loop {
+            if handle.cancel.load(Ordering::SeqCst) {
+                finish(&handle, ReembedJobState::Cancelled, None, &on_progress).await;
+                return;
+            }
+            match manager.reembed_batch(options.batch_size).await {
+                Ok(0) => {
+                    finish(&handle, ReembedJobState::Completed, None, &on_progress).await;
+                    return;
+                }
+                Ok(count) => {
+                    let snapshot = {
+                        let mut progress = handle.progress.write().await;
+                        progress.processed += count as i64;
+                        progress.total = progress.total.max(progress.processed);
+                        progress.updated_at = Utc::now();
+                        progress.clone()
+                    };
+                    on_progress(&snapshot);
+                }
+                Err(err) => {
+                    tracing::warn!("memory re-embedding job failed: {}", err);
+                    finish(
+                        &handle,
+                        ReembedJobState::Failed,
+                        Some(err.to_string()),
+                        &on_progress,
+                    )
+                    .await;
+                    return;
+                }
+            }
+            if options.throttle_ms > 0 {
+                tokio::time::sleep(Duration::from_millis(options.throttle_ms)).await;
+            }
+        }
+    });
+
+    Ok(progress)
+}
+
+async fn finish<F>(
+    handle: &ReembedJobHandle,
+    state: ReembedJobState,
+    error: Option<String>,
+    on_progress: &F,
+) where
+    F: Fn(&ReembedProgress),
+{
+    let snapshot = {
+        let mut progress = handle.progress.write().await;
+        let now = Utc::now();
+        progress.state = state;
+        progress.updated_at = now;
+        progress.finished_at = Some(now);
+        progress.last_error = error;
+        progress.clone()
+    };
+    handle.running.store(false, Ordering::SeqCst);
+    on_progress(&snapshot);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(job_id: &str) -> ReembedProgress {
+        let now = Utc::now();
+        ReembedProgress {
+            job_id: job_id.to_string(),
+            target_model: "bge-small-en-v1.5".to_string(),
+            state: ReembedJobState::Running,
+            total: 0,
+            processed: 0,
+            batch_size: 32,
+            throttle_ms: 0,
+            started_at: now,
+            updated_at: now,
+            finished_at: None,
+            last_error: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn only_one_job_runs_per_database() {
+        let db_path =
+            std::env::temp_dir().join(format!("tandem-reembed-{}.db", uuid::Uuid::new_v4()));
+        let first = claim_job(&db_path, progress("first")).expect("first claim");
+        let second = claim_job(&db_path, progress("second"));
+        assert!(second
+            .err()
+            .is_some_and(|err| err.to_string().contains("first is already running")));
+
+        finish(
+            &first,
+            ReembedJobState::Completed,
+            None,
+            &|_: &ReembedProgress| {},
+        )
+        .await;
+        claim_job(&db_path, progress("third")).expect("claim after finish");
+        assert_eq!(
+            reembed_progress(&db_path).await.map(|p| p.job_id),
+            Some("third".to_string())
+        );
+    }
+}
@@ -7,16 +7,55 @@
 //!
 //! Lives alongside `memory.sqlite` as `response_cache.db` so it can be
 //! independently wiped without touching memory chunks.
-
-use chrono::{Duration, Utc};
-use rusqlite::{params, Connection};
+//!
+//! Scoped entries (see [`ResponseCache::put_scoped`]) also keep the
+//! normalized prompt and, optionally, its embedding. A scoped lookup tries the
+//! exact key first and then, given a query embedding, the most similar fresh
+//! entry in the same scope and model. The scan is linear; scopes are small
+//! enough that no vector index is needed.
+
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
 use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+use crate::embeddings::EmbeddingService;
 use crate::types::{MemoryError, MemoryResult};
 
+/// A response to store under a scope, e.g. one routine.
+#[derive(Debug, Clone)]
+pub struct ScopedCacheEntry {
+    pub key: String,
+    pub model: String,
+    pub scope: String,
+    pub prompt: String,
+    pub response: String,
+    pub token_count: u32,
+    pub embedding: Option<Vec<f32>>,
+    /// What produced the response, e.g. a run id.
+    pub source_id: Option<String>,
+}
+
+/// A fresh entry found by [`ResponseCache::lookup_scoped`].
+#[derive(Debug, Clone)]
+pub struct ScopedCacheHit {
+    pub key: String,
+    pub response: String,
+    pub source_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// Cosine similarity for a semantic match; `None` for an exact one.
+    pub similarity: Option<f32>,
+}
+
+fn parse_created_at(raw: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(raw)
+        .map(|at| at.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now())
+}
+
 /// Response cache backed by a dedicated SQLite database.
 pub struct ResponseCache {
     conn: Arc<Mutex<Connection>>,
@@ -56,6 +95,25 @@ impl ResponseCache {
             CREATE INDEX IF NOT EXISTS idx_rc_created  ON response_cache(created_at);",
         )?;
 
+        // Migrations: scoped entry columns (SQLite has no ADD COLUMN IF NOT
+        // EXISTS, so inspect table_info)
+        let cols: HashSet<String> = {
+            let mut stmt = conn.prepare("PRAGMA table_info(response_cache)")?;
+            let rows = stmt.query_map([], |row| row.get::<_, String>(1))?;
+            rows.collect::<Result<HashSet<_>, _>>()?
+        };
+        for column in ["scope", "prompt", "embedding", "source_id"] {
+            if !cols.contains(column) {
+                conn.execute(
+                    &format!("ALTER TABLE response_cache ADD COLUMN {column} TEXT"),
+                    [],
+                )?;
+            }
+        }
+        conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS idx_rc_scope ON response_cache(scope, model);",
+        )?;
+
         Ok(Self {
             conn: Arc::new(Mutex::new(conn)),
             db_path,
@@ -144,6 +202,115 @@ impl ResponseCache {
         Ok(())
     }
 
+    /// Store a scoped response; otherwise behaves like [`put`](Self::put).
+    pub async fn put_scoped(&self, entry: &ScopedCacheEntry) -> MemoryResult<()> {
+        self.put(&entry.key, &entry.model, &entry.response, entry.token_count)
+            .await?;
+        let embedding = entry
+            .embedding
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| MemoryError::Embedding(e.to_string()))?;
+        let conn = self.conn.lock().await;
+        conn.execute(
+            "UPDATE response_cache
+             SET scope = ?1, prompt = ?2, embedding = ?3, source_id = ?4
+             WHERE prompt_hash = ?5",
+            params![
+                entry.scope,
+                entry.prompt,
+                embedding,
+                entry.source_id,
+                entry.key
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Find a scoped entry newer than `max_age`: the exact `key`, or else the
+    /// entry in `scope` and `model` whose embedding is most similar to
+    /// `query`, if it reaches `threshold`.
+    pub async fn lookup_scoped(
+        &self,
+        scope: &str,
+        model: &str,
+        key: &str,
+        max_age: Duration,
+        query: Option<(&[f32], f32)>,
+    ) -> MemoryResult<Option<ScopedCacheHit>> {
+        let conn = self.conn.lock().await;
+        let cutoff = (Utc::now() - max_age.min(Duration::minutes(self.ttl_minutes))).to_rfc3339();
+
+        let exact = conn
+            .query_row(
+                "SELECT response, source_id, created_at FROM response_cache
+                 WHERE prompt_hash = ?1 AND scope = ?2 AND created_at > ?3",
+                params![key, scope, cutoff],
+                |row| {
+                    Ok(ScopedCacheHit {
+                        key: key.to_string(),
+                        response: row.get(0)?,
+                        source_id: row.get(1)?,
+                        created_at: parse_created_at(&row.get::<_, String>(2)?),
+                        similarity: None,
+                    })
+                },
+            )
+            .optional()?;
+
+        let found = match (exact, query) {
+            (Some(hit), _) => Some(hit),
+            (None, Some((query, threshold))) => {
+                let mut stmt = conn.prepare(
+                    "SELECT prompt_hash, response, source_id, created_at, embedding
+                     FROM response_cache
+                     WHERE scope = ?1 AND model = ?2 AND created_at > ?3
+                       AND embedding IS NOT NULL",
+                )?;
+                let rows = stmt.query_map(params![scope, model, cutoff], |row| {
+                    let hit = ScopedCacheHit {
+                        key: row.get(0)?,
+                        response: row.get(1)?,
+                        source_id: row.get(2)?,
+                        created_at: parse_created_at(&row.get::<_, String>(3)?),
+                        similarity: None,
+                    };
+                    Ok((hit, row.get::<_, String>(4)?))
+                })?;
+                let mut best: Option<ScopedCacheHit> = None;
+                for row in rows {
+                    let (mut hit, embedding) = row?;
+                    let Ok(embedding) = serde_json::from_str::<Vec<f32>>(&embedding) else {
+                        continue;
+                    };
+                    let similarity = EmbeddingService::cosine_similarity(query, &embedding);
+                    if similarity >= threshold
+                        && best
+                            .as_ref()
+                            .and_then(|b| b.similarity)
+                            .is_none_or(|best| similarity > best)
+                    {
+                        hit.similarity = Some(similarity);
+                        best = Some(hit);
+                    }
+                }
+                best
+            }
+            (None, None) => None,
+        };
+
+        if let Some(hit) = found.as_ref() {
+            conn.execute(
+                "UPDATE response_cache
+                 SET accessed_at = ?1, hit_count = hit_count + 1
+                 WHERE prompt_hash = ?2",
+                params![Utc::now().to_rfc3339(), hit.key],
+            )?;
+        }
+        Ok(found)
+    }
+
     /// Return cache statistics: `(total_entries, total_hits, estimated_tokens_saved)`.
     pub async fn stats(&self) -> MemoryResult<(usize, u64, u64)> {
         let conn = self.conn.lock().await;
@@ -247,6 +414,56 @@ mod tests {
         assert_eq!(tokens, 500);
     }
 
+    #[tokio::test]
+    async fn scoped_lookup_matches_exact_then_similar_prompts() {
+        let (_tmp, cache) = temp_cache(60).await;
+        let entry = |key: &str, embedding: Vec<f32>| ScopedCacheEntry {
+            key: key.to_string(),
+            model: "gpt-4".to_string(),
+            scope: "routine-1".to_string(),
+            prompt: "summarize the inbox".to_string(),
+            response: format!("response for {key}"),
+            token_count: 10,
+            embedding: Some(embedding),
+            source_id: Some(format!("run-{key}")),
+        };
+        cache.put_scoped(&entry("a", vec![1.0, 0.0])).await.unwrap();
+        cache.put_scoped(&entry("b", vec![0.0, 1.0])).await.unwrap();
+        let fresh = Duration::minutes(10);
+
+        let exact = cache
+            .lookup_scoped("routine-1", "gpt-4", "a", fresh, None)
+            .await
+            .unwrap()
+            .expect("exact hit");
+        assert_eq!(exact.source_id.as_deref(), Some("run-a"));
+        assert!(exact.similarity.is_none());
+
+        let near = cache
+            .lookup_scoped("routine-1", "gpt-4", "c", fresh, Some((&[0.1, 0.9], 0.9)))
+            .await
+            .unwrap()
+            .expect("similar hit");
+        assert_eq!(near.key, "b");
+        assert!(near.similarity.is_some_and(|s| s > 0.9));
+
+        assert!(cache
+            .lookup_scoped("routine-2", "gpt-4", "a", fresh, None)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(cache
+            .lookup_scoped("routine-1", "gpt-4", "c", fresh, Some((&[0.7, 0.7], 0.99)))
+            .await
+            .unwrap()
+            .is_none());
+        assert!(cache
+            .lookup_scoped("routine-1", "gpt-4", "a", Duration::zero(), None)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
     #[tokio::test]
     async fn lru_eviction_respects_max_entries() {
         let tmp = TempDir::new().unwrap();
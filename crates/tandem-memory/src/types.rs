@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// Memory tier - determines persistence level
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum MemoryTier {
     /// Ephemeral session memory - cleared when session ends
@@ -55,6 +55,47 @@ pub struct MemoryChunk {
     pub created_at: DateTime<Utc>,
     pub token_count: i64,
     pub metadata: Option<serde_json::Value>,
+    /// Embedding model that produced this chunk's vector. `None` for chunks
+    /// stored before model versioning, which all used the default model.
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+    /// Where the content came from, captured at write time
+    #[serde(default)]
+    pub provenance: Option<MemoryProvenance>,
+}
+
+/// Origin of a memory write, used to resolve a chunk back to its transcript location
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MemoryProvenance {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+}
+
+impl MemoryProvenance {
+    pub fn is_empty(&self) -> bool {
+        self.session_id.is_none()
+            && self.message_id.is_none()
+            && self.run_id.is_none()
+            && self.tool_call_id.is_none()
+            && self.tool_name.is_none()
+    }
+}
+
+impl MemoryChunk {
+    /// Embedding model of the stored vector, treating legacy chunks as the default model
+    pub fn embedding_model_or_default(&self) -> &str {
+        self.embedding_model
+            .as_deref()
+            .unwrap_or(DEFAULT_EMBEDDING_MODEL)
+    }
 }
 
 /// Search result with similarity score
@@ -198,6 +239,8 @@ pub struct StoreMessageRequest {
     pub source_size: Option<i64>,
     pub source_hash: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    #[serde(default)]
+    pub provenance: Option<MemoryProvenance>,
 }
 
 /// Project-scoped memory statistics (filtered by project_id)
@@ -0,0 +1,538 @@
+//! Pluggable vector index backends for memory search.
+//!
+//! The default backend queries the sqlite-vec `vec0` tables directly, which is
+//! an exact scan over every stored embedding. For large stores the `hnsw`
+//! backend keeps an in-process Hierarchical Navigable Small World graph per
+//! tier, built from the persisted vectors and shared by every
+//! `MemoryManager` opened against the same database file. Select it with
+//! `TANDEM_MEMORY_VECTOR_BACKEND=hnsw` or through the rebuild endpoint.
+
+use chrono::{DateTime, Utc};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::types::MemoryTier;
+
+const MAX_LEVEL: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VectorIndexBackend {
+    #[default]
+    SqliteVec,
+    Hnsw,
+}
+
+impl VectorIndexBackend {
+    pub fn from_env() -> Self {
+        std::env::var("TANDEM_MEMORY_VECTOR_BACKEND")
+            .ok()
+            .and_then(|value| Self::parse(&value))
+            .unwrap_or_default()
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "sqlite_vec" | "sqlite-vec" | "sqlite" => Some(Self::SqliteVec),
+            "hnsw" => Some(Self::Hnsw),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for VectorIndexBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SqliteVec => write!(f, "sqlite_vec"),
+            Self::Hnsw => write!(f, "hnsw"),
+        }
+    }
+}
+
+/// Graph construction and query parameters for the HNSW backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HnswParams {
+    /// Maximum neighbours per node on upper layers (layer 0 keeps `2 * m`).
+    pub m: usize,
+    pub ef_construction: usize,
+    pub ef_search: usize,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        Self {
+            m: 16,
+            ef_construction: 100,
+            ef_search: 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    distance: f32,
+    node: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then_with(|| self.node.cmp(&other.node))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct HnswNode {
+    id: String,
+    vector: Vec<f32>,
+    neighbors: Vec<Vec<usize>>,
+}
+
+/// Approximate nearest-neighbour index using L2 distance, matching the
+/// distances sqlite-vec reports so similarity scoring is backend-agnostic.
+#[derive(Debug, Clone)]
+pub struct HnswIndex {
+    params: HnswParams,
+    nodes: Vec<HnswNode>,
+    ids: HashMap<String, usize>,
+    entry: Option<usize>,
+    max_level: usize,
+    rng_state: u64,
+}
+
+impl HnswIndex {
+    pub fn new(params: HnswParams) -> Self {
+        Self {
+            params,
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+            entry: None,
+            max_level: 0,
+            rng_state: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.ids.contains_key(id)
+    }
+
+    pub fn insert(&mut self, id: String, vector: Vec<f32>) {
+        if self.ids.contains_key(&id) {
+            return;
+        }
+        let level = self.random_level();
+        let node = self.nodes.len();
+        self.nodes.push(HnswNode {
+            id: id.clone(),
+            vector,
+            neighbors: vec![Vec::new(); level + 1],
+        });
+        self.ids.insert(id, node);
+
+        let Some(mut entry) = self.entry else {
+            self.entry = Some(node);
+            self.max_level = level;
+            return;
+        };
+
+        let query = self.nodes[node].vector.clone();
+        for layer in (level + 1..=self.max_level).rev() {
+            entry = self.greedy_closest(&query, entry, layer);
+        }
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let candidates = self.search_layer(&query, entry, self.params.ef_construction, layer);
+            let max_links = self.max_links(layer);
+            let selected = candidates
+                .iter()
+                .take(max_links)
+                .map(|candidate| candidate.node)
+                .collect::<Vec<_>>();
+            for &neighbor in &selected {
+                self.nodes[neighbor].neighbors[layer].push(node);
+                if self.nodes[neighbor].neighbors[layer].len() > max_links {
+                    self.prune(neighbor, layer, max_links);
+                }
+            }
+            self.nodes[node].neighbors[layer] = selected;
+            if let Some(closest) = candidates.first() {
+                entry = closest.node;
+            }
+        }
+
+        if level > self.max_level {
+            self.max_level = level;
+            self.entry = Some(node);
+        }
+    }
+
+    /// Return up to `k` `(id, distance)` pairs ordered from nearest to farthest.
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<(String, f64)> {
+        let Some(mut entry) = self.entry else {
+            return Vec::new();
+        };
+        for layer in (1..=self.max_level).rev() {
+            entry = self.greedy_closest(query, entry, layer);
+        }
+        self.search_layer(query, entry, self.params.ef_search.max(k), 0)
+            .into_iter()
+            .take(k)
+            .map(|candidate| {
+                (
+                    self.nodes[candidate.node].id.clone(),
+                    candidate.distance as f64,
+                )
+            })
+            .collect()
+    }
+
+    /// Exact nearest neighbours by scanning every node; used to measure recall.
+    pub fn exact_search(&self, query: &[f32], k: usize) -> Vec<(String, f64)> {
+        let mut all = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(node, entry)| Candidate {
+                distance: l2_distance(query, &entry.vector),
+                node,
+            })
+            .collect::<Vec<_>>();
+        all.sort();
+        all.into_iter()
+            .take(k)
+            .map(|candidate| {
+                (
+                    self.nodes[candidate.node].id.clone(),
+                    candidate.distance as f64,
+                )
+            })
+            .collect()
+    }
+
+    pub fn sample_vectors(&self, count: usize) -> Vec<Vec<f32>> {
+        if self.nodes.is_empty() || count == 0 {
+            return Vec::new();
+        }
+        let step = (self.nodes.len() / count).max(1);
+        self.nodes
+            .iter()
+            .step_by(step)
+            .take(count)
+            .map(|node| node.vector.clone())
+            .collect()
+    }
+
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.params.m * 2
+        } else {
+            self.params.m
+        }
+    }
+
+    fn random_level(&mut self) -> usize {
+        // xorshift64*; deterministic so rebuilds of the same data are stable.
+        self.rng_state ^= self.rng_state >> 12;
+        self.rng_state ^= self.rng_state << 25;
+        self.rng_state ^= self.rng_state >> 27;
+        let bits = self.rng_state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        let uniform = ((bits >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+        let scale = 1.0 / (self.params.m.max(2) as f64).ln();
+        ((-uniform.ln() * scale) as usize).min(MAX_LEVEL)
+    }
+
+    fn greedy_closest(&self, query: &[f32], mut current: usize, layer: usize) -> usize {
+        let mut best = l2_distance(query, &self.nodes[current].vector);
+        loop {
+            let mut changed = false;
+            for &neighbor in self.layer_neighbors(current, layer) {
+                let distance = l2_distance(query, &self.nodes[neighbor].vector);
+                if distance < best {
+                    best = distance;
+                    current = neighbor;
+                    changed = true;
+                }
+            }
+            if !changed {
+                return current;
+            }
+        }
+    }
+
+    fn search_layer(&self, query: &[f32], entry: usize, ef: usize, layer: usize) -> Vec<Candidate> {
+        let start = Candidate {
+            distance: l2_distance(query, &self.nodes[entry].vector),
+            node: entry,
+        };
+        let mut visited = HashSet::from([entry]);
+        let mut frontier = BinaryHeap::from([Reverse(start)]);
+        let mut results = BinaryHeap::from([start]);
+
+        while let Some(Reverse(current)) = frontier.pop() {
+            let worst = results.peek().map(|c| c.distance).unwrap_or(f32::MAX);
+            if current.distance > worst && results.len() >= ef {
+                break;
+            }
+            for &neighbor in self.layer_neighbors(current.node, layer) {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let candidate = Candidate {
+                    distance: l2_distance(query, &self.nodes[neighbor].vector),
+                    node: neighbor,
+                };
+                let worst = results.peek().map(|c| c.distance).unwrap_or(f32::MAX);
+                if results.len() < ef || candidate.distance < worst {
+                    frontier.push(Reverse(candidate));
+                    results.push(candidate);
+                    if results.len() > ef {
+                        results.pop();
+                    }
+                }
+            }
+        }
+
+        results.into_sorted_vec()
+    }
+
+    fn layer_neighbors(&self, node: usize, layer: usize) -> &[usize] {
+        self.nodes[node]
+            .neighbors
+            .get(layer)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    fn prune(&mut self, node: usize, layer: usize, max_links: usize) {
+        let base = self.nodes[node].vector.clone();
+        let mut scored = self.nodes[node].neighbors[layer]
+            .iter()
+            .map(|&neighbor| Candidate {
+                distance: l2_distance(&base, &self.nodes[neighbor].vector),
+                node: neighbor,
+            })
+            .collect::<Vec<_>>();
+        scored.sort();
+        self.nodes[node].neighbors[layer] = scored
+            .into_iter()
+            .take(max_links)
+            .map(|candidate| candidate.node)
+            .collect();
+    }
+}
+
+fn l2_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f32>()
+        .sqrt()
+}
+
+/// Built index for one memory tier plus the counters reported by stats.
+#[derive(Debug, Clone)]
+pub struct TierVectorIndex {
+    pub index: HnswIndex,
+    pub built_at: DateTime<Utc>,
+    pub build_ms: u64,
+    pub searches: u64,
+    pub total_search_us: u64,
+}
+
+/// Backend selection and per-tier HNSW graphs for a single memory database.
+#[derive(Debug, Clone)]
+pub struct VectorIndexSet {
+    pub backend: VectorIndexBackend,
+    pub params: HnswParams,
+    pub tiers: HashMap<MemoryTier, TierVectorIndex>,
+}
+
+impl VectorIndexSet {
+    pub fn new(backend: VectorIndexBackend) -> Self {
+        Self {
+            backend,
+            params: HnswParams::default(),
+            tiers: HashMap::new(),
+        }
+    }
+}
+
+static SHARED_INDEXES: Lazy<std::sync::Mutex<HashMap<PathBuf, Arc<RwLock<VectorIndexSet>>>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+/// Index set shared by all managers opened against `db_path` in this process.
+pub fn shared_vector_index(db_path: &Path) -> Arc<RwLock<VectorIndexSet>> {
+    let mut shared = SHARED_INDEXES
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    shared
+        .entry(db_path.to_path_buf())
+        .or_insert_with(|| {
+            Arc::new(RwLock::new(VectorIndexSet::new(
+                VectorIndexBackend::from_env(),
+            )))
+        })
+        .clone()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorIndexTierStats {
+    pub tier: MemoryTier,
+    /// Vectors persisted in the sqlite-vec table for this tier.
+    pub stored_vectors: i64,
+    /// Vectors present in the in-process HNSW graph, if one is built.
+    pub indexed_vectors: Option<usize>,
+    pub built_at: Option<DateTime<Utc>>,
+    pub build_ms: Option<u64>,
+    pub searches: u64,
+    pub avg_search_us: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorIndexStats {
+    pub backend: VectorIndexBackend,
+    pub params: HnswParams,
+    pub tiers: Vec<VectorIndexTierStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorSearchTiming {
+    pub avg_us: f64,
+    pub p95_us: u64,
+}
+
+impl VectorSearchTiming {
+    pub fn from_samples(mut samples: Vec<u64>) -> Self {
+        if samples.is_empty() {
+            return Self {
+                avg_us: 0.0,
+                p95_us: 0,
+            };
+        }
+        samples.sort_unstable();
+        let avg_us = samples.iter().sum::<u64>() as f64 / samples.len() as f64;
+        let p95_index = ((samples.len() as f64 * 0.95).ceil() as usize).clamp(1, samples.len()) - 1;
+        Self {
+            avg_us,
+            p95_us: samples[p95_index],
+        }
+    }
+}
+
+/// Side-by-side latency and recall comparison of the two backends.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorIndexBenchmark {
+    pub tier: MemoryTier,
+    pub vectors: usize,
+    pub queries: usize,
+    pub k: usize,
+    pub hnsw: VectorSearchTiming,
+    pub sqlite_vec: VectorSearchTiming,
+    /// Fraction of exact top-k neighbours also returned by HNSW.
+    pub recall_at_k: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pseudo_vectors(count: usize, dim: usize) -> Vec<Vec<f32>> {
+        let mut state = 42u64;
+        (0..count)
+            .map(|_| {
+                (0..dim)
+                    .map(|_| {
+                        state = state
+                            .wrapping_mul(6364136223846793005)
+                            .wrapping_add(1442695040888963407);
+                        ((state >> 33) as f32 / u32::MAX as f32) - 0.25
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hnsw_search_finds_exact_match_and_high_recall() {
+        let vectors = pseudo_vectors(600, 16);
+        let mut index = HnswIndex::new(HnswParams::default());
+        for (i, vector) in vectors.iter().enumerate() {
+            index.insert(format!("chunk-{i}"), vector.clone());
+        }
+        assert_eq!(index.len(), 600);
+
+        let hits = index.search(&vectors[123], 1);
+        assert_eq!(hits[0].0, "chunk-123");
+        assert!(hits[0].1 < 1e-6);
+
+        let mut found = 0usize;
+        let queries = index.sample_vectors(20);
+        for query in &queries {
+            let exact = index
+                .exact_search(query, 10)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<HashSet<_>>();
+            found += index
+                .search(query, 10)
+                .into_iter()
+                .filter(|(id, _)| exact.contains(id))
+                .count();
+        }
+        let recall = found as f64 / (queries.len() * 10) as f64;
+        assert!(recall >= 0.9, "recall {recall} too low");
+    }
+
+    #[test]
+    fn duplicate_ids_are_ignored() {
+        let mut index = HnswIndex::new(HnswParams::default());
+        index.insert("a".to_string(), vec![0.0, 1.0]);
+        index.insert("a".to_string(), vec![1.0, 0.0]);
+        assert_eq!(index.len(), 1);
+        assert!(index.contains("a"));
+    }
+
+    #[test]
+    fn backend_parses_aliases_and_timing_percentiles() {
+        assert_eq!(
+            VectorIndexBackend::parse("sqlite-vec"),
+            Some(VectorIndexBackend::SqliteVec)
+        );
+        assert_eq!(
+            VectorIndexBackend::parse("HNSW"),
+            Some(VectorIndexBackend::Hnsw)
+        );
+        assert_eq!(VectorIndexBackend::parse("faiss"), None);
+
+        let timing = VectorSearchTiming::from_samples((1..=100).collect());
+        assert_eq!(timing.p95_us, 95);
+        assert!((timing.avg_us - 50.5).abs() < f64::EPSILON);
+    }
+}
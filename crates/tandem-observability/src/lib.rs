@@ -6,6 +6,12 @@ use tracing::Level;
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
+mod live_logs;
+
+pub use live_logs::{
+    subscribe_logs, LiveLogLayer, LogFilter, LogLevel, LogRecord, BACKLOG_CAPACITY,
+};
+
 #[derive(Debug, Clone, Copy, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ProcessKind {
@@ -154,6 +160,7 @@ pub fn init_process_logging(
         .with(filter)
         .with(console_layer)
         .with(file_layer)
+        .with(LiveLogLayer)
         .try_init()
         .ok();
 
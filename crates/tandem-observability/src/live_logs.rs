@@ -0,0 +1,242 @@
+//! In-process tail of the structured log, for clients that cannot read the
+//! log files on the host.
+//!
+//! [`init_process_logging`](crate::init_process_logging) installs
+//! [`LiveLogLayer`] next to the console and file layers, so it sees the same
+//! events the `RUST_LOG` filter lets through. Each event becomes a
+//! [`LogRecord`], is kept in a ring of the last [`BACKLOG_CAPACITY`] records
+//! and is broadcast to [`subscribe_logs`] receivers.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tokio::sync::broadcast;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// Records replayed to a new subscriber at most.
+pub const BACKLOG_CAPACITY: usize = 500;
+const CHANNEL_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "trace" => Some(Self::Trace),
+            "debug" => Some(Self::Debug),
+            "info" => Some(Self::Info),
+            "warn" | "warning" => Some(Self::Warn),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+impl From<&Level> for LogLevel {
+    fn from(level: &Level) -> Self {
+        match *level {
+            Level::TRACE => Self::Trace,
+            Level::DEBUG => Self::Debug,
+            Level::INFO => Self::Info,
+            Level::WARN => Self::Warn,
+            Level::ERROR => Self::Error,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogRecord {
+    /// Increasing per process; a gap means the receiver lagged.
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub level: LogLevel,
+    pub target: String,
+    pub message: String,
+    #[serde(skip_serializing_if = "Map::is_empty")]
+    pub fields: Map<String, Value>,
+}
+
+/// Which records a stream client wants.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    pub min_level: Option<LogLevel>,
+    /// Target prefixes; `tandem-server` matches `tandem_server::http`.
+    pub modules: Vec<String>,
+}
+
+impl LogFilter {
+    /// Build from query values: a level name and comma-separated modules.
+    pub fn parse(level: Option<&str>, modules: Option<&str>) -> Result<Self, String> {
+        let min_level = match level.map(str::trim).filter(|l| !l.is_empty()) {
+            Some(raw) => Some(LogLevel::parse(raw).ok_or_else(|| {
+                format!("unknown log level `{raw}`; use trace, debug, info, warn or error")
+            })?),
+            None => None,
+        };
+        let modules = modules
+            .unwrap_or_default()
+            .split(',')
+            .map(|m| m.trim().replace('-', "_"))
+            .filter(|m| !m.is_empty())
+            .collect();
+        Ok(Self { min_level, modules })
+    }
+
+    pub fn matches(&self, record: &LogRecord) -> bool {
+        if self.min_level.is_some_and(|min| record.level < min) {
+            return false;
+        }
+        self.modules.is_empty()
+            || self.modules.iter().any(|module| {
+                record
+                    .target
+                    .strip_prefix(module.as_str())
+                    .is_some_and(|rest| {
+                        rest.is_empty() || rest.starts_with("::") || rest.starts_with('.')
+                    })
+            })
+    }
+}
+
+struct LiveLogs {
+    tx: broadcast::Sender<Arc<LogRecord>>,
+    backlog: Mutex<VecDeque<Arc<LogRecord>>>,
+    seq: AtomicU64,
+}
+
+fn live_logs() -> &'static LiveLogs {
+    static LOGS: OnceLock<LiveLogs> = OnceLock::new();
+    LOGS.get_or_init(|| LiveLogs {
+        tx: broadcast::channel(CHANNEL_CAPACITY).0,
+        backlog: Mutex::new(VecDeque::with_capacity(BACKLOG_CAPACITY)),
+        seq: AtomicU64::new(1),
+    })
+}
+
+/// Recent records, oldest first, and a receiver for everything after them.
+pub fn subscribe_logs() -> (Vec<Arc<LogRecord>>, broadcast::Receiver<Arc<LogRecord>>) {
+    let logs = live_logs();
+    // Holding the backlog lock keeps a record from landing in both halves
+    // or in neither.
+    let backlog = logs.backlog.lock().unwrap_or_else(|e| e.into_inner());
+    (backlog.iter().cloned().collect(), logs.tx.subscribe())
+}
+
+fn publish(level: LogLevel, target: &str, fields: FieldVisitor) {
+    let logs = live_logs();
+    let record = Arc::new(LogRecord {
+        seq: logs.seq.fetch_add(1, Ordering::Relaxed),
+        timestamp: Utc::now(),
+        level,
+        target: target.to_string(),
+        message: fields.message,
+        fields: fields.fields,
+    });
+    let mut backlog = logs.backlog.lock().unwrap_or_else(|e| e.into_inner());
+    if backlog.len() == BACKLOG_CAPACITY {
+        backlog.pop_front();
+    }
+    backlog.push_back(record.clone());
+    let _ = logs.tx.send(record);
+}
+
+/// Tracing layer feeding [`subscribe_logs`].
+pub struct LiveLogLayer;
+
+impl<S: Subscriber> Layer<S> for LiveLogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut fields = FieldVisitor::default();
+        event.record(&mut fields);
+        publish(metadata.level().into(), metadata.target(), fields);
+    }
+}
+
+#[derive(Default)]
+struct FieldVisitor {
+    message: String,
+    fields: Map<String, Value>,
+}
+
+impl FieldVisitor {
+    fn insert(&mut self, field: &Field, value: Value) {
+        if field.name() == "message" {
+            self.message = match value {
+                Value::String(text) => text,
+                other => other.to_string(),
+            };
+        } else {
+            self.fields.insert(field.name().to_string(), value);
+        }
+    }
+}
+
+impl Visit for FieldVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.insert(field, Value::String(format!("{value:?}")));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.insert(field, Value::String(value.to_string()));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.insert(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.insert(field, value.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn layer_broadcasts_records_with_fields() {
+        let (_, mut rx) = subscribe_logs();
+        let subscriber = tracing_subscriber::registry().with(LiveLogLayer);
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(target: "tandem_live_test::inner", attempt = 3, "provider slow");
+        });
+        let record = std::iter::from_fn(|| rx.try_recv().ok())
+            .find(|record| record.target == "tandem_live_test::inner")
+            .expect("record broadcast");
+        assert_eq!(record.level, LogLevel::Warn);
+        assert_eq!(record.message, "provider slow");
+        assert_eq!(record.fields["attempt"], 3);
+
+        let filter = LogFilter::parse(Some("warn"), Some("tandem-live-test")).expect("filter");
+        assert!(filter.matches(&record));
+        let filter = LogFilter::parse(Some("error"), None).expect("filter");
+        assert!(!filter.matches(&record));
+        let filter = LogFilter::parse(None, Some("tandem_live")).expect("filter");
+        assert!(!filter.matches(&record));
+        assert!(LogFilter::parse(Some("loud"), None).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, VecDeque};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -13,6 +13,8 @@ pub enum AgentRole {
     Reviewer,
     Tester,
     Committer,
+    /// Read-only auditor: sees team events and tool results, runs no tools.
+    Observer,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -266,6 +268,148 @@ pub struct AgentInstance {
     pub metadata: Option<Value>,
 }
 
+/// One line of an observer's running audit commentary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObserverEntry {
+    #[serde(rename = "timestampMs")]
+    pub timestamp_ms: u64,
+    #[serde(rename = "instanceID")]
+    pub instance_id: String,
+    pub role: AgentRole,
+    /// `tool_call`, `tool_result`, `tool_failed`, `tool_denied`,
+    /// `capability_denied` or `instance_status`.
+    pub kind: String,
+    pub summary: String,
+    /// Entries a compliance reviewer should look at.
+    #[serde(default)]
+    pub flagged: bool,
+}
+
+/// Observer commentary for one mission, capped at `limit` entries. The
+/// totals cover everything observed; once full, the oldest unflagged entries
+/// are evicted first so flagged ones survive for the compliance report.
+#[derive(Debug, Clone, Default)]
+pub struct ObserverLog {
+    entries: VecDeque<ObserverEntry>,
+    limit: usize,
+    instances: BTreeSet<String>,
+    tool_calls: usize,
+    tool_failures: usize,
+    denials: usize,
+    dropped: usize,
+}
+
+impl ObserverLog {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            limit: limit.max(1),
+            ..Self::default()
+        }
+    }
+
+    pub fn push(&mut self, entry: ObserverEntry) {
+        match entry.kind.as_str() {
+            "tool_call" => self.tool_calls += 1,
+            "tool_failed" => self.tool_failures += 1,
+            "tool_denied" | "capability_denied" => self.denials += 1,
+            _ => {}
+        }
+        self.instances.insert(entry.instance_id.clone());
+        self.entries.push_back(entry);
+        while self.entries.len() > self.limit {
+            let evict = self.entries.iter().position(|e| !e.flagged).unwrap_or(0);
+            self.entries.remove(evict);
+            self.dropped += 1;
+        }
+    }
+
+    pub fn entries(&self) -> Vec<ObserverEntry> {
+        self.entries.iter().cloned().collect()
+    }
+
+    /// Entries evicted to stay under the cap.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComplianceVerdict {
+    Clean,
+    Flagged,
+}
+
+/// Final observer summary attached to a mission once its agents finish.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ComplianceReport {
+    #[serde(rename = "missionID")]
+    pub mission_id: String,
+    #[serde(rename = "observerInstanceIDs")]
+    pub observer_instance_ids: Vec<String>,
+    #[serde(rename = "generatedAtMs")]
+    pub generated_at_ms: u64,
+    #[serde(rename = "observedInstances")]
+    pub observed_instances: usize,
+    #[serde(rename = "toolCalls")]
+    pub tool_calls: usize,
+    #[serde(rename = "toolFailures")]
+    pub tool_failures: usize,
+    pub denials: usize,
+    pub verdict: ComplianceVerdict,
+    #[serde(default)]
+    pub flagged: Vec<ObserverEntry>,
+    /// Commentary entries evicted from the capped log; the counts above
+    /// still include them.
+    #[serde(rename = "droppedEntries", default)]
+    pub dropped_entries: usize,
+}
+
+impl ComplianceReport {
+    pub fn from_entries(
+        mission_id: &str,
+        observer_instance_ids: Vec<String>,
+        entries: &[ObserverEntry],
+        generated_at_ms: u64,
+    ) -> Self {
+        let mut log = ObserverLog::new(entries.len());
+        for entry in entries {
+            log.push(entry.clone());
+        }
+        Self::from_log(mission_id, observer_instance_ids, &log, generated_at_ms)
+    }
+
+    pub fn from_log(
+        mission_id: &str,
+        observer_instance_ids: Vec<String>,
+        log: &ObserverLog,
+        generated_at_ms: u64,
+    ) -> Self {
+        let flagged = log
+            .entries
+            .iter()
+            .filter(|e| e.flagged)
+            .cloned()
+            .collect::<Vec<_>>();
+        Self {
+            mission_id: mission_id.to_string(),
+            observer_instance_ids,
+            generated_at_ms,
+            observed_instances: log.instances.len(),
+            tool_calls: log.tool_calls,
+            tool_failures: log.tool_failures,
+            denials: log.denials,
+            verdict: if flagged.is_empty() && log.tool_failures + log.denials == 0 {
+                ComplianceVerdict::Clean
+            } else {
+                ComplianceVerdict::Flagged
+            },
+            flagged,
+            dropped_entries: log.dropped,
+        }
+    }
+}
+
 impl SpawnPolicy {
     pub fn evaluate(
         &self,
@@ -378,7 +522,7 @@ fn template_has_requirement(template: &AgentTemplate, req: &SkillRequirement) ->
     })
 }
 
-fn role_name(role: &AgentRole) -> &'static str {
+pub(crate) fn role_name(role: &AgentRole) -> &'static str {
     match role {
         AgentRole::Orchestrator => "orchestrator",
         AgentRole::Delegator => "delegator",
@@ -387,6 +531,7 @@ fn role_name(role: &AgentRole) -> &'static str {
         AgentRole::Reviewer => "reviewer",
         AgentRole::Tester => "tester",
         AgentRole::Committer => "committer",
+        AgentRole::Observer => "observer",
     }
 }
 
@@ -519,4 +664,56 @@ mod tests {
             Some(SpawnDenyCode::SpawnRequiredSkillMissing)
         );
     }
+
+    #[test]
+    fn compliance_report_counts_and_flags_entries() {
+        let entry = |kind: &str, flagged: bool| ObserverEntry {
+            timestamp_ms: 1,
+            instance_id: "i-1".to_string(),
+            role: AgentRole::Worker,
+            kind: kind.to_string(),
+            summary: kind.to_string(),
+            flagged,
+        };
+        let entries = vec![
+            entry("tool_call", false),
+            entry("tool_result", false),
+            entry("tool_call", false),
+            entry("capability_denied", true),
+        ];
+        let report = ComplianceReport::from_entries("m1", vec!["obs-1".to_string()], &entries, 10);
+        assert_eq!(report.tool_calls, 2);
+        assert_eq!(report.denials, 1);
+        assert_eq!(report.observed_instances, 1);
+        assert_eq!(report.verdict, ComplianceVerdict::Flagged);
+        assert_eq!(report.flagged.len(), 1);
+        assert_eq!(report.dropped_entries, 0);
+    }
+
+    #[test]
+    fn observer_log_is_capped_but_keeps_flagged_entries_and_totals() {
+        let entry = |kind: &str, flagged: bool| ObserverEntry {
+            timestamp_ms: 1,
+            instance_id: "i-1".to_string(),
+            role: AgentRole::Worker,
+            kind: kind.to_string(),
+            summary: kind.to_string(),
+            flagged,
+        };
+        let mut log = ObserverLog::new(3);
+        log.push(entry("tool_failed", true));
+        for _ in 0..10 {
+            log.push(entry("tool_call", false));
+        }
+        let entries = log.entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].kind, "tool_failed");
+        assert_eq!(log.dropped(), 8);
+
+        let report = ComplianceReport::from_log("m1", Vec::new(), &log, 10);
+        assert_eq!(report.tool_calls, 10);
+        assert_eq!(report.tool_failures, 1);
+        assert_eq!(report.flagged.len(), 1);
+        assert_eq!(report.dropped_entries, 8);
+    }
 }
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::{AgentRole, ComplianceReport};
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum MissionStatus {
@@ -95,6 +97,42 @@ pub struct WorkItem {
     pub metadata: Option<Value>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandoffKind {
+    /// The role currently holding the mission passes it on.
+    Handoff,
+    /// Another role claims the mission without the holder's cooperation,
+    /// e.g. after the holder failed or stalled.
+    Takeover,
+}
+
+/// What the outgoing role leaves for the incoming one.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct HandoffDocument {
+    pub summary: String,
+    #[serde(default)]
+    pub open_items: Vec<String>,
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MissionHandoff {
+    pub handoff_id: String,
+    pub kind: HandoffKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_role: Option<AgentRole>,
+    pub to_role: AgentRole,
+    /// Work items reassigned to `to_role`; empty means the whole mission.
+    #[serde(default)]
+    pub work_item_ids: Vec<String>,
+    pub document: HandoffDocument,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub created_at_ms: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MissionState {
     pub mission_id: String,
@@ -102,6 +140,14 @@ pub struct MissionState {
     pub spec: MissionSpec,
     #[serde(default)]
     pub work_items: Vec<WorkItem>,
+    /// Role that currently owns the mission, set by the first handoff.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_role: Option<AgentRole>,
+    #[serde(default)]
+    pub handoffs: Vec<MissionHandoff>,
+    /// Observer's final report, attached when the mission's agents finish.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compliance_report: Option<ComplianceReport>,
     pub revision: u64,
     pub updated_at_ms: u64,
 }
@@ -160,6 +206,10 @@ pub enum MissionEvent {
         key: String,
         rev: u64,
     },
+    RoleHandoff {
+        mission_id: String,
+        handoff: MissionHandoff,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -1,5 +1,6 @@
+use crate::agent_team::role_name;
 use crate::{
-    MissionCommand, MissionEvent, MissionSpec, MissionState, MissionStatus, WorkItem,
+    HandoffKind, MissionCommand, MissionEvent, MissionSpec, MissionState, MissionStatus, WorkItem,
     WorkItemStatus,
 };
 use serde_json::json;
@@ -19,6 +20,9 @@ impl MissionReducer for NoopMissionReducer {
             status: MissionStatus::Draft,
             spec,
             work_items: Vec::new(),
+            active_role: None,
+            handoffs: Vec::new(),
+            compliance_report: None,
             revision: 1,
             updated_at_ms: 0,
         }
@@ -163,6 +167,60 @@ impl DefaultMissionReducer {
                     }
                 }
             }
+            MissionEvent::RoleHandoff {
+                mission_id,
+                handoff,
+            } if mission_id == next.mission_id => {
+                let holder_mismatch = handoff.kind == HandoffKind::Handoff
+                    && next.active_role.is_some()
+                    && handoff.from_role != next.active_role;
+                let unknown_item = handoff
+                    .work_item_ids
+                    .iter()
+                    .find(|id| !next.work_items.iter().any(|item| &item.work_item_id == *id));
+                let rejection = if holder_mismatch {
+                    Some("from_role does not hold the mission".to_string())
+                } else if handoff.document.summary.trim().is_empty() {
+                    Some("handoff summary is required".to_string())
+                } else {
+                    unknown_item.map(|id| format!("unknown work item `{id}`"))
+                };
+                if let Some(reason) = rejection {
+                    commands.push(MissionCommand::EmitNotice {
+                        mission_id: next.mission_id.clone(),
+                        event_type: "mission.handoff.rejected".to_string(),
+                        properties: json!({
+                            "handoffID": handoff.handoff_id,
+                            "reason": reason,
+                        }),
+                    });
+                } else {
+                    let assignee = role_name(&handoff.to_role);
+                    for item in next.work_items.iter_mut().filter(|item| {
+                        handoff.work_item_ids.is_empty()
+                            || handoff.work_item_ids.contains(&item.work_item_id)
+                    }) {
+                        if item.status != WorkItemStatus::Done {
+                            item.assigned_agent = Some(assignee.to_string());
+                        }
+                    }
+                    commands.push(MissionCommand::EmitNotice {
+                        mission_id: next.mission_id.clone(),
+                        event_type: "mission.handoff.recorded".to_string(),
+                        properties: json!({
+                            "handoffID": handoff.handoff_id,
+                            "kind": handoff.kind,
+                            "fromRole": handoff.from_role,
+                            "toRole": handoff.to_role,
+                            "workItemIDs": handoff.work_item_ids,
+                            "openItems": handoff.document.open_items.len(),
+                        }),
+                    });
+                    next.active_role = Some(handoff.to_role.clone());
+                    next.handoffs.push(handoff);
+                    changed = true;
+                }
+            }
             _ => {}
         }
 
@@ -192,7 +250,24 @@ fn is_success_status(status: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::WorkItemStatus;
+    use crate::{AgentRole, HandoffDocument, MissionHandoff, WorkItemStatus};
+
+    fn handoff(kind: HandoffKind, from: Option<AgentRole>, to: AgentRole) -> MissionHandoff {
+        MissionHandoff {
+            handoff_id: "h-1".to_string(),
+            kind,
+            from_role: from,
+            to_role: to,
+            work_item_ids: vec!["w-1".to_string()],
+            document: HandoffDocument {
+                summary: "Patch drafted, tests pending".to_string(),
+                open_items: vec!["add regression test".to_string()],
+                artifacts: vec!["artifact://patch.diff".to_string()],
+            },
+            reason: None,
+            created_at_ms: 1,
+        }
+    }
 
     fn base_state() -> MissionState {
         let spec = MissionSpec::new("Default flow", "Ship with gates");
@@ -211,6 +286,9 @@ mod tests {
                 artifact_refs: Vec::new(),
                 metadata: None,
             }],
+            active_role: None,
+            handoffs: Vec::new(),
+            compliance_report: None,
             revision: 1,
             updated_at_ms: 0,
         }
@@ -299,4 +377,62 @@ mod tests {
             )
         }));
     }
+
+    #[test]
+    fn handoff_reassigns_work_and_records_document() {
+        let state = base_state();
+        let (next, commands) = DefaultMissionReducer::reduce(
+            &state,
+            MissionEvent::RoleHandoff {
+                mission_id: state.mission_id.clone(),
+                handoff: handoff(
+                    HandoffKind::Handoff,
+                    Some(AgentRole::Worker),
+                    AgentRole::Tester,
+                ),
+            },
+        );
+        assert_eq!(next.active_role, Some(AgentRole::Tester));
+        assert_eq!(next.work_items[0].assigned_agent.as_deref(), Some("tester"));
+        assert_eq!(next.handoffs.len(), 1);
+        assert_eq!(next.handoffs[0].document.open_items.len(), 1);
+        assert_eq!(next.revision, 2);
+        assert!(commands.iter().any(|command| matches!(
+            command,
+            MissionCommand::EmitNotice { event_type, .. } if event_type == "mission.handoff.recorded"
+        )));
+    }
+
+    #[test]
+    fn handoff_from_non_holder_is_rejected_but_takeover_is_not() {
+        let mut state = base_state();
+        state.active_role = Some(AgentRole::Reviewer);
+        let (next, commands) = DefaultMissionReducer::reduce(
+            &state,
+            MissionEvent::RoleHandoff {
+                mission_id: state.mission_id.clone(),
+                handoff: handoff(
+                    HandoffKind::Handoff,
+                    Some(AgentRole::Worker),
+                    AgentRole::Tester,
+                ),
+            },
+        );
+        assert_eq!(next.revision, 1);
+        assert!(next.handoffs.is_empty());
+        assert!(commands.iter().any(|command| matches!(
+            command,
+            MissionCommand::EmitNotice { event_type, .. } if event_type == "mission.handoff.rejected"
+        )));
+
+        let (next, _) = DefaultMissionReducer::reduce(
+            &state,
+            MissionEvent::RoleHandoff {
+                mission_id: state.mission_id.clone(),
+                handoff: handoff(HandoffKind::Takeover, None, AgentRole::Orchestrator),
+            },
+        );
+        assert_eq!(next.active_role, Some(AgentRole::Orchestrator));
+        assert_eq!(next.handoffs[0].kind, HandoffKind::Takeover);
+    }
 }
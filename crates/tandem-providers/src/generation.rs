@@ -0,0 +1,235 @@
+//! Generation parameters and named presets.
+//!
+//! [`GenerationParams`] carries the sampling settings of one request
+//! (temperature, top_p, max_tokens, stop sequences); unset fields keep the
+//! provider's defaults. A [`GenerationPreset`] names a set of parameters,
+//! optionally pinned to a provider/model, so agent profiles, sessions and
+//! routines can pick one by name. `precise`, `creative` and `cheap-long` are
+//! built in; `generation_presets` in the config adds presets or replaces
+//! built-in ones by name.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Most providers accept at most four stop sequences.
+pub const MAX_STOP_SEQUENCES: usize = 4;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+}
+
+impl GenerationParams {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// These parameters with `temperature` replaced when it is set.
+    pub fn with_temperature(&self, temperature: Option<f32>) -> Self {
+        Self {
+            temperature: temperature.or(self.temperature),
+            ..self.clone()
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                return Err(format!("temperature {temperature} is outside 0..=2"));
+            }
+        }
+        if let Some(top_p) = self.top_p {
+            if !(top_p > 0.0 && top_p <= 1.0) {
+                return Err(format!("top_p {top_p} is outside (0, 1]"));
+            }
+        }
+        if self.max_tokens == Some(0) {
+            return Err("max_tokens must be positive".to_string());
+        }
+        if self.stop.len() > MAX_STOP_SEQUENCES {
+            return Err(format!(
+                "at most {MAX_STOP_SEQUENCES} stop sequences are supported"
+            ));
+        }
+        if self.stop.iter().any(|stop| stop.is_empty()) {
+            return Err("stop sequences must not be empty".to_string());
+        }
+        Ok(())
+    }
+
+    /// Set the fields on an OpenAI-compatible chat completions body.
+    /// `max_tokens` replaces the body's default.
+    pub fn apply_openai(&self, body: &mut Value) {
+        if let Some(temperature) = self.temperature {
+            body["temperature"] = json!(temperature);
+        }
+        if let Some(top_p) = self.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+        if !self.stop.is_empty() {
+            body["stop"] = json!(self.stop);
+        }
+    }
+
+    /// Set the fields on an Anthropic messages body.
+    pub fn apply_anthropic(&self, body: &mut Value) {
+        if let Some(temperature) = self.temperature {
+            // Anthropic accepts 0.0..=1.0.
+            body["temperature"] = json!(temperature.clamp(0.0, 1.0));
+        }
+        if let Some(top_p) = self.top_p {
+            body["top_p"] = json!(top_p);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+        if !self.stop.is_empty() {
+            body["stop_sequences"] = json!(self.stop);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationPreset {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Provider and model the preset runs on; both must be set to take
+    /// effect. An explicit model on the request still wins.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(flatten)]
+    pub params: GenerationParams,
+}
+
+impl GenerationPreset {
+    /// `(provider, model)` when the preset pins both.
+    pub fn model_route(&self) -> Option<(String, String)> {
+        let provider = self.provider.as_deref().map(str::trim)?;
+        let model = self.model.as_deref().map(str::trim)?;
+        if provider.is_empty() || model.is_empty() {
+            return None;
+        }
+        Some((provider.to_string(), model.to_string()))
+    }
+}
+
+pub fn builtin_generation_presets() -> BTreeMap<String, GenerationPreset> {
+    let preset = |description: &str, params: GenerationParams| GenerationPreset {
+        description: Some(description.to_string()),
+        params,
+        ..Default::default()
+    };
+    BTreeMap::from([
+        (
+            "precise".to_string(),
+            preset(
+                "Low temperature for focused, repeatable answers",
+                GenerationParams {
+                    temperature: Some(0.1),
+                    top_p: Some(0.9),
+                    ..Default::default()
+                },
+            ),
+        ),
+        (
+            "creative".to_string(),
+            preset(
+                "High temperature for brainstorming and drafting",
+                GenerationParams {
+                    temperature: Some(1.0),
+                    top_p: Some(0.95),
+                    ..Default::default()
+                },
+            ),
+        ),
+        (
+            "cheap-long".to_string(),
+            preset(
+                "Long outputs; override with a cheap provider/model in generation_presets",
+                GenerationParams {
+                    temperature: Some(0.3),
+                    max_tokens: Some(8_192),
+                    ..Default::default()
+                },
+            ),
+        ),
+    ])
+}
+
+/// Built-in presets overlaid with `configured` ones. Invalid configured
+/// presets are dropped and reported.
+pub fn resolve_generation_presets(
+    configured: &HashMap<String, GenerationPreset>,
+) -> (BTreeMap<String, GenerationPreset>, Vec<String>) {
+    let mut presets = builtin_generation_presets();
+    let mut errors = Vec::new();
+    for (name, preset) in configured {
+        let name = name.trim();
+        if name.is_empty() {
+            errors.push("generation preset with an empty name".to_string());
+            continue;
+        }
+        match preset.params.validate() {
+            Ok(()) => {
+                presets.insert(name.to_string(), preset.clone());
+            }
+            Err(error) => errors.push(format!("generation preset `{name}`: {error}")),
+        }
+    }
+    (presets, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn configured_presets_override_builtins_and_apply_to_bodies() {
+        let configured: HashMap<String, GenerationPreset> = serde_json::from_value(json!({
+            "cheap-long": {"provider": "groq", "model": "llama-3.1-8b", "max_tokens": 16000},
+            "strict-json": {"temperature": 0, "stop": ["```"]},
+            "broken": {"top_p": 1.5},
+        }))
+        .expect("presets");
+        let (presets, errors) = resolve_generation_presets(&configured);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("broken"));
+        assert!(presets.contains_key("precise") && presets.contains_key("creative"));
+        let cheap = &presets["cheap-long"];
+        assert_eq!(
+            cheap.model_route(),
+            Some(("groq".to_string(), "llama-3.1-8b".to_string()))
+        );
+        assert_eq!(cheap.params.temperature, None);
+
+        let params = presets["strict-json"].params.clone();
+        let mut openai = json!({"model": "m", "max_tokens": 2048});
+        params.apply_openai(&mut openai);
+        assert_eq!(openai["temperature"], json!(0.0));
+        assert_eq!(openai["stop"], json!(["```"]));
+        assert_eq!(openai["max_tokens"], json!(2048));
+
+        let mut anthropic = json!({"model": "m", "max_tokens": 1024});
+        presets["creative"]
+            .params
+            .with_temperature(Some(1.6))
+            .apply_anthropic(&mut anthropic);
+        assert_eq!(anthropic["temperature"], json!(1.0));
+        assert!(anthropic["top_p"].as_f64().is_some());
+        assert!(anthropic.get("stop_sequences").is_none());
+    }
+}
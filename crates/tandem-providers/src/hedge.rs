@@ -0,0 +1,279 @@
+//! Hedged provider requests for latency-sensitive runs.
+//!
+//! The primary request gets a head start of `delay`. If it has not produced
+//! its first chunk by then, the same request is sent to a fast fallback model
+//! and whichever stream yields first is kept; the other one is cancelled.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+
+use crate::StreamChunk;
+
+pub type ChunkStream = Pin<Box<dyn Stream<Item = anyhow::Result<StreamChunk>> + Send>>;
+
+const DEFAULT_HEDGE_DELAY_MS: u64 = 2_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HedgePolicy {
+    /// Head start the primary gets before the fallback is sent.
+    pub delay_ms: u64,
+    pub fallback_provider: String,
+    pub fallback_model: String,
+}
+
+impl HedgePolicy {
+    /// Policy from `TANDEM_HEDGE_FALLBACK_MODEL` (`provider/model`) and
+    /// `TANDEM_HEDGE_DELAY_MS`. Hedging is off unless a fallback is set.
+    pub fn from_env() -> Option<Self> {
+        let raw = std::env::var("TANDEM_HEDGE_FALLBACK_MODEL").ok()?;
+        let (provider, model) = raw.trim().split_once('/')?;
+        if provider.trim().is_empty() || model.trim().is_empty() {
+            return None;
+        }
+        let delay_ms = std::env::var("TANDEM_HEDGE_DELAY_MS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(DEFAULT_HEDGE_DELAY_MS);
+        Some(Self {
+            delay_ms,
+            fallback_provider: provider.trim().to_string(),
+            fallback_model: model.trim().to_string(),
+        })
+    }
+
+    /// Hedging to the model that is already the primary is pointless.
+    pub fn targets(&self, provider_id: &str, model_id: &str) -> bool {
+        self.fallback_provider == provider_id && self.fallback_model == model_id
+    }
+
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HedgeWinner {
+    Primary,
+    Fallback,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HedgeOutcome {
+    /// Whether the fallback request was actually sent.
+    pub hedged: bool,
+    pub winner: HedgeWinner,
+    pub first_chunk_ms: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct HedgeStats {
+    requests: AtomicU64,
+    hedged: AtomicU64,
+    primary_wins: AtomicU64,
+    fallback_wins: AtomicU64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HedgeStatsSnapshot {
+    pub requests: u64,
+    pub hedged: u64,
+    pub primary_wins: u64,
+    pub fallback_wins: u64,
+}
+
+impl HedgeStats {
+    pub fn record(&self, outcome: &HedgeOutcome) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if outcome.hedged {
+            self.hedged.fetch_add(1, Ordering::Relaxed);
+        }
+        match outcome.winner {
+            HedgeWinner::Primary => self.primary_wins.fetch_add(1, Ordering::Relaxed),
+            HedgeWinner::Fallback => self.fallback_wins.fetch_add(1, Ordering::Relaxed),
+        };
+    }
+
+    pub fn snapshot(&self) -> HedgeStatsSnapshot {
+        HedgeStatsSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            hedged: self.hedged.load(Ordering::Relaxed),
+            primary_wins: self.primary_wins.load(Ordering::Relaxed),
+            fallback_wins: self.fallback_wins.load(Ordering::Relaxed),
+        }
+    }
+}
+
+type FirstChunk = (Option<anyhow::Result<StreamChunk>>, Option<ChunkStream>);
+
+/// Open a stream and wait for its first item.
+async fn first_chunk<F>(start: F) -> FirstChunk
+where
+    F: Future<Output = anyhow::Result<ChunkStream>>,
+{
+    match start.await {
+        Ok(mut stream) => {
+            let first = stream.next().await;
+            (first, Some(stream))
+        }
+        Err(err) => (Some(Err(err)), None),
+    }
+}
+
+fn is_chunk(first: &FirstChunk) -> bool {
+    matches!(first.0, Some(Ok(_)))
+}
+
+/// Put the first item back in front of the rest of the stream. A request that
+/// failed before streaming surfaces as an error, like an unhedged call would.
+fn reassemble((first, rest): FirstChunk) -> anyhow::Result<ChunkStream> {
+    match (first, rest) {
+        (Some(Err(err)), None) => Err(err),
+        (first, Some(rest)) => Ok(Box::pin(futures::stream::iter(first).chain(rest))),
+        (first, None) => Ok(Box::pin(futures::stream::iter(first))),
+    }
+}
+
+/// Race a primary request against a delayed fallback. Both start futures are
+/// expected to honour their cancellation token; the loser's is cancelled.
+pub async fn race_hedged<P, F>(
+    primary_start: P,
+    fallback_start: F,
+    delay: Duration,
+    primary_cancel: CancellationToken,
+    fallback_cancel: CancellationToken,
+) -> anyhow::Result<(ChunkStream, HedgeOutcome)>
+where
+    P: Future<Output = anyhow::Result<ChunkStream>>,
+    F: Future<Output = anyhow::Result<ChunkStream>>,
+{
+    let started = Instant::now();
+    let outcome = |hedged, winner| HedgeOutcome {
+        hedged,
+        winner,
+        first_chunk_ms: started.elapsed().as_millis() as u64,
+    };
+    let primary = first_chunk(primary_start);
+    tokio::pin!(primary);
+    tokio::select! {
+        first = &mut primary => {
+            fallback_cancel.cancel();
+            return Ok((reassemble(first)?, outcome(false, HedgeWinner::Primary)));
+        }
+        _ = tokio::time::sleep(delay) => {}
+    }
+
+    let fallback = first_chunk(fallback_start);
+    tokio::pin!(fallback);
+    let mut primary_failed: Option<FirstChunk> = None;
+    let mut fallback_failed = false;
+    loop {
+        tokio::select! {
+            first = &mut primary, if primary_failed.is_none() => {
+                if is_chunk(&first) || fallback_failed {
+                    fallback_cancel.cancel();
+                    return Ok((reassemble(first)?, outcome(true, HedgeWinner::Primary)));
+                }
+                primary_failed = Some(first);
+            }
+            first = &mut fallback, if !fallback_failed => {
+                if is_chunk(&first) {
+                    primary_cancel.cancel();
+                    return Ok((reassemble(first)?, outcome(true, HedgeWinner::Fallback)));
+                }
+                fallback_failed = true;
+                if let Some(first) = primary_failed.take() {
+                    // Both failed; report the primary's failure.
+                    return Ok((reassemble(first)?, outcome(true, HedgeWinner::Primary)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn delayed(ms: u64, text: &'static str) -> anyhow::Result<ChunkStream> {
+        Ok(Box::pin(async_stream::try_stream! {
+            tokio::time::sleep(Duration::from_millis(ms)).await;
+            yield StreamChunk::TextDelta(text.to_string());
+        }))
+    }
+
+    async fn first_text(mut stream: ChunkStream) -> String {
+        match stream.next().await {
+            Some(Ok(StreamChunk::TextDelta(text))) => text,
+            other => panic!("unexpected first chunk: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn fast_primary_is_not_hedged() {
+        let fallback_cancel = CancellationToken::new();
+        let (stream, outcome) = race_hedged(
+            async { delayed(0, "primary") },
+            async { delayed(0, "fallback") },
+            Duration::from_millis(200),
+            CancellationToken::new(),
+            fallback_cancel.clone(),
+        )
+        .await
+        .expect("race");
+        assert!(!outcome.hedged);
+        assert_eq!(outcome.winner, HedgeWinner::Primary);
+        assert!(fallback_cancel.is_cancelled());
+        assert_eq!(first_text(stream).await, "primary");
+    }
+
+    #[tokio::test]
+    async fn slow_primary_loses_to_fallback() {
+        let primary_cancel = CancellationToken::new();
+        let (stream, outcome) = race_hedged(
+            async { delayed(500, "primary") },
+            async { delayed(0, "fallback") },
+            Duration::from_millis(20),
+            primary_cancel.clone(),
+            CancellationToken::new(),
+        )
+        .await
+        .expect("race");
+        assert!(outcome.hedged);
+        assert_eq!(outcome.winner, HedgeWinner::Fallback);
+        assert!(primary_cancel.is_cancelled());
+        assert_eq!(first_text(stream).await, "fallback");
+    }
+
+    #[tokio::test]
+    async fn failed_fallback_waits_for_primary() {
+        let (stream, outcome) = race_hedged(
+            async { delayed(60, "primary") },
+            async { Err(anyhow::anyhow!("fallback down")) },
+            Duration::from_millis(10),
+            CancellationToken::new(),
+            CancellationToken::new(),
+        )
+        .await
+        .expect("race");
+        assert!(outcome.hedged);
+        assert_eq!(outcome.winner, HedgeWinner::Primary);
+        assert_eq!(first_text(stream).await, "primary");
+
+        let stats = HedgeStats::default();
+        stats.record(&outcome);
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.requests, 1);
+        assert_eq!(snapshot.hedged, 1);
+        assert_eq!(snapshot.primary_wins, 1);
+    }
+}
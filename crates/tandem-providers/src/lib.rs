@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::{pin::Pin, str};
 
@@ -14,6 +15,16 @@ use tokio_util::sync::CancellationToken;
 
 use tandem_types::{ModelInfo, ProviderInfo, ToolSchema};
 
+pub mod generation;
+pub mod hedge;
+pub mod pool;
+
+pub use generation::{
+    builtin_generation_presets, resolve_generation_presets, GenerationParams, GenerationPreset,
+};
+pub use hedge::{HedgeOutcome, HedgePolicy, HedgeStatsSnapshot, HedgeWinner};
+pub use pool::{ConnectionPoolConfig, ConnectionStatsSnapshot, PrewarmResult};
+
 fn provider_max_tokens() -> u32 {
     std::env::var("TANDEM_PROVIDER_MAX_TOKENS")
         .ok()
@@ -27,6 +38,9 @@ pub struct ProviderConfig {
     pub api_key: Option<String>,
     pub url: Option<String>,
     pub default_model: Option<String>,
+    /// Overrides `connection_pool.max_idle_per_host` for this provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_idle_connections: Option<usize>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -34,6 +48,10 @@ pub struct AppConfig {
     #[serde(default)]
     pub providers: HashMap<String, ProviderConfig>,
     pub default_provider: Option<String>,
+    #[serde(default)]
+    pub connection_pool: ConnectionPoolConfig,
+    #[serde(default)]
+    pub generation_presets: HashMap<String, GenerationPreset>,
 }
 
 /// Configuration for background memory consolidation via a cheap/free LLM.
@@ -88,6 +106,11 @@ pub struct TokenUsage {
 #[async_trait]
 pub trait Provider: Send + Sync {
     fn info(&self) -> ProviderInfo;
+    /// Base URL of the provider's API, for connectivity checks. `None` for
+    /// providers that make no network calls.
+    fn base_url(&self) -> Option<String> {
+        None
+    }
     async fn complete(&self, prompt: &str, model_override: Option<&str>) -> anyhow::Result<String>;
     async fn stream(
         &self,
@@ -111,27 +134,108 @@ pub trait Provider: Send + Sync {
         ]);
         Ok(Box::pin(stream))
     }
+
+    /// Stream with explicit generation parameters. Providers ignore the
+    /// ones they cannot set.
+    async fn stream_with_params(
+        &self,
+        messages: Vec<ChatMessage>,
+        model_override: Option<&str>,
+        tools: Option<Vec<ToolSchema>>,
+        _params: &GenerationParams,
+        cancel: CancellationToken,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<StreamChunk>> + Send>>> {
+        self.stream(messages, model_override, tools, cancel).await
+    }
 }
 
 #[derive(Clone)]
 pub struct ProviderRegistry {
     providers: Arc<RwLock<Vec<Arc<dyn Provider>>>>,
     default_provider: Arc<RwLock<Option<String>>>,
+    hedge_stats: Arc<hedge::HedgeStats>,
+    pool: Arc<pool::ConnectionPool>,
+    prewarm: Arc<AtomicBool>,
+    presets: Arc<RwLock<PresetTable>>,
+}
+
+#[derive(Default)]
+struct PresetTable {
+    presets: BTreeMap<String, GenerationPreset>,
+    errors: Vec<String>,
 }
 
 impl ProviderRegistry {
     pub fn new(config: AppConfig) -> Self {
-        let providers = build_providers(&config);
+        let pool = Arc::new(pool::ConnectionPool::default());
+        let providers = build_providers(&config, &pool);
+        let presets = build_presets(&config);
         Self {
             providers: Arc::new(RwLock::new(providers)),
             default_provider: Arc::new(RwLock::new(config.default_provider)),
+            hedge_stats: Arc::new(hedge::HedgeStats::default()),
+            pool,
+            prewarm: Arc::new(AtomicBool::new(config.connection_pool.prewarm)),
+            presets: Arc::new(RwLock::new(presets)),
         }
     }
 
     pub async fn reload(&self, config: AppConfig) {
-        let rebuilt = build_providers(&config);
+        self.pool.begin_reload();
+        let rebuilt = build_providers(&config, &self.pool);
+        self.pool
+            .retain(&rebuilt.iter().map(|p| p.info().id).collect::<Vec<_>>());
         *self.providers.write().await = rebuilt;
-        *self.default_provider.write().await = config.default_provider;
+        *self.default_provider.write().await = config.default_provider.clone();
+        *self.presets.write().await = build_presets(&config);
+        self.prewarm
+            .store(config.connection_pool.prewarm, Ordering::Relaxed);
+        self.spawn_prewarm_if_enabled();
+    }
+
+    /// Open a connection to every remote provider through its pooled client.
+    pub async fn prewarm(&self) -> Vec<(String, PrewarmResult)> {
+        let endpoints = self.endpoints().await;
+        let attempts = endpoints.iter().map(|(id, url)| async move {
+            let result = self.pool.prewarm(id, url).await?;
+            Some((id.clone(), result))
+        });
+        futures::future::join_all(attempts)
+            .await
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// Pre-warm in the background when `connection_pool.prewarm` is set.
+    pub fn spawn_prewarm_if_enabled(&self) {
+        if !self.prewarm.load(Ordering::Relaxed) {
+            return;
+        }
+        let registry = self.clone();
+        // Outcomes are kept in the connection stats.
+        tokio::spawn(async move {
+            registry.prewarm().await;
+        });
+    }
+
+    /// Built-in and configured generation presets by name.
+    pub async fn generation_presets(&self) -> BTreeMap<String, GenerationPreset> {
+        self.presets.read().await.presets.clone()
+    }
+
+    pub async fn generation_preset(&self, name: &str) -> Option<GenerationPreset> {
+        self.presets.read().await.presets.get(name.trim()).cloned()
+    }
+
+    /// Configured presets that were dropped as invalid on the last load.
+    pub async fn generation_preset_errors(&self) -> Vec<String> {
+        self.presets.read().await.errors.clone()
+    }
+
+    /// Pool settings and request counters of every provider's client.
+    pub fn connection_stats(&self) -> Vec<ConnectionStatsSnapshot> {
+        self.pool.snapshot()
     }
 
     pub async fn list(&self) -> Vec<ProviderInfo> {
@@ -143,6 +247,16 @@ impl ProviderRegistry {
             .collect()
     }
 
+    /// `(provider id, base URL)` for every provider that talks to a remote API.
+    pub async fn endpoints(&self) -> Vec<(String, String)> {
+        self.providers
+            .read()
+            .await
+            .iter()
+            .filter_map(|p| p.base_url().map(|url| (p.info().id, url)))
+            .collect()
+    }
+
     pub async fn default_complete(&self, prompt: &str) -> anyhow::Result<String> {
         let provider = self.select_provider(None).await?;
         provider.complete(prompt, None).await
@@ -240,9 +354,81 @@ impl ProviderRegistry {
         messages: Vec<ChatMessage>,
         tools: Option<Vec<ToolSchema>>,
         cancel: CancellationToken,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<StreamChunk>> + Send>>> {
+        self.stream_for_provider_with_params(
+            provider_id,
+            model_id,
+            messages,
+            tools,
+            &GenerationParams::default(),
+            cancel,
+        )
+        .await
+    }
+
+    pub async fn stream_for_provider_with_params(
+        &self,
+        provider_id: Option<&str>,
+        model_id: Option<&str>,
+        messages: Vec<ChatMessage>,
+        tools: Option<Vec<ToolSchema>>,
+        params: &GenerationParams,
+        cancel: CancellationToken,
     ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<StreamChunk>> + Send>>> {
         let provider = self.select_provider(provider_id).await?;
-        provider.stream(messages, model_id, tools, cancel).await
+        provider
+            .stream_with_params(messages, model_id, tools, params, cancel)
+            .await
+    }
+
+    /// Stream from the primary model, hedging to `policy`'s fallback model if
+    /// the primary has not produced a first chunk within the policy delay.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn stream_hedged(
+        &self,
+        provider_id: Option<&str>,
+        model_id: Option<&str>,
+        messages: Vec<ChatMessage>,
+        tools: Option<Vec<ToolSchema>>,
+        params: &GenerationParams,
+        policy: &HedgePolicy,
+        cancel: CancellationToken,
+    ) -> anyhow::Result<(
+        Pin<Box<dyn Stream<Item = anyhow::Result<StreamChunk>> + Send>>,
+        HedgeOutcome,
+    )> {
+        let primary_cancel = cancel.child_token();
+        let fallback_cancel = cancel.child_token();
+        let primary = self.stream_for_provider_with_params(
+            provider_id,
+            model_id,
+            messages.clone(),
+            tools.clone(),
+            params,
+            primary_cancel.clone(),
+        );
+        let fallback = self.stream_for_provider_with_params(
+            Some(policy.fallback_provider.as_str()),
+            Some(policy.fallback_model.as_str()),
+            messages,
+            tools,
+            params,
+            fallback_cancel.clone(),
+        );
+        let (stream, outcome) = hedge::race_hedged(
+            primary,
+            fallback,
+            policy.delay(),
+            primary_cancel,
+            fallback_cancel,
+        )
+        .await?;
+        self.hedge_stats.record(&outcome);
+        Ok((stream, outcome))
+    }
+
+    pub fn hedge_stats(&self) -> HedgeStatsSnapshot {
+        self.hedge_stats.snapshot()
     }
 
     async fn select_provider(
@@ -277,11 +463,17 @@ impl ProviderRegistry {
     }
 }
 
-fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
+fn build_presets(config: &AppConfig) -> PresetTable {
+    let (presets, errors) = resolve_generation_presets(&config.generation_presets);
+    PresetTable { presets, errors }
+}
+
+fn build_providers(config: &AppConfig, pool: &pool::ConnectionPool) -> Vec<Arc<dyn Provider>> {
     let mut providers: Vec<Arc<dyn Provider>> = Vec::new();
 
     add_openai_provider(
         config,
+        pool,
         &mut providers,
         "ollama",
         "Ollama",
@@ -291,6 +483,7 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
     );
     add_openai_provider(
         config,
+        pool,
         &mut providers,
         "openai",
         "OpenAI",
@@ -300,6 +493,7 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
     );
     add_openai_provider(
         config,
+        pool,
         &mut providers,
         "openrouter",
         "OpenRouter",
@@ -309,6 +503,7 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
     );
     add_openai_provider(
         config,
+        pool,
         &mut providers,
         "groq",
         "Groq",
@@ -318,6 +513,7 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
     );
     add_openai_provider(
         config,
+        pool,
         &mut providers,
         "mistral",
         "Mistral",
@@ -327,6 +523,7 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
     );
     add_openai_provider(
         config,
+        pool,
         &mut providers,
         "together",
         "Together",
@@ -336,6 +533,7 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
     );
     add_openai_provider(
         config,
+        pool,
         &mut providers,
         "azure",
         "Azure OpenAI-Compatible",
@@ -345,6 +543,7 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
     );
     add_openai_provider(
         config,
+        pool,
         &mut providers,
         "bedrock",
         "Bedrock-Compatible",
@@ -354,6 +553,7 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
     );
     add_openai_provider(
         config,
+        pool,
         &mut providers,
         "vertex",
         "Vertex-Compatible",
@@ -363,6 +563,7 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
     );
     add_openai_provider(
         config,
+        pool,
         &mut providers,
         "copilot",
         "GitHub Copilot-Compatible",
@@ -372,6 +573,7 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
     );
 
     if let Some(anthropic) = config.providers.get("anthropic") {
+        let (client, connections) = pooled_client(config, pool, "anthropic", anthropic);
         providers.push(Arc::new(AnthropicProvider {
             api_key: anthropic
                 .api_key
@@ -387,10 +589,12 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
                 .default_model
                 .clone()
                 .unwrap_or_else(|| "claude-sonnet-4-6".to_string()),
-            client: Client::new(),
+            client,
+            connections,
         }));
     }
     if let Some(cohere) = config.providers.get("cohere") {
+        let (client, connections) = pooled_client(config, pool, "cohere", cohere);
         providers.push(Arc::new(CohereProvider {
             api_key: cohere
                 .api_key
@@ -409,7 +613,8 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
                 .default_model
                 .clone()
                 .unwrap_or_else(|| "command-r-plus".to_string()),
-            client: Client::new(),
+            client,
+            connections,
         }));
     }
 
@@ -423,6 +628,7 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
             continue;
         }
 
+        let (client, connections) = pooled_client(config, pool, provider_id, entry);
         providers.push(Arc::new(OpenAICompatibleProvider {
             id: provider_id.to_string(),
             name: humanize_provider_name(provider_id),
@@ -437,7 +643,8 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
                 .default_model
                 .clone()
                 .unwrap_or_else(|| "gpt-4o-mini".to_string()),
-            client: Client::new(),
+            client,
+            connections,
         }));
     }
 
@@ -448,8 +655,25 @@ fn build_providers(config: &AppConfig) -> Vec<Arc<dyn Provider>> {
     providers
 }
 
+/// The shared client for a provider, sized by its pool settings.
+fn pooled_client(
+    config: &AppConfig,
+    pool: &pool::ConnectionPool,
+    id: &str,
+    entry: &ProviderConfig,
+) -> (Client, Arc<pool::ConnectionStats>) {
+    pool.client(
+        id,
+        &config
+            .connection_pool
+            .for_provider(entry.max_idle_connections),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
 fn add_openai_provider(
     config: &AppConfig,
+    pool: &pool::ConnectionPool,
     providers: &mut Vec<Arc<dyn Provider>>,
     id: &str,
     name: &str,
@@ -460,6 +684,7 @@ fn add_openai_provider(
     let Some(entry) = config.providers.get(id) else {
         return;
     };
+    let (client, connections) = pooled_client(config, pool, id, entry);
     providers.push(Arc::new(OpenAICompatibleProvider {
         id: id.to_string(),
         name: name.to_string(),
@@ -478,7 +703,8 @@ fn add_openai_provider(
             .default_model
             .clone()
             .unwrap_or_else(|| default_model.to_string()),
-        client: Client::new(),
+        client,
+        connections,
     }));
 }
 
@@ -609,6 +835,7 @@ struct OpenAICompatibleProvider {
     api_key: Option<String>,
     default_model: String,
     client: Client,
+    connections: Arc<pool::ConnectionStats>,
 }
 
 #[async_trait]
@@ -626,6 +853,10 @@ impl Provider for OpenAICompatibleProvider {
         }
     }
 
+    fn base_url(&self) -> Option<String> {
+        Some(self.base_url.clone())
+    }
+
     async fn complete(&self, prompt: &str, model_override: Option<&str>) -> anyhow::Result<String> {
         let model = model_override
             .map(str::trim)
@@ -651,7 +882,7 @@ impl Provider for OpenAICompatibleProvider {
                 req = req.bearer_auth(api_key);
             }
 
-            match req.send().await {
+            match self.connections.send(req).await {
                 Ok(resp) => {
                     response_opt = Some(resp);
                     break;
@@ -719,6 +950,24 @@ impl Provider for OpenAICompatibleProvider {
         model_override: Option<&str>,
         tools: Option<Vec<ToolSchema>>,
         cancel: CancellationToken,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<StreamChunk>> + Send>>> {
+        self.stream_with_params(
+            messages,
+            model_override,
+            tools,
+            &GenerationParams::default(),
+            cancel,
+        )
+        .await
+    }
+
+    async fn stream_with_params(
+        &self,
+        messages: Vec<ChatMessage>,
+        model_override: Option<&str>,
+        tools: Option<Vec<ToolSchema>>,
+        params: &GenerationParams,
+        cancel: CancellationToken,
     ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<StreamChunk>> + Send>>> {
         let model = model_override
             .map(str::trim)
@@ -751,6 +1000,7 @@ impl Provider for OpenAICompatibleProvider {
             "stream": true,
             "max_tokens": provider_max_tokens(),
         });
+        params.apply_openai(&mut body);
         if !wire_tools.is_empty() {
             body["tools"] = serde_json::Value::Array(wire_tools);
             body["tool_choice"] = json!("auto");
@@ -769,7 +1019,7 @@ impl Provider for OpenAICompatibleProvider {
                 req = req.bearer_auth(api_key);
             }
 
-            match req.send().await {
+            match self.connections.send(req).await {
                 Ok(resp) => {
                     resp_opt = Some(resp);
                     break;
@@ -972,6 +1222,7 @@ struct AnthropicProvider {
     api_key: Option<String>,
     default_model: String,
     client: Client,
+    connections: Arc<pool::ConnectionStats>,
 }
 
 struct CohereProvider {
@@ -979,6 +1230,7 @@ struct CohereProvider {
     base_url: String,
     default_model: String,
     client: Client,
+    connections: Arc<pool::ConnectionStats>,
 }
 
 #[async_trait]
@@ -996,6 +1248,10 @@ impl Provider for AnthropicProvider {
         }
     }
 
+    fn base_url(&self) -> Option<String> {
+        Some("https://api.anthropic.com/v1".to_string())
+    }
+
     async fn complete(&self, prompt: &str, model_override: Option<&str>) -> anyhow::Result<String> {
         let model = model_override
             .map(str::trim)
@@ -1013,7 +1269,7 @@ impl Provider for AnthropicProvider {
         if let Some(key) = &self.api_key {
             req = req.header("x-api-key", key);
         }
-        let value: serde_json::Value = req.send().await?.json().await?;
+        let value: serde_json::Value = self.connections.send(req).await?.json().await?;
         let text = value["content"][0]["text"]
             .as_str()
             .unwrap_or("No completion content.")
@@ -1022,34 +1278,54 @@ impl Provider for AnthropicProvider {
     }
 
     async fn stream(
+        &self,
+        messages: Vec<ChatMessage>,
+        model_override: Option<&str>,
+        tools: Option<Vec<ToolSchema>>,
+        cancel: CancellationToken,
+    ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<StreamChunk>> + Send>>> {
+        self.stream_with_params(
+            messages,
+            model_override,
+            tools,
+            &GenerationParams::default(),
+            cancel,
+        )
+        .await
+    }
+
+    async fn stream_with_params(
         &self,
         messages: Vec<ChatMessage>,
         model_override: Option<&str>,
         _tools: Option<Vec<ToolSchema>>,
+        params: &GenerationParams,
         cancel: CancellationToken,
     ) -> anyhow::Result<Pin<Box<dyn Stream<Item = anyhow::Result<StreamChunk>> + Send>>> {
         let model = model_override
             .map(str::trim)
             .filter(|m| !m.is_empty())
             .unwrap_or(self.default_model.as_str());
+        let mut body = json!({
+            "model": model,
+            "max_tokens": 1024,
+            "stream": true,
+            "messages": messages
+                .into_iter()
+                .map(|m| json!({"role": m.role, "content": m.content}))
+                .collect::<Vec<_>>(),
+        });
+        params.apply_anthropic(&mut body);
         let mut req = self
             .client
             .post("https://api.anthropic.com/v1/messages")
             .header("anthropic-version", "2023-06-01")
-            .json(&json!({
-                "model": model,
-                "max_tokens": 1024,
-                "stream": true,
-                "messages": messages
-                    .into_iter()
-                    .map(|m| json!({"role": m.role, "content": m.content}))
-                    .collect::<Vec<_>>(),
-            }));
+            .json(&body);
         if let Some(key) = &self.api_key {
             req = req.header("x-api-key", key);
         }
 
-        let resp = req.send().await?;
+        let resp = self.connections.send(req).await?;
         let mut bytes = resp.bytes_stream();
         let stream = try_stream! {
             let mut buffer = String::new();
@@ -1122,6 +1398,10 @@ impl Provider for CohereProvider {
         }
     }
 
+    fn base_url(&self) -> Option<String> {
+        Some(self.base_url.clone())
+    }
+
     async fn complete(&self, prompt: &str, model_override: Option<&str>) -> anyhow::Result<String> {
         let model = model_override
             .map(str::trim)
@@ -1137,7 +1417,7 @@ impl Provider for CohereProvider {
         if let Some(key) = &self.api_key {
             req = req.bearer_auth(key);
         }
-        let value: serde_json::Value = req.send().await?.json().await?;
+        let value: serde_json::Value = self.connections.send(req).await?.json().await?;
         let text = value["message"]["content"][0]["text"]
             .as_str()
             .or_else(|| value["text"].as_str())
@@ -1312,12 +1592,14 @@ mod tests {
                     api_key,
                     url: None,
                     default_model: Some(format!("{id}-model")),
+                    max_idle_connections: None,
                 },
             );
         }
         AppConfig {
             providers,
             default_provider: default_provider.map(|s| s.to_string()),
+            ..Default::default()
         }
     }
 
@@ -1346,6 +1628,20 @@ mod tests {
         assert_eq!(provider.info().id, "openai");
     }
 
+    #[tokio::test]
+    async fn endpoints_list_remote_providers_only() {
+        let registry = ProviderRegistry::new(cfg(&["ollama"], None, false));
+        assert_eq!(
+            registry.endpoints().await,
+            vec![(
+                "ollama".to_string(),
+                "http://127.0.0.1:11434/v1".to_string()
+            )]
+        );
+        let empty = ProviderRegistry::new(AppConfig::default());
+        assert!(empty.endpoints().await.is_empty());
+    }
+
     #[tokio::test]
     async fn explicit_unknown_provider_errors() {
         let registry = ProviderRegistry::new(cfg(&["openai"], None, true));
@@ -0,0 +1,308 @@
+//! Shared HTTP clients for providers.
+//!
+//! Every provider gets one pooled `reqwest::Client`, keyed by provider id and
+//! kept across config reloads while its pool settings are unchanged, so warm
+//! TLS connections survive a reload. With `connection_pool.prewarm` set, the
+//! registry opens a connection to each provider's API at startup and after
+//! each reload, so the first prompt does not pay for the handshake.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+
+const PREWARM_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConnectionPoolConfig {
+    /// Idle connections kept open per host.
+    #[serde(default = "default_max_idle_per_host")]
+    pub max_idle_per_host: usize,
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+    #[serde(default = "default_connect_timeout_secs")]
+    pub connect_timeout_secs: u64,
+    #[serde(default = "default_tcp_keepalive_secs")]
+    pub tcp_keepalive_secs: u64,
+    /// Open a connection to every provider at startup and on config reload.
+    #[serde(default)]
+    pub prewarm: bool,
+}
+
+fn default_max_idle_per_host() -> usize {
+    8
+}
+
+fn default_idle_timeout_secs() -> u64 {
+    90
+}
+
+fn default_connect_timeout_secs() -> u64 {
+    10
+}
+
+fn default_tcp_keepalive_secs() -> u64 {
+    60
+}
+
+impl Default for ConnectionPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_idle_per_host: default_max_idle_per_host(),
+            idle_timeout_secs: default_idle_timeout_secs(),
+            connect_timeout_secs: default_connect_timeout_secs(),
+            tcp_keepalive_secs: default_tcp_keepalive_secs(),
+            prewarm: false,
+        }
+    }
+}
+
+impl ConnectionPoolConfig {
+    /// Settings for one provider, with its own pool size if it sets one.
+    pub fn for_provider(&self, max_idle_override: Option<usize>) -> Self {
+        Self {
+            max_idle_per_host: max_idle_override.unwrap_or(self.max_idle_per_host),
+            ..self.clone()
+        }
+    }
+
+    /// Whether clients built from both would be the same (`prewarm` aside).
+    fn same_pool(&self, other: &Self) -> bool {
+        Self {
+            prewarm: other.prewarm,
+            ..self.clone()
+        } == *other
+    }
+
+    fn build_client(&self) -> Client {
+        Client::builder()
+            .pool_max_idle_per_host(self.max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(self.idle_timeout_secs))
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs.max(1)))
+            .tcp_keepalive(Duration::from_secs(self.tcp_keepalive_secs.max(1)))
+            .build()
+            .unwrap_or_else(|_| Client::new())
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrewarmResult {
+    pub ok: bool,
+    pub latency_ms: u64,
+    pub at_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Request counters for one provider's client.
+#[derive(Debug, Default)]
+pub struct ConnectionStats {
+    requests: AtomicU64,
+    failures: AtomicU64,
+    connect_errors: AtomicU64,
+    latency_total_ms: AtomicU64,
+    last_latency_ms: AtomicU64,
+    last_request_ms: AtomicU64,
+    prewarm: Mutex<Option<PrewarmResult>>,
+}
+
+impl ConnectionStats {
+    /// Send `req`, recording the time to response headers and send errors.
+    pub async fn send(&self, req: RequestBuilder) -> reqwest::Result<Response> {
+        let started = Instant::now();
+        let result = req.send().await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.last_request_ms.store(now_ms(), Ordering::Relaxed);
+        match &result {
+            Ok(_) => {
+                self.latency_total_ms
+                    .fetch_add(latency_ms, Ordering::Relaxed);
+                self.last_latency_ms.store(latency_ms, Ordering::Relaxed);
+            }
+            Err(err) => {
+                self.failures.fetch_add(1, Ordering::Relaxed);
+                if err.is_connect() {
+                    self.connect_errors.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        result
+    }
+
+    fn record_prewarm(&self, result: PrewarmResult) {
+        if let Ok(mut slot) = self.prewarm.lock() {
+            *slot = Some(result);
+        }
+    }
+}
+
+struct PooledClient {
+    client: Client,
+    settings: ConnectionPoolConfig,
+    stats: Arc<ConnectionStats>,
+    created_at_ms: u64,
+    /// Pool generation the client was built in.
+    generation: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStatsSnapshot {
+    pub provider_id: String,
+    pub max_idle_per_host: usize,
+    pub idle_timeout_secs: u64,
+    pub requests: u64,
+    pub failures: u64,
+    pub connect_errors: u64,
+    /// Mean time to response headers over successful requests.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub avg_latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_latency_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_request_ms: Option<u64>,
+    pub created_at_ms: u64,
+    pub reloads_survived: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prewarm: Option<PrewarmResult>,
+}
+
+/// One pooled client per provider id.
+#[derive(Default)]
+pub struct ConnectionPool {
+    clients: Mutex<HashMap<String, PooledClient>>,
+    /// Bumped on every config reload.
+    generation: AtomicU64,
+}
+
+impl ConnectionPool {
+    /// The client for `provider_id`; reused when `settings` match the ones
+    /// it was built with.
+    pub fn client(
+        &self,
+        provider_id: &str,
+        settings: &ConnectionPoolConfig,
+    ) -> (Client, Arc<ConnectionStats>) {
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        let reuse = clients
+            .get(provider_id)
+            .is_some_and(|pooled| pooled.settings.same_pool(settings));
+        if !reuse {
+            clients.insert(
+                provider_id.to_string(),
+                PooledClient {
+                    client: settings.build_client(),
+                    settings: settings.clone(),
+                    stats: Arc::new(ConnectionStats::default()),
+                    created_at_ms: now_ms(),
+                    generation: self.generation.load(Ordering::Relaxed),
+                },
+            );
+        }
+        let pooled = clients.get(provider_id).expect("client inserted above");
+        (pooled.client.clone(), pooled.stats.clone())
+    }
+
+    /// Start a config reload; clients kept through it count it as survived.
+    pub fn begin_reload(&self) {
+        self.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Drop clients of providers that are no longer configured.
+    pub fn retain(&self, provider_ids: &[String]) {
+        let mut clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        clients.retain(|id, _| provider_ids.contains(id));
+    }
+
+    /// Open a connection through the provider's client. Any HTTP response
+    /// counts: the point is the TCP and TLS handshake, not the status.
+    pub async fn prewarm(&self, provider_id: &str, base_url: &str) -> Option<PrewarmResult> {
+        let (client, stats) = {
+            let clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+            let pooled = clients.get(provider_id)?;
+            (pooled.client.clone(), pooled.stats.clone())
+        };
+        let started = Instant::now();
+        let outcome = client.head(base_url).timeout(PREWARM_TIMEOUT).send().await;
+        let result = PrewarmResult {
+            ok: outcome.is_ok(),
+            latency_ms: started.elapsed().as_millis() as u64,
+            at_ms: now_ms(),
+            error: outcome.err().map(|err| err.to_string()),
+        };
+        stats.record_prewarm(result.clone());
+        Some(result)
+    }
+
+    pub fn snapshot(&self) -> Vec<ConnectionStatsSnapshot> {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let clients = self.clients.lock().unwrap_or_else(|e| e.into_inner());
+        let mut rows = clients
+            .iter()
+            .map(|(id, pooled)| {
+                let stats = &pooled.stats;
+                let requests = stats.requests.load(Ordering::Relaxed);
+                let failures = stats.failures.load(Ordering::Relaxed);
+                let succeeded = requests.saturating_sub(failures);
+                let nonzero = |v: u64| (v > 0).then_some(v);
+                ConnectionStatsSnapshot {
+                    provider_id: id.clone(),
+                    max_idle_per_host: pooled.settings.max_idle_per_host,
+                    idle_timeout_secs: pooled.settings.idle_timeout_secs,
+                    requests,
+                    failures,
+                    connect_errors: stats.connect_errors.load(Ordering::Relaxed),
+                    avg_latency_ms: (succeeded > 0)
+                        .then(|| stats.latency_total_ms.load(Ordering::Relaxed) / succeeded),
+                    last_latency_ms: (succeeded > 0)
+                        .then(|| stats.last_latency_ms.load(Ordering::Relaxed)),
+                    last_request_ms: nonzero(stats.last_request_ms.load(Ordering::Relaxed)),
+                    created_at_ms: pooled.created_at_ms,
+                    reloads_survived: generation.saturating_sub(pooled.generation),
+                    prewarm: stats.prewarm.lock().ok().and_then(|p| p.clone()),
+                }
+            })
+            .collect::<Vec<_>>();
+        rows.sort_by(|a, b| a.provider_id.cmp(&b.provider_id));
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clients_are_kept_until_their_settings_change() {
+        let pool = ConnectionPool::default();
+        let settings = ConnectionPoolConfig::default();
+        let (_, first) = pool.client("openai", &settings);
+        let (_, again) = pool.client("openai", &settings);
+        assert!(Arc::ptr_eq(&first, &again));
+
+        pool.begin_reload();
+        pool.client("openai", &settings);
+        assert_eq!(pool.snapshot()[0].reloads_survived, 1);
+
+        let (_, resized) = pool.client("openai", &settings.for_provider(Some(32)));
+        assert!(!Arc::ptr_eq(&first, &resized));
+        let snapshot = pool.snapshot();
+        assert_eq!(snapshot[0].max_idle_per_host, 32);
+        assert_eq!(snapshot[0].reloads_survived, 0);
+
+        pool.retain(&[]);
+        assert!(pool.snapshot().is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+//! Advisory per-file locks for concurrent sessions.
+//!
+//! Mutating tools take a lock on every file they touch, owned by the run that
+//! issued the call. A run keeps its locks until it finishes or is cancelled,
+//! so a second session editing the same file gets a [`FileLockConflict`]
+//! instead of silently overwriting the first one's work. Locks are advisory:
+//! they only coordinate callers that go through the registry.
+
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::now_ms;
+
+/// Locks not refreshed for this long are ignored, in case a run ended
+/// without its release being observed.
+const DEFAULT_LOCK_TTL_MS: u64 = 30 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileLock {
+    pub path: String,
+    #[serde(rename = "runID")]
+    pub run_id: String,
+    #[serde(rename = "sessionID")]
+    pub session_id: String,
+    pub tool: String,
+    pub acquired_at_ms: u64,
+    pub refreshed_at_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileLockConflict {
+    pub path: String,
+    pub held_by: FileLock,
+}
+
+impl FileLockConflict {
+    /// Message for the model that tried to write: what is locked, by whom,
+    /// and how to proceed.
+    pub fn render(&self) -> String {
+        let payload = serde_json::json!({
+            "conflict": "file_locked",
+            "path": self.path,
+            "heldBy": {
+                "sessionID": self.held_by.session_id,
+                "runID": self.held_by.run_id,
+                "tool": self.held_by.tool,
+                "sinceMs": self.held_by.acquired_at_ms,
+            },
+            "suggestion": "Another session is editing this file. Work on other files, wait for that run to finish, or coordinate with the other session (e.g. via sendmessage) before retrying.",
+        });
+        format!(
+            "File `{}` is locked by another session's run; the change was not applied.\n{}",
+            self.path, payload
+        )
+    }
+}
+
+#[derive(Clone)]
+pub struct FileLockRegistry {
+    locks: Arc<RwLock<HashMap<PathBuf, FileLock>>>,
+    ttl_ms: u64,
+}
+
+impl Default for FileLockRegistry {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOCK_TTL_MS)
+    }
+}
+
+/// Lexically normalize so `a/./b` and `a/c/../b` map to the same lock.
+pub fn normalize_lock_path(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other.as_os_str()),
+        }
+    }
+    out
+}
+
+impl FileLockRegistry {
+    pub fn new(ttl_ms: u64) -> Self {
+        Self {
+            locks: Arc::new(RwLock::new(HashMap::new())),
+            ttl_ms,
+        }
+    }
+
+    /// TTL from `TANDEM_FILE_LOCK_TTL_MS`.
+    pub fn from_env() -> Self {
+        let ttl_ms = std::env::var("TANDEM_FILE_LOCK_TTL_MS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_LOCK_TTL_MS);
+        Self::new(ttl_ms)
+    }
+
+    /// Lock all `paths` for `run_id`, or none of them if any is held by a
+    /// different run. Locks the run already holds are refreshed.
+    pub async fn acquire(
+        &self,
+        run_id: &str,
+        session_id: &str,
+        tool: &str,
+        paths: &[PathBuf],
+    ) -> Result<(), FileLockConflict> {
+        let now = now_ms();
+        let mut locks = self.locks.write().await;
+        let keys = paths
+            .iter()
+            .map(|p| normalize_lock_path(p))
+            .collect::<Vec<_>>();
+        for key in &keys {
+            if let Some(existing) = locks.get(key) {
+                let expired = now.saturating_sub(existing.refreshed_at_ms) > self.ttl_ms;
+                if existing.run_id != run_id && !expired {
+                    return Err(FileLockConflict {
+                        path: key.to_string_lossy().to_string(),
+                        held_by: existing.clone(),
+                    });
+                }
+            }
+        }
+        for key in keys {
+            let acquired_at_ms = locks
+                .get(&key)
+                .filter(|existing| existing.run_id == run_id)
+                .map(|existing| existing.acquired_at_ms)
+                .unwrap_or(now);
+            locks.insert(
+                key.clone(),
+                FileLock {
+                    path: key.to_string_lossy().to_string(),
+                    run_id: run_id.to_string(),
+                    session_id: session_id.to_string(),
+                    tool: tool.to_string(),
+                    acquired_at_ms,
+                    refreshed_at_ms: now,
+                },
+            );
+        }
+        Ok(())
+    }
+
+    /// Drop every lock held by `run_id`; returns how many were released.
+    pub async fn release_run(&self, run_id: &str) -> usize {
+        let mut locks = self.locks.write().await;
+        let before = locks.len();
+        locks.retain(|_, lock| lock.run_id != run_id);
+        before - locks.len()
+    }
+
+    pub async fn release_session(&self, session_id: &str) -> usize {
+        let mut locks = self.locks.write().await;
+        let before = locks.len();
+        locks.retain(|_, lock| lock.session_id != session_id);
+        before - locks.len()
+    }
+
+    /// Live locks, ordered by path.
+    pub async fn list(&self) -> Vec<FileLock> {
+        let now = now_ms();
+        let mut out = self
+            .locks
+            .read()
+            .await
+            .values()
+            .filter(|lock| now.saturating_sub(lock.refreshed_at_ms) <= self.ttl_ms)
+            .cloned()
+            .collect::<Vec<_>>();
+        out.sort_by(|a, b| a.path.cmp(&b.path));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn conflicting_run_is_rejected_until_release() {
+        let locks = FileLockRegistry::default();
+        let a = vec![PathBuf::from("/ws/src/lib.rs")];
+        locks
+            .acquire("run-1", "s1", "edit", &a)
+            .await
+            .expect("first lock");
+        // Re-entrant for the same run.
+        locks
+            .acquire("run-1", "s1", "write", &a)
+            .await
+            .expect("same run");
+
+        let both = vec![
+            PathBuf::from("/ws/src/main.rs"),
+            PathBuf::from("/ws/src/./lib.rs"),
+        ];
+        let conflict = locks
+            .acquire("run-2", "s2", "apply_patch", &both)
+            .await
+            .unwrap_err();
+        assert_eq!(conflict.path, "/ws/src/lib.rs");
+        assert_eq!(conflict.held_by.session_id, "s1");
+        assert!(conflict.render().contains("file_locked"));
+        // All-or-nothing: main.rs was not locked by the failed attempt.
+        assert_eq!(locks.list().await.len(), 1);
+
+        assert_eq!(locks.release_run("run-1").await, 1);
+        locks
+            .acquire("run-2", "s2", "apply_patch", &both)
+            .await
+            .expect("after release");
+        assert_eq!(locks.release_session("s2").await, 2);
+    }
+
+    #[tokio::test]
+    async fn expired_locks_do_not_block() {
+        let locks = FileLockRegistry::new(1);
+        let path = vec![PathBuf::from("/ws/a.txt")];
+        locks
+            .acquire("run-1", "s1", "write", &path)
+            .await
+            .expect("lock");
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        locks
+            .acquire("run-2", "s2", "write", &path)
+            .await
+            .expect("expired lock is taken over");
+    }
+}
@@ -0,0 +1,559 @@
+//! Small lexical syntax highlighter for file previews.
+//!
+//! This is deliberately not a parser: it recognises comments, strings,
+//! numbers, keywords, type-like and call-like identifiers, which is enough
+//! for readable previews without shipping grammars for every language.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    Plain,
+    Keyword,
+    String,
+    Number,
+    Comment,
+    Type,
+    Function,
+    Punctuation,
+}
+
+impl TokenKind {
+    fn css_class(self) -> &'static str {
+        match self {
+            Self::Plain => "",
+            Self::Keyword => "tk-keyword",
+            Self::String => "tk-string",
+            Self::Number => "tk-number",
+            Self::Comment => "tk-comment",
+            Self::Type => "tk-type",
+            Self::Function => "tk-function",
+            Self::Punctuation => "tk-punctuation",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct HighlightToken {
+    #[serde(rename = "t")]
+    pub kind: TokenKind,
+    #[serde(rename = "v")]
+    pub text: String,
+}
+
+struct LangSpec {
+    keywords: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+}
+
+const C_FAMILY_KEYWORDS: &[&str] = &[
+    "abstract",
+    "as",
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "final",
+    "finally",
+    "for",
+    "from",
+    "func",
+    "function",
+    "go",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "namespace",
+    "new",
+    "null",
+    "nil",
+    "override",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "struct",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "throws",
+    "true",
+    "try",
+    "type",
+    "typeof",
+    "undefined",
+    "using",
+    "var",
+    "void",
+    "when",
+    "while",
+    "yield",
+    "val",
+    "fun",
+    "object",
+    "sealed",
+    "data",
+    "goto",
+    "sizeof",
+    "typedef",
+    "union",
+    "unsigned",
+    "signed",
+    "extern",
+    "volatile",
+    "inline",
+    "template",
+    "typename",
+    "virtual",
+    "operator",
+    "readonly",
+    "declare",
+    "keyof",
+    "satisfies",
+    "chan",
+    "map",
+    "range",
+    "select",
+    "fallthrough",
+    "guard",
+    "let",
+    "self",
+    "Self",
+    "protocol",
+    "extension",
+    "where",
+    "internal",
+    "lateinit",
+    "companion",
+];
+const RUST_KEYWORDS: &[&str] = &[
+    "as",
+    "async",
+    "await",
+    "break",
+    "const",
+    "continue",
+    "crate",
+    "dyn",
+    "else",
+    "enum",
+    "extern",
+    "false",
+    "fn",
+    "for",
+    "if",
+    "impl",
+    "in",
+    "let",
+    "loop",
+    "match",
+    "mod",
+    "move",
+    "mut",
+    "pub",
+    "ref",
+    "return",
+    "self",
+    "Self",
+    "static",
+    "struct",
+    "super",
+    "trait",
+    "true",
+    "type",
+    "unsafe",
+    "use",
+    "where",
+    "while",
+    "macro_rules",
+];
+const PYTHON_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield", "match", "case", "self",
+];
+const RUBY_KEYWORDS: &[&str] = &[
+    "alias",
+    "and",
+    "begin",
+    "break",
+    "case",
+    "class",
+    "def",
+    "defined?",
+    "do",
+    "else",
+    "elsif",
+    "end",
+    "ensure",
+    "false",
+    "for",
+    "if",
+    "in",
+    "module",
+    "next",
+    "nil",
+    "not",
+    "or",
+    "redo",
+    "rescue",
+    "retry",
+    "return",
+    "self",
+    "super",
+    "then",
+    "true",
+    "undef",
+    "unless",
+    "until",
+    "when",
+    "while",
+    "yield",
+    "require",
+    "attr_accessor",
+];
+const SHELL_KEYWORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "case", "esac", "for", "while", "until", "do", "done",
+    "in", "function", "return", "local", "export", "readonly", "set", "unset", "echo", "exit",
+];
+const SQL_KEYWORDS: &[&str] = &[
+    "select",
+    "from",
+    "where",
+    "insert",
+    "into",
+    "values",
+    "update",
+    "set",
+    "delete",
+    "create",
+    "table",
+    "drop",
+    "alter",
+    "add",
+    "index",
+    "join",
+    "left",
+    "right",
+    "inner",
+    "outer",
+    "on",
+    "group",
+    "by",
+    "order",
+    "having",
+    "limit",
+    "offset",
+    "and",
+    "or",
+    "not",
+    "null",
+    "is",
+    "as",
+    "distinct",
+    "union",
+    "all",
+    "primary",
+    "key",
+    "foreign",
+    "references",
+    "default",
+    "case",
+    "when",
+    "then",
+    "else",
+    "end",
+    "in",
+    "exists",
+    "begin",
+    "commit",
+    "rollback",
+    "with",
+];
+const DATA_KEYWORDS: &[&str] = &["true", "false", "null", "yes", "no", "on", "off"];
+
+fn spec_for(language: &str) -> Option<LangSpec> {
+    let slash = &["//"][..];
+    let hash = &["#"][..];
+    Some(match language {
+        "rust" => LangSpec {
+            keywords: RUST_KEYWORDS,
+            line_comments: slash,
+            block_comment: Some(("/*", "*/")),
+            quotes: &['"'],
+        },
+        "c" | "cpp" | "csharp" | "java" | "kotlin" | "swift" | "dart" | "scala" | "go"
+        | "javascript" | "jsx" | "typescript" | "tsx" | "php" | "protobuf" | "vue" | "svelte"
+        | "scss" | "css" => LangSpec {
+            keywords: C_FAMILY_KEYWORDS,
+            line_comments: if language == "php" {
+                &["//", "#"]
+            } else {
+                slash
+            },
+            block_comment: Some(("/*", "*/")),
+            quotes: &['"', '\'', '`'],
+        },
+        "python" => LangSpec {
+            keywords: PYTHON_KEYWORDS,
+            line_comments: hash,
+            block_comment: None,
+            quotes: &['"', '\''],
+        },
+        "ruby" | "elixir" => LangSpec {
+            keywords: RUBY_KEYWORDS,
+            line_comments: hash,
+            block_comment: None,
+            quotes: &['"', '\''],
+        },
+        "shell" | "powershell" | "dockerfile" | "makefile" | "cmake" => LangSpec {
+            keywords: SHELL_KEYWORDS,
+            line_comments: hash,
+            block_comment: None,
+            quotes: &['"', '\''],
+        },
+        "sql" => LangSpec {
+            keywords: SQL_KEYWORDS,
+            line_comments: &["--"],
+            block_comment: Some(("/*", "*/")),
+            quotes: &['\'', '"'],
+        },
+        "lua" => LangSpec {
+            keywords: PYTHON_KEYWORDS,
+            line_comments: &["--"],
+            block_comment: Some(("--[[", "]]")),
+            quotes: &['"', '\''],
+        },
+        "yaml" | "toml" => LangSpec {
+            keywords: DATA_KEYWORDS,
+            line_comments: hash,
+            block_comment: None,
+            quotes: &['"', '\''],
+        },
+        "json" => LangSpec {
+            keywords: DATA_KEYWORDS,
+            line_comments: &[],
+            block_comment: None,
+            quotes: &['"'],
+        },
+        _ => return None,
+    })
+}
+
+fn push(tokens: &mut Vec<HighlightToken>, kind: TokenKind, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    match tokens.last_mut() {
+        Some(last) if last.kind == kind => last.text.push_str(text),
+        _ => tokens.push(HighlightToken {
+            kind,
+            text: text.to_string(),
+        }),
+    }
+}
+
+fn highlight_line(line: &str, spec: &LangSpec, in_block: &mut bool) -> Vec<HighlightToken> {
+    let mut tokens = Vec::new();
+    let mut rest = line;
+    let case_insensitive = std::ptr::eq(spec.keywords, SQL_KEYWORDS);
+    while !rest.is_empty() {
+        if *in_block {
+            let (_, close) = spec.block_comment.unwrap_or(("", ""));
+            match rest.find(close) {
+                Some(end) => {
+                    push(&mut tokens, TokenKind::Comment, &rest[..end + close.len()]);
+                    rest = &rest[end + close.len()..];
+                    *in_block = false;
+                }
+                None => {
+                    push(&mut tokens, TokenKind::Comment, rest);
+                    rest = "";
+                }
+            }
+            continue;
+        }
+        if let Some((open, _)) = spec.block_comment {
+            if rest.starts_with(open) {
+                push(&mut tokens, TokenKind::Comment, open);
+                rest = &rest[open.len()..];
+                *in_block = true;
+                continue;
+            }
+        }
+        if spec.line_comments.iter().any(|p| rest.starts_with(p)) {
+            push(&mut tokens, TokenKind::Comment, rest);
+            break;
+        }
+        let ch = rest.chars().next().unwrap_or(' ');
+        if spec.quotes.contains(&ch) {
+            let mut end = rest.len();
+            let mut escaped = false;
+            for (idx, c) in rest.char_indices().skip(1) {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == ch {
+                    end = idx + c.len_utf8();
+                    break;
+                }
+            }
+            push(&mut tokens, TokenKind::String, &rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        if ch.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_'))
+                .unwrap_or(rest.len());
+            push(&mut tokens, TokenKind::Number, &rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        if ch.is_alphabetic() || ch == '_' || ch == '$' || ch == '@' {
+            let end = rest
+                .char_indices()
+                .skip(1)
+                .find(|(_, c)| !(c.is_alphanumeric() || *c == '_' || *c == '$' || *c == '?'))
+                .map(|(idx, _)| idx)
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            let after = rest[end..].trim_start();
+            let is_keyword = if case_insensitive {
+                spec.keywords.iter().any(|k| k.eq_ignore_ascii_case(word))
+            } else {
+                spec.keywords.contains(&word)
+            };
+            let kind = if is_keyword {
+                TokenKind::Keyword
+            } else if after.starts_with('(') || after.starts_with('!') {
+                TokenKind::Function
+            } else if ch.is_uppercase() {
+                TokenKind::Type
+            } else {
+                TokenKind::Plain
+            };
+            push(&mut tokens, kind, word);
+            rest = &rest[end..];
+            continue;
+        }
+        let kind = if ch.is_whitespace() || ch.is_alphanumeric() {
+            TokenKind::Plain
+        } else {
+            TokenKind::Punctuation
+        };
+        push(&mut tokens, kind, &rest[..ch.len_utf8()]);
+        rest = &rest[ch.len_utf8()..];
+    }
+    tokens
+}
+
+/// Tokenize `source` line by line. Unknown languages yield one plain token
+/// per non-empty line.
+pub fn highlight_lines(source: &str, language: Option<&str>) -> Vec<Vec<HighlightToken>> {
+    let spec = language.and_then(spec_for);
+    let mut in_block = false;
+    source
+        .lines()
+        .map(|line| match spec.as_ref() {
+            Some(spec) => highlight_line(line, spec, &mut in_block),
+            None => {
+                let mut tokens = Vec::new();
+                push(&mut tokens, TokenKind::Plain, line);
+                tokens
+            }
+        })
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Render one highlighted line as HTML `<span class="tk-*">` runs.
+pub fn tokens_to_html(tokens: &[HighlightToken]) -> String {
+    tokens
+        .iter()
+        .map(|token| match token.kind.css_class() {
+            "" => escape_html(&token.text),
+            class => format!(
+                "<span class=\"{class}\">{}</span>",
+                escape_html(&token.text)
+            ),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(line: &[HighlightToken]) -> Vec<(TokenKind, &str)> {
+        line.iter().map(|t| (t.kind, t.text.as_str())).collect()
+    }
+
+    #[test]
+    fn highlights_rust_and_tracks_block_comments() {
+        let lines = highlight_lines(
+            "pub fn main() { let s = \"a\\\"b\"; } // done\n/* start\nend */ Vec::new(42)",
+            Some("rust"),
+        );
+        let first = kinds(&lines[0]);
+        assert_eq!(first[0], (TokenKind::Keyword, "pub"));
+        assert_eq!(first[2], (TokenKind::Keyword, "fn"));
+        assert_eq!(first[4], (TokenKind::Function, "main"));
+        assert!(first.contains(&(TokenKind::String, "\"a\\\"b\"")));
+        assert_eq!(first.last(), Some(&(TokenKind::Comment, "// done")));
+        assert_eq!(kinds(&lines[1]), vec![(TokenKind::Comment, "/* start")]);
+        let third = kinds(&lines[2]);
+        assert_eq!(third[0], (TokenKind::Comment, "end */"));
+        assert!(third.contains(&(TokenKind::Type, "Vec")));
+        assert!(third.contains(&(TokenKind::Number, "42")));
+    }
+
+    #[test]
+    fn renders_escaped_html() {
+        let lines = highlight_lines("x = '<b>' # note", Some("python"));
+        assert_eq!(
+            tokens_to_html(&lines[0]),
+            "x <span class=\"tk-punctuation\">=</span> <span class=\"tk-string\">&#39;&lt;b&gt;&#39;</span> <span class=\"tk-comment\"># note</span>"
+        );
+        assert_eq!(highlight_lines("plain <text>", None)[0].len(), 1);
+    }
+}
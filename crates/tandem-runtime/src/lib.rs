@@ -1,9 +1,24 @@
+pub mod file_locks;
+pub mod highlight;
 pub mod lsp;
 pub mod mcp;
 pub mod pty;
+pub mod workspace_ignore;
 pub mod workspace_index;
+pub mod workspace_snapshots;
 
+pub use file_locks::*;
+pub use highlight::*;
 pub use lsp::*;
 pub use mcp::*;
 pub use pty::*;
+pub use workspace_ignore::*;
 pub use workspace_index::*;
+pub use workspace_snapshots::*;
+
+pub(crate) fn now_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
@@ -4,6 +4,8 @@ use std::sync::Arc;
 use regex::Regex;
 use serde::Serialize;
 
+use crate::workspace_ignore::WorkspaceIgnore;
+
 #[derive(Clone)]
 pub struct LspManager {
     workspace_root: Arc<PathBuf>,
@@ -92,7 +94,8 @@ impl LspManager {
         let ts_fn =
             Regex::new(r"^\s*(export\s+)?(async\s+)?function\s+([A-Za-z_][A-Za-z0-9_]*)").ok();
 
-        for entry in ignore::WalkBuilder::new(self.workspace_root.as_path())
+        for entry in WorkspaceIgnore::for_root(&self.workspace_root)
+            .walker(std::slice::from_ref(self.workspace_root.as_ref()))
             .build()
             .flatten()
         {
@@ -175,7 +178,8 @@ impl LspManager {
             return Vec::new();
         };
         let mut refs = Vec::new();
-        for entry in ignore::WalkBuilder::new(self.workspace_root.as_path())
+        for entry in WorkspaceIgnore::for_root(&self.workspace_root)
+            .walker(std::slice::from_ref(self.workspace_root.as_ref()))
             .build()
             .flatten()
         {
@@ -1,7 +1,6 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
 
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE};
 use serde::{Deserialize, Serialize};
@@ -11,6 +10,8 @@ use tandem_types::ToolResult;
 use tokio::process::{Child, Command};
 use tokio::sync::{Mutex, RwLock};
 
+use crate::now_ms;
+
 const MCP_PROTOCOL_VERSION: &str = "2025-11-25";
 const MCP_CLIENT_NAME: &str = "tandem";
 const MCP_CLIENT_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -568,13 +569,6 @@ fn schema_hash(schema: &Value) -> String {
     format!("{:x}", hasher.finalize())
 }
 
-fn now_ms() -> u64 {
-    SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_millis() as u64)
-        .unwrap_or(0)
-}
-
 fn build_headers(headers: &HashMap<String, String>) -> Result<HeaderMap, String> {
     let mut map = HeaderMap::new();
     map.insert(
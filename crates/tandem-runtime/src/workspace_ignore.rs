@@ -0,0 +1,393 @@
+//! Exclusion rules shared by the search tools and the workspace index.
+//!
+//! Walkers already honor `.gitignore`, but a workspace without one still
+//! gets `node_modules/`, `target/` and multi-megabyte binaries walked and
+//! read. The `workspace_ignore` config block adds rules on top:
+//!
+//! ```json
+//! {
+//!   "builtin_dirs": true,
+//!   "exclude": ["*.min.js", "/fixtures/large/"],
+//!   "include": ["vendor/"],
+//!   "max_file_bytes": 1048576,
+//!   "skip_binary": true,
+//!   "workspaces": {
+//!     "/home/me/monorepo": { "exclude": ["generated/"], "max_file_bytes": 4194304 }
+//!   }
+//! }
+//! ```
+//!
+//! Patterns use gitignore syntax relative to the workspace root. `include`
+//! patterns are added as negations after everything else, so they win over
+//! `exclude` and the built-in directories; as in gitignore, a path inside an
+//! excluded directory cannot be re-included. Invalid patterns are skipped.
+//! A `.tandemignore` file in any directory is read like a `.gitignore`.
+//! Entries in `workspaces` are keyed by workspace root: their lists extend the
+//! global ones and their scalar fields replace them.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{DirEntry, WalkBuilder};
+use serde::{Deserialize, Serialize};
+
+/// Per-directory ignore file read alongside `.gitignore`.
+pub const IGNORE_FILE_NAME: &str = ".tandemignore";
+
+/// Directories skipped when `builtin_dirs` is on.
+pub const BUILTIN_EXCLUDED_DIRS: &[&str] = &[
+    "node_modules",
+    "target",
+    "dist",
+    "build",
+    "out",
+    "coverage",
+    "vendor",
+    "__pycache__",
+    ".venv",
+    "venv",
+    ".tox",
+    ".mypy_cache",
+    ".pytest_cache",
+    ".gradle",
+    ".next",
+    ".nuxt",
+    ".svelte-kit",
+    ".turbo",
+    ".cache",
+];
+
+/// Extensions treated as binary without looking at the content.
+const BINARY_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "bmp", "ico", "webp", "tif", "tiff", "psd", "pdf", "zip", "gz",
+    "tgz", "bz2", "xz", "zst", "7z", "rar", "tar", "jar", "war", "class", "exe", "dll", "so",
+    "dylib", "a", "lib", "o", "obj", "pyc", "wasm", "mp3", "mp4", "mov", "avi", "mkv", "webm",
+    "wav", "flac", "ogg", "woff", "woff2", "ttf", "otf", "eot", "sqlite", "db", "bin", "iso",
+    "dmg", "parquet",
+];
+
+/// Bytes read from files with other extensions to look for a NUL byte.
+const SNIFF_BYTES: u64 = 8 * 1024;
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_max_file_bytes() -> u64 {
+    1024 * 1024
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceIgnoreConfig {
+    /// Skip [`BUILTIN_EXCLUDED_DIRS`].
+    #[serde(default = "default_true")]
+    pub builtin_dirs: bool,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Files larger than this are skipped; `0` disables the cap.
+    #[serde(default = "default_max_file_bytes")]
+    pub max_file_bytes: u64,
+    #[serde(default = "default_true")]
+    pub skip_binary: bool,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub workspaces: HashMap<String, WorkspaceIgnoreOverride>,
+}
+
+impl Default for WorkspaceIgnoreConfig {
+    fn default() -> Self {
+        Self {
+            builtin_dirs: true,
+            exclude: Vec::new(),
+            include: Vec::new(),
+            max_file_bytes: default_max_file_bytes(),
+            skip_binary: true,
+            workspaces: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspaceIgnoreOverride {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub builtin_dirs: Option<bool>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_file_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_binary: Option<bool>,
+}
+
+impl WorkspaceIgnoreConfig {
+    fn override_for(&self, root: &Path) -> Option<&WorkspaceIgnoreOverride> {
+        self.workspaces
+            .iter()
+            .find(|(key, _)| Path::new(key.trim()) == root)
+            .map(|(_, rules)| rules)
+    }
+}
+
+fn config_slot() -> &'static RwLock<WorkspaceIgnoreConfig> {
+    static CONFIG: OnceLock<RwLock<WorkspaceIgnoreConfig>> = OnceLock::new();
+    CONFIG.get_or_init(Default::default)
+}
+
+/// Replace the process-wide rules; returns whether they changed.
+pub fn set_workspace_ignore_config(config: WorkspaceIgnoreConfig) -> bool {
+    let mut slot = config_slot().write().unwrap_or_else(|e| e.into_inner());
+    if *slot == config {
+        return false;
+    }
+    *slot = config;
+    true
+}
+
+pub fn workspace_ignore_config() -> WorkspaceIgnoreConfig {
+    config_slot()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    TooLarge,
+    Binary,
+}
+
+/// Rules resolved for one workspace root.
+pub struct WorkspaceIgnore {
+    root: PathBuf,
+    matcher: Gitignore,
+    max_file_bytes: Option<u64>,
+    skip_binary: bool,
+}
+
+impl WorkspaceIgnore {
+    /// Rules for the workspace at `root` under the current config.
+    pub fn for_root(root: &Path) -> Arc<Self> {
+        Arc::new(Self::from_config(root, &workspace_ignore_config()))
+    }
+
+    pub fn from_config(root: &Path, config: &WorkspaceIgnoreConfig) -> Self {
+        let local = config.override_for(root);
+        let local_list = |pick: fn(&WorkspaceIgnoreOverride) -> &Vec<String>| {
+            local.map(pick).into_iter().flatten()
+        };
+        let mut builder = GitignoreBuilder::new(root);
+        if local
+            .and_then(|rules| rules.builtin_dirs)
+            .unwrap_or(config.builtin_dirs)
+        {
+            for dir in BUILTIN_EXCLUDED_DIRS {
+                let _ = builder.add_line(None, &format!("{dir}/"));
+            }
+        }
+        for pattern in config.exclude.iter().chain(local_list(|r| &r.exclude)) {
+            let _ = builder.add_line(None, pattern.trim());
+        }
+        for pattern in config.include.iter().chain(local_list(|r| &r.include)) {
+            let pattern = pattern.trim().trim_start_matches('!');
+            if !pattern.is_empty() {
+                let _ = builder.add_line(None, &format!("!{pattern}"));
+            }
+        }
+        let max_file_bytes = local
+            .and_then(|rules| rules.max_file_bytes)
+            .unwrap_or(config.max_file_bytes);
+        Self {
+            root: root.to_path_buf(),
+            matcher: builder.build().unwrap_or_else(|_| Gitignore::empty()),
+            max_file_bytes: (max_file_bytes > 0).then_some(max_file_bytes),
+            skip_binary: local
+                .and_then(|rules| rules.skip_binary)
+                .unwrap_or(config.skip_binary),
+        }
+    }
+
+    /// Whether `path`, or a directory between it and the root, is excluded
+    /// by a pattern. `.git` is always excluded.
+    pub fn excludes(&self, path: &Path, is_dir: bool) -> bool {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        if relative.components().any(|c| c.as_os_str() == ".git") {
+            return true;
+        }
+        if relative.has_root() {
+            return self.matcher.matched(path, is_dir).is_ignore();
+        }
+        self.matcher
+            .matched_path_or_any_parents(relative, is_dir)
+            .is_ignore()
+    }
+
+    /// Why the file at `path`, `len` bytes long, is skipped, if it is.
+    pub fn skip_reason(&self, path: &Path, len: u64) -> Option<SkipReason> {
+        if self.max_file_bytes.is_some_and(|max| len > max) {
+            return Some(SkipReason::TooLarge);
+        }
+        if self.skip_binary && is_binary_file(path) {
+            return Some(SkipReason::Binary);
+        }
+        None
+    }
+
+    /// Walker over `roots` skipping excluded paths, oversized files and
+    /// binaries, on top of `.gitignore` and `.tandemignore`.
+    pub fn walker(self: &Arc<Self>, roots: &[PathBuf]) -> WalkBuilder {
+        self.build_walker(roots, true)
+    }
+
+    /// Like [`Self::walker`], but keeps every file that no pattern excludes;
+    /// for listings, where large and binary files still belong.
+    pub fn path_walker(self: &Arc<Self>, roots: &[PathBuf]) -> WalkBuilder {
+        self.build_walker(roots, false)
+    }
+
+    fn build_walker(self: &Arc<Self>, roots: &[PathBuf], check_files: bool) -> WalkBuilder {
+        let mut builder = WalkBuilder::new(&roots[0]);
+        for root in &roots[1..] {
+            builder.add(root);
+        }
+        builder.add_custom_ignore_filename(IGNORE_FILE_NAME);
+        let rules = self.clone();
+        builder.filter_entry(move |entry| rules.keeps(entry, check_files));
+        builder
+    }
+
+    fn keeps(&self, entry: &DirEntry, check_files: bool) -> bool {
+        // A root the caller asked for explicitly is walked regardless.
+        if entry.depth() == 0 {
+            return true;
+        }
+        let Some(file_type) = entry.file_type() else {
+            return true;
+        };
+        // Parents were checked on the way down, so only the entry itself is.
+        if self
+            .matcher
+            .matched(entry.path(), file_type.is_dir())
+            .is_ignore()
+            || entry.file_name() == ".git"
+        {
+            return false;
+        }
+        if !check_files || !file_type.is_file() {
+            return true;
+        }
+        let len = entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+        self.skip_reason(entry.path(), len).is_none()
+    }
+}
+
+/// Known binary extension, or a NUL byte near the start of the file.
+pub fn is_binary_file(path: &Path) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    if BINARY_EXTENSIONS.contains(&extension.as_str()) {
+        return true;
+    }
+    let Ok(file) = std::fs::File::open(path) else {
+        return false;
+    };
+    let mut head = Vec::new();
+    if file.take(SNIFF_BYTES).read_to_end(&mut head).is_err() {
+        return false;
+    }
+    head.contains(&0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn walked(rules: &Arc<WorkspaceIgnore>, root: &Path) -> Vec<String> {
+        let mut files = rules
+            .walker(&[root.to_path_buf()])
+            .build()
+            .flatten()
+            .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+            .map(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(root)
+                    .expect("under root")
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn walker_skips_build_dirs_large_files_and_binaries() {
+        let root = std::env::temp_dir().join(format!("tandem-ignore-{}", uuid::Uuid::new_v4()));
+        for dir in [
+            "src",
+            "node_modules/pkg",
+            "target/debug",
+            "vendor",
+            "generated",
+        ] {
+            std::fs::create_dir_all(root.join(dir)).expect("dirs");
+        }
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").expect("file");
+        std::fs::write(root.join("src/app.min.js"), "x").expect("file");
+        std::fs::write(root.join("src/blob.dat"), b"ab\0cd").expect("file");
+        std::fs::write(root.join("src/big.txt"), "x".repeat(64)).expect("file");
+        std::fs::write(root.join("node_modules/pkg/index.js"), "x").expect("file");
+        std::fs::write(root.join("target/debug/app"), "x").expect("file");
+        std::fs::write(root.join("vendor/lib.rs"), "x").expect("file");
+        std::fs::write(root.join("generated/api.rs"), "x").expect("file");
+        std::fs::write(root.join(IGNORE_FILE_NAME), "generated/\n").expect("file");
+
+        let mut config = WorkspaceIgnoreConfig {
+            exclude: vec!["*.min.js".to_string()],
+            include: vec!["vendor/".to_string()],
+            max_file_bytes: 32,
+            ..WorkspaceIgnoreConfig::default()
+        };
+        let rules = Arc::new(WorkspaceIgnore::from_config(&root, &config));
+        assert_eq!(walked(&rules, &root), vec!["src/main.rs", "vendor/lib.rs"]);
+        assert!(rules.excludes(&root.join("node_modules/pkg/index.js"), false));
+        assert!(!rules.excludes(&root.join("vendor/lib.rs"), false));
+        assert_eq!(
+            rules.skip_reason(&root.join("src/blob.dat"), 5),
+            Some(SkipReason::Binary)
+        );
+
+        config.workspaces.insert(
+            root.to_string_lossy().to_string(),
+            WorkspaceIgnoreOverride {
+                builtin_dirs: Some(false),
+                max_file_bytes: Some(0),
+                skip_binary: Some(false),
+                ..WorkspaceIgnoreOverride::default()
+            },
+        );
+        let rules = Arc::new(WorkspaceIgnore::from_config(&root, &config));
+        assert_eq!(
+            walked(&rules, &root),
+            vec![
+                "node_modules/pkg/index.js",
+                "src/big.txt",
+                "src/blob.dat",
+                "src/main.rs",
+                "target/debug/app",
+                "vendor/lib.rs",
+            ]
+        );
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -1,10 +1,11 @@
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
 
-use ignore::WalkBuilder;
 use serde::Serialize;
 use tokio::sync::RwLock;
 
+use crate::workspace_ignore::WorkspaceIgnore;
+
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct WorkspaceIndexSnapshot {
     pub root: String,
@@ -19,6 +20,156 @@ pub struct IndexedFile {
     pub bytes: u64,
 }
 
+/// Deepest expansion a single tree request may ask for.
+pub const MAX_TREE_DEPTH: usize = 6;
+const MAX_TREE_CHILDREN: usize = 1_000;
+
+/// One node of a gitignore-aware workspace tree. Directories beyond the
+/// requested depth have `children: None` and are expanded with another
+/// request for their `path`.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceTreeNode {
+    pub name: String,
+    /// Path relative to the workspace root, `/`-separated; empty for the root.
+    pub path: String,
+    #[serde(rename = "type")]
+    pub kind: WorkspaceTreeNodeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<WorkspaceTreeNode>>,
+    /// Set when a directory had more than the per-directory child limit.
+    #[serde(skip_serializing_if = "is_false")]
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceTreeNodeKind {
+    File,
+    Dir,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkspaceTreeError {
+    /// Absolute paths and `..` segments are rejected.
+    InvalidPath,
+    NotFound,
+    NotADirectory,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Language hint derived from the file name, in the identifiers editors use.
+pub fn language_hint(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+    match name {
+        "Dockerfile" => return Some("dockerfile"),
+        "Makefile" | "makefile" => return Some("makefile"),
+        "CMakeLists.txt" => return Some("cmake"),
+        _ => {}
+    }
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match ext.as_str() {
+        "rs" => "rust",
+        "ts" | "mts" | "cts" => "typescript",
+        "tsx" => "tsx",
+        "js" | "mjs" | "cjs" => "javascript",
+        "jsx" => "jsx",
+        "py" | "pyi" => "python",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "swift" => "swift",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "sh" | "bash" | "zsh" => "shell",
+        "ps1" => "powershell",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" | "sass" => "scss",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "xml" => "xml",
+        "md" | "markdown" => "markdown",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        "lua" => "lua",
+        "dart" => "dart",
+        "scala" => "scala",
+        "ex" | "exs" => "elixir",
+        "proto" => "protobuf",
+        _ => return None,
+    })
+}
+
+fn tree_node(
+    root: &Path,
+    rules: &Arc<WorkspaceIgnore>,
+    path: &Path,
+    is_dir: bool,
+    depth: usize,
+) -> WorkspaceTreeNode {
+    let rel = relativize(root, path).replace('\\', "/");
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| rel.clone());
+    if !is_dir {
+        return WorkspaceTreeNode {
+            name,
+            size: std::fs::metadata(path).ok().map(|m| m.len()),
+            language: language_hint(path),
+            path: rel,
+            kind: WorkspaceTreeNodeKind::File,
+            children: None,
+            truncated: false,
+        };
+    }
+    let mut node = WorkspaceTreeNode {
+        name,
+        path: rel,
+        kind: WorkspaceTreeNodeKind::Dir,
+        size: None,
+        language: None,
+        children: None,
+        truncated: false,
+    };
+    if depth == 0 {
+        return node;
+    }
+    let mut entries = rules
+        .path_walker(&[path.to_path_buf()])
+        .max_depth(Some(1))
+        .build()
+        .flatten()
+        .filter(|entry| entry.depth() == 1)
+        .map(|entry| {
+            let is_dir = entry.file_type().is_some_and(|t| t.is_dir());
+            (entry.into_path(), is_dir)
+        })
+        .collect::<Vec<_>>();
+    entries.sort_by(|(a, a_dir), (b, b_dir)| b_dir.cmp(a_dir).then_with(|| a.cmp(b)));
+    node.truncated = entries.len() > MAX_TREE_CHILDREN;
+    node.children = Some(
+        entries
+            .into_iter()
+            .take(MAX_TREE_CHILDREN)
+            .map(|(child, is_dir)| tree_node(root, rules, &child, is_dir, depth - 1))
+            .collect(),
+    );
+    node
+}
+
 #[derive(Clone)]
 pub struct WorkspaceIndex {
     root: Arc<PathBuf>,
@@ -48,7 +199,8 @@ impl WorkspaceIndex {
         let (mut files, count) = tokio::task::spawn_blocking(move || {
             let mut files = Vec::new();
             let mut count = 0usize;
-            for entry in WalkBuilder::new(root.as_path()).build().flatten() {
+            let rules = WorkspaceIgnore::for_root(root.as_path());
+            for entry in rules.walker(&[root.to_path_buf()]).build().flatten() {
                 if !entry.file_type().map(|f| f.is_file()).unwrap_or(false) {
                     continue;
                 }
@@ -80,6 +232,42 @@ impl WorkspaceIndex {
     pub async fn snapshot(&self) -> WorkspaceIndexSnapshot {
         self.snapshot.read().await.clone()
     }
+
+    /// Join a workspace-relative path onto the root, rejecting absolute
+    /// paths and `..` segments.
+    pub fn resolve(&self, relative: &str) -> Result<PathBuf, WorkspaceTreeError> {
+        let relative = Path::new(relative.trim().trim_start_matches("./"));
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(WorkspaceTreeError::InvalidPath);
+        }
+        Ok(self.root.join(relative))
+    }
+
+    /// Tree rooted at `relative` (empty for the workspace root), expanded
+    /// `depth` levels deep and honoring `.gitignore`, hidden-file and
+    /// `workspace_ignore` rules.
+    pub async fn tree(
+        &self,
+        relative: &str,
+        depth: usize,
+    ) -> Result<WorkspaceTreeNode, WorkspaceTreeError> {
+        let target = self.resolve(relative)?;
+        let root = self.root.clone();
+        let depth = depth.clamp(1, MAX_TREE_DEPTH);
+        tokio::task::spawn_blocking(move || {
+            let meta = std::fs::metadata(&target).map_err(|_| WorkspaceTreeError::NotFound)?;
+            if !meta.is_dir() {
+                return Err(WorkspaceTreeError::NotADirectory);
+            }
+            let rules = WorkspaceIgnore::for_root(root.as_path());
+            Ok(tree_node(root.as_path(), &rules, &target, true, depth))
+        })
+        .await
+        .unwrap_or(Err(WorkspaceTreeError::NotFound))
+    }
 }
 
 fn relativize(root: &std::path::Path, path: &std::path::Path) -> String {
@@ -87,3 +275,56 @@ fn relativize(root: &std::path::Path, path: &std::path::Path) -> String {
         .map(|v| v.to_string_lossy().to_string())
         .unwrap_or_else(|_| path.to_string_lossy().to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tree_respects_gitignore_and_expands_lazily() {
+        let root = std::env::temp_dir().join(format!("tandem-tree-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("src/nested")).expect("dirs");
+        std::fs::create_dir_all(root.join("target")).expect("dirs");
+        std::fs::create_dir(root.join(".git")).expect("git dir");
+        std::fs::write(root.join(".gitignore"), "target/\n").expect("ignore");
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").expect("file");
+        std::fs::write(root.join("src/nested/deep.py"), "x = 1").expect("file");
+        std::fs::write(root.join("README.md"), "# hi").expect("file");
+        std::fs::write(root.join("target/out.bin"), "x").expect("file");
+        let index = WorkspaceIndex {
+            root: Arc::new(root.clone()),
+            snapshot: Arc::new(RwLock::new(WorkspaceIndexSnapshot::default())),
+        };
+
+        let tree = index.tree("", 2).await.expect("tree");
+        let names = tree
+            .children
+            .as_ref()
+            .expect("children")
+            .iter()
+            .map(|n| n.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["src", "README.md"]);
+        let src = &tree.children.as_ref().expect("children")[0];
+        let src_children = src.children.as_ref().expect("expanded");
+        assert_eq!(src_children[0].path, "src/nested");
+        assert!(src_children[0].children.is_none());
+        assert_eq!(src_children[1].language, Some("rust"));
+        assert_eq!(src_children[1].size, Some(12));
+
+        let nested = index.tree("src/nested", 1).await.expect("subtree");
+        assert_eq!(
+            nested.children.expect("children")[0].path,
+            "src/nested/deep.py"
+        );
+        assert_eq!(
+            index.tree("../etc", 1).await.unwrap_err(),
+            WorkspaceTreeError::InvalidPath
+        );
+        assert_eq!(
+            index.tree("README.md", 1).await.unwrap_err(),
+            WorkspaceTreeError::NotADirectory
+        );
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,386 @@
+//! Periodic workspace file-hash snapshots and the diffs between them.
+//!
+//! A snapshot records a content hash for every non-ignored file under the
+//! workspace root. Comparing the snapshot taken at or before some moment with
+//! the current tree answers "what changed since yesterday" as a structured
+//! list of added, modified and deleted paths.
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tokio::sync::Mutex;
+
+use crate::now_ms;
+
+/// Files above this size are fingerprinted by length and mtime only.
+const MAX_HASHED_FILE_BYTES: u64 = 16 * 1024 * 1024;
+const DEFAULT_RETAIN: usize = 48;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileFingerprint {
+    pub hash: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceSnapshot {
+    pub snapshot_id: String,
+    pub root: String,
+    pub taken_at_ms: u64,
+    /// Workspace-relative, `/`-separated path to fingerprint.
+    pub files: BTreeMap<String, FileFingerprint>,
+}
+
+/// Snapshot metadata without the file table.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceSnapshotSummary {
+    pub snapshot_id: String,
+    pub root: String,
+    pub taken_at_ms: u64,
+    pub file_count: usize,
+}
+
+impl WorkspaceSnapshot {
+    pub fn summary(&self) -> WorkspaceSnapshotSummary {
+        WorkspaceSnapshotSummary {
+            snapshot_id: self.snapshot_id.clone(),
+            root: self.root.clone(),
+            taken_at_ms: self.taken_at_ms,
+            file_count: self.files.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceFileChange {
+    pub path: String,
+    pub change: FileChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_before: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes_after: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceChangeSet {
+    pub root: String,
+    /// When the baseline snapshot was taken.
+    pub since_ms: u64,
+    pub until_ms: u64,
+    pub baseline_snapshot_id: String,
+    pub added: usize,
+    pub modified: usize,
+    pub deleted: usize,
+    pub changes: Vec<WorkspaceFileChange>,
+}
+
+impl WorkspaceChangeSet {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+fn fingerprint(path: &Path, meta: &std::fs::Metadata) -> Option<FileFingerprint> {
+    let bytes = meta.len();
+    if bytes > MAX_HASHED_FILE_BYTES {
+        let mtime = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis())
+            .unwrap_or(0);
+        return Some(FileFingerprint {
+            hash: format!("size:{bytes}:mtime:{mtime}"),
+            bytes,
+        });
+    }
+    let mut file = std::fs::File::open(path).ok()?;
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buf).ok()?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Some(FileFingerprint {
+        hash: format!("{:x}", hasher.finalize()),
+        bytes,
+    })
+}
+
+/// Hash every non-ignored file under `root` (honours `.gitignore` and
+/// hidden-file rules, like the workspace index).
+pub async fn take_workspace_snapshot(root: &Path) -> WorkspaceSnapshot {
+    let walk_root = root.to_path_buf();
+    let files = tokio::task::spawn_blocking(move || {
+        let mut files = BTreeMap::new();
+        for entry in WalkBuilder::new(&walk_root).build().flatten() {
+            if !entry.file_type().is_some_and(|t| t.is_file()) {
+                continue;
+            }
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            let Some(print) = fingerprint(entry.path(), &meta) else {
+                continue;
+            };
+            let rel = entry
+                .path()
+                .strip_prefix(&walk_root)
+                .unwrap_or(entry.path())
+                .to_string_lossy()
+                .replace('\\', "/");
+            files.insert(rel, print);
+        }
+        files
+    })
+    .await
+    .unwrap_or_default();
+    let taken_at_ms = now_ms();
+    WorkspaceSnapshot {
+        snapshot_id: format!("ws-{taken_at_ms}"),
+        root: root.to_string_lossy().to_string(),
+        taken_at_ms,
+        files,
+    }
+}
+
+/// Changes from `before` to `after`, sorted by path.
+pub fn diff_workspace_snapshots(
+    before: &WorkspaceSnapshot,
+    after: &WorkspaceSnapshot,
+) -> WorkspaceChangeSet {
+    let mut changes = Vec::new();
+    for (path, old) in &before.files {
+        match after.files.get(path) {
+            None => changes.push(WorkspaceFileChange {
+                path: path.clone(),
+                change: FileChangeKind::Deleted,
+                bytes_before: Some(old.bytes),
+                bytes_after: None,
+            }),
+            Some(new) if new.hash != old.hash => changes.push(WorkspaceFileChange {
+                path: path.clone(),
+                change: FileChangeKind::Modified,
+                bytes_before: Some(old.bytes),
+                bytes_after: Some(new.bytes),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (path, new) in &after.files {
+        if !before.files.contains_key(path) {
+            changes.push(WorkspaceFileChange {
+                path: path.clone(),
+                change: FileChangeKind::Added,
+                bytes_before: None,
+                bytes_after: Some(new.bytes),
+            });
+        }
+    }
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    let count = |kind| changes.iter().filter(|c| c.change == kind).count();
+    WorkspaceChangeSet {
+        root: after.root.clone(),
+        since_ms: before.taken_at_ms,
+        until_ms: after.taken_at_ms,
+        baseline_snapshot_id: before.snapshot_id.clone(),
+        added: count(FileChangeKind::Added),
+        modified: count(FileChangeKind::Modified),
+        deleted: count(FileChangeKind::Deleted),
+        changes,
+    }
+}
+
+/// Snapshots persisted as `{dir}/{snapshot_id}.json`, newest `retain` kept.
+#[derive(Clone)]
+pub struct WorkspaceSnapshotStore {
+    dir: PathBuf,
+    retain: usize,
+    // Serializes capture + prune so concurrent callers do not race.
+    lock: Arc<Mutex<()>>,
+}
+
+impl WorkspaceSnapshotStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            retain: DEFAULT_RETAIN,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn with_retain(mut self, retain: usize) -> Self {
+        self.retain = retain.max(1);
+        self
+    }
+
+    pub fn set_dir(&mut self, dir: PathBuf) {
+        self.dir = dir;
+    }
+
+    async fn read_all(&self, root: &str) -> Vec<WorkspaceSnapshot> {
+        let mut out = Vec::new();
+        let Ok(mut entries) = fs::read_dir(&self.dir).await else {
+            return out;
+        };
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Ok(raw) = fs::read_to_string(&path).await else {
+                continue;
+            };
+            if let Ok(snapshot) = serde_json::from_str::<WorkspaceSnapshot>(&raw) {
+                if snapshot.root == root {
+                    out.push(snapshot);
+                }
+            }
+        }
+        out.sort_by_key(|s| s.taken_at_ms);
+        out
+    }
+
+    /// Snapshots of `root`, oldest first.
+    pub async fn list(&self, root: &str) -> Vec<WorkspaceSnapshotSummary> {
+        self.read_all(root)
+            .await
+            .iter()
+            .map(WorkspaceSnapshot::summary)
+            .collect()
+    }
+
+    /// Take and persist a snapshot of `root`, pruning old ones.
+    pub async fn capture(&self, root: &Path) -> anyhow::Result<WorkspaceSnapshotSummary> {
+        let _guard = self.lock.lock().await;
+        let snapshot = take_workspace_snapshot(root).await;
+        fs::create_dir_all(&self.dir).await?;
+        let path = self.dir.join(format!("{}.json", snapshot.snapshot_id));
+        fs::write(&path, serde_json::to_vec(&snapshot)?).await?;
+        let existing = self.read_all(&snapshot.root).await;
+        if existing.len() > self.retain {
+            for old in &existing[..existing.len() - self.retain] {
+                let _ = fs::remove_file(self.dir.join(format!("{}.json", old.snapshot_id))).await;
+            }
+        }
+        Ok(snapshot.summary())
+    }
+
+    /// Changes in `root` between the latest snapshot taken at or before
+    /// `since_ms` (the oldest one if none is that old) and the tree now.
+    /// `None` when there is no snapshot to compare against yet.
+    pub async fn changes_since(&self, root: &Path, since_ms: u64) -> Option<WorkspaceChangeSet> {
+        let root_str = root.to_string_lossy().to_string();
+        let snapshots = self.read_all(&root_str).await;
+        let baseline = snapshots
+            .iter()
+            .rev()
+            .find(|s| s.taken_at_ms <= since_ms)
+            .or_else(|| snapshots.first())?;
+        let current = take_workspace_snapshot(root).await;
+        Some(diff_workspace_snapshots(baseline, &current))
+    }
+}
+
+/// Parse a `since` value: epoch milliseconds, or a duration back from now
+/// such as `30m`, `6h`, `1d` or `2w`.
+pub fn parse_since_ms(raw: &str, now_ms: u64) -> Option<u64> {
+    let raw = raw.trim();
+    if let Ok(ms) = raw.parse::<u64>() {
+        return Some(ms);
+    }
+    let unit_at = raw.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = raw.split_at(unit_at);
+    let amount = amount.parse::<u64>().ok()?;
+    let unit_ms = match unit {
+        "m" | "min" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        "w" => 7 * 86_400_000,
+        _ => return None,
+    };
+    Some(now_ms.saturating_sub(amount.saturating_mul(unit_ms)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn changes_since_reports_added_modified_deleted() {
+        let base = std::env::temp_dir().join(format!("tandem-ws-snap-{}", uuid::Uuid::new_v4()));
+        let root = base.join("repo");
+        std::fs::create_dir_all(root.join("src")).expect("dirs");
+        std::fs::create_dir_all(root.join(".git")).expect("git dir");
+        std::fs::write(root.join(".gitignore"), "target/\n").expect("ignore");
+        std::fs::write(root.join("src/lib.rs"), "pub fn a() {}").expect("file");
+        std::fs::write(root.join("README.md"), "# hi").expect("file");
+        let store = WorkspaceSnapshotStore::new(base.join("snapshots")).with_retain(2);
+        let first = store.capture(&root).await.expect("capture");
+        assert_eq!(first.file_count, 2);
+
+        std::fs::write(root.join("src/lib.rs"), "pub fn b() {}").expect("edit");
+        std::fs::remove_file(root.join("README.md")).expect("delete");
+        std::fs::write(root.join("src/new.rs"), "").expect("add");
+        std::fs::create_dir_all(root.join("target")).expect("target");
+        std::fs::write(root.join("target/out"), "x").expect("ignored");
+
+        let changes = store
+            .changes_since(&root, first.taken_at_ms)
+            .await
+            .expect("baseline");
+        let summary = changes
+            .changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.change))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("README.md", FileChangeKind::Deleted),
+                ("src/lib.rs", FileChangeKind::Modified),
+                ("src/new.rs", FileChangeKind::Added),
+            ]
+        );
+        assert_eq!(changes.baseline_snapshot_id, first.snapshot_id);
+
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        store.capture(&root).await.expect("second");
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        store.capture(&root).await.expect("third");
+        assert_eq!(store.list(&root.to_string_lossy()).await.len(), 2);
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[test]
+    fn since_accepts_epoch_and_relative_durations() {
+        assert_eq!(parse_since_ms("1700000000000", 0), Some(1_700_000_000_000));
+        assert_eq!(
+            parse_since_ms("1d", 100_000_000),
+            Some(100_000_000 - 86_400_000)
+        );
+        assert_eq!(
+            parse_since_ms("30m", 10_000_000),
+            Some(10_000_000 - 1_800_000)
+        );
+        assert_eq!(parse_since_ms("yesterday", 0), None);
+    }
+}
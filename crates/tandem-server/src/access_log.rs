@@ -0,0 +1,315 @@
+//! HTTP access log.
+//!
+//! Every request that passes through the router is timed and recorded as a
+//! structured entry (route, caller token id, status, latency) in a bounded
+//! in-memory ring, queryable through `GET /audit/access`. Successful requests
+//! are sampled; 4xx/5xx responses are always kept since those are what is
+//! needed to diagnose a misbehaving client. Client addresses are truncated by
+//! default and the API token itself is never stored, only a short hash of it.
+
+use std::collections::VecDeque;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+
+const DEFAULT_ACCESS_LOG_CAPACITY: usize = 2_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IpLogMode {
+    /// Store the full client address.
+    Full,
+    /// Zero the host part: IPv4 keeps a /24, IPv6 keeps a /48.
+    Truncate,
+    /// Do not store the client address at all.
+    Omit,
+}
+
+impl IpLogMode {
+    fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "full" => Some(Self::Full),
+            "truncate" | "anonymize" => Some(Self::Truncate),
+            "omit" | "none" | "off" => Some(Self::Omit),
+            _ => None,
+        }
+    }
+
+    pub fn apply(self, ip: IpAddr) -> Option<String> {
+        match self {
+            Self::Full => Some(ip.to_string()),
+            Self::Truncate => Some(anonymize_ip(ip).to_string()),
+            Self::Omit => None,
+        }
+    }
+}
+
+pub fn anonymize_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            IpAddr::from([a, b, c, 0])
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return anonymize_ip(IpAddr::V4(v4));
+            }
+            let s = v6.segments();
+            IpAddr::from([s[0], s[1], s[2], 0, 0, 0, 0, 0])
+        }
+    }
+}
+
+/// Short, stable identifier for an API token. The token cannot be recovered
+/// from it.
+pub fn token_id(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    format!("tok-{}", crate::object_store::hex(&digest[..6]))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AccessLogConfig {
+    pub enabled: bool,
+    /// Fraction of successful requests recorded, `0.0..=1.0`.
+    pub sample_rate: f64,
+    pub ip_mode: IpLogMode,
+    pub capacity: usize,
+}
+
+impl Default for AccessLogConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sample_rate: 1.0,
+            ip_mode: IpLogMode::Truncate,
+            capacity: DEFAULT_ACCESS_LOG_CAPACITY,
+        }
+    }
+}
+
+impl AccessLogConfig {
+    /// Config from `TANDEM_ACCESS_LOG` (`0`/`false` disables),
+    /// `TANDEM_ACCESS_LOG_SAMPLE_RATE`, `TANDEM_ACCESS_LOG_IP`
+    /// (`full`/`truncate`/`omit`) and `TANDEM_ACCESS_LOG_CAPACITY`.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |key: &str| std::env::var(key).ok();
+        Self {
+            enabled: var("TANDEM_ACCESS_LOG")
+                .map(|v| {
+                    !matches!(
+                        v.trim().to_ascii_lowercase().as_str(),
+                        "0" | "false" | "off"
+                    )
+                })
+                .unwrap_or(defaults.enabled),
+            sample_rate: var("TANDEM_ACCESS_LOG_SAMPLE_RATE")
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|v| v.is_finite())
+                .map(|v| v.clamp(0.0, 1.0))
+                .unwrap_or(defaults.sample_rate),
+            ip_mode: var("TANDEM_ACCESS_LOG_IP")
+                .and_then(|v| IpLogMode::parse(&v))
+                .unwrap_or(defaults.ip_mode),
+            capacity: var("TANDEM_ACCESS_LOG_CAPACITY")
+                .and_then(|v| v.trim().parse::<usize>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(defaults.capacity),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccessLogEntry {
+    pub access_id: String,
+    pub method: String,
+    /// Matched route template (e.g. `/session/{id}`), or the raw path when no
+    /// route matched.
+    pub route: String,
+    pub status: u16,
+    pub latency_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_ip: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+    pub created_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AccessLogQuery {
+    pub limit: Option<usize>,
+    pub route: Option<String>,
+    pub method: Option<String>,
+    pub status: Option<u16>,
+    /// Only responses with status >= 400.
+    pub errors_only: Option<bool>,
+    pub token_id: Option<String>,
+    pub correlation_id: Option<String>,
+    pub since_ms: Option<u64>,
+}
+
+impl AccessLogQuery {
+    fn matches(&self, entry: &AccessLogEntry) -> bool {
+        self.route
+            .as_deref()
+            .is_none_or(|route| entry.route.starts_with(route))
+            && self
+                .method
+                .as_deref()
+                .is_none_or(|method| entry.method.eq_ignore_ascii_case(method))
+            && self.status.is_none_or(|status| entry.status == status)
+            && (!self.errors_only.unwrap_or(false) || entry.status >= 400)
+            && self
+                .token_id
+                .as_deref()
+                .is_none_or(|id| entry.token_id.as_deref() == Some(id))
+            && self
+                .correlation_id
+                .as_deref()
+                .is_none_or(|id| entry.correlation_id.as_deref() == Some(id))
+            && self
+                .since_ms
+                .is_none_or(|since| entry.created_at_ms >= since)
+    }
+}
+
+#[derive(Clone)]
+pub struct AccessLog {
+    config: AccessLogConfig,
+    entries: Arc<RwLock<VecDeque<AccessLogEntry>>>,
+    seen: Arc<AtomicU64>,
+}
+
+impl AccessLog {
+    pub fn new(config: AccessLogConfig) -> Self {
+        Self {
+            config,
+            entries: Arc::new(RwLock::new(VecDeque::new())),
+            seen: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn config(&self) -> AccessLogConfig {
+        self.config
+    }
+
+    /// Whether a request that finished with `status` should be recorded.
+    /// Successful requests are sampled evenly at `sample_rate`.
+    pub fn should_record(&self, status: u16) -> bool {
+        if !self.config.enabled {
+            return false;
+        }
+        if status >= 400 || self.config.sample_rate >= 1.0 {
+            return true;
+        }
+        if self.config.sample_rate <= 0.0 {
+            return false;
+        }
+        let n = self.seen.fetch_add(1, Ordering::Relaxed) as f64;
+        ((n + 1.0) * self.config.sample_rate).floor() > (n * self.config.sample_rate).floor()
+    }
+
+    pub async fn record(&self, entry: AccessLogEntry) {
+        let mut entries = self.entries.write().await;
+        while entries.len() >= self.config.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Matching entries, newest first.
+    pub async fn query(&self, query: &AccessLogQuery) -> Vec<AccessLogEntry> {
+        let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+        self.entries
+            .read()
+            .await
+            .iter()
+            .rev()
+            .filter(|entry| query.matches(entry))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(route: &str, status: u16, created_at_ms: u64) -> AccessLogEntry {
+        AccessLogEntry {
+            access_id: format!("acc-{created_at_ms}"),
+            method: "GET".to_string(),
+            route: route.to_string(),
+            status,
+            latency_ms: 1,
+            token_id: None,
+            user_id: None,
+            client_ip: None,
+            user_agent: None,
+            correlation_id: None,
+            created_at_ms,
+        }
+    }
+
+    #[test]
+    fn anonymizes_addresses_and_hashes_tokens() {
+        assert_eq!(
+            IpLogMode::Truncate.apply("203.0.113.77".parse().unwrap()),
+            Some("203.0.113.0".to_string())
+        );
+        assert_eq!(
+            IpLogMode::Truncate.apply("2001:db8:abcd:12::1".parse().unwrap()),
+            Some("2001:db8:abcd::".to_string())
+        );
+        assert_eq!(
+            IpLogMode::Truncate.apply("::ffff:10.1.2.3".parse().unwrap()),
+            Some("10.1.2.0".to_string())
+        );
+        assert_eq!(IpLogMode::Omit.apply("10.1.2.3".parse().unwrap()), None);
+        let id = token_id("secret-token");
+        assert!(id.starts_with("tok-") && id.len() == 16);
+        assert!(!id.contains("secret"));
+        assert_eq!(id, token_id("secret-token"));
+    }
+
+    #[tokio::test]
+    async fn samples_successes_keeps_errors_and_bounds_the_ring() {
+        let log = AccessLog::new(AccessLogConfig {
+            sample_rate: 0.25,
+            capacity: 3,
+            ..AccessLogConfig::default()
+        });
+        let kept = (0..8).filter(|_| log.should_record(200)).count();
+        assert_eq!(kept, 2);
+        assert!(log.should_record(500));
+
+        for (idx, route) in ["/a", "/b", "/c", "/d"].iter().enumerate() {
+            log.record(entry(route, if idx == 3 { 404 } else { 200 }, idx as u64))
+                .await;
+        }
+        let all = log.query(&AccessLogQuery::default()).await;
+        assert_eq!(
+            all.iter().map(|e| e.route.as_str()).collect::<Vec<_>>(),
+            vec!["/d", "/c", "/b"]
+        );
+        let errors = log
+            .query(&AccessLogQuery {
+                errors_only: Some(true),
+                ..AccessLogQuery::default()
+            })
+            .await;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].status, 404);
+    }
+}
@@ -0,0 +1,567 @@
+//! Agent pipelines: an ordered chain of agent profiles run as one routine.
+//!
+//! Pipelines sit between a single agent and a full agent team. They are
+//! declared in the `pipelines` config block and run by routines whose
+//! entrypoint is `pipeline.<name>`:
+//!
+//! ```json
+//! {
+//!   "pipelines": [{
+//!     "name": "research-report",
+//!     "stages": [
+//!       { "name": "research", "agent": "explore",
+//!         "instructions": "Collect sources on the objective.",
+//!         "handoff": { "format": "json", "required_fields": ["findings", "sources"] } },
+//!       { "name": "write", "agent": "general", "instructions": "Write the report." },
+//!       { "name": "review", "agent": "reviewer", "instructions": "Fix errors and tighten." }
+//!     ]
+//!   }]
+//! }
+//! ```
+//!
+//! Each stage runs with its agent profile in a session of its own and is
+//! given the routine's brief plus the previous stage's output. A stage's
+//! `handoff` says what that output must look like: free text, or a JSON
+//! object with the listed fields; a stage whose output does not match fails
+//! the run. The last stage runs in the run's own session, so its answer is
+//! the run's report. Every stage is recorded on the run as a
+//! [`PipelineStageRecord`].
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tandem_types::{EngineEvent, MessagePartInput, ModelSpec, SendMessageRequest, Session};
+
+use crate::{now_ms, AppState, RoutineRunRecord};
+
+pub const PIPELINE_ENTRYPOINT_PREFIX: &str = "pipeline.";
+/// Longest previous-stage output passed on to the next stage.
+const MAX_HANDOFF_CHARS: usize = 24_000;
+/// Longest output kept on a stage record.
+const MAX_OUTPUT_PREVIEW_CHARS: usize = 2_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HandoffFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PipelineHandoff {
+    #[serde(default)]
+    pub format: HandoffFormat,
+    /// Top-level fields a `json` handoff must contain.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub required_fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineStage {
+    pub name: String,
+    /// Agent profile the stage runs as, e.g. `explore` or a custom agent.
+    pub agent: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    #[serde(default)]
+    pub handoff: PipelineHandoff,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentPipeline {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub stages: Vec<PipelineStage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PipelineStageStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// One stage of a pipeline run, kept on the routine run record.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PipelineStageRecord {
+    pub index: usize,
+    pub name: String,
+    pub agent: String,
+    pub status: PipelineStageStatus,
+    pub session_id: String,
+    pub started_at_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_preview: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The pipeline a routine entrypoint names, if it is `pipeline.<name>`.
+pub fn pipeline_name(entrypoint: &str) -> Option<&str> {
+    entrypoint
+        .trim()
+        .strip_prefix(PIPELINE_ENTRYPOINT_PREFIX)
+        .filter(|name| !name.is_empty())
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+impl AgentPipeline {
+    pub fn validate(&self) -> Result<(), String> {
+        if !valid_name(&self.name) {
+            return Err(format!(
+                "pipeline name `{}` must be letters, digits, '-' or '_'",
+                self.name
+            ));
+        }
+        if self.stages.is_empty() {
+            return Err(format!("pipeline `{}` has no stages", self.name));
+        }
+        for (idx, stage) in self.stages.iter().enumerate() {
+            if stage.name.trim().is_empty() || stage.agent.trim().is_empty() {
+                return Err(format!(
+                    "pipeline `{}` stage {idx} needs a name and an agent",
+                    self.name
+                ));
+            }
+            if self.stages[..idx].iter().any(|s| s.name == stage.name) {
+                return Err(format!(
+                    "pipeline `{}` has two stages named `{}`",
+                    self.name, stage.name
+                ));
+            }
+            if stage.handoff.format == HandoffFormat::Text
+                && !stage.handoff.required_fields.is_empty()
+            {
+                return Err(format!(
+                    "pipeline `{}` stage `{}`: required_fields need the json handoff format",
+                    self.name, stage.name
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse the `pipelines` config block, keeping the valid pipelines and a
+/// message for each one that failed to parse or validate.
+pub fn parse_pipelines(raw: &Value) -> (Vec<AgentPipeline>, Vec<String>) {
+    let entries = match raw {
+        Value::Null => return (Vec::new(), Vec::new()),
+        Value::Array(entries) => entries,
+        _ => return (Vec::new(), vec!["`pipelines` must be a list".to_string()]),
+    };
+    let mut pipelines = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, entry) in entries.iter().enumerate() {
+        match serde_json::from_value::<AgentPipeline>(entry.clone())
+            .map_err(|err| format!("pipelines[{idx}]: {err}"))
+            .and_then(|pipeline| pipeline.validate().map(|()| pipeline))
+        {
+            Ok(pipeline)
+                if pipelines
+                    .iter()
+                    .any(|p: &AgentPipeline| p.name == pipeline.name) =>
+            {
+                errors.push(format!(
+                    "pipelines[{idx}]: duplicate pipeline name `{}`",
+                    pipeline.name
+                ));
+            }
+            Ok(pipeline) => pipelines.push(pipeline),
+            Err(err) => errors.push(err),
+        }
+    }
+    (pipelines, errors)
+}
+
+/// The JSON object in a stage's answer: the whole answer, its last fenced
+/// code block, or the outermost braces.
+fn extract_json_object(text: &str) -> Option<Value> {
+    let parse = |raw: &str| {
+        serde_json::from_str::<Value>(raw.trim())
+            .ok()
+            .filter(Value::is_object)
+    };
+    if let Some(value) = parse(text) {
+        return Some(value);
+    }
+    let fenced = text.split("```").skip(1).step_by(2).filter_map(|block| {
+        let body = block.split_once('\n').map_or(block, |(lang, body)| {
+            if lang.trim().chars().all(|c| c.is_ascii_alphanumeric()) {
+                body
+            } else {
+                block
+            }
+        });
+        parse(body)
+    });
+    if let Some(value) = fenced.last() {
+        return Some(value);
+    }
+    let start = text.find('{')?;
+    let end = text.rfind('}')?;
+    (start < end).then(|| parse(&text[start..=end])).flatten()
+}
+
+impl PipelineHandoff {
+    /// What to pass on to the next stage, or why the output does not match.
+    pub fn check(&self, output: &str) -> Result<String, String> {
+        match self.format {
+            HandoffFormat::Text if output.trim().is_empty() => {
+                Err("stage produced no output".to_string())
+            }
+            HandoffFormat::Text => Ok(output.trim().to_string()),
+            HandoffFormat::Json => {
+                let value = extract_json_object(output)
+                    .ok_or_else(|| "stage output has no JSON object".to_string())?;
+                let missing = self
+                    .required_fields
+                    .iter()
+                    .filter(|field| value.get(field.as_str()).is_none_or(Value::is_null))
+                    .map(String::as_str)
+                    .collect::<Vec<_>>();
+                if !missing.is_empty() {
+                    return Err(format!(
+                        "stage output is missing required fields: {}",
+                        missing.join(", ")
+                    ));
+                }
+                Ok(serde_json::to_string_pretty(&value).unwrap_or_default())
+            }
+        }
+    }
+
+    fn instructions(&self) -> Option<String> {
+        match self.format {
+            HandoffFormat::Text => None,
+            HandoffFormat::Json if self.required_fields.is_empty() => {
+                Some("End your answer with a single JSON object.".to_string())
+            }
+            HandoffFormat::Json => Some(format!(
+                "End your answer with a single JSON object with the fields: {}.",
+                self.required_fields.join(", ")
+            )),
+        }
+    }
+}
+
+/// Prompt for stage `index`, given the run's brief and the previous stage's
+/// handoff.
+fn stage_prompt(
+    brief: &str,
+    pipeline: &AgentPipeline,
+    index: usize,
+    previous: Option<&str>,
+) -> String {
+    let stage = &pipeline.stages[index];
+    let mut lines = vec![
+        brief.trim().to_string(),
+        String::new(),
+        format!(
+            "Pipeline {}: stage {} of {} ({}).",
+            pipeline.name,
+            index + 1,
+            pipeline.stages.len(),
+            stage.name
+        ),
+    ];
+    if let Some(instructions) = stage.instructions.as_deref().map(str::trim) {
+        lines.push(instructions.to_string());
+    }
+    if let (Some(previous), Some(from)) = (previous, index.checked_sub(1)) {
+        lines.push(String::new());
+        lines.push(format!(
+            "Output of the previous stage ({}):",
+            pipeline.stages[from].name
+        ));
+        lines.push(crate::clip(previous, MAX_HANDOFF_CHARS));
+    }
+    if let Some(handoff) = stage.handoff.instructions() {
+        lines.push(String::new());
+        lines.push(handoff);
+    }
+    lines.join("\n")
+}
+
+impl AppState {
+    async fn pipelines_config(&self) -> Value {
+        self.config_section("pipelines").await
+    }
+
+    /// Valid pipelines from config, with messages for the invalid ones.
+    pub async fn agent_pipelines(&self) -> (Vec<AgentPipeline>, Vec<String>) {
+        parse_pipelines(&self.pipelines_config().await)
+    }
+
+    pub async fn agent_pipeline(&self, name: &str) -> Option<AgentPipeline> {
+        self.agent_pipelines()
+            .await
+            .0
+            .into_iter()
+            .find(|pipeline| pipeline.name == name)
+    }
+
+    async fn record_pipeline_stage(&self, run_id: &str, record: &PipelineStageRecord) {
+        let mut guard = self.routine_runs.write().await;
+        let Some(row) = guard.get_mut(run_id) else {
+            return;
+        };
+        let previous = row.clone();
+        match row
+            .pipeline_stages
+            .iter_mut()
+            .find(|stage| stage.index == record.index)
+        {
+            Some(stage) => *stage = record.clone(),
+            None => row.pipeline_stages.push(record.clone()),
+        }
+        row.updated_at_ms = now_ms();
+        drop(guard);
+        if let Err(error) = self.persist_run_change(previous).await {
+            tracing::warn!("routine run {run_id}: failed to record pipeline stage: {error}");
+        }
+    }
+
+    /// A fresh session for a stage before the last, tagged like the run's.
+    async fn pipeline_stage_session(
+        &self,
+        run: &RoutineRunRecord,
+        stage: &PipelineStage,
+        workspace_root: &str,
+    ) -> anyhow::Result<String> {
+        let mut session = Session::new(
+            Some(format!("Routine {} · {}", run.routine_id, stage.name)),
+            Some(workspace_root.to_string()),
+        );
+        session.workspace_root = Some(workspace_root.to_string());
+        let routine = self.get_routine(&run.routine_id).await;
+        session.tags = crate::routine_session_tags(&run.routine_id, routine.as_ref());
+        session.project_id = routine.and_then(|routine| routine.tenant_id);
+        let session_id = session.id.clone();
+        self.storage.save_session(session).await?;
+        self.set_routine_session_policy(
+            session_id.clone(),
+            run.run_id.clone(),
+            run.routine_id.clone(),
+            run.allowed_tools.clone(),
+        )
+        .await;
+        self.engine_loop
+            .set_session_allowed_tools(&session_id, run.allowed_tools.clone())
+            .await;
+        self.engine_loop
+            .set_session_priority(&session_id, run.priority)
+            .await;
+        self.engine_loop
+            .set_session_run(&session_id, &run.run_id)
+            .await;
+        Ok(session_id)
+    }
+
+    async fn release_pipeline_stage_session(&self, session_id: &str) {
+        self.clear_routine_session_policy(session_id).await;
+        self.engine_loop
+            .clear_session_allowed_tools(session_id)
+            .await;
+        self.engine_loop.clear_session_priority(session_id).await;
+        self.engine_loop.clear_session_run(session_id).await;
+    }
+
+    /// Run the stages of pipeline `name` for `run`, the last one in
+    /// `run_session_id`. Fails at the first stage that errors or whose
+    /// output does not match its handoff.
+    pub(crate) async fn run_agent_pipeline(
+        &self,
+        run: &RoutineRunRecord,
+        name: &str,
+        run_session_id: &str,
+        workspace_root: &str,
+        brief: &str,
+        model: Option<ModelSpec>,
+    ) -> anyhow::Result<()> {
+        let pipeline = self
+            .agent_pipeline(name)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("pipeline `{name}` is not defined"))?;
+        let agents = self.agents.list().await;
+        if let Some(stage) = pipeline
+            .stages
+            .iter()
+            .find(|stage| !agents.iter().any(|agent| agent.name == stage.agent))
+        {
+            anyhow::bail!(
+                "pipeline `{name}` stage `{}` uses unknown agent `{}`",
+                stage.name,
+                stage.agent
+            );
+        }
+
+        let last = pipeline.stages.len() - 1;
+        let mut handoff: Option<String> = None;
+        for (index, stage) in pipeline.stages.iter().enumerate() {
+            let session_id = if index == last {
+                run_session_id.to_string()
+            } else {
+                self.pipeline_stage_session(run, stage, workspace_root)
+                    .await?
+            };
+            let mut record = PipelineStageRecord {
+                index,
+                name: stage.name.clone(),
+                agent: stage.agent.clone(),
+                status: PipelineStageStatus::Running,
+                session_id: session_id.clone(),
+                started_at_ms: now_ms(),
+                finished_at_ms: None,
+                output_preview: None,
+                error: None,
+            };
+            self.record_pipeline_stage(&run.run_id, &record).await;
+            self.event_bus.publish(EngineEvent::new(
+                "routine.run.stage_started",
+                json!({
+                    "runID": run.run_id,
+                    "routineID": run.routine_id,
+                    "pipeline": name,
+                    "stage": stage.name,
+                    "index": index,
+                    "agent": stage.agent,
+                    "sessionID": session_id,
+                }),
+            ));
+
+            let request = SendMessageRequest {
+                parts: vec![MessagePartInput::Text {
+                    text: stage_prompt(brief, &pipeline, index, handoff.as_deref()),
+                }],
+                model: model.clone(),
+                agent: Some(stage.agent.clone()),
+                system: None,
+                preset: None,
+            };
+            let result = self
+                .engine_loop
+                .run_prompt_async_with_context(
+                    session_id.clone(),
+                    request,
+                    Some(format!("routine:{}", run.run_id)),
+                )
+                .await;
+            if index != last {
+                self.release_pipeline_stage_session(&session_id).await;
+            }
+            let output = match result {
+                Ok(()) => self
+                    .storage
+                    .get_session(&session_id)
+                    .await
+                    .and_then(|session| {
+                        crate::routine_cache::last_assistant_text(&session.messages)
+                    })
+                    .unwrap_or_default(),
+                Err(error) => {
+                    record.error = Some(error.to_string());
+                    String::new()
+                }
+            };
+            let checked = match record.error.clone() {
+                Some(error) => Err(error),
+                None => stage.handoff.check(&output),
+            };
+
+            record.finished_at_ms = Some(now_ms());
+            record.output_preview =
+                (!output.is_empty()).then(|| crate::clip(&output, MAX_OUTPUT_PREVIEW_CHARS));
+            match checked {
+                Ok(payload) => {
+                    record.status = PipelineStageStatus::Completed;
+                    self.record_pipeline_stage(&run.run_id, &record).await;
+                    self.event_bus.publish(EngineEvent::new(
+                        "routine.run.stage_completed",
+                        json!({
+                            "runID": run.run_id,
+                            "routineID": run.routine_id,
+                            "pipeline": name,
+                            "stage": stage.name,
+                            "index": index,
+                        }),
+                    ));
+                    handoff = Some(payload);
+                }
+                Err(error) => {
+                    record.status = PipelineStageStatus::Failed;
+                    record.error = Some(error.clone());
+                    self.record_pipeline_stage(&run.run_id, &record).await;
+                    self.event_bus.publish(EngineEvent::new(
+                        "routine.run.stage_failed",
+                        json!({
+                            "runID": run.run_id,
+                            "routineID": run.routine_id,
+                            "pipeline": name,
+                            "stage": stage.name,
+                            "index": index,
+                            "error": error,
+                        }),
+                    ));
+                    anyhow::bail!("pipeline stage `{}` failed: {error}", stage.name);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pipelines_parse_and_json_handoffs_are_checked() {
+        let (pipelines, errors) = parse_pipelines(&json!([
+            {
+                "name": "research-report",
+                "stages": [
+                    { "name": "research", "agent": "explore",
+                      "handoff": { "format": "json", "required_fields": ["findings"] } },
+                    { "name": "write", "agent": "general" }
+                ]
+            },
+            { "name": "empty", "stages": [] },
+            { "name": "research-report", "stages": [{ "name": "a", "agent": "b" }] },
+            { "name": "bad", "stages": [
+                { "name": "a", "agent": "b", "handoff": { "required_fields": ["x"] } }
+            ] }
+        ]));
+        assert_eq!(pipelines.len(), 1);
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        assert_eq!(
+            pipeline_name("pipeline.research-report"),
+            Some("research-report")
+        );
+        assert_eq!(pipeline_name("mission.default"), None);
+
+        let handoff = &pipelines[0].stages[0].handoff;
+        let fenced = "Found two.\n```json\n{\"findings\": [\"a\", \"b\"]}\n```";
+        assert!(handoff.check(fenced).expect("handoff").contains("findings"));
+        assert!(handoff
+            .check("Result: {\"findings\": 1, \"extra\": true} done")
+            .is_ok());
+        assert!(handoff.check("{\"notes\": 1}").is_err());
+        assert!(handoff.check("no json here").is_err());
+        assert!(PipelineHandoff::default().check("  ").is_err());
+
+        let prompt = stage_prompt("Brief", &pipelines[0], 1, Some("{\"findings\": 1}"));
+        assert!(prompt.contains("stage 2 of 2 (write)"));
+        assert!(prompt.contains("Output of the previous stage (research):"));
+    }
+}
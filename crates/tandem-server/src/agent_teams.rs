@@ -13,8 +13,9 @@ use tandem_core::{
     ToolPolicyDecision, ToolPolicyHook,
 };
 use tandem_orchestrator::{
-    AgentInstance, AgentInstanceStatus, AgentRole, AgentTemplate, BudgetLimit, SpawnDecision,
-    SpawnDenyCode, SpawnPolicy, SpawnRequest, SpawnSource,
+    AgentInstance, AgentInstanceStatus, AgentRole, AgentTemplate, BudgetLimit, ComplianceReport,
+    HandoffKind, MissionHandoff, ObserverEntry, ObserverLog, SpawnDecision, SpawnDenyCode,
+    SpawnPolicy, SpawnRequest, SpawnSource,
 };
 use tandem_skills::SkillService;
 use tandem_types::{EngineEvent, Session};
@@ -34,8 +35,15 @@ pub struct AgentTeamRuntime {
     spawn_approvals: Arc<RwLock<HashMap<String, PendingSpawnApproval>>>,
     loaded_workspace: Arc<RwLock<Option<String>>>,
     audit_path: Arc<RwLock<PathBuf>>,
+    /// Observer commentary per mission, only kept for missions with an observer.
+    observer_logs: Arc<RwLock<HashMap<String, ObserverLog>>>,
 }
 
+/// Longest tool result excerpt kept in observer commentary.
+const OBSERVER_SUMMARY_MAX_CHARS: usize = 200;
+/// Commentary entries kept per mission; older unflagged ones are evicted.
+const OBSERVER_LOG_MAX_ENTRIES: usize = 1_000;
+
 #[derive(Debug, Clone)]
 pub struct SpawnResult {
     pub decision: SpawnDecision,
@@ -255,10 +263,7 @@ impl ToolPolicyHook for ServerToolPolicyHook {
                 .instance_for_session(&ctx.session_id)
                 .await
             else {
-                return Ok(ToolPolicyDecision {
-                    allowed: true,
-                    reason: None,
-                });
+                return acquire_tool_file_locks(&state, &ctx, &tool).await;
             };
             let caps = instance.capabilities.clone();
             let deny = evaluate_capability_deny(
@@ -290,14 +295,133 @@ impl ToolPolicyHook for ServerToolPolicyHook {
                     reason: Some(reason),
                 });
             }
+            acquire_tool_file_locks(&state, &ctx, &tool).await
+        })
+    }
+}
+
+/// Take advisory locks on the files a mutating tool is about to touch. Runs
+/// after every other policy check so a denied call never holds a lock.
+async fn acquire_tool_file_locks(
+    state: &AppState,
+    ctx: &ToolPolicyContext,
+    tool: &str,
+) -> anyhow::Result<ToolPolicyDecision> {
+    let allowed = ToolPolicyDecision {
+        allowed: true,
+        reason: None,
+    };
+    let raw_paths = extract_tool_lock_paths(tool, &ctx.args);
+    if raw_paths.is_empty() {
+        return Ok(allowed);
+    }
+    // Same base the engine resolves relative tool paths against.
+    let base = state
+        .storage
+        .get_session(&ctx.session_id)
+        .await
+        .and_then(|session| {
+            let directory = session.directory.trim();
+            if directory.is_empty() || directory == "." {
+                session.workspace_root.clone()
+            } else {
+                Some(directory.to_string())
+            }
+        });
+    let paths = raw_paths
+        .iter()
+        .filter_map(|raw| resolve_lock_path(base.as_deref(), raw))
+        .collect::<Vec<_>>();
+    if paths.is_empty() {
+        return Ok(allowed);
+    }
+    let run_id = match state.run_registry.get(&ctx.session_id).await {
+        Some(run) => run.run_id,
+        None => state
+            .engine_loop
+            .session_run(&ctx.session_id)
+            .await
+            .unwrap_or_else(|| format!("session:{}", ctx.session_id)),
+    };
+    match state
+        .file_locks
+        .acquire(&run_id, &ctx.session_id, tool, &paths)
+        .await
+    {
+        Ok(()) => Ok(allowed),
+        Err(conflict) => {
+            state.event_bus.publish(EngineEvent::new(
+                "file.lock.conflict",
+                json!({
+                    "sessionID": ctx.session_id,
+                    "messageID": ctx.message_id,
+                    "runID": run_id,
+                    "tool": tool,
+                    "path": conflict.path,
+                    "heldBy": conflict.held_by,
+                    "timestampMs": crate::now_ms(),
+                }),
+            ));
             Ok(ToolPolicyDecision {
-                allowed: true,
-                reason: None,
+                allowed: false,
+                reason: Some(conflict.render()),
             })
-        })
+        }
+    }
+}
+
+fn extract_tool_lock_paths(tool: &str, args: &Value) -> Vec<String> {
+    match tool {
+        "write" | "edit" => ["path", "filePath"]
+            .iter()
+            .filter_map(|key| args.get(*key).and_then(|v| v.as_str()))
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .take(1)
+            .map(ToString::to_string)
+            .collect(),
+        "apply_patch" => {
+            let patch = args
+                .get("patchText")
+                .or_else(|| args.get("patch"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            let mut out = Vec::new();
+            for line in patch.lines() {
+                let path = [
+                    "*** Add File:",
+                    "*** Update File:",
+                    "*** Delete File:",
+                    "*** Move to:",
+                ]
+                .iter()
+                .find_map(|prefix| line.strip_prefix(prefix))
+                .or_else(|| line.strip_prefix("+++ b/"))
+                .map(str::trim)
+                .filter(|p| !p.is_empty() && *p != "/dev/null");
+                if let Some(path) = path {
+                    if !out.iter().any(|existing| existing == path) {
+                        out.push(path.to_string());
+                    }
+                }
+            }
+            out
+        }
+        _ => Vec::new(),
     }
 }
 
+fn resolve_lock_path(base: Option<&str>, raw: &str) -> Option<PathBuf> {
+    let candidate = PathBuf::from(raw);
+    let absolute = if candidate.is_absolute() {
+        candidate
+    } else {
+        let base = tandem_core::normalize_workspace_path(base?)?;
+        PathBuf::from(base).join(candidate)
+    };
+    Some(tandem_runtime::normalize_lock_path(&absolute))
+}
+
 impl AgentTeamRuntime {
     pub fn new(audit_path: PathBuf) -> Self {
         Self {
@@ -309,6 +433,7 @@ impl AgentTeamRuntime {
             spawn_approvals: Arc::new(RwLock::new(HashMap::new())),
             loaded_workspace: Arc::new(RwLock::new(None)),
             audit_path: Arc::new(RwLock::new(audit_path)),
+            observer_logs: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -927,6 +1052,7 @@ impl AgentTeamRuntime {
         let Some(instance_id) = self.instance_id_for_session(&session_id).await else {
             return;
         };
+        self.observe_event(state, &instance_id, event).await;
         if event.event_type == "provider.usage" {
             let total_tokens = event
                 .properties
@@ -1006,6 +1132,107 @@ impl AgentTeamRuntime {
         }
     }
 
+    /// Observer commentary recorded so far for a mission.
+    pub async fn observer_log(&self, mission_id: &str) -> Vec<ObserverEntry> {
+        self.observer_logs
+            .read()
+            .await
+            .get(mission_id)
+            .map(ObserverLog::entries)
+            .unwrap_or_default()
+    }
+
+    async fn mission_observers(&self, mission_id: &str) -> Vec<String> {
+        let mut ids = self
+            .instances
+            .read()
+            .await
+            .values()
+            .filter(|i| i.mission_id == mission_id && i.role == AgentRole::Observer)
+            .map(|i| i.instance_id.clone())
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    /// Feed a team event to the mission's observers as audit commentary, and
+    /// attach the compliance report once every observed agent has finished.
+    async fn observe_event(&self, state: &AppState, instance_id: &str, event: &EngineEvent) {
+        let Some(instance) = self.instances.read().await.get(instance_id).cloned() else {
+            return;
+        };
+        if instance.role == AgentRole::Observer {
+            return;
+        }
+        let observers = self.mission_observers(&instance.mission_id).await;
+        if observers.is_empty() {
+            return;
+        }
+        let Some((kind, summary, flagged)) = observer_commentary(event) else {
+            return;
+        };
+        let entry = ObserverEntry {
+            timestamp_ms: crate::now_ms(),
+            instance_id: instance.instance_id.clone(),
+            role: instance.role.clone(),
+            kind: kind.to_string(),
+            summary,
+            flagged,
+        };
+        self.observer_logs
+            .write()
+            .await
+            .entry(instance.mission_id.clone())
+            .or_insert_with(|| ObserverLog::new(OBSERVER_LOG_MAX_ENTRIES))
+            .push(entry.clone());
+        emit_observer_commentary(state, &instance.mission_id, &observers, &entry);
+
+        if event.event_type == "session.run.finished" {
+            let still_active = self.instances.read().await.values().any(|i| {
+                i.mission_id == instance.mission_id
+                    && i.role != AgentRole::Observer
+                    && i.instance_id != instance.instance_id
+                    && matches!(
+                        i.status,
+                        AgentInstanceStatus::Queued | AgentInstanceStatus::Running
+                    )
+            });
+            if !still_active {
+                let _ = self
+                    .attach_compliance_report(state, &instance.mission_id)
+                    .await;
+            }
+        }
+    }
+
+    /// Build the observers' compliance report for a mission and attach it to
+    /// the mission state. Returns `None` when the mission has no observer.
+    pub async fn attach_compliance_report(
+        &self,
+        state: &AppState,
+        mission_id: &str,
+    ) -> Option<ComplianceReport> {
+        let observers = self.mission_observers(mission_id).await;
+        if observers.is_empty() {
+            return None;
+        }
+        let log = self
+            .observer_logs
+            .read()
+            .await
+            .get(mission_id)
+            .cloned()
+            .unwrap_or_default();
+        let report = ComplianceReport::from_log(mission_id, observers, &log, crate::now_ms());
+        if let Some(mission) = state.missions.write().await.get_mut(mission_id) {
+            mission.compliance_report = Some(report.clone());
+            mission.revision = mission.revision.saturating_add(1);
+        }
+        let _ = self.append_compliance_audit(&report).await;
+        emit_compliance_report(state, &report);
+        Some(report)
+    }
+
     async fn instance_id_for_session(&self, session_id: &str) -> Option<String> {
         self.instances
             .read()
@@ -1328,6 +1555,68 @@ impl AgentTeamRuntime {
         Ok(())
     }
 
+    /// Record an accepted mission handoff or takeover in the team audit log.
+    pub async fn append_handoff_audit(
+        &self,
+        mission_id: &str,
+        handoff: &MissionHandoff,
+    ) -> anyhow::Result<()> {
+        let path = self.audit_path.read().await.clone();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let row = json!({
+            "action": match handoff.kind {
+                HandoffKind::Handoff => "mission.handoff",
+                HandoffKind::Takeover => "mission.takeover",
+            },
+            "missionID": mission_id,
+            "handoffID": handoff.handoff_id,
+            "fromRole": handoff.from_role,
+            "toRole": handoff.to_role,
+            "workItemIDs": handoff.work_item_ids,
+            "document": handoff.document,
+            "reason": handoff.reason,
+            "timestampMs": crate::now_ms(),
+        });
+        let mut existing = if path.exists() {
+            fs::read_to_string(&path).await.unwrap_or_default()
+        } else {
+            String::new()
+        };
+        existing.push_str(&serde_json::to_string(&row)?);
+        existing.push('\n');
+        fs::write(path, existing).await?;
+        Ok(())
+    }
+
+    async fn append_compliance_audit(&self, report: &ComplianceReport) -> anyhow::Result<()> {
+        let path = self.audit_path.read().await.clone();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let row = json!({
+            "action": "mission.compliance_report",
+            "missionID": report.mission_id,
+            "observerInstanceIDs": report.observer_instance_ids,
+            "verdict": report.verdict,
+            "toolCalls": report.tool_calls,
+            "toolFailures": report.tool_failures,
+            "denials": report.denials,
+            "flaggedCount": report.flagged.len(),
+            "timestampMs": crate::now_ms(),
+        });
+        let mut existing = if path.exists() {
+            fs::read_to_string(&path).await.unwrap_or_default()
+        } else {
+            String::new()
+        };
+        existing.push_str(&serde_json::to_string(&row)?);
+        existing.push('\n');
+        fs::write(path, existing).await?;
+        Ok(())
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn apply_mission_budget_delta(
         &self,
@@ -1410,6 +1699,7 @@ impl AgentTeamRuntime {
         self.budgets.write().await.clear();
         self.mission_budgets.write().await.clear();
         self.spawn_approvals.write().await.clear();
+        self.observer_logs.write().await.clear();
         *self.loaded_workspace.write().await = workspace_root;
     }
 }
@@ -1556,7 +1846,7 @@ async fn compute_skill_hash(
             let raw = fs::read_to_string(&skill_path)
                 .await
                 .map_err(|_| format!("missing required skill path `{}`", skill_path.display()))?;
-            let digest = hash_hex(raw.as_bytes());
+            let digest = crate::object_store::hex(raw.as_bytes());
             validate_pinned_hash(skill.id.as_deref(), Some(path), &digest, policy)?;
             rows.push(format!("path:{}:{}", path, digest));
         } else if let Some(id) = skill.id.as_deref() {
@@ -1567,7 +1857,7 @@ async fn compute_skill_hash(
             let Some(loaded) = loaded else {
                 return Err(format!("missing required skill id `{id}`"));
             };
-            let digest = hash_hex(loaded.content.as_bytes());
+            let digest = crate::object_store::hex(loaded.content.as_bytes());
             validate_pinned_hash(Some(id), None, &digest, policy)?;
             rows.push(format!("id:{}:{}", id, digest));
         }
@@ -1579,7 +1869,10 @@ async fn compute_skill_hash(
         hasher.update(b"\n");
     }
     let digest = hasher.finalize();
-    Ok(format!("sha256:{}", hash_hex(digest.as_slice())))
+    Ok(format!(
+        "sha256:{}",
+        crate::object_store::hex(digest.as_slice())
+    ))
 }
 
 fn validate_skill_source(
@@ -1647,15 +1940,6 @@ fn validate_pinned_hash(
     Ok(())
 }
 
-fn hash_hex(bytes: &[u8]) -> String {
-    let mut out = String::with_capacity(bytes.len() * 2);
-    for byte in bytes {
-        use std::fmt::Write as _;
-        let _ = write!(&mut out, "{:02x}", byte);
-    }
-    out
-}
-
 fn estimate_tokens(text: &str) -> u64 {
     let chars = text.chars().count() as u64;
     (chars / 4).max(1)
@@ -1701,6 +1985,76 @@ fn merge_metadata_usage(
     Value::Object(base)
 }
 
+/// `(kind, summary, flagged)` for team events an observer comments on.
+fn observer_commentary(event: &EngineEvent) -> Option<(&'static str, String, bool)> {
+    match event.event_type.as_str() {
+        "message.part.updated" => {
+            let part = event.properties.get("part")?;
+            if part.get("type").and_then(|v| v.as_str()) != Some("tool") {
+                return None;
+            }
+            let tool = part.get("tool").and_then(|v| v.as_str()).unwrap_or("?");
+            let error = part.get("error").and_then(|v| v.as_str()).unwrap_or("");
+            match part.get("state").and_then(|v| v.as_str()).unwrap_or("") {
+                "pending" | "running" => {
+                    let args = part.get("args").map(Value::to_string).unwrap_or_default();
+                    Some((
+                        "tool_call",
+                        format!("called `{tool}` {}", excerpt(&args)),
+                        false,
+                    ))
+                }
+                "completed" => {
+                    let result = part.get("result").map(Value::to_string).unwrap_or_default();
+                    Some((
+                        "tool_result",
+                        format!("`{tool}` returned {}", excerpt(&result)),
+                        false,
+                    ))
+                }
+                "denied" => Some(("tool_denied", format!("`{tool}` denied: {error}"), true)),
+                "failed" => Some(("tool_failed", format!("`{tool}` failed: {error}"), true)),
+                _ => None,
+            }
+        }
+        "agent_team.capability.denied" => {
+            let tool = event.properties.get("tool").and_then(|v| v.as_str())?;
+            let reason = event
+                .properties
+                .get("reason")
+                .and_then(|v| v.as_str())
+                .unwrap_or("");
+            Some((
+                "capability_denied",
+                format!("blocked from `{tool}`: {reason}"),
+                true,
+            ))
+        }
+        "session.run.finished" => {
+            let status = event
+                .properties
+                .get("status")
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown");
+            let failed = matches!(status, "failed" | "error");
+            Some(("instance_status", format!("run finished: {status}"), failed))
+        }
+        _ => None,
+    }
+}
+
+fn excerpt(text: &str) -> String {
+    if text.chars().count() <= OBSERVER_SUMMARY_MAX_CHARS {
+        return text.to_string();
+    }
+    let mut out = text
+        .chars()
+        .take(OBSERVER_SUMMARY_MAX_CHARS)
+        .collect::<String>();
+    out.push('…');
+    out
+}
+
 fn normalize_tool_name(name: &str) -> String {
     match name.trim().to_lowercase().replace('-', "_").as_str() {
         "todowrite" | "update_todo_list" | "update_todos" => "todo_write".to_string(),
@@ -1717,6 +2071,12 @@ async fn evaluate_capability_deny(
     session_id: &str,
     message_id: &str,
 ) -> Option<String> {
+    if instance.role == AgentRole::Observer {
+        return Some(format!(
+            "observer instance `{}` is read-only and cannot run `{tool}`",
+            instance.instance_id
+        ));
+    }
     if !caps.tool_denylist.is_empty()
         && caps
             .tool_denylist
@@ -1861,7 +2221,7 @@ fn denied_secrets_reason(
 
 fn tool_fs_access_kind(tool: &str) -> Option<&'static str> {
     match tool {
-        "read" | "glob" | "grep" | "codesearch" | "lsp" => Some("read"),
+        "read" | "glob" | "grep" | "codesearch" | "lsp" | "secrets_scan" => Some("read"),
         "write" | "edit" | "apply_patch" => Some("write"),
         _ => None,
     }
@@ -2184,3 +2544,61 @@ pub fn emit_mission_budget_exhausted(
         }),
     ));
 }
+
+pub fn emit_mission_handoff(state: &AppState, mission_id: &str, handoff: &MissionHandoff) {
+    state.event_bus.publish(EngineEvent::new(
+        "agent_team.mission.handoff",
+        json!({
+            "sessionID": Value::Null,
+            "messageID": Value::Null,
+            "runID": Value::Null,
+            "missionID": mission_id,
+            "instanceID": Value::Null,
+            "handoffID": handoff.handoff_id,
+            "kind": handoff.kind,
+            "fromRole": handoff.from_role,
+            "toRole": handoff.to_role,
+            "workItemIDs": handoff.work_item_ids,
+            "summary": handoff.document.summary,
+            "openItems": handoff.document.open_items,
+            "artifacts": handoff.document.artifacts,
+            "timestampMs": crate::now_ms(),
+        }),
+    ));
+}
+
+pub fn emit_observer_commentary(
+    state: &AppState,
+    mission_id: &str,
+    observer_instance_ids: &[String],
+    entry: &ObserverEntry,
+) {
+    state.event_bus.publish(EngineEvent::new(
+        "agent_team.observer.commentary",
+        json!({
+            "sessionID": Value::Null,
+            "messageID": Value::Null,
+            "runID": Value::Null,
+            "missionID": mission_id,
+            "instanceID": entry.instance_id,
+            "observerInstanceIDs": observer_instance_ids,
+            "entry": entry,
+            "timestampMs": crate::now_ms(),
+        }),
+    ));
+}
+
+pub fn emit_compliance_report(state: &AppState, report: &ComplianceReport) {
+    state.event_bus.publish(EngineEvent::new(
+        "agent_team.mission.compliance_report",
+        json!({
+            "sessionID": Value::Null,
+            "messageID": Value::Null,
+            "runID": Value::Null,
+            "missionID": report.mission_id,
+            "instanceID": Value::Null,
+            "report": report,
+            "timestampMs": crate::now_ms(),
+        }),
+    ));
+}
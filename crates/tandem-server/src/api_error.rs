@@ -0,0 +1,180 @@
+//! Normalization of HTTP error responses onto [`ErrorEnvelope`].
+//!
+//! Handlers fail in several shapes: `ErrorEnvelope` bodies, ad-hoc
+//! `{"error", "code"}` objects, bare status codes and plain text. The error
+//! middleware rewrites every 4xx/5xx response through [`normalize_error_body`]
+//! so clients always get `code`, `message`, `retryable` and `correlation_id`.
+//! Keys from the original body are kept at the top level (including the legacy
+//! `error` string) so existing clients keep working.
+
+use axum::http::{HeaderMap, StatusCode};
+use serde_json::{Map, Value};
+use tandem_types::{default_error_code, is_retryable_status, ErrorEnvelope};
+
+const MAX_CORRELATION_ID_CHARS: usize = 128;
+const MAX_TEXT_MESSAGE_CHARS: usize = 2_000;
+const ENVELOPE_KEYS: [&str; 6] = [
+    "code",
+    "message",
+    "error",
+    "details",
+    "retryable",
+    "correlation_id",
+];
+
+/// Correlation id of the current request, available to handlers as an
+/// `Extension<CorrelationId>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorrelationId(pub String);
+
+pub fn new_correlation_id() -> String {
+    format!("corr-{}", uuid::Uuid::new_v4().simple())
+}
+
+/// Caller-supplied correlation id from `x-tandem-correlation-id` (or the
+/// generic `x-correlation-id`/`x-request-id`), if it is short and uses only
+/// URL-safe characters.
+pub fn correlation_id_from_headers(headers: &HeaderMap) -> Option<String> {
+    [
+        tandem_types::CORRELATION_ID_HEADER,
+        "x-correlation-id",
+        tandem_types::REQUEST_ID_HEADER,
+    ]
+    .iter()
+    .filter_map(|name| headers.get(*name).and_then(|v| v.to_str().ok()))
+    .map(str::trim)
+    .find(|id| {
+        !id.is_empty()
+            && id.len() <= MAX_CORRELATION_ID_CHARS
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'))
+    })
+    .map(str::to_string)
+}
+
+/// Build the envelope for an error response and the JSON body that replaces
+/// the original one.
+pub fn normalize_error_body(
+    status: StatusCode,
+    body: &[u8],
+    correlation_id: &str,
+) -> (ErrorEnvelope, Value) {
+    let parsed = serde_json::from_slice::<Value>(body).ok();
+    let text = match &parsed {
+        Some(Value::String(text)) => text.trim().to_string(),
+        None => String::from_utf8_lossy(body)
+            .trim()
+            .chars()
+            .take(MAX_TEXT_MESSAGE_CHARS)
+            .collect(),
+        Some(_) => String::new(),
+    };
+    let mut object = match parsed {
+        Some(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+
+    let message = ["message", "error"]
+        .iter()
+        .find_map(|key| match object.get(*key) {
+            Some(Value::String(s)) => Some(s.clone()),
+            Some(Value::Object(inner)) => inner
+                .get("message")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            _ => None,
+        })
+        .filter(|s| !s.trim().is_empty())
+        .or_else(|| (!text.is_empty()).then_some(text))
+        .unwrap_or_else(|| {
+            status
+                .canonical_reason()
+                .unwrap_or("Request failed")
+                .to_string()
+        });
+    let code = object
+        .get("code")
+        .and_then(Value::as_str)
+        .filter(|s| !s.trim().is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| default_error_code(status.as_u16()).to_string());
+    let retryable = object
+        .get("retryable")
+        .and_then(Value::as_bool)
+        .unwrap_or_else(|| is_retryable_status(status.as_u16()));
+    let details = object.get("details").cloned().or_else(|| {
+        let extra = object
+            .iter()
+            .filter(|(key, _)| !ENVELOPE_KEYS.contains(&key.as_str()))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect::<Map<_, _>>();
+        (!extra.is_empty()).then_some(Value::Object(extra))
+    });
+
+    let mut envelope = ErrorEnvelope::new(code, message)
+        .with_retryable(retryable)
+        .with_correlation_id(correlation_id);
+    envelope.details = details;
+
+    if let Ok(Value::Object(fields)) = serde_json::to_value(&envelope) {
+        object.extend(fields);
+    }
+    if !object.get("error").is_some_and(Value::is_string) {
+        object.insert("error".to_string(), Value::String(envelope.message.clone()));
+    }
+    (envelope, Value::Object(object))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn normalizes_legacy_objects_text_and_empty_bodies() {
+        let legacy =
+            json!({"error": "Mission not found", "code": "MISSION_NOT_FOUND", "missionID": "m1"});
+        let (envelope, body) = normalize_error_body(
+            StatusCode::NOT_FOUND,
+            legacy.to_string().as_bytes(),
+            "corr-1",
+        );
+        assert_eq!(envelope.code, "MISSION_NOT_FOUND");
+        assert_eq!(envelope.message, "Mission not found");
+        assert!(!envelope.retryable);
+        assert_eq!(envelope.details, Some(json!({"missionID": "m1"})));
+        assert_eq!(body["error"], json!("Mission not found"));
+        assert_eq!(body["missionID"], json!("m1"));
+        assert_eq!(body["correlation_id"], json!("corr-1"));
+
+        let (envelope, body) =
+            normalize_error_body(StatusCode::BAD_GATEWAY, b"upstream exploded", "corr-2");
+        assert_eq!(envelope.code, "BAD_GATEWAY");
+        assert_eq!(envelope.message, "upstream exploded");
+        assert!(envelope.retryable);
+        assert_eq!(body["error"], json!("upstream exploded"));
+
+        let (envelope, _) = normalize_error_body(StatusCode::NOT_FOUND, b"", "corr-3");
+        assert_eq!(
+            (envelope.code.as_str(), envelope.message.as_str()),
+            ("NOT_FOUND", "Not Found")
+        );
+    }
+
+    #[test]
+    fn accepts_only_safe_caller_correlation_ids() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", "req-42".parse().unwrap());
+        assert_eq!(
+            correlation_id_from_headers(&headers),
+            Some("req-42".to_string())
+        );
+        headers.insert("x-tandem-correlation-id", "bad id<script>".parse().unwrap());
+        assert_eq!(
+            correlation_id_from_headers(&headers),
+            Some("req-42".to_string())
+        );
+        assert!(new_correlation_id().starts_with("corr-"));
+    }
+}
@@ -0,0 +1,1268 @@
+//! Tamper-evident audit chain for compliance-sensitive deployments.
+//!
+//! With the `audit_chain` config block enabled, audit-relevant engine events
+//! (memory access, permission prompts and replies, tool calls, command-policy
+//! decisions and approvals) are appended to `audit-chain/chain.jsonl` under
+//! the state directory. Every record carries the SHA-256 of the record before
+//! it, so editing, dropping or reordering a line breaks the chain from that
+//! point on.
+//!
+//! Every `anchor_interval_secs` the current head is anchored: an anchor
+//! record signs the head hash with an HMAC key and, when `anchor_url` is set,
+//! stores the receipt of an external timestamping service the head hash was
+//! posted to. The key is supplied out of band, through the environment
+//! variable named by `key_env` or a `key_file` outside the state directory,
+//! so whoever can write the chain cannot also re-sign it. Rewriting the whole
+//! chain then also needs the key, and cannot change what the external service
+//! already acknowledged.
+//!
+//! Every append also records the signed head in `audit-chain/head.json`, so
+//! dropping records from the end of the chain is caught as well.
+//! `GET /audit/chain/verify` recomputes the chain, checks every anchor and the
+//! recorded head and, with `anchor_verify_url`, asks the timestamping service
+//! to confirm each receipt.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tandem_types::EngineEvent;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use crate::object_store::{hex, hmac_sha256, sha256_hex};
+use crate::{now_ms, AppState};
+
+const CHAIN_FILE: &str = "chain.jsonl";
+const HEAD_FILE: &str = "head.json";
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+const CONFIG_REFRESH_MS: u64 = 5_000;
+const ANCHOR_CHECK_SECS: u64 = 30;
+const MAX_RECEIPT_CHARS: usize = 4_096;
+/// Synthetic event type for finished or started tool calls, which the engine
+/// publishes as `message.part.updated`.
+pub const TOOL_CALL_EVENT: &str = "tool.call";
+
+fn default_event_types() -> Vec<String> {
+    [
+        "memory.put",
+        "memory.promote",
+        "memory.search",
+        "memory.deleted",
+        "permission.asked",
+        "permission.replied",
+        TOOL_CALL_EVENT,
+        "tool.command_policy",
+        "routine.tool.denied",
+        "routine.approval_required",
+        "routine.run.approved",
+        "routine.run.denied",
+        "agent_team.spawn.*",
+        "agent_team.capability.denied",
+    ]
+    .into_iter()
+    .map(str::to_string)
+    .collect()
+}
+
+fn default_anchor_interval_secs() -> u64 {
+    3_600
+}
+
+fn default_key_env() -> String {
+    "TANDEM_AUDIT_CHAIN_KEY".to_string()
+}
+
+/// `audit_chain` config block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditChainConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Event types to record; a trailing `*` matches a prefix.
+    #[serde(default = "default_event_types")]
+    pub event_types: Vec<String>,
+    #[serde(default = "default_anchor_interval_secs")]
+    pub anchor_interval_secs: u64,
+    /// External timestamping endpoint. Receives `{"hash","seq","timestampMs"}`
+    /// as a JSON POST; its response is kept as the anchor's receipt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor_url: Option<String>,
+    /// Receipt lookup on the timestamping service; `{hash}` is replaced by
+    /// the anchored head hash. A 2xx response whose body contains the hash
+    /// confirms the anchor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor_verify_url: Option<String>,
+    /// Environment variable holding the anchor HMAC key.
+    #[serde(default = "default_key_env")]
+    pub key_env: String,
+    /// File holding the anchor HMAC key, used when `key_env` is unset. It
+    /// must live outside the state directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<PathBuf>,
+}
+
+impl Default for AuditChainConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            event_types: default_event_types(),
+            anchor_interval_secs: default_anchor_interval_secs(),
+            anchor_url: None,
+            anchor_verify_url: None,
+            key_env: default_key_env(),
+            key_file: None,
+        }
+    }
+}
+
+impl AuditChainConfig {
+    pub fn records(&self, event_type: &str) -> bool {
+        // Never chain our own bookkeeping events.
+        !event_type.starts_with("audit_chain.")
+            && self
+                .event_types
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => event_type.starts_with(prefix),
+                    None => pattern == event_type,
+                })
+    }
+
+    /// The anchor key from `key_env` or `key_file`; `None` when neither is
+    /// set.
+    pub fn anchor_key(&self, state_dir: &Path) -> anyhow::Result<Option<Vec<u8>>> {
+        if let Ok(key) = std::env::var(&self.key_env) {
+            if !key.trim().is_empty() {
+                return Ok(Some(key.trim().as_bytes().to_vec()));
+            }
+        }
+        let Some(path) = &self.key_file else {
+            return Ok(None);
+        };
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("reading audit chain key file {}", path.display()))?;
+        let state_dir = state_dir
+            .canonicalize()
+            .unwrap_or_else(|_| state_dir.to_path_buf());
+        if path.starts_with(&state_dir) {
+            anyhow::bail!("audit chain key file must live outside the state directory");
+        }
+        let key = std::fs::read_to_string(&path).context("reading audit chain key file")?;
+        if key.trim().is_empty() {
+            anyhow::bail!("audit chain key file is empty");
+        }
+        Ok(Some(key.trim().as_bytes().to_vec()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditRecordKind {
+    Event,
+    /// Events dropped because the recorder fell behind the event bus.
+    Gap,
+    Anchor,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditChainRecord {
+    pub seq: u64,
+    pub kind: AuditRecordKind,
+    pub event_type: String,
+    pub timestamp_ms: u64,
+    pub properties: Value,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+/// The hashed part of a record: everything but `hash`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HashedFields<'a> {
+    seq: u64,
+    kind: AuditRecordKind,
+    event_type: &'a str,
+    timestamp_ms: u64,
+    properties: &'a Value,
+    prev_hash: &'a str,
+}
+
+impl AuditChainRecord {
+    pub fn compute_hash(&self) -> String {
+        let fields = HashedFields {
+            seq: self.seq,
+            kind: self.kind,
+            event_type: &self.event_type,
+            timestamp_ms: self.timestamp_ms,
+            properties: &self.properties,
+            prev_hash: &self.prev_hash,
+        };
+        sha256_hex(&serde_json::to_vec(&fields).unwrap_or_default())
+    }
+}
+
+fn anchor_signature(key: &[u8], seq: u64, hash: &str) -> String {
+    hex(&hmac_sha256(key, format!("{seq}:{hash}").as_bytes()))
+}
+
+fn head_signature(key: &[u8], seq: u64, hash: &str) -> String {
+    hex(&hmac_sha256(key, format!("head:{seq}:{hash}").as_bytes()))
+}
+
+fn key_id(key: &[u8]) -> String {
+    sha256_hex(key)[..16].to_string()
+}
+
+/// The newest record as of the last append, kept in `head.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedHead {
+    pub seq: u64,
+    pub hash: String,
+    /// HMAC of the head, present when an anchor key was configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl RecordedHead {
+    fn new(seq: u64, hash: &str, key: Option<&[u8]>) -> Self {
+        Self {
+            seq,
+            hash: hash.to_string(),
+            signature: key.map(|key| head_signature(key, seq, hash)),
+        }
+    }
+}
+
+/// Where verification stopped.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditChainFailure {
+    /// 1-based line of `chain.jsonl`.
+    pub line: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<u64>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditChainVerification {
+    pub valid: bool,
+    pub records: u64,
+    pub anchors: u64,
+    pub external_anchors: u64,
+    /// External receipts the timestamping service confirmed during this
+    /// verification.
+    pub confirmed_anchors: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head_seq: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_anchor_seq: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_anchor_at_ms: Option<u64>,
+    /// Records after the newest anchored head; these are only protected by
+    /// the hash chain until the next anchor.
+    pub unanchored_records: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<AuditChainFailure>,
+}
+
+/// Recompute the chain in `raw` (the contents of `chain.jsonl`), check every
+/// anchor against `key` and check that the chain still reaches `head`.
+pub fn verify_chain(
+    raw: &str,
+    key: Option<&[u8]>,
+    head: Option<&RecordedHead>,
+) -> AuditChainVerification {
+    let mut report = AuditChainVerification {
+        valid: true,
+        records: 0,
+        anchors: 0,
+        external_anchors: 0,
+        confirmed_anchors: 0,
+        head_seq: None,
+        head_hash: None,
+        last_anchor_seq: None,
+        last_anchor_at_ms: None,
+        unanchored_records: 0,
+        failure: None,
+    };
+    let mut hashes: Vec<String> = Vec::new();
+    let mut anchor_flags: Vec<bool> = Vec::new();
+    let mut anchored_seq: Option<u64> = None;
+    let mut lines = 0;
+    for (index, line) in raw.lines().enumerate() {
+        lines = index + 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fail = |report: &mut AuditChainVerification, seq: Option<u64>, reason: String| {
+            report.valid = false;
+            report.failure = Some(AuditChainFailure {
+                line: index + 1,
+                seq,
+                reason,
+            });
+        };
+        let record = match serde_json::from_str::<AuditChainRecord>(line) {
+            Ok(record) => record,
+            Err(err) => {
+                fail(&mut report, None, format!("unparseable record: {err}"));
+                break;
+            }
+        };
+        let seq = Some(record.seq);
+        let expected_prev = hashes.last().map_or(GENESIS_HASH, String::as_str);
+        if record.seq != hashes.len() as u64 {
+            fail(
+                &mut report,
+                seq,
+                format!("expected seq {}, found {}", hashes.len(), record.seq),
+            );
+            break;
+        }
+        if record.prev_hash != expected_prev {
+            fail(
+                &mut report,
+                seq,
+                "prevHash does not match the previous record".to_string(),
+            );
+            break;
+        }
+        if record.compute_hash() != record.hash {
+            fail(
+                &mut report,
+                seq,
+                "record hash does not match its contents".to_string(),
+            );
+            break;
+        }
+        if record.kind == AuditRecordKind::Anchor {
+            let props = &record.properties;
+            let head_seq = props.get("headSeq").and_then(Value::as_u64);
+            let head_hash = props.get("headHash").and_then(Value::as_str);
+            let (Some(head_seq), Some(head_hash)) = (head_seq, head_hash) else {
+                fail(
+                    &mut report,
+                    seq,
+                    "anchor is missing headSeq/headHash".to_string(),
+                );
+                break;
+            };
+            if hashes.get(head_seq as usize).map(String::as_str) != Some(head_hash) {
+                fail(
+                    &mut report,
+                    seq,
+                    format!("anchored head {head_seq} does not match the chain"),
+                );
+                break;
+            }
+            let Some(key) = key else {
+                fail(&mut report, seq, "anchor key is missing".to_string());
+                break;
+            };
+            let signature = props.get("signature").and_then(Value::as_str);
+            if signature != Some(anchor_signature(key, head_seq, head_hash).as_str()) {
+                fail(&mut report, seq, "anchor signature is invalid".to_string());
+                break;
+            }
+            report.anchors += 1;
+            if props
+                .pointer("/receipt/ok")
+                .and_then(Value::as_bool)
+                .unwrap_or(false)
+            {
+                report.external_anchors += 1;
+            }
+            report.last_anchor_seq = Some(record.seq);
+            report.last_anchor_at_ms = Some(record.timestamp_ms);
+            anchored_seq = Some(anchored_seq.map_or(head_seq, |s| s.max(head_seq)));
+        }
+        report.records += 1;
+        report.head_seq = Some(record.seq);
+        report.head_hash = Some(record.hash.clone());
+        hashes.push(record.hash);
+        anchor_flags.push(record.kind == AuditRecordKind::Anchor);
+    }
+    if report.valid {
+        if let Some(reason) = head_mismatch(&hashes, key, head) {
+            report.valid = false;
+            report.failure = Some(AuditChainFailure {
+                line: lines + 1,
+                seq: Some(hashes.len() as u64),
+                reason,
+            });
+        }
+    }
+    let first_unanchored = anchored_seq.map_or(0, |seq| seq as usize + 1);
+    report.unanchored_records = anchor_flags
+        .iter()
+        .skip(first_unanchored)
+        .filter(|is_anchor| !**is_anchor)
+        .count() as u64;
+    report
+}
+
+/// Why the chain with record `hashes` does not reach the recorded `head`.
+fn head_mismatch(
+    hashes: &[String],
+    key: Option<&[u8]>,
+    head: Option<&RecordedHead>,
+) -> Option<String> {
+    let Some(head) = head else {
+        return (!hashes.is_empty()).then(|| "recorded head is missing".to_string());
+    };
+    if let Some(key) = key {
+        if head.signature.as_deref() != Some(head_signature(key, head.seq, &head.hash).as_str()) {
+            return Some("recorded head signature is invalid".to_string());
+        }
+    }
+    match hashes.get(head.seq as usize) {
+        Some(hash) if *hash == head.hash => None,
+        Some(_) => Some(format!(
+            "record {} does not match the recorded head",
+            head.seq
+        )),
+        None => Some(format!(
+            "chain is truncated: it ends before the recorded head {}",
+            head.seq
+        )),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ChainHead {
+    next_seq: u64,
+    hash: String,
+    /// Newest seq covered by an anchor.
+    anchored_seq: Option<u64>,
+    /// When the oldest record not yet covered by an anchor was written.
+    unanchored_since_ms: Option<u64>,
+}
+
+#[derive(Default)]
+struct ChainInner {
+    head: Option<ChainHead>,
+    config: Option<(AuditChainConfig, u64)>,
+}
+
+#[derive(Clone, Default)]
+pub struct AuditChain {
+    inner: Arc<Mutex<ChainInner>>,
+}
+
+async fn read_chain(dir: &Path) -> anyhow::Result<String> {
+    match tokio::fs::read_to_string(dir.join(CHAIN_FILE)).await {
+        Ok(raw) => Ok(raw),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(err) => Err(err).context("reading audit chain"),
+    }
+}
+
+async fn read_recorded_head(dir: &Path) -> anyhow::Result<Option<RecordedHead>> {
+    match tokio::fs::read(dir.join(HEAD_FILE)).await {
+        Ok(raw) => serde_json::from_slice(&raw)
+            .map(Some)
+            .context("audit chain head record is unparseable"),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).context("reading audit chain head record"),
+    }
+}
+
+async fn write_recorded_head(dir: &Path, head: &RecordedHead) -> anyhow::Result<()> {
+    let path = dir.join(HEAD_FILE);
+    let tmp = path.with_extension("partial");
+    tokio::fs::write(&tmp, serde_json::to_vec(head)?).await?;
+    tokio::fs::rename(&tmp, &path).await?;
+    Ok(())
+}
+
+/// Replay the chain to find its head. Refuses to continue a chain that no
+/// longer reaches the recorded head, so a truncation is not papered over by
+/// new records.
+async fn load_head(dir: &Path) -> anyhow::Result<ChainHead> {
+    let raw = read_chain(dir).await?;
+    let mut head = ChainHead {
+        next_seq: 0,
+        hash: GENESIS_HASH.to_string(),
+        anchored_seq: None,
+        unanchored_since_ms: None,
+    };
+    let mut hashes = Vec::new();
+    for line in raw.lines().filter(|line| !line.trim().is_empty()) {
+        let record = serde_json::from_str::<AuditChainRecord>(line)
+            .context("audit chain has an unparseable record; verify it before appending")?;
+        head.advance(&record);
+        hashes.push(record.hash);
+    }
+    let recorded = read_recorded_head(dir).await?;
+    if recorded.is_some() || !hashes.is_empty() {
+        // Signatures are checked by verification; appending only needs the
+        // chain to still reach the head.
+        if let Some(reason) = head_mismatch(&hashes, None, recorded.as_ref()) {
+            anyhow::bail!("{reason}; verify the audit chain before appending");
+        }
+    }
+    Ok(head)
+}
+
+impl ChainHead {
+    fn advance(&mut self, record: &AuditChainRecord) {
+        if record.kind == AuditRecordKind::Anchor {
+            let anchored = record.properties.get("headSeq").and_then(Value::as_u64);
+            self.anchored_seq = anchored;
+            // Records written while the anchor was being obtained stay pending.
+            self.unanchored_since_ms = anchored
+                .filter(|anchored| anchored + 1 < record.seq)
+                .map(|_| record.timestamp_ms);
+        } else if self.unanchored_since_ms.is_none() {
+            self.unanchored_since_ms = Some(record.timestamp_ms);
+        }
+        self.next_seq = record.seq + 1;
+        self.hash = record.hash.clone();
+    }
+}
+
+impl AuditChain {
+    async fn append_locked(
+        inner: &mut ChainInner,
+        dir: &Path,
+        key: Option<&[u8]>,
+        kind: AuditRecordKind,
+        event_type: &str,
+        properties: Value,
+        now: u64,
+    ) -> anyhow::Result<AuditChainRecord> {
+        let mut head = match inner.head.clone() {
+            Some(head) => head,
+            None => load_head(dir).await?,
+        };
+        let mut record = AuditChainRecord {
+            seq: head.next_seq,
+            kind,
+            event_type: event_type.to_string(),
+            timestamp_ms: now,
+            properties,
+            prev_hash: head.hash.clone(),
+            hash: String::new(),
+        };
+        record.hash = record.compute_hash();
+        tokio::fs::create_dir_all(dir).await?;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(CHAIN_FILE))
+            .await?;
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+        write_recorded_head(dir, &RecordedHead::new(record.seq, &record.hash, key)).await?;
+
+        head.advance(&record);
+        inner.head = Some(head);
+        Ok(record)
+    }
+
+    /// Append a record. `key` signs the recorded head.
+    pub async fn append(
+        &self,
+        dir: &Path,
+        key: Option<&[u8]>,
+        kind: AuditRecordKind,
+        event_type: &str,
+        properties: Value,
+        now: u64,
+    ) -> anyhow::Result<AuditChainRecord> {
+        let mut inner = self.inner.lock().await;
+        Self::append_locked(&mut inner, dir, key, kind, event_type, properties, now)
+            .await
+            .inspect_err(|_| inner.head = None)
+    }
+
+    /// `(seq, hash)` of the newest record when it is not anchored yet and
+    /// `due_after_ms` has passed since the oldest unanchored record.
+    async fn anchor_candidate(
+        &self,
+        dir: &Path,
+        due_after_ms: Option<u64>,
+        now: u64,
+    ) -> anyhow::Result<Option<(u64, String)>> {
+        let mut inner = self.inner.lock().await;
+        let head = match inner.head.clone() {
+            Some(head) => head,
+            None => {
+                let head = load_head(dir).await?;
+                inner.head = Some(head.clone());
+                head
+            }
+        };
+        let Some(since) = head.unanchored_since_ms else {
+            return Ok(None);
+        };
+        if due_after_ms.is_some_and(|after| now.saturating_sub(since) < after) {
+            return Ok(None);
+        }
+        Ok(Some((head.next_seq - 1, head.hash)))
+    }
+
+    /// Anchor the current head with `key`, posting it to `anchor_url` for an
+    /// external receipt when set. Returns `None` when everything is already
+    /// anchored or the anchor is not due yet.
+    pub async fn anchor(
+        &self,
+        dir: &Path,
+        key: &[u8],
+        due_after_ms: Option<u64>,
+        anchor_url: Option<&str>,
+        now: u64,
+    ) -> anyhow::Result<Option<AuditChainRecord>> {
+        let Some((head_seq, head_hash)) = self.anchor_candidate(dir, due_after_ms, now).await?
+        else {
+            return Ok(None);
+        };
+        let receipt = match anchor_url {
+            Some(url) => Some(external_receipt(url, head_seq, &head_hash, now).await),
+            None => None,
+        };
+        let properties = json!({
+            "headSeq": head_seq,
+            "headHash": head_hash,
+            "keyID": key_id(key),
+            "signature": anchor_signature(key, head_seq, &head_hash),
+            "receipt": receipt,
+        });
+        self.append(
+            dir,
+            Some(key),
+            AuditRecordKind::Anchor,
+            "audit_chain.anchor",
+            properties,
+            now,
+        )
+        .await
+        .map(Some)
+    }
+
+    /// Newest records first.
+    pub async fn recent(&self, dir: &Path, limit: usize) -> Vec<AuditChainRecord> {
+        let raw = tokio::fs::read_to_string(dir.join(CHAIN_FILE))
+            .await
+            .unwrap_or_default();
+        raw.lines()
+            .rev()
+            .filter_map(|line| serde_json::from_str::<AuditChainRecord>(line).ok())
+            .take(limit)
+            .collect()
+    }
+
+    /// Verify the chain in `dir` against `key` and its recorded head, then
+    /// confirm external receipts through `verify_url` when set.
+    pub async fn verify(
+        &self,
+        dir: &Path,
+        key: Option<&[u8]>,
+        verify_url: Option<&str>,
+    ) -> anyhow::Result<AuditChainVerification> {
+        let (raw, head) = {
+            // Hold the lock so a half-written append is never read.
+            let _inner = self.inner.lock().await;
+            (read_chain(dir).await?, read_recorded_head(dir).await?)
+        };
+        let mut report = verify_chain(&raw, key, head.as_ref());
+        if let (true, Some(url)) = (report.valid, verify_url) {
+            confirm_receipts(&raw, url, &mut report).await;
+        }
+        Ok(report)
+    }
+}
+
+/// Ask the timestamping service about every anchor with a successful
+/// receipt; the first one it does not confirm fails the verification.
+async fn confirm_receipts(raw: &str, verify_url: &str, report: &mut AuditChainVerification) {
+    let client = reqwest::Client::new();
+    for (index, line) in raw.lines().enumerate() {
+        let Ok(record) = serde_json::from_str::<AuditChainRecord>(line) else {
+            continue;
+        };
+        let props = &record.properties;
+        let receipt_ok = props.pointer("/receipt/ok").and_then(Value::as_bool);
+        let head_hash = props.get("headHash").and_then(Value::as_str);
+        let (AuditRecordKind::Anchor, Some(true), Some(head_hash)) =
+            (record.kind, receipt_ok, head_hash)
+        else {
+            continue;
+        };
+        let url = verify_url.replace("{hash}", head_hash);
+        let confirmed = match client
+            .get(&url)
+            .timeout(Duration::from_secs(15))
+            .send()
+            .await
+        {
+            Ok(response) if response.status().is_success() => response
+                .text()
+                .await
+                .is_ok_and(|body| body.contains(head_hash)),
+            _ => false,
+        };
+        if !confirmed {
+            report.valid = false;
+            report.failure = Some(AuditChainFailure {
+                line: index + 1,
+                seq: Some(record.seq),
+                reason: "timestamping service did not confirm the anchor receipt".to_string(),
+            });
+            return;
+        }
+        report.confirmed_anchors += 1;
+    }
+}
+
+/// Post the head to the timestamping service. Failures are recorded in the
+/// receipt rather than failing the anchor: the local signature still holds.
+async fn external_receipt(url: &str, seq: u64, hash: &str, now: u64) -> Value {
+    let response = reqwest::Client::new()
+        .post(url)
+        .timeout(Duration::from_secs(15))
+        .json(&json!({"hash": hash, "seq": seq, "timestampMs": now}))
+        .send()
+        .await;
+    match response {
+        Ok(response) => {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            json!({
+                "url": url,
+                "ok": status.is_success(),
+                "status": status.as_u16(),
+                "body": body.chars().take(MAX_RECEIPT_CHARS).collect::<String>(),
+            })
+        }
+        Err(err) => json!({"url": url, "ok": false, "error": err.to_string()}),
+    }
+}
+
+/// The record for `event`, as `(event_type, properties)`. Tool parts of
+/// `message.part.updated` become `tool.call` records; streaming argument
+/// deltas and non-tool parts are skipped.
+pub fn audit_event(event: &EngineEvent) -> Option<(String, Value)> {
+    if event.event_type != "message.part.updated" {
+        return Some((event.event_type.clone(), event.properties.clone()));
+    }
+    let props = &event.properties;
+    let part = props.get("part")?;
+    if part.get("type").and_then(Value::as_str) != Some("tool")
+        || props.get("toolCallDelta").is_some()
+    {
+        return None;
+    }
+    let result_sha256 = part
+        .get("result")
+        .filter(|result| !result.is_null())
+        .map(|result| sha256_hex(result.to_string().as_bytes()));
+    Some((
+        TOOL_CALL_EVENT.to_string(),
+        json!({
+            "sessionID": part.get("sessionID"),
+            "messageID": part.get("messageID"),
+            "partID": part.get("id"),
+            "tool": part.get("tool"),
+            "state": part.get("state"),
+            "args": part.get("args"),
+            "error": part.get("error"),
+            "resultSha256": result_sha256,
+        }),
+    ))
+}
+
+/// Identifiers kept when an incognito session's event is audited.
+const INCOGNITO_AUDIT_KEYS: &[&str] = &[
+    "sessionID",
+    "messageID",
+    "partID",
+    "runID",
+    "requestID",
+    "tool",
+    "state",
+];
+
+/// An incognito session's event with only its identifiers and a hash of the
+/// full content, so the trail stays complete without storing what was said.
+pub fn redact_incognito(properties: &Value) -> Value {
+    let mut redacted = serde_json::Map::new();
+    for key in INCOGNITO_AUDIT_KEYS {
+        if let Some(value) = properties.get(*key).filter(|value| !value.is_null()) {
+            redacted.insert(key.to_string(), value.clone());
+        }
+    }
+    redacted.insert("incognito".to_string(), Value::Bool(true));
+    redacted.insert(
+        "contentSha256".to_string(),
+        Value::String(sha256_hex(properties.to_string().as_bytes())),
+    );
+    Value::Object(redacted)
+}
+
+impl AppState {
+    pub async fn audit_chain_config(&self) -> AuditChainConfig {
+        let now = now_ms();
+        if let Some((config, loaded_at)) = &self.audit_chain.inner.lock().await.config {
+            if now.saturating_sub(*loaded_at) < CONFIG_REFRESH_MS {
+                return config.clone();
+            }
+        }
+        let config: AuditChainConfig = self.config_section("audit_chain").await;
+        self.audit_chain.inner.lock().await.config = Some((config.clone(), now));
+        config
+    }
+
+    pub fn audit_chain_dir(&self) -> PathBuf {
+        self.state_dir.join("audit-chain")
+    }
+
+    pub async fn audit_chain_key(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        self.audit_chain_config().await.anchor_key(&self.state_dir)
+    }
+
+    pub async fn verify_audit_chain(&self) -> anyhow::Result<AuditChainVerification> {
+        let config = self.audit_chain_config().await;
+        let key = config.anchor_key(&self.state_dir)?;
+        self.audit_chain
+            .verify(
+                &self.audit_chain_dir(),
+                key.as_deref(),
+                config.anchor_verify_url.as_deref(),
+            )
+            .await
+    }
+
+    /// Append a record of `kind`, signing the recorded head with the
+    /// configured key.
+    pub async fn append_audit_record(
+        &self,
+        kind: AuditRecordKind,
+        event_type: &str,
+        properties: Value,
+    ) -> anyhow::Result<AuditChainRecord> {
+        let key = self.audit_chain_key().await?;
+        self.audit_chain
+            .append(
+                &self.audit_chain_dir(),
+                key.as_deref(),
+                kind,
+                event_type,
+                properties,
+                now_ms(),
+            )
+            .await
+    }
+
+    /// Append `event` to the chain when the chain is enabled and records its
+    /// type.
+    pub async fn record_audit_event(
+        &self,
+        event: &EngineEvent,
+    ) -> anyhow::Result<Option<AuditChainRecord>> {
+        let config = self.audit_chain_config().await;
+        if !config.enabled {
+            return Ok(None);
+        }
+        let Some((event_type, properties)) = audit_event(event) else {
+            return Ok(None);
+        };
+        if !config.records(&event_type) {
+            return Ok(None);
+        }
+        let incognito = match properties.get("sessionID").and_then(Value::as_str) {
+            Some(session_id) => self.storage.is_incognito(session_id).await,
+            None => false,
+        };
+        let properties = if incognito {
+            redact_incognito(&properties)
+        } else {
+            properties
+        };
+        self.append_audit_record(AuditRecordKind::Event, &event_type, properties)
+            .await
+            .map(Some)
+    }
+
+    /// Anchor the chain head now (`force`) or when the anchor interval has
+    /// passed.
+    pub async fn anchor_audit_chain(
+        &self,
+        force: bool,
+    ) -> anyhow::Result<Option<AuditChainRecord>> {
+        let config = self.audit_chain_config().await;
+        let Some(key) = config.anchor_key(&self.state_dir)? else {
+            anyhow::bail!(
+                "no audit chain anchor key; set {} or audit_chain.key_file",
+                config.key_env
+            );
+        };
+        let due_after_ms = (!force).then(|| config.anchor_interval_secs.max(60) * 1_000);
+        let anchor = self
+            .audit_chain
+            .anchor(
+                &self.audit_chain_dir(),
+                &key,
+                due_after_ms,
+                config.anchor_url.as_deref(),
+                now_ms(),
+            )
+            .await?;
+        if let Some(anchor) = &anchor {
+            let props = &anchor.properties;
+            self.event_bus.publish(EngineEvent::new(
+                "audit_chain.anchored",
+                json!({
+                    "seq": anchor.seq,
+                    "headSeq": props.get("headSeq"),
+                    "headHash": props.get("headHash"),
+                    "external": props.pointer("/receipt/ok"),
+                }),
+            ));
+        }
+        Ok(anchor)
+    }
+}
+
+pub async fn run_audit_chain(state: AppState) {
+    let mut rx = state.event_bus.subscribe();
+    let mut ticker = tokio::time::interval(Duration::from_secs(ANCHOR_CHECK_SECS));
+    loop {
+        tokio::select! {
+            received = rx.recv() => match received {
+                Ok(event) => {
+                    if let Err(err) = state.record_audit_event(&event).await {
+                        tracing::warn!("failed to append audit chain record: {err:#}");
+                    }
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(missed)) => {
+                    if !state.audit_chain_config().await.enabled {
+                        continue;
+                    }
+                    let appended = state
+                        .append_audit_record(
+                            AuditRecordKind::Gap,
+                            "audit_chain.gap",
+                            json!({"missedEvents": missed}),
+                        )
+                        .await;
+                    if let Err(err) = appended {
+                        tracing::warn!("failed to record audit chain gap: {err:#}");
+                    }
+                }
+            },
+            _ = ticker.tick() => {
+                if !state.audit_chain_config().await.enabled {
+                    continue;
+                }
+                if let Err(err) = state.anchor_audit_chain(false).await {
+                    tracing::warn!("failed to anchor audit chain: {err:#}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    const KEY: &[u8] = b"test anchor key";
+
+    #[tokio::test]
+    async fn chain_detects_edits_and_forged_rewrites() {
+        let dir = std::env::temp_dir().join(format!("tandem-audit-chain-{}", Uuid::new_v4()));
+        let chain = AuditChain::default();
+        for (i, event_type) in ["permission.asked", "permission.replied", TOOL_CALL_EVENT]
+            .into_iter()
+            .enumerate()
+        {
+            chain
+                .append(
+                    &dir,
+                    Some(KEY),
+                    AuditRecordKind::Event,
+                    event_type,
+                    json!({"n": i}),
+                    1_000,
+                )
+                .await
+                .expect("append");
+        }
+        // Not due yet, then anchored when forced.
+        assert!(chain
+            .anchor(&dir, KEY, Some(60_000), None, 2_000)
+            .await
+            .expect("anchor")
+            .is_none());
+        let anchor = chain
+            .anchor(&dir, KEY, None, None, 2_000)
+            .await
+            .expect("anchor")
+            .expect("anchor record");
+        assert_eq!(anchor.properties["headSeq"], json!(2));
+        assert!(chain
+            .anchor(&dir, KEY, None, None, 3_000)
+            .await
+            .expect("anchor")
+            .is_none());
+        chain
+            .append(
+                &dir,
+                Some(KEY),
+                AuditRecordKind::Event,
+                "memory.put",
+                json!({"n": 4}),
+                4_000,
+            )
+            .await
+            .expect("append");
+
+        let report = chain.verify(&dir, Some(KEY), None).await.expect("verify");
+        assert!(report.valid, "{report:?}");
+        assert_eq!(report.records, 5);
+        assert_eq!(report.anchors, 1);
+        assert_eq!(report.last_anchor_seq, Some(3));
+        assert_eq!(report.unanchored_records, 1);
+
+        let raw = std::fs::read_to_string(dir.join(CHAIN_FILE)).expect("chain");
+        let head = read_recorded_head(&dir).await.expect("head").expect("head");
+        assert_eq!(head.seq, 4);
+        let head = Some(&head);
+        let key = Some(KEY);
+
+        let edited = raw.replacen("{\"n\":1}", "{\"n\":9}", 1);
+        let failure = verify_chain(&edited, key, head).failure.expect("failure");
+        assert_eq!((failure.line, failure.seq), (2, Some(1)));
+
+        let dropped = raw
+            .lines()
+            .enumerate()
+            .filter(|(i, _)| *i != 1)
+            .map(|(_, line)| format!("{line}\n"))
+            .collect::<String>();
+        assert!(!verify_chain(&dropped, key, head).valid);
+
+        // Re-hashing everything after an edit still leaves the anchor
+        // pointing at the original head.
+        let mut prev = GENESIS_HASH.to_string();
+        let mut forged = String::new();
+        for line in edited.lines() {
+            let mut record = serde_json::from_str::<AuditChainRecord>(line).expect("record");
+            record.prev_hash = prev;
+            record.hash = record.compute_hash();
+            prev = record.hash.clone();
+            forged.push_str(&serde_json::to_string(&record).expect("json"));
+            forged.push('\n');
+        }
+        let failure = verify_chain(&forged, key, head).failure.expect("failure");
+        assert_eq!(failure.seq, Some(3));
+        assert!(failure.reason.contains("anchored head"), "{failure:?}");
+        assert!(!verify_chain(&raw, Some(b"other key"), head).valid);
+    }
+
+    #[tokio::test]
+    async fn chain_detects_truncation() {
+        let dir = std::env::temp_dir().join(format!("tandem-audit-chain-{}", Uuid::new_v4()));
+        let chain = AuditChain::default();
+        for n in 0..3 {
+            chain
+                .append(
+                    &dir,
+                    Some(KEY),
+                    AuditRecordKind::Event,
+                    "memory.put",
+                    json!({"n": n}),
+                    1_000,
+                )
+                .await
+                .expect("append");
+        }
+        chain
+            .anchor(&dir, KEY, None, None, 2_000)
+            .await
+            .expect("anchor")
+            .expect("anchor record");
+        chain
+            .append(
+                &dir,
+                Some(KEY),
+                AuditRecordKind::Event,
+                "memory.put",
+                json!({"n": 4}),
+                3_000,
+            )
+            .await
+            .expect("append");
+
+        // Dropping the tail keeps every remaining record and anchor valid;
+        // only the recorded head shows it.
+        let path = dir.join(CHAIN_FILE);
+        let raw = std::fs::read_to_string(&path).expect("chain");
+        let truncated = raw.lines().take(4).map(|line| format!("{line}\n"));
+        std::fs::write(&path, truncated.collect::<String>()).expect("truncate");
+        let report = chain.verify(&dir, Some(KEY), None).await.expect("verify");
+        let failure = report.failure.expect("failure");
+        assert_eq!((failure.line, failure.seq), (5, Some(4)));
+        assert!(failure.reason.contains("truncated"), "{failure:?}");
+
+        // A fresh process refuses to extend the truncated chain.
+        let reopened = AuditChain::default();
+        let err = reopened
+            .append(
+                &dir,
+                Some(KEY),
+                AuditRecordKind::Event,
+                "memory.put",
+                json!({}),
+                4_000,
+            )
+            .await
+            .expect_err("truncated chain");
+        assert!(err.to_string().contains("truncated"), "{err}");
+
+        // Rewriting the head record to match needs the key.
+        let forged = RecordedHead::new(3, &chain_hash_at(&path, 3), Some(b"other key"));
+        std::fs::write(
+            dir.join(HEAD_FILE),
+            serde_json::to_vec(&forged).expect("json"),
+        )
+        .expect("forge head");
+        let report = chain.verify(&dir, Some(KEY), None).await.expect("verify");
+        assert!(!report.valid);
+        std::fs::remove_file(dir.join(HEAD_FILE)).expect("remove head");
+        let report = chain.verify(&dir, Some(KEY), None).await.expect("verify");
+        assert_eq!(
+            report.failure.map(|failure| failure.reason),
+            Some("recorded head is missing".to_string())
+        );
+    }
+
+    fn chain_hash_at(path: &Path, seq: usize) -> String {
+        let raw = std::fs::read_to_string(path).expect("chain");
+        let line = raw.lines().nth(seq).expect("line");
+        serde_json::from_str::<AuditChainRecord>(line)
+            .expect("record")
+            .hash
+    }
+
+    #[tokio::test]
+    async fn receipts_are_confirmed_with_the_timestamping_service() {
+        use axum::extract::Path as UrlPath;
+        use axum::routing::{get, post};
+
+        let acknowledged = Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+        let stored = acknowledged.clone();
+        let lookup = acknowledged.clone();
+        let app = axum::Router::new()
+            .route(
+                "/stamp",
+                post(move |axum::Json(body): axum::Json<Value>| async move {
+                    let hash = body["hash"].as_str().unwrap_or_default().to_string();
+                    stored.lock().expect("lock").push(hash.clone());
+                    axum::Json(json!({ "hash": hash }))
+                }),
+            )
+            .route(
+                "/stamp/{hash}",
+                get(move |UrlPath(hash): UrlPath<String>| async move {
+                    if lookup.lock().expect("lock").contains(&hash) {
+                        Ok(axum::Json(json!({ "hash": hash })))
+                    } else {
+                        Err(axum::http::StatusCode::NOT_FOUND)
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind");
+        let base = format!("http://{}", listener.local_addr().expect("addr"));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let dir = std::env::temp_dir().join(format!("tandem-audit-chain-{}", Uuid::new_v4()));
+        let chain = AuditChain::default();
+        chain
+            .append(
+                &dir,
+                Some(KEY),
+                AuditRecordKind::Event,
+                "memory.put",
+                json!({}),
+                1_000,
+            )
+            .await
+            .expect("append");
+        let anchor_url = format!("{base}/stamp");
+        chain
+            .anchor(&dir, KEY, None, Some(&anchor_url), 2_000)
+            .await
+            .expect("anchor")
+            .expect("anchor record");
+        let verify_url = format!("{base}/stamp/{{hash}}");
+        let report = chain
+            .verify(&dir, Some(KEY), Some(&verify_url))
+            .await
+            .expect("verify");
+        assert!(report.valid, "{report:?}");
+        assert_eq!((report.external_anchors, report.confirmed_anchors), (1, 1));
+
+        // The service no longer knows the hash the receipt claims.
+        acknowledged.lock().expect("lock").clear();
+        let report = chain
+            .verify(&dir, Some(KEY), Some(&verify_url))
+            .await
+            .expect("verify");
+        let failure = report.failure.expect("failure");
+        assert_eq!(failure.seq, Some(1));
+        assert!(failure.reason.contains("did not confirm"), "{failure:?}");
+    }
+
+    #[test]
+    fn tool_parts_become_tool_call_records() {
+        let config = AuditChainConfig::default();
+        let part = |extra: Value| {
+            let mut props = json!({"part": {
+                "id": "p1", "sessionID": "s1", "type": "tool", "tool": "bash",
+                "state": "completed", "result": {"output": "secret"}
+            }});
+            props
+                .as_object_mut()
+                .expect("object")
+                .extend(extra.as_object().cloned().unwrap_or_default());
+            EngineEvent::new("message.part.updated", props)
+        };
+        let (event_type, props) = audit_event(&part(json!({}))).expect("tool call");
+        assert_eq!(event_type, TOOL_CALL_EVENT);
+        assert!(config.records(&event_type));
+        assert_eq!(props["tool"], json!("bash"));
+        assert_eq!(props["resultSha256"].as_str().map(str::len), Some(64));
+        assert!(audit_event(&part(json!({"toolCallDelta": {}}))).is_none());
+
+        assert!(config.records("agent_team.spawn.denied"));
+        assert!(!config.records("message.part.updated"));
+        assert!(!config.records("audit_chain.anchored"));
+
+        let redacted = redact_incognito(&json!({
+            "sessionID": "s1", "tool": "bash", "args": {"command": "cat notes.txt"}
+        }));
+        assert_eq!(redacted["sessionID"], json!("s1"));
+        assert_eq!(redacted["tool"], json!("bash"));
+        assert_eq!(redacted["incognito"], json!(true));
+        assert!(redacted.get("args").is_none());
+        assert_eq!(redacted["contentSha256"].as_str().map(str::len), Some(64));
+    }
+}
@@ -0,0 +1,189 @@
+//! Capability document for `GET /capabilities`.
+//!
+//! Clients read it once on connect to learn what this build and its current
+//! configuration support, instead of probing endpoints. The document is
+//! versioned with [`CAPABILITIES_SCHEMA_VERSION`]; fields are only added
+//! within a version, so clients should ignore ones they do not know.
+
+use serde::Serialize;
+
+use crate::AppState;
+
+/// Bumped when a field of the document is removed or changes meaning.
+pub const CAPABILITIES_SCHEMA_VERSION: u32 = 1;
+
+/// Channel integrations compiled into this build.
+const COMPILED_CHANNELS: [&str; 3] = ["telegram", "discord", "slack"];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapabilityDocument {
+    pub schema_version: u32,
+    pub server: ServerCapabilities,
+    pub features: FeatureCapabilities,
+    pub channels: Vec<ChannelCapability>,
+    pub providers: ProviderCapabilities,
+    pub tools: ToolCapabilities,
+    /// Declared feature flags as they apply to the caller.
+    pub feature_flags: Vec<tandem_core::FeatureFlagState>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerCapabilities {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub build_id: String,
+    pub mode: &'static str,
+    pub api_token_required: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureCapabilities {
+    pub memory: MemoryCapability,
+    pub routines: bool,
+    pub missions: bool,
+    pub agent_teams: bool,
+    pub mcp: bool,
+    pub web_ui: WebUiCapability,
+    pub oidc: bool,
+    pub tenants: bool,
+    pub replication: bool,
+    pub shared_sync: bool,
+    pub audit_chain: bool,
+    pub host_pressure: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryCapability {
+    pub enabled: bool,
+    /// Embeddings are computed on this host rather than by a provider.
+    pub local_embeddings: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebUiCapability {
+    pub enabled: bool,
+    pub prefix: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChannelCapability {
+    pub name: &'static str,
+    pub enabled: bool,
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderCapabilities {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    pub configured: Vec<ProviderCapability>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderCapability {
+    pub id: String,
+    pub name: String,
+    pub model_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCapabilities {
+    /// Every tool the engine can call, MCP tools included.
+    pub names: Vec<String>,
+    pub mcp_servers: Vec<String>,
+}
+
+impl AppState {
+    /// The capability document as seen by `tenant` (server-wide when `None`).
+    pub async fn capabilities(&self, tenant: Option<&str>) -> CapabilityDocument {
+        let channel_statuses = self.channel_statuses().await;
+        let channels = COMPILED_CHANNELS
+            .into_iter()
+            .map(|name| {
+                let status = channel_statuses.get(name);
+                ChannelCapability {
+                    name,
+                    enabled: status.is_some_and(|s| s.enabled),
+                    connected: status.is_some_and(|s| s.connected),
+                }
+            })
+            .collect();
+
+        let configured = self
+            .providers
+            .list()
+            .await
+            .into_iter()
+            .map(|provider| ProviderCapability {
+                model_count: provider.models.len(),
+                id: provider.id,
+                name: provider.name,
+            })
+            .collect();
+
+        let mut names = self
+            .tools
+            .list()
+            .await
+            .into_iter()
+            .map(|tool| tool.name)
+            .collect::<Vec<_>>();
+        names.sort();
+        let mut mcp_servers = self
+            .mcp
+            .list()
+            .await
+            .into_values()
+            .filter(|server| server.connected)
+            .map(|server| server.name)
+            .collect::<Vec<_>>();
+        mcp_servers.sort();
+
+        CapabilityDocument {
+            schema_version: CAPABILITIES_SCHEMA_VERSION,
+            server: ServerCapabilities {
+                name: env!("CARGO_PKG_NAME"),
+                version: env!("CARGO_PKG_VERSION"),
+                build_id: crate::build_id(),
+                mode: self.mode_label(),
+                api_token_required: self.api_token().await.is_some(),
+            },
+            features: FeatureCapabilities {
+                memory: MemoryCapability {
+                    enabled: true,
+                    local_embeddings: true,
+                },
+                routines: true,
+                missions: true,
+                agent_teams: true,
+                mcp: true,
+                web_ui: WebUiCapability {
+                    enabled: self.web_ui_enabled(),
+                    prefix: self.web_ui_prefix(),
+                },
+                oidc: self.oidc_config().await.is_active(),
+                tenants: self.tenants.is_enabled().await,
+                replication: self.replication_config().await.enabled,
+                shared_sync: self.shared_sync_config().await.enabled,
+                audit_chain: self.audit_chain_config().await.enabled,
+                host_pressure: self.host_pressure_config().await.enabled,
+            },
+            channels,
+            providers: ProviderCapabilities {
+                default: self.config.get().await.default_provider,
+                configured,
+            },
+            tools: ToolCapabilities { names, mcp_servers },
+            feature_flags: self.config.feature_flags().await.states(tenant),
+        }
+    }
+}
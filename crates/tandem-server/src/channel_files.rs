@@ -0,0 +1,195 @@
+//! `channel_send_file`: lets a routine run send a workspace file to a chat.
+//!
+//! Files can only go to the run's own `channel://` output targets, so a
+//! prompt cannot redirect them to another chat, and the configured target is
+//! used as written (a `secure=true` target still gets a link, not the file).
+//! Sensitive paths (keys, credential files, `.env`) and files with credential
+//! patterns in them are refused.
+//!
+//! The tool records the file as a run artifact whose metadata lists the
+//! `deliver_to` targets. The channel dispatcher follows the resulting
+//! `routine.run.artifact_added` event and uploads the file, or posts a
+//! secure link to it when the file is too large for the platform.
+
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tandem_tools::Tool;
+use tandem_types::{EngineEvent, ToolResult, ToolSchema};
+
+use crate::{now_ms, AppState, RoutineRunArtifact};
+
+pub const CHANNEL_SEND_FILE_TOOL: &str = "channel_send_file";
+const CHANNEL_TARGET_PREFIX: &str = "channel://";
+
+pub struct ChannelSendFileTool {
+    state: AppState,
+}
+
+impl ChannelSendFileTool {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+fn str_arg<'a>(args: &'a Value, key: &str) -> Option<&'a str> {
+    args.get(key)
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+/// `channel/recipient` of a `channel://` target, without its options.
+fn target_address(target: &str) -> Option<String> {
+    let rest = target.trim().strip_prefix(CHANNEL_TARGET_PREFIX)?;
+    let path = rest.split_once('?').map_or(rest, |(path, _)| path);
+    let (channel, recipient) = path.split_once('/')?;
+    if channel.is_empty() || recipient.is_empty() {
+        return None;
+    }
+    Some(format!("{}/{recipient}", channel.to_ascii_lowercase()))
+}
+
+/// The run output target `requested` names, as configured on the run.
+fn matching_output_target<'a>(output_targets: &'a [String], requested: &str) -> Option<&'a str> {
+    let address = target_address(requested)?;
+    output_targets
+        .iter()
+        .find(|target| target_address(target).as_deref() == Some(address.as_str()))
+        .map(String::as_str)
+}
+
+#[async_trait]
+impl Tool for ChannelSendFileTool {
+    fn schema(&self) -> ToolSchema {
+        ToolSchema {
+            name: CHANNEL_SEND_FILE_TOOL.to_string(),
+            description: "Send a workspace file (report, patch, export) to a chat as an attachment of the current routine run. Target must be one of the run's channel:// output targets, e.g. channel://slack/<channel_id>.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "path": { "type": "string", "description": "File path inside the workspace" },
+                    "target": { "type": "string", "description": "channel://{channel}/{recipient}" },
+                    "caption": { "type": "string", "description": "Short text sent with the file" }
+                },
+                "required": ["path", "target"]
+            }),
+        }
+    }
+
+    async fn execute(&self, args: Value) -> anyhow::Result<ToolResult> {
+        let path = str_arg(&args, "path").ok_or_else(|| anyhow::anyhow!("path is required"))?;
+        let target =
+            str_arg(&args, "target").ok_or_else(|| anyhow::anyhow!("target is required"))?;
+        if !target.starts_with(CHANNEL_TARGET_PREFIX) {
+            anyhow::bail!("target must be a channel://{{channel}}/{{recipient}} URI");
+        }
+        let policy = match str_arg(&args, "__session_id") {
+            Some(session_id) => self.state.routine_session_policy(session_id).await,
+            None => None,
+        }
+        .ok_or_else(|| {
+            anyhow::anyhow!("{CHANNEL_SEND_FILE_TOOL} is only available inside routine runs")
+        })?;
+        let root = match str_arg(&args, "__workspace_root") {
+            Some(root) => PathBuf::from(root),
+            None => PathBuf::from(self.state.workspace_index.snapshot().await.root),
+        };
+        let run = self
+            .state
+            .get_routine_run(&policy.run_id)
+            .await
+            .ok_or_else(|| anyhow::anyhow!("routine run {} not found", policy.run_id))?;
+        let target = matching_output_target(&run.output_targets, target)
+            .ok_or_else(|| {
+                anyhow::anyhow!("target `{target}` is not one of this run's channel output targets")
+            })?
+            .to_string();
+        let file = crate::routine_diff::artifact_file_path(path, &root)
+            .filter(|file| file.is_file())
+            .ok_or_else(|| anyhow::anyhow!("`{path}` is not a file inside the workspace"))?;
+        if tandem_core::is_sensitive_path(&file) {
+            anyhow::bail!("`{path}` is a sensitive path and cannot be sent");
+        }
+        let scanned = file.clone();
+        if tokio::task::spawn_blocking(move || tandem_tools::file_has_secrets(&scanned)).await? {
+            anyhow::bail!("`{path}` contains credentials and cannot be sent");
+        }
+        let file_name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+
+        let mut metadata = json!({
+            "source": format!("tool.{CHANNEL_SEND_FILE_TOOL}"),
+            "deliver_to": [target],
+        });
+        if let Some(caption) = str_arg(&args, "caption") {
+            metadata["caption"] = Value::String(caption.to_string());
+        }
+        let artifact = RoutineRunArtifact {
+            artifact_id: format!("artifact-{}", uuid::Uuid::new_v4()),
+            uri: file.to_string_lossy().to_string(),
+            kind: "file".to_string(),
+            label: Some(file_name.clone()),
+            created_at_ms: now_ms(),
+            metadata: Some(metadata),
+        };
+        let run = self
+            .state
+            .append_routine_run_artifact(&policy.run_id, artifact.clone())
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("routine run {} not found", policy.run_id))?;
+        self.state.event_bus.publish(EngineEvent::new(
+            "routine.run.artifact_added",
+            json!({
+                "runID": run.run_id,
+                "routineID": run.routine_id,
+                "artifact": artifact,
+            }),
+        ));
+        Ok(ToolResult {
+            output: format!("Queued {file_name} for delivery to {target}."),
+            metadata: json!({
+                "artifactID": artifact.artifact_id,
+                "runID": run.run_id,
+                "target": target,
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_runs_own_channel_targets_match() {
+        let outputs = vec![
+            "channel://slack/C123?secure=true".to_string(),
+            "channel://telegram/42".to_string(),
+            "webhook://https://example.com/hook".to_string(),
+        ];
+        assert_eq!(
+            matching_output_target(&outputs, "channel://Slack/C123"),
+            Some("channel://slack/C123?secure=true")
+        );
+        assert_eq!(
+            matching_output_target(&outputs, "channel://telegram/42?urgent=true"),
+            Some("channel://telegram/42")
+        );
+        assert_eq!(
+            matching_output_target(&outputs, "channel://slack/C999"),
+            None
+        );
+        assert_eq!(
+            matching_output_target(&outputs, "channel://telegram/"),
+            None
+        );
+        assert_eq!(
+            matching_output_target(&outputs, "https://example.com/hook"),
+            None
+        );
+    }
+}
@@ -0,0 +1,132 @@
+//! Audit log of shell commands stopped by the command policy.
+//!
+//! The engine publishes `tool.command_policy` whenever the bash tool's
+//! command policy denies a command or asks for approval, and again with the
+//! user's answer. Each event is appended to a JSONL file in the state
+//! directory and can be listed through `GET /audit/commands`.
+
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tandem_tools::CommandDecision;
+use tandem_types::EngineEvent;
+use tokio::io::AsyncWriteExt;
+
+use crate::{now_ms, AppState};
+
+const DEFAULT_LIST_LIMIT: usize = 200;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandAuditEntry {
+    #[serde(rename = "sessionID")]
+    pub session_id: String,
+    #[serde(rename = "messageID", default)]
+    pub message_id: Option<String>,
+    pub tool: String,
+    pub command: String,
+    /// `blocked`, `approved` or `rejected`.
+    pub outcome: String,
+    pub decision: CommandDecision,
+    pub timestamp_ms: u64,
+}
+
+impl CommandAuditEntry {
+    /// Entry for a `tool.command_policy` event.
+    pub fn from_event(event: &EngineEvent, timestamp_ms: u64) -> Option<Self> {
+        if event.event_type != "tool.command_policy" {
+            return None;
+        }
+        let props = &event.properties;
+        let text = |key: &str| props.get(key).and_then(|v| v.as_str()).map(str::to_string);
+        Some(Self {
+            session_id: text("sessionID")?,
+            message_id: text("messageID"),
+            tool: text("tool").unwrap_or_else(|| "bash".to_string()),
+            command: text("command")?,
+            outcome: text("outcome")?,
+            decision: serde_json::from_value(props.get("decision")?.clone()).ok()?,
+            timestamp_ms,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CommandAuditQuery {
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub outcome: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandAuditLog {
+    path: PathBuf,
+}
+
+impl CommandAuditLog {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.path = path;
+    }
+
+    pub async fn record(&self, entry: &CommandAuditEntry) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        file.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Matching entries, newest first.
+    pub async fn query(&self, query: &CommandAuditQuery) -> Vec<CommandAuditEntry> {
+        let raw = tokio::fs::read_to_string(&self.path)
+            .await
+            .unwrap_or_default();
+        raw.lines()
+            .rev()
+            .filter_map(|line| serde_json::from_str::<CommandAuditEntry>(line).ok())
+            .filter(|entry| {
+                query
+                    .session_id
+                    .as_deref()
+                    .is_none_or(|id| entry.session_id == id)
+                    && query
+                        .outcome
+                        .as_deref()
+                        .is_none_or(|outcome| entry.outcome == outcome)
+            })
+            .take(query.limit.unwrap_or(DEFAULT_LIST_LIMIT))
+            .collect()
+    }
+}
+
+pub async fn run_command_auditor(state: AppState) {
+    let mut rx = state.event_bus.subscribe();
+    loop {
+        match rx.recv().await {
+            Ok(event) => {
+                let Some(entry) = CommandAuditEntry::from_event(&event, now_ms()) else {
+                    continue;
+                };
+                if let Err(err) = state.command_audit.record(&entry).await {
+                    tracing::warn!("failed to record command policy audit entry: {}", err);
+                }
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+        }
+    }
+}
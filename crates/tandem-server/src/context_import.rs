@@ -0,0 +1,212 @@
+//! Seeding a session with earlier conversation from a channel thread.
+//!
+//! When someone mentions the bot in an existing Discord/Slack thread, the
+//! channel dispatcher posts the preceding messages to
+//! `POST /session/{id}/context/import`. They are summarized (by the cheapest
+//! configured provider, or as a trimmed excerpt when none answers) and the
+//! summary is appended to the session as a context message. Each import is
+//! recorded in the session metadata, and a conversation is imported at most
+//! once per session.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+use tandem_core::ContextImport;
+use tandem_types::{EngineEvent, Message, MessagePart, MessageRole};
+
+use crate::{now_ms, AppState};
+
+/// Most messages taken from one import; older ones are dropped.
+pub const MAX_IMPORT_MESSAGES: usize = 50;
+const MAX_MESSAGE_CHARS: usize = 1_000;
+const MAX_TRANSCRIPT_CHARS: usize = 24_000;
+const EXCERPT_MESSAGES: usize = 8;
+const SUMMARY_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportedMessage {
+    pub author: String,
+    pub text: String,
+    /// Platform timestamp, shown verbatim in the transcript.
+    #[serde(default)]
+    pub timestamp: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContextImportInput {
+    pub source: String,
+    pub conversation: String,
+    /// Oldest first.
+    #[serde(default)]
+    pub messages: Vec<ImportedMessage>,
+}
+
+#[derive(Debug)]
+pub enum ContextImportOutcome {
+    Imported(ContextImport),
+    /// The conversation was imported into this session before.
+    AlreadyImported(ContextImport),
+}
+
+/// The newest [`MAX_IMPORT_MESSAGES`] non-empty messages as
+/// `author: text` lines, each clipped, oldest first.
+pub fn render_transcript(messages: &[ImportedMessage]) -> String {
+    let kept = messages
+        .iter()
+        .filter(|m| !m.text.trim().is_empty())
+        .collect::<Vec<_>>();
+    let skip = kept.len().saturating_sub(MAX_IMPORT_MESSAGES);
+    let mut lines = kept[skip..]
+        .iter()
+        .map(|m| {
+            let text = crate::clip(m.text.trim(), MAX_MESSAGE_CHARS);
+            match m.timestamp.as_deref() {
+                Some(ts) => format!("[{ts}] {}: {text}", m.author.trim()),
+                None => format!("{}: {text}", m.author.trim()),
+            }
+        })
+        .collect::<Vec<_>>();
+    // Keep the most recent lines when the whole transcript is too long.
+    let mut total = lines.iter().map(|l| l.len() + 1).sum::<usize>();
+    while total > MAX_TRANSCRIPT_CHARS && lines.len() > 1 {
+        total -= lines.remove(0).len() + 1;
+    }
+    lines.join("\n")
+}
+
+pub fn summary_prompt(source: &str, transcript: &str) -> String {
+    format!(
+        "Below are the most recent messages from a {source} conversation that an assistant \
+was just asked to join. Summarize what was discussed for the assistant: the topic, decisions, \
+open questions and any requests addressed to the assistant, naming who said what where it \
+matters. Reply with at most 12 short bullet points.\n\n<conversation>\n{transcript}\n</conversation>"
+    )
+}
+
+/// Summary used when no provider answers: the last few transcript lines.
+pub fn excerpt_summary(transcript: &str) -> String {
+    let lines = transcript.lines().collect::<Vec<_>>();
+    let skip = lines.len().saturating_sub(EXCERPT_MESSAGES);
+    let mut out = String::new();
+    if skip > 0 {
+        out.push_str(&format!("({skip} earlier messages omitted)\n"));
+    }
+    out.push_str(&lines[skip..].join("\n"));
+    out
+}
+
+fn render_context_message(source: &str, message_count: usize, summary: &str) -> String {
+    format!(
+        "[Context imported from the {source} conversation this session was started from: \
+{message_count} earlier messages, summarized below. They were written before you joined.]\n{}",
+        summary.trim()
+    )
+}
+
+impl AppState {
+    /// Summarize `input` into `session_id`. `None` when the session does not
+    /// exist.
+    pub async fn import_session_context(
+        &self,
+        session_id: &str,
+        input: ContextImportInput,
+    ) -> anyhow::Result<Option<ContextImportOutcome>> {
+        if self.storage.get_session(session_id).await.is_none() {
+            return Ok(None);
+        }
+        if let Some(existing) = self
+            .storage
+            .context_imports(session_id)
+            .await
+            .into_iter()
+            .find(|i| i.source == input.source && i.conversation == input.conversation)
+        {
+            return Ok(Some(ContextImportOutcome::AlreadyImported(existing)));
+        }
+        let transcript = render_transcript(&input.messages);
+        if transcript.is_empty() {
+            anyhow::bail!("no messages to import");
+        }
+        let message_count = transcript.lines().count();
+        let prompt = summary_prompt(&input.source, &transcript);
+        let (summary, summarizer) = match tokio::time::timeout(
+            SUMMARY_TIMEOUT,
+            self.providers.complete_cheapest(&prompt, None, None),
+        )
+        .await
+        {
+            Ok(Ok(summary)) if !summary.trim().is_empty() => (summary, "llm"),
+            Ok(Err(err)) => {
+                tracing::warn!("context import summarization failed: {err}");
+                (excerpt_summary(&transcript), "excerpt")
+            }
+            _ => (excerpt_summary(&transcript), "excerpt"),
+        };
+        let mut message = Message::new(
+            MessageRole::User,
+            vec![MessagePart::Text {
+                text: render_context_message(&input.source, message_count, &summary),
+            }],
+        );
+        let import = ContextImport {
+            source: input.source,
+            conversation: input.conversation,
+            message_count,
+            summarizer: summarizer.to_string(),
+            message_id: message.id.clone(),
+            imported_at_ms: now_ms(),
+        };
+        message.metadata = Some(json!({ "contextImport": import }));
+        self.storage.append_message(session_id, message).await?;
+        self.storage
+            .record_context_import(session_id, import.clone())
+            .await?;
+        self.event_bus.publish(EngineEvent::new(
+            "session.context.imported",
+            json!({
+                "sessionID": session_id,
+                "import": import,
+            }),
+        ));
+        Ok(Some(ContextImportOutcome::Imported(import)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn msg(author: &str, text: &str) -> ImportedMessage {
+        ImportedMessage {
+            author: author.to_string(),
+            text: text.to_string(),
+            timestamp: None,
+        }
+    }
+
+    #[test]
+    fn transcript_keeps_newest_messages_and_clips_long_ones() {
+        let mut messages = (0..60)
+            .map(|i| msg("ana", &format!("message {i}")))
+            .collect::<Vec<_>>();
+        messages.push(msg("ben", "   "));
+        messages.push(msg("ben", &"x".repeat(MAX_MESSAGE_CHARS + 10)));
+        let transcript = render_transcript(&messages);
+        let lines = transcript.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), MAX_IMPORT_MESSAGES);
+        assert_eq!(lines[0], "ana: message 11");
+        assert!(lines.last().unwrap().ends_with('…'));
+    }
+
+    #[test]
+    fn excerpt_notes_omitted_messages() {
+        let transcript = (0..10)
+            .map(|i| format!("ana: {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let excerpt = excerpt_summary(&transcript);
+        assert!(excerpt.starts_with("(2 earlier messages omitted)"));
+        assert!(excerpt.ends_with("ana: 9"));
+    }
+}
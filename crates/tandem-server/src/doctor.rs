@@ -0,0 +1,479 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Clock drift against provider `Date` headers that is worth flagging.
+const CLOCK_SKEW_WARN_SECS: i64 = 60;
+/// Drift past which signed provider requests (e.g. SigV4) start failing.
+const CLOCK_SKEW_FAIL_SECS: i64 = 300;
+const PROVIDER_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Skipped,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorCheck {
+    pub id: String,
+    pub category: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<String>,
+}
+
+impl DoctorCheck {
+    fn new(
+        id: impl Into<String>,
+        category: &'static str,
+        status: CheckStatus,
+        detail: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            category,
+            status,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn with_fix(mut self, fix: impl Into<String>) -> Self {
+        self.fix = Some(fix.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DoctorSummary {
+    pub ok: usize,
+    pub skipped: usize,
+    pub warn: usize,
+    pub fail: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorReport {
+    pub generated_at_ms: u64,
+    pub status: CheckStatus,
+    pub summary: DoctorSummary,
+    pub checks: Vec<DoctorCheck>,
+}
+
+impl DoctorReport {
+    pub fn from_checks(generated_at_ms: u64, checks: Vec<DoctorCheck>) -> Self {
+        let mut summary = DoctorSummary::default();
+        for check in &checks {
+            match check.status {
+                CheckStatus::Ok => summary.ok += 1,
+                CheckStatus::Skipped => summary.skipped += 1,
+                CheckStatus::Warn => summary.warn += 1,
+                CheckStatus::Fail => summary.fail += 1,
+            }
+        }
+        let status = match checks.iter().map(|check| check.status).max() {
+            Some(CheckStatus::Fail) => CheckStatus::Fail,
+            Some(CheckStatus::Warn) => CheckStatus::Warn,
+            _ => CheckStatus::Ok,
+        };
+        Self {
+            generated_at_ms,
+            status,
+            summary,
+            checks,
+        }
+    }
+}
+
+/// What the doctor should look at. The HTTP endpoint and the CLI fill this in
+/// from their own view of the environment.
+#[derive(Debug, Clone, Default)]
+pub struct DoctorOptions {
+    pub state_dirs: Vec<PathBuf>,
+    pub memory_db_path: Option<PathBuf>,
+    /// `(provider id, base URL)` pairs to probe.
+    pub provider_endpoints: Vec<(String, String)>,
+    /// `(host, port)` pairs that should be free to bind.
+    pub ports: Vec<(String, u16)>,
+    /// When false, provider connectivity and clock skew are skipped.
+    pub network: bool,
+}
+
+struct BinarySpec {
+    name: &'static str,
+    missing: CheckStatus,
+    fix: &'static str,
+}
+
+const BINARIES: &[BinarySpec] = &[
+    BinarySpec {
+        name: "git",
+        missing: CheckStatus::Fail,
+        fix: "Install git (https://git-scm.com/downloads) and make sure it is on PATH.",
+    },
+    BinarySpec {
+        name: "rg",
+        missing: CheckStatus::Warn,
+        fix: "Install ripgrep (https://github.com/BurntSushi/ripgrep) for faster workspace search.",
+    },
+    BinarySpec {
+        name: "docker",
+        missing: CheckStatus::Warn,
+        fix: "Install Docker if you run containerized MCP servers or sandboxes.",
+    },
+];
+
+const LANGUAGE_SERVERS: &[&str] = &[
+    "rust-analyzer",
+    "typescript-language-server",
+    "pyright-langserver",
+    "gopls",
+];
+
+pub async fn run_doctor(options: DoctorOptions) -> DoctorReport {
+    // PATH lookups, probe files, port binds and the SQLite check are all
+    // blocking, so they run off the async workers.
+    let local = {
+        let options = options.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut checks = Vec::new();
+            let path_var = std::env::var_os("PATH").unwrap_or_default();
+            checks.extend(check_binaries(&path_var));
+            for dir in &options.state_dirs {
+                checks.push(check_writable_dir(dir));
+            }
+            for (host, port) in &options.ports {
+                checks.push(check_port(host, *port));
+            }
+            checks.push(check_memory_db(options.memory_db_path.as_deref()));
+            checks
+        })
+    };
+    let mut checks = local.await.unwrap_or_else(|err| {
+        vec![DoctorCheck::new(
+            "doctor.local",
+            "doctor",
+            CheckStatus::Fail,
+            format!("local checks panicked: {err}"),
+        )]
+    });
+    if options.network {
+        let (provider_checks, skews) = check_providers(&options.provider_endpoints).await;
+        checks.extend(provider_checks);
+        checks.push(check_clock_skew(&skews));
+    } else {
+        checks.push(DoctorCheck::new(
+            "clock.skew",
+            "clock",
+            CheckStatus::Skipped,
+            "network checks disabled",
+        ));
+    }
+    DoctorReport::from_checks(crate::now_ms(), checks)
+}
+
+/// Locate `name` in a `PATH`-style list, honouring `.exe` on Windows.
+pub fn find_binary(name: &str, path_var: &OsStr) -> Option<PathBuf> {
+    std::env::split_paths(path_var).find_map(|dir| {
+        let candidate = dir.join(name);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if cfg!(windows) {
+            let exe = dir.join(format!("{name}.exe"));
+            if exe.is_file() {
+                return Some(exe);
+            }
+        }
+        None
+    })
+}
+
+fn check_binaries(path_var: &OsStr) -> Vec<DoctorCheck> {
+    let mut checks = BINARIES
+        .iter()
+        .map(|spec| {
+            let id = format!("binary.{}", spec.name);
+            match find_binary(spec.name, path_var) {
+                Some(path) => {
+                    DoctorCheck::new(id, "binaries", CheckStatus::Ok, path.to_string_lossy())
+                }
+                None => DoctorCheck::new(
+                    id,
+                    "binaries",
+                    spec.missing,
+                    format!("`{}` not found on PATH", spec.name),
+                )
+                .with_fix(spec.fix),
+            }
+        })
+        .collect::<Vec<_>>();
+    let found = LANGUAGE_SERVERS
+        .iter()
+        .filter(|name| find_binary(name, path_var).is_some())
+        .copied()
+        .collect::<Vec<_>>();
+    checks.push(if found.is_empty() {
+        DoctorCheck::new(
+            "binary.language_servers",
+            "binaries",
+            CheckStatus::Warn,
+            "no language servers found on PATH",
+        )
+        .with_fix(format!(
+            "Install a language server for your workspace ({}).",
+            LANGUAGE_SERVERS.join(", ")
+        ))
+    } else {
+        DoctorCheck::new(
+            "binary.language_servers",
+            "binaries",
+            CheckStatus::Ok,
+            found.join(", "),
+        )
+    });
+    checks
+}
+
+fn check_writable_dir(dir: &Path) -> DoctorCheck {
+    let id = format!("state_dir.{}", dir.to_string_lossy());
+    let probe = dir.join(format!(".tandem-doctor-{}", uuid::Uuid::new_v4().simple()));
+    let result = std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&probe, b"ok"))
+        .and_then(|_| std::fs::remove_file(&probe));
+    match result {
+        Ok(()) => DoctorCheck::new(id, "storage", CheckStatus::Ok, "writable"),
+        Err(err) => DoctorCheck::new(
+            id,
+            "storage",
+            CheckStatus::Fail,
+            format!("not writable: {err}"),
+        )
+        .with_fix("Fix the directory permissions or point TANDEM_STATE_DIR at a writable path."),
+    }
+}
+
+fn check_port(host: &str, port: u16) -> DoctorCheck {
+    let id = format!("port.{port}");
+    match std::net::TcpListener::bind((host, port)) {
+        Ok(_) => DoctorCheck::new(
+            id,
+            "network",
+            CheckStatus::Ok,
+            format!("{host}:{port} is free"),
+        ),
+        Err(err) if err.kind() == std::io::ErrorKind::AddrInUse => DoctorCheck::new(
+            id,
+            "network",
+            CheckStatus::Warn,
+            format!("{host}:{port} is already in use"),
+        )
+        .with_fix("Stop the other process (possibly another engine) or pass --port."),
+        Err(err) => DoctorCheck::new(
+            id,
+            "network",
+            CheckStatus::Fail,
+            format!("cannot bind {host}:{port}: {err}"),
+        )
+        .with_fix("Check the --hostname value and local firewall rules."),
+    }
+}
+
+/// Runs `PRAGMA quick_check` on its own read-only connection rather than
+/// opening the engine's database handle, which would also run schema setup.
+fn check_memory_db(path: Option<&Path>) -> DoctorCheck {
+    let Some(path) = path else {
+        return DoctorCheck::new(
+            "memory.db",
+            "memory",
+            CheckStatus::Skipped,
+            "memory database path not configured",
+        );
+    };
+    if !path.exists() {
+        return DoctorCheck::new(
+            "memory.db",
+            "memory",
+            CheckStatus::Skipped,
+            format!("{} has not been created yet", path.to_string_lossy()),
+        );
+    }
+    let fix =
+        "Restore the memory database from a backup, or move it aside to let the engine rebuild it.";
+    match tandem_memory::db::quick_check_path(path) {
+        Ok(problems) if problems.is_empty() => {
+            DoctorCheck::new("memory.db", "memory", CheckStatus::Ok, "quick_check ok")
+        }
+        Ok(problems) => DoctorCheck::new(
+            "memory.db",
+            "memory",
+            CheckStatus::Fail,
+            format!("quick_check reported: {}", problems.join("; ")),
+        )
+        .with_fix(fix),
+        Err(err) => DoctorCheck::new(
+            "memory.db",
+            "memory",
+            CheckStatus::Fail,
+            format!("quick_check failed: {err}"),
+        )
+        .with_fix(fix),
+    }
+}
+
+/// Probe each endpoint. Any HTTP response counts as reachable; the `Date`
+/// header, when present, is turned into a skew sample (local minus remote).
+async fn check_providers(endpoints: &[(String, String)]) -> (Vec<DoctorCheck>, Vec<i64>) {
+    let client = match reqwest::Client::builder()
+        .timeout(PROVIDER_PROBE_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(err) => {
+            return (
+                vec![DoctorCheck::new(
+                    "provider.client",
+                    "providers",
+                    CheckStatus::Fail,
+                    format!("failed to build HTTP client: {err}"),
+                )],
+                Vec::new(),
+            )
+        }
+    };
+    let mut checks = Vec::new();
+    let mut skews = Vec::new();
+    for (provider_id, base_url) in endpoints {
+        let id = format!("provider.{provider_id}");
+        match client.get(base_url).send().await {
+            Ok(resp) => {
+                let skew = resp
+                    .headers()
+                    .get(reqwest::header::DATE)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| chrono::DateTime::parse_from_rfc2822(value).ok())
+                    .map(|remote| chrono::Utc::now().timestamp() - remote.timestamp());
+                skews.extend(skew);
+                checks.push(DoctorCheck::new(
+                    id,
+                    "providers",
+                    CheckStatus::Ok,
+                    format!("{base_url} reachable (HTTP {})", resp.status().as_u16()),
+                ));
+            }
+            Err(err) => checks.push(
+                DoctorCheck::new(
+                    id,
+                    "providers",
+                    CheckStatus::Fail,
+                    format!("{base_url} unreachable: {err}"),
+                )
+                .with_fix("Check network/proxy settings and the provider base URL in config."),
+            ),
+        }
+    }
+    (checks, skews)
+}
+
+fn check_clock_skew(skews: &[i64]) -> DoctorCheck {
+    let Some(skew) = skews.iter().copied().max_by_key(|skew| skew.abs()) else {
+        return DoctorCheck::new(
+            "clock.skew",
+            "clock",
+            CheckStatus::Skipped,
+            "no provider returned a Date header",
+        );
+    };
+    let detail = format!("local clock differs from provider time by {skew}s");
+    let status = match skew.abs() {
+        s if s > CLOCK_SKEW_FAIL_SECS => CheckStatus::Fail,
+        s if s > CLOCK_SKEW_WARN_SECS => CheckStatus::Warn,
+        _ => CheckStatus::Ok,
+    };
+    let check = DoctorCheck::new("clock.skew", "clock", status, detail);
+    if status == CheckStatus::Ok {
+        check
+    } else {
+        check.with_fix("Enable automatic time synchronisation (NTP) on this machine.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_binary_searches_path_entries() {
+        let dir = std::env::temp_dir().join(format!("tandem-doctor-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        std::fs::write(dir.join("fake-tool"), b"#!/bin/sh\n").expect("write");
+        let path_var = std::env::join_paths([dir.clone()]).expect("path");
+        assert_eq!(
+            find_binary("fake-tool", &path_var),
+            Some(dir.join("fake-tool"))
+        );
+        assert_eq!(find_binary("missing-tool", &path_var), None);
+        let checks = check_binaries(&path_var);
+        let git = checks.iter().find(|c| c.id == "binary.git").expect("git");
+        assert_eq!(git.status, CheckStatus::Fail);
+        assert!(git.fix.is_some());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn port_in_use_is_reported() {
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).expect("bind");
+        let port = listener.local_addr().expect("addr").port();
+        assert_eq!(check_port("127.0.0.1", port).status, CheckStatus::Warn);
+        drop(listener);
+        assert_eq!(check_port("127.0.0.1", port).status, CheckStatus::Ok);
+    }
+
+    #[test]
+    fn clock_skew_thresholds() {
+        assert_eq!(check_clock_skew(&[]).status, CheckStatus::Skipped);
+        assert_eq!(check_clock_skew(&[2, -5]).status, CheckStatus::Ok);
+        assert_eq!(check_clock_skew(&[3, -120]).status, CheckStatus::Warn);
+        assert_eq!(check_clock_skew(&[900]).status, CheckStatus::Fail);
+    }
+
+    #[tokio::test]
+    async fn report_status_is_worst_non_skipped_check() {
+        let dir = std::env::temp_dir().join(format!("tandem-doctor-{}", uuid::Uuid::new_v4()));
+        let report = run_doctor(DoctorOptions {
+            state_dirs: vec![dir.clone()],
+            memory_db_path: Some(dir.join("memory.sqlite")),
+            ..Default::default()
+        })
+        .await;
+        let state_check = report
+            .checks
+            .iter()
+            .find(|c| c.category == "storage")
+            .expect("storage check");
+        assert_eq!(state_check.status, CheckStatus::Ok);
+        let memory = report
+            .checks
+            .iter()
+            .find(|c| c.id == "memory.db")
+            .expect("memory");
+        assert_eq!(memory.status, CheckStatus::Skipped);
+        let worst = report
+            .checks
+            .iter()
+            .map(|c| c.status)
+            .filter(|s| *s != CheckStatus::Skipped)
+            .max()
+            .unwrap_or(CheckStatus::Ok);
+        assert_eq!(report.status, worst);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
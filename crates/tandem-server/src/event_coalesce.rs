@@ -0,0 +1,420 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::Stream;
+use serde_json::Value;
+use tandem_types::EngineEvent;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+
+/// Upper bound on merged delta text before a coalesced event is flushed early.
+const MAX_COALESCED_CHARS: usize = 8_000;
+
+/// Merges consecutive streaming text deltas (`message.part.updated` with a
+/// `delta`) for the same message part into a single event.
+///
+/// Any other event flushes the pending delta first, so relative ordering of
+/// the stream is preserved.
+#[derive(Debug, Default)]
+pub struct EventCoalescer {
+    pending: Option<PendingDelta>,
+}
+
+#[derive(Debug)]
+struct PendingDelta {
+    key: String,
+    event: EngineEvent,
+    count: usize,
+    chars: usize,
+}
+
+impl EventCoalescer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Feed one event and return the events that are ready to emit, in order.
+    pub fn push(&mut self, event: EngineEvent) -> Vec<EngineEvent> {
+        let Some(key) = coalesce_key(&event) else {
+            let mut out = Vec::with_capacity(2);
+            out.extend(self.flush());
+            out.push(event);
+            return out;
+        };
+        let delta_len = delta_text(&event).map(str::len).unwrap_or(0);
+        match self.pending.as_mut() {
+            Some(pending) if pending.key == key => {
+                merge_delta(&mut pending.event, &event);
+                pending.count += 1;
+                pending.chars += delta_len;
+                if pending.chars >= MAX_COALESCED_CHARS {
+                    return self.flush().into_iter().collect();
+                }
+                Vec::new()
+            }
+            _ => {
+                let out = self.flush().into_iter().collect();
+                self.pending = Some(PendingDelta {
+                    key,
+                    event,
+                    count: 1,
+                    chars: delta_len,
+                });
+                out
+            }
+        }
+    }
+
+    /// Emit whatever delta is buffered.
+    pub fn flush(&mut self) -> Option<EngineEvent> {
+        let pending = self.pending.take()?;
+        let mut event = pending.event;
+        if pending.count > 1 {
+            if let Some(props) = event.properties.as_object_mut() {
+                props.insert("coalesced".to_string(), Value::from(pending.count as u64));
+            }
+        }
+        Some(event)
+    }
+}
+
+fn delta_text(event: &EngineEvent) -> Option<&str> {
+    event.properties.get("delta").and_then(|v| v.as_str())
+}
+
+fn coalesce_key(event: &EngineEvent) -> Option<String> {
+    if event.event_type != "message.part.updated" {
+        return None;
+    }
+    delta_text(event)?;
+    let part = event.properties.get("part")?;
+    let part_type = part.get("type").and_then(|v| v.as_str())?;
+    if part_type != "text" && part_type != "reasoning" {
+        return None;
+    }
+    let field = |name: &str| part.get(name).and_then(|v| v.as_str()).unwrap_or("");
+    Some(format!(
+        "{}|{}|{}|{}",
+        field("sessionID"),
+        field("messageID"),
+        field("id"),
+        part_type
+    ))
+}
+
+fn merge_delta(target: &mut EngineEvent, next: &EngineEvent) {
+    let Some(delta) = delta_text(next).map(ToString::to_string) else {
+        return;
+    };
+    let Some(props) = target.properties.as_object_mut() else {
+        return;
+    };
+    if let Some(Value::String(existing)) = props.get_mut("delta") {
+        existing.push_str(&delta);
+    }
+    if let Some(Value::String(text)) = props.get_mut("part").and_then(|part| part.get_mut("text")) {
+        text.push_str(&delta);
+    }
+}
+
+/// Per-consumer hooks for [`coalesced_event_stream_for`].
+pub trait StreamConsumer: Send + Sync + 'static {
+    /// Whether the consumer wants `event`; rejected events are never queued.
+    fn accepts(&self, _event: &EngineEvent) -> bool {
+        true
+    }
+
+    /// How long an event may wait for room in the consumer's buffer before the
+    /// consumer is considered stalled and dropped. `None` waits forever.
+    fn stall_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Called when the bus skipped `skipped` events because this consumer fell
+    /// behind. Returning `true` drops the consumer.
+    fn on_lagged(&self, _skipped: u64) -> bool {
+        false
+    }
+
+    fn on_delivered(&self) {}
+
+    /// Called once when the stream ends, with the reason if it was dropped.
+    fn on_finished(&self, _dropped: Option<StreamDropReason>) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamDropReason {
+    /// The consumer did not read for the stall timeout while its buffer was full.
+    Stalled { waited_ms: u64 },
+    /// The consumer fell too far behind the bus.
+    Lagged { skipped: u64 },
+}
+
+struct PassThrough;
+
+impl StreamConsumer for PassThrough {}
+
+/// Adapt a bus receiver into a stream that coalesces text deltas, flushing at
+/// most every `flush_interval`. A zero interval passes events through as-is.
+pub fn coalesced_event_stream(
+    rx: broadcast::Receiver<EngineEvent>,
+    flush_interval: Duration,
+) -> impl Stream<Item = EngineEvent> {
+    coalesced_event_stream_for(rx, flush_interval, Arc::new(PassThrough))
+}
+
+/// Like [`coalesced_event_stream`], filtered and supervised by `consumer`.
+/// The stream ends when the consumer is dropped for stalling or lagging, and
+/// the consumer is finished as soon as the stream's reader goes away, even if
+/// no event was due for it.
+pub fn coalesced_event_stream_for(
+    mut rx: broadcast::Receiver<EngineEvent>,
+    flush_interval: Duration,
+    consumer: Arc<dyn StreamConsumer>,
+) -> impl Stream<Item = EngineEvent> {
+    let (tx, out) = mpsc::channel::<EngineEvent>(256);
+    tokio::spawn(async move {
+        let mut coalescer = EventCoalescer::new();
+        let mut deadline: Option<tokio::time::Instant> = None;
+        let dropped = loop {
+            let received = match deadline {
+                Some(at) if !flush_interval.is_zero() => {
+                    tokio::select! {
+                        received = rx.recv() => Some(received),
+                        _ = tokio::time::sleep_until(at) => None,
+                        _ = tx.closed() => break None,
+                    }
+                }
+                _ => tokio::select! {
+                    received = rx.recv() => Some(received),
+                    _ = tx.closed() => break None,
+                },
+            };
+            let ready = match received {
+                None => {
+                    deadline = None;
+                    coalescer.flush().into_iter().collect::<Vec<_>>()
+                }
+                Some(Ok(event)) => {
+                    if !consumer.accepts(&event) {
+                        continue;
+                    }
+                    if flush_interval.is_zero() {
+                        vec![event]
+                    } else {
+                        let ready = coalescer.push(event);
+                        if !coalescer.has_pending() {
+                            deadline = None;
+                        } else if deadline.is_none() {
+                            deadline = Some(tokio::time::Instant::now() + flush_interval);
+                        }
+                        ready
+                    }
+                }
+                Some(Err(broadcast::error::RecvError::Lagged(skipped))) => {
+                    if consumer.on_lagged(skipped) {
+                        break Some(StreamDropReason::Lagged { skipped });
+                    }
+                    continue;
+                }
+                Some(Err(broadcast::error::RecvError::Closed)) => {
+                    if let Some(event) = coalescer.flush() {
+                        let _ = tx.send(event).await;
+                    }
+                    break None;
+                }
+            };
+            match deliver(&tx, ready, consumer.as_ref()).await {
+                Delivery::Sent => {}
+                Delivery::Closed => break None,
+                Delivery::Stalled(waited) => {
+                    break Some(StreamDropReason::Stalled {
+                        waited_ms: waited.as_millis() as u64,
+                    })
+                }
+            }
+        };
+        consumer.on_finished(dropped);
+    });
+    ReceiverStream::new(out)
+}
+
+enum Delivery {
+    Sent,
+    Closed,
+    Stalled(Duration),
+}
+
+async fn deliver(
+    tx: &mpsc::Sender<EngineEvent>,
+    events: Vec<EngineEvent>,
+    consumer: &dyn StreamConsumer,
+) -> Delivery {
+    for event in events {
+        let sent = match consumer.stall_timeout() {
+            Some(limit) => match tokio::time::timeout(limit, tx.send(event)).await {
+                Ok(sent) => sent,
+                Err(_) => return Delivery::Stalled(limit),
+            },
+            None => tx.send(event).await,
+        };
+        if sent.is_err() {
+            return Delivery::Closed;
+        }
+        consumer.on_delivered();
+    }
+    Delivery::Sent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio_stream::StreamExt;
+
+    fn text_delta(message_id: &str, delta: &str) -> EngineEvent {
+        EngineEvent::new(
+            "message.part.updated",
+            json!({
+                "part": {
+                    "sessionID": "s-1",
+                    "messageID": message_id,
+                    "type": "text",
+                    "text": delta,
+                },
+                "delta": delta,
+            }),
+        )
+    }
+
+    #[test]
+    fn merges_consecutive_deltas_for_same_part() {
+        let mut coalescer = EventCoalescer::new();
+        assert!(coalescer.push(text_delta("m-1", "Hel")).is_empty());
+        assert!(coalescer.push(text_delta("m-1", "lo")).is_empty());
+        let merged = coalescer.flush().expect("merged");
+        assert_eq!(
+            merged.properties.get("delta").and_then(|v| v.as_str()),
+            Some("Hello")
+        );
+        assert_eq!(
+            merged
+                .properties
+                .get("part")
+                .and_then(|p| p.get("text"))
+                .and_then(|v| v.as_str()),
+            Some("Hello")
+        );
+        assert_eq!(
+            merged.properties.get("coalesced").and_then(|v| v.as_u64()),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn non_delta_event_flushes_pending_in_order() {
+        let mut coalescer = EventCoalescer::new();
+        let _ = coalescer.push(text_delta("m-1", "a"));
+        let _ = coalescer.push(text_delta("m-1", "b"));
+        let out = coalescer.push(EngineEvent::new(
+            "session.run.finished",
+            json!({"sessionID": "s-1"}),
+        ));
+        assert_eq!(out.len(), 2);
+        assert_eq!(
+            out[0].properties.get("delta").and_then(|v| v.as_str()),
+            Some("ab")
+        );
+        assert_eq!(out[1].event_type, "session.run.finished");
+        assert!(!coalescer.has_pending());
+    }
+
+    #[test]
+    fn different_parts_are_not_merged() {
+        let mut coalescer = EventCoalescer::new();
+        let _ = coalescer.push(text_delta("m-1", "a"));
+        let out = coalescer.push(text_delta("m-2", "b"));
+        assert_eq!(out.len(), 1);
+        assert_eq!(
+            out[0].properties.get("delta").and_then(|v| v.as_str()),
+            Some("a")
+        );
+        assert!(out[0].properties.get("coalesced").is_none());
+    }
+
+    #[tokio::test]
+    async fn stream_flushes_after_interval() {
+        let (tx, rx) = broadcast::channel(16);
+        let mut stream = Box::pin(coalesced_event_stream(rx, Duration::from_millis(20)));
+        for chunk in ["a", "b", "c"] {
+            tx.send(text_delta("m-1", chunk)).expect("send");
+        }
+        let event = tokio::time::timeout(Duration::from_secs(2), stream.next())
+            .await
+            .expect("flush timeout")
+            .expect("event");
+        assert_eq!(
+            event.properties.get("delta").and_then(|v| v.as_str()),
+            Some("abc")
+        );
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        finished: std::sync::Mutex<Option<Option<StreamDropReason>>>,
+    }
+
+    impl StreamConsumer for Recorder {
+        fn accepts(&self, event: &EngineEvent) -> bool {
+            event.event_type != "ignored"
+        }
+
+        fn stall_timeout(&self) -> Option<Duration> {
+            Some(Duration::from_millis(30))
+        }
+
+        fn on_finished(&self, dropped: Option<StreamDropReason>) {
+            *self.finished.lock().unwrap() = Some(dropped);
+        }
+    }
+
+    #[tokio::test]
+    async fn stalled_consumer_is_dropped_and_reported() {
+        let (tx, rx) = broadcast::channel(1024);
+        let recorder = Arc::new(Recorder::default());
+        let stream = coalesced_event_stream_for(rx, Duration::ZERO, recorder.clone());
+        tx.send(EngineEvent::new("ignored", json!({})))
+            .expect("send");
+        for _ in 0..300 {
+            tx.send(EngineEvent::new("tick", json!({}))).expect("send");
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(matches!(
+            *recorder.finished.lock().unwrap(),
+            Some(Some(StreamDropReason::Stalled { .. }))
+        ));
+        let received = stream.collect::<Vec<_>>().await;
+        assert_eq!(received.len(), 256);
+        assert!(received.iter().all(|e| e.event_type == "tick"));
+    }
+
+    #[tokio::test]
+    async fn disconnect_finishes_an_idle_consumer() {
+        let (_tx, rx) = broadcast::channel::<EngineEvent>(16);
+        let recorder = Arc::new(Recorder::default());
+        let stream = coalesced_event_stream_for(rx, Duration::ZERO, recorder.clone());
+        drop(stream);
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while recorder.finished.lock().unwrap().is_none() {
+                tokio::time::sleep(Duration::from_millis(5)).await;
+            }
+        })
+        .await
+        .expect("finished after disconnect");
+        assert_eq!(*recorder.finished.lock().unwrap(), Some(None));
+    }
+}
@@ -0,0 +1,767 @@
+//! Event rules: config-defined "when this engine event happens, do that".
+//!
+//! Rules come from the `event_rules` config block. A rule matches on event
+//! type (`routine.run.failed`, or a `prefix.*` pattern) and on conditions
+//! evaluated against `{"type": ..., "properties": ...}` with a small JSONPath
+//! subset (`$.properties.status`, `$.properties.tags[*]`). Matching rules run
+//! their actions in order:
+//!
+//! - `notify_channel`: publish `rule.notify` for the channel dispatcher to send
+//!   `text` to a `channel://{channel}/{recipient}` target.
+//! - `run_routine`: queue a run of `routine_id` (subject to its approval policy).
+//! - `write_resource`: put `value` into the shared resource store at `key`.
+//! - `webhook`: POST `{rule, event}` as JSON to `url`.
+//!
+//! String fields of actions may reference the event with `{{ path }}`, e.g.
+//! `"Run {{ $.properties.runID }} failed"`. Every firing is published as
+//! `rule.fired`; `rule.*` events never trigger rules, so rules cannot loop.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tandem_types::EngineEvent;
+use tokio::sync::Mutex;
+
+use crate::{
+    evaluate_routine_execution_policy, now_ms, AppState, RoutineExecutionDecision,
+    RoutineHistoryEvent, RoutineRunStatus,
+};
+
+/// How long the parsed rule set is reused before config is read again.
+const RULES_REFRESH_MS: u64 = 5_000;
+const MAX_RECENT_FIRINGS: usize = 100;
+const DEFAULT_WEBHOOK_TIMEOUT_MS: u64 = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventRule {
+    pub id: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Event types to match; a trailing `*` matches by prefix.
+    pub events: Vec<String>,
+    /// All conditions must hold.
+    #[serde(default)]
+    pub conditions: Vec<RuleCondition>,
+    pub actions: Vec<RuleAction>,
+    /// Minimum time between two firings of this rule.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooldown_ms: Option<u64>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConditionOp {
+    #[default]
+    Eq,
+    Ne,
+    /// Substring of a string, or element of an array.
+    Contains,
+    /// Equal to one of the values in the `value` array.
+    In,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    /// String matches the regex in `value`.
+    Matches,
+    Exists,
+    Missing,
+}
+
+/// `path` selects values from the event; the condition holds when any
+/// selected value satisfies `op` (`missing`: when nothing is selected).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleCondition {
+    pub path: String,
+    #[serde(default)]
+    pub op: ConditionOp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleAction {
+    NotifyChannel {
+        /// `channel://{channel}/{recipient}`, with the same query options as
+        /// routine output targets.
+        target: String,
+        text: String,
+    },
+    RunRoutine {
+        routine_id: String,
+    },
+    WriteResource {
+        key: String,
+        value: Value,
+    },
+    Webhook {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout_ms: Option<u64>,
+    },
+}
+
+impl RuleAction {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::NotifyChannel { .. } => "notify_channel",
+            Self::RunRoutine { .. } => "run_routine",
+            Self::WriteResource { .. } => "write_resource",
+            Self::Webhook { .. } => "webhook",
+        }
+    }
+
+    /// The action with `{{ path }}` references filled in from `event`.
+    fn rendered(&self, event: &Value) -> Self {
+        match self {
+            Self::NotifyChannel { target, text } => Self::NotifyChannel {
+                target: render_template(target, event),
+                text: render_template(text, event),
+            },
+            Self::RunRoutine { routine_id } => Self::RunRoutine {
+                routine_id: render_template(routine_id, event),
+            },
+            Self::WriteResource { key, value } => Self::WriteResource {
+                key: render_template(key, event),
+                value: render_value(value, event),
+            },
+            Self::Webhook {
+                url,
+                headers,
+                timeout_ms,
+            } => Self::Webhook {
+                url: render_template(url, event),
+                headers: headers.clone(),
+                timeout_ms: *timeout_ms,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+    Wildcard,
+}
+
+/// Parse the supported JSONPath subset: an optional `$`, `.key`, `.*`,
+/// `['key']`, `[0]` and `[*]`.
+fn parse_path(path: &str) -> Result<Vec<PathSegment>, String> {
+    let path = path.trim();
+    let mut rest = path.strip_prefix('$').unwrap_or(path);
+    let mut segments = Vec::new();
+    if !rest.is_empty() && !rest.starts_with(['.', '[']) {
+        let (key, tail) = split_key(rest);
+        segments.push(PathSegment::Key(key.to_string()));
+        rest = tail;
+    }
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix('.') {
+            let (key, tail) = split_key(tail);
+            match key {
+                "" => return Err(format!("empty key in path `{path}`")),
+                "*" => segments.push(PathSegment::Wildcard),
+                key => segments.push(PathSegment::Key(key.to_string())),
+            }
+            rest = tail;
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let end = tail
+                .find(']')
+                .ok_or_else(|| format!("unclosed `[` in path `{path}`"))?;
+            let inner = tail[..end].trim();
+            let quoted = inner
+                .strip_prefix('\'')
+                .and_then(|v| v.strip_suffix('\''))
+                .or_else(|| inner.strip_prefix('"').and_then(|v| v.strip_suffix('"')));
+            segments.push(match (inner, quoted) {
+                (_, Some(key)) => PathSegment::Key(key.to_string()),
+                ("*", None) => PathSegment::Wildcard,
+                (index, None) => PathSegment::Index(
+                    index
+                        .parse()
+                        .map_err(|_| format!("invalid index `{index}` in path `{path}`"))?,
+                ),
+            });
+            rest = &tail[end + 1..];
+        } else {
+            return Err(format!("unexpected `{rest}` in path `{path}`"));
+        }
+    }
+    Ok(segments)
+}
+
+fn split_key(raw: &str) -> (&str, &str) {
+    let end = raw.find(['.', '[']).unwrap_or(raw.len());
+    (&raw[..end], &raw[end..])
+}
+
+/// Values selected by `path`; an invalid path selects nothing.
+pub fn select_path<'a>(root: &'a Value, path: &str) -> Vec<&'a Value> {
+    let Ok(segments) = parse_path(path) else {
+        return Vec::new();
+    };
+    let mut current = vec![root];
+    for segment in &segments {
+        current = current
+            .into_iter()
+            .flat_map(|value| -> Vec<&'a Value> {
+                match (segment, value) {
+                    (PathSegment::Key(key), Value::Object(map)) => {
+                        map.get(key).into_iter().collect()
+                    }
+                    (PathSegment::Index(idx), Value::Array(items)) => {
+                        items.get(*idx).into_iter().collect()
+                    }
+                    (PathSegment::Wildcard, Value::Array(items)) => items.iter().collect(),
+                    (PathSegment::Wildcard, Value::Object(map)) => map.values().collect(),
+                    _ => Vec::new(),
+                }
+            })
+            .collect();
+    }
+    current
+}
+
+fn compare_numbers(actual: &Value, expected: Option<&Value>) -> Option<std::cmp::Ordering> {
+    let actual = actual
+        .as_f64()
+        .or_else(|| actual.as_str().and_then(|s| s.parse().ok()))?;
+    let expected = expected?.as_f64()?;
+    actual.partial_cmp(&expected)
+}
+
+impl RuleCondition {
+    pub fn holds(&self, event: &Value) -> bool {
+        let selected = select_path(event, &self.path);
+        let expected = self.value.as_ref();
+        match self.op {
+            ConditionOp::Exists => !selected.is_empty(),
+            ConditionOp::Missing => selected.is_empty(),
+            ConditionOp::Ne => selected.iter().all(|v| Some(*v) != expected),
+            op => selected.iter().any(|actual| match op {
+                ConditionOp::Eq => Some(*actual) == expected,
+                ConditionOp::Contains => match (actual, expected) {
+                    (Value::String(s), Some(Value::String(needle))) => s.contains(needle.as_str()),
+                    (Value::Array(items), Some(needle)) => items.contains(needle),
+                    _ => false,
+                },
+                ConditionOp::In => expected
+                    .and_then(|v| v.as_array())
+                    .is_some_and(|options| options.contains(actual)),
+                ConditionOp::Gt => compare_numbers(actual, expected).is_some_and(|o| o.is_gt()),
+                ConditionOp::Gte => compare_numbers(actual, expected).is_some_and(|o| o.is_ge()),
+                ConditionOp::Lt => compare_numbers(actual, expected).is_some_and(|o| o.is_lt()),
+                ConditionOp::Lte => compare_numbers(actual, expected).is_some_and(|o| o.is_le()),
+                ConditionOp::Matches => {
+                    match (actual.as_str(), expected.and_then(|v| v.as_str())) {
+                        (Some(text), Some(pattern)) => {
+                            regex::Regex::new(pattern).is_ok_and(|re| re.is_match(text))
+                        }
+                        _ => false,
+                    }
+                }
+                ConditionOp::Ne | ConditionOp::Exists | ConditionOp::Missing => unreachable!(),
+            }),
+        }
+    }
+}
+
+/// Parse the `event_rules` config block, keeping the valid rules and a
+/// message for each rule that failed to parse or validate.
+pub fn parse_rules(raw: &Value) -> (Vec<EventRule>, Vec<String>) {
+    let entries = match raw {
+        Value::Null => return (Vec::new(), Vec::new()),
+        Value::Array(entries) => entries,
+        _ => return (Vec::new(), vec!["`event_rules` must be a list".to_string()]),
+    };
+    let mut rules = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, entry) in entries.iter().enumerate() {
+        match serde_json::from_value::<EventRule>(entry.clone())
+            .map_err(|err| format!("event_rules[{idx}]: {err}"))
+            .and_then(|rule| rule.validate().map(|()| rule))
+        {
+            Ok(rule) if rules.iter().any(|r: &EventRule| r.id == rule.id) => {
+                errors.push(format!(
+                    "event_rules[{idx}]: duplicate rule id `{}`",
+                    rule.id
+                ));
+            }
+            Ok(rule) => rules.push(rule),
+            Err(err) => errors.push(err),
+        }
+    }
+    (rules, errors)
+}
+
+impl EventRule {
+    /// Problems that would make the rule never fire or fail at runtime.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id.trim().is_empty() {
+            return Err("rule needs an id".to_string());
+        }
+        if self.events.iter().all(|e| e.trim().is_empty()) {
+            return Err(format!("rule `{}` matches no events", self.id));
+        }
+        if self.actions.is_empty() {
+            return Err(format!("rule `{}` has no actions", self.id));
+        }
+        for condition in &self.conditions {
+            parse_path(&condition.path).map_err(|err| format!("rule `{}`: {err}", self.id))?;
+            if condition.op == ConditionOp::Matches {
+                let pattern = condition.value.as_ref().and_then(|v| v.as_str());
+                pattern
+                    .ok_or_else(|| "`matches` needs a string pattern".to_string())
+                    .and_then(|p| regex::Regex::new(p).map_err(|err| err.to_string()))
+                    .map_err(|err| format!("rule `{}`: {err}", self.id))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn matches_type(&self, event_type: &str) -> bool {
+        self.events
+            .iter()
+            .map(|e| e.trim())
+            .any(|pattern| match pattern.strip_suffix('*') {
+                Some(prefix) => event_type.starts_with(prefix),
+                None => pattern == event_type,
+            })
+    }
+
+    pub fn matches(&self, event: &EngineEvent) -> bool {
+        if !self.enabled || !self.matches_type(&event.event_type) {
+            return false;
+        }
+        let document = event_document(event);
+        self.conditions
+            .iter()
+            .all(|condition| condition.holds(&document))
+    }
+}
+
+/// The document conditions and templates are evaluated against.
+pub fn event_document(event: &EngineEvent) -> Value {
+    json!({ "type": event.event_type, "properties": event.properties })
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Replace `{{ path }}` with the first value `path` selects (empty if none).
+pub fn render_template(template: &str, event: &Value) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let path = rest[start + 2..start + 2 + len].trim();
+        if let Some(value) = select_path(event, path).first() {
+            out.push_str(&value_text(value));
+        }
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn render_value(value: &Value, event: &Value) -> Value {
+    match value {
+        Value::String(s) => Value::String(render_template(s, event)),
+        Value::Array(items) => Value::Array(items.iter().map(|v| render_value(v, event)).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), render_value(v, event)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleActionOutcome {
+    pub action: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleFiring {
+    pub rule_id: String,
+    pub event_type: String,
+    pub fired_at_ms: u64,
+    pub actions: Vec<RuleActionOutcome>,
+}
+
+#[derive(Default)]
+struct RulesInner {
+    rules: Vec<EventRule>,
+    loaded_at_ms: Option<u64>,
+    last_fired_ms: HashMap<String, u64>,
+    recent: VecDeque<RuleFiring>,
+}
+
+#[derive(Clone, Default)]
+pub struct EventRulesEngine {
+    inner: Arc<Mutex<RulesInner>>,
+}
+
+impl EventRulesEngine {
+    /// Valid rules from config, re-read at most every few seconds. Invalid
+    /// rules are logged and left out.
+    async fn rules(&self, state: &AppState, now: u64) -> Vec<EventRule> {
+        let stale = {
+            let inner = self.inner.lock().await;
+            inner
+                .loaded_at_ms
+                .is_none_or(|at| now.saturating_sub(at) >= RULES_REFRESH_MS)
+        };
+        if stale {
+            let (rules, errors) = parse_rules(&state.event_rules_config().await);
+            for err in errors {
+                tracing::warn!("ignoring event rule: {err}");
+            }
+            let mut inner = self.inner.lock().await;
+            inner.rules = rules;
+            inner.loaded_at_ms = Some(now);
+        }
+        self.inner.lock().await.rules.clone()
+    }
+
+    /// Claim a firing slot for `rule`, honouring its cooldown.
+    async fn try_fire(&self, rule: &EventRule, now: u64) -> bool {
+        let mut inner = self.inner.lock().await;
+        let cooldown = rule.cooldown_ms.unwrap_or(0);
+        if let Some(last) = inner.last_fired_ms.get(&rule.id) {
+            if cooldown > 0 && now.saturating_sub(*last) < cooldown {
+                return false;
+            }
+        }
+        inner.last_fired_ms.insert(rule.id.clone(), now);
+        true
+    }
+
+    async fn record(&self, firing: RuleFiring) {
+        let mut inner = self.inner.lock().await;
+        if inner.recent.len() >= MAX_RECENT_FIRINGS {
+            inner.recent.pop_front();
+        }
+        inner.recent.push_back(firing);
+    }
+
+    /// Most recent firings, newest first.
+    pub async fn recent_firings(&self, limit: usize) -> Vec<RuleFiring> {
+        let inner = self.inner.lock().await;
+        inner.recent.iter().rev().take(limit).cloned().collect()
+    }
+}
+
+async fn queue_routine_run(
+    state: &AppState,
+    routine_id: &str,
+    rule_id: &str,
+) -> Result<String, String> {
+    let routine = state
+        .get_routine(routine_id)
+        .await
+        .ok_or_else(|| format!("routine `{routine_id}` not found"))?;
+    let trigger_type = "rule";
+    let detail = Some(format!("triggered by event rule `{rule_id}`"));
+    let (status, history_status, reason) =
+        match evaluate_routine_execution_policy(&routine, trigger_type) {
+            RoutineExecutionDecision::Allowed => (RoutineRunStatus::Queued, "queued", None),
+            RoutineExecutionDecision::RequiresApproval { reason } => (
+                RoutineRunStatus::PendingApproval,
+                "pending_approval",
+                Some(reason),
+            ),
+            RoutineExecutionDecision::Blocked { reason } => return Err(reason),
+        };
+    let now = now_ms();
+    if status == RoutineRunStatus::Queued {
+        let _ = state.mark_routine_fired(&routine.routine_id, now).await;
+    }
+    let run = state
+        .create_routine_run(
+            &routine,
+            trigger_type,
+            1,
+            status,
+            reason.clone().or_else(|| detail.clone()),
+        )
+        .await
+        .map_err(|err| err.to_string())?;
+    state
+        .append_routine_history(RoutineHistoryEvent {
+            routine_id: routine.routine_id.clone(),
+            trigger_type: trigger_type.to_string(),
+            run_count: 1,
+            fired_at_ms: now,
+            status: history_status.to_string(),
+            detail: reason.clone().or(detail),
+        })
+        .await;
+    if let Some(reason) = reason {
+        state.event_bus.publish(EngineEvent::new(
+            "routine.approval_required",
+            json!({
+                "routineID": routine.routine_id,
+                "runID": run.run_id,
+                "runCount": 1,
+                "triggerType": trigger_type,
+                "reason": reason,
+            }),
+        ));
+        state.notify_routine_approvers(&run, false);
+    } else {
+        state.event_bus.publish(EngineEvent::new(
+            "routine.fired",
+            json!({
+                "routineID": routine.routine_id,
+                "runID": run.run_id,
+                "runCount": 1,
+                "triggerType": trigger_type,
+                "firedAtMs": now,
+            }),
+        ));
+    }
+    state.event_bus.publish(EngineEvent::new(
+        "routine.run.created",
+        json!({ "run": run }),
+    ));
+    Ok(run.run_id)
+}
+
+async fn execute_action(
+    state: &AppState,
+    rule: &EventRule,
+    action: &RuleAction,
+    event: &EngineEvent,
+) -> Result<String, String> {
+    match action {
+        RuleAction::NotifyChannel { target, text } => {
+            if !target.starts_with("channel://") {
+                return Err(format!("`{target}` is not a channel:// target"));
+            }
+            state.event_bus.publish(EngineEvent::new(
+                "rule.notify",
+                json!({
+                    "ruleID": rule.id,
+                    "eventType": event.event_type,
+                    "target": target,
+                    "text": text,
+                }),
+            ));
+            Ok(format!("notified {target}"))
+        }
+        RuleAction::RunRoutine { routine_id } => queue_routine_run(state, routine_id, &rule.id)
+            .await
+            .map(|run_id| format!("queued routine run {run_id}")),
+        RuleAction::WriteResource { key, value } => state
+            .put_shared_resource(
+                key.clone(),
+                value.clone(),
+                None,
+                format!("rule:{}", rule.id),
+                None,
+            )
+            .await
+            .map(|record| format!("wrote {} rev {}", record.key, record.rev))
+            .map_err(|err| format!("{err:?}")),
+        RuleAction::Webhook {
+            url,
+            headers,
+            timeout_ms,
+        } => {
+            let mut request = reqwest::Client::new()
+                .post(url)
+                .timeout(Duration::from_millis(
+                    timeout_ms.unwrap_or(DEFAULT_WEBHOOK_TIMEOUT_MS).max(1),
+                ))
+                .json(&json!({ "rule": rule.id, "event": event }));
+            for (name, value) in headers {
+                request = request.header(name, value);
+            }
+            match request.send().await {
+                Ok(resp) if resp.status().is_success() => Ok(format!("HTTP {}", resp.status())),
+                Ok(resp) => Err(format!("HTTP {}", resp.status())),
+                Err(err) => Err(format!("request failed: {err}")),
+            }
+        }
+    }
+}
+
+/// Run `rule`'s actions for `event` in order and publish `rule.fired`.
+async fn fire_rule(state: AppState, rule: EventRule, event: EngineEvent) {
+    let document = event_document(&event);
+    let mut outcomes = Vec::with_capacity(rule.actions.len());
+    for action in &rule.actions {
+        let action = action.rendered(&document);
+        let result = execute_action(&state, &rule, &action, &event).await;
+        if let Err(err) = &result {
+            tracing::warn!("event rule `{}` {} failed: {err}", rule.id, action.kind());
+        }
+        outcomes.push(RuleActionOutcome {
+            action: action.kind().to_string(),
+            ok: result.is_ok(),
+            detail: result.unwrap_or_else(|err| err),
+        });
+    }
+    let firing = RuleFiring {
+        rule_id: rule.id.clone(),
+        event_type: event.event_type.clone(),
+        fired_at_ms: now_ms(),
+        actions: outcomes,
+    };
+    state
+        .event_bus
+        .publish(EngineEvent::new("rule.fired", json!(firing)));
+    state.event_rules.record(firing).await;
+}
+
+/// Rules that would fire for `event`, with their actions rendered but not run.
+pub fn dry_run(rules: &[EventRule], event: &EngineEvent) -> Value {
+    let document = event_document(event);
+    let matched = rules
+        .iter()
+        .filter(|rule| rule.matches(event))
+        .map(|rule| {
+            json!({
+                "ruleID": rule.id,
+                "actions": rule
+                    .actions
+                    .iter()
+                    .map(|action| action.rendered(&document))
+                    .collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({ "matched": matched })
+}
+
+/// Evaluate configured rules against every engine event.
+pub async fn run_event_rules(state: AppState) {
+    let mut rx = state.event_bus.subscribe();
+    loop {
+        match rx.recv().await {
+            Ok(event) if !event.event_type.starts_with("rule.") => {
+                let now = now_ms();
+                for rule in state.event_rules.rules(&state, now).await {
+                    if rule.matches(&event) && state.event_rules.try_fire(&rule, now).await {
+                        tokio::spawn(fire_rule(state.clone(), rule, event.clone()));
+                    }
+                }
+            }
+            Ok(_) => {}
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(value: Value) -> EventRule {
+        serde_json::from_value(value).expect("rule")
+    }
+
+    #[test]
+    fn paths_select_nested_values_and_wildcards() {
+        let doc = json!({"type": "t", "properties": {"run": {"tags": ["a", "b"]}, "odd key": 1}});
+        assert_eq!(
+            select_path(&doc, "$.properties.run.tags[1]"),
+            vec![&json!("b")]
+        );
+        assert_eq!(select_path(&doc, "properties.run.tags[*]").len(), 2);
+        assert_eq!(
+            select_path(&doc, "$['properties']['odd key']"),
+            vec![&json!(1)]
+        );
+        assert!(select_path(&doc, "$.properties.missing").is_empty());
+        assert!(parse_path("$.properties[").is_err());
+    }
+
+    #[test]
+    fn rules_match_event_type_patterns_and_conditions() {
+        let failed = rule(json!({
+            "id": "release-failures",
+            "events": ["routine.run.*"],
+            "conditions": [
+                {"path": "$.type", "op": "eq", "value": "routine.run.failed"},
+                {"path": "$.properties.routineID", "op": "matches", "value": "^release-"},
+                {"path": "$.properties.attempt", "op": "gte", "value": 2}
+            ],
+            "actions": [{"type": "notify_channel", "target": "channel://slack/C1", "text": "{{ $.properties.routineID }} failed: {{ $.properties.reason }}"}]
+        }));
+        assert!(failed.validate().is_ok());
+        let event = EngineEvent::new(
+            "routine.run.failed",
+            json!({"routineID": "release-nightly", "attempt": 3, "reason": "timeout"}),
+        );
+        assert!(failed.matches(&event));
+        assert!(!failed.matches(&EngineEvent::new(
+            "routine.run.failed",
+            json!({"routineID": "docs", "attempt": 3}),
+        )));
+        assert!(!failed.matches(&EngineEvent::new(
+            "session.run.finished",
+            json!({"routineID": "release-nightly", "attempt": 3}),
+        )));
+
+        let preview = dry_run(&[failed], &event);
+        assert_eq!(
+            preview["matched"][0]["actions"][0]["text"],
+            json!("release-nightly failed: timeout")
+        );
+    }
+
+    #[test]
+    fn invalid_rules_are_reported() {
+        let bad = rule(json!({
+            "id": "bad",
+            "events": ["x"],
+            "conditions": [{"path": "$.properties.name", "op": "matches", "value": "("}],
+            "actions": [{"type": "run_routine", "routine_id": "r"}]
+        }));
+        assert!(bad.validate().is_err());
+        let (rules, errors) = parse_rules(&json!([
+            {"id": "ok", "events": ["x"], "actions": [{"type": "run_routine", "routine_id": "r"}]},
+            {"id": "typo", "events": ["x"], "actions": [{"type": "email"}]},
+            {"id": "ok", "events": ["y"], "actions": [{"type": "run_routine", "routine_id": "r"}]}
+        ]));
+        assert_eq!(rules.len(), 1);
+        assert_eq!(errors.len(), 2);
+        let write = rule(json!({
+            "id": "w",
+            "events": ["x"],
+            "actions": [{"type": "write_resource", "key": "project/last/{{ $.type }}", "value": {"at": "{{ $.properties.at }}", "n": 1}}]
+        }));
+        let rendered = write.actions[0].rendered(&json!({"type": "x", "properties": {"at": 5}}));
+        let RuleAction::WriteResource { key, value } = rendered else {
+            panic!("write_resource");
+        };
+        assert_eq!(key, "project/last/x");
+        assert_eq!(value, json!({"at": "5", "n": 1}));
+    }
+}
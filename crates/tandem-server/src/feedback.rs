@@ -0,0 +1,367 @@
+//! Thumbs-up/down and free-text feedback on assistant messages.
+//!
+//! Each record keeps the session, model and tool context of the rated
+//! message so the export can be used as a prompt/agent evaluation dataset.
+//! Feedback is persisted to `message_feedback.json`.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tandem_types::{Message, MessagePart, Session};
+use tokio::fs;
+use tokio::sync::RwLock;
+
+const EXCERPT_CHARS: usize = 280;
+pub const MAX_FEEDBACK_COMMENT_CHARS: usize = 4_000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedbackRating {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FeedbackContext {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_id: Option<String>,
+    /// Tools invoked while producing the message, in call order.
+    #[serde(default)]
+    pub tools: Vec<String>,
+    #[serde(default)]
+    pub message_excerpt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MessageFeedbackRecord {
+    pub feedback_id: String,
+    pub session_id: String,
+    pub message_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<FeedbackRating>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// `web`, `api`, or `channel:<name>` for chat reactions.
+    pub source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submitted_by: Option<String>,
+    pub created_at_ms: u64,
+    pub updated_at_ms: u64,
+    pub context: FeedbackContext,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FeedbackInput {
+    pub rating: Option<FeedbackRating>,
+    pub comment: Option<String>,
+    pub source: Option<String>,
+    pub submitted_by: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FeedbackFilter {
+    pub session_id: Option<String>,
+    pub rating: Option<FeedbackRating>,
+    pub since_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct FeedbackSummary {
+    pub total: usize,
+    pub up: usize,
+    pub down: usize,
+    pub with_comment: usize,
+}
+
+impl FeedbackSummary {
+    pub fn of(records: &[MessageFeedbackRecord]) -> Self {
+        Self {
+            total: records.len(),
+            up: records
+                .iter()
+                .filter(|r| r.rating == Some(FeedbackRating::Up))
+                .count(),
+            down: records
+                .iter()
+                .filter(|r| r.rating == Some(FeedbackRating::Down))
+                .count(),
+            with_comment: records.iter().filter(|r| r.comment.is_some()).count(),
+        }
+    }
+}
+
+/// Capture the context of the rated message from its session.
+pub fn feedback_context(session: &Session, message: &Message) -> FeedbackContext {
+    let mut tools = Vec::new();
+    let mut text = String::new();
+    for part in &message.parts {
+        match part {
+            MessagePart::Text { text: chunk } => text.push_str(chunk),
+            MessagePart::ToolInvocation { tool, .. } => tools.push(tool.clone()),
+            MessagePart::Reasoning { .. } => {}
+        }
+    }
+    FeedbackContext {
+        provider_id: session
+            .model
+            .as_ref()
+            .map(|m| m.provider_id.clone())
+            .or_else(|| session.provider.clone()),
+        model_id: session.model.as_ref().map(|m| m.model_id.clone()),
+        tools,
+        message_excerpt: text.trim().chars().take(EXCERPT_CHARS).collect(),
+        message_metadata: message.metadata.clone(),
+    }
+}
+
+#[derive(Clone)]
+pub struct FeedbackStore {
+    path: PathBuf,
+    records: Arc<RwLock<Vec<MessageFeedbackRecord>>>,
+    /// Why the file on disk could not be parsed. While set, nothing is
+    /// written so the existing records are not replaced by an empty store.
+    load_error: Arc<RwLock<Option<String>>>,
+}
+
+impl FeedbackStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            records: Arc::new(RwLock::new(Vec::new())),
+            load_error: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.path = path;
+    }
+
+    pub async fn load(&self) -> anyhow::Result<()> {
+        if !self.path.exists() {
+            return Ok(());
+        }
+        let raw = fs::read_to_string(&self.path).await?;
+        match serde_json::from_str::<Vec<MessageFeedbackRecord>>(&raw) {
+            Ok(parsed) => {
+                *self.records.write().await = parsed;
+                *self.load_error.write().await = None;
+                Ok(())
+            }
+            Err(err) => {
+                *self.load_error.write().await = Some(err.to_string());
+                anyhow::bail!("invalid feedback file {}: {err}", self.path.display())
+            }
+        }
+    }
+
+    pub async fn persist(&self) -> anyhow::Result<()> {
+        if let Some(err) = self.load_error.read().await.as_deref() {
+            anyhow::bail!(
+                "refusing to overwrite unparseable feedback file {}: {err}",
+                self.path.display()
+            );
+        }
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let payload = {
+            let guard = self.records.read().await;
+            serde_json::to_string_pretty(&*guard)?
+        };
+        fs::write(&self.path, payload).await?;
+        Ok(())
+    }
+
+    /// Record feedback. A submitter rating the same message again from the
+    /// same source replaces their earlier feedback instead of stacking up.
+    pub async fn submit(
+        &self,
+        session_id: &str,
+        message_id: &str,
+        input: FeedbackInput,
+        context: FeedbackContext,
+        now_ms: u64,
+    ) -> anyhow::Result<MessageFeedbackRecord> {
+        let source = input
+            .source
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "api".to_string());
+        let comment = input
+            .comment
+            .map(|c| {
+                c.trim()
+                    .chars()
+                    .take(MAX_FEEDBACK_COMMENT_CHARS)
+                    .collect::<String>()
+            })
+            .filter(|c| !c.is_empty());
+        let submitted_by = input
+            .submitted_by
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty());
+        let record = {
+            let mut guard = self.records.write().await;
+            let existing = submitted_by.as_ref().and_then(|who| {
+                guard.iter_mut().find(|r| {
+                    r.message_id == message_id
+                        && r.source == source
+                        && r.submitted_by.as_ref() == Some(who)
+                })
+            });
+            match existing {
+                Some(row) => {
+                    if input.rating.is_some() {
+                        row.rating = input.rating;
+                    }
+                    if comment.is_some() {
+                        row.comment = comment;
+                    }
+                    row.updated_at_ms = now_ms;
+                    row.context = context;
+                    row.clone()
+                }
+                None => {
+                    let row = MessageFeedbackRecord {
+                        feedback_id: format!("feedback-{}", uuid::Uuid::new_v4()),
+                        session_id: session_id.to_string(),
+                        message_id: message_id.to_string(),
+                        rating: input.rating,
+                        comment,
+                        source,
+                        submitted_by,
+                        created_at_ms: now_ms,
+                        updated_at_ms: now_ms,
+                        context,
+                    };
+                    guard.push(row.clone());
+                    row
+                }
+            }
+        };
+        self.persist().await?;
+        Ok(record)
+    }
+
+    pub async fn list(&self, filter: &FeedbackFilter) -> Vec<MessageFeedbackRecord> {
+        self.records
+            .read()
+            .await
+            .iter()
+            .filter(|r| {
+                filter
+                    .session_id
+                    .as_ref()
+                    .is_none_or(|id| &r.session_id == id)
+                    && filter.rating.is_none_or(|rating| r.rating == Some(rating))
+                    && filter.since_ms.is_none_or(|since| r.updated_at_ms >= since)
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn an_unparseable_file_is_never_overwritten() {
+        let path =
+            std::env::temp_dir().join(format!("tandem-feedback-{}.json", uuid::Uuid::new_v4()));
+        tokio::fs::write(&path, "[{\"feedback_id\": ")
+            .await
+            .expect("write");
+        let store = FeedbackStore::new(path.clone());
+        assert!(store.load().await.is_err());
+        let submitted = store
+            .submit(
+                "s1",
+                "m1",
+                FeedbackInput {
+                    rating: Some(FeedbackRating::Up),
+                    ..FeedbackInput::default()
+                },
+                FeedbackContext::default(),
+                1,
+            )
+            .await;
+        assert!(submitted.is_err());
+        let raw = tokio::fs::read_to_string(&path).await.expect("read");
+        assert_eq!(raw, "[{\"feedback_id\": ");
+        let _ = tokio::fs::remove_file(path).await;
+    }
+
+    #[tokio::test]
+    async fn resubmitting_replaces_feedback_from_same_submitter() {
+        let path =
+            std::env::temp_dir().join(format!("tandem-feedback-{}.json", uuid::Uuid::new_v4()));
+        let store = FeedbackStore::new(path.clone());
+        let input = |rating, comment: Option<&str>, who: &str| FeedbackInput {
+            rating: Some(rating),
+            comment: comment.map(str::to_string),
+            source: Some("channel:discord".to_string()),
+            submitted_by: Some(who.to_string()),
+        };
+        store
+            .submit(
+                "s1",
+                "m1",
+                input(FeedbackRating::Up, None, "alice"),
+                FeedbackContext::default(),
+                1,
+            )
+            .await
+            .expect("submit");
+        store
+            .submit(
+                "s1",
+                "m1",
+                input(FeedbackRating::Down, Some("wrong file"), "alice"),
+                FeedbackContext::default(),
+                2,
+            )
+            .await
+            .expect("resubmit");
+        store
+            .submit(
+                "s1",
+                "m1",
+                input(FeedbackRating::Up, None, "bob"),
+                FeedbackContext::default(),
+                3,
+            )
+            .await
+            .expect("other user");
+
+        let all = store.list(&FeedbackFilter::default()).await;
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].rating, Some(FeedbackRating::Down));
+        assert_eq!(all[0].comment.as_deref(), Some("wrong file"));
+        assert_eq!(
+            FeedbackSummary::of(&all),
+            FeedbackSummary {
+                total: 2,
+                up: 1,
+                down: 1,
+                with_comment: 1
+            }
+        );
+
+        let reloaded = FeedbackStore::new(path.clone());
+        reloaded.load().await.expect("load");
+        let since = reloaded
+            .list(&FeedbackFilter {
+                since_ms: Some(3),
+                ..FeedbackFilter::default()
+            })
+            .await;
+        assert_eq!(since.len(), 1);
+        let _ = fs::remove_file(path).await;
+    }
+}
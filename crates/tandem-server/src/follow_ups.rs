@@ -0,0 +1,270 @@
+//! Suggested follow-up actions for a finished run.
+//!
+//! After a run completes, 2–3 short suggestions are derived from the run's
+//! transcript and the session's unresolved todos and stored under the final
+//! assistant message's `followUps` metadata. Clients render them as
+//! quick-reply buttons; choosing one sends its `prompt` back to the session
+//! as a normal user message.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tandem_types::{EngineEvent, Message, MessagePart, MessageRole};
+
+use crate::AppState;
+
+/// Metadata key on the final assistant message.
+pub const FOLLOW_UPS_METADATA_KEY: &str = "followUps";
+const MAX_SUGGESTIONS: usize = 3;
+const MIN_SUGGESTIONS: usize = 2;
+const MAX_LABEL_CHARS: usize = 48;
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FollowUpSuggestion {
+    /// Short button text.
+    pub label: String,
+    /// Text sent to the session when the suggestion is chosen.
+    pub prompt: String,
+    /// What the suggestion was derived from: `todo`, `question`,
+    /// `tool_error`, `edits` or `default`.
+    pub source: String,
+}
+
+impl FollowUpSuggestion {
+    fn new(label: impl Into<String>, prompt: impl Into<String>, source: &str) -> Self {
+        Self {
+            label: truncate_label(&label.into()),
+            prompt: prompt.into(),
+            source: source.to_string(),
+        }
+    }
+}
+
+fn truncate_label(label: &str) -> String {
+    let label = label.trim();
+    if label.chars().count() <= MAX_LABEL_CHARS {
+        return label.to_string();
+    }
+    let mut out = label.chars().take(MAX_LABEL_CHARS - 1).collect::<String>();
+    out.push('…');
+    out
+}
+
+fn is_unresolved_todo(todo: &Value) -> bool {
+    !matches!(
+        todo.get("status")
+            .and_then(|v| v.as_str())
+            .unwrap_or("pending"),
+        "completed" | "done" | "cancelled" | "canceled"
+    )
+}
+
+fn message_text(message: &Message) -> String {
+    message
+        .parts
+        .iter()
+        .filter_map(|part| match part {
+            MessagePart::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Whether the reply ends by asking the user something.
+fn ends_with_question(text: &str) -> bool {
+    text.trim_end()
+        .trim_end_matches(['*', '_', '`', ')'])
+        .ends_with('?')
+}
+
+/// Up to three follow-ups for the run whose messages are those after the
+/// last user message in `messages`, most specific first.
+pub fn suggest_follow_ups(messages: &[Message], todos: &[Value]) -> Vec<FollowUpSuggestion> {
+    let run_start = messages
+        .iter()
+        .rposition(|m| matches!(m.role, MessageRole::User))
+        .map(|idx| idx + 1)
+        .unwrap_or(0);
+    let run_messages = &messages[run_start..];
+    let final_text = run_messages
+        .iter()
+        .rev()
+        .find(|m| matches!(m.role, MessageRole::Assistant))
+        .map(message_text)
+        .unwrap_or_default();
+
+    let mut failed_tool = None;
+    let mut edited = false;
+    for part in run_messages.iter().flat_map(|m| m.parts.iter()) {
+        if let MessagePart::ToolInvocation { tool, error, .. } = part {
+            if error.is_some() {
+                failed_tool = Some(tool.clone());
+            }
+            if matches!(tool.as_str(), "write" | "edit" | "apply_patch") && error.is_none() {
+                edited = true;
+            }
+        }
+    }
+
+    let mut suggestions = Vec::new();
+    if ends_with_question(&final_text) {
+        suggestions.push(FollowUpSuggestion::new(
+            "Yes, go ahead",
+            "Yes, go ahead.",
+            "question",
+        ));
+    }
+    for todo in todos.iter().filter(|t| is_unresolved_todo(t)).take(2) {
+        let Some(content) = todo.get("content").and_then(|v| v.as_str()) else {
+            continue;
+        };
+        suggestions.push(FollowUpSuggestion::new(
+            format!("Continue: {content}"),
+            format!("Continue with the next open task: {content}"),
+            "todo",
+        ));
+    }
+    if let Some(tool) = failed_tool {
+        suggestions.push(FollowUpSuggestion::new(
+            format!("Retry the failed {tool} step"),
+            format!("The {tool} step failed. Look at the error and try again."),
+            "tool_error",
+        ));
+    }
+    if edited {
+        suggestions.push(FollowUpSuggestion::new(
+            "Run the tests",
+            "Run the relevant tests for the files you changed and fix any failures.",
+            "edits",
+        ));
+    }
+    for (label, prompt) in [
+        (
+            "Summarize what changed",
+            "Summarize what you did and anything left to do.",
+        ),
+        ("What next?", "What should we do next?"),
+    ] {
+        if suggestions.len() >= MIN_SUGGESTIONS {
+            break;
+        }
+        suggestions.push(FollowUpSuggestion::new(label, prompt, "default"));
+    }
+    suggestions.dedup_by(|a, b| a.prompt == b.prompt);
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
+
+/// Generate follow-ups for the run that just finished in `session_id`,
+/// store them on its final assistant message and publish
+/// `message.follow_ups`. Returns the suggestions that were attached.
+pub async fn attach_follow_ups(
+    state: &AppState,
+    session_id: &str,
+    run_id: &str,
+) -> Vec<FollowUpSuggestion> {
+    let Some(session) = state.storage.get_session(session_id).await else {
+        return Vec::new();
+    };
+    let Some(final_message) = session
+        .messages
+        .last()
+        .filter(|m| matches!(m.role, MessageRole::Assistant))
+    else {
+        return Vec::new();
+    };
+    let todos = state.storage.get_todos(session_id).await;
+    let suggestions = suggest_follow_ups(&session.messages, &todos);
+    if suggestions.is_empty() {
+        return suggestions;
+    }
+    let stored = state
+        .storage
+        .set_message_metadata(
+            session_id,
+            &final_message.id,
+            FOLLOW_UPS_METADATA_KEY,
+            json!(suggestions),
+        )
+        .await;
+    if !matches!(stored, Ok(true)) {
+        return Vec::new();
+    }
+    state.event_bus.publish(EngineEvent::new(
+        "message.follow_ups",
+        json!({
+            "sessionID": session_id,
+            "runID": run_id,
+            "messageID": final_message.id,
+            "followUps": suggestions,
+        }),
+    ));
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(role: MessageRole, text: &str) -> Message {
+        Message::new(
+            role,
+            vec![MessagePart::Text {
+                text: text.to_string(),
+            }],
+        )
+    }
+
+    #[test]
+    fn questions_and_open_todos_come_first() {
+        let messages = vec![
+            text(MessageRole::User, "fix the parser"),
+            Message::new(
+                MessageRole::Assistant,
+                vec![MessagePart::ToolInvocation {
+                    tool: "edit".to_string(),
+                    args: json!({"path": "src/parser.rs"}),
+                    result: Some(json!("ok")),
+                    error: None,
+                }],
+            ),
+            text(
+                MessageRole::Assistant,
+                "Fixed the parser. Should I also update the docs?",
+            ),
+        ];
+        let todos = vec![
+            json!({"id": "1", "content": "fix parser", "status": "completed"}),
+            json!({"id": "2", "content": "update CHANGELOG", "status": "pending"}),
+        ];
+        let suggestions = suggest_follow_ups(&messages, &todos);
+        let sources = suggestions
+            .iter()
+            .map(|s| s.source.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(sources, vec!["question", "todo", "edits"]);
+        assert_eq!(suggestions[1].label, "Continue: update CHANGELOG");
+    }
+
+    #[test]
+    fn pads_with_defaults_and_only_looks_at_the_last_run() {
+        let messages = vec![
+            text(MessageRole::User, "first"),
+            Message::new(
+                MessageRole::Assistant,
+                vec![MessagePart::ToolInvocation {
+                    tool: "bash".to_string(),
+                    args: json!({}),
+                    result: None,
+                    error: Some("exit 1".to_string()),
+                }],
+            ),
+            text(MessageRole::User, "thanks"),
+            text(MessageRole::Assistant, "You're welcome."),
+        ];
+        let suggestions = suggest_follow_ups(&messages, &[]);
+        assert_eq!(suggestions.len(), 2);
+        assert!(suggestions.iter().all(|s| s.source == "default"));
+    }
+}
@@ -0,0 +1,520 @@
+//! Host resource monitor with adaptive throttling.
+//!
+//! [`run_host_monitor`] samples CPU, memory and disk use every
+//! `sample_interval_secs` and classifies the host as normal, elevated or
+//! critical against the `host_pressure` config block. A level drops only once
+//! every metric is `hysteresis_percent` below the threshold that raised it, so
+//! a host hovering at a threshold does not flap.
+//!
+//! Under pressure:
+//!
+//! - new session and routine runs are refused or held while the number of
+//!   active runs is at the level's run limit;
+//! - each turn's tool calls run at most `tool_parallelism` at a time;
+//! - workspace index refreshes are deferred until the pressure clears, and
+//!   memory re-embedding and vector index rebuilds are refused.
+//!
+//! Every level change is published as a `host.pressure` event, and the
+//! current sample and limits are reported by `/global/health`.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tandem_types::EngineEvent;
+
+use crate::{now_ms, AppState};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostPressureLevel {
+    Normal,
+    Elevated,
+    Critical,
+}
+
+impl HostPressureLevel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Normal => "normal",
+            Self::Elevated => "elevated",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+/// Usage percentages at which a level is entered.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PressureThresholds {
+    pub cpu_percent: f32,
+    pub memory_percent: f32,
+    pub disk_percent: f32,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_sample_interval_secs() -> u64 {
+    15
+}
+
+fn default_elevated() -> PressureThresholds {
+    PressureThresholds {
+        cpu_percent: 85.0,
+        memory_percent: 90.0,
+        disk_percent: 95.0,
+    }
+}
+
+fn default_critical() -> PressureThresholds {
+    PressureThresholds {
+        cpu_percent: 95.0,
+        memory_percent: 97.0,
+        disk_percent: 98.0,
+    }
+}
+
+fn default_hysteresis_percent() -> f32 {
+    5.0
+}
+
+fn default_elevated_max_runs() -> usize {
+    4
+}
+
+fn default_elevated_tool_parallelism() -> usize {
+    2
+}
+
+fn default_critical_max_runs() -> usize {
+    1
+}
+
+fn default_critical_tool_parallelism() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostPressureConfig {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_sample_interval_secs")]
+    pub sample_interval_secs: u64,
+    #[serde(default = "default_elevated")]
+    pub elevated: PressureThresholds,
+    #[serde(default = "default_critical")]
+    pub critical: PressureThresholds,
+    #[serde(default = "default_hysteresis_percent")]
+    pub hysteresis_percent: f32,
+    #[serde(default = "default_elevated_max_runs")]
+    pub elevated_max_runs: usize,
+    #[serde(default = "default_elevated_tool_parallelism")]
+    pub elevated_tool_parallelism: usize,
+    #[serde(default = "default_critical_max_runs")]
+    pub critical_max_runs: usize,
+    #[serde(default = "default_critical_tool_parallelism")]
+    pub critical_tool_parallelism: usize,
+}
+
+impl Default for HostPressureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            sample_interval_secs: default_sample_interval_secs(),
+            elevated: default_elevated(),
+            critical: default_critical(),
+            hysteresis_percent: default_hysteresis_percent(),
+            elevated_max_runs: default_elevated_max_runs(),
+            elevated_tool_parallelism: default_elevated_tool_parallelism(),
+            critical_max_runs: default_critical_max_runs(),
+            critical_tool_parallelism: default_critical_tool_parallelism(),
+        }
+    }
+}
+
+impl HostPressureConfig {
+    fn thresholds(&self, level: HostPressureLevel) -> Option<&PressureThresholds> {
+        match level {
+            HostPressureLevel::Normal => None,
+            HostPressureLevel::Elevated => Some(&self.elevated),
+            HostPressureLevel::Critical => Some(&self.critical),
+        }
+    }
+
+    pub fn limits_for(&self, level: HostPressureLevel) -> HostLimits {
+        match level {
+            HostPressureLevel::Normal => HostLimits::default(),
+            HostPressureLevel::Elevated => HostLimits {
+                max_concurrent_runs: Some(self.elevated_max_runs.max(1)),
+                tool_parallelism: Some(self.elevated_tool_parallelism.max(1)),
+                indexing_paused: true,
+            },
+            HostPressureLevel::Critical => HostLimits {
+                max_concurrent_runs: Some(self.critical_max_runs.max(1)),
+                tool_parallelism: Some(self.critical_tool_parallelism.max(1)),
+                indexing_paused: true,
+            },
+        }
+    }
+}
+
+/// Usage percentages from one sample; a metric the platform does not
+/// report is left unset and never raises the level.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostSample {
+    pub cpu_percent: Option<f32>,
+    pub memory_percent: Option<f32>,
+    pub disk_percent: Option<f32>,
+    pub sampled_at_ms: u64,
+}
+
+impl HostSample {
+    fn exceeds(&self, thresholds: &PressureThresholds, slack: f32) -> bool {
+        let over = |value: Option<f32>, limit: f32| value.is_some_and(|v| v >= limit - slack);
+        over(self.cpu_percent, thresholds.cpu_percent)
+            || over(self.memory_percent, thresholds.memory_percent)
+            || over(self.disk_percent, thresholds.disk_percent)
+    }
+}
+
+/// The level for `sample`, given the level the host was at before.
+pub fn classify(
+    config: &HostPressureConfig,
+    sample: &HostSample,
+    previous: HostPressureLevel,
+) -> HostPressureLevel {
+    [HostPressureLevel::Critical, HostPressureLevel::Elevated]
+        .into_iter()
+        .find(|level| {
+            let slack = if previous >= *level {
+                config.hysteresis_percent.max(0.0)
+            } else {
+                0.0
+            };
+            config
+                .thresholds(*level)
+                .is_some_and(|thresholds| sample.exceeds(thresholds, slack))
+        })
+        .unwrap_or(HostPressureLevel::Normal)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostLimits {
+    /// Active runs above which new ones are held back; unset when unlimited.
+    pub max_concurrent_runs: Option<usize>,
+    /// Cap on a turn's concurrent tool calls; unset when uncapped.
+    pub tool_parallelism: Option<usize>,
+    pub indexing_paused: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostPressureStatus {
+    pub enabled: bool,
+    pub level: HostPressureLevel,
+    pub sample: Option<HostSample>,
+    pub limits: HostLimits,
+    pub since_ms: u64,
+    /// A workspace index refresh is waiting for the pressure to clear.
+    pub index_refresh_deferred: bool,
+}
+
+/// Current pressure level and limits, shared by the monitor and the places
+/// it throttles.
+#[derive(Clone)]
+pub struct HostMonitor {
+    status: Arc<RwLock<HostPressureStatus>>,
+    deferred_index_refresh: Arc<AtomicBool>,
+}
+
+impl Default for HostMonitor {
+    fn default() -> Self {
+        Self {
+            status: Arc::new(RwLock::new(HostPressureStatus {
+                enabled: false,
+                level: HostPressureLevel::Normal,
+                sample: None,
+                limits: HostLimits::default(),
+                since_ms: now_ms(),
+                index_refresh_deferred: false,
+            })),
+            deferred_index_refresh: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+impl HostMonitor {
+    pub fn status(&self) -> HostPressureStatus {
+        let mut status = self
+            .status
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        status.index_refresh_deferred = self.deferred_index_refresh.load(Ordering::Relaxed);
+        status
+    }
+
+    pub fn limits(&self) -> HostLimits {
+        self.status
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .limits
+            .clone()
+    }
+
+    /// Record a sample's outcome; returns the previous level.
+    pub(crate) fn update(
+        &self,
+        enabled: bool,
+        level: HostPressureLevel,
+        sample: Option<HostSample>,
+        limits: HostLimits,
+    ) -> HostPressureLevel {
+        tandem_core::set_host_tool_parallelism_cap(limits.tool_parallelism);
+        let mut status = self.status.write().unwrap_or_else(|e| e.into_inner());
+        let previous = status.level;
+        if previous != level {
+            status.since_ms = now_ms();
+        }
+        status.enabled = enabled;
+        status.level = level;
+        status.sample = sample;
+        status.limits = limits;
+        previous
+    }
+}
+
+/// Reads host usage. CPU and memory come from `/proc` on Linux; disk use of
+/// the state directory's filesystem comes from `df` on Unix.
+#[derive(Default)]
+struct HostSampler {
+    last_cpu: Option<(u64, u64)>,
+}
+
+impl HostSampler {
+    async fn sample(&mut self, state_dir: &Path) -> HostSample {
+        let cpu_times = tokio::fs::read_to_string("/proc/stat")
+            .await
+            .ok()
+            .and_then(|raw| parse_cpu_times(&raw));
+        let cpu_percent = match (self.last_cpu, cpu_times) {
+            (Some((idle0, total0)), Some((idle1, total1))) if total1 > total0 => {
+                let busy = (total1 - total0).saturating_sub(idle1.saturating_sub(idle0));
+                Some(busy as f32 * 100.0 / (total1 - total0) as f32)
+            }
+            _ => None,
+        };
+        if cpu_times.is_some() {
+            self.last_cpu = cpu_times;
+        }
+        let memory_percent = tokio::fs::read_to_string("/proc/meminfo")
+            .await
+            .ok()
+            .and_then(|raw| parse_memory_percent(&raw));
+        HostSample {
+            cpu_percent,
+            memory_percent,
+            disk_percent: disk_percent(state_dir).await,
+            sampled_at_ms: now_ms(),
+        }
+    }
+}
+
+/// Idle and total jiffies from the aggregate `cpu` line of `/proc/stat`.
+fn parse_cpu_times(raw: &str) -> Option<(u64, u64)> {
+    let line = raw.lines().find(|line| line.starts_with("cpu "))?;
+    let values = line
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|v| v.parse::<u64>().ok())
+        .collect::<Vec<_>>();
+    // idle + iowait count as idle time.
+    let idle = values.get(3)? + values.get(4).copied().unwrap_or(0);
+    Some((idle, values.iter().sum()))
+}
+
+fn parse_memory_percent(raw: &str) -> Option<f32> {
+    let field = |name: &str| {
+        raw.lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|v| v.parse::<u64>().ok())
+    };
+    let total = field("MemTotal:").filter(|total| *total > 0)?;
+    let available = field("MemAvailable:")?;
+    Some(total.saturating_sub(available) as f32 * 100.0 / total as f32)
+}
+
+/// The capacity column of `df -P` output.
+fn parse_df_capacity(raw: &str) -> Option<f32> {
+    let line = raw.lines().nth(1)?;
+    line.split_whitespace()
+        .find_map(|column| column.strip_suffix('%'))
+        .and_then(|v| v.parse::<f32>().ok())
+}
+
+#[cfg(unix)]
+async fn disk_percent(dir: &Path) -> Option<f32> {
+    let output = tokio::process::Command::new("df")
+        .arg("-Pk")
+        .arg(dir)
+        .output()
+        .await
+        .ok()
+        .filter(|output| output.status.success())?;
+    parse_df_capacity(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(not(unix))]
+async fn disk_percent(_dir: &Path) -> Option<f32> {
+    None
+}
+
+impl AppState {
+    /// The `host_pressure` config block.
+    pub async fn host_pressure_config(&self) -> HostPressureConfig {
+        self.config_section("host_pressure").await
+    }
+
+    /// The run limit in force when the host is too loaded for another run.
+    pub async fn host_run_limit_reached(&self) -> Option<usize> {
+        let limit = self.host_monitor.limits().max_concurrent_runs?;
+        (self.run_registry.active_count().await >= limit).then_some(limit)
+    }
+
+    /// Rebuild the workspace index now, or once the pressure clears while
+    /// indexing is paused. Returns whether the refresh started.
+    pub fn refresh_workspace_index_when_idle(&self) -> bool {
+        if self.host_monitor.limits().indexing_paused {
+            self.host_monitor
+                .deferred_index_refresh
+                .store(true, Ordering::Relaxed);
+            return false;
+        }
+        let index = self.workspace_index.clone();
+        tokio::spawn(async move {
+            index.refresh().await;
+        });
+        true
+    }
+}
+
+pub async fn run_host_monitor(state: AppState) {
+    let mut sampler = HostSampler::default();
+    loop {
+        let config = state.host_pressure_config().await;
+        if !config.enabled {
+            state.host_monitor.update(
+                false,
+                HostPressureLevel::Normal,
+                None,
+                HostLimits::default(),
+            );
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            continue;
+        }
+        let sample = sampler.sample(&state.state_dir).await;
+        let previous_level = state.host_monitor.status().level;
+        let level = classify(&config, &sample, previous_level);
+        let limits = config.limits_for(level);
+        state
+            .host_monitor
+            .update(true, level, Some(sample.clone()), limits.clone());
+        if level != previous_level {
+            if level > previous_level {
+                tracing::warn!(
+                    "host pressure {}: cpu {:?}%, memory {:?}%, disk {:?}%",
+                    level.as_str(),
+                    sample.cpu_percent,
+                    sample.memory_percent,
+                    sample.disk_percent
+                );
+            } else {
+                tracing::info!("host pressure eased to {}", level.as_str());
+            }
+            state.event_bus.publish(EngineEvent::new(
+                "host.pressure",
+                json!({
+                    "level": level,
+                    "previousLevel": previous_level,
+                    "cpuPercent": sample.cpu_percent,
+                    "memoryPercent": sample.memory_percent,
+                    "diskPercent": sample.disk_percent,
+                    "limits": limits,
+                }),
+            ));
+        }
+        if !limits.indexing_paused
+            && state
+                .host_monitor
+                .deferred_index_refresh
+                .swap(false, Ordering::Relaxed)
+        {
+            state.refresh_workspace_index_when_idle();
+        }
+        tokio::time::sleep(Duration::from_secs(config.sample_interval_secs.max(1))).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(cpu: f32, memory: f32, disk: Option<f32>) -> HostSample {
+        HostSample {
+            cpu_percent: Some(cpu),
+            memory_percent: Some(memory),
+            disk_percent: disk,
+            sampled_at_ms: 0,
+        }
+    }
+
+    #[test]
+    fn levels_rise_at_thresholds_and_ease_below_hysteresis() {
+        let config = HostPressureConfig::default();
+        let normal = HostPressureLevel::Normal;
+        assert_eq!(classify(&config, &sample(50.0, 60.0, None), normal), normal);
+        assert_eq!(
+            classify(&config, &sample(86.0, 60.0, None), normal),
+            HostPressureLevel::Elevated
+        );
+        assert_eq!(
+            classify(&config, &sample(50.0, 60.0, Some(99.0)), normal),
+            HostPressureLevel::Critical
+        );
+        // Just under the threshold keeps the level until it drops past the
+        // hysteresis band.
+        let elevated = HostPressureLevel::Elevated;
+        assert_eq!(
+            classify(&config, &sample(82.0, 60.0, None), elevated),
+            elevated
+        );
+        assert_eq!(
+            classify(&config, &sample(79.0, 60.0, None), elevated),
+            normal
+        );
+
+        let limits = config.limits_for(HostPressureLevel::Critical);
+        assert_eq!(limits.max_concurrent_runs, Some(1));
+        assert_eq!(limits.tool_parallelism, Some(1));
+        assert!(limits.indexing_paused);
+        assert_eq!(config.limits_for(normal), HostLimits::default());
+    }
+
+    #[test]
+    fn parses_proc_and_df_output() {
+        let stat = "cpu  100 0 100 700 100 0 0 0 0 0\ncpu0 50 0 50 350 50 0 0 0 0 0\n";
+        assert_eq!(parse_cpu_times(stat), Some((800, 1000)));
+        let meminfo =
+            "MemTotal:       16000000 kB\nMemFree:  1000000 kB\nMemAvailable:    4000000 kB\n";
+        assert_eq!(parse_memory_percent(meminfo), Some(75.0));
+        let df = "Filesystem 1024-blocks Used Available Capacity Mounted on\n/dev/sda1 100 42 58 42% /\n";
+        assert_eq!(parse_df_capacity(df), Some(42.0));
+    }
+}
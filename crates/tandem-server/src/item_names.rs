@@ -0,0 +1,25 @@
+//! Names for items that become files on disk (agents, skills, routines).
+
+/// Item names become file names; keep them to a single plain segment.
+pub fn valid_item_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn item_names_are_single_plain_segments() {
+        for name in ["review", "rust-ci", "lint_v2", "notes.v1"] {
+            assert!(valid_item_name(name), "{name}");
+        }
+        for name in ["", ".hidden", "../etc", "a/b", "a\\b", "with space"] {
+            assert!(!valid_item_name(name), "{name}");
+        }
+    }
+}
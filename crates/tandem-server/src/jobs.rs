@@ -0,0 +1,845 @@
+//! Background jobs: long-running work (memory ingestion and re-embedding,
+//! backups, plugin tasks) that runs outside any chat session.
+//!
+//! Engine jobs are spawned with [`JobRegistry::spawn`] and report through a
+//! [`JobContext`]; external jobs are registered by a client (e.g. a plugin)
+//! that does the work itself and reports progress over HTTP. Either way the
+//! registry publishes `job.created`, `job.progress` and `job.finished`
+//! events. Records are kept in memory and only the most recent finished jobs
+//! are retained.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tandem_core::EventBus;
+use tandem_types::EngineEvent;
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::now_ms;
+use crate::object_store::{ObjectClass, S3Bucket};
+
+const MAX_FINISHED_JOBS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn is_terminal(self) -> bool {
+        !matches!(self, Self::Running)
+    }
+}
+
+/// Who does the work: the engine itself, or a client that registered the job
+/// and reports on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobRunner {
+    Engine,
+    External,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JobProgress {
+    pub done: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub job_id: String,
+    /// Free-form type such as `memory.reembed`, `backup` or `plugin.<name>`.
+    pub kind: String,
+    pub title: String,
+    pub runner: JobRunner,
+    pub state: JobState,
+    pub progress: JobProgress,
+    pub created_at_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    /// File or directory holding the job's output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub artifact_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub metadata: Value,
+}
+
+/// What a finished job leaves behind.
+#[derive(Debug, Clone, Default)]
+pub struct JobOutcome {
+    pub result: Value,
+    pub artifact_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobError {
+    NotFound,
+    /// Progress and completion can only be reported for external jobs.
+    NotExternal,
+    AlreadyFinished,
+}
+
+impl std::fmt::Display for JobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "job not found"),
+            Self::NotExternal => write!(f, "job is run by the engine"),
+            Self::AlreadyFinished => write!(f, "job already finished"),
+        }
+    }
+}
+
+struct JobEntry {
+    record: JobRecord,
+    cancel: CancellationToken,
+}
+
+#[derive(Clone, Default)]
+pub struct JobRegistry {
+    jobs: Arc<RwLock<HashMap<String, JobEntry>>>,
+}
+
+/// Handle given to an engine job's work future.
+#[derive(Clone)]
+pub struct JobContext {
+    pub job_id: String,
+    registry: JobRegistry,
+    event_bus: EventBus,
+    cancel: CancellationToken,
+}
+
+impl JobContext {
+    pub async fn progress(&self, done: u64, total: Option<u64>, message: Option<String>) {
+        let _ = self
+            .registry
+            .update_progress(
+                &self.event_bus,
+                &self.job_id,
+                JobProgress {
+                    done,
+                    total,
+                    message,
+                },
+            )
+            .await;
+    }
+
+    /// Jobs check this cooperatively; a job that returns after cancellation
+    /// was requested is recorded as cancelled whatever it returned.
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+}
+
+fn publish(event_bus: &EventBus, event_type: &str, record: &JobRecord) {
+    let payload = match event_type {
+        "job.progress" => json!({
+            "jobID": record.job_id,
+            "kind": record.kind,
+            "progress": record.progress,
+        }),
+        _ => json!({
+            "jobID": record.job_id,
+            "kind": record.kind,
+            "title": record.title,
+            "runner": record.runner,
+            "state": record.state,
+            "error": record.error,
+            "artifactPath": record.artifact_path,
+        }),
+    };
+    event_bus.publish(EngineEvent::new(event_type, payload));
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn insert(
+        &self,
+        event_bus: &EventBus,
+        kind: &str,
+        title: &str,
+        runner: JobRunner,
+        metadata: Value,
+    ) -> (JobRecord, CancellationToken) {
+        let record = JobRecord {
+            job_id: format!("job-{}", Uuid::new_v4()),
+            kind: kind.to_string(),
+            title: title.to_string(),
+            runner,
+            state: JobState::Running,
+            progress: JobProgress::default(),
+            created_at_ms: now_ms(),
+            finished_at_ms: None,
+            result: None,
+            artifact_path: None,
+            error: None,
+            metadata,
+        };
+        let cancel = CancellationToken::new();
+        let mut jobs = self.jobs.write().await;
+        prune_finished(&mut jobs);
+        jobs.insert(
+            record.job_id.clone(),
+            JobEntry {
+                record: record.clone(),
+                cancel: cancel.clone(),
+            },
+        );
+        drop(jobs);
+        publish(event_bus, "job.created", &record);
+        (record, cancel)
+    }
+
+    /// Run `work` on the runtime as an engine job.
+    pub async fn spawn<F, Fut>(
+        &self,
+        event_bus: EventBus,
+        kind: &str,
+        title: &str,
+        metadata: Value,
+        work: F,
+    ) -> JobRecord
+    where
+        F: FnOnce(JobContext) -> Fut,
+        Fut: Future<Output = anyhow::Result<JobOutcome>> + Send + 'static,
+    {
+        let (record, cancel) = self
+            .insert(&event_bus, kind, title, JobRunner::Engine, metadata)
+            .await;
+        let ctx = JobContext {
+            job_id: record.job_id.clone(),
+            registry: self.clone(),
+            event_bus: event_bus.clone(),
+            cancel: cancel.clone(),
+        };
+        let registry = self.clone();
+        let job_id = record.job_id.clone();
+        let fut = work(ctx);
+        tandem_core::spawn_in_request(async move {
+            let result = fut.await;
+            let finish = if cancel.is_cancelled() {
+                Err((JobState::Cancelled, None))
+            } else {
+                result.map_err(|err| (JobState::Failed, Some(err.to_string())))
+            };
+            let _ = registry.finish(&event_bus, &job_id, finish).await;
+        });
+        record
+    }
+
+    /// Register a job whose work is done by the caller.
+    pub async fn register_external(
+        &self,
+        event_bus: &EventBus,
+        kind: &str,
+        title: &str,
+        metadata: Value,
+    ) -> JobRecord {
+        self.insert(event_bus, kind, title, JobRunner::External, metadata)
+            .await
+            .0
+    }
+
+    async fn update_progress(
+        &self,
+        event_bus: &EventBus,
+        job_id: &str,
+        progress: JobProgress,
+    ) -> Result<JobRecord, JobError> {
+        let mut jobs = self.jobs.write().await;
+        let entry = jobs.get_mut(job_id).ok_or(JobError::NotFound)?;
+        if entry.record.state.is_terminal() {
+            return Err(JobError::AlreadyFinished);
+        }
+        entry.record.progress = progress;
+        let record = entry.record.clone();
+        drop(jobs);
+        publish(event_bus, "job.progress", &record);
+        Ok(record)
+    }
+
+    pub async fn report_progress(
+        &self,
+        event_bus: &EventBus,
+        job_id: &str,
+        progress: JobProgress,
+    ) -> Result<JobRecord, JobError> {
+        self.ensure_external(job_id).await?;
+        self.update_progress(event_bus, job_id, progress).await
+    }
+
+    /// Finish an external job; `Err` carries the failure message.
+    pub async fn complete_external(
+        &self,
+        event_bus: &EventBus,
+        job_id: &str,
+        outcome: Result<JobOutcome, String>,
+    ) -> Result<JobRecord, JobError> {
+        self.ensure_external(job_id).await?;
+        self.finish(
+            event_bus,
+            job_id,
+            outcome.map_err(|err| (JobState::Failed, Some(err))),
+        )
+        .await
+    }
+
+    async fn ensure_external(&self, job_id: &str) -> Result<(), JobError> {
+        let jobs = self.jobs.read().await;
+        let entry = jobs.get(job_id).ok_or(JobError::NotFound)?;
+        if entry.record.runner != JobRunner::External {
+            return Err(JobError::NotExternal);
+        }
+        Ok(())
+    }
+
+    async fn finish(
+        &self,
+        event_bus: &EventBus,
+        job_id: &str,
+        outcome: Result<JobOutcome, (JobState, Option<String>)>,
+    ) -> Result<JobRecord, JobError> {
+        let mut jobs = self.jobs.write().await;
+        let entry = jobs.get_mut(job_id).ok_or(JobError::NotFound)?;
+        if entry.record.state.is_terminal() {
+            return Err(JobError::AlreadyFinished);
+        }
+        let record = &mut entry.record;
+        match outcome {
+            Ok(outcome) => {
+                record.state = JobState::Succeeded;
+                record.result = Some(outcome.result);
+                record.artifact_path = outcome
+                    .artifact_path
+                    .map(|path| path.to_string_lossy().to_string());
+            }
+            Err((state, error)) => {
+                record.state = state;
+                record.error = error;
+            }
+        }
+        record.finished_at_ms = Some(now_ms());
+        let record = record.clone();
+        drop(jobs);
+        publish(event_bus, "job.finished", &record);
+        Ok(record)
+    }
+
+    /// Request cancellation. Engine jobs stop at their next check; external
+    /// jobs are marked cancelled right away and their runner sees the
+    /// `job.finished` event.
+    pub async fn cancel(&self, event_bus: &EventBus, job_id: &str) -> Result<JobRecord, JobError> {
+        let jobs = self.jobs.read().await;
+        let entry = jobs.get(job_id).ok_or(JobError::NotFound)?;
+        if entry.record.state.is_terminal() {
+            return Err(JobError::AlreadyFinished);
+        }
+        entry.cancel.cancel();
+        let runner = entry.record.runner;
+        let record = entry.record.clone();
+        drop(jobs);
+        match runner {
+            JobRunner::Engine => Ok(record),
+            JobRunner::External => {
+                self.finish(event_bus, job_id, Err((JobState::Cancelled, None)))
+                    .await
+            }
+        }
+    }
+
+    pub async fn get(&self, job_id: &str) -> Option<JobRecord> {
+        self.jobs
+            .read()
+            .await
+            .get(job_id)
+            .map(|entry| entry.record.clone())
+    }
+
+    /// Newest first.
+    pub async fn list(&self, kind: Option<&str>, state: Option<JobState>) -> Vec<JobRecord> {
+        let mut records = self
+            .jobs
+            .read()
+            .await
+            .values()
+            .map(|entry| &entry.record)
+            .filter(|record| kind.is_none() || kind == Some(record.kind.as_str()))
+            .filter(|record| state.is_none() || state == Some(record.state))
+            .cloned()
+            .collect::<Vec<_>>();
+        records.sort_by(|a, b| b.created_at_ms.cmp(&a.created_at_ms));
+        records
+    }
+}
+
+/// Copy every file under `source` into a new timestamped directory in
+/// `backups_dir`, skipping `backups_dir` itself. With a `bucket` the files
+/// are uploaded under `backups/backup-<ms>/` instead and nothing is written
+/// locally. Stops early on cancellation.
+pub async fn backup_state_dir(
+    ctx: JobContext,
+    source: PathBuf,
+    backups_dir: PathBuf,
+    bucket: Option<S3Bucket>,
+) -> anyhow::Result<JobOutcome> {
+    let skip = backups_dir.clone();
+    let walk_root = source.clone();
+    let files = tokio::task::spawn_blocking(move || {
+        ignore::WalkBuilder::new(&walk_root)
+            .standard_filters(false)
+            .filter_entry(move |entry| entry.path() != skip)
+            .build()
+            .flatten()
+            .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+            .map(|entry| entry.into_path())
+            .collect::<Vec<_>>()
+    })
+    .await?;
+    let backup_name = format!("backup-{}", now_ms());
+    let target = backups_dir.join(&backup_name);
+    let object_prefix = format!("{}/{backup_name}", ObjectClass::Backups.key_prefix());
+    let total = files.len() as u64;
+    let mut bytes = 0u64;
+    for (idx, file) in files.iter().enumerate() {
+        if ctx.is_cancelled() {
+            break;
+        }
+        let Ok(relative) = file.strip_prefix(&source) else {
+            continue;
+        };
+        if let Some(bucket) = &bucket {
+            bytes += upload_file(bucket, file, &object_prefix, relative).await?;
+        } else {
+            let dest = target.join(relative);
+            if let Some(parent) = dest.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            bytes += copy_file(file, &dest).await?;
+        }
+        ctx.progress(
+            idx as u64 + 1,
+            Some(total),
+            Some(relative.to_string_lossy().to_string()),
+        )
+        .await;
+    }
+    if let Some(bucket) = &bucket {
+        return Ok(JobOutcome {
+            result: json!({
+                "source": source.to_string_lossy(),
+                "files": total,
+                "bytes": bytes,
+                "bucket": bucket.describe(),
+                "objectPrefix": object_prefix,
+            }),
+            artifact_path: None,
+        });
+    }
+    Ok(JobOutcome {
+        result: json!({
+            "source": source.to_string_lossy(),
+            "files": total,
+            "bytes": bytes,
+        }),
+        artifact_path: Some(target),
+    })
+}
+
+/// Ingest the text files under `root` into project-tier memory as file
+/// chunks for `project_id`. Files whose content hash is unchanged since the
+/// last run are skipped, changed files have their old chunks replaced, and
+/// files that disappeared are dropped from the index. `root` only limits
+/// what is walked; indexed paths stay relative to `workspace_root`.
+pub async fn ingest_files(
+    ctx: JobContext,
+    manager: Arc<tandem_memory::MemoryManager>,
+    workspace_root: PathBuf,
+    root: PathBuf,
+    project_id: String,
+) -> anyhow::Result<JobOutcome> {
+    use sha2::{Digest, Sha256};
+
+    let rules = tandem_runtime::WorkspaceIgnore::for_root(&workspace_root);
+    let walk_root = root.clone();
+    let files = tokio::task::spawn_blocking(move || {
+        rules
+            .walker(&[walk_root])
+            .build()
+            .flatten()
+            .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+            .map(|entry| entry.into_path())
+            .collect::<Vec<_>>()
+    })
+    .await?;
+    let relative_to_workspace = |path: &Path| {
+        path.strip_prefix(&workspace_root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .replace('\\', "/")
+    };
+    let scope = relative_to_workspace(&root);
+    let db = manager.db();
+    let total = files.len() as u64;
+    let mut seen = std::collections::HashSet::new();
+    let (mut indexed, mut skipped, mut errors, mut chunks) = (0u64, 0u64, 0u64, 0usize);
+    for (idx, file) in files.iter().enumerate() {
+        if ctx.is_cancelled() {
+            break;
+        }
+        let relative = relative_to_workspace(file);
+        seen.insert(relative.clone());
+        ctx.progress(idx as u64 + 1, Some(total), Some(relative.clone()))
+            .await;
+        let (Ok(meta), Ok(content)) = (
+            tokio::fs::metadata(file).await,
+            tokio::fs::read_to_string(file).await,
+        ) else {
+            skipped += 1;
+            continue;
+        };
+        if content.trim().is_empty() {
+            skipped += 1;
+            continue;
+        }
+        let mtime = meta
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or_default();
+        let size = meta.len() as i64;
+        let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        let existing = db.get_file_index_entry(&project_id, &relative).await?;
+        if existing.is_some_and(|(_, _, existing_hash)| existing_hash == hash) {
+            db.upsert_file_index_entry(&project_id, &relative, mtime, size, &hash)
+                .await?;
+            skipped += 1;
+            continue;
+        }
+        if let Err(err) = db
+            .delete_project_file_chunks_by_path(&project_id, &relative)
+            .await
+        {
+            tracing::warn!("ingest: dropping old chunks of {relative} failed: {err}");
+            errors += 1;
+            continue;
+        }
+        let request = tandem_memory::types::StoreMessageRequest {
+            content,
+            tier: tandem_memory::types::MemoryTier::Project,
+            session_id: None,
+            project_id: Some(project_id.clone()),
+            source: "file".to_string(),
+            source_path: Some(relative.clone()),
+            source_mtime: Some(mtime),
+            source_size: Some(size),
+            source_hash: Some(hash.clone()),
+            metadata: Some(json!({ "path": relative })),
+            provenance: None,
+        };
+        match manager.store_message(request).await {
+            Ok(ids) => {
+                db.upsert_file_index_entry(&project_id, &relative, mtime, size, &hash)
+                    .await?;
+                indexed += 1;
+                chunks += ids.len();
+            }
+            Err(err) => {
+                tracing::warn!("ingest: storing {relative} failed: {err}");
+                errors += 1;
+            }
+        }
+    }
+    // Only prune after a full walk; a cancelled run has not seen every file.
+    let mut removed = 0u64;
+    if !ctx.is_cancelled() {
+        for path in db.list_file_index_paths(&project_id).await? {
+            let in_scope =
+                scope.is_empty() || path == scope || path.starts_with(&format!("{scope}/"));
+            if !in_scope || seen.contains(&path) {
+                continue;
+            }
+            db.delete_project_file_chunks_by_path(&project_id, &path)
+                .await?;
+            db.delete_file_index_entry(&project_id, &path).await?;
+            removed += 1;
+        }
+    }
+    db.upsert_project_index_status(
+        &project_id,
+        total as i64,
+        seen.len() as i64,
+        indexed as i64,
+        skipped as i64,
+        errors as i64,
+    )
+    .await?;
+    Ok(JobOutcome {
+        result: json!({
+            "projectID": project_id,
+            "root": root.to_string_lossy(),
+            "files": total,
+            "indexed": indexed,
+            "skipped": skipped,
+            "removed": removed,
+            "errors": errors,
+            "chunks": chunks,
+        }),
+        artifact_path: None,
+    })
+}
+
+async fn upload_file(
+    bucket: &S3Bucket,
+    from: &Path,
+    object_prefix: &str,
+    relative: &Path,
+) -> anyhow::Result<u64> {
+    let data = match tokio::fs::read(from).await {
+        Ok(data) => data,
+        // Same as `copy_file`: vanished files are skipped.
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(anyhow::anyhow!("read {}: {err}", from.display())),
+    };
+    let key = relative
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .fold(object_prefix.to_string(), |key, part| {
+            format!("{key}/{part}")
+        });
+    let len = data.len() as u64;
+    bucket.put(&key, data).await?;
+    Ok(len)
+}
+
+async fn copy_file(from: &Path, to: &Path) -> anyhow::Result<u64> {
+    match tokio::fs::copy(from, to).await {
+        Ok(bytes) => Ok(bytes),
+        // Files can disappear while the engine keeps running (temp files,
+        // rotated logs); that should not fail the whole backup.
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(err) => Err(anyhow::anyhow!("copy {}: {err}", from.display())),
+    }
+}
+
+fn prune_finished(jobs: &mut HashMap<String, JobEntry>) {
+    let mut finished = jobs
+        .values()
+        .filter(|entry| entry.record.state.is_terminal())
+        .map(|entry| {
+            (
+                entry.record.finished_at_ms.unwrap_or_default(),
+                entry.record.job_id.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    if finished.len() < MAX_FINISHED_JOBS {
+        return;
+    }
+    finished.sort();
+    for (_, job_id) in finished.iter().take(finished.len() + 1 - MAX_FINISHED_JOBS) {
+        jobs.remove(job_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn wait_finished(registry: &JobRegistry, job_id: &str) -> JobRecord {
+        for _ in 0..100 {
+            let record = registry.get(job_id).await.expect("job");
+            if record.state.is_terminal() {
+                return record;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("job {job_id} did not finish");
+    }
+
+    #[tokio::test]
+    async fn engine_job_reports_progress_and_result() {
+        let registry = JobRegistry::new();
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        let record = registry
+            .spawn(
+                bus.clone(),
+                "test",
+                "Test job",
+                json!({}),
+                |ctx| async move {
+                    ctx.progress(1, Some(2), None).await;
+                    Ok(JobOutcome {
+                        result: json!({"ok": true}),
+                        artifact_path: None,
+                    })
+                },
+            )
+            .await;
+        let finished = wait_finished(&registry, &record.job_id).await;
+        assert_eq!(finished.state, JobState::Succeeded);
+        assert_eq!(finished.progress.done, 1);
+        assert_eq!(finished.result, Some(json!({"ok": true})));
+        let first = events.recv().await.expect("event");
+        assert_eq!(first.event_type, "job.created");
+    }
+
+    #[tokio::test]
+    async fn cancelled_engine_job_is_recorded_as_cancelled() {
+        let registry = JobRegistry::new();
+        let bus = EventBus::new();
+        let record = registry
+            .spawn(
+                bus.clone(),
+                "test",
+                "Slow job",
+                json!({}),
+                |ctx| async move {
+                    ctx.cancel_token().cancelled().await;
+                    Ok(JobOutcome::default())
+                },
+            )
+            .await;
+        registry.cancel(&bus, &record.job_id).await.expect("cancel");
+        let finished = wait_finished(&registry, &record.job_id).await;
+        assert_eq!(finished.state, JobState::Cancelled);
+        assert_eq!(
+            registry.cancel(&bus, &record.job_id).await.unwrap_err(),
+            JobError::AlreadyFinished
+        );
+    }
+
+    #[tokio::test]
+    async fn only_external_jobs_accept_reports() {
+        let registry = JobRegistry::new();
+        let bus = EventBus::new();
+        let engine = registry
+            .spawn(
+                bus.clone(),
+                "test",
+                "Engine job",
+                json!({}),
+                |ctx| async move {
+                    ctx.cancel_token().cancelled().await;
+                    Ok(JobOutcome::default())
+                },
+            )
+            .await;
+        assert_eq!(
+            registry
+                .report_progress(&bus, &engine.job_id, JobProgress::default())
+                .await
+                .unwrap_err(),
+            JobError::NotExternal
+        );
+        let external = registry
+            .register_external(&bus, "plugin.index", "Index docs", json!({}))
+            .await;
+        let done = registry
+            .complete_external(&bus, &external.job_id, Err("boom".to_string()))
+            .await
+            .expect("complete");
+        assert_eq!(done.state, JobState::Failed);
+        assert_eq!(done.error.as_deref(), Some("boom"));
+        assert_eq!(registry.list(Some("plugin.index"), None).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn backup_copies_state_without_previous_backups() {
+        let dir = std::env::temp_dir().join(format!("tandem-jobs-{}", Uuid::new_v4()));
+        let backups = dir.join("backups");
+        std::fs::create_dir_all(backups.join("backup-1")).expect("mkdir");
+        std::fs::write(backups.join("backup-1").join("old.json"), "{}").expect("write");
+        std::fs::create_dir_all(dir.join("sessions")).expect("mkdir");
+        std::fs::write(dir.join("sessions").join("s1.json"), "{}").expect("write");
+        std::fs::write(dir.join("routines.json"), "[]").expect("write");
+
+        let registry = JobRegistry::new();
+        let bus = EventBus::new();
+        let source = dir.clone();
+        let record = registry
+            .spawn(bus, "backup", "Backup", json!({}), move |ctx| {
+                backup_state_dir(ctx, source, backups, None)
+            })
+            .await;
+        let finished = wait_finished(&registry, &record.job_id).await;
+        assert_eq!(finished.state, JobState::Succeeded);
+        assert_eq!(finished.result.as_ref().expect("result")["files"], 2);
+        let artifact = PathBuf::from(finished.artifact_path.expect("artifact"));
+        assert!(artifact.join("sessions").join("s1.json").exists());
+        assert!(!artifact.join("backups").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn ingest_skips_unchanged_files_and_prunes_only_its_scope() {
+        use sha2::{Digest, Sha256};
+
+        let dir = std::env::temp_dir().join(format!("tandem-jobs-{}", Uuid::new_v4()));
+        let workspace = dir.join("workspace");
+        std::fs::create_dir_all(workspace.join("docs")).expect("mkdir");
+        std::fs::write(workspace.join("docs").join("a.md"), "# alpha").expect("write");
+        let manager = Arc::new(
+            tandem_memory::MemoryManager::new(&dir.join("memory.sqlite"))
+                .await
+                .expect("memory"),
+        );
+        let db = manager.db();
+        let hash = format!("{:x}", Sha256::digest("# alpha".as_bytes()));
+        for path in ["docs/a.md", "docs/gone.md", "notes/keep.md"] {
+            db.upsert_file_index_entry("p1", path, 0, 0, &hash)
+                .await
+                .expect("seed");
+        }
+
+        let registry = JobRegistry::new();
+        let bus = EventBus::new();
+        let job_manager = manager.clone();
+        let root = workspace.join("docs");
+        let record = registry
+            .spawn(bus, "memory.ingest", "Ingest", json!({}), move |ctx| {
+                ingest_files(ctx, job_manager, workspace, root, "p1".to_string())
+            })
+            .await;
+        let finished = wait_finished(&registry, &record.job_id).await;
+        assert_eq!(finished.state, JobState::Succeeded);
+        let result = finished.result.expect("result");
+        assert_eq!(result["skipped"], 1);
+        assert_eq!(result["removed"], 1);
+        let mut paths = manager
+            .db()
+            .list_file_index_paths("p1")
+            .await
+            .expect("paths");
+        paths.sort();
+        assert_eq!(paths, vec!["docs/a.md", "notes/keep.md"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
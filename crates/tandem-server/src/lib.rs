@@ -8,7 +8,10 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tandem_memory::{GovernedMemoryTier, MemoryClassification, MemoryContentKind, MemoryPartition};
+use tandem_memory::{
+    GovernedMemoryTier, MemoryClassification, MemoryContentKind, MemoryPartition,
+    RedactionManifest, RedactionProfile,
+};
 use tandem_orchestrator::MissionState;
 use tandem_types::{
     EngineEvent, HostOs, HostRuntimeContext, MessagePartInput, ModelSpec, PathStyle,
@@ -20,15 +23,64 @@ use tokio::sync::RwLock;
 use tandem_channels::config::{ChannelsConfig, DiscordConfig, SlackConfig, TelegramConfig};
 use tandem_core::{
     resolve_shared_paths, AgentRegistry, CancellationRegistry, ConfigStore, EngineLoop, EventBus,
-    PermissionManager, PluginRegistry, Storage,
+    PermissionManager, PluginRegistry, RunPriority, Storage,
 };
 use tandem_providers::ProviderRegistry;
 use tandem_runtime::{LspManager, McpRegistry, PtyManager, WorkspaceIndex};
 use tandem_tools::ToolRegistry;
 
+pub mod access_log;
+pub mod agent_pipelines;
 mod agent_teams;
+pub mod api_error;
+pub mod audit_chain;
+pub mod capabilities;
+pub mod channel_files;
+pub mod command_audit;
+pub mod context_import;
+pub mod doctor;
+pub mod event_coalesce;
+pub mod event_rules;
+pub mod feedback;
+pub mod follow_ups;
+pub mod host_monitor;
 mod http;
+pub mod item_names;
+pub mod jobs;
+pub mod message_batch;
+pub mod object_store;
+pub mod oidc;
+pub mod parquet_writer;
+pub mod persistence;
+pub mod quotas;
+pub mod replication;
+pub mod report_builder;
+pub mod resource_snapshots;
+pub mod retention;
+mod routine_approvals;
+pub mod routine_cache;
+pub mod routine_diff;
+pub mod routine_notifications;
+pub mod routine_presets;
+pub mod routine_recovery;
+pub mod routine_templates;
+pub mod run_export;
+pub mod run_hooks;
+pub mod run_timeline;
+pub mod secure_links;
+pub mod session_compare;
+pub mod session_filters;
+pub mod session_import;
+pub mod shared_sync;
+pub mod sse_clients;
+pub mod state_file;
+pub mod storage_stats;
+pub mod success_criteria;
+pub mod tenants;
+pub mod uploads;
 pub mod webui;
+pub mod workspace_changes;
+pub mod workspace_onboarding;
 
 pub use agent_teams::AgentTeamRuntime;
 pub use http::serve;
@@ -48,6 +100,10 @@ pub struct WebUiConfig {
     pub enabled: bool,
     #[serde(default = "default_web_ui_prefix")]
     pub path_prefix: String,
+    /// External base URL the Web UI is reached at, for links sent to chat
+    /// platforms. Defaults to the server's listen address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_url: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -66,6 +122,8 @@ pub struct TelegramConfigFile {
     pub allowed_users: Vec<String>,
     #[serde(default)]
     pub mention_only: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<tandem_channels::delivery::QuietHours>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -77,6 +135,12 @@ pub struct DiscordConfigFile {
     pub allowed_users: Vec<String>,
     #[serde(default = "default_discord_mention_only")]
     pub mention_only: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<tandem_channels::delivery::QuietHours>,
+    /// Earlier messages to import as context when the bot joins a
+    /// conversation; `0` disables history import.
+    #[serde(default)]
+    pub history_import: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -85,6 +149,12 @@ pub struct SlackConfigFile {
     pub channel_id: String,
     #[serde(default = "default_allow_all")]
     pub allowed_users: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quiet_hours: Option<tandem_channels::delivery::QuietHours>,
+    /// Earlier messages to import as context when the bot joins a
+    /// conversation; `0` disables history import.
+    #[serde(default)]
+    pub history_import: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -133,6 +203,7 @@ pub struct ActiveRun {
     pub agent_id: Option<String>,
     #[serde(rename = "agentProfile", skip_serializing_if = "Option::is_none")]
     pub agent_profile: Option<String>,
+    pub priority: RunPriority,
 }
 
 #[derive(Clone, Default)]
@@ -169,6 +240,7 @@ impl RunRegistry {
             client_id,
             agent_id,
             agent_profile,
+            priority: RunPriority::Interactive,
         };
         guard.insert(session_id.to_string(), run.clone());
         Ok(run)
@@ -193,6 +265,19 @@ impl RunRegistry {
         None
     }
 
+    pub async fn active_count(&self) -> usize {
+        self.active.read().await.len()
+    }
+
+    pub async fn count_by_priority(&self, priority: RunPriority) -> usize {
+        self.active
+            .read()
+            .await
+            .values()
+            .filter(|run| run.priority == priority)
+            .count()
+    }
+
     pub async fn finish_active(&self, session_id: &str) -> Option<ActiveRun> {
         self.active.write().await.remove(session_id)
     }
@@ -227,6 +312,14 @@ pub fn now_ms() -> u64 {
         .unwrap_or(0)
 }
 
+/// `text` cut to at most `max_chars` characters, with `…` marking the cut.
+pub(crate) fn clip(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((idx, _)) => format!("{}…", &text[..idx]),
+        None => text.to_string(),
+    }
+}
+
 pub fn build_id() -> String {
     if let Some(explicit) = option_env!("TANDEM_BUILD_ID") {
         let trimmed = explicit.trim();
@@ -308,6 +401,7 @@ pub struct GovernedMemoryRecord {
     pub classification: MemoryClassification,
     pub metadata: Option<Value>,
     pub source_memory_id: Option<String>,
+    pub redaction: Option<RedactionManifest>,
     pub created_at_ms: u64,
 }
 
@@ -327,7 +421,7 @@ pub struct MemoryAuditEvent {
     pub created_at_ms: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SharedResourceRecord {
     pub key: String,
     pub value: Value,
@@ -383,6 +477,33 @@ pub struct RoutineSpec {
     pub next_fire_at_ms: Option<u64>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub last_fired_at_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_policy: Option<RoutineApprovalPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_mode: Option<routine_templates::RoutineTemplateMode>,
+    /// Owning tenant in isolation mode; see [`tenants`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    /// Opt-in reuse of earlier answers; see [`routine_cache`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_cache: Option<routine_cache::RoutineResponseCachePolicy>,
+    /// Failure and recovery alerts; see [`routine_notifications`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<routine_notifications::RoutineNotificationPolicy>,
+}
+
+/// Who may approve a routine's pending runs and what happens when nobody does.
+/// Approvers are Tandem user ids (see channel identity mapping).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RoutineApprovalPolicy {
+    #[serde(default)]
+    pub approvers: Vec<String>,
+    #[serde(default)]
+    pub escalation_approvers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub escalate_after_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_deny_after_ms: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -455,6 +576,45 @@ pub struct RoutineRunRecord {
     pub output_targets: Vec<String>,
     #[serde(default)]
     pub artifacts: Vec<RoutineRunArtifact>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub approvers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approval_escalated_at_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decided_by: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change_summary: Option<routine_diff::RoutineArtifactChangeSummary>,
+    #[serde(default = "routine_run_priority")]
+    pub priority: RunPriority,
+    /// Time spent queued before the executor claimed the run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_wait_ms: Option<u64>,
+    /// Id of the HTTP request that created the run; the executor runs it under
+    /// the same request id so its events can be traced back to that request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Outcome of the machine-checkable `success_criteria`, evaluated after
+    /// the run's model turn finished.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub criteria_results: Vec<success_criteria::CriterionResult>,
+    /// Session the executor ran the run in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Session a recovered run continues in instead of starting a new one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resume_session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recoveries: Vec<routine_recovery::RunRecovery>,
+    /// Set when the run was answered from the routine's response cache.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_hit: Option<routine_cache::RunCacheHit>,
+    /// Stages of a `pipeline.<name>` run, in order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pipeline_stages: Vec<agent_pipelines::PipelineStageRecord>,
+}
+
+fn routine_run_priority() -> RunPriority {
+    RunPriority::Routine
 }
 
 #[derive(Debug, Clone)]
@@ -488,11 +648,51 @@ pub enum ResourceStoreError {
     PersistFailed { message: String },
 }
 
+/// One operation of an all-or-nothing shared resource batch.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum SharedResourceOp {
+    Put {
+        key: String,
+        value: Value,
+        #[serde(default)]
+        if_match_rev: Option<u64>,
+        #[serde(default)]
+        ttl_ms: Option<u64>,
+    },
+    Delete {
+        key: String,
+        #[serde(default)]
+        if_match_rev: Option<u64>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SharedResourceOpResult {
+    pub op: &'static str,
+    pub key: String,
+    /// New revision for puts; revision of the removed record for deletes.
+    pub rev: Option<u64>,
+    /// False for a delete of a key that did not exist.
+    pub applied: bool,
+}
+
+/// A failed batch; `op_index` points at the operation that caused it.
+#[derive(Debug, Clone)]
+pub struct ResourceBatchError {
+    pub op_index: Option<usize>,
+    pub error: ResourceStoreError,
+}
+
+pub const MAX_RESOURCE_BATCH_OPS: usize = 100;
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum RoutineStoreError {
     InvalidRoutineId { routine_id: String },
     InvalidSchedule { detail: String },
+    InvalidSuccessCriteria { detail: String },
+    InvalidNotifications { detail: String },
     PersistFailed { message: String },
 }
 
@@ -531,11 +731,18 @@ pub struct AppState {
     pub engine_leases: Arc<RwLock<std::collections::HashMap<String, EngineLease>>>,
     pub run_registry: RunRegistry,
     pub run_stale_ms: u64,
+    pub event_coalesce_ms: u64,
     pub memory_records: Arc<RwLock<std::collections::HashMap<String, GovernedMemoryRecord>>>,
     pub memory_audit_log: Arc<RwLock<Vec<MemoryAuditEvent>>>,
+    pub access_log: access_log::AccessLog,
+    pub sse_clients: sse_clients::SseClientRegistry,
+    pub sse_settings: sse_clients::SseSettings,
+    pub memory_redaction_profiles: Arc<RwLock<std::collections::HashMap<String, RedactionProfile>>>,
+    memory_redaction_key: Arc<tokio::sync::OnceCell<Vec<u8>>>,
     pub missions: Arc<RwLock<std::collections::HashMap<String, MissionState>>>,
     pub shared_resources: Arc<RwLock<std::collections::HashMap<String, SharedResourceRecord>>>,
     pub shared_resources_path: PathBuf,
+    pub resource_snapshots: resource_snapshots::ResourceSnapshotStore,
     pub routines: Arc<RwLock<std::collections::HashMap<String, RoutineSpec>>>,
     pub routine_history: Arc<RwLock<std::collections::HashMap<String, Vec<RoutineHistoryEvent>>>>,
     pub routine_runs: Arc<RwLock<std::collections::HashMap<String, RoutineRunRecord>>>,
@@ -544,7 +751,30 @@ pub struct AppState {
     pub routines_path: PathBuf,
     pub routine_history_path: PathBuf,
     pub routine_runs_path: PathBuf,
+    pub storage_stats_history: Arc<RwLock<storage_stats::StorageStatsHistory>>,
+    pub channel_identities_path: PathBuf,
+    pub user_usage: quotas::UserUsageTracker,
+    pub feedback: feedback::FeedbackStore,
+    pub routine_cache: routine_cache::RoutineResponseCache,
+    pub routine_notifier: routine_notifications::RoutineNotifier,
+    pub secure_links: secure_links::SecureLinks,
+    pub session_filters: session_filters::SessionFilterStore,
+    pub tenants: tenants::TenantRegistry,
+    pub run_timelines: run_timeline::RunTimelineRecorder,
+    pub command_audit: command_audit::CommandAuditLog,
+    pub audit_chain: audit_chain::AuditChain,
+    pub jobs: jobs::JobRegistry,
+    pub file_locks: tandem_runtime::FileLockRegistry,
+    pub event_rules: event_rules::EventRulesEngine,
+    pub persistence: persistence::PersistenceHealth,
+    pub replication: replication::ReplicationHandle,
+    pub shared_sync: shared_sync::SharedSyncHandle,
+    pub host_monitor: host_monitor::HostMonitor,
+    pub workspace_snapshots: tandem_runtime::WorkspaceSnapshotStore,
+    pub state_dir: PathBuf,
+    pub backups_dir: PathBuf,
     pub agent_teams: AgentTeamRuntime,
+    pub oidc: oidc::OidcAuth,
     pub web_ui_enabled: Arc<AtomicBool>,
     pub web_ui_prefix: Arc<std::sync::RwLock<String>>,
     pub server_base_url: Arc<std::sync::RwLock<String>>,
@@ -574,11 +804,20 @@ impl AppState {
             engine_leases: Arc::new(RwLock::new(std::collections::HashMap::new())),
             run_registry: RunRegistry::new(),
             run_stale_ms: resolve_run_stale_ms(),
+            event_coalesce_ms: resolve_event_coalesce_ms(),
             memory_records: Arc::new(RwLock::new(std::collections::HashMap::new())),
             memory_audit_log: Arc::new(RwLock::new(Vec::new())),
+            access_log: access_log::AccessLog::new(access_log::AccessLogConfig::from_env()),
+            sse_clients: sse_clients::SseClientRegistry::new(),
+            sse_settings: sse_clients::SseSettings::from_env(),
+            memory_redaction_profiles: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            memory_redaction_key: Arc::new(tokio::sync::OnceCell::new()),
             missions: Arc::new(RwLock::new(std::collections::HashMap::new())),
             shared_resources: Arc::new(RwLock::new(std::collections::HashMap::new())),
             shared_resources_path: resolve_shared_resources_path(),
+            resource_snapshots: resource_snapshots::ResourceSnapshotStore::new(
+                resolve_resource_snapshots_path(),
+            ),
             routines: Arc::new(RwLock::new(std::collections::HashMap::new())),
             routine_history: Arc::new(RwLock::new(std::collections::HashMap::new())),
             routine_runs: Arc::new(RwLock::new(std::collections::HashMap::new())),
@@ -586,7 +825,34 @@ impl AppState {
             routines_path: resolve_routines_path(),
             routine_history_path: resolve_routine_history_path(),
             routine_runs_path: resolve_routine_runs_path(),
+            storage_stats_history: Arc::new(RwLock::new(storage_stats::StorageStatsHistory::new())),
+            channel_identities_path: tandem_channels::identity::IdentityDirectory::path(),
+            user_usage: quotas::UserUsageTracker::new(resolve_user_usage_path()),
+            feedback: feedback::FeedbackStore::new(resolve_feedback_path()),
+            routine_cache: routine_cache::RoutineResponseCache::new(resolve_state_dir()),
+            routine_notifier: routine_notifications::RoutineNotifier::default(),
+            secure_links: secure_links::SecureLinks::new(resolve_secure_links_path()),
+            session_filters: session_filters::SessionFilterStore::new(
+                resolve_session_filters_path(),
+            ),
+            tenants: tenants::TenantRegistry::new(resolve_tenants_path()),
+            run_timelines: run_timeline::RunTimelineRecorder::new(resolve_run_timelines_dir()),
+            command_audit: command_audit::CommandAuditLog::new(resolve_command_audit_path()),
+            audit_chain: audit_chain::AuditChain::default(),
+            jobs: jobs::JobRegistry::new(),
+            file_locks: tandem_runtime::FileLockRegistry::from_env(),
+            event_rules: event_rules::EventRulesEngine::default(),
+            persistence: persistence::PersistenceHealth::default(),
+            replication: replication::ReplicationHandle::default(),
+            shared_sync: shared_sync::SharedSyncHandle::default(),
+            host_monitor: host_monitor::HostMonitor::default(),
+            workspace_snapshots: tandem_runtime::WorkspaceSnapshotStore::new(
+                resolve_workspace_snapshots_dir(),
+            ),
+            state_dir: resolve_state_dir(),
+            backups_dir: resolve_backups_dir(),
             agent_teams: AgentTeamRuntime::new(resolve_agent_team_audit_path()),
+            oidc: oidc::OidcAuth::default(),
             web_ui_enabled: Arc::new(AtomicBool::new(false)),
             web_ui_prefix: Arc::new(std::sync::RwLock::new("/admin".to_string())),
             server_base_url: Arc::new(std::sync::RwLock::new("http://127.0.0.1:39731".to_string())),
@@ -684,10 +950,50 @@ impl AppState {
                 crate::agent_teams::ServerToolPolicyHook::new(self.clone()),
             ))
             .await;
+        self.tools
+            .register_tool(
+                crate::channel_files::CHANNEL_SEND_FILE_TOOL.to_string(),
+                std::sync::Arc::new(crate::channel_files::ChannelSendFileTool::new(self.clone())),
+            )
+            .await;
+        self.tools
+            .register_tool(
+                crate::report_builder::REPORT_BUILD_TOOL.to_string(),
+                std::sync::Arc::new(crate::report_builder::ReportBuildTool::new(self.clone())),
+            )
+            .await;
+        self.tools
+            .register_tool(
+                crate::workspace_changes::WORKSPACE_CHANGES_TOOL.to_string(),
+                std::sync::Arc::new(crate::workspace_changes::WorkspaceChangesTool::new(
+                    self.clone(),
+                )),
+            )
+            .await;
+        self.apply_tool_config().await;
         let _ = self.load_shared_resources().await;
+        if let Err(err) = self.resource_snapshots.load().await {
+            tracing::warn!("failed to load resource snapshots: {err:#}");
+        }
         let _ = self.load_routines().await;
         let _ = self.load_routine_history().await;
+        let runs_loaded_at_ms = now_ms();
         let _ = self.load_routine_runs().await;
+        self.recover_interrupted_routine_runs(runs_loaded_at_ms)
+            .await;
+        // Pause requests no run reached died with the previous process.
+        self.engine_loop.run_pauses().clear_unreached_all().await;
+        self.user_usage.load().await?;
+        if let Err(err) = self.feedback.load().await {
+            tracing::warn!("failed to load message feedback: {err:#}");
+        }
+        if let Err(err) = self.session_filters.load().await {
+            tracing::warn!("failed to load saved session filters: {err:#}");
+        }
+        if let Err(err) = self.secure_links.load().await {
+            tracing::warn!("failed to load secure links: {err:#}");
+        }
+        self.tenants.load().await?;
         let workspace_root = self.workspace_index.snapshot().await.root;
         let _ = self
             .agent_teams
@@ -707,6 +1013,86 @@ impl AppState {
         startup.last_error = Some(error.into());
     }
 
+    /// Parse one top-level block of the effective config. A malformed block
+    /// is logged and falls back to its defaults without affecting the others.
+    pub(crate) async fn config_section<T>(&self, key: &str) -> T
+    where
+        T: serde::de::DeserializeOwned + Default,
+    {
+        let effective = self.config.get_effective_value().await;
+        let Some(section) = effective.get(key).filter(|value| !value.is_null()) else {
+            return T::default();
+        };
+        match serde_json::from_value(section.clone()) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                tracing::warn!("invalid `{key}` config block, using defaults: {err}");
+                T::default()
+            }
+        }
+    }
+
+    /// Effective locale settings: `locale` config block with env overrides applied.
+    pub async fn locale_settings(&self) -> tandem_channels::locale::LocaleSettings {
+        self.config_section::<tandem_channels::locale::LocaleSettings>("locale")
+            .await
+            .with_env_overrides()
+    }
+
+    /// Effective per-user quota limits from the `quotas` config block.
+    pub async fn quota_limits(&self, user_id: &str) -> quotas::QuotaLimits {
+        self.config_section::<quotas::QuotaConfig>("quotas")
+            .await
+            .limits_for(user_id)
+    }
+
+    /// Lifecycle hooks from the `run_hooks` config block.
+    pub async fn run_hooks_config(&self) -> run_hooks::RunHooksConfig {
+        self.config_section("run_hooks").await
+    }
+
+    /// Policy for routine runs interrupted by a restart, from the
+    /// `routine_recovery` config block.
+    pub async fn routine_recovery_config(&self) -> routine_recovery::RoutineRecoveryConfig {
+        self.config_section("routine_recovery").await
+    }
+
+    /// Push the `tool_timeouts`, `command_policy` and `workspace_ignore`
+    /// config blocks and the server-wide `global_memory` feature flag into the
+    /// tools, and the `provider_lanes` block into the engine loop; called at
+    /// startup, after every config patch and after a flag is toggled. The
+    /// workspace index is rebuilt when the ignore rules change.
+    pub async fn apply_tool_config(&self) {
+        let lanes: tandem_core::ProviderLanesConfig = self.config_section("provider_lanes").await;
+        if let Some(concurrency) = lanes.concurrency {
+            self.engine_loop
+                .provider_lanes()
+                .set_limit(Some(concurrency));
+        }
+        self.tools
+            .set_timeouts(self.config_section("tool_timeouts").await)
+            .await;
+        self.tools
+            .set_command_policy(self.config_section("command_policy").await)
+            .await;
+        let flags = self.config.feature_flags().await;
+        for key in flags.unknown_keys() {
+            tracing::warn!("ignoring unknown feature flag `{key}`");
+        }
+        tandem_tools::set_global_memory_flag(flags.is_enabled("global_memory", None));
+        if tandem_runtime::set_workspace_ignore_config(
+            self.config_section("workspace_ignore").await,
+        ) {
+            self.refresh_workspace_index_when_idle();
+        }
+    }
+
+    /// The raw `event_rules` block; parsed rule by rule in
+    /// [`event_rules::parse_rules`] so one bad rule does not disable the rest.
+    pub async fn event_rules_config(&self) -> Value {
+        self.config_section("event_rules").await
+    }
+
     pub async fn channel_statuses(&self) -> std::collections::HashMap<String, ChannelStatus> {
         let runtime = self.channels_runtime.lock().await;
         runtime.statuses.clone()
@@ -756,7 +1142,9 @@ impl AppState {
             },
         );
 
-        if let Some(channels_cfg) = build_channels_config(self, &parsed.channels).await {
+        if let Some(channels_cfg) =
+            build_channels_config(self, &parsed.channels, &self.locale_settings().await).await
+        {
             let listeners = tandem_channels::start_channel_listeners(channels_cfg).await;
             runtime.listeners = Some(listeners);
             for status in status_map.values_mut() {
@@ -776,6 +1164,27 @@ impl AppState {
         Ok(())
     }
 
+    /// Key for redaction manifest fingerprints, kept in
+    /// `<state>/memory_redaction.key` (never served) so fingerprints stay
+    /// comparable across restarts.
+    pub async fn memory_redaction_key(&self) -> Vec<u8> {
+        self.memory_redaction_key
+            .get_or_init(|| async {
+                let path = self.state_dir.join("memory_redaction.key");
+                match load_or_create_secret_key(&path).await {
+                    Ok(key) => key,
+                    Err(err) => {
+                        tracing::warn!(
+                            "memory redaction key unavailable ({err:#}); fingerprints will only match within this process"
+                        );
+                        random_secret_key()
+                    }
+                }
+            })
+            .await
+            .clone()
+    }
+
     pub async fn load_shared_resources(&self) -> anyhow::Result<()> {
         if !self.shared_resources_path.exists() {
             return Ok(());
@@ -830,6 +1239,20 @@ impl AppState {
         rows
     }
 
+    /// Every record under `prefix`, uncapped and ordered by key.
+    pub async fn shared_resources_under(&self, prefix: &str) -> Vec<SharedResourceRecord> {
+        let mut rows = self
+            .shared_resources
+            .read()
+            .await
+            .values()
+            .filter(|record| resource_snapshots::key_is_under(&record.key, prefix))
+            .cloned()
+            .collect::<Vec<_>>();
+        rows.sort_by(|a, b| a.key.cmp(&b.key));
+        rows
+    }
+
     pub async fn put_shared_resource(
         &self,
         key: String,
@@ -874,7 +1297,10 @@ impl AppState {
         let previous = guard.insert(key.clone(), record.clone());
         drop(guard);
 
-        if let Err(error) = self.persist_shared_resources().await {
+        if let Err(error) = self
+            .persist_checked(persistence::PersistTarget::SharedResources)
+            .await
+        {
             let mut rollback = self.shared_resources.write().await;
             if let Some(previous) = previous {
                 rollback.insert(key, previous);
@@ -916,7 +1342,10 @@ impl AppState {
         let removed = guard.remove(key);
         drop(guard);
 
-        if let Err(error) = self.persist_shared_resources().await {
+        if let Err(error) = self
+            .persist_checked(persistence::PersistTarget::SharedResources)
+            .await
+        {
             if let Some(record) = removed.clone() {
                 self.shared_resources
                     .write()
@@ -931,6 +1360,119 @@ impl AppState {
         Ok(removed)
     }
 
+    /// Apply `ops` in order against a single snapshot of the store. Either
+    /// every operation is applied and persisted, or none is. Revision guards
+    /// see the effects of earlier operations in the same batch.
+    pub async fn apply_shared_resource_batch(
+        &self,
+        ops: Vec<SharedResourceOp>,
+        updated_by: String,
+    ) -> Result<Vec<SharedResourceOpResult>, ResourceBatchError> {
+        let now = now_ms();
+        let mut guard = self.shared_resources.write().await;
+        let mut staged: std::collections::HashMap<String, Option<SharedResourceRecord>> =
+            std::collections::HashMap::new();
+        let mut results = Vec::with_capacity(ops.len());
+        for (index, op) in ops.into_iter().enumerate() {
+            let (key, if_match_rev) = match &op {
+                SharedResourceOp::Put {
+                    key, if_match_rev, ..
+                }
+                | SharedResourceOp::Delete { key, if_match_rev } => (key.clone(), *if_match_rev),
+            };
+            if !is_valid_resource_key(&key) {
+                return Err(ResourceBatchError {
+                    op_index: Some(index),
+                    error: ResourceStoreError::InvalidKey { key },
+                });
+            }
+            let current = staged
+                .get(&key)
+                .cloned()
+                .unwrap_or_else(|| guard.get(&key).cloned());
+            let current_rev = current.as_ref().map(|row| row.rev);
+            if let Some(expected) = if_match_rev {
+                if current_rev != Some(expected) {
+                    return Err(ResourceBatchError {
+                        op_index: Some(index),
+                        error: ResourceStoreError::RevisionConflict(ResourceConflict {
+                            key,
+                            expected_rev: Some(expected),
+                            current_rev,
+                        }),
+                    });
+                }
+            }
+            match op {
+                SharedResourceOp::Put { value, ttl_ms, .. } => {
+                    let rev = current_rev.map(|rev| rev.saturating_add(1)).unwrap_or(1);
+                    staged.insert(
+                        key.clone(),
+                        Some(SharedResourceRecord {
+                            key: key.clone(),
+                            value,
+                            rev,
+                            updated_at_ms: now,
+                            updated_by: updated_by.clone(),
+                            ttl_ms,
+                        }),
+                    );
+                    results.push(SharedResourceOpResult {
+                        op: "put",
+                        key,
+                        rev: Some(rev),
+                        applied: true,
+                    });
+                }
+                SharedResourceOp::Delete { .. } => {
+                    staged.insert(key.clone(), None);
+                    results.push(SharedResourceOpResult {
+                        op: "delete",
+                        key,
+                        rev: current_rev,
+                        applied: current.is_some(),
+                    });
+                }
+            }
+        }
+
+        let mut previous = Vec::with_capacity(staged.len());
+        for (key, next) in staged {
+            let prior = match next.clone() {
+                Some(record) => guard.insert(key.clone(), record),
+                None => guard.remove(&key),
+            };
+            previous.push((key, prior, next));
+        }
+        drop(guard);
+
+        if let Err(error) = self
+            .persist_checked(persistence::PersistTarget::SharedResources)
+            .await
+        {
+            // Other writers may have committed since the guard was dropped;
+            // only keys still holding what this batch wrote are restored.
+            let mut rollback = self.shared_resources.write().await;
+            for (key, prior, written) in previous {
+                if rollback.get(&key) != written.as_ref() {
+                    continue;
+                }
+                match prior {
+                    Some(record) => rollback.insert(key, record),
+                    None => rollback.remove(&key),
+                };
+            }
+            return Err(ResourceBatchError {
+                op_index: None,
+                error: ResourceStoreError::PersistFailed {
+                    message: error.to_string(),
+                },
+            });
+        }
+
+        Ok(results)
+    }
+
     pub async fn load_routines(&self) -> anyhow::Result<()> {
         if !self.routines_path.exists() {
             return Ok(());
@@ -1030,6 +1572,16 @@ impl AppState {
             }
             RoutineSchedule::Cron { .. } => None,
         };
+        success_criteria::checkable_criteria(&routine.args)
+            .and_then(|criteria| {
+                success_criteria::check_commands_allowed(&criteria, routine.tenant_id.as_deref())
+            })
+            .map_err(|detail| RoutineStoreError::InvalidSuccessCriteria { detail })?;
+        if let Some(notifications) = &routine.notifications {
+            notifications
+                .validate()
+                .map_err(|detail| RoutineStoreError::InvalidNotifications { detail })?;
+        }
         if routine.next_fire_at_ms.is_none() {
             routine.next_fire_at_ms = Some(now_ms().saturating_add(interval.unwrap_or(60) * 1000));
         }
@@ -1038,7 +1590,10 @@ impl AppState {
         let previous = guard.insert(routine.routine_id.clone(), routine.clone());
         drop(guard);
 
-        if let Err(error) = self.persist_routines().await {
+        if let Err(error) = self
+            .persist_checked(persistence::PersistTarget::Routines)
+            .await
+        {
             let mut rollback = self.routines.write().await;
             if let Some(previous) = previous {
                 rollback.insert(previous.routine_id.clone(), previous);
@@ -1077,7 +1632,10 @@ impl AppState {
         let removed = guard.remove(routine_id);
         drop(guard);
 
-        if let Err(error) = self.persist_routines().await {
+        if let Err(error) = self
+            .persist_checked(persistence::PersistTarget::Routines)
+            .await
+        {
             if let Some(removed) = removed.clone() {
                 self.routines
                     .write()
@@ -1125,7 +1683,9 @@ impl AppState {
             });
         }
         drop(guard);
-        let _ = self.persist_routines().await;
+        let _ = self
+            .persist_checked(persistence::PersistTarget::Routines)
+            .await;
         plans
     }
 
@@ -1139,7 +1699,9 @@ impl AppState {
         routine.last_fired_at_ms = Some(fired_at_ms);
         let updated = routine.clone();
         drop(guard);
-        let _ = self.persist_routines().await;
+        let _ = self
+            .persist_checked(persistence::PersistTarget::Routines)
+            .await;
         Some(updated)
     }
 
@@ -1150,7 +1712,9 @@ impl AppState {
             .or_default()
             .push(event);
         drop(history);
-        let _ = self.persist_routine_history().await;
+        let _ = self
+            .persist_checked(persistence::PersistTarget::RoutineHistory)
+            .await;
     }
 
     pub async fn list_routine_history(
@@ -1178,8 +1742,12 @@ impl AppState {
         run_count: u32,
         status: RoutineRunStatus,
         detail: Option<String>,
-    ) -> RoutineRunRecord {
+    ) -> anyhow::Result<RoutineRunRecord> {
         let now = now_ms();
+        let approvers = match (&status, routine.approval_policy.as_ref()) {
+            (RoutineRunStatus::PendingApproval, Some(policy)) => policy.approvers.clone(),
+            _ => Vec::new(),
+        };
         let record = RoutineRunRecord {
             run_id: format!("routine-run-{}", uuid::Uuid::new_v4()),
             routine_id: routine.routine_id.clone(),
@@ -1201,13 +1769,32 @@ impl AppState {
             allowed_tools: routine.allowed_tools.clone(),
             output_targets: routine.output_targets.clone(),
             artifacts: Vec::new(),
+            approvers,
+            approval_escalated_at_ms: None,
+            decided_by: None,
+            change_summary: None,
+            priority: RunPriority::Routine,
+            queue_wait_ms: None,
+            request_id: tandem_core::current_request_id(),
+            criteria_results: Vec::new(),
+            session_id: None,
+            resume_session_id: None,
+            recoveries: Vec::new(),
+            cache_hit: None,
+            pipeline_stages: Vec::new(),
         };
         self.routine_runs
             .write()
             .await
             .insert(record.run_id.clone(), record.clone());
-        let _ = self.persist_routine_runs().await;
-        record
+        if let Err(err) = self
+            .persist_checked(persistence::PersistTarget::RoutineRuns)
+            .await
+        {
+            self.routine_runs.write().await.remove(&record.run_id);
+            return Err(err);
+        }
+        Ok(record)
     }
 
     pub async fn get_routine_run(&self, run_id: &str) -> Option<RoutineRunRecord> {
@@ -1238,9 +1825,46 @@ impl AppState {
         rows
     }
 
+    /// Ids of routine runs created in `[since_ms, until_ms)`, oldest first.
+    pub async fn routine_run_ids_between(
+        &self,
+        routine_id: Option<&str>,
+        since_ms: Option<u64>,
+        until_ms: Option<u64>,
+    ) -> Vec<String> {
+        let mut rows = self
+            .routine_runs
+            .read()
+            .await
+            .values()
+            .filter(|row| routine_id.is_none_or(|id| row.routine_id == id))
+            .filter(|row| since_ms.is_none_or(|since| row.created_at_ms >= since))
+            .filter(|row| until_ms.is_none_or(|until| row.created_at_ms < until))
+            .map(|row| (row.created_at_ms, row.run_id.clone()))
+            .collect::<Vec<_>>();
+        rows.sort();
+        rows.into_iter().map(|(_, run_id)| run_id).collect()
+    }
+
+    /// The runs among `run_ids` that still exist, in the given order.
+    pub async fn routine_runs_by_id(&self, run_ids: &[String]) -> Vec<RoutineRunRecord> {
+        let runs = self.routine_runs.read().await;
+        run_ids
+            .iter()
+            .filter_map(|run_id| runs.get(run_id).cloned())
+            .collect()
+    }
+
+    /// Claim the oldest queued routine run. Routine runs yield to active
+    /// interactive runs until the oldest one has waited past the starvation
+    /// window.
     pub async fn claim_next_queued_routine_run(&self) -> Option<RoutineRunRecord> {
+        let interactive_active = self
+            .run_registry
+            .count_by_priority(RunPriority::Interactive)
+            .await;
         let mut guard = self.routine_runs.write().await;
-        let next_run_id = guard
+        let (next_run_id, created_at_ms) = guard
             .values()
             .filter(|row| row.status == RoutineRunStatus::Queued)
             .min_by(|a, b| {
@@ -1248,15 +1872,23 @@ impl AppState {
                     .cmp(&b.created_at_ms)
                     .then_with(|| a.run_id.cmp(&b.run_id))
             })
-            .map(|row| row.run_id.clone())?;
+            .map(|row| (row.run_id.clone(), row.created_at_ms))?;
         let now = now_ms();
+        let waited_ms = now.saturating_sub(created_at_ms);
+        let starvation_ms = tandem_core::priority_starvation_window().as_millis() as u64;
+        if interactive_active > 0 && waited_ms < starvation_ms {
+            return None;
+        }
         let row = guard.get_mut(&next_run_id)?;
         row.status = RoutineRunStatus::Running;
         row.updated_at_ms = now;
         row.started_at_ms = Some(now);
+        row.queue_wait_ms = Some(waited_ms);
         let claimed = row.clone();
         drop(guard);
-        let _ = self.persist_routine_runs().await;
+        let _ = self
+            .persist_checked(persistence::PersistTarget::RoutineRuns)
+            .await;
         Some(claimed)
     }
 
@@ -1294,14 +1926,102 @@ impl AppState {
             .remove(session_id);
     }
 
+    /// Interpolate `{{variables}}` in the run's entrypoint and args and store
+    /// the rendered values on the run. Errors name the unresolved variables
+    /// when the routine is in strict mode.
+    pub async fn resolve_routine_run_templates(
+        &self,
+        run: &RoutineRunRecord,
+        workspace_root: &str,
+    ) -> Result<RoutineRunRecord, String> {
+        use routine_templates::{
+            referenced_resource_keys, render_template_str, render_template_value,
+            RoutineTemplateContext, RoutineTemplateMode,
+        };
+        let routine = self.get_routine(&run.routine_id).await;
+        let mode = routine
+            .as_ref()
+            .and_then(|r| r.template_mode)
+            .unwrap_or_default();
+        let now = chrono::Utc::now();
+        let mut ctx = RoutineTemplateContext::default();
+        let mut set = |name: &str, value: String| {
+            ctx.vars.insert(name.to_string(), value);
+        };
+        set("date", now.format("%Y-%m-%d").to_string());
+        set("time", now.format("%H:%M:%S").to_string());
+        set("datetime", now.to_rfc3339());
+        set("timestamp_ms", now.timestamp_millis().to_string());
+        set("workspace_root", workspace_root.to_string());
+        set("routine.id", run.routine_id.clone());
+        if let Some(routine) = routine.as_ref() {
+            set("routine.name", routine.name.clone());
+        }
+        set("run.id", run.run_id.clone());
+        set("run.trigger_type", run.trigger_type.clone());
+        set("run.count", run.run_count.to_string());
+        let last_run = self
+            .list_routine_runs(Some(&run.routine_id), 500)
+            .await
+            .into_iter()
+            .find(|row| row.run_id != run.run_id && row.finished_at_ms.is_some());
+        if let Some(last) = last_run {
+            let status = serde_json::to_value(&last.status)
+                .ok()
+                .and_then(|v| v.as_str().map(String::from))
+                .unwrap_or_default();
+            set("last_run.id", last.run_id);
+            set("last_run.status", status);
+            if let Some(finished) = last.finished_at_ms {
+                set("last_run.finished_at_ms", finished.to_string());
+            }
+            set("last_run.detail", last.detail.unwrap_or_default());
+        }
+        let entrypoint = Value::String(run.entrypoint.clone());
+        for key in referenced_resource_keys(&[&entrypoint, &run.args]) {
+            if let Some(record) = self.get_shared_resource(&key).await {
+                ctx.resources.insert(key, record.value);
+            }
+        }
+
+        let mut unresolved = Vec::new();
+        let rendered_entrypoint = render_template_str(&run.entrypoint, &ctx, &mut unresolved);
+        let rendered_args = render_template_value(&run.args, &ctx, &mut unresolved);
+        if !unresolved.is_empty() {
+            let detail = format!("unresolved template variables: {}", unresolved.join(", "));
+            if mode == RoutineTemplateMode::Strict {
+                return Err(detail);
+            }
+            tracing::warn!("routine run {}: {}", run.run_id, detail);
+        }
+        if rendered_entrypoint == run.entrypoint && rendered_args == run.args {
+            return Ok(run.clone());
+        }
+        let mut guard = self.routine_runs.write().await;
+        let Some(row) = guard.get_mut(&run.run_id) else {
+            return Ok(run.clone());
+        };
+        row.entrypoint = rendered_entrypoint;
+        row.args = rendered_args;
+        let updated = row.clone();
+        drop(guard);
+        let _ = self
+            .persist_checked(persistence::PersistTarget::RoutineRuns)
+            .await;
+        Ok(updated)
+    }
+
     pub async fn update_routine_run_status(
         &self,
         run_id: &str,
         status: RoutineRunStatus,
         reason: Option<String>,
-    ) -> Option<RoutineRunRecord> {
+    ) -> anyhow::Result<Option<RoutineRunRecord>> {
         let mut guard = self.routine_runs.write().await;
-        let row = guard.get_mut(run_id)?;
+        let Some(row) = guard.get_mut(run_id) else {
+            return Ok(None);
+        };
+        let previous = row.clone();
         row.status = status.clone();
         row.updated_at_ms = now_ms();
         match status {
@@ -1330,56 +2050,145 @@ impl AppState {
         }
         let updated = row.clone();
         drop(guard);
-        let _ = self.persist_routine_runs().await;
-        Some(updated)
+        let finished = previous.status != updated.status;
+        self.persist_run_change(previous).await?;
+        if finished {
+            self.notify_routine_run_finished(&updated).await;
+        }
+        Ok(Some(updated))
+    }
+
+    /// Record the session a run executes in; a pending resume is consumed.
+    pub async fn set_routine_run_session(
+        &self,
+        run_id: &str,
+        session_id: &str,
+    ) -> anyhow::Result<Option<RoutineRunRecord>> {
+        let mut guard = self.routine_runs.write().await;
+        let Some(row) = guard.get_mut(run_id) else {
+            return Ok(None);
+        };
+        let previous = row.clone();
+        row.session_id = Some(session_id.to_string());
+        row.resume_session_id = None;
+        row.updated_at_ms = now_ms();
+        let updated = row.clone();
+        drop(guard);
+        self.persist_run_change(previous).await?;
+        Ok(Some(updated))
     }
 
+    /// Add an artifact to a run. Like status updates, the change is rolled
+    /// back and the error returned when it cannot be written to disk.
     pub async fn append_routine_run_artifact(
         &self,
         run_id: &str,
         artifact: RoutineRunArtifact,
-    ) -> Option<RoutineRunRecord> {
+    ) -> anyhow::Result<Option<RoutineRunRecord>> {
         let mut guard = self.routine_runs.write().await;
-        let row = guard.get_mut(run_id)?;
+        let Some(row) = guard.get_mut(run_id) else {
+            return Ok(None);
+        };
+        let previous = row.clone();
         row.updated_at_ms = now_ms();
         row.artifacts.push(artifact);
         let updated = row.clone();
         drop(guard);
-        let _ = self.persist_routine_runs().await;
-        Some(updated)
+        self.persist_run_change(previous).await?;
+        Ok(Some(updated))
+    }
+
+    pub async fn set_routine_run_criteria_results(
+        &self,
+        run_id: &str,
+        results: Vec<success_criteria::CriterionResult>,
+    ) -> anyhow::Result<Option<RoutineRunRecord>> {
+        let mut guard = self.routine_runs.write().await;
+        let Some(row) = guard.get_mut(run_id) else {
+            return Ok(None);
+        };
+        let previous = row.clone();
+        row.updated_at_ms = now_ms();
+        row.criteria_results = results;
+        let updated = row.clone();
+        drop(guard);
+        self.persist_run_change(previous).await?;
+        Ok(Some(updated))
+    }
+
+    /// Persist routine runs after changing one, restoring `previous` in
+    /// memory if the write fails so reads never return unpersisted state.
+    async fn persist_run_change(&self, previous: RoutineRunRecord) -> anyhow::Result<()> {
+        if let Err(err) = self
+            .persist_checked(persistence::PersistTarget::RoutineRuns)
+            .await
+        {
+            self.routine_runs
+                .write()
+                .await
+                .insert(previous.run_id.clone(), previous);
+            return Err(err);
+        }
+        Ok(())
     }
 }
 
 async fn build_channels_config(
     state: &AppState,
     channels: &ChannelsConfigFile,
+    locale: &tandem_channels::locale::LocaleSettings,
 ) -> Option<ChannelsConfig> {
     if channels.telegram.is_none() && channels.discord.is_none() && channels.slack.is_none() {
         return None;
     }
+    if let Err(err) = locale.validate() {
+        tracing::warn!("invalid `locale` config block, rendering channel output in UTC: {err}");
+    }
     Some(ChannelsConfig {
         telegram: channels.telegram.clone().map(|cfg| TelegramConfig {
             bot_token: cfg.bot_token,
             allowed_users: cfg.allowed_users,
             mention_only: cfg.mention_only,
+            quiet_hours: valid_quiet_hours("telegram", cfg.quiet_hours),
         }),
         discord: channels.discord.clone().map(|cfg| DiscordConfig {
             bot_token: cfg.bot_token,
             guild_id: cfg.guild_id,
             allowed_users: cfg.allowed_users,
             mention_only: cfg.mention_only,
+            quiet_hours: valid_quiet_hours("discord", cfg.quiet_hours),
+            history_import: cfg.history_import,
         }),
         slack: channels.slack.clone().map(|cfg| SlackConfig {
             bot_token: cfg.bot_token,
             channel_id: cfg.channel_id,
             allowed_users: cfg.allowed_users,
+            quiet_hours: valid_quiet_hours("slack", cfg.quiet_hours),
+            history_import: cfg.history_import,
         }),
         server_base_url: state.server_base_url(),
         api_token: state.api_token().await.unwrap_or_default(),
         tool_policy: channels.tool_policy.clone(),
+        locale: locale.clone(),
     })
 }
 
+/// Drop a quiet-hours window that slipped past config validation (e.g. a
+/// hand-edited `config.json`) rather than holding deliveries in the wrong zone.
+fn valid_quiet_hours(
+    channel: &str,
+    quiet_hours: Option<tandem_channels::delivery::QuietHours>,
+) -> Option<tandem_channels::delivery::QuietHours> {
+    let quiet_hours = quiet_hours?;
+    match quiet_hours.validate() {
+        Ok(()) => Some(quiet_hours),
+        Err(err) => {
+            tracing::warn!("ignoring `channels.{channel}.quiet_hours`: {err}");
+            None
+        }
+    }
+}
+
 fn normalize_web_ui_prefix(prefix: &str) -> String {
     let trimmed = prefix.trim();
     if trimmed.is_empty() || trimmed == "/" {
@@ -1432,18 +2241,61 @@ fn resolve_run_stale_ms() -> u64 {
         .clamp(30_000, 600_000)
 }
 
-fn resolve_shared_resources_path() -> PathBuf {
-    if let Ok(dir) = std::env::var("TANDEM_STATE_DIR") {
-        let trimmed = dir.trim();
-        if !trimmed.is_empty() {
-            return PathBuf::from(trimmed).join("shared_resources.json");
-        }
-    }
-    default_state_dir().join("shared_resources.json")
+/// Seconds between workspace snapshots; `0` disables periodic snapshots.
+fn resolve_workspace_snapshot_interval_secs() -> u64 {
+    std::env::var("TANDEM_WORKSPACE_SNAPSHOT_INTERVAL_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(|secs| if secs == 0 { 0 } else { secs.max(60) })
+        .unwrap_or(3_600)
 }
 
-fn resolve_routines_path() -> PathBuf {
-    if let Ok(dir) = std::env::var("TANDEM_STATE_DIR") {
+fn resolve_event_coalesce_ms() -> u64 {
+    std::env::var("TANDEM_EVENT_COALESCE_MS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(50)
+        .min(1_000)
+}
+
+fn random_secret_key() -> Vec<u8> {
+    let mut key = uuid::Uuid::new_v4().as_bytes().to_vec();
+    key.extend_from_slice(uuid::Uuid::new_v4().as_bytes());
+    key
+}
+
+/// Read a hex key from `path`, creating it with a fresh random key first.
+async fn load_or_create_secret_key(path: &std::path::Path) -> anyhow::Result<Vec<u8>> {
+    if let Ok(raw) = tokio::fs::read_to_string(path).await {
+        let raw = raw.trim();
+        let key = (0..raw.len())
+            .step_by(2)
+            .map(|i| {
+                raw.get(i..i + 2)
+                    .and_then(|b| u8::from_str_radix(b, 16).ok())
+            })
+            .collect::<Option<Vec<_>>>()
+            .filter(|key| key.len() >= 16)
+            .ok_or_else(|| anyhow::anyhow!("{} is not a hex key", path.display()))?;
+        return Ok(key);
+    }
+    let key = random_secret_key();
+    state_file::write_atomic(path, object_store::hex(&key).as_bytes()).await?;
+    Ok(key)
+}
+
+fn resolve_shared_resources_path() -> PathBuf {
+    if let Ok(dir) = std::env::var("TANDEM_STATE_DIR") {
+        let trimmed = dir.trim();
+        if !trimmed.is_empty() {
+            return PathBuf::from(trimmed).join("shared_resources.json");
+        }
+    }
+    default_state_dir().join("shared_resources.json")
+}
+
+fn resolve_routines_path() -> PathBuf {
+    if let Ok(dir) = std::env::var("TANDEM_STATE_DIR") {
         let trimmed = dir.trim();
         if !trimmed.is_empty() {
             return PathBuf::from(trimmed).join("routines.json");
@@ -1472,6 +2324,116 @@ fn resolve_routine_runs_path() -> PathBuf {
     default_state_dir().join("routine_runs.json")
 }
 
+fn resolve_user_usage_path() -> PathBuf {
+    if let Ok(root) = std::env::var("TANDEM_STATE_DIR") {
+        let trimmed = root.trim();
+        if !trimmed.is_empty() {
+            return PathBuf::from(trimmed).join("user_usage.json");
+        }
+    }
+    default_state_dir().join("user_usage.json")
+}
+
+fn resolve_resource_snapshots_path() -> PathBuf {
+    if let Ok(root) = std::env::var("TANDEM_STATE_DIR") {
+        let trimmed = root.trim();
+        if !trimmed.is_empty() {
+            return PathBuf::from(trimmed).join("shared_resource_snapshots.json");
+        }
+    }
+    default_state_dir().join("shared_resource_snapshots.json")
+}
+
+fn resolve_feedback_path() -> PathBuf {
+    if let Ok(root) = std::env::var("TANDEM_STATE_DIR") {
+        let trimmed = root.trim();
+        if !trimmed.is_empty() {
+            return PathBuf::from(trimmed).join("message_feedback.json");
+        }
+    }
+    default_state_dir().join("message_feedback.json")
+}
+
+fn resolve_session_filters_path() -> PathBuf {
+    if let Ok(root) = std::env::var("TANDEM_STATE_DIR") {
+        let trimmed = root.trim();
+        if !trimmed.is_empty() {
+            return PathBuf::from(trimmed).join("session_filters.json");
+        }
+    }
+    default_state_dir().join("session_filters.json")
+}
+
+fn resolve_secure_links_path() -> PathBuf {
+    if let Ok(root) = std::env::var("TANDEM_STATE_DIR") {
+        let trimmed = root.trim();
+        if !trimmed.is_empty() {
+            return PathBuf::from(trimmed).join("secure_links.json");
+        }
+    }
+    default_state_dir().join("secure_links.json")
+}
+
+fn resolve_tenants_path() -> PathBuf {
+    if let Ok(root) = std::env::var("TANDEM_STATE_DIR") {
+        let trimmed = root.trim();
+        if !trimmed.is_empty() {
+            return PathBuf::from(trimmed).join("tenants.json");
+        }
+    }
+    default_state_dir().join("tenants.json")
+}
+
+fn resolve_workspace_snapshots_dir() -> PathBuf {
+    if let Ok(root) = std::env::var("TANDEM_STATE_DIR") {
+        let trimmed = root.trim();
+        if !trimmed.is_empty() {
+            return PathBuf::from(trimmed).join("workspace_snapshots");
+        }
+    }
+    default_state_dir().join("workspace_snapshots")
+}
+
+fn resolve_run_timelines_dir() -> PathBuf {
+    if let Ok(root) = std::env::var("TANDEM_STATE_DIR") {
+        let trimmed = root.trim();
+        if !trimmed.is_empty() {
+            return PathBuf::from(trimmed).join("run_timelines");
+        }
+    }
+    default_state_dir().join("run_timelines")
+}
+
+fn resolve_command_audit_path() -> PathBuf {
+    if let Ok(root) = std::env::var("TANDEM_STATE_DIR") {
+        let trimmed = root.trim();
+        if !trimmed.is_empty() {
+            return PathBuf::from(trimmed).join("command_audit.jsonl");
+        }
+    }
+    default_state_dir().join("command_audit.jsonl")
+}
+
+fn resolve_state_dir() -> PathBuf {
+    if let Ok(root) = std::env::var("TANDEM_STATE_DIR") {
+        let trimmed = root.trim();
+        if !trimmed.is_empty() {
+            return PathBuf::from(trimmed);
+        }
+    }
+    default_state_dir()
+}
+
+fn resolve_backups_dir() -> PathBuf {
+    if let Ok(root) = std::env::var("TANDEM_STATE_DIR") {
+        let trimmed = root.trim();
+        if !trimmed.is_empty() {
+            return PathBuf::from(trimmed).join("backups");
+        }
+    }
+    default_state_dir().join("backups")
+}
+
 fn resolve_agent_team_audit_path() -> PathBuf {
     if let Ok(base) = std::env::var("TANDEM_STATE_DIR") {
         let trimmed = base.trim();
@@ -1694,10 +2656,22 @@ fn derive_status_index_update(event: &EngineEvent) -> Option<StatusIndexUpdate>
 
 pub async fn run_status_indexer(state: AppState) {
     let mut rx = state.event_bus.subscribe();
+    // Streaming tool-call deltas repeat the same status many times per second;
+    // only persist when the derived value actually changes.
+    let mut last_written: std::collections::HashMap<String, Value> =
+        std::collections::HashMap::new();
     loop {
         match rx.recv().await {
             Ok(event) => {
                 if let Some(update) = derive_status_index_update(&event) {
+                    if last_written.get(&update.key) == Some(&update.value) {
+                        continue;
+                    }
+                    if event.event_type == "session.run.finished" {
+                        last_written.remove(&update.key);
+                    } else {
+                        last_written.insert(update.key.clone(), update.value.clone());
+                    }
                     if let Err(error) = state
                         .put_shared_resource(
                             update.key,
@@ -1731,10 +2705,160 @@ pub async fn run_agent_team_supervisor(state: AppState) {
     }
 }
 
+pub async fn run_usage_tracker(state: AppState) {
+    let mut rx = state.event_bus.subscribe();
+    loop {
+        match rx.recv().await {
+            Ok(event) if event.event_type == "provider.usage" => {
+                let session_id = event
+                    .properties
+                    .get("sessionID")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default();
+                let total_tokens = event
+                    .properties
+                    .get("totalTokens")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(0);
+                if !session_id.is_empty()
+                    && total_tokens > 0
+                    && state
+                        .user_usage
+                        .record_tokens(session_id, total_tokens, now_ms())
+                        .await
+                        .is_some()
+                {
+                    let _ = state
+                        .persist_checked(persistence::PersistTarget::UserUsage)
+                        .await;
+                }
+            }
+            Ok(_) => {}
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+        }
+    }
+}
+
+pub async fn run_timeline_recorder(state: AppState) {
+    let mut rx = state.event_bus.subscribe();
+    loop {
+        match rx.recv().await {
+            Ok(event) => {
+                // Incognito sessions leave no timeline behind.
+                if let Some(session_id) = run_timeline::event_session_id(&event) {
+                    if state.storage.is_incognito(&session_id).await {
+                        continue;
+                    }
+                }
+                if let Err(err) = state.run_timelines.handle_event(&event, now_ms()).await {
+                    tracing::warn!("failed to record run timeline entry: {}", err);
+                }
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+        }
+    }
+}
+
+/// Release a run's file locks once it finishes, fails or is cancelled.
+pub async fn run_file_lock_releaser(state: AppState) {
+    let mut rx = state.event_bus.subscribe();
+    loop {
+        match rx.recv().await {
+            Ok(event) if event.event_type == "session.run.finished" => {
+                let run_id = event
+                    .properties
+                    .get("runID")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default();
+                let session_id = event
+                    .properties
+                    .get("sessionID")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default();
+                let mut released = state.file_locks.release_run(run_id).await;
+                if !session_id.is_empty() {
+                    released += state
+                        .file_locks
+                        .release_run(&format!("session:{session_id}"))
+                        .await;
+                }
+                if released > 0 {
+                    state.event_bus.publish(EngineEvent::new(
+                        "file.lock.released",
+                        serde_json::json!({
+                            "sessionID": session_id,
+                            "runID": run_id,
+                            "count": released,
+                        }),
+                    ));
+                }
+            }
+            Ok(_) => {}
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+        }
+    }
+}
+
+/// Periodically snapshot workspace file hashes so "what changed since X"
+/// can be answered without the agent diffing through bash.
+pub async fn run_workspace_snapshotter(state: AppState) {
+    let interval_secs = resolve_workspace_snapshot_interval_secs();
+    if interval_secs == 0 {
+        return;
+    }
+    loop {
+        let root = state.workspace_index.snapshot().await.root;
+        if !root.is_empty() {
+            match state
+                .workspace_snapshots
+                .capture(std::path::Path::new(&root))
+                .await
+            {
+                Ok(summary) => state.event_bus.publish(EngineEvent::new(
+                    "workspace.snapshot.created",
+                    serde_json::json!({
+                        "snapshotID": summary.snapshot_id,
+                        "root": summary.root,
+                        "fileCount": summary.file_count,
+                    }),
+                )),
+                Err(err) => tracing::warn!("workspace snapshot failed: {err}"),
+            }
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(interval_secs)).await;
+    }
+}
+
+pub async fn run_state_pruner(state: AppState) {
+    // Initial delay so startup is not impacted.
+    tokio::time::sleep(std::time::Duration::from_secs(120)).await;
+    loop {
+        let policy = state.retention_policy().await;
+        if policy.is_enabled() {
+            match state.prune_state(&policy, now_ms(), false).await {
+                Ok(report) if report.total() > 0 => tracing::info!(
+                    "state pruning removed {} routine runs, {} history events, {} audit rows",
+                    report.routine_runs,
+                    report.routine_history,
+                    report.audit_log_rows
+                ),
+                Ok(_) => {}
+                Err(e) => tracing::warn!("state pruning failed: {}", e),
+            }
+        }
+        let interval = policy.interval_secs.max(60);
+        tokio::time::sleep(std::time::Duration::from_secs(interval)).await;
+    }
+}
+
 pub async fn run_routine_scheduler(state: AppState) {
     loop {
         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         let now = now_ms();
+        state.process_routine_approval_timeouts(now).await;
         let plans = state.evaluate_routine_misfires(now).await;
         for plan in plans {
             let Some(routine) = state.get_routine(&plan.routine_id).await else {
@@ -1743,7 +2867,7 @@ pub async fn run_routine_scheduler(state: AppState) {
             match evaluate_routine_execution_policy(&routine, "scheduled") {
                 RoutineExecutionDecision::Allowed => {
                     let _ = state.mark_routine_fired(&plan.routine_id, now).await;
-                    let run = state
+                    let Ok(run) = state
                         .create_routine_run(
                             &routine,
                             "scheduled",
@@ -1751,7 +2875,10 @@ pub async fn run_routine_scheduler(state: AppState) {
                             RoutineRunStatus::Queued,
                             None,
                         )
-                        .await;
+                        .await
+                    else {
+                        continue;
+                    };
                     state
                         .append_routine_history(RoutineHistoryEvent {
                             routine_id: plan.routine_id.clone(),
@@ -1780,7 +2907,7 @@ pub async fn run_routine_scheduler(state: AppState) {
                     ));
                 }
                 RoutineExecutionDecision::RequiresApproval { reason } => {
-                    let run = state
+                    let Ok(run) = state
                         .create_routine_run(
                             &routine,
                             "scheduled",
@@ -1788,7 +2915,10 @@ pub async fn run_routine_scheduler(state: AppState) {
                             RoutineRunStatus::PendingApproval,
                             Some(reason.clone()),
                         )
-                        .await;
+                        .await
+                    else {
+                        continue;
+                    };
                     state
                         .append_routine_history(RoutineHistoryEvent {
                             routine_id: plan.routine_id.clone(),
@@ -1809,6 +2939,7 @@ pub async fn run_routine_scheduler(state: AppState) {
                             "reason": reason,
                         }),
                     ));
+                    state.notify_routine_approvers(&run, false);
                     state.event_bus.publish(EngineEvent::new(
                         "routine.run.created",
                         serde_json::json!({
@@ -1817,7 +2948,7 @@ pub async fn run_routine_scheduler(state: AppState) {
                     ));
                 }
                 RoutineExecutionDecision::Blocked { reason } => {
-                    let run = state
+                    let Ok(run) = state
                         .create_routine_run(
                             &routine,
                             "scheduled",
@@ -1825,7 +2956,10 @@ pub async fn run_routine_scheduler(state: AppState) {
                             RoutineRunStatus::BlockedPolicy,
                             Some(reason.clone()),
                         )
-                        .await;
+                        .await
+                    else {
+                        continue;
+                    };
                     state
                         .append_routine_history(RoutineHistoryEvent {
                             routine_id: plan.routine_id.clone(),
@@ -1861,30 +2995,46 @@ pub async fn run_routine_scheduler(state: AppState) {
 pub async fn run_routine_executor(state: AppState) {
     loop {
         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        // Queued runs wait while the host is at its pressure run limit.
+        if state.host_run_limit_reached().await.is_some() {
+            continue;
+        }
         let Some(run) = state.claim_next_queued_routine_run().await else {
             continue;
         };
-
-        state.event_bus.publish(EngineEvent::new(
-            "routine.run.started",
-            serde_json::json!({
-                "runID": run.run_id,
-                "routineID": run.routine_id,
-                "triggerType": run.trigger_type,
-                "startedAtMs": now_ms(),
-            }),
-        ));
-
-        let workspace_root = state.workspace_index.snapshot().await.root;
-        let mut session = Session::new(
-            Some(format!("Routine {}", run.routine_id)),
-            Some(workspace_root.clone()),
+        let span = tracing::info_span!(
+            "routine_run",
+            correlation_id = %run.request_id.as_deref().unwrap_or(""),
+            run_id = %run.run_id,
+            routine_id = %run.routine_id
         );
-        let session_id = session.id.clone();
-        session.workspace_root = Some(workspace_root);
+        let request_id = run.request_id.clone();
+        tracing::Instrument::instrument(
+            tandem_core::with_optional_request_id(request_id, execute_routine_run(&state, run)),
+            span,
+        )
+        .await;
+    }
+}
+
+async fn execute_routine_run(state: &AppState, run: RoutineRunRecord) {
+    state.event_bus.publish(EngineEvent::new(
+        "routine.run.started",
+        serde_json::json!({
+            "runID": run.run_id,
+            "routineID": run.routine_id,
+            "triggerType": run.trigger_type,
+            "startedAtMs": now_ms(),
+        }),
+    ));
 
-        if let Err(error) = state.storage.save_session(session).await {
-            let detail = format!("failed to create routine session: {error}");
+    let workspace_root = state.workspace_index.snapshot().await.root;
+    let run = match state
+        .resolve_routine_run_templates(&run, &workspace_root)
+        .await
+    {
+        Ok(run) => run,
+        Err(detail) => {
             let _ = state
                 .update_routine_run_status(
                     &run.run_id,
@@ -1900,81 +3050,29 @@ pub async fn run_routine_executor(state: AppState) {
                     "reason": detail,
                 }),
             ));
-            continue;
-        }
-
-        state
-            .set_routine_session_policy(
-                session_id.clone(),
-                run.run_id.clone(),
-                run.routine_id.clone(),
-                run.allowed_tools.clone(),
-            )
-            .await;
-        state
-            .engine_loop
-            .set_session_allowed_tools(&session_id, run.allowed_tools.clone())
-            .await;
-
-        let (selected_model, model_source) = resolve_routine_model_spec_for_run(&state, &run).await;
-        if let Some(spec) = selected_model.as_ref() {
-            state.event_bus.publish(EngineEvent::new(
-                "routine.run.model_selected",
-                serde_json::json!({
-                    "runID": run.run_id,
-                    "routineID": run.routine_id,
-                    "providerID": spec.provider_id,
-                    "modelID": spec.model_id,
-                    "source": model_source,
-                }),
-            ));
+            return;
         }
-
-        let request = SendMessageRequest {
-            parts: vec![MessagePartInput::Text {
-                text: build_routine_prompt(&state, &run).await,
-            }],
-            model: selected_model,
-            agent: None,
-        };
-
-        let run_result = state
-            .engine_loop
-            .run_prompt_async_with_context(
-                session_id.clone(),
-                request,
-                Some(format!("routine:{}", run.run_id)),
-            )
-            .await;
-
-        state.clear_routine_session_policy(&session_id).await;
-        state
-            .engine_loop
-            .clear_session_allowed_tools(&session_id)
-            .await;
-
-        match run_result {
-            Ok(()) => {
-                append_configured_output_artifacts(&state, &run).await;
-                let _ = state
-                    .update_routine_run_status(
-                        &run.run_id,
-                        RoutineRunStatus::Completed,
-                        Some("routine run completed".to_string()),
-                    )
-                    .await;
-                state.event_bus.publish(EngineEvent::new(
-                    "routine.run.completed",
-                    serde_json::json!({
-                        "runID": run.run_id,
-                        "routineID": run.routine_id,
-                        "sessionID": session_id,
-                        "finishedAtMs": now_ms(),
-                    }),
-                ));
-            }
-            Err(error) => {
-                let detail = truncate_text(&error.to_string(), 500);
+    };
+    let resumed_session = match run.resume_session_id.as_deref() {
+        Some(session_id) => state.storage.get_session(session_id).await,
+        None => None,
+    };
+    let resumed = resumed_session.is_some();
+    let session_id = match resumed_session {
+        Some(session) => session.id,
+        None => {
+            let mut session = Session::new(
+                Some(format!("Routine {}", run.routine_id)),
+                Some(workspace_root.clone()),
+            );
+            let session_id = session.id.clone();
+            session.workspace_root = Some(workspace_root.clone());
+            let routine = state.get_routine(&run.routine_id).await;
+            session.tags = routine_session_tags(&run.routine_id, routine.as_ref());
+            session.project_id = routine.and_then(|routine| routine.tenant_id);
+
+            if let Err(error) = state.storage.save_session(session).await {
+                let detail = format!("failed to create routine session: {error}");
                 let _ = state
                     .update_routine_run_status(
                         &run.run_id,
@@ -1987,14 +3085,235 @@ pub async fn run_routine_executor(state: AppState) {
                     serde_json::json!({
                         "runID": run.run_id,
                         "routineID": run.routine_id,
-                        "sessionID": session_id,
                         "reason": detail,
-                        "finishedAtMs": now_ms(),
                     }),
                 ));
+                return;
             }
+            session_id
         }
+    };
+    let _ = state
+        .set_routine_run_session(&run.run_id, &session_id)
+        .await;
+
+    state
+        .set_routine_session_policy(
+            session_id.clone(),
+            run.run_id.clone(),
+            run.routine_id.clone(),
+            run.allowed_tools.clone(),
+        )
+        .await;
+    state
+        .engine_loop
+        .set_session_allowed_tools(&session_id, run.allowed_tools.clone())
+        .await;
+    state
+        .engine_loop
+        .set_session_priority(&session_id, run.priority)
+        .await;
+    state
+        .engine_loop
+        .set_session_run(&session_id, &run.run_id)
+        .await;
+
+    let (selected_model, model_source) = resolve_routine_model_spec_for_run(state, &run).await;
+    if let Some(spec) = selected_model.as_ref() {
+        state.event_bus.publish(EngineEvent::new(
+            "routine.run.model_selected",
+            serde_json::json!({
+                "runID": run.run_id,
+                "routineID": run.routine_id,
+                "providerID": spec.provider_id,
+                "modelID": spec.model_id,
+                "source": model_source,
+            }),
+        ));
     }
+
+    let mut prompt = build_routine_prompt(state, &run).await;
+    if resumed && !prompt.starts_with("/tool ") {
+        prompt = format!("{}\n\n{prompt}", routine_recovery::RESUME_NOTE);
+    }
+    let pipeline = agent_pipelines::pipeline_name(&run.entrypoint).map(str::to_string);
+    let incognito = state.storage.is_incognito(&session_id).await;
+    let cache_policy = state
+        .get_routine(&run.routine_id)
+        .await
+        .and_then(|routine| routine.response_cache)
+        .filter(|policy| {
+            policy.enabled
+                && !resumed
+                && !incognito
+                && pipeline.is_none()
+                && !prompt.starts_with("/tool ")
+        });
+    let cache_probe = match cache_policy.as_ref() {
+        Some(policy) => Some(
+            state
+                .routine_cache
+                .probe(&run.routine_id, selected_model.as_ref(), &prompt, policy)
+                .await,
+        ),
+        None => None,
+    };
+    let cached = match (cache_probe.as_ref(), cache_policy.as_ref()) {
+        (Some(probe), Some(policy)) => state.routine_cache.lookup(probe, policy).await,
+        _ => None,
+    };
+    let from_cache = cached.is_some();
+
+    let run_result = match (cached, pipeline.as_deref()) {
+        (Some((hit, response)), _) => {
+            state
+                .answer_routine_run_from_cache(&run, &session_id, &prompt, hit, response)
+                .await
+        }
+        (None, Some(name)) => {
+            state
+                .run_agent_pipeline(
+                    &run,
+                    name,
+                    &session_id,
+                    &workspace_root,
+                    &prompt,
+                    selected_model,
+                )
+                .await
+        }
+        (None, None) => {
+            let request = SendMessageRequest {
+                parts: vec![MessagePartInput::Text { text: prompt }],
+                model: selected_model,
+                agent: None,
+                system: None,
+                preset: generation_preset_from_args(&run.args),
+            };
+            state
+                .engine_loop
+                .run_prompt_async_with_context(
+                    session_id.clone(),
+                    request,
+                    Some(format!("routine:{}", run.run_id)),
+                )
+                .await
+        }
+    };
+
+    state.clear_routine_session_policy(&session_id).await;
+    state
+        .engine_loop
+        .clear_session_allowed_tools(&session_id)
+        .await;
+    state.engine_loop.clear_session_priority(&session_id).await;
+    state.engine_loop.clear_session_run(&session_id).await;
+
+    // A finished model turn only counts as success once the checkable
+    // success criteria pass; the model's own claims are not consulted.
+    let mut criteria_results = Vec::new();
+    let run_result = match run_result {
+        Ok(()) => {
+            append_configured_output_artifacts(state, &run).await;
+            criteria_results = state.evaluate_success_criteria(&run, &workspace_root).await;
+            match success_criteria::failure_detail(&criteria_results) {
+                Some(detail) => Err(truncate_text(&detail, 500)),
+                None => Ok(()),
+            }
+        }
+        Err(error) => Err(truncate_text(&error.to_string(), 500)),
+    };
+    if !criteria_results.is_empty() {
+        let _ = state
+            .set_routine_run_criteria_results(&run.run_id, criteria_results.clone())
+            .await;
+    }
+
+    let run_tag = if run_result.is_ok() {
+        "run:completed"
+    } else {
+        "run:failed"
+    };
+    let _ = state
+        .storage
+        .update_session_tags(&session_id, &[run_tag.to_string()], &["run:*".to_string()])
+        .await;
+
+    if let (Ok(()), Some(probe), false) = (&run_result, cache_probe.as_ref(), from_cache) {
+        let answer = state
+            .storage
+            .get_session(&session_id)
+            .await
+            .and_then(|session| routine_cache::last_assistant_text(&session.messages));
+        if let Some(answer) = answer {
+            state.routine_cache.store(probe, &answer, &run.run_id).await;
+        }
+    }
+
+    match run_result {
+        Ok(()) => {
+            let detail = if from_cache {
+                "routine run completed from cache"
+            } else {
+                "routine run completed"
+            };
+            let _ = state
+                .update_routine_run_status(
+                    &run.run_id,
+                    RoutineRunStatus::Completed,
+                    Some(detail.to_string()),
+                )
+                .await;
+            let change_summary = state.attach_routine_artifact_diff(&run.run_id).await;
+            state.event_bus.publish(EngineEvent::new(
+                "routine.run.completed",
+                serde_json::json!({
+                    "runID": run.run_id,
+                    "routineID": run.routine_id,
+                    "sessionID": session_id,
+                    "finishedAtMs": now_ms(),
+                    "changed": change_summary.as_ref().map(|c| c.changed),
+                    "deliverySuppressed": change_summary
+                        .as_ref()
+                        .is_some_and(|c| c.delivery_suppressed),
+                    "criteriaResults": criteria_results,
+                    "cacheHit": from_cache,
+                }),
+            ));
+        }
+        Err(detail) => {
+            let _ = state
+                .update_routine_run_status(
+                    &run.run_id,
+                    RoutineRunStatus::Failed,
+                    Some(detail.clone()),
+                )
+                .await;
+            state.event_bus.publish(EngineEvent::new(
+                "routine.run.failed",
+                serde_json::json!({
+                    "runID": run.run_id,
+                    "routineID": run.routine_id,
+                    "sessionID": session_id,
+                    "reason": detail,
+                    "finishedAtMs": now_ms(),
+                    "criteriaResults": criteria_results,
+                }),
+            ));
+        }
+    }
+}
+
+/// `routine`, `routine:<id>` and, for routines an agent created,
+/// `agent:<creator_id>`. Ids that are not valid tags are left out.
+fn routine_session_tags(routine_id: &str, routine: Option<&RoutineSpec>) -> Vec<String> {
+    let mut tags = vec!["routine".to_string(), format!("routine:{routine_id}")];
+    if let Some(routine) = routine.filter(|r| r.creator_type == "agent") {
+        tags.push(format!("agent:{}", routine.creator_id));
+    }
+    tags.iter()
+        .filter_map(|tag| tandem_types::normalize_session_tag(tag))
+        .collect()
 }
 
 async fn build_routine_prompt(state: &AppState, run: &RoutineRunRecord) -> String {
@@ -2041,15 +3360,25 @@ fn routine_mode_from_args(args: &Value) -> &str {
         .unwrap_or("standalone")
 }
 
+/// Free-text criteria plus the labels of checkable ones, which the server
+/// verifies after the run.
 fn routine_success_criteria_from_args(args: &Value) -> Vec<String> {
     args.get("success_criteria")
         .and_then(|v| v.as_array())
         .map(|rows| {
             rows.iter()
-                .filter_map(|row| row.as_str())
-                .map(str::trim)
+                .filter_map(|row| match row {
+                    Value::String(text) => Some(text.trim().to_string()),
+                    Value::Object(_) => {
+                        serde_json::from_value::<success_criteria::SuccessCriterion>(row.clone())
+                            .ok()
+                            .map(|criterion| {
+                                format!("{} (verified by the server)", criterion.label())
+                            })
+                    }
+                    _ => None,
+                })
                 .filter(|row| !row.is_empty())
-                .map(ToString::to_string)
                 .collect::<Vec<_>>()
         })
         .unwrap_or_default()
@@ -2141,9 +3470,14 @@ async fn append_configured_output_artifacts(state: &AppState, run: &RoutineRunRe
                 "routineID": run.routine_id,
             })),
         };
-        let _ = state
-            .append_routine_run_artifact(&run.run_id, artifact.clone())
-            .await;
+        if !matches!(
+            state
+                .append_routine_run_artifact(&run.run_id, artifact.clone())
+                .await,
+            Ok(Some(_))
+        ) {
+            continue;
+        }
         state.event_bus.publish(EngineEvent::new(
             "routine.run.artifact_added",
             serde_json::json!({
@@ -2181,6 +3515,15 @@ fn default_model_spec_from_args(args: &Value) -> Option<ModelSpec> {
         .and_then(parse_model_spec)
 }
 
+fn generation_preset_from_args(args: &Value) -> Option<String> {
+    args.get("model_policy")
+        .and_then(|v| v.get("preset"))
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+}
+
 fn provider_catalog_has_model(providers: &[tandem_types::ProviderInfo], spec: &ModelSpec) -> bool {
     providers.iter().any(|provider| {
         provider.id == spec.provider_id
@@ -2207,6 +3550,22 @@ async fn resolve_routine_model_spec_for_run(
     if let Some(default_model) = default_model_spec_from_args(&run.args) {
         requested.push((default_model, "args.model_policy.default_model"));
     }
+    if let Some(name) = generation_preset_from_args(&run.args) {
+        let route = state
+            .providers
+            .generation_preset(&name)
+            .await
+            .and_then(|preset| preset.model_route());
+        if let Some((provider_id, model_id)) = route {
+            requested.push((
+                ModelSpec {
+                    provider_id,
+                    model_id,
+                },
+                "args.model_policy.preset",
+            ));
+        }
+    }
 
     for (candidate, source) in requested {
         if provider_catalog_has_model(&providers, &candidate) {
@@ -2293,6 +3652,88 @@ mod tests {
         let _ = tokio::fs::remove_file(path).await;
     }
 
+    #[tokio::test]
+    async fn shared_resource_batch_is_all_or_nothing() {
+        let path = tmp_resource_file("shared-resource-batch");
+        let state = test_state_with_path(path.clone());
+        state
+            .put_shared_resource(
+                "project/demo/count".to_string(),
+                serde_json::json!(1),
+                None,
+                "agent-1".to_string(),
+                None,
+            )
+            .await
+            .expect("seed");
+
+        let err = state
+            .apply_shared_resource_batch(
+                vec![
+                    SharedResourceOp::Put {
+                        key: "project/demo/card".to_string(),
+                        value: serde_json::json!({"lane":"done"}),
+                        if_match_rev: None,
+                        ttl_ms: None,
+                    },
+                    SharedResourceOp::Put {
+                        key: "project/demo/count".to_string(),
+                        value: serde_json::json!(2),
+                        if_match_rev: Some(7),
+                        ttl_ms: None,
+                    },
+                ],
+                "agent-2".to_string(),
+            )
+            .await
+            .expect_err("conflict");
+        assert_eq!(err.op_index, Some(1));
+        assert!(matches!(
+            err.error,
+            ResourceStoreError::RevisionConflict(ResourceConflict {
+                current_rev: Some(1),
+                ..
+            })
+        ));
+        assert!(state
+            .get_shared_resource("project/demo/card")
+            .await
+            .is_none());
+
+        let results = state
+            .apply_shared_resource_batch(
+                vec![
+                    SharedResourceOp::Put {
+                        key: "project/demo/count".to_string(),
+                        value: serde_json::json!(2),
+                        if_match_rev: Some(1),
+                        ttl_ms: None,
+                    },
+                    SharedResourceOp::Put {
+                        key: "project/demo/count".to_string(),
+                        value: serde_json::json!(3),
+                        if_match_rev: Some(2),
+                        ttl_ms: None,
+                    },
+                    SharedResourceOp::Delete {
+                        key: "project/demo/missing".to_string(),
+                        if_match_rev: None,
+                    },
+                ],
+                "agent-2".to_string(),
+            )
+            .await
+            .expect("batch");
+        assert_eq!(results[1].rev, Some(3));
+        assert!(!results[2].applied);
+        let count = state
+            .get_shared_resource("project/demo/count")
+            .await
+            .expect("count");
+        assert_eq!(count.value, serde_json::json!(3));
+        let _ = tokio::fs::remove_file(path).await;
+    }
+
     #[tokio::test]
     async fn shared_resource_put_detects_revision_conflict() {
         let path = tmp_resource_file("shared-resource-conflict");
@@ -2453,6 +3894,11 @@ mod tests {
             external_integrations_allowed: false,
             next_fire_at_ms: Some(5_000),
             last_fired_at_ms: None,
+            approval_policy: None,
+            template_mode: None,
+            tenant_id: None,
+            response_cache: None,
+            notifications: None,
         };
 
         state.put_routine(routine).await.expect("store routine");
@@ -2490,6 +3936,11 @@ mod tests {
             external_integrations_allowed: false,
             next_fire_at_ms: Some(5_000),
             last_fired_at_ms: None,
+            approval_policy: None,
+            template_mode: None,
+            tenant_id: None,
+            response_cache: None,
+            notifications: None,
         };
 
         state
@@ -2547,6 +3998,11 @@ mod tests {
             external_integrations_allowed: false,
             next_fire_at_ms: None,
             last_fired_at_ms: None,
+            approval_policy: None,
+            template_mode: None,
+            tenant_id: None,
+            response_cache: None,
+            notifications: None,
         };
 
         let decision = evaluate_routine_execution_policy(&routine, "manual");
@@ -2572,6 +4028,11 @@ mod tests {
             external_integrations_allowed: true,
             next_fire_at_ms: None,
             last_fired_at_ms: None,
+            approval_policy: None,
+            template_mode: None,
+            tenant_id: None,
+            response_cache: None,
+            notifications: None,
         };
 
         let decision = evaluate_routine_execution_policy(&routine, "manual");
@@ -2600,6 +4061,11 @@ mod tests {
             external_integrations_allowed: false,
             next_fire_at_ms: None,
             last_fired_at_ms: None,
+            approval_policy: None,
+            template_mode: None,
+            tenant_id: None,
+            response_cache: None,
+            notifications: None,
         };
 
         let decision = evaluate_routine_execution_policy(&routine, "manual");
@@ -2632,6 +4098,19 @@ mod tests {
             allowed_tools: vec![],
             output_targets: vec![],
             artifacts: vec![],
+            approvers: vec![],
+            approval_escalated_at_ms: None,
+            decided_by: None,
+            change_summary: None,
+            priority: RunPriority::Routine,
+            queue_wait_ms: None,
+            request_id: None,
+            criteria_results: Vec::new(),
+            session_id: None,
+            resume_session_id: None,
+            recoveries: Vec::new(),
+            cache_hit: None,
+            pipeline_stages: Vec::new(),
         };
 
         {
@@ -2648,6 +4127,129 @@ mod tests {
         assert_eq!(claimed.run_id, "run-early");
         assert_eq!(claimed.status, RoutineRunStatus::Running);
         assert!(claimed.started_at_ms.is_some());
+        assert_eq!(claimed.priority, RunPriority::Routine);
+        assert!(claimed.queue_wait_ms.is_some());
+    }
+
+    #[tokio::test]
+    async fn routine_claims_yield_to_interactive_runs_until_starved() {
+        let mut state = AppState::new_starting("routine-yield".to_string(), true);
+        state.routine_runs_path = tmp_routines_file("routine-yield-runs");
+        let now = now_ms();
+        let mut run: RoutineRunRecord = serde_json::from_value(serde_json::json!({
+            "run_id": "run-fresh",
+            "routine_id": "routine-yield",
+            "trigger_type": "scheduled",
+            "run_count": 1,
+            "status": "queued",
+            "created_at_ms": now,
+            "updated_at_ms": now,
+            "requires_approval": false,
+            "entrypoint": "mission.default",
+        }))
+        .expect("run");
+        assert_eq!(run.priority, RunPriority::Routine);
+        state
+            .routine_runs
+            .write()
+            .await
+            .insert(run.run_id.clone(), run.clone());
+        state
+            .run_registry
+            .acquire("session-1", "run-1".to_string(), None, None, None)
+            .await
+            .expect("interactive run");
+
+        assert!(state.claim_next_queued_routine_run().await.is_none());
+
+        run.created_at_ms = 1_000;
+        state
+            .routine_runs
+            .write()
+            .await
+            .insert(run.run_id.clone(), run);
+        let claimed = state
+            .claim_next_queued_routine_run()
+            .await
+            .expect("starved run is claimed");
+        assert!(claimed.queue_wait_ms.unwrap_or_default() > 1_000);
+    }
+
+    #[tokio::test]
+    async fn routine_run_templates_resolve_context_and_fail_in_strict_mode() {
+        let state = test_state_with_path(tmp_resource_file("routine-templates"));
+        state
+            .put_shared_resource(
+                "project/demo/goal".to_string(),
+                serde_json::json!("ship the beta"),
+                None,
+                "agent-1".to_string(),
+                None,
+            )
+            .await
+            .expect("resource");
+        let mut routine = RoutineSpec {
+            routine_id: "routine-tpl".to_string(),
+            name: "Daily report".to_string(),
+            status: RoutineStatus::Active,
+            schedule: RoutineSchedule::IntervalSeconds { seconds: 60 },
+            timezone: "UTC".to_string(),
+            misfire_policy: RoutineMisfirePolicy::RunOnce,
+            entrypoint: "mission.default".to_string(),
+            args: serde_json::json!({
+                "prompt": "{{routine.name}} in {{workspace_root}}: previous {{last_run.status}}, goal {{resource:project/demo/goal}}"
+            }),
+            allowed_tools: vec![],
+            output_targets: vec![],
+            creator_type: "user".to_string(),
+            creator_id: "u-1".to_string(),
+            requires_approval: false,
+            external_integrations_allowed: false,
+            next_fire_at_ms: None,
+            last_fired_at_ms: None,
+            approval_policy: None,
+            template_mode: None,
+            tenant_id: None,
+            response_cache: None,
+            notifications: None,
+        };
+        state.put_routine(routine.clone()).await.expect("routine");
+        let previous = state
+            .create_routine_run(&routine, "manual", 1, RoutineRunStatus::Running, None)
+            .await
+            .expect("run");
+        state
+            .update_routine_run_status(&previous.run_id, RoutineRunStatus::Failed, None)
+            .await
+            .expect("status");
+        let run = state
+            .create_routine_run(&routine, "scheduled", 1, RoutineRunStatus::Running, None)
+            .await
+            .expect("run");
+
+        let resolved = state
+            .resolve_routine_run_templates(&run, "/ws")
+            .await
+            .expect("resolved");
+        assert_eq!(
+            resolved.args["prompt"],
+            serde_json::json!("Daily report in /ws: previous failed, goal ship the beta")
+        );
+        let stored = state.get_routine_run(&run.run_id).await.expect("stored");
+        assert_eq!(stored.args, resolved.args);
+
+        routine.args = serde_json::json!({ "prompt": "{{nope}}" });
+        routine.template_mode = Some(routine_templates::RoutineTemplateMode::Strict);
+        state.put_routine(routine.clone()).await.expect("routine");
+        let strict_run = state
+            .create_routine_run(&routine, "manual", 1, RoutineRunStatus::Running, None)
+            .await
+            .expect("run");
+        let error = state
+            .resolve_routine_run_templates(&strict_run, "/ws")
+            .await
+            .expect_err("strict");
+        assert!(error.contains("nope"));
     }
 
     #[tokio::test]
@@ -2699,12 +4301,28 @@ mod tests {
             args: serde_json::json!({
                 "prompt": "Coordinate a multi-step release readiness check.",
                 "mode": "orchestrated",
-                "success_criteria": ["All blockers listed", "Output artifact written"],
+                "success_criteria": [
+                    "All blockers listed",
+                    { "type": "file_exists", "path": "reports/release-readiness.md" }
+                ],
                 "orchestrator_only_tool_calls": true
             }),
             allowed_tools: vec!["read".to_string(), "webfetch".to_string()],
             output_targets: vec!["file://reports/release-readiness.md".to_string()],
             artifacts: vec![],
+            approvers: vec![],
+            approval_escalated_at_ms: None,
+            decided_by: None,
+            change_summary: None,
+            priority: RunPriority::Routine,
+            queue_wait_ms: None,
+            request_id: None,
+            criteria_results: Vec::new(),
+            session_id: None,
+            resume_session_id: None,
+            recoveries: Vec::new(),
+            cache_hit: None,
+            pipeline_stages: Vec::new(),
         };
 
         let objective = routine_objective_from_args(&run).expect("objective");
@@ -2715,6 +4333,9 @@ mod tests {
         assert!(prompt.contains("only the orchestrator may execute tools"));
         assert!(prompt.contains("Allowed Tools: read, webfetch"));
         assert!(prompt.contains("file://reports/release-readiness.md"));
+        assert!(prompt.contains("- All blockers listed"));
+        assert!(prompt
+            .contains("- file `reports/release-readiness.md` exists (verified by the server)"));
     }
 
     #[test]
@@ -2743,6 +4364,19 @@ mod tests {
             allowed_tools: vec![],
             output_targets: vec![],
             artifacts: vec![],
+            approvers: vec![],
+            approval_escalated_at_ms: None,
+            decided_by: None,
+            change_summary: None,
+            priority: RunPriority::Routine,
+            queue_wait_ms: None,
+            request_id: None,
+            criteria_results: Vec::new(),
+            session_id: None,
+            resume_session_id: None,
+            recoveries: Vec::new(),
+            cache_hit: None,
+            pipeline_stages: Vec::new(),
         };
 
         let objective = routine_objective_from_args(&run).expect("objective");
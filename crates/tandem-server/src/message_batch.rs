@@ -0,0 +1,314 @@
+//! Replaying a scripted conversation in one request.
+//!
+//! `POST /session/{id}/message/batch` takes an ordered list of user messages
+//! and runs them one after another through the engine loop, each as its own
+//! run, then answers with a report of every turn. `on_error` decides whether
+//! a failed turn stops the batch (the rest are reported as `skipped`) or the
+//! next turn runs anyway; `dry_run` validates the batch and returns the plan
+//! without sending anything. Every turn is admitted like a single prompt:
+//! it counts against the host's run limit and the caller's daily quota, and
+//! once a turn is refused the rest are skipped with the refusal as error.
+
+use serde::{Deserialize, Serialize};
+use tandem_types::{Message, MessagePart, MessagePartInput, MessageRole, SendMessageRequest};
+
+/// Most messages accepted in one batch.
+pub const MAX_BATCH_MESSAGES: usize = 100;
+/// Longest pause allowed between turns.
+pub const MAX_TURN_DELAY_MS: u64 = 60_000;
+const PREVIEW_CHARS: usize = 120;
+
+/// One turn: plain text, or a full message request.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum BatchMessage {
+    Text(String),
+    Request(SendMessageRequest),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchErrorPolicy {
+    #[default]
+    Stop,
+    Continue,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct MessageBatchInput {
+    pub messages: Vec<BatchMessage>,
+    #[serde(default)]
+    pub on_error: BatchErrorPolicy,
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Pause before every turn after the first.
+    #[serde(default)]
+    pub delay_ms: u64,
+    /// Agent for turns that do not name one.
+    #[serde(default)]
+    pub agent: Option<String>,
+}
+
+impl MessageBatchInput {
+    /// The turns as message requests, with the batch defaults applied.
+    pub fn requests(&self) -> Result<Vec<SendMessageRequest>, String> {
+        if self.messages.is_empty() {
+            return Err("batch has no messages".to_string());
+        }
+        if self.messages.len() > MAX_BATCH_MESSAGES {
+            return Err(format!(
+                "batch has {} messages; at most {MAX_BATCH_MESSAGES} are allowed",
+                self.messages.len()
+            ));
+        }
+        if self.delay_ms > MAX_TURN_DELAY_MS {
+            return Err(format!("delay_ms must be at most {MAX_TURN_DELAY_MS}"));
+        }
+        self.messages
+            .iter()
+            .enumerate()
+            .map(|(index, message)| {
+                let mut req = match message {
+                    BatchMessage::Text(text) => SendMessageRequest {
+                        parts: vec![MessagePartInput::Text { text: text.clone() }],
+                        model: None,
+                        agent: None,
+                        system: None,
+                        preset: None,
+                    },
+                    BatchMessage::Request(req) => req.clone(),
+                };
+                if preview(&req).is_none() && !has_attachment(&req) {
+                    return Err(format!("message {index} is empty"));
+                }
+                if req.agent.is_none() {
+                    req.agent = self.agent.clone();
+                }
+                Ok(req)
+            })
+            .collect()
+    }
+}
+
+fn has_attachment(req: &SendMessageRequest) -> bool {
+    req.parts
+        .iter()
+        .any(|part| !matches!(part, MessagePartInput::Text { .. }))
+}
+
+/// Start of the message text, for the report.
+pub fn preview(req: &SendMessageRequest) -> Option<String> {
+    let text = req
+        .parts
+        .iter()
+        .filter_map(|part| match part {
+            MessagePartInput::Text { text } => Some(text.trim()),
+            _ => None,
+        })
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (!text.is_empty()).then(|| crate::clip(&text, PREVIEW_CHARS))
+}
+
+/// Text of the assistant messages among `messages`.
+pub fn assistant_reply(messages: &[Message]) -> Option<String> {
+    let reply = messages
+        .iter()
+        .filter(|m| matches!(m.role, MessageRole::Assistant))
+        .flat_map(|m| m.parts.iter())
+        .filter_map(|part| match part {
+            MessagePart::Text { text } => Some(text.trim()),
+            _ => None,
+        })
+        .filter(|text| !text.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n");
+    (!reply.is_empty()).then_some(reply)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TurnStatus {
+    /// Dry run: the turn would be sent.
+    Planned,
+    Completed,
+    Error,
+    Timeout,
+    /// Not run because an earlier turn failed or was refused.
+    Skipped,
+}
+
+impl TurnStatus {
+    pub fn from_run_status(status: &str) -> Self {
+        match status {
+            "completed" => Self::Completed,
+            "timeout" => Self::Timeout,
+            _ => Self::Error,
+        }
+    }
+
+    pub fn is_failure(self) -> bool {
+        matches!(self, Self::Error | Self::Timeout)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchTurn {
+    pub index: usize,
+    pub status: TurnStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    #[serde(rename = "runID", skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reply: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(rename = "messageIDs", skip_serializing_if = "Vec::is_empty")]
+    pub message_ids: Vec<String>,
+    pub duration_ms: u64,
+}
+
+impl BatchTurn {
+    pub fn new(index: usize, status: TurnStatus, req: &SendMessageRequest) -> Self {
+        Self {
+            index,
+            status,
+            prompt: preview(req),
+            run_id: None,
+            reply: None,
+            error: None,
+            message_ids: Vec::new(),
+            duration_ms: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BatchSummary {
+    pub total: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReport {
+    #[serde(rename = "sessionID")]
+    pub session_id: String,
+    /// `planned`, `completed`, `partial` (some turns failed) or `failed`.
+    pub status: String,
+    pub dry_run: bool,
+    pub on_error: BatchErrorPolicy,
+    pub summary: BatchSummary,
+    pub turns: Vec<BatchTurn>,
+    pub duration_ms: u64,
+}
+
+impl BatchReport {
+    pub fn new(
+        session_id: &str,
+        input: &MessageBatchInput,
+        turns: Vec<BatchTurn>,
+        duration_ms: u64,
+    ) -> Self {
+        let summary = BatchSummary {
+            total: turns.len(),
+            completed: turns
+                .iter()
+                .filter(|t| t.status == TurnStatus::Completed)
+                .count(),
+            failed: turns.iter().filter(|t| t.status.is_failure()).count(),
+            skipped: turns
+                .iter()
+                .filter(|t| t.status == TurnStatus::Skipped)
+                .count(),
+        };
+        let status = if input.dry_run {
+            "planned"
+        } else if summary.completed == summary.total {
+            "completed"
+        } else if summary.completed == 0 {
+            "failed"
+        } else {
+            "partial"
+        };
+        Self {
+            session_id: session_id.to_string(),
+            status: status.to_string(),
+            dry_run: input.dry_run,
+            on_error: input.on_error,
+            summary,
+            turns,
+            duration_ms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn input(value: serde_json::Value) -> MessageBatchInput {
+        serde_json::from_value(value).expect("input")
+    }
+
+    #[test]
+    fn accepts_text_and_full_requests_with_defaults() {
+        let batch = input(json!({
+            "messages": [
+                "hello",
+                { "parts": [{ "type": "text", "text": "and now?" }], "agent": "explore" }
+            ],
+            "agent": "build",
+        }));
+        assert_eq!(batch.on_error, BatchErrorPolicy::Stop);
+        let requests = batch.requests().expect("requests");
+        assert_eq!(requests[0].agent.as_deref(), Some("build"));
+        assert_eq!(requests[1].agent.as_deref(), Some("explore"));
+        assert_eq!(preview(&requests[1]).as_deref(), Some("and now?"));
+    }
+
+    #[test]
+    fn rejects_empty_and_oversized_batches() {
+        assert!(input(json!({ "messages": [] })).requests().is_err());
+        assert_eq!(
+            input(json!({ "messages": ["ok", "  "] }))
+                .requests()
+                .unwrap_err(),
+            "message 1 is empty"
+        );
+        let many = vec!["hi"; MAX_BATCH_MESSAGES + 1];
+        assert!(input(json!({ "messages": many })).requests().is_err());
+        assert!(
+            input(json!({ "messages": ["hi"], "delay_ms": MAX_TURN_DELAY_MS + 1 }))
+                .requests()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn report_status_reflects_turn_outcomes() {
+        let batch = input(json!({ "messages": ["a", "b"] }));
+        let requests = batch.requests().expect("requests");
+        let turns = vec![
+            BatchTurn::new(0, TurnStatus::Completed, &requests[0]),
+            BatchTurn::new(1, TurnStatus::Skipped, &requests[1]),
+        ];
+        let report = BatchReport::new("s1", &batch, turns, 5);
+        assert_eq!(report.status, "partial");
+        assert_eq!(
+            report.summary,
+            BatchSummary {
+                total: 2,
+                completed: 1,
+                failed: 0,
+                skipped: 1,
+            }
+        );
+    }
+}
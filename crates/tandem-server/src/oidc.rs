@@ -0,0 +1,838 @@
+//! OpenID Connect login for the Web UI.
+//!
+//! Configured by the `oidc` config block:
+//!
+//! ```json
+//! {
+//!   "enabled": true,
+//!   "issuer": "https://login.example.com/realms/ops",
+//!   "client_id": "tandem",
+//!   "group_roles": { "tandem-admins": "admin", "support": "viewer", "acme": "tenant:acme" },
+//!   "default_role": null
+//! }
+//! ```
+//!
+//! `GET /auth/oidc/login` starts the authorization code flow (with PKCE) at
+//! the issuer's discovered authorization endpoint; `GET /auth/oidc/callback`
+//! exchanges the code, checks the ID token and opens a server-side session
+//! held in an HttpOnly `tandem_session` cookie. Sessions last
+//! `session_ttl_secs` and are refreshed with the IdP's refresh token when its
+//! access token expires; a failed refresh ends the session.
+//!
+//! The user's IdP groups (the `groups_claim` claim of the ID token) map to a
+//! role: `admin` has the API token's access, `viewer` may only read, and
+//! `tenant:<id>` acts like that tenant's token. The strongest mapped role
+//! wins; users mapped to more than one tenant (and not to `admin`) are
+//! refused rather than placed in an arbitrary one, and users with no mapped
+//! group get `default_role`, or are refused when it is unset. Cookie
+//! requests that change state, logout included, must echo the session's
+//! CSRF token in `X-Tandem-CSRF`; only the login redirect and its callback
+//! are exempt. API clients keep using token auth, which takes precedence
+//! over the cookie.
+//!
+//! The ID token comes straight from the token endpoint over TLS, so, as
+//! OpenID Connect Core §3.1.3.7 allows, its signature is not verified; its
+//! issuer, audience, expiry and nonce are. The client secret is read from the
+//! config file or `TANDEM_OIDC_CLIENT_SECRET`; config patches reject it.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail};
+use axum::http::{header, HeaderMap, Method};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+use crate::{now_ms, AppState};
+
+pub const SESSION_COOKIE: &str = "tandem_session";
+pub const CSRF_HEADER: &str = "x-tandem-csrf";
+const LOGIN_TTL_MS: u64 = 10 * 60 * 1000;
+const DEFAULT_SESSION_TTL_SECS: u64 = 8 * 60 * 60;
+/// Refresh this long before the IdP access token expires.
+const REFRESH_SKEW_MS: u64 = 30_000;
+const MAX_PENDING_LOGINS: usize = 256;
+
+fn default_scopes() -> Vec<String> {
+    vec![
+        "openid".to_string(),
+        "profile".to_string(),
+        "email".to_string(),
+    ]
+}
+
+fn default_groups_claim() -> String {
+    "groups".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OidcConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub issuer: String,
+    #[serde(default)]
+    pub client_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    /// Defaults to `<server base url>/auth/oidc/callback`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_url: Option<String>,
+    #[serde(default = "default_scopes")]
+    pub scopes: Vec<String>,
+    #[serde(default = "default_groups_claim")]
+    pub groups_claim: String,
+    /// IdP group -> `admin`, `viewer` or `tenant:<id>`.
+    #[serde(default)]
+    pub group_roles: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_role: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_ttl_secs: Option<u64>,
+    /// Mark the session cookie `Secure`; turn off only for plain-HTTP
+    /// development setups.
+    #[serde(default = "default_true")]
+    pub secure_cookie: bool,
+}
+
+impl Default for OidcConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            issuer: String::new(),
+            client_id: String::new(),
+            client_secret: None,
+            redirect_url: None,
+            scopes: default_scopes(),
+            groups_claim: default_groups_claim(),
+            group_roles: HashMap::new(),
+            default_role: None,
+            session_ttl_secs: None,
+            secure_cookie: true,
+        }
+    }
+}
+
+impl OidcConfig {
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.issuer.trim().is_empty() && !self.client_id.trim().is_empty()
+    }
+
+    fn issuer(&self) -> &str {
+        self.issuer.trim().trim_end_matches('/')
+    }
+
+    fn client_secret(&self) -> Option<String> {
+        self.client_secret
+            .clone()
+            .or_else(|| std::env::var("TANDEM_OIDC_CLIENT_SECRET").ok())
+            .filter(|s| !s.trim().is_empty())
+    }
+
+    fn session_ttl_ms(&self) -> u64 {
+        self.session_ttl_secs
+            .unwrap_or(DEFAULT_SESSION_TTL_SECS)
+            .max(60)
+            .saturating_mul(1000)
+    }
+
+    /// Role for a user in `groups`, or `None` when they may not sign in.
+    pub fn role_for_groups(&self, groups: &[String]) -> Option<UiRole> {
+        let mapped = groups
+            .iter()
+            .filter_map(|group| self.group_roles.get(group))
+            .filter_map(|role| UiRole::parse(role))
+            .collect::<Vec<_>>();
+        if mapped.contains(&UiRole::Admin) {
+            return Some(UiRole::Admin);
+        }
+        let mut tenants = mapped
+            .iter()
+            .filter(|role| matches!(role, UiRole::Tenant(_)))
+            .collect::<Vec<_>>();
+        tenants.dedup();
+        match tenants.as_slice() {
+            [] => {}
+            [tenant] => return Some((*tenant).clone()),
+            // Which tenant would depend on group order; refuse instead.
+            _ => return None,
+        }
+        mapped
+            .first()
+            .cloned()
+            .or_else(|| self.default_role.as_deref().and_then(UiRole::parse))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UiRole {
+    Admin,
+    Viewer,
+    Tenant(String),
+}
+
+impl UiRole {
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim();
+        match raw {
+            "admin" => Some(Self::Admin),
+            "viewer" => Some(Self::Viewer),
+            _ => raw
+                .strip_prefix("tenant:")
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .map(|id| Self::Tenant(id.to_string())),
+        }
+    }
+
+    /// Whether the role may make a `method` request at all.
+    pub fn allows(&self, method: &Method) -> bool {
+        match self {
+            Self::Viewer => matches!(*method, Method::GET | Method::HEAD),
+            Self::Admin | Self::Tenant(_) => true,
+        }
+    }
+}
+
+impl std::fmt::Display for UiRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Admin => f.write_str("admin"),
+            Self::Viewer => f.write_str("viewer"),
+            Self::Tenant(id) => write!(f, "tenant:{id}"),
+        }
+    }
+}
+
+impl Serialize for UiRole {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PendingLogin {
+    verifier: String,
+    nonce: String,
+    return_to: String,
+    created_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OidcSession {
+    pub subject: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub groups: Vec<String>,
+    pub role: UiRole,
+    pub csrf_token: String,
+    pub created_at_ms: u64,
+    pub expires_at_ms: u64,
+    #[serde(skip)]
+    refresh_token: Option<String>,
+    #[serde(skip)]
+    access_expires_at_ms: Option<u64>,
+}
+
+impl OidcSession {
+    fn needs_refresh(&self, now: u64) -> bool {
+        self.refresh_token.is_some()
+            && self
+                .access_expires_at_ms
+                .is_some_and(|at| at <= now.saturating_add(REFRESH_SKEW_MS))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn for_test(role: UiRole) -> Self {
+        let now = now_ms();
+        Self {
+            subject: "user-1".to_string(),
+            email: None,
+            name: None,
+            groups: Vec::new(),
+            role,
+            csrf_token: random_token(),
+            created_at_ms: now,
+            expires_at_ms: now + 60_000,
+            refresh_token: None,
+            access_expires_at_ms: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    #[serde(default)]
+    end_session_endpoint: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    #[serde(default)]
+    id_token: Option<String>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+/// Pending logins, open sessions and the cached discovery document.
+#[derive(Clone, Default)]
+pub struct OidcAuth {
+    pending: Arc<RwLock<HashMap<String, PendingLogin>>>,
+    sessions: Arc<RwLock<HashMap<String, OidcSession>>>,
+    /// One lock per session being refreshed, so concurrent requests spend
+    /// its refresh token once.
+    refreshing: Arc<Mutex<HashMap<String, Arc<Mutex<()>>>>>,
+    discovery: Arc<RwLock<Option<Discovery>>>,
+}
+
+fn random_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// PKCE S256 challenge for `verifier`.
+pub fn pkce_challenge(verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+}
+
+fn audience_contains(aud: Option<&Value>, client_id: &str) -> bool {
+    match aud {
+        Some(Value::String(aud)) => aud == client_id,
+        Some(Value::Array(items)) => items.iter().any(|v| v.as_str() == Some(client_id)),
+        _ => false,
+    }
+}
+
+/// Claims of `id_token` once its issuer, audience, expiry and (when given)
+/// nonce check out.
+pub fn validate_id_token(
+    id_token: &str,
+    issuer: &str,
+    client_id: &str,
+    nonce: Option<&str>,
+    now_ms: u64,
+) -> Result<Value, String> {
+    let payload = id_token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| "id token is not a JWT".to_string())?;
+    let claims = URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice::<Value>(&bytes).ok())
+        .ok_or_else(|| "id token payload is not JSON".to_string())?;
+    let iss = claims.get("iss").and_then(|v| v.as_str()).unwrap_or("");
+    if iss.trim_end_matches('/') != issuer.trim_end_matches('/') {
+        return Err(format!("unexpected issuer `{iss}`"));
+    }
+    if !audience_contains(claims.get("aud"), client_id) {
+        return Err("id token was not issued for this client".to_string());
+    }
+    let exp = claims.get("exp").and_then(|v| v.as_u64()).unwrap_or(0);
+    if exp.saturating_mul(1000) <= now_ms {
+        return Err("id token has expired".to_string());
+    }
+    if let Some(nonce) = nonce {
+        if claims.get("nonce").and_then(|v| v.as_str()) != Some(nonce) {
+            return Err("id token nonce does not match".to_string());
+        }
+    }
+    Ok(claims)
+}
+
+fn claim_string(claims: &Value, key: &str) -> Option<String> {
+    claims
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(ToString::to_string)
+}
+
+/// Groups from `claim`, which may be a list or a single string.
+pub fn claim_groups(claims: &Value, claim: &str) -> Vec<String> {
+    match claims.get(claim) {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|v| v.as_str())
+            .map(ToString::to_string)
+            .collect(),
+        Some(Value::String(group)) => vec![group.clone()],
+        _ => Vec::new(),
+    }
+}
+
+/// Session id from the request's `tandem_session` cookie.
+pub fn session_cookie(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+pub fn session_cookie_header(session_id: &str, max_age_secs: u64, secure: bool) -> String {
+    let secure = if secure { "; Secure" } else { "" };
+    format!(
+        "{SESSION_COOKIE}={session_id}; Path=/; HttpOnly; SameSite=Lax; Max-Age={max_age_secs}{secure}"
+    )
+}
+
+/// A same-site path to return to after login; anything else falls back to
+/// `fallback` so the callback cannot be used as an open redirect.
+pub fn safe_return_to(raw: Option<&str>, fallback: &str) -> String {
+    match raw.map(str::trim) {
+        Some(path) if path.starts_with('/') && !path.starts_with("//") && !path.contains('\\') => {
+            path.to_string()
+        }
+        _ => fallback.to_string(),
+    }
+}
+
+fn session_from_claims(
+    config: &OidcConfig,
+    claims: &Value,
+    tokens: &TokenResponse,
+    now: u64,
+) -> anyhow::Result<OidcSession> {
+    let subject = claim_string(claims, "sub").ok_or_else(|| anyhow!("id token has no subject"))?;
+    let groups = claim_groups(claims, &config.groups_claim);
+    let role = config
+        .role_for_groups(&groups)
+        .ok_or_else(|| anyhow!("no Tandem role is mapped to this user's groups"))?;
+    Ok(OidcSession {
+        subject,
+        email: claim_string(claims, "email"),
+        name: claim_string(claims, "name").or_else(|| claim_string(claims, "preferred_username")),
+        groups,
+        role,
+        csrf_token: random_token(),
+        created_at_ms: now,
+        expires_at_ms: now.saturating_add(config.session_ttl_ms()),
+        refresh_token: tokens.refresh_token.clone(),
+        access_expires_at_ms: tokens
+            .expires_in
+            .map(|secs| now.saturating_add(secs.saturating_mul(1000))),
+    })
+}
+
+impl AppState {
+    /// The `oidc` config block.
+    pub async fn oidc_config(&self) -> OidcConfig {
+        self.config_section("oidc").await
+    }
+
+    fn oidc_redirect_url(&self, config: &OidcConfig) -> String {
+        config.redirect_url.clone().unwrap_or_else(|| {
+            format!(
+                "{}/auth/oidc/callback",
+                self.server_base_url().trim_end_matches('/')
+            )
+        })
+    }
+
+    async fn oidc_discovery(&self, config: &OidcConfig) -> anyhow::Result<Discovery> {
+        if let Some(cached) = self.oidc.discovery.read().await.clone() {
+            if cached.issuer.trim_end_matches('/') == config.issuer() {
+                return Ok(cached);
+            }
+        }
+        let url = format!("{}/.well-known/openid-configuration", config.issuer());
+        let discovery = reqwest::Client::new()
+            .get(&url)
+            .timeout(std::time::Duration::from_secs(10))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Discovery>()
+            .await?;
+        if discovery.issuer.trim_end_matches('/') != config.issuer() {
+            bail!("discovery document is for issuer `{}`", discovery.issuer);
+        }
+        *self.oidc.discovery.write().await = Some(discovery.clone());
+        Ok(discovery)
+    }
+
+    async fn oidc_token_request(
+        &self,
+        config: &OidcConfig,
+        discovery: &Discovery,
+        mut form: Vec<(&str, String)>,
+    ) -> anyhow::Result<TokenResponse> {
+        form.push(("client_id", config.client_id.clone()));
+        if let Some(secret) = config.client_secret() {
+            form.push(("client_secret", secret));
+        }
+        let response = reqwest::Client::new()
+            .post(&discovery.token_endpoint)
+            .timeout(std::time::Duration::from_secs(15))
+            .form(&form)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            bail!("token endpoint returned {}", response.status());
+        }
+        Ok(response.json::<TokenResponse>().await?)
+    }
+
+    /// Authorization URL to send the browser to.
+    pub async fn begin_oidc_login(&self, return_to: String) -> anyhow::Result<String> {
+        let config = self.oidc_config().await;
+        if !config.is_active() {
+            bail!("OIDC login is not configured");
+        }
+        let discovery = self.oidc_discovery(&config).await?;
+        let state = random_token();
+        let verifier = random_token();
+        let nonce = random_token();
+        let url = reqwest::Url::parse_with_params(
+            &discovery.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", config.client_id.as_str()),
+                ("redirect_uri", self.oidc_redirect_url(&config).as_str()),
+                ("scope", config.scopes.join(" ").as_str()),
+                ("state", state.as_str()),
+                ("nonce", nonce.as_str()),
+                ("code_challenge", pkce_challenge(&verifier).as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )?;
+        let now = now_ms();
+        let mut pending = self.oidc.pending.write().await;
+        pending.retain(|_, login| now.saturating_sub(login.created_at_ms) < LOGIN_TTL_MS);
+        if pending.len() >= MAX_PENDING_LOGINS {
+            bail!("too many logins in progress");
+        }
+        pending.insert(
+            state,
+            PendingLogin {
+                verifier,
+                nonce,
+                return_to,
+                created_at_ms: now,
+            },
+        );
+        Ok(url.to_string())
+    }
+
+    /// Exchange the callback's code; returns the new session id, the session
+    /// and where to send the browser.
+    pub async fn complete_oidc_login(
+        &self,
+        code: &str,
+        state: &str,
+    ) -> anyhow::Result<(String, OidcSession, String)> {
+        let login = self
+            .oidc
+            .pending
+            .write()
+            .await
+            .remove(state)
+            .filter(|login| now_ms().saturating_sub(login.created_at_ms) < LOGIN_TTL_MS)
+            .ok_or_else(|| anyhow!("unknown or expired login"))?;
+        let config = self.oidc_config().await;
+        if !config.is_active() {
+            bail!("OIDC login is not configured");
+        }
+        let discovery = self.oidc_discovery(&config).await?;
+        let tokens = self
+            .oidc_token_request(
+                &config,
+                &discovery,
+                vec![
+                    ("grant_type", "authorization_code".to_string()),
+                    ("code", code.to_string()),
+                    ("redirect_uri", self.oidc_redirect_url(&config)),
+                    ("code_verifier", login.verifier),
+                ],
+            )
+            .await?;
+        let id_token = tokens
+            .id_token
+            .as_deref()
+            .ok_or_else(|| anyhow!("token response has no id_token"))?;
+        let now = now_ms();
+        let claims = validate_id_token(
+            id_token,
+            config.issuer(),
+            &config.client_id,
+            Some(&login.nonce),
+            now,
+        )
+        .map_err(|err| anyhow!(err))?;
+        let session = session_from_claims(&config, &claims, &tokens, now)?;
+        let session_id = random_token();
+        let mut sessions = self.oidc.sessions.write().await;
+        sessions.retain(|_, s| s.expires_at_ms > now);
+        sessions.insert(session_id.clone(), session.clone());
+        Ok((session_id, session, login.return_to))
+    }
+
+    /// The open session `session_id`, refreshed with the IdP when its access
+    /// token has expired. `None` when it is unknown, expired or the refresh
+    /// failed. Concurrent requests for one session share a single refresh.
+    pub async fn oidc_session(&self, session_id: &str) -> Option<OidcSession> {
+        let now = now_ms();
+        let session = self.oidc.sessions.read().await.get(session_id).cloned()?;
+        if session.expires_at_ms <= now {
+            self.oidc.sessions.write().await.remove(session_id);
+            return None;
+        }
+        if !session.needs_refresh(now) {
+            return Some(session);
+        }
+        let lock = self
+            .oidc
+            .refreshing
+            .lock()
+            .await
+            .entry(session_id.to_string())
+            .or_default()
+            .clone();
+        let _refreshing = lock.lock().await;
+        // Another request may have refreshed (or ended) it while this one
+        // waited.
+        let session = self.oidc.sessions.read().await.get(session_id).cloned()?;
+        let refresh_token = match session.refresh_token.clone() {
+            Some(token) if session.needs_refresh(now_ms()) => token,
+            _ => return Some(session),
+        };
+        let refreshed = self.refresh_oidc_session(&session, refresh_token).await;
+        let mut sessions = self.oidc.sessions.write().await;
+        let result = match refreshed {
+            Ok(refreshed) => {
+                sessions.insert(session_id.to_string(), refreshed.clone());
+                Some(refreshed)
+            }
+            Err(err) => {
+                tracing::info!("oidc session refresh failed: {err}");
+                sessions.remove(session_id);
+                None
+            }
+        };
+        drop(sessions);
+        self.oidc.refreshing.lock().await.remove(session_id);
+        result
+    }
+
+    async fn refresh_oidc_session(
+        &self,
+        session: &OidcSession,
+        refresh_token: String,
+    ) -> anyhow::Result<OidcSession> {
+        let config = self.oidc_config().await;
+        if !config.is_active() {
+            bail!("OIDC login is not configured");
+        }
+        let discovery = self.oidc_discovery(&config).await?;
+        let tokens = self
+            .oidc_token_request(
+                &config,
+                &discovery,
+                vec![
+                    ("grant_type", "refresh_token".to_string()),
+                    ("refresh_token", refresh_token.clone()),
+                ],
+            )
+            .await?;
+        let now = now_ms();
+        let mut refreshed = session.clone();
+        if let Some(id_token) = tokens.id_token.as_deref() {
+            let claims = validate_id_token(id_token, config.issuer(), &config.client_id, None, now)
+                .map_err(|err| anyhow!(err))?;
+            if claim_string(&claims, "sub").as_deref() != Some(session.subject.as_str()) {
+                bail!("refreshed id token is for another user");
+            }
+            refreshed.groups = claim_groups(&claims, &config.groups_claim);
+            refreshed.role = config
+                .role_for_groups(&refreshed.groups)
+                .ok_or_else(|| anyhow!("user no longer has a Tandem role"))?;
+        }
+        refreshed.refresh_token = tokens.refresh_token.or(Some(refresh_token));
+        refreshed.access_expires_at_ms = tokens
+            .expires_in
+            .map(|secs| now.saturating_add(secs.saturating_mul(1000)));
+        Ok(refreshed)
+    }
+
+    /// Close a session; returns the IdP logout URL when it publishes one.
+    pub async fn end_oidc_session(&self, session_id: &str) -> Option<String> {
+        self.oidc.sessions.write().await.remove(session_id)?;
+        self.oidc.refreshing.lock().await.remove(session_id);
+        self.oidc
+            .discovery
+            .read()
+            .await
+            .as_ref()
+            .and_then(|d| d.end_session_endpoint.clone())
+    }
+
+    #[cfg(test)]
+    pub(crate) async fn insert_oidc_session_for_test(&self, role: UiRole) -> (String, String) {
+        let session = OidcSession::for_test(role);
+        let session_id = random_token();
+        let csrf = session.csrf_token.clone();
+        self.oidc
+            .sessions
+            .write()
+            .await
+            .insert(session_id.clone(), session);
+        (session_id, csrf)
+    }
+
+    /// Give a test session a refresh token and an expired access token.
+    #[cfg(test)]
+    pub(crate) async fn expire_oidc_access_for_test(&self, session_id: &str) {
+        if let Some(session) = self.oidc.sessions.write().await.get_mut(session_id) {
+            session.refresh_token = Some("refresh-1".to_string());
+            session.access_expires_at_ms = Some(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn config() -> OidcConfig {
+        serde_json::from_value(json!({
+            "enabled": true,
+            "issuer": "https://idp.example.com/",
+            "client_id": "tandem",
+            "group_roles": {
+                "ops-admins": "admin",
+                "support": "viewer",
+                "acme": "tenant:acme",
+                "globex": "tenant:globex"
+            }
+        }))
+        .expect("config")
+    }
+
+    fn jwt(claims: Value) -> String {
+        format!(
+            "{}.{}.sig",
+            URL_SAFE_NO_PAD.encode(br#"{"alg":"RS256"}"#),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        )
+    }
+
+    #[test]
+    fn strongest_mapped_role_wins() {
+        let cfg = config();
+        let groups = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            cfg.role_for_groups(&groups(&["support", "ops-admins"])),
+            Some(UiRole::Admin)
+        );
+        assert_eq!(
+            cfg.role_for_groups(&groups(&["support", "acme"])),
+            Some(UiRole::Tenant("acme".to_string()))
+        );
+        assert_eq!(cfg.role_for_groups(&groups(&["marketing"])), None);
+        // Two tenants is ambiguous unless an admin group settles it.
+        assert_eq!(cfg.role_for_groups(&groups(&["acme", "globex"])), None);
+        assert_eq!(cfg.role_for_groups(&groups(&["globex", "acme"])), None);
+        assert_eq!(
+            cfg.role_for_groups(&groups(&["acme", "globex", "ops-admins"])),
+            Some(UiRole::Admin)
+        );
+        let with_default = OidcConfig {
+            default_role: Some("viewer".to_string()),
+            ..cfg
+        };
+        assert_eq!(
+            with_default.role_for_groups(&groups(&["marketing"])),
+            Some(UiRole::Viewer)
+        );
+        assert!(!UiRole::Viewer.allows(&Method::POST));
+        assert!(UiRole::Viewer.allows(&Method::GET));
+    }
+
+    #[test]
+    fn pkce_challenge_matches_the_rfc_vector() {
+        // RFC 7636 appendix B.
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[test]
+    fn id_token_checks_issuer_audience_expiry_and_nonce() {
+        let now = 1_700_000_000_000;
+        let good = json!({
+            "iss": "https://idp.example.com",
+            "aud": ["tandem", "other"],
+            "exp": 1_700_000_600,
+            "nonce": "n-1",
+            "sub": "u-1",
+            "groups": ["acme"]
+        });
+        let claims = validate_id_token(
+            &jwt(good.clone()),
+            "https://idp.example.com/",
+            "tandem",
+            Some("n-1"),
+            now,
+        )
+        .expect("valid");
+        assert_eq!(claim_groups(&claims, "groups"), vec!["acme".to_string()]);
+
+        let check = |claims: Value, nonce: &str| {
+            validate_id_token(
+                &jwt(claims),
+                "https://idp.example.com",
+                "tandem",
+                Some(nonce),
+                now,
+            )
+        };
+        assert!(check(good.clone(), "n-2").is_err());
+        let mut other_aud = good.clone();
+        other_aud["aud"] = json!("someone-else");
+        assert!(check(other_aud, "n-1").is_err());
+        let mut expired = good.clone();
+        expired["exp"] = json!(1_699_999_999);
+        assert!(check(expired, "n-1").is_err());
+        let mut other_iss = good;
+        other_iss["iss"] = json!("https://evil.example.com");
+        assert!(check(other_iss, "n-1").is_err());
+    }
+
+    #[test]
+    fn cookie_and_return_path_helpers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::COOKIE,
+            "theme=dark; tandem_session=abc123".parse().unwrap(),
+        );
+        assert_eq!(session_cookie(&headers).as_deref(), Some("abc123"));
+        assert!(session_cookie_header("abc", 60, true).ends_with("; Secure"));
+        assert_eq!(safe_return_to(Some("/admin/runs"), "/admin"), "/admin/runs");
+        assert_eq!(safe_return_to(Some("//evil.com"), "/admin"), "/admin");
+        assert_eq!(safe_return_to(Some("https://evil.com"), "/admin"), "/admin");
+    }
+}
@@ -0,0 +1,404 @@
+//! Minimal streaming Parquet writer for flat tables.
+//!
+//! Enough of the format for exports: a flat schema of optional columns,
+//! PLAIN-encoded values, uncompressed V1 data pages, one page per column per
+//! row group. Row groups are encoded as they are handed in, so a caller can
+//! send each group to the client and drop it; only the row group metadata is
+//! kept until the footer is written.
+
+/// Leading and trailing magic bytes of every Parquet file.
+pub const MAGIC: &[u8; 4] = b"PAR1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Text,
+    Int64,
+    /// Milliseconds since the epoch, annotated `TIMESTAMP_MILLIS`.
+    TimestampMs,
+    Double,
+    Bool,
+}
+
+impl ColumnType {
+    fn physical(self) -> i32 {
+        match self {
+            Self::Bool => 0,
+            Self::Int64 | Self::TimestampMs => 2,
+            Self::Double => 5,
+            Self::Text => 6,
+        }
+    }
+
+    fn converted(self) -> Option<i32> {
+        match self {
+            Self::Text => Some(0),
+            Self::TimestampMs => Some(9),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Null,
+    Text(String),
+    Int(i64),
+    Double(f64),
+    Bool(bool),
+}
+
+impl Cell {
+    pub fn text(value: impl Into<String>) -> Self {
+        Self::Text(value.into())
+    }
+
+    pub fn opt_text(value: Option<&str>) -> Self {
+        value.map_or(Self::Null, Self::text)
+    }
+
+    pub fn int(value: u64) -> Self {
+        Self::Int(i64::try_from(value).unwrap_or(i64::MAX))
+    }
+
+    pub fn opt_int(value: Option<u64>) -> Self {
+        value.map_or(Self::Null, Self::int)
+    }
+
+    pub fn opt_double(value: Option<f64>) -> Self {
+        value.map_or(Self::Null, Self::Double)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParquetColumn {
+    pub name: String,
+    pub kind: ColumnType,
+}
+
+struct ChunkMeta {
+    num_values: i64,
+    size: i64,
+    page_offset: i64,
+}
+
+struct RowGroupMeta {
+    chunks: Vec<ChunkMeta>,
+    num_rows: i64,
+    size: i64,
+}
+
+pub struct ParquetWriter {
+    columns: Vec<ParquetColumn>,
+    offset: u64,
+    row_groups: Vec<RowGroupMeta>,
+}
+
+impl ParquetWriter {
+    pub fn new(columns: Vec<ParquetColumn>) -> Self {
+        Self {
+            columns,
+            offset: 0,
+            row_groups: Vec::new(),
+        }
+    }
+
+    /// Bytes that open the file.
+    pub fn start(&mut self) -> Vec<u8> {
+        self.offset = MAGIC.len() as u64;
+        MAGIC.to_vec()
+    }
+
+    /// Encode `rows` as one row group. Cells that do not match their
+    /// column's type are written as nulls.
+    pub fn row_group(&mut self, rows: &[Vec<Cell>]) -> Vec<u8> {
+        if rows.is_empty() {
+            return Vec::new();
+        }
+        let mut out = Vec::new();
+        let mut chunks = Vec::with_capacity(self.columns.len());
+        for (index, column) in self.columns.iter().enumerate() {
+            let cells = rows.iter().map(|row| row.get(index).unwrap_or(&Cell::Null));
+            let page = encode_page(column.kind, cells);
+            let header = page_header(rows.len(), page.len());
+            chunks.push(ChunkMeta {
+                num_values: rows.len() as i64,
+                size: (header.len() + page.len()) as i64,
+                page_offset: (self.offset + out.len() as u64) as i64,
+            });
+            out.extend_from_slice(&header);
+            out.extend_from_slice(&page);
+        }
+        self.offset += out.len() as u64;
+        self.row_groups.push(RowGroupMeta {
+            chunks,
+            num_rows: rows.len() as i64,
+            size: out.len() as i64,
+        });
+        out
+    }
+
+    /// The footer: file metadata, its length and the closing magic.
+    pub fn finish(self) -> Vec<u8> {
+        let mut meta = Thrift::default();
+        meta.i32(1, 1);
+        meta.list_begin(2, STRUCT, self.columns.len() + 1);
+        meta.struct_begin();
+        meta.binary(4, b"schema");
+        meta.i32(5, self.columns.len() as i32);
+        meta.struct_end();
+        for column in &self.columns {
+            meta.struct_begin();
+            meta.i32(1, column.kind.physical());
+            meta.i32(3, 1); // OPTIONAL
+            meta.binary(4, column.name.as_bytes());
+            if let Some(converted) = column.kind.converted() {
+                meta.i32(6, converted);
+            }
+            meta.struct_end();
+        }
+        meta.i64(3, self.row_groups.iter().map(|g| g.num_rows).sum());
+        meta.list_begin(4, STRUCT, self.row_groups.len());
+        for group in &self.row_groups {
+            meta.struct_begin();
+            meta.list_begin(1, STRUCT, group.chunks.len());
+            for (chunk, column) in group.chunks.iter().zip(&self.columns) {
+                meta.struct_begin();
+                meta.i64(2, chunk.page_offset);
+                meta.field(3, STRUCT);
+                meta.struct_begin();
+                meta.i32(1, column.kind.physical());
+                meta.list_begin(2, I32, 2);
+                meta.varint(zigzag(PLAIN.into()));
+                meta.varint(zigzag(RLE.into()));
+                meta.list_begin(3, BINARY, 1);
+                meta.raw_binary(column.name.as_bytes());
+                meta.i32(4, 0); // UNCOMPRESSED
+                meta.i64(5, chunk.num_values);
+                meta.i64(6, chunk.size);
+                meta.i64(7, chunk.size);
+                meta.i64(9, chunk.page_offset);
+                meta.struct_end();
+                meta.struct_end();
+            }
+            meta.i64(2, group.size);
+            meta.i64(3, group.num_rows);
+            meta.struct_end();
+        }
+        meta.binary(6, b"tandem-server");
+        meta.stop();
+        let mut out = meta.buf;
+        let len = out.len() as u32;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(MAGIC);
+        out
+    }
+}
+
+const PLAIN: i32 = 0;
+const RLE: i32 = 3;
+
+/// Definition levels (RLE, length-prefixed) followed by the PLAIN values.
+fn encode_page<'a>(kind: ColumnType, cells: impl Iterator<Item = &'a Cell>) -> Vec<u8> {
+    let mut present = Vec::new();
+    let mut values = Vec::new();
+    let mut bits = Vec::new();
+    for cell in cells {
+        let written = match (kind, cell) {
+            (ColumnType::Text, Cell::Text(text)) => {
+                values.extend_from_slice(&(text.len() as u32).to_le_bytes());
+                values.extend_from_slice(text.as_bytes());
+                true
+            }
+            (ColumnType::Int64 | ColumnType::TimestampMs, Cell::Int(value)) => {
+                values.extend_from_slice(&value.to_le_bytes());
+                true
+            }
+            (ColumnType::Double, Cell::Double(value)) => {
+                values.extend_from_slice(&value.to_le_bytes());
+                true
+            }
+            (ColumnType::Bool, Cell::Bool(value)) => {
+                bits.push(*value);
+                true
+            }
+            _ => false,
+        };
+        present.push(written);
+    }
+    if kind == ColumnType::Bool {
+        values = bits.chunks(8).map(pack_bits).collect();
+    }
+    let levels = rle_levels(&present);
+    let mut page = Vec::with_capacity(4 + levels.len() + values.len());
+    page.extend_from_slice(&(levels.len() as u32).to_le_bytes());
+    page.extend_from_slice(&levels);
+    page.extend_from_slice(&values);
+    page
+}
+
+fn pack_bits(bits: &[bool]) -> u8 {
+    bits.iter()
+        .enumerate()
+        .fold(0u8, |byte, (i, bit)| byte | (u8::from(*bit) << i))
+}
+
+/// Bit width 1 runs of the RLE/bit-packing hybrid: `varint(len << 1), value`.
+fn rle_levels(present: &[bool]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut index = 0;
+    while index < present.len() {
+        let value = present[index];
+        let run = present[index..].iter().take_while(|v| **v == value).count();
+        write_varint(&mut out, (run as u64) << 1);
+        out.push(u8::from(value));
+        index += run;
+    }
+    out
+}
+
+fn page_header(num_values: usize, page_len: usize) -> Vec<u8> {
+    let mut header = Thrift::default();
+    header.i32(1, 0); // DATA_PAGE
+    header.i32(2, page_len as i32);
+    header.i32(3, page_len as i32);
+    header.field(5, STRUCT);
+    header.struct_begin();
+    header.i32(1, num_values as i32);
+    header.i32(2, PLAIN);
+    header.i32(3, RLE);
+    header.i32(4, RLE);
+    header.struct_end();
+    header.stop();
+    header.buf
+}
+
+const I32: u8 = 5;
+const I64: u8 = 6;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const STRUCT: u8 = 12;
+
+/// Thrift compact protocol, just the parts the metadata needs.
+#[derive(Default)]
+struct Thrift {
+    buf: Vec<u8>,
+    last_field: i16,
+    stack: Vec<i16>,
+}
+
+impl Thrift {
+    fn field(&mut self, id: i16, kind: u8) {
+        let delta = id - self.last_field;
+        if (1..=15).contains(&delta) {
+            self.buf.push(((delta as u8) << 4) | kind);
+        } else {
+            self.buf.push(kind);
+            self.varint(zigzag(id.into()));
+        }
+        self.last_field = id;
+    }
+
+    fn varint(&mut self, value: u64) {
+        write_varint(&mut self.buf, value);
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, I32);
+        self.varint(zigzag(value.into()));
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, I64);
+        self.varint(zigzag(value));
+    }
+
+    fn binary(&mut self, id: i16, value: &[u8]) {
+        self.field(id, BINARY);
+        self.raw_binary(value);
+    }
+
+    fn raw_binary(&mut self, value: &[u8]) {
+        self.varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    fn list_begin(&mut self, id: i16, element: u8, len: usize) {
+        self.field(id, LIST);
+        if len < 15 {
+            self.buf.push(((len as u8) << 4) | element);
+        } else {
+            self.buf.push(0xF0 | element);
+            self.varint(len as u64);
+        }
+    }
+
+    fn struct_begin(&mut self) {
+        self.stack.push(self.last_field);
+        self.last_field = 0;
+    }
+
+    fn struct_end(&mut self) {
+        self.stop();
+        self.last_field = self.stack.pop().unwrap_or(0);
+    }
+
+    fn stop(&mut self) {
+        self.buf.push(0);
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_is_framed_by_magic_and_footer_length() {
+        let mut writer = ParquetWriter::new(vec![
+            ParquetColumn {
+                name: "id".to_string(),
+                kind: ColumnType::Text,
+            },
+            ParquetColumn {
+                name: "ok".to_string(),
+                kind: ColumnType::Bool,
+            },
+        ]);
+        let mut file = writer.start();
+        file.extend(writer.row_group(&[
+            vec![Cell::text("a"), Cell::Bool(true)],
+            vec![Cell::Null, Cell::Bool(false)],
+        ]));
+        let body_len = file.len();
+        file.extend(writer.finish());
+        assert_eq!(&file[..4], MAGIC);
+        assert_eq!(&file[file.len() - 4..], MAGIC);
+        let footer = u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap());
+        assert_eq!(body_len + footer as usize + 8, file.len());
+    }
+
+    #[test]
+    fn pages_carry_definition_levels_then_plain_values() {
+        let cells = [Cell::Int(7), Cell::Null, Cell::Int(-1)];
+        let page = encode_page(ColumnType::Int64, cells.iter());
+        // 3 RLE runs (present, null, present) of 2 bytes each.
+        assert_eq!(&page[..4], &6u32.to_le_bytes());
+        assert_eq!(&page[4..10], &[2, 1, 2, 0, 2, 1]);
+        assert_eq!(&page[10..18], &7i64.to_le_bytes());
+        assert_eq!(&page[18..], &(-1i64).to_le_bytes());
+        assert_eq!(pack_bits(&[true, false, true]), 0b101);
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+    }
+}
@@ -0,0 +1,238 @@
+//! Failed state-file writes and their retries.
+//!
+//! Server stores are written as whole JSON snapshots of in-memory state, so a
+//! failed write (disk full, permissions) can be retried later by writing the
+//! current state again. While any store has an unpersisted change the server
+//! reports itself as degraded in `GET /global/health`. Each failure is
+//! published as `storage.write_failed` and the eventual successful retry as
+//! `storage.write_recovered`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::json;
+use tandem_types::EngineEvent;
+use tokio::sync::RwLock;
+
+use crate::{now_ms, AppState};
+
+const RETRY_BASE_MS: u64 = 1_000;
+const RETRY_MAX_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PersistTarget {
+    Routines,
+    RoutineHistory,
+    RoutineRuns,
+    SharedResources,
+    UserUsage,
+}
+
+impl PersistTarget {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Routines => "routines",
+            Self::RoutineHistory => "routine_history",
+            Self::RoutineRuns => "routine_runs",
+            Self::SharedResources => "shared_resources",
+            Self::UserUsage => "user_usage",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistFailure {
+    pub store: PersistTarget,
+    pub error: String,
+    pub first_failed_at_ms: u64,
+    pub last_failed_at_ms: u64,
+    /// Consecutive failed writes, including retries.
+    pub attempts: u32,
+}
+
+/// Delay before retry number `attempts` (1-based): 1s, 2s, 4s, ... capped at
+/// one minute.
+pub fn retry_delay_ms(attempts: u32) -> u64 {
+    let exponent = attempts.saturating_sub(1).min(16);
+    RETRY_BASE_MS
+        .saturating_mul(1 << exponent)
+        .min(RETRY_MAX_MS)
+}
+
+#[derive(Clone, Default)]
+pub struct PersistenceHealth {
+    failures: Arc<RwLock<HashMap<PersistTarget, PersistFailure>>>,
+}
+
+impl PersistenceHealth {
+    async fn record_failure(
+        &self,
+        target: PersistTarget,
+        error: String,
+        now: u64,
+    ) -> PersistFailure {
+        let mut failures = self.failures.write().await;
+        let entry = failures.entry(target).or_insert_with(|| PersistFailure {
+            store: target,
+            error: String::new(),
+            first_failed_at_ms: now,
+            last_failed_at_ms: now,
+            attempts: 0,
+        });
+        entry.error = error;
+        entry.last_failed_at_ms = now;
+        entry.attempts = entry.attempts.saturating_add(1);
+        entry.clone()
+    }
+
+    /// Clear `target`; returns the failure it replaces, if any.
+    async fn record_success(&self, target: PersistTarget) -> Option<PersistFailure> {
+        self.failures.write().await.remove(&target)
+    }
+
+    async fn is_failing(&self, target: PersistTarget) -> bool {
+        self.failures.read().await.contains_key(&target)
+    }
+
+    pub async fn failures(&self) -> Vec<PersistFailure> {
+        let mut rows = self
+            .failures
+            .read()
+            .await
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|row| row.first_failed_at_ms);
+        rows
+    }
+
+    pub async fn is_degraded(&self) -> bool {
+        !self.failures.read().await.is_empty()
+    }
+}
+
+impl AppState {
+    async fn persist_target(&self, target: PersistTarget) -> anyhow::Result<()> {
+        match target {
+            PersistTarget::Routines => self.persist_routines().await,
+            PersistTarget::RoutineHistory => self.persist_routine_history().await,
+            PersistTarget::RoutineRuns => self.persist_routine_runs().await,
+            PersistTarget::SharedResources => self.persist_shared_resources().await,
+            PersistTarget::UserUsage => self.user_usage.persist().await,
+        }
+    }
+
+    /// Write `target` to disk. A failure marks the server degraded, is
+    /// published as `storage.write_failed` and is retried in the background
+    /// until a write of the store succeeds; the error is still returned so
+    /// API callers can report it.
+    pub async fn persist_checked(&self, target: PersistTarget) -> anyhow::Result<()> {
+        match self.persist_target(target).await {
+            Ok(()) => {
+                self.note_persist_success(target).await;
+                Ok(())
+            }
+            Err(err) => {
+                let failure = self
+                    .persistence
+                    .record_failure(target, err.to_string(), now_ms())
+                    .await;
+                self.publish_persist_failure(&failure);
+                if failure.attempts == 1 {
+                    tandem_core::spawn_in_request(self.clone().retry_persist(target));
+                }
+                Err(err)
+            }
+        }
+    }
+
+    async fn note_persist_success(&self, target: PersistTarget) {
+        self.replication.notify_change();
+        if let Some(failure) = self.persistence.record_success(target).await {
+            tracing::info!(
+                "{} writes recovered after {} failed attempts",
+                target.as_str(),
+                failure.attempts
+            );
+            self.event_bus.publish(EngineEvent::new(
+                "storage.write_recovered",
+                json!({
+                    "store": target,
+                    "attempts": failure.attempts,
+                    "degradedForMs": now_ms().saturating_sub(failure.first_failed_at_ms),
+                }),
+            ));
+        }
+    }
+
+    fn publish_persist_failure(&self, failure: &PersistFailure) {
+        tracing::warn!(
+            "failed to persist {} (attempt {}): {}",
+            failure.store.as_str(),
+            failure.attempts,
+            failure.error
+        );
+        self.event_bus.publish(EngineEvent::new(
+            "storage.write_failed",
+            json!({
+                "store": failure.store,
+                "error": failure.error,
+                "attempts": failure.attempts,
+                "retryInMs": retry_delay_ms(failure.attempts),
+            }),
+        ));
+    }
+
+    async fn retry_persist(self, target: PersistTarget) {
+        let mut attempts = 1;
+        while self.persistence.is_failing(target).await {
+            tokio::time::sleep(Duration::from_millis(retry_delay_ms(attempts))).await;
+            match self.persist_target(target).await {
+                Ok(()) => self.note_persist_success(target).await,
+                Err(err) => {
+                    let failure = self
+                        .persistence
+                        .record_failure(target, err.to_string(), now_ms())
+                        .await;
+                    attempts = failure.attempts;
+                    self.publish_persist_failure(&failure);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_delay_backs_off_to_a_cap() {
+        assert_eq!(retry_delay_ms(1), 1_000);
+        assert_eq!(retry_delay_ms(2), 2_000);
+        assert_eq!(retry_delay_ms(4), 8_000);
+        assert_eq!(retry_delay_ms(7), 60_000);
+        assert_eq!(retry_delay_ms(u32::MAX), 60_000);
+    }
+
+    #[tokio::test]
+    async fn failures_accumulate_until_a_success() {
+        let health = PersistenceHealth::default();
+        health
+            .record_failure(PersistTarget::RoutineRuns, "disk full".to_string(), 10)
+            .await;
+        let second = health
+            .record_failure(PersistTarget::RoutineRuns, "disk full".to_string(), 20)
+            .await;
+        assert_eq!(second.attempts, 2);
+        assert_eq!(second.first_failed_at_ms, 10);
+        assert!(health.is_degraded().await);
+        let cleared = health.record_success(PersistTarget::RoutineRuns).await;
+        assert_eq!(cleared.map(|f| f.attempts), Some(2));
+        assert!(!health.is_degraded().await);
+    }
+}
@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::state_file::JsonStateFile;
+use crate::tenants::TenantScope;
+
+/// Header naming the user an admin-token caller (such as the channel
+/// dispatcher) acts for: a linked Tandem user or `channel:sender`. Ignored
+/// for callers that authenticate as someone themselves.
+pub const USER_HEADER: &str = "x-tandem-user-id";
+
+/// Request extension set by the auth gate when the caller's identity comes
+/// from its credentials (the subject of an OIDC session).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthenticatedUser(pub String);
+
+/// Extractor for the principal a request's quota is counted against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuotaUser {
+    /// A signed-in user, or `tenant:<id>` for a tenant token.
+    Authenticated(String),
+    /// An admin-token (or unauthenticated single-user) caller, acting for the
+    /// user named in [`USER_HEADER`], if any.
+    Delegated(Option<String>),
+}
+
+impl QuotaUser {
+    pub fn user_id(&self) -> Option<&str> {
+        match self {
+            Self::Authenticated(user_id) => Some(user_id),
+            Self::Delegated(user_id) => user_id.as_deref(),
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for QuotaUser {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(AuthenticatedUser(user_id)) = parts.extensions.get::<AuthenticatedUser>() {
+            return Ok(Self::Authenticated(user_id.clone()));
+        }
+        if let Some(scope) = parts.extensions.get::<TenantScope>() {
+            return Ok(Self::Authenticated(format!("tenant:{}", scope.tenant_id)));
+        }
+        Ok(Self::Delegated(
+            parts
+                .headers
+                .get(USER_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(ToString::to_string),
+        ))
+    }
+}
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Daily limits. `None` or `0` means unlimited.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuotaLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_runs: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_tokens: Option<u64>,
+}
+
+/// `quotas` config block: defaults plus per-user overrides.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QuotaConfig {
+    #[serde(flatten)]
+    pub default: QuotaLimits,
+    #[serde(default)]
+    pub users: HashMap<String, QuotaLimits>,
+}
+
+impl QuotaConfig {
+    /// Limits for `user_id`: per-user fields override the defaults.
+    pub fn limits_for(&self, user_id: &str) -> QuotaLimits {
+        let user = self.users.get(user_id);
+        QuotaLimits {
+            daily_runs: user
+                .and_then(|u| u.daily_runs)
+                .or(self.default.daily_runs)
+                .filter(|v| *v > 0),
+            daily_tokens: user
+                .and_then(|u| u.daily_tokens)
+                .or(self.default.daily_tokens)
+                .filter(|v| *v > 0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserUsage {
+    pub user_id: String,
+    /// Days since the Unix epoch (UTC) that `runs_today`/`tokens_today` cover.
+    pub day: u64,
+    pub runs_today: u32,
+    pub tokens_today: u64,
+    pub total_runs: u64,
+    pub total_tokens: u64,
+    pub last_seen_at_ms: u64,
+}
+
+impl UserUsage {
+    fn roll_day(&mut self, day: u64) {
+        if self.day != day {
+            self.day = day;
+            self.runs_today = 0;
+            self.tokens_today = 0;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaSnapshot {
+    pub user_id: String,
+    pub runs_used: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runs_limit: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runs_remaining: Option<u32>,
+    pub tokens_used: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens_limit: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens_remaining: Option<u64>,
+    pub total_runs: u64,
+    pub total_tokens: u64,
+    pub resets_at_ms: u64,
+}
+
+impl QuotaSnapshot {
+    fn new(usage: &UserUsage, limits: &QuotaLimits, day: u64) -> Self {
+        let (runs_used, tokens_used) = if usage.day == day {
+            (usage.runs_today, usage.tokens_today)
+        } else {
+            (0, 0)
+        };
+        Self {
+            user_id: usage.user_id.clone(),
+            runs_used,
+            runs_limit: limits.daily_runs,
+            runs_remaining: limits
+                .daily_runs
+                .map(|limit| limit.saturating_sub(runs_used)),
+            tokens_used,
+            tokens_limit: limits.daily_tokens,
+            tokens_remaining: limits
+                .daily_tokens
+                .map(|limit| limit.saturating_sub(tokens_used)),
+            total_runs: usage.total_runs,
+            total_tokens: usage.total_tokens,
+            resets_at_ms: (day + 1) * DAY_MS,
+        }
+    }
+
+    pub fn exhausted(&self) -> Option<&'static str> {
+        if self.runs_remaining == Some(0) {
+            return Some("daily run quota exhausted");
+        }
+        if self.tokens_remaining == Some(0) {
+            return Some("daily token budget exhausted");
+        }
+        None
+    }
+}
+
+/// Tracks per-user run and token usage, persisted to `user_usage.json`.
+#[derive(Clone)]
+pub struct UserUsageTracker {
+    file: JsonStateFile,
+    usage: Arc<RwLock<HashMap<String, UserUsage>>>,
+    session_users: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl UserUsageTracker {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            file: JsonStateFile::new(path),
+            usage: Arc::new(RwLock::new(HashMap::new())),
+            session_users: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.file.set_path(path);
+    }
+
+    /// A usage file that does not parse is an error: treating it as empty
+    /// would hand every user a fresh quota.
+    pub async fn load(&self) -> anyhow::Result<()> {
+        if let Some(parsed) = self.file.load::<HashMap<String, UserUsage>>().await? {
+            *self.usage.write().await = parsed;
+        }
+        Ok(())
+    }
+
+    /// Written by [`AppState::persist_checked`](crate::AppState::persist_checked)
+    /// after every change, so failed writes mark the server degraded and are
+    /// retried.
+    pub async fn persist(&self) -> anyhow::Result<()> {
+        let guard = self.usage.read().await;
+        self.file.save(&*guard).await
+    }
+
+    /// Count a run against `user_id` unless their daily quota is exhausted.
+    /// On success, token usage reported for `session_id` is attributed to them.
+    pub async fn try_start_run(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        limits: &QuotaLimits,
+        now_ms: u64,
+    ) -> Result<QuotaSnapshot, QuotaSnapshot> {
+        let day = now_ms / DAY_MS;
+        let snapshot = {
+            let mut usage = self.usage.write().await;
+            let entry = usage
+                .entry(user_id.to_string())
+                .or_insert_with(|| UserUsage {
+                    user_id: user_id.to_string(),
+                    day,
+                    ..UserUsage::default()
+                });
+            entry.roll_day(day);
+            let before = QuotaSnapshot::new(entry, limits, day);
+            if before.exhausted().is_some() {
+                return Err(before);
+            }
+            entry.runs_today += 1;
+            entry.total_runs += 1;
+            entry.last_seen_at_ms = now_ms;
+            QuotaSnapshot::new(entry, limits, day)
+        };
+        self.session_users
+            .write()
+            .await
+            .insert(session_id.to_string(), user_id.to_string());
+        Ok(snapshot)
+    }
+
+    /// Attribute provider token usage for `session_id` to the user who started
+    /// its latest run. Returns the user charged, if any.
+    pub async fn record_tokens(
+        &self,
+        session_id: &str,
+        tokens: u64,
+        now_ms: u64,
+    ) -> Option<String> {
+        let user_id = self.session_users.read().await.get(session_id).cloned()?;
+        {
+            let mut usage = self.usage.write().await;
+            let entry = usage.get_mut(&user_id)?;
+            entry.roll_day(now_ms / DAY_MS);
+            entry.tokens_today = entry.tokens_today.saturating_add(tokens);
+            entry.total_tokens = entry.total_tokens.saturating_add(tokens);
+            entry.last_seen_at_ms = now_ms;
+        }
+        Some(user_id)
+    }
+
+    pub async fn snapshot(
+        &self,
+        user_id: &str,
+        limits: &QuotaLimits,
+        now_ms: u64,
+    ) -> QuotaSnapshot {
+        let day = now_ms / DAY_MS;
+        let usage = self.usage.read().await;
+        let empty = UserUsage {
+            user_id: user_id.to_string(),
+            day,
+            ..UserUsage::default()
+        };
+        QuotaSnapshot::new(usage.get(user_id).unwrap_or(&empty), limits, day)
+    }
+
+    pub async fn user_ids(&self) -> Vec<String> {
+        let mut ids = self.usage.read().await.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker() -> UserUsageTracker {
+        UserUsageTracker::new(
+            std::env::temp_dir()
+                .join(format!("tandem-quota-test-{}", uuid::Uuid::new_v4()))
+                .join("user_usage.json"),
+        )
+    }
+
+    #[test]
+    fn user_override_wins_over_default() {
+        let config: QuotaConfig = serde_json::from_value(serde_json::json!({
+            "daily_runs": 10,
+            "daily_tokens": 5000,
+            "users": { "evan": { "daily_runs": 0 } }
+        }))
+        .expect("config");
+        let evan = config.limits_for("evan");
+        assert_eq!(evan.daily_runs, None);
+        assert_eq!(evan.daily_tokens, Some(5000));
+        assert_eq!(config.limits_for("alice").daily_runs, Some(10));
+    }
+
+    #[tokio::test]
+    async fn run_quota_blocks_after_limit_and_resets_next_day() {
+        let tracker = tracker();
+        let limits = QuotaLimits {
+            daily_runs: Some(2),
+            daily_tokens: None,
+        };
+        let now = 10 * DAY_MS;
+        assert!(tracker
+            .try_start_run("evan", "s1", &limits, now)
+            .await
+            .is_ok());
+        let second = tracker
+            .try_start_run("evan", "s1", &limits, now)
+            .await
+            .expect("second run");
+        assert_eq!(second.runs_remaining, Some(0));
+        assert!(tracker
+            .try_start_run("evan", "s1", &limits, now)
+            .await
+            .is_err());
+        assert!(tracker
+            .try_start_run("evan", "s1", &limits, now + DAY_MS)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn only_admin_callers_may_name_the_quota_user() {
+        let parts = |tenant: Option<&str>, user: Option<&str>| {
+            let mut request = axum::http::Request::builder()
+                .header(USER_HEADER, "someone-else")
+                .body(())
+                .expect("request");
+            if let Some(tenant_id) = tenant {
+                request.extensions_mut().insert(TenantScope {
+                    tenant_id: tenant_id.to_string(),
+                });
+            }
+            if let Some(user_id) = user {
+                request
+                    .extensions_mut()
+                    .insert(AuthenticatedUser(user_id.to_string()));
+            }
+            request.into_parts().0
+        };
+        let resolve = |mut parts: Parts| async move {
+            QuotaUser::from_request_parts(&mut parts, &())
+                .await
+                .expect("infallible")
+        };
+        assert_eq!(
+            resolve(parts(None, None)).await,
+            QuotaUser::Delegated(Some("someone-else".to_string()))
+        );
+        assert_eq!(
+            resolve(parts(Some("acme"), None)).await.user_id(),
+            Some("tenant:acme")
+        );
+        assert_eq!(
+            resolve(parts(Some("acme"), Some("sub-1"))).await.user_id(),
+            Some("sub-1")
+        );
+    }
+
+    #[tokio::test]
+    async fn tokens_are_attributed_through_session() {
+        let tracker = tracker();
+        let limits = QuotaLimits {
+            daily_runs: None,
+            daily_tokens: Some(100),
+        };
+        let now = 3 * DAY_MS;
+        tracker
+            .try_start_run("evan", "s1", &limits, now)
+            .await
+            .expect("run");
+        assert_eq!(
+            tracker.record_tokens("s1", 150, now).await.as_deref(),
+            Some("evan")
+        );
+        assert!(tracker.record_tokens("unknown", 10, now).await.is_none());
+        let snapshot = tracker.snapshot("evan", &limits, now).await;
+        assert_eq!(snapshot.tokens_remaining, Some(0));
+        assert_eq!(snapshot.exhausted(), Some("daily token budget exhausted"));
+    }
+
+    #[tokio::test]
+    async fn corrupt_usage_file_fails_closed() {
+        let tracker = tracker();
+        let limits = QuotaLimits {
+            daily_runs: Some(1),
+            daily_tokens: None,
+        };
+        let now = 5 * DAY_MS;
+        tracker
+            .try_start_run("evan", "s1", &limits, now)
+            .await
+            .expect("run");
+        tracker.persist().await.expect("persist");
+        let path = tracker.file.path().to_path_buf();
+
+        let reloaded = UserUsageTracker::new(path.clone());
+        reloaded.load().await.expect("load");
+        assert!(reloaded
+            .try_start_run("evan", "s1", &limits, now)
+            .await
+            .is_err());
+
+        std::fs::write(&path, "{\"evan\": {\"user_id\"").expect("truncate");
+        let corrupt = UserUsageTracker::new(path.clone());
+        assert!(corrupt.load().await.is_err());
+        assert!(corrupt.persist().await.is_err());
+        let _ = std::fs::remove_dir_all(path.parent().expect("dir"));
+    }
+}
@@ -0,0 +1,763 @@
+//! Hot-standby replication of the state directory.
+//!
+//! When the `replication` config block is enabled, files under the state
+//! directory are mirrored to a second directory or an S3-compatible bucket.
+//! File contents are stored content-addressed under `objects/<sha256>` and a
+//! `manifest.json` maps state-relative paths to those objects, so a replica
+//! is only ever observed at a consistent point: objects are uploaded first,
+//! then the manifest, and unreferenced objects are removed last.
+//!
+//! Syncs run every `interval_secs` and shortly after any store write. A
+//! replica is restored with `tandem-engine replica restore` while the engine
+//! is stopped; every object is checked against the manifest before any file
+//! in the state directory is replaced.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use tandem_types::EngineEvent;
+use tokio::sync::{Mutex, Notify};
+
+use crate::object_store::{hex, sha256_hex, S3Bucket, S3BucketConfig};
+use crate::{now_ms, AppState};
+
+pub const MANIFEST_KEY: &str = "manifest.json";
+const OBJECTS_PREFIX: &str = "objects/";
+const MANIFEST_VERSION: u32 = 1;
+/// Writes arrive in bursts; wait this long after a change before syncing.
+const CHANGE_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// `replication` config block. Exactly one of `directory` or `s3` must be
+/// set when `enabled` is true.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicationConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub directory: Option<PathBuf>,
+    /// S3-compatible bucket; keys fall back to
+    /// `TANDEM_REPLICA_S3_ACCESS_KEY_ID` / `TANDEM_REPLICA_S3_SECRET_ACCESS_KEY`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub s3: Option<S3BucketConfig>,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    /// Paths relative to the state directory that are not replicated.
+    #[serde(default = "default_exclude")]
+    pub exclude: Vec<String>,
+}
+
+impl Default for ReplicationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: None,
+            s3: None,
+            interval_secs: default_interval_secs(),
+            exclude: default_exclude(),
+        }
+    }
+}
+
+fn default_interval_secs() -> u64 {
+    5 * 60
+}
+
+fn default_exclude() -> Vec<String> {
+    vec!["backups".to_string(), "logs".to_string()]
+}
+
+impl ReplicationConfig {
+    /// The configured replica, `None` when replication is disabled.
+    pub fn target(&self) -> Result<Option<ReplicaTarget>, String> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        match (&self.directory, &self.s3) {
+            (Some(dir), None) => Ok(Some(ReplicaTarget::Directory(dir.clone()))),
+            (None, Some(s3)) => {
+                S3Bucket::from_config(s3, "TANDEM_REPLICA_S3").map(|s3| Some(ReplicaTarget::S3(s3)))
+            }
+            (None, None) => Err("replication needs a `directory` or `s3` target".to_string()),
+            (Some(_), Some(_)) => {
+                Err("replication takes either `directory` or `s3`, not both".to_string())
+            }
+        }
+    }
+
+    fn is_excluded(&self, relative: &str) -> bool {
+        self.exclude.iter().any(|rule| {
+            let rule = rule.trim_matches('/');
+            !rule.is_empty()
+                && (relative == rule
+                    || relative
+                        .strip_prefix(rule)
+                        .is_some_and(|rest| rest.starts_with('/')))
+        })
+    }
+}
+
+/// Where replicated files are written.
+#[derive(Debug, Clone)]
+pub enum ReplicaTarget {
+    Directory(PathBuf),
+    S3(S3Bucket),
+}
+
+impl ReplicaTarget {
+    /// Location for status output; never includes credentials.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Directory(dir) => dir.to_string_lossy().to_string(),
+            Self::S3(s3) => s3.describe(),
+        }
+    }
+
+    pub async fn put(&self, key: &str, bytes: Vec<u8>) -> anyhow::Result<()> {
+        match self {
+            Self::Directory(dir) => {
+                let path = dir.join(key);
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                // Write then rename so a reader never sees a partial object.
+                let tmp = path.with_extension("partial");
+                tokio::fs::write(&tmp, bytes).await?;
+                tokio::fs::rename(&tmp, &path).await?;
+                Ok(())
+            }
+            Self::S3(s3) => s3.put(key, bytes).await,
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        match self {
+            Self::Directory(dir) => match tokio::fs::read(dir.join(key)).await {
+                Ok(bytes) => Ok(Some(bytes)),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err.into()),
+            },
+            Self::S3(s3) => s3.get(key).await,
+        }
+    }
+
+    pub async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Directory(dir) => match tokio::fs::remove_file(dir.join(key)).await {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+                _ => Ok(()),
+            },
+            Self::S3(s3) => s3.delete(key).await,
+        }
+    }
+
+    /// Fetch and check the replica's manifest; `None` for an empty replica.
+    pub async fn manifest(&self) -> anyhow::Result<Option<ReplicaManifest>> {
+        let Some(bytes) = self.get(MANIFEST_KEY).await? else {
+            return Ok(None);
+        };
+        let manifest: ReplicaManifest =
+            serde_json::from_slice(&bytes).context("replica manifest is not valid JSON")?;
+        if manifest.checksum != ReplicaManifest::checksum_of(&manifest.files) {
+            bail!("replica manifest checksum does not match its file list");
+        }
+        Ok(Some(manifest))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path relative to the state directory, `/`-separated.
+    pub path: String,
+    pub sha256: String,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicaManifest {
+    pub version: u32,
+    pub generated_at_ms: u64,
+    pub files: Vec<ManifestEntry>,
+    /// SHA-256 over the file list, so a truncated or hand-edited manifest is
+    /// rejected on restore.
+    pub checksum: String,
+}
+
+impl ReplicaManifest {
+    pub fn new(mut files: Vec<ManifestEntry>, now_ms: u64) -> Self {
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let checksum = Self::checksum_of(&files);
+        Self {
+            version: MANIFEST_VERSION,
+            generated_at_ms: now_ms,
+            files,
+            checksum,
+        }
+    }
+
+    fn checksum_of(files: &[ManifestEntry]) -> String {
+        let mut hasher = Sha256::new();
+        for entry in files {
+            hasher.update(entry.path.as_bytes());
+            hasher.update([0]);
+            hasher.update(entry.sha256.as_bytes());
+            hasher.update([0]);
+            hasher.update(entry.bytes.to_le_bytes());
+        }
+        hex(&hasher.finalize())
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|entry| entry.bytes).sum()
+    }
+}
+
+fn object_key(sha256: &str) -> String {
+    format!("{OBJECTS_PREFIX}{sha256}")
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicationStatus {
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_sync_at_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_success_at_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub files: usize,
+    pub bytes: u64,
+    pub syncs: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub target: String,
+    /// False when nothing changed since the previous manifest.
+    pub changed: bool,
+    pub files: usize,
+    pub bytes: u64,
+    pub uploaded_objects: usize,
+    pub uploaded_bytes: u64,
+    pub deleted_objects: usize,
+}
+
+struct CachedHash {
+    modified: Option<SystemTime>,
+    len: u64,
+    sha256: String,
+}
+
+#[derive(Default)]
+struct ReplicatorState {
+    status: ReplicationStatus,
+    /// Manifest last written to (or read from) `target_key`.
+    manifest: Option<ReplicaManifest>,
+    target_key: Option<String>,
+    hashes: HashMap<String, CachedHash>,
+}
+
+/// Shared replication state; one sync runs at a time.
+#[derive(Clone, Default)]
+pub struct ReplicationHandle {
+    inner: Arc<Mutex<ReplicatorState>>,
+    changed: Arc<Notify>,
+}
+
+impl ReplicationHandle {
+    /// Wake the replication task after a store write.
+    pub fn notify_change(&self) {
+        self.changed.notify_one();
+    }
+
+    pub async fn status(&self) -> ReplicationStatus {
+        self.inner.lock().await.status.clone()
+    }
+
+    async fn set_disabled(&self, error: Option<String>) {
+        let mut inner = self.inner.lock().await;
+        inner.status.enabled = false;
+        inner.status.target = None;
+        inner.status.last_error = error;
+    }
+
+    /// Mirror `root` to `target` once.
+    pub async fn sync(
+        &self,
+        target: &ReplicaTarget,
+        root: &Path,
+        config: &ReplicationConfig,
+    ) -> anyhow::Result<SyncReport> {
+        let mut inner = self.inner.lock().await;
+        let target_key = target.describe();
+        inner.status.enabled = true;
+        inner.status.target = Some(target_key.clone());
+        inner.status.last_sync_at_ms = Some(now_ms());
+        let result = sync_locked(&mut inner, target, &target_key, root, config).await;
+        match &result {
+            Ok(report) => {
+                inner.status.last_success_at_ms = inner.status.last_sync_at_ms;
+                inner.status.last_error = None;
+                inner.status.files = report.files;
+                inner.status.bytes = report.bytes;
+                inner.status.syncs += 1;
+            }
+            Err(err) => inner.status.last_error = Some(err.to_string()),
+        }
+        result
+    }
+}
+
+async fn sync_locked(
+    inner: &mut ReplicatorState,
+    target: &ReplicaTarget,
+    target_key: &str,
+    root: &Path,
+    config: &ReplicationConfig,
+) -> anyhow::Result<SyncReport> {
+    if inner.target_key.as_deref() != Some(target_key) {
+        // New target (or first sync since startup): resume from whatever the
+        // replica already holds instead of uploading everything again.
+        inner.manifest = target.manifest().await.unwrap_or(None);
+        inner.target_key = Some(target_key.to_string());
+    }
+    let previous = inner.manifest.clone();
+    let stored = previous
+        .iter()
+        .flat_map(|manifest| manifest.files.iter().map(|entry| entry.sha256.clone()))
+        .collect::<HashSet<_>>();
+
+    let mut report = SyncReport {
+        target: target_key.to_string(),
+        ..SyncReport::default()
+    };
+    let mut entries = Vec::new();
+    let mut uploaded = HashSet::new();
+    for (relative, path) in list_state_files(root, config, target).await? {
+        let metadata = match tokio::fs::metadata(&path).await {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err.into()),
+        };
+        let modified = metadata.modified().ok();
+        let cached = inner
+            .hashes
+            .get(&relative)
+            .filter(|c| c.modified.is_some() && c.modified == modified && c.len == metadata.len())
+            .map(|c| c.sha256.clone());
+        let entry = match cached {
+            Some(sha256) if stored.contains(&sha256) || uploaded.contains(&sha256) => {
+                ManifestEntry {
+                    path: relative.clone(),
+                    sha256,
+                    bytes: metadata.len(),
+                }
+            }
+            _ => {
+                let bytes = match tokio::fs::read(&path).await {
+                    Ok(bytes) => bytes,
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+                    Err(err) => return Err(err.into()),
+                };
+                let sha256 = sha256_hex(&bytes);
+                let len = bytes.len() as u64;
+                if !stored.contains(&sha256) && uploaded.insert(sha256.clone()) {
+                    target
+                        .put(&object_key(&sha256), bytes)
+                        .await
+                        .with_context(|| format!("upload {relative}"))?;
+                    report.uploaded_objects += 1;
+                    report.uploaded_bytes += len;
+                }
+                inner.hashes.insert(
+                    relative.clone(),
+                    CachedHash {
+                        modified,
+                        len,
+                        sha256: sha256.clone(),
+                    },
+                );
+                ManifestEntry {
+                    path: relative.clone(),
+                    sha256,
+                    bytes: len,
+                }
+            }
+        };
+        entries.push(entry);
+    }
+
+    let manifest = ReplicaManifest::new(entries, now_ms());
+    report.files = manifest.files.len();
+    report.bytes = manifest.total_bytes();
+    report.changed = !matches!(
+        &previous,
+        Some(previous) if previous.checksum == manifest.checksum
+    );
+    if !report.changed {
+        return Ok(report);
+    }
+    target
+        .put(MANIFEST_KEY, serde_json::to_vec_pretty(&manifest)?)
+        .await
+        .context("upload manifest")?;
+
+    let live = manifest
+        .files
+        .iter()
+        .map(|entry| entry.sha256.as_str())
+        .collect::<HashSet<_>>();
+    for sha256 in stored.iter().filter(|sha| !live.contains(sha.as_str())) {
+        // Orphans are harmless; a failed delete is retried on the next change.
+        if target.delete(&object_key(sha256)).await.is_ok() {
+            report.deleted_objects += 1;
+        }
+    }
+    inner
+        .hashes
+        .retain(|path, _| manifest.files.iter().any(|entry| &entry.path == path));
+    inner.manifest = Some(manifest);
+    Ok(report)
+}
+
+/// Files under `root` as `(relative path, absolute path)`, minus excluded
+/// paths and the replica itself when it lives inside the state directory.
+async fn list_state_files(
+    root: &Path,
+    config: &ReplicationConfig,
+    target: &ReplicaTarget,
+) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    let root = root.to_path_buf();
+    let config = config.clone();
+    let replica_dir = match target {
+        ReplicaTarget::Directory(dir) => Some(dir.clone()),
+        ReplicaTarget::S3(_) => None,
+    };
+    let files = tokio::task::spawn_blocking(move || {
+        let skip_dir = replica_dir.clone();
+        ignore::WalkBuilder::new(&root)
+            .standard_filters(false)
+            .filter_entry(move |entry| skip_dir.as_deref() != Some(entry.path()))
+            .build()
+            .flatten()
+            .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+            .filter_map(|entry| {
+                let relative = entry
+                    .path()
+                    .strip_prefix(&root)
+                    .ok()?
+                    .components()
+                    .map(|part| part.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                (!config.is_excluded(&relative)).then(|| (relative, entry.into_path()))
+            })
+            .collect::<Vec<_>>()
+    })
+    .await?;
+    Ok(files)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyReport {
+    pub target: String,
+    pub generated_at_ms: u64,
+    pub files: usize,
+    pub bytes: u64,
+    /// Paths whose object is missing or does not match its checksum.
+    pub problems: Vec<String>,
+}
+
+/// Download every object in the replica and check it against the manifest.
+pub async fn verify_replica(target: &ReplicaTarget) -> anyhow::Result<VerifyReport> {
+    let manifest = target
+        .manifest()
+        .await?
+        .ok_or_else(|| anyhow!("replica at {} has no manifest", target.describe()))?;
+    let mut problems = Vec::new();
+    for entry in &manifest.files {
+        match target.get(&object_key(&entry.sha256)).await? {
+            Some(bytes) if sha256_hex(&bytes) == entry.sha256 => {}
+            Some(_) => problems.push(format!("{}: checksum mismatch", entry.path)),
+            None => problems.push(format!("{}: object missing", entry.path)),
+        }
+    }
+    Ok(VerifyReport {
+        target: target.describe(),
+        generated_at_ms: manifest.generated_at_ms,
+        files: manifest.files.len(),
+        bytes: manifest.total_bytes(),
+        problems,
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestoreReport {
+    pub target: String,
+    pub generated_at_ms: u64,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Copy the replica into `state_dir`. All objects are downloaded and verified
+/// into a staging directory first, so a damaged replica leaves `state_dir`
+/// untouched. Existing files are only replaced with `overwrite`.
+pub async fn restore_from_replica(
+    target: &ReplicaTarget,
+    state_dir: &Path,
+    overwrite: bool,
+) -> anyhow::Result<RestoreReport> {
+    let manifest = target
+        .manifest()
+        .await?
+        .ok_or_else(|| anyhow!("replica at {} has no manifest", target.describe()))?;
+    let mut relative_paths = Vec::with_capacity(manifest.files.len());
+    for entry in &manifest.files {
+        let relative = safe_relative_path(&entry.path)
+            .ok_or_else(|| anyhow!("manifest contains unsafe path `{}`", entry.path))?;
+        if !overwrite && tokio::fs::try_exists(state_dir.join(&relative)).await? {
+            bail!(
+                "{} already exists in {}; pass --overwrite to replace state files",
+                entry.path,
+                state_dir.display()
+            );
+        }
+        relative_paths.push(relative);
+    }
+
+    let dir_name = state_dir
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "state".to_string());
+    let staging = state_dir.with_file_name(format!(".{dir_name}.restore-{}", now_ms()));
+    let staged = stage_replica(target, &manifest, &relative_paths, &staging).await;
+    if let Err(err) = staged {
+        let _ = tokio::fs::remove_dir_all(&staging).await;
+        return Err(err);
+    }
+    for relative in &relative_paths {
+        let dest = state_dir.join(relative);
+        if let Some(parent) = dest.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::rename(staging.join(relative), &dest)
+            .await
+            .with_context(|| format!("move {} into place", relative.display()))?;
+    }
+    let _ = tokio::fs::remove_dir_all(&staging).await;
+    Ok(RestoreReport {
+        target: target.describe(),
+        generated_at_ms: manifest.generated_at_ms,
+        files: manifest.files.len(),
+        bytes: manifest.total_bytes(),
+    })
+}
+
+async fn stage_replica(
+    target: &ReplicaTarget,
+    manifest: &ReplicaManifest,
+    relative_paths: &[PathBuf],
+    staging: &Path,
+) -> anyhow::Result<()> {
+    for (entry, relative) in manifest.files.iter().zip(relative_paths) {
+        let bytes = target
+            .get(&object_key(&entry.sha256))
+            .await?
+            .ok_or_else(|| anyhow!("{}: object missing from replica", entry.path))?;
+        if sha256_hex(&bytes) != entry.sha256 {
+            bail!("{}: checksum mismatch", entry.path);
+        }
+        let dest = staging.join(relative);
+        if let Some(parent) = dest.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&dest, bytes).await?;
+    }
+    Ok(())
+}
+
+/// Reject absolute paths and `..` so a manifest cannot write outside the
+/// state directory.
+fn safe_relative_path(raw: &str) -> Option<PathBuf> {
+    let mut out = PathBuf::new();
+    for part in raw.split('/') {
+        if part.is_empty() || part == "." || part == ".." || part.contains('\\') {
+            return None;
+        }
+        out.push(part);
+    }
+    (!out.as_os_str().is_empty()).then_some(out)
+}
+
+impl AppState {
+    /// Effective `replication` config block.
+    pub async fn replication_config(&self) -> ReplicationConfig {
+        self.config_section("replication").await
+    }
+
+    /// Run one sync against the configured replica. `Ok(None)` when
+    /// replication is disabled.
+    pub async fn sync_replica(&self) -> anyhow::Result<Option<SyncReport>> {
+        let config = self.replication_config().await;
+        let target = match config.target() {
+            Ok(Some(target)) => target,
+            Ok(None) => {
+                self.replication.set_disabled(None).await;
+                return Ok(None);
+            }
+            Err(error) => {
+                self.replication.set_disabled(Some(error.clone())).await;
+                bail!(error);
+            }
+        };
+        match self
+            .replication
+            .sync(&target, &self.state_dir, &config)
+            .await
+        {
+            Ok(report) => {
+                if report.changed {
+                    self.event_bus.publish(EngineEvent::new(
+                        "replication.synced",
+                        json!({
+                            "target": report.target,
+                            "files": report.files,
+                            "uploadedObjects": report.uploaded_objects,
+                            "uploadedBytes": report.uploaded_bytes,
+                        }),
+                    ));
+                }
+                Ok(Some(report))
+            }
+            Err(err) => {
+                self.event_bus.publish(EngineEvent::new(
+                    "replication.failed",
+                    json!({
+                        "target": target.describe(),
+                        "error": err.to_string(),
+                    }),
+                ));
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Background task: sync on an interval and shortly after store writes.
+pub async fn run_state_replication(state: AppState) {
+    // Initial delay so startup is not impacted.
+    tokio::time::sleep(Duration::from_secs(30)).await;
+    loop {
+        if let Err(err) = state.sync_replica().await {
+            tracing::warn!("state replication failed: {err:#}");
+        }
+        let interval = state.replication_config().await.interval_secs.max(10);
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(interval)) => {}
+            _ = state.replication.changed.notified() => {
+                tokio::time::sleep(CHANGE_DEBOUNCE).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(label: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "tandem-replication-{label}-{}",
+            uuid::Uuid::new_v4()
+        ))
+    }
+
+    #[test]
+    fn exclude_matches_whole_path_segments() {
+        let config = ReplicationConfig::default();
+        assert!(config.is_excluded("backups"));
+        assert!(config.is_excluded("backups/backup-1/routines.json"));
+        assert!(!config.is_excluded("backups-old.json"));
+        assert!(!config.is_excluded("routines.json"));
+        assert!(safe_relative_path("../etc/passwd").is_none());
+        assert!(safe_relative_path("/abs").is_none());
+        assert_eq!(
+            safe_relative_path("agent-team/audit.log.jsonl"),
+            Some(PathBuf::from("agent-team").join("audit.log.jsonl"))
+        );
+    }
+
+    #[tokio::test]
+    async fn directory_replica_syncs_incrementally_and_restores() {
+        let state = temp_dir("state");
+        let replica = temp_dir("replica");
+        std::fs::create_dir_all(state.join("backups")).expect("state dir");
+        std::fs::write(state.join("routines.json"), "[]").expect("write");
+        std::fs::write(state.join("backups").join("old.json"), "skip").expect("write");
+        let config = ReplicationConfig {
+            enabled: true,
+            directory: Some(replica.clone()),
+            ..ReplicationConfig::default()
+        };
+        let target = config.target().expect("valid").expect("enabled");
+        let handle = ReplicationHandle::default();
+
+        let first = handle.sync(&target, &state, &config).await.expect("sync");
+        assert!(first.changed);
+        assert_eq!(first.files, 1);
+        assert_eq!(first.uploaded_objects, 1);
+        let unchanged = handle.sync(&target, &state, &config).await.expect("sync");
+        assert!(!unchanged.changed);
+        assert_eq!(unchanged.uploaded_objects, 0);
+
+        std::fs::write(state.join("routines.json"), r#"[{"id":1}]"#).expect("write");
+        let second = handle.sync(&target, &state, &config).await.expect("sync");
+        assert!(second.changed);
+        assert_eq!(second.uploaded_objects, 1);
+        assert_eq!(second.deleted_objects, 1);
+        assert!(verify_replica(&target)
+            .await
+            .expect("verify")
+            .problems
+            .is_empty());
+
+        let restored = temp_dir("restored");
+        let report = restore_from_replica(&target, &restored, false)
+            .await
+            .expect("restore");
+        assert_eq!(report.files, 1);
+        assert_eq!(
+            std::fs::read_to_string(restored.join("routines.json")).expect("read"),
+            r#"[{"id":1}]"#
+        );
+        assert!(restore_from_replica(&target, &restored, false)
+            .await
+            .is_err());
+
+        // A tampered object fails the restore before anything is replaced.
+        let manifest = target.manifest().await.expect("manifest").expect("some");
+        std::fs::write(
+            replica.join(object_key(&manifest.files[0].sha256)),
+            "tampered",
+        )
+        .expect("tamper");
+        assert!(restore_from_replica(&target, &restored, true)
+            .await
+            .is_err());
+        assert_eq!(
+            std::fs::read_to_string(restored.join("routines.json")).expect("read"),
+            r#"[{"id":1}]"#
+        );
+    }
+}
@@ -0,0 +1,651 @@
+//! `report_build`: assembles a structured research report and saves it to
+//! the artifact store.
+//!
+//! The model supplies sections, tables, citations and references to existing
+//! artifacts; the tool renders them into one consistently laid out markdown
+//! (or HTML) document. Sections cite sources inline with `[@id]`, which is
+//! rewritten to a numbered `[n]` that matches the References list. Inside a
+//! routine run the report is also recorded as a run artifact, so it can be
+//! downloaded, diffed against earlier runs and delivered like any other.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tandem_tools::Tool;
+use tandem_types::{EngineEvent, ToolResult, ToolSchema};
+
+use crate::{now_ms, AppState, RoutineRunArtifact};
+
+pub const REPORT_BUILD_TOOL: &str = "report_build";
+/// Text artifacts up to this size are embedded in the report body.
+const MAX_EMBED_BYTES: usize = 64 * 1024;
+const MAX_REPORT_BYTES: u64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportSpec {
+    pub title: String,
+    #[serde(default)]
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub sections: Vec<ReportSection>,
+    #[serde(default)]
+    pub citations: Vec<ReportCitation>,
+    #[serde(default)]
+    pub artifacts: Vec<ReportArtifactRef>,
+    #[serde(default)]
+    pub format: ReportFormat,
+    #[serde(default)]
+    pub filename: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportSection {
+    pub heading: String,
+    #[serde(default)]
+    pub body: String,
+    #[serde(default)]
+    pub tables: Vec<ReportTable>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportTable {
+    #[serde(default)]
+    pub caption: Option<String>,
+    pub columns: Vec<String>,
+    #[serde(default)]
+    pub rows: Vec<Vec<Value>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportCitation {
+    /// Key used as `[@id]` in section bodies; defaults to the 1-based index.
+    #[serde(default)]
+    pub id: Option<String>,
+    pub title: String,
+    #[serde(default)]
+    pub url: Option<String>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// An attachment (`artifact_id`) or workspace file (`path`) to include.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportArtifactRef {
+    #[serde(default)]
+    pub artifact_id: Option<String>,
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub caption: Option<String>,
+}
+
+/// A resolved artifact ready to be rendered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddedArtifact {
+    pub caption: String,
+    pub name: String,
+    pub mime: String,
+    pub size_bytes: u64,
+    /// Inlined content for small text artifacts.
+    pub text: Option<String>,
+    /// `artifact://<id>` or the workspace-relative path.
+    pub reference: String,
+}
+
+fn citation_keys(citations: &[ReportCitation]) -> HashMap<String, usize> {
+    citations
+        .iter()
+        .enumerate()
+        .map(|(idx, citation)| {
+            let key = citation
+                .id
+                .clone()
+                .filter(|id| !id.trim().is_empty())
+                .unwrap_or_else(|| (idx + 1).to_string());
+            (key, idx + 1)
+        })
+        .collect()
+}
+
+/// Rewrite `[@id]` markers to `[n]`, collecting ids with no citation.
+fn resolve_citations(
+    body: &str,
+    keys: &HashMap<String, usize>,
+    unknown: &mut Vec<String>,
+) -> String {
+    let marker = Regex::new(r"\[@([A-Za-z0-9_.:-]+)\]").expect("citation marker regex");
+    marker
+        .replace_all(body, |caps: &regex::Captures| match keys.get(&caps[1]) {
+            Some(n) => format!("[{n}]"),
+            None => {
+                unknown.push(caps[1].to_string());
+                caps[0].to_string()
+            }
+        })
+        .to_string()
+}
+
+fn table_cell(value: &Value) -> String {
+    let raw = match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    };
+    raw.replace('|', "\\|").replace(['\r', '\n'], " ")
+}
+
+fn render_table(table: &ReportTable, out: &mut String) -> Result<(), String> {
+    if table.columns.is_empty() {
+        return Err("tables need at least one column".to_string());
+    }
+    if let Some(caption) = table.caption.as_deref().filter(|c| !c.trim().is_empty()) {
+        out.push_str(&format!("*{}*\n\n", caption.trim()));
+    }
+    let header = table
+        .columns
+        .iter()
+        .map(|c| table_cell(&Value::String(c.clone())))
+        .collect::<Vec<_>>();
+    out.push_str(&format!("| {} |\n", header.join(" | ")));
+    out.push_str(&format!("|{}\n", " --- |".repeat(header.len())));
+    for (idx, row) in table.rows.iter().enumerate() {
+        if row.len() != header.len() {
+            return Err(format!(
+                "table row {} has {} cells, expected {}",
+                idx + 1,
+                row.len(),
+                header.len()
+            ));
+        }
+        let cells = row.iter().map(table_cell).collect::<Vec<_>>();
+        out.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+    out.push('\n');
+    Ok(())
+}
+
+fn fence_language(name: &str) -> &'static str {
+    tandem_runtime::language_hint(std::path::Path::new(name)).unwrap_or("")
+}
+
+/// Render `spec` as markdown. Errors describe what the caller must fix.
+pub fn render_report_markdown(
+    spec: &ReportSpec,
+    artifacts: &[EmbeddedArtifact],
+    generated_at: &str,
+) -> Result<String, String> {
+    let title = spec.title.trim();
+    if title.is_empty() {
+        return Err("title is required".to_string());
+    }
+    let keys = citation_keys(&spec.citations);
+    let mut unknown = Vec::new();
+    let mut out = format!("# {title}\n\n_Generated {generated_at}_\n\n");
+
+    if let Some(summary) = spec.summary.as_deref().filter(|s| !s.trim().is_empty()) {
+        out.push_str("## Summary\n\n");
+        out.push_str(&resolve_citations(summary.trim(), &keys, &mut unknown));
+        out.push_str("\n\n");
+    }
+    if spec.sections.len() > 1 {
+        out.push_str("## Contents\n\n");
+        for (idx, section) in spec.sections.iter().enumerate() {
+            out.push_str(&format!("{}. {}\n", idx + 1, section.heading.trim()));
+        }
+        out.push('\n');
+    }
+    for section in &spec.sections {
+        let heading = section.heading.trim();
+        if heading.is_empty() {
+            return Err("every section needs a heading".to_string());
+        }
+        out.push_str(&format!("## {heading}\n\n"));
+        let body = section.body.trim();
+        if !body.is_empty() {
+            out.push_str(&resolve_citations(body, &keys, &mut unknown));
+            out.push_str("\n\n");
+        }
+        for table in &section.tables {
+            render_table(table, &mut out).map_err(|err| format!("section `{heading}`: {err}"))?;
+        }
+    }
+    if !artifacts.is_empty() {
+        out.push_str("## Attachments\n\n");
+        for artifact in artifacts {
+            out.push_str(&format!(
+                "### {}\n\n`{}` ({}, {} bytes)\n\n",
+                artifact.caption, artifact.reference, artifact.mime, artifact.size_bytes
+            ));
+            if let Some(text) = &artifact.text {
+                out.push_str(&format!(
+                    "```{}\n{}\n```\n\n",
+                    fence_language(&artifact.name),
+                    text.trim_end()
+                ));
+            }
+        }
+    }
+    if !spec.citations.is_empty() {
+        out.push_str("## References\n\n");
+        for (idx, citation) in spec.citations.iter().enumerate() {
+            let mut line = format!("{}. ", idx + 1);
+            match citation.url.as_deref().filter(|u| !u.trim().is_empty()) {
+                Some(url) => line.push_str(&format!("[{}]({})", citation.title.trim(), url.trim())),
+                None => line.push_str(citation.title.trim()),
+            }
+            if let Some(note) = citation.note.as_deref().filter(|n| !n.trim().is_empty()) {
+                line.push_str(&format!(" — {}", note.trim()));
+            }
+            out.push_str(&line);
+            out.push('\n');
+        }
+    }
+    if !unknown.is_empty() {
+        unknown.sort();
+        unknown.dedup();
+        return Err(format!(
+            "unknown citation ids: {} (define them in `citations`)",
+            unknown.join(", ")
+        ));
+    }
+    Ok(format!("{}\n", out.trim_end()))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Standalone HTML page for rendered report markdown.
+pub fn report_markdown_to_html(title: &str, markdown: &str) -> String {
+    use pulldown_cmark::{html, Options, Parser};
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    let mut body = String::new();
+    html::push_html(&mut body, Parser::new_ext(markdown, options));
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>body{{font-family:system-ui,sans-serif;max-width:52rem;margin:2rem auto;padding:0 1rem;line-height:1.5}}table{{border-collapse:collapse}}th,td{{border:1px solid #ccc;padding:.3rem .6rem}}pre{{background:#f5f5f5;padding:.75rem;overflow-x:auto}}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(title.trim()),
+        body
+    )
+}
+
+fn report_filename(spec: &ReportSpec) -> String {
+    let ext = match spec.format {
+        ReportFormat::Markdown => "md",
+        ReportFormat::Html => "html",
+    };
+    let stem = spec
+        .filename
+        .as_deref()
+        .map(|name| {
+            name.trim()
+                .trim_end_matches(".md")
+                .trim_end_matches(".html")
+                .to_string()
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| spec.title.clone());
+    let slug = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect::<String>();
+    let slug = slug
+        .split('-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    let slug = if slug.is_empty() {
+        "report".to_string()
+    } else {
+        slug.chars().take(80).collect()
+    };
+    format!("{slug}.{ext}")
+}
+
+pub struct ReportBuildTool {
+    state: AppState,
+}
+
+impl ReportBuildTool {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    async fn resolve_artifact(
+        &self,
+        reference: &ReportArtifactRef,
+        workspace_root: &std::path::Path,
+    ) -> anyhow::Result<EmbeddedArtifact> {
+        if let Some(artifact_id) = reference.artifact_id.as_deref().map(str::trim) {
+            let store = self.state.storage.attachments();
+            let record = store
+                .get(artifact_id)
+                .await
+                .ok_or_else(|| anyhow::anyhow!("artifact `{artifact_id}` not found"))?;
+            let text = if record.mime.starts_with("text/")
+                && record.size_bytes as usize <= MAX_EMBED_BYTES
+            {
+                match store.blob_path(artifact_id) {
+                    Some(path) => tokio::fs::read_to_string(path).await.ok(),
+                    None => None,
+                }
+            } else {
+                None
+            };
+            return Ok(EmbeddedArtifact {
+                caption: reference
+                    .caption
+                    .clone()
+                    .unwrap_or_else(|| record.filename.clone()),
+                name: record.filename,
+                mime: record.mime,
+                size_bytes: record.size_bytes,
+                text,
+                reference: format!("artifact://{artifact_id}"),
+            });
+        }
+        let path = reference
+            .path
+            .as_deref()
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("each artifact needs `artifact_id` or `path`"))?;
+        let file = crate::routine_diff::artifact_file_path(path, workspace_root)
+            .filter(|file| file.is_file())
+            .ok_or_else(|| anyhow::anyhow!("`{path}` is not a file inside the workspace"))?;
+        let size_bytes = tokio::fs::metadata(&file).await?.len();
+        let name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| path.to_string());
+        let text = if size_bytes as usize <= MAX_EMBED_BYTES {
+            tokio::fs::read(&file)
+                .await
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+        } else {
+            None
+        };
+        Ok(EmbeddedArtifact {
+            caption: reference.caption.clone().unwrap_or_else(|| name.clone()),
+            mime: tandem_channels::attachments::content_type_for(&name).to_string(),
+            name,
+            size_bytes,
+            text,
+            reference: path.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl Tool for ReportBuildTool {
+    fn schema(&self) -> ToolSchema {
+        ToolSchema {
+            name: REPORT_BUILD_TOOL.to_string(),
+            description: "Assemble a research report from sections, tables, citations and existing artifacts, and save it as a markdown or HTML artifact. Cite sources in section text with [@id]; they are numbered to match the References list. Use this for final deliverables instead of pasting a long report into chat.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "title": { "type": "string" },
+                    "summary": { "type": "string", "description": "Executive summary (markdown)" },
+                    "sections": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "heading": { "type": "string" },
+                                "body": { "type": "string", "description": "Markdown; cite with [@id]" },
+                                "tables": {
+                                    "type": "array",
+                                    "items": {
+                                        "type": "object",
+                                        "properties": {
+                                            "caption": { "type": "string" },
+                                            "columns": { "type": "array", "items": { "type": "string" } },
+                                            "rows": { "type": "array", "items": { "type": "array" } }
+                                        },
+                                        "required": ["columns"]
+                                    }
+                                }
+                            },
+                            "required": ["heading"]
+                        }
+                    },
+                    "citations": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "id": { "type": "string" },
+                                "title": { "type": "string" },
+                                "url": { "type": "string" },
+                                "note": { "type": "string" }
+                            },
+                            "required": ["title"]
+                        }
+                    },
+                    "artifacts": {
+                        "type": "array",
+                        "description": "Existing attachments (artifact_id) or workspace files (path) to include",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "artifact_id": { "type": "string" },
+                                "path": { "type": "string" },
+                                "caption": { "type": "string" }
+                            }
+                        }
+                    },
+                    "format": { "type": "string", "enum": ["markdown", "html"] },
+                    "filename": { "type": "string" }
+                },
+                "required": ["title", "sections"]
+            }),
+        }
+    }
+
+    async fn execute(&self, args: Value) -> anyhow::Result<ToolResult> {
+        let session_id = args
+            .get("__session_id")
+            .and_then(|v| v.as_str())
+            .filter(|v| !v.trim().is_empty())
+            .map(ToString::to_string)
+            .ok_or_else(|| anyhow::anyhow!("{REPORT_BUILD_TOOL} must run inside a session"))?;
+        let spec: ReportSpec = serde_json::from_value(args.clone())
+            .map_err(|err| anyhow::anyhow!("invalid report: {err}"))?;
+        let workspace_root = match args.get("__workspace_root").and_then(|v| v.as_str()) {
+            Some(root) if !root.trim().is_empty() => PathBuf::from(root),
+            _ => PathBuf::from(self.state.workspace_index.snapshot().await.root),
+        };
+        let mut embedded = Vec::with_capacity(spec.artifacts.len());
+        for reference in &spec.artifacts {
+            embedded.push(self.resolve_artifact(reference, &workspace_root).await?);
+        }
+        let generated_at = chrono::Utc::now().format("%Y-%m-%d %H:%M UTC").to_string();
+        let markdown = render_report_markdown(&spec, &embedded, &generated_at)
+            .map_err(|err| anyhow::anyhow!(err))?;
+        let (content, mime) = match spec.format {
+            ReportFormat::Markdown => (markdown, "text/markdown"),
+            ReportFormat::Html => (report_markdown_to_html(&spec.title, &markdown), "text/html"),
+        };
+        let filename = report_filename(&spec);
+
+        let store = self.state.storage.attachments();
+        let mut writer = store
+            .begin(&session_id, &filename, mime, MAX_REPORT_BYTES)
+            .await
+            .map_err(|err| anyhow::anyhow!("{err}"))?;
+        if let Err(err) = writer.write_chunk(content.as_bytes()).await {
+            writer.abort().await;
+            anyhow::bail!("{err}");
+        }
+        let record = writer
+            .finish()
+            .await
+            .map_err(|err| anyhow::anyhow!("{err}"))?;
+
+        let mut metadata = json!({
+            "artifactID": record.artifact_id,
+            "filename": record.filename,
+            "mime": record.mime,
+            "sizeBytes": record.size_bytes,
+            "sections": spec.sections.len(),
+            "citations": spec.citations.len(),
+            "embedded": embedded.len(),
+        });
+        if let Some(policy) = self.state.routine_session_policy(&session_id).await {
+            let artifact = RoutineRunArtifact {
+                artifact_id: format!("artifact-{}", uuid::Uuid::new_v4()),
+                uri: format!("artifact://{}", record.artifact_id),
+                kind: "report".to_string(),
+                label: Some(spec.title.trim().to_string()),
+                created_at_ms: now_ms(),
+                metadata: Some(json!({
+                    "source": format!("tool.{REPORT_BUILD_TOOL}"),
+                    "attachmentID": record.artifact_id,
+                    "filename": record.filename,
+                    "mime": record.mime,
+                })),
+            };
+            self.state
+                .store_routine_artifact_text(&policy.run_id, &artifact.artifact_id, &content)
+                .await;
+            if let Some(run) = self
+                .state
+                .append_routine_run_artifact(&policy.run_id, artifact.clone())
+                .await?
+            {
+                self.state.event_bus.publish(EngineEvent::new(
+                    "routine.run.artifact_added",
+                    json!({
+                        "runID": run.run_id,
+                        "routineID": run.routine_id,
+                        "artifact": artifact,
+                    }),
+                ));
+                metadata["routineArtifactID"] = json!(artifact.artifact_id);
+            }
+        }
+        Ok(ToolResult {
+            output: format!(
+                "Saved report `{}` ({} bytes) as artifact {}.",
+                record.filename, record.size_bytes, record.artifact_id
+            ),
+            metadata,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(value: Value) -> ReportSpec {
+        serde_json::from_value(value).expect("spec")
+    }
+
+    #[test]
+    fn renders_sections_tables_and_numbered_citations() {
+        let spec = spec(json!({
+            "title": "Vector DB survey",
+            "summary": "pgvector is enough for us [@pg].",
+            "sections": [
+                {
+                    "heading": "Options",
+                    "body": "Qdrant [@qd] and pgvector [@pg] were compared.",
+                    "tables": [{
+                        "caption": "Latency",
+                        "columns": ["Engine", "p95 ms"],
+                        "rows": [["pgvector", 12], ["qdrant|cloud", 9.5]]
+                    }]
+                },
+                { "heading": "Recommendation", "body": "Use pgvector." }
+            ],
+            "citations": [
+                { "id": "qd", "title": "Qdrant docs", "url": "https://qdrant.tech/documentation/" },
+                { "id": "pg", "title": "pgvector README", "note": "v0.7" }
+            ]
+        }));
+        let artifacts = vec![EmbeddedArtifact {
+            caption: "Benchmark script".to_string(),
+            name: "bench.py".to_string(),
+            mime: "text/x-python".to_string(),
+            size_bytes: 12,
+            text: Some("print('hi')\n".to_string()),
+            reference: "scripts/bench.py".to_string(),
+        }];
+        let md = render_report_markdown(&spec, &artifacts, "2026-01-01 00:00 UTC").expect("md");
+        assert!(md.starts_with("# Vector DB survey\n"));
+        assert!(md.contains("pgvector is enough for us [2]."));
+        assert!(md.contains("Qdrant [1] and pgvector [2]"));
+        assert!(md.contains("1. Options\n2. Recommendation"));
+        assert!(md.contains("| Engine | p95 ms |\n| --- | --- |\n| pgvector | 12 |"));
+        assert!(md.contains("| qdrant\\|cloud | 9.5 |"));
+        assert!(md.contains("```python\nprint('hi')\n```"));
+        assert!(md.contains("1. [Qdrant docs](https://qdrant.tech/documentation/)"));
+        assert!(md.contains("2. pgvector README — v0.7"));
+
+        let html = report_markdown_to_html(&spec.title, &md);
+        assert!(html.contains("<title>Vector DB survey</title>"));
+        assert!(html.contains("<table>"));
+    }
+
+    #[test]
+    fn rejects_unknown_citations_and_ragged_tables() {
+        let err = render_report_markdown(
+            &spec(json!({
+                "title": "T",
+                "sections": [{ "heading": "A", "body": "see [@missing]" }]
+            })),
+            &[],
+            "now",
+        )
+        .expect_err("unknown citation");
+        assert!(err.contains("missing"));
+
+        let err = render_report_markdown(
+            &spec(json!({
+                "title": "T",
+                "sections": [{
+                    "heading": "A",
+                    "tables": [{ "columns": ["a", "b"], "rows": [["only one"]] }]
+                }]
+            })),
+            &[],
+            "now",
+        )
+        .expect_err("ragged table");
+        assert!(err.contains("row 1 has 1 cells"));
+    }
+
+    #[test]
+    fn filenames_are_slugged() {
+        let mut report = spec(json!({ "title": "Q3: Market / Pricing Review", "sections": [] }));
+        assert_eq!(report_filename(&report), "q3-market-pricing-review.md");
+        report.format = ReportFormat::Html;
+        report.filename = Some("final.md".to_string());
+        assert_eq!(report_filename(&report), "final.html");
+    }
+}
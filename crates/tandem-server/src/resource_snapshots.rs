@@ -0,0 +1,373 @@
+//! Named, immutable snapshots of a shared-resource namespace.
+//!
+//! A snapshot copies every resource under a key prefix (for example
+//! `mission/launch/`) so an agent team can checkpoint a mission board before a
+//! risky phase, diff it against the live state later, and restore it.
+//! Snapshots are persisted to `shared_resource_snapshots.json`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::RwLock;
+
+use crate::state_file::JsonStateFile;
+use crate::{SharedResourceOp, SharedResourceRecord};
+
+pub const MAX_SNAPSHOT_LABEL_CHARS: usize = 120;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceSnapshot {
+    pub snapshot_id: String,
+    pub prefix: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub created_by: String,
+    pub created_at_ms: u64,
+    pub records: Vec<SharedResourceRecord>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct ResourceSnapshotSummary {
+    pub snapshot_id: String,
+    pub prefix: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub created_by: String,
+    pub created_at_ms: u64,
+    pub resource_count: usize,
+}
+
+impl ResourceSnapshot {
+    pub fn summary(&self) -> ResourceSnapshotSummary {
+        ResourceSnapshotSummary {
+            snapshot_id: self.snapshot_id.clone(),
+            prefix: self.prefix.clone(),
+            label: self.label.clone(),
+            created_by: self.created_by.clone(),
+            created_at_ms: self.created_at_ms,
+            resource_count: self.records.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceDiffKind {
+    /// Present now, absent from the snapshot.
+    Added,
+    /// In the snapshot, deleted since.
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ResourceDiffEntry {
+    pub key: String,
+    pub kind: ResourceDiffKind,
+    pub snapshot_rev: Option<u64>,
+    pub current_rev: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snapshot_value: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current_value: Option<Value>,
+}
+
+/// Whether `key` lives under `prefix` on whole path segments, so that
+/// `mission/x` covers `mission/x` and `mission/x/a` but not `mission/x2/a`.
+pub fn key_is_under(key: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    if prefix.is_empty() {
+        return true;
+    }
+    key == prefix
+        || key
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Changes from `snapshot` to the `current` records under the same prefix,
+/// ordered by key. Revision bumps that left the value unchanged are ignored.
+pub fn diff_snapshot(
+    snapshot: &ResourceSnapshot,
+    current: &[SharedResourceRecord],
+) -> Vec<ResourceDiffEntry> {
+    let before = snapshot
+        .records
+        .iter()
+        .map(|record| (record.key.as_str(), record))
+        .collect::<BTreeMap<_, _>>();
+    let after = current
+        .iter()
+        .map(|record| (record.key.as_str(), record))
+        .collect::<BTreeMap<_, _>>();
+    let keys = before
+        .keys()
+        .chain(after.keys())
+        .copied()
+        .collect::<BTreeSet<_>>();
+    keys.into_iter()
+        .filter_map(|key| {
+            let old = before.get(key);
+            let new = after.get(key);
+            let kind = match (old, new) {
+                (None, Some(_)) => ResourceDiffKind::Added,
+                (Some(_), None) => ResourceDiffKind::Removed,
+                (Some(old), Some(new)) if old.value != new.value => ResourceDiffKind::Changed,
+                _ => return None,
+            };
+            Some(ResourceDiffEntry {
+                key: key.to_string(),
+                kind,
+                snapshot_rev: old.map(|r| r.rev),
+                current_rev: new.map(|r| r.rev),
+                snapshot_value: old.map(|r| r.value.clone()),
+                current_value: new.map(|r| r.value.clone()),
+            })
+        })
+        .collect()
+}
+
+/// Batch operations that bring the namespace back to the snapshot. Each op
+/// is guarded by the current revision so concurrent writers surface as a
+/// conflict instead of being overwritten.
+pub fn restore_ops(
+    snapshot: &ResourceSnapshot,
+    diff: &[ResourceDiffEntry],
+) -> Vec<SharedResourceOp> {
+    diff.iter()
+        .map(|entry| match entry.kind {
+            ResourceDiffKind::Added => SharedResourceOp::Delete {
+                key: entry.key.clone(),
+                if_match_rev: entry.current_rev,
+            },
+            ResourceDiffKind::Removed | ResourceDiffKind::Changed => {
+                let ttl_ms = snapshot
+                    .records
+                    .iter()
+                    .find(|record| record.key == entry.key)
+                    .and_then(|record| record.ttl_ms);
+                SharedResourceOp::Put {
+                    key: entry.key.clone(),
+                    value: entry.snapshot_value.clone().unwrap_or(Value::Null),
+                    if_match_rev: entry.current_rev,
+                    ttl_ms,
+                }
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone)]
+pub struct ResourceSnapshotStore {
+    file: JsonStateFile,
+    snapshots: Arc<RwLock<Vec<ResourceSnapshot>>>,
+}
+
+impl ResourceSnapshotStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            file: JsonStateFile::new(path),
+            snapshots: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.file.set_path(path);
+    }
+
+    /// A file that does not parse is an error, and is then never
+    /// overwritten; see [`JsonStateFile`].
+    pub async fn load(&self) -> anyhow::Result<()> {
+        if let Some(parsed) = self.file.load::<Vec<ResourceSnapshot>>().await? {
+            *self.snapshots.write().await = parsed;
+        }
+        Ok(())
+    }
+
+    pub async fn persist(&self) -> anyhow::Result<()> {
+        let guard = self.snapshots.read().await;
+        self.file.save(&*guard).await
+    }
+
+    pub async fn find_label(&self, prefix: &str, label: &str) -> Option<ResourceSnapshotSummary> {
+        self.snapshots
+            .read()
+            .await
+            .iter()
+            .find(|s| s.prefix == prefix && s.label.as_deref() == Some(label))
+            .map(ResourceSnapshot::summary)
+    }
+
+    pub async fn create(
+        &self,
+        prefix: String,
+        label: Option<String>,
+        created_by: String,
+        mut records: Vec<SharedResourceRecord>,
+        now_ms: u64,
+    ) -> anyhow::Result<ResourceSnapshot> {
+        records.sort_by(|a, b| a.key.cmp(&b.key));
+        let snapshot = ResourceSnapshot {
+            snapshot_id: format!("snap-{}", uuid::Uuid::new_v4()),
+            prefix,
+            label,
+            created_by,
+            created_at_ms: now_ms,
+            records,
+        };
+        self.snapshots.write().await.push(snapshot.clone());
+        if let Err(err) = self.persist().await {
+            self.snapshots
+                .write()
+                .await
+                .retain(|s| s.snapshot_id != snapshot.snapshot_id);
+            return Err(err);
+        }
+        Ok(snapshot)
+    }
+
+    pub async fn get(&self, snapshot_id: &str) -> Option<ResourceSnapshot> {
+        self.snapshots
+            .read()
+            .await
+            .iter()
+            .find(|s| s.snapshot_id == snapshot_id)
+            .cloned()
+    }
+
+    /// Summaries of snapshots whose prefix starts with `prefix`, newest first.
+    pub async fn list(&self, prefix: Option<&str>) -> Vec<ResourceSnapshotSummary> {
+        let mut rows = self
+            .snapshots
+            .read()
+            .await
+            .iter()
+            .filter(|s| prefix.is_none_or(|p| key_is_under(&s.prefix, p)))
+            .map(ResourceSnapshot::summary)
+            .collect::<Vec<_>>();
+        rows.sort_by_key(|row| std::cmp::Reverse(row.created_at_ms));
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(key: &str, value: Value, rev: u64) -> SharedResourceRecord {
+        SharedResourceRecord {
+            key: key.to_string(),
+            value,
+            rev,
+            updated_at_ms: 0,
+            updated_by: "test".to_string(),
+            ttl_ms: None,
+        }
+    }
+
+    #[test]
+    fn prefixes_match_whole_key_segments() {
+        assert!(key_is_under("mission/x", "mission/x"));
+        assert!(key_is_under("mission/x/a", "mission/x"));
+        assert!(key_is_under("mission/x/a", "mission/x/"));
+        assert!(!key_is_under("mission/x2/a", "mission/x"));
+        assert!(!key_is_under("mission/x2", "mission/x/"));
+        assert!(key_is_under("anything", ""));
+    }
+
+    #[test]
+    fn diff_and_restore_ops_cover_added_removed_and_changed_keys() {
+        let snapshot = ResourceSnapshot {
+            snapshot_id: "snap-1".to_string(),
+            prefix: "mission/x/".to_string(),
+            label: Some("before-deploy".to_string()),
+            created_by: "lead".to_string(),
+            created_at_ms: 1,
+            records: vec![
+                record("mission/x/a", json!({"lane": "todo"}), 1),
+                record("mission/x/b", json!(1), 1),
+                record("mission/x/c", json!("same"), 1),
+            ],
+        };
+        let current = vec![
+            record("mission/x/a", json!({"lane": "done"}), 2),
+            record("mission/x/c", json!("same"), 3),
+            record("mission/x/d", json!(true), 1),
+        ];
+        let diff = diff_snapshot(&snapshot, &current);
+        let kinds = diff
+            .iter()
+            .map(|entry| (entry.key.as_str(), entry.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                ("mission/x/a", ResourceDiffKind::Changed),
+                ("mission/x/b", ResourceDiffKind::Removed),
+                ("mission/x/d", ResourceDiffKind::Added),
+            ]
+        );
+
+        let ops = restore_ops(&snapshot, &diff);
+        assert!(matches!(
+            &ops[0],
+            SharedResourceOp::Put { key, if_match_rev: Some(2), .. } if key == "mission/x/a"
+        ));
+        assert!(matches!(
+            &ops[1],
+            SharedResourceOp::Put {
+                if_match_rev: None,
+                ..
+            }
+        ));
+        assert!(matches!(
+            &ops[2],
+            SharedResourceOp::Delete {
+                if_match_rev: Some(1),
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn corrupt_snapshot_file_is_not_replaced() {
+        let path = std::env::temp_dir()
+            .join(format!("tandem-snapshots-{}", uuid::Uuid::new_v4()))
+            .join("shared_resource_snapshots.json");
+        let store = ResourceSnapshotStore::new(path.clone());
+        store
+            .create(
+                "mission/x".to_string(),
+                None,
+                "test".to_string(),
+                vec![record("mission/x/a", json!(1), 1)],
+                1,
+            )
+            .await
+            .expect("create");
+
+        std::fs::write(&path, "[{\"snapshot_id\":").expect("truncate");
+        let reloaded = ResourceSnapshotStore::new(path.clone());
+        assert!(reloaded.load().await.is_err());
+        assert!(reloaded
+            .create(
+                "mission/x".to_string(),
+                None,
+                "test".to_string(),
+                Vec::new(),
+                2
+            )
+            .await
+            .is_err());
+        assert!(reloaded.list(None).await.is_empty());
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("read"),
+            "[{\"snapshot_id\":"
+        );
+        let _ = std::fs::remove_dir_all(path.parent().expect("dir"));
+    }
+}
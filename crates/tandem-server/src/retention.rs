@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tandem_types::EngineEvent;
+
+use crate::{AppState, RoutineRunStatus};
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// `retention` config block. Retention is opt-in: every rule defaults to zero,
+/// which disables it, so nothing is deleted until a rule is configured.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    #[serde(default)]
+    pub routine_runs_days: u32,
+    #[serde(default)]
+    pub routine_history_days: u32,
+    #[serde(default)]
+    pub audit_log_max_rows: usize,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            routine_runs_days: 0,
+            routine_history_days: 0,
+            audit_log_max_rows: 0,
+            interval_secs: default_interval_secs(),
+        }
+    }
+}
+
+fn default_interval_secs() -> u64 {
+    6 * 60 * 60
+}
+
+impl RetentionPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.routine_runs_days > 0 || self.routine_history_days > 0 || self.audit_log_max_rows > 0
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PruneReport {
+    pub dry_run: bool,
+    pub pruned_at_ms: u64,
+    pub routine_runs: usize,
+    pub routine_history: usize,
+    pub audit_log_rows: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub routine_run_ids: Vec<String>,
+}
+
+impl PruneReport {
+    pub fn total(&self) -> usize {
+        self.routine_runs + self.routine_history + self.audit_log_rows
+    }
+}
+
+fn cutoff_ms(now_ms: u64, days: u32) -> Option<u64> {
+    (days > 0).then(|| now_ms.saturating_sub(u64::from(days) * DAY_MS))
+}
+
+fn is_terminal(status: &RoutineRunStatus) -> bool {
+    matches!(
+        status,
+        RoutineRunStatus::BlockedPolicy
+            | RoutineRunStatus::Denied
+            | RoutineRunStatus::Completed
+            | RoutineRunStatus::Failed
+            | RoutineRunStatus::Cancelled
+    )
+}
+
+impl AppState {
+    /// Effective retention policy from the `retention` config block.
+    pub async fn retention_policy(&self) -> RetentionPolicy {
+        self.config_section("retention").await
+    }
+
+    /// Apply `policy` to routine runs, routine history, and the memory audit
+    /// log. Only finished routine runs are eligible. With `dry_run` nothing is
+    /// removed and no event is published.
+    pub async fn prune_state(
+        &self,
+        policy: &RetentionPolicy,
+        now_ms: u64,
+        dry_run: bool,
+    ) -> anyhow::Result<PruneReport> {
+        let mut report = PruneReport {
+            dry_run,
+            pruned_at_ms: now_ms,
+            ..PruneReport::default()
+        };
+
+        if let Some(cutoff) = cutoff_ms(now_ms, policy.routine_runs_days) {
+            let mut runs = self.routine_runs.write().await;
+            let mut expired = runs
+                .values()
+                .filter(|run| is_terminal(&run.status) && run.updated_at_ms < cutoff)
+                .map(|run| run.run_id.clone())
+                .collect::<Vec<_>>();
+            expired.sort();
+            if !dry_run {
+                for run_id in &expired {
+                    runs.remove(run_id);
+                }
+            }
+            report.routine_runs = expired.len();
+            report.routine_run_ids = expired;
+        }
+
+        if let Some(cutoff) = cutoff_ms(now_ms, policy.routine_history_days) {
+            let mut history = self.routine_history.write().await;
+            for events in history.values_mut() {
+                let before = events.len();
+                let kept = events
+                    .iter()
+                    .filter(|event| event.fired_at_ms >= cutoff)
+                    .count();
+                report.routine_history += before - kept;
+                if !dry_run {
+                    events.retain(|event| event.fired_at_ms >= cutoff);
+                }
+            }
+            if !dry_run {
+                history.retain(|_, events| !events.is_empty());
+            }
+        }
+
+        if policy.audit_log_max_rows > 0 {
+            let mut audit = self.memory_audit_log.write().await;
+            let excess = audit.len().saturating_sub(policy.audit_log_max_rows);
+            report.audit_log_rows = excess;
+            if !dry_run && excess > 0 {
+                audit.drain(..excess);
+            }
+        }
+
+        if dry_run || report.total() == 0 {
+            return Ok(report);
+        }
+        if report.routine_runs > 0 {
+            self.persist_checked(crate::persistence::PersistTarget::RoutineRuns)
+                .await?;
+            for run_id in &report.routine_run_ids {
+                self.remove_routine_artifact_snapshots(run_id).await;
+            }
+        }
+        if report.routine_history > 0 {
+            self.persist_checked(crate::persistence::PersistTarget::RoutineHistory)
+                .await?;
+        }
+        self.event_bus.publish(EngineEvent::new(
+            "state.pruned",
+            json!({
+                "prunedAtMs": report.pruned_at_ms,
+                "routineRuns": report.routine_runs,
+                "routineHistory": report.routine_history,
+                "auditLogRows": report.audit_log_rows,
+                "policy": policy,
+            }),
+        ));
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_days_disables_cutoff() {
+        assert_eq!(cutoff_ms(10 * DAY_MS, 0), None);
+        assert_eq!(cutoff_ms(10 * DAY_MS, 3), Some(7 * DAY_MS));
+        assert_eq!(cutoff_ms(DAY_MS, 3), Some(0));
+    }
+
+    #[test]
+    fn retention_is_off_unless_configured() {
+        assert!(!RetentionPolicy::default().is_enabled());
+        let policy: RetentionPolicy =
+            serde_json::from_value(json!({ "routine_history_days": 90 })).expect("policy");
+        assert_eq!(policy.routine_runs_days, 0);
+        assert_eq!(policy.audit_log_max_rows, 0);
+        assert!(policy.is_enabled());
+    }
+}
@@ -0,0 +1,266 @@
+use serde_json::json;
+use tandem_types::EngineEvent;
+
+use crate::{
+    now_ms, AppState, RoutineApprovalPolicy, RoutineHistoryEvent, RoutineRunRecord,
+    RoutineRunStatus,
+};
+
+/// What a pending run should do given its routine's approval policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ApprovalTimeoutAction {
+    None,
+    Escalate,
+    AutoDeny,
+}
+
+pub(crate) fn approval_timeout_action(
+    run: &RoutineRunRecord,
+    policy: &RoutineApprovalPolicy,
+    now_ms: u64,
+) -> ApprovalTimeoutAction {
+    if run.status != RoutineRunStatus::PendingApproval {
+        return ApprovalTimeoutAction::None;
+    }
+    let waited = now_ms.saturating_sub(run.created_at_ms);
+    if policy
+        .auto_deny_after_ms
+        .is_some_and(|after| after > 0 && waited >= after)
+    {
+        return ApprovalTimeoutAction::AutoDeny;
+    }
+    if run.approval_escalated_at_ms.is_none()
+        && !policy.escalation_approvers.is_empty()
+        && policy
+            .escalate_after_ms
+            .is_some_and(|after| after > 0 && waited >= after)
+    {
+        return ApprovalTimeoutAction::Escalate;
+    }
+    ApprovalTimeoutAction::None
+}
+
+/// Runs without designated approvers accept any operator decision.
+pub(crate) fn approver_allowed(run: &RoutineRunRecord, approver: Option<&str>) -> bool {
+    if run.approvers.is_empty() {
+        return true;
+    }
+    approver
+        .map(str::trim)
+        .is_some_and(|approver| run.approvers.iter().any(|a| a == approver))
+}
+
+impl AppState {
+    /// Ask the run's current approvers (via their linked channels) for a decision.
+    pub fn notify_routine_approvers(&self, run: &RoutineRunRecord, escalated: bool) {
+        if run.approvers.is_empty() {
+            return;
+        }
+        self.event_bus.publish(EngineEvent::new(
+            "routine.approval.notify",
+            json!({
+                "runID": run.run_id,
+                "routineID": run.routine_id,
+                "approvers": run.approvers,
+                "escalated": escalated,
+                "reason": run.approval_reason,
+            }),
+        ));
+    }
+
+    /// Record who decided a pending run and add the decision to routine history.
+    pub async fn record_routine_run_decision(
+        &self,
+        run_id: &str,
+        decided_by: Option<String>,
+        status: &str,
+        detail: String,
+    ) -> Option<RoutineRunRecord> {
+        let updated = {
+            let mut guard = self.routine_runs.write().await;
+            let row = guard.get_mut(run_id)?;
+            row.decided_by = decided_by;
+            row.clone()
+        };
+        let _ = self
+            .persist_checked(crate::persistence::PersistTarget::RoutineRuns)
+            .await;
+        self.append_routine_history(RoutineHistoryEvent {
+            routine_id: updated.routine_id.clone(),
+            trigger_type: updated.trigger_type.clone(),
+            run_count: updated.run_count,
+            fired_at_ms: now_ms(),
+            status: status.to_string(),
+            detail: Some(detail),
+        })
+        .await;
+        Some(updated)
+    }
+
+    /// Escalate or auto-deny pending runs whose approval window has elapsed.
+    pub async fn process_routine_approval_timeouts(&self, now_ms: u64) {
+        let pending = self
+            .routine_runs
+            .read()
+            .await
+            .values()
+            .filter(|run| run.status == RoutineRunStatus::PendingApproval)
+            .cloned()
+            .collect::<Vec<_>>();
+        if pending.is_empty() {
+            return;
+        }
+        for run in pending {
+            let Some(policy) = self
+                .get_routine(&run.routine_id)
+                .await
+                .and_then(|routine| routine.approval_policy)
+            else {
+                continue;
+            };
+            match approval_timeout_action(&run, &policy, now_ms) {
+                ApprovalTimeoutAction::None => {}
+                ApprovalTimeoutAction::Escalate => {
+                    self.escalate_routine_run(&run.run_id, &policy, now_ms)
+                        .await;
+                }
+                ApprovalTimeoutAction::AutoDeny => {
+                    self.auto_deny_routine_run(&run, &policy).await;
+                }
+            }
+        }
+    }
+
+    async fn escalate_routine_run(&self, run_id: &str, policy: &RoutineApprovalPolicy, now: u64) {
+        let updated = {
+            let mut guard = self.routine_runs.write().await;
+            let Some(row) = guard.get_mut(run_id) else {
+                return;
+            };
+            row.approval_escalated_at_ms = Some(now);
+            row.updated_at_ms = now;
+            for approver in &policy.escalation_approvers {
+                if !row.approvers.contains(approver) {
+                    row.approvers.push(approver.clone());
+                }
+            }
+            row.clone()
+        };
+        let _ = self
+            .persist_checked(crate::persistence::PersistTarget::RoutineRuns)
+            .await;
+        self.append_routine_history(RoutineHistoryEvent {
+            routine_id: updated.routine_id.clone(),
+            trigger_type: updated.trigger_type.clone(),
+            run_count: updated.run_count,
+            fired_at_ms: now,
+            status: "approval_escalated".to_string(),
+            detail: Some(format!(
+                "escalated to {}",
+                policy.escalation_approvers.join(", ")
+            )),
+        })
+        .await;
+        self.event_bus.publish(EngineEvent::new(
+            "routine.approval.escalated",
+            json!({
+                "runID": updated.run_id,
+                "routineID": updated.routine_id,
+                "approvers": updated.approvers,
+            }),
+        ));
+        self.notify_routine_approvers(&updated, true);
+    }
+
+    async fn auto_deny_routine_run(&self, run: &RoutineRunRecord, policy: &RoutineApprovalPolicy) {
+        let reason = format!(
+            "auto-denied: no approval within {}s",
+            policy.auto_deny_after_ms.unwrap_or_default() / 1000
+        );
+        let Ok(Some(updated)) = self
+            .update_routine_run_status(&run.run_id, RoutineRunStatus::Denied, Some(reason.clone()))
+            .await
+        else {
+            return;
+        };
+        let _ = self
+            .record_routine_run_decision(
+                &run.run_id,
+                Some("system".to_string()),
+                "auto_denied",
+                reason.clone(),
+            )
+            .await;
+        self.event_bus.publish(EngineEvent::new(
+            "routine.run.denied",
+            json!({
+                "runID": updated.run_id,
+                "routineID": updated.routine_id,
+                "reason": reason,
+                "auto": true,
+            }),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending_run(created_at_ms: u64) -> RoutineRunRecord {
+        serde_json::from_value(json!({
+            "run_id": "run-1",
+            "routine_id": "routine-1",
+            "trigger_type": "scheduled",
+            "run_count": 1,
+            "status": "pending_approval",
+            "created_at_ms": created_at_ms,
+            "updated_at_ms": created_at_ms,
+            "requires_approval": true,
+            "entrypoint": "mission.default",
+            "approvers": ["alice"],
+        }))
+        .expect("run")
+    }
+
+    fn policy() -> RoutineApprovalPolicy {
+        RoutineApprovalPolicy {
+            approvers: vec!["alice".to_string()],
+            escalation_approvers: vec!["bob".to_string()],
+            escalate_after_ms: Some(1_000),
+            auto_deny_after_ms: Some(5_000),
+        }
+    }
+
+    #[test]
+    fn escalates_then_auto_denies() {
+        let mut run = pending_run(0);
+        assert_eq!(
+            approval_timeout_action(&run, &policy(), 500),
+            ApprovalTimeoutAction::None
+        );
+        assert_eq!(
+            approval_timeout_action(&run, &policy(), 1_000),
+            ApprovalTimeoutAction::Escalate
+        );
+        run.approval_escalated_at_ms = Some(1_000);
+        assert_eq!(
+            approval_timeout_action(&run, &policy(), 2_000),
+            ApprovalTimeoutAction::None
+        );
+        assert_eq!(
+            approval_timeout_action(&run, &policy(), 5_000),
+            ApprovalTimeoutAction::AutoDeny
+        );
+    }
+
+    #[test]
+    fn only_designated_approvers_may_decide() {
+        let mut run = pending_run(0);
+        assert!(approver_allowed(&run, Some("alice")));
+        assert!(!approver_allowed(&run, Some("mallory")));
+        assert!(!approver_allowed(&run, None));
+        run.approvers.clear();
+        assert!(approver_allowed(&run, None));
+    }
+}
@@ -0,0 +1,314 @@
+//! Response cache for routines whose prompts repeat from run to run.
+//!
+//! A routine opts in with `response_cache` on its spec. Before the provider
+//! is called, the run's prompt is normalized (the run id line dropped,
+//! whitespace collapsed, case folded) and hashed together with the model; a
+//! fresh entry for the same routine answers the run without a provider call.
+//! With `similarity_threshold` set, prompts are also embedded locally and an
+//! earlier prompt at least that similar counts as a hit too.
+//!
+//! Entries live in `response_cache.db` in the state directory (see
+//! [`tandem_memory::ResponseCache`]). A cached run is marked with
+//! `cache_hit` on its record and `cacheHit` metadata on the answer.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tandem_memory::embeddings::EmbeddingService;
+use tandem_memory::response_cache::{ResponseCache, ScopedCacheEntry};
+use tandem_types::{EngineEvent, Message, MessagePart, MessageRole, ModelSpec};
+use tokio::sync::{Mutex, OnceCell};
+
+use crate::{now_ms, AppState, RoutineRunRecord};
+
+/// Entries older than this are evicted whatever a routine's TTL says.
+const MAX_TTL_MINUTES: u32 = 7 * 24 * 60;
+const MAX_ENTRIES: usize = 2_000;
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_ttl_ms() -> u64 {
+    24 * 60 * 60 * 1000
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RoutineResponseCachePolicy {
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// How long a cached answer stays fresh; capped at seven days.
+    #[serde(default = "default_ttl_ms")]
+    pub ttl_ms: u64,
+    /// Cosine similarity (0–1) at which a different prompt still counts as a
+    /// hit. Only exact normalized matches hit when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity_threshold: Option<f32>,
+}
+
+/// Where a cached run's answer came from.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunCacheHit {
+    pub key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_run_id: Option<String>,
+    pub cached_at_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f32>,
+}
+
+/// Prompt text with the parts that change on every run removed.
+pub fn normalize_prompt(prompt: &str) -> String {
+    prompt
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("Run ID:"))
+        .flat_map(str::split_whitespace)
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn model_label(model: Option<&ModelSpec>) -> String {
+    model
+        .map(|spec| format!("{}/{}", spec.provider_id, spec.model_id))
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// A run's prompt, keyed for lookup and later storage.
+pub struct CacheProbe {
+    routine_id: String,
+    model: String,
+    key: String,
+    prompt: String,
+    embedding: Option<Vec<f32>>,
+}
+
+#[derive(Clone)]
+pub struct RoutineResponseCache {
+    dir: PathBuf,
+    cache: Arc<OnceCell<Option<Arc<ResponseCache>>>>,
+    embedder: Arc<OnceCell<Option<Arc<Mutex<EmbeddingService>>>>>,
+}
+
+impl RoutineResponseCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            cache: Arc::new(OnceCell::new()),
+            embedder: Arc::new(OnceCell::new()),
+        }
+    }
+
+    pub fn set_dir(&mut self, dir: PathBuf) {
+        self.dir = dir;
+        self.cache = Arc::new(OnceCell::new());
+    }
+
+    async fn cache(&self) -> Option<Arc<ResponseCache>> {
+        self.cache
+            .get_or_init(|| async {
+                match ResponseCache::new(&self.dir, MAX_TTL_MINUTES, MAX_ENTRIES).await {
+                    Ok(cache) => Some(Arc::new(cache)),
+                    Err(error) => {
+                        tracing::warn!("routine response cache unavailable: {error}");
+                        None
+                    }
+                }
+            })
+            .await
+            .clone()
+    }
+
+    async fn embed(&self, text: &str) -> Option<Vec<f32>> {
+        let embedder = self
+            .embedder
+            .get_or_init(|| async {
+                tokio::task::spawn_blocking(EmbeddingService::new)
+                    .await
+                    .ok()
+                    .filter(EmbeddingService::is_available)
+                    .map(|service| Arc::new(Mutex::new(service)))
+            })
+            .await
+            .clone()?;
+        let embedder = embedder.lock().await;
+        embedder.embed(text).await.ok()
+    }
+
+    pub async fn probe(
+        &self,
+        routine_id: &str,
+        model: Option<&ModelSpec>,
+        prompt: &str,
+        policy: &RoutineResponseCachePolicy,
+    ) -> CacheProbe {
+        let model = model_label(model);
+        let prompt = normalize_prompt(prompt);
+        let embedding = match policy.similarity_threshold {
+            Some(_) => self.embed(&prompt).await,
+            None => None,
+        };
+        CacheProbe {
+            routine_id: routine_id.to_string(),
+            key: ResponseCache::cache_key(&model, Some(routine_id), &prompt),
+            model,
+            prompt,
+            embedding,
+        }
+    }
+
+    /// A fresh cached answer for the probe, if any.
+    pub async fn lookup(
+        &self,
+        probe: &CacheProbe,
+        policy: &RoutineResponseCachePolicy,
+    ) -> Option<(RunCacheHit, String)> {
+        let cache = self.cache().await?;
+        let query = probe.embedding.as_deref().zip(policy.similarity_threshold);
+        let max_age = chrono::Duration::milliseconds(policy.ttl_ms.min(i64::MAX as u64) as i64);
+        let hit = cache
+            .lookup_scoped(&probe.routine_id, &probe.model, &probe.key, max_age, query)
+            .await
+            .map_err(|error| tracing::warn!("routine response cache lookup failed: {error}"))
+            .ok()??;
+        Some((
+            RunCacheHit {
+                key: hit.key,
+                source_run_id: hit.source_id,
+                cached_at_ms: hit.created_at.timestamp_millis().max(0) as u64,
+                similarity: hit.similarity,
+            },
+            hit.response,
+        ))
+    }
+
+    pub async fn store(&self, probe: &CacheProbe, response: &str, run_id: &str) {
+        let Some(cache) = self.cache().await else {
+            return;
+        };
+        let entry = ScopedCacheEntry {
+            key: probe.key.clone(),
+            model: probe.model.clone(),
+            scope: probe.routine_id.clone(),
+            prompt: probe.prompt.clone(),
+            response: response.to_string(),
+            token_count: (response.len() / 4) as u32,
+            embedding: probe.embedding.clone(),
+            source_id: Some(run_id.to_string()),
+        };
+        if let Err(error) = cache.put_scoped(&entry).await {
+            tracing::warn!("routine response cache store failed: {error}");
+        }
+    }
+}
+
+impl AppState {
+    /// Complete a run's model turn with a cached answer: the prompt and the
+    /// answer are added to its session as if the provider had replied.
+    pub(crate) async fn answer_routine_run_from_cache(
+        &self,
+        run: &RoutineRunRecord,
+        session_id: &str,
+        prompt: &str,
+        hit: RunCacheHit,
+        response: String,
+    ) -> anyhow::Result<()> {
+        self.storage
+            .append_message(
+                session_id,
+                Message::new(
+                    MessageRole::User,
+                    vec![MessagePart::Text {
+                        text: prompt.to_string(),
+                    }],
+                ),
+            )
+            .await?;
+        let mut answer = Message::new(
+            MessageRole::Assistant,
+            vec![MessagePart::Text { text: response }],
+        );
+        answer.metadata = Some(json!({ "cacheHit": hit }));
+        self.storage.append_message(session_id, answer).await?;
+
+        {
+            let mut guard = self.routine_runs.write().await;
+            if let Some(row) = guard.get_mut(&run.run_id) {
+                row.cache_hit = Some(hit.clone());
+                row.updated_at_ms = now_ms();
+            }
+        }
+        self.event_bus.publish(EngineEvent::new(
+            "routine.run.cache_hit",
+            json!({
+                "runID": run.run_id,
+                "routineID": run.routine_id,
+                "sessionID": session_id,
+                "key": hit.key,
+                "sourceRunID": hit.source_run_id,
+                "cachedAtMs": hit.cached_at_ms,
+                "similarity": hit.similarity,
+            }),
+        ));
+        self.event_bus.publish(EngineEvent::new(
+            "session.updated",
+            json!({ "sessionID": session_id, "status": "idle" }),
+        ));
+        Ok(())
+    }
+}
+
+/// Text of the session's last assistant message, to cache after a run.
+pub(crate) fn last_assistant_text(messages: &[Message]) -> Option<String> {
+    let message = messages
+        .iter()
+        .rev()
+        .find(|message| matches!(message.role, MessageRole::Assistant))?;
+    let text = message
+        .parts
+        .iter()
+        .filter_map(|part| match part {
+            MessagePart::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<String>();
+    (!text.trim().is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn repeated_prompt_hits_until_stale() {
+        let dir =
+            std::env::temp_dir().join(format!("tandem-routine-cache-{}", uuid::Uuid::new_v4()));
+        let cache = RoutineResponseCache::new(dir);
+        let policy = RoutineResponseCachePolicy {
+            enabled: true,
+            ttl_ms: 60_000,
+            similarity_threshold: None,
+        };
+        let first = "Automation ID: daily\nRun ID: run-1\nSummarize   the inbox.";
+        let probe = cache.probe("daily", None, first, &policy).await;
+        assert!(cache.lookup(&probe, &policy).await.is_none());
+        cache.store(&probe, "Nothing new.", "run-1").await;
+
+        let second = "Automation ID: daily\nRun ID: run-2\nsummarize the inbox.";
+        let probe = cache.probe("daily", None, second, &policy).await;
+        let (hit, response) = cache.lookup(&probe, &policy).await.expect("hit");
+        assert_eq!(response, "Nothing new.");
+        assert_eq!(hit.source_run_id.as_deref(), Some("run-1"));
+        assert!(hit.similarity.is_none());
+
+        let other_routine = cache.probe("weekly", None, second, &policy).await;
+        assert!(cache.lookup(&other_routine, &policy).await.is_none());
+        let stale = RoutineResponseCachePolicy {
+            ttl_ms: 0,
+            ..policy
+        };
+        assert!(cache.lookup(&probe, &stale).await.is_none());
+    }
+}
@@ -0,0 +1,482 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tandem_types::EngineEvent;
+use tokio::fs;
+
+use crate::{now_ms, AppState, RoutineRunArtifact, RoutineRunRecord, RoutineRunStatus};
+
+/// Text artifacts larger than this are not snapshotted or compared.
+const MAX_TEXT_ARTIFACT_BYTES: u64 = 512 * 1024;
+/// Artifacts larger than this are not served for download.
+pub(crate) const MAX_SERVED_ARTIFACT_BYTES: u64 = 100 * 1024 * 1024;
+/// Upper bound on diff lines kept per artifact in a change summary.
+const MAX_DIFF_LINES: usize = 200;
+/// Above this many line pairs the diff falls back to counting lines only.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArtifactChangeKind {
+    Added,
+    Removed,
+    Modified,
+    Unchanged,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutineArtifactDiff {
+    pub key: String,
+    pub change: ArtifactChangeKind,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// Changed lines prefixed with `+`/`-`, truncated to a bounded length.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diff: Vec<String>,
+}
+
+/// Result of comparing a run's text artifacts with an earlier run of the
+/// same routine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutineArtifactChangeSummary {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_run_id: Option<String>,
+    pub compared_at_ms: u64,
+    pub changed: bool,
+    pub summary: String,
+    #[serde(default)]
+    pub artifacts: Vec<RoutineArtifactDiff>,
+    #[serde(default)]
+    pub delivery_suppressed: bool,
+}
+
+/// Line-level diff. Returns `(added, removed, changed_lines)`.
+fn diff_lines(old: &str, new: &str) -> (usize, usize, Vec<String>) {
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+    if old_lines.len().saturating_mul(new_lines.len()) > MAX_LCS_CELLS {
+        return count_only_diff(&old_lines, &new_lines);
+    }
+    let (n, m) = (old_lines.len(), new_lines.len());
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_lines[i] == new_lines[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let (mut added, mut removed) = (0, 0);
+    let mut out = Vec::new();
+    let mut push = |line: String| {
+        if out.len() < MAX_DIFF_LINES {
+            out.push(line);
+        }
+    };
+    while i < n || j < m {
+        if i < n && j < m && old_lines[i] == new_lines[j] {
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            push(format!("-{}", old_lines[i]));
+            removed += 1;
+            i += 1;
+        } else {
+            push(format!("+{}", new_lines[j]));
+            added += 1;
+            j += 1;
+        }
+    }
+    (added, removed, out)
+}
+
+fn count_only_diff(old_lines: &[&str], new_lines: &[&str]) -> (usize, usize, Vec<String>) {
+    let mut counts = HashMap::<&str, i64>::new();
+    for line in old_lines {
+        *counts.entry(line).or_default() += 1;
+    }
+    for line in new_lines {
+        *counts.entry(line).or_default() -= 1;
+    }
+    let removed = counts.values().filter(|v| **v > 0).sum::<i64>() as usize;
+    let added = counts.values().filter(|v| **v < 0).map(|v| -v).sum::<i64>() as usize;
+    (added, removed, Vec::new())
+}
+
+/// Compare artifact texts keyed by artifact key (URI or label).
+pub fn compare_artifact_texts(
+    previous: &BTreeMap<String, String>,
+    current: &BTreeMap<String, String>,
+) -> Vec<RoutineArtifactDiff> {
+    let mut out = Vec::new();
+    for (key, text) in current {
+        let diff = match previous.get(key) {
+            None => RoutineArtifactDiff {
+                key: key.clone(),
+                change: ArtifactChangeKind::Added,
+                lines_added: text.lines().count(),
+                lines_removed: 0,
+                diff: Vec::new(),
+            },
+            Some(old) if old == text => RoutineArtifactDiff {
+                key: key.clone(),
+                change: ArtifactChangeKind::Unchanged,
+                lines_added: 0,
+                lines_removed: 0,
+                diff: Vec::new(),
+            },
+            Some(old) => {
+                let (lines_added, lines_removed, diff) = diff_lines(old, text);
+                RoutineArtifactDiff {
+                    key: key.clone(),
+                    change: ArtifactChangeKind::Modified,
+                    lines_added,
+                    lines_removed,
+                    diff,
+                }
+            }
+        };
+        out.push(diff);
+    }
+    for (key, text) in previous {
+        if !current.contains_key(key) {
+            out.push(RoutineArtifactDiff {
+                key: key.clone(),
+                change: ArtifactChangeKind::Removed,
+                lines_added: 0,
+                lines_removed: text.lines().count(),
+                diff: Vec::new(),
+            });
+        }
+    }
+    out
+}
+
+fn summarize(diffs: &[RoutineArtifactDiff]) -> String {
+    let count = |kind: ArtifactChangeKind| diffs.iter().filter(|d| d.change == kind).count();
+    let mut parts = Vec::new();
+    for (kind, label) in [
+        (ArtifactChangeKind::Modified, "modified"),
+        (ArtifactChangeKind::Added, "added"),
+        (ArtifactChangeKind::Removed, "removed"),
+    ] {
+        let n = count(kind);
+        if n > 0 {
+            parts.push(format!("{n} {label}"));
+        }
+    }
+    if parts.is_empty() {
+        return "no changes".to_string();
+    }
+    let added = diffs.iter().map(|d| d.lines_added).sum::<usize>();
+    let removed = diffs.iter().map(|d| d.lines_removed).sum::<usize>();
+    format!("{} (+{added}/-{removed} lines)", parts.join(", "))
+}
+
+fn artifact_key(artifact: &RoutineRunArtifact) -> String {
+    artifact
+        .label
+        .as_ref()
+        .filter(|_| artifact.kind != "output_target")
+        .map(|label| format!("{}:{label}", artifact.kind))
+        .unwrap_or_else(|| artifact.uri.clone())
+}
+
+fn inline_artifact_text(artifact: &RoutineRunArtifact) -> Option<String> {
+    let metadata = artifact.metadata.as_ref()?;
+    metadata
+        .get("text")
+        .or_else(|| metadata.get("content"))
+        .and_then(|v| v.as_str())
+        .map(ToString::to_string)
+}
+
+/// Local file backing an artifact URI, confined to the workspace root.
+pub(crate) fn artifact_file_path(uri: &str, workspace_root: &Path) -> Option<PathBuf> {
+    let raw = uri.strip_prefix("file://").unwrap_or(uri);
+    if raw.is_empty() || raw.contains("://") {
+        return None;
+    }
+    let path = PathBuf::from(raw);
+    let path = if path.is_absolute() {
+        path
+    } else {
+        workspace_root.join(path)
+    };
+    let canonical = std::fs::canonicalize(&path).ok()?;
+    let root = std::fs::canonicalize(workspace_root).ok()?;
+    canonical.starts_with(root).then_some(canonical)
+}
+
+async fn read_text_file(path: &Path) -> Option<String> {
+    let meta = fs::metadata(path).await.ok()?;
+    if !meta.is_file() || meta.len() > MAX_TEXT_ARTIFACT_BYTES {
+        return None;
+    }
+    String::from_utf8(fs::read(path).await.ok()?).ok()
+}
+
+/// Downloadable content of an artifact.
+pub(crate) enum ArtifactContent {
+    Bytes(Vec<u8>),
+    TooLarge(u64),
+    Missing,
+}
+
+/// Whether the routine opted into skipping delivery of unchanged output.
+fn suppress_unchanged(run: &RoutineRunRecord) -> bool {
+    run.args
+        .get("suppress_unchanged")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false)
+}
+
+impl AppState {
+    fn routine_artifact_snapshot_dir(&self, run_id: &str) -> PathBuf {
+        self.routine_runs_path
+            .parent()
+            .map(|parent| parent.join("routine_artifacts"))
+            .unwrap_or_else(|| PathBuf::from(".tandem").join("routine_artifacts"))
+            .join(run_id)
+    }
+
+    pub(crate) async fn remove_routine_artifact_snapshots(&self, run_id: &str) {
+        let _ = fs::remove_dir_all(self.routine_artifact_snapshot_dir(run_id)).await;
+    }
+
+    /// Record `text` as the content of a run artifact that has no workspace
+    /// file behind it (e.g. a generated report kept in the artifact store).
+    pub(crate) async fn store_routine_artifact_text(
+        &self,
+        run_id: &str,
+        artifact_id: &str,
+        text: &str,
+    ) {
+        let dir = self.routine_artifact_snapshot_dir(run_id);
+        if fs::create_dir_all(&dir).await.is_ok() {
+            let _ = fs::write(dir.join(format!("{artifact_id}.txt")), text).await;
+        }
+    }
+
+    async fn live_artifact_text(&self, artifact: &RoutineRunArtifact) -> Option<String> {
+        if let Some(text) = inline_artifact_text(artifact) {
+            return Some(text);
+        }
+        let root = PathBuf::from(self.workspace_index.snapshot().await.root);
+        read_text_file(&artifact_file_path(&artifact.uri, &root)?).await
+    }
+
+    /// Bytes behind an artifact: its completion snapshot, inline text, or the
+    /// workspace file it points at.
+    pub(crate) async fn routine_run_artifact_content(
+        &self,
+        run_id: &str,
+        artifact: &RoutineRunArtifact,
+    ) -> ArtifactContent {
+        let snapshot = self
+            .routine_artifact_snapshot_dir(run_id)
+            .join(format!("{}.txt", artifact.artifact_id));
+        if let Ok(bytes) = fs::read(&snapshot).await {
+            return ArtifactContent::Bytes(bytes);
+        }
+        if let Some(text) = inline_artifact_text(artifact) {
+            return ArtifactContent::Bytes(text.into_bytes());
+        }
+        let root = PathBuf::from(self.workspace_index.snapshot().await.root);
+        let Some(path) = artifact_file_path(&artifact.uri, &root) else {
+            return ArtifactContent::Missing;
+        };
+        match fs::metadata(&path).await {
+            Ok(meta) if meta.is_file() && meta.len() > MAX_SERVED_ARTIFACT_BYTES => {
+                ArtifactContent::TooLarge(meta.len())
+            }
+            Ok(meta) if meta.is_file() => match fs::read(&path).await {
+                Ok(bytes) => ArtifactContent::Bytes(bytes),
+                Err(_) => ArtifactContent::Missing,
+            },
+            _ => ArtifactContent::Missing,
+        }
+    }
+
+    /// Text content of a run's artifacts, preferring the snapshot taken when
+    /// the run completed so later overwrites don't affect comparisons.
+    async fn routine_run_artifact_texts(&self, run: &RoutineRunRecord) -> BTreeMap<String, String> {
+        let dir = self.routine_artifact_snapshot_dir(&run.run_id);
+        let mut out = BTreeMap::new();
+        for artifact in &run.artifacts {
+            let snapshot = dir.join(format!("{}.txt", artifact.artifact_id));
+            let text = match read_text_file(&snapshot).await {
+                Some(text) => Some(text),
+                None => self.live_artifact_text(artifact).await,
+            };
+            if let Some(text) = text {
+                out.insert(artifact_key(artifact), text);
+            }
+        }
+        out
+    }
+
+    async fn snapshot_routine_run_artifacts(&self, run: &RoutineRunRecord) {
+        let dir = self.routine_artifact_snapshot_dir(&run.run_id);
+        for artifact in &run.artifacts {
+            let Some(text) = self.live_artifact_text(artifact).await else {
+                continue;
+            };
+            if fs::create_dir_all(&dir).await.is_err() {
+                return;
+            }
+            let _ = fs::write(dir.join(format!("{}.txt", artifact.artifact_id)), text).await;
+        }
+    }
+
+    /// Most recent completed run of the same routine created before `run`.
+    pub async fn previous_completed_routine_run(
+        &self,
+        run: &RoutineRunRecord,
+    ) -> Option<RoutineRunRecord> {
+        self.routine_runs
+            .read()
+            .await
+            .values()
+            .filter(|row| {
+                row.routine_id == run.routine_id
+                    && row.run_id != run.run_id
+                    && row.status == RoutineRunStatus::Completed
+                    && row.created_at_ms <= run.created_at_ms
+            })
+            .max_by_key(|row| (row.created_at_ms, row.run_id.clone()))
+            .cloned()
+    }
+
+    /// Diff `run`'s text artifacts against `previous` (or report everything
+    /// as added when there is no earlier run).
+    pub async fn compare_routine_runs(
+        &self,
+        run: &RoutineRunRecord,
+        previous: Option<&RoutineRunRecord>,
+    ) -> RoutineArtifactChangeSummary {
+        let current = self.routine_run_artifact_texts(run).await;
+        let before = match previous {
+            Some(previous) => self.routine_run_artifact_texts(previous).await,
+            None => BTreeMap::new(),
+        };
+        let artifacts = compare_artifact_texts(&before, &current);
+        let changed = previous.is_none()
+            || artifacts
+                .iter()
+                .any(|diff| diff.change != ArtifactChangeKind::Unchanged);
+        RoutineArtifactChangeSummary {
+            previous_run_id: previous.map(|p| p.run_id.clone()),
+            compared_at_ms: now_ms(),
+            changed,
+            summary: if previous.is_none() {
+                "first run".to_string()
+            } else {
+                summarize(&artifacts)
+            },
+            artifacts,
+            delivery_suppressed: false,
+        }
+    }
+
+    /// Snapshot a finished run's artifacts, compare them with the previous
+    /// run, and attach the change summary to the run record.
+    pub async fn attach_routine_artifact_diff(
+        &self,
+        run_id: &str,
+    ) -> Option<RoutineArtifactChangeSummary> {
+        let run = self.get_routine_run(run_id).await?;
+        self.snapshot_routine_run_artifacts(&run).await;
+        let previous = self.previous_completed_routine_run(&run).await;
+        let mut summary = self.compare_routine_runs(&run, previous.as_ref()).await;
+        summary.delivery_suppressed = !summary.changed && suppress_unchanged(&run);
+        {
+            let mut guard = self.routine_runs.write().await;
+            let row = guard.get_mut(run_id)?;
+            row.change_summary = Some(summary.clone());
+            row.updated_at_ms = now_ms();
+        }
+        let _ = self
+            .persist_checked(crate::persistence::PersistTarget::RoutineRuns)
+            .await;
+        self.event_bus.publish(EngineEvent::new(
+            "routine.run.artifacts_compared",
+            json!({
+                "runID": run.run_id,
+                "routineID": run.routine_id,
+                "previousRunID": summary.previous_run_id,
+                "changed": summary.changed,
+                "summary": summary.summary,
+            }),
+        ));
+        if summary.delivery_suppressed {
+            self.event_bus.publish(EngineEvent::new(
+                "routine.run.delivery_suppressed",
+                json!({
+                    "runID": run.run_id,
+                    "routineID": run.routine_id,
+                    "previousRunID": summary.previous_run_id,
+                    "reason": "no changes since previous run",
+                }),
+            ));
+        }
+        Some(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(rows: &[(&str, &str)]) -> BTreeMap<String, String> {
+        rows.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn diff_lines_reports_added_and_removed() {
+        let (added, removed, lines) = diff_lines("a\nb\nc\n", "a\nc\nd\n");
+        assert_eq!((added, removed), (1, 1));
+        assert_eq!(lines, vec!["-b".to_string(), "+d".to_string()]);
+    }
+
+    #[test]
+    fn compare_detects_each_change_kind() {
+        let previous = texts(&[("report.md", "x\ny"), ("old.txt", "gone")]);
+        let current = texts(&[("report.md", "x\nz"), ("new.txt", "hello")]);
+        let diffs = compare_artifact_texts(&previous, &current);
+        let kind = |key: &str| diffs.iter().find(|d| d.key == key).map(|d| d.change);
+        assert_eq!(kind("report.md"), Some(ArtifactChangeKind::Modified));
+        assert_eq!(kind("new.txt"), Some(ArtifactChangeKind::Added));
+        assert_eq!(kind("old.txt"), Some(ArtifactChangeKind::Removed));
+        assert_eq!(
+            summarize(&diffs),
+            "1 modified, 1 added, 1 removed (+2/-2 lines)"
+        );
+    }
+
+    #[test]
+    fn identical_texts_are_unchanged() {
+        let same = texts(&[("report.md", "same")]);
+        let diffs = compare_artifact_texts(&same, &same);
+        assert_eq!(diffs[0].change, ArtifactChangeKind::Unchanged);
+        assert_eq!(summarize(&diffs), "no changes");
+    }
+
+    #[test]
+    fn artifact_paths_are_confined_to_workspace() {
+        let root = std::env::temp_dir().join(format!("tandem-diff-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("root");
+        std::fs::write(root.join("report.md"), "ok").expect("write");
+        assert!(artifact_file_path("file://report.md", &root).is_some());
+        assert!(artifact_file_path("../../etc/passwd", &root).is_none());
+        assert!(artifact_file_path("s3://bucket/report.md", &root).is_none());
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,331 @@
+//! Per-routine notifications when runs finish.
+//!
+//! A routine opts in with `notifications` on its spec: a list of targets
+//! (`channel://{channel}/{recipient}` or an `http(s)://` webhook) and which
+//! outcomes to report:
+//!
+//! - `on_failure` (default on): the run failed;
+//! - `on_recovery` (default on): the first successful run after a failure;
+//! - `on_every_run`: every finished run, failed or not.
+//!
+//! Failure and recovery alerts are deduplicated per routine: a failure is not
+//! repeated while the previous alert was also a failure sent within
+//! `dedupe_window_ms`, and at most `max_per_window` alerts go out per window,
+//! so a flapping routine does not spam. The next alert sent says how many
+//! were suppressed.
+//!
+//! Channel targets are published as `routine.run.notify` for the channel
+//! dispatcher; webhooks get the same payload POSTed as JSON.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tandem_types::EngineEvent;
+use tokio::sync::Mutex;
+
+use crate::{now_ms, AppState, RoutineRunRecord, RoutineRunStatus};
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_dedupe_window_ms() -> u64 {
+    60 * 60 * 1000
+}
+
+fn default_max_per_window() -> u32 {
+    3
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RoutineNotificationPolicy {
+    pub targets: Vec<String>,
+    #[serde(default = "default_true")]
+    pub on_failure: bool,
+    #[serde(default = "default_true")]
+    pub on_recovery: bool,
+    #[serde(default)]
+    pub on_every_run: bool,
+    #[serde(default = "default_dedupe_window_ms")]
+    pub dedupe_window_ms: u64,
+    #[serde(default = "default_max_per_window")]
+    pub max_per_window: u32,
+}
+
+impl RoutineNotificationPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.targets.is_empty() {
+            return Err("notifications need at least one target".to_string());
+        }
+        for target in &self.targets {
+            let valid = target.starts_with("channel://")
+                || target.starts_with("https://")
+                || target.starts_with("http://");
+            if !valid {
+                return Err(format!(
+                    "`{target}` is neither a channel:// target nor an http(s) webhook"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutineNotificationKind {
+    Failure,
+    Recovery,
+    Completed,
+}
+
+impl RoutineNotificationKind {
+    fn is_alert(self) -> bool {
+        matches!(self, Self::Failure | Self::Recovery)
+    }
+}
+
+/// What to report for a finished run, given whether the routine's previous
+/// finished run failed.
+pub(crate) fn notification_kind(
+    policy: &RoutineNotificationPolicy,
+    failed: bool,
+    previous_failed: bool,
+) -> Option<RoutineNotificationKind> {
+    if failed {
+        (policy.on_failure || policy.on_every_run).then_some(RoutineNotificationKind::Failure)
+    } else if previous_failed && policy.on_recovery {
+        Some(RoutineNotificationKind::Recovery)
+    } else {
+        policy
+            .on_every_run
+            .then_some(RoutineNotificationKind::Completed)
+    }
+}
+
+/// Alerts sent for one routine in the current dedupe window.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AlertWindow {
+    started_at_ms: u64,
+    sent: u32,
+    last: Option<(RoutineNotificationKind, u64)>,
+    suppressed: u32,
+}
+
+impl AlertWindow {
+    /// `Some(suppressed)` when the notification should go out, with the
+    /// number of alerts held back since the last one sent.
+    pub(crate) fn admit(
+        &mut self,
+        kind: RoutineNotificationKind,
+        policy: &RoutineNotificationPolicy,
+        now: u64,
+    ) -> Option<u32> {
+        if !kind.is_alert() {
+            return Some(0);
+        }
+        let window = policy.dedupe_window_ms;
+        if now.saturating_sub(self.started_at_ms) >= window {
+            self.started_at_ms = now;
+            self.sent = 0;
+        }
+        let repeated = self.last.is_some_and(|(last, at)| {
+            last == RoutineNotificationKind::Failure
+                && kind == RoutineNotificationKind::Failure
+                && now.saturating_sub(at) < window
+        });
+        if repeated || self.sent >= policy.max_per_window.max(1) {
+            self.suppressed += 1;
+            return None;
+        }
+        self.sent += 1;
+        self.last = Some((kind, now));
+        Some(std::mem::take(&mut self.suppressed))
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct RoutineNotifier {
+    windows: Arc<Mutex<HashMap<String, AlertWindow>>>,
+}
+
+fn notification_text(
+    kind: RoutineNotificationKind,
+    run: &RoutineRunRecord,
+    suppressed: u32,
+) -> String {
+    let mut text = match kind {
+        RoutineNotificationKind::Failure => format!(
+            "Routine {} failed: {}",
+            run.routine_id,
+            run.detail.as_deref().unwrap_or("no reason recorded")
+        ),
+        RoutineNotificationKind::Recovery => {
+            format!("Routine {} succeeded again after failing", run.routine_id)
+        }
+        RoutineNotificationKind::Completed => format!("Routine {} completed", run.routine_id),
+    };
+    if suppressed > 0 {
+        text.push_str(&format!(
+            " ({suppressed} earlier alert{} suppressed)",
+            if suppressed == 1 { "" } else { "s" }
+        ));
+    }
+    text
+}
+
+impl AppState {
+    /// Whether the routine's last finished run before `run` failed.
+    async fn previous_routine_run_failed(&self, run: &RoutineRunRecord) -> bool {
+        self.routine_runs
+            .read()
+            .await
+            .values()
+            .filter(|other| other.routine_id == run.routine_id && other.run_id != run.run_id)
+            .filter(|other| {
+                matches!(
+                    other.status,
+                    RoutineRunStatus::Completed | RoutineRunStatus::Failed
+                )
+            })
+            .max_by_key(|other| other.finished_at_ms.unwrap_or(other.updated_at_ms))
+            .is_some_and(|other| other.status == RoutineRunStatus::Failed)
+    }
+
+    /// Send the notifications the run's routine asks for now that the run
+    /// has completed or failed.
+    pub async fn notify_routine_run_finished(&self, run: &RoutineRunRecord) {
+        let failed = match run.status {
+            RoutineRunStatus::Failed => true,
+            RoutineRunStatus::Completed => false,
+            _ => return,
+        };
+        let Some(policy) = self
+            .get_routine(&run.routine_id)
+            .await
+            .and_then(|routine| routine.notifications)
+        else {
+            return;
+        };
+        let previous_failed = self.previous_routine_run_failed(run).await;
+        let Some(kind) = notification_kind(&policy, failed, previous_failed) else {
+            return;
+        };
+        let admitted = self
+            .routine_notifier
+            .windows
+            .lock()
+            .await
+            .entry(run.routine_id.clone())
+            .or_default()
+            .admit(kind, &policy, now_ms());
+        let Some(suppressed) = admitted else {
+            self.event_bus.publish(EngineEvent::new(
+                "routine.notification.suppressed",
+                json!({
+                    "runID": run.run_id,
+                    "routineID": run.routine_id,
+                    "kind": kind,
+                }),
+            ));
+            return;
+        };
+        let payload = json!({
+            "runID": run.run_id,
+            "routineID": run.routine_id,
+            "kind": kind,
+            "status": run.status,
+            "detail": run.detail,
+            "text": notification_text(kind, run, suppressed),
+            "suppressed": suppressed,
+            "finishedAtMs": run.finished_at_ms,
+        });
+        for target in &policy.targets {
+            if target.starts_with("channel://") {
+                let mut properties = payload.clone();
+                properties["target"] = json!(target);
+                self.event_bus
+                    .publish(EngineEvent::new("routine.run.notify", properties));
+            } else {
+                let state = self.clone();
+                let target = target.clone();
+                let payload = payload.clone();
+                tandem_core::spawn_in_request(async move {
+                    state.post_routine_webhook(&target, &payload).await;
+                });
+            }
+        }
+    }
+
+    async fn post_routine_webhook(&self, url: &str, payload: &Value) {
+        let result = reqwest::Client::new()
+            .post(url)
+            .timeout(WEBHOOK_TIMEOUT)
+            .json(payload)
+            .send()
+            .await;
+        let error = match result {
+            Ok(resp) if resp.status().is_success() => return,
+            Ok(resp) => format!("HTTP {}", resp.status()),
+            Err(err) => format!("request failed: {err}"),
+        };
+        tracing::warn!("routine notification webhook {url} failed: {error}");
+        self.event_bus.publish(EngineEvent::new(
+            "routine.notification.failed",
+            json!({
+                "runID": payload["runID"],
+                "routineID": payload["routineID"],
+                "target": url,
+                "error": error,
+            }),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> RoutineNotificationPolicy {
+        serde_json::from_value(json!({ "targets": ["channel://slack/C1"] })).expect("policy")
+    }
+
+    #[test]
+    fn recovery_follows_failure_and_flapping_is_capped() {
+        use RoutineNotificationKind::{Failure, Recovery};
+        let policy = policy();
+        assert_eq!(notification_kind(&policy, true, false), Some(Failure));
+        assert_eq!(notification_kind(&policy, false, true), Some(Recovery));
+        assert_eq!(notification_kind(&policy, false, false), None);
+        let every_run = RoutineNotificationPolicy {
+            on_every_run: true,
+            ..policy.clone()
+        };
+        assert_eq!(
+            notification_kind(&every_run, false, false),
+            Some(RoutineNotificationKind::Completed)
+        );
+        assert!(RoutineNotificationPolicy {
+            targets: vec!["slack".to_string()],
+            ..policy.clone()
+        }
+        .validate()
+        .is_err());
+
+        let mut window = AlertWindow::default();
+        let hour = policy.dedupe_window_ms;
+        assert_eq!(window.admit(Failure, &policy, hour), Some(0));
+        // A repeat failure inside the window is held back.
+        assert_eq!(window.admit(Failure, &policy, hour + 1), None);
+        assert_eq!(window.admit(Recovery, &policy, hour + 2), Some(1));
+        assert_eq!(window.admit(Failure, &policy, hour + 3), Some(0));
+        // Flapping: the fourth alert in the window is over the cap.
+        assert_eq!(window.admit(Recovery, &policy, hour + 4), None);
+        assert_eq!(window.admit(Recovery, &policy, 2 * hour + 4), Some(1));
+    }
+}
@@ -0,0 +1,53 @@
+//! Built-in routine presets. Each preset's `routine` is a ready-to-send
+//! `POST /routines` body; clients fill in ids/creators and may adjust the
+//! schedule before creating it.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoutinePreset {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub routine: Value,
+}
+
+pub fn routine_presets() -> Vec<RoutinePreset> {
+    vec![
+        RoutinePreset {
+            id: "secrets-scan-daily",
+            name: "Daily secrets scan",
+            description: "Scan modified and untracked workspace files for credentials once a day and report findings by severity.",
+            routine: json!({
+                "name": "Daily secrets scan",
+                "schedule": { "interval_seconds": { "seconds": 86_400 } },
+                "entrypoint": "secrets_scan",
+                "args": { "mode": "changed", "min_severity": "medium" },
+                "allowed_tools": ["secrets_scan"],
+                "requires_approval": false,
+                "external_integrations_allowed": false,
+            }),
+        },
+        RoutinePreset {
+            id: "release-notes",
+            name: "Release notes",
+            description: "Collect the commits since the latest tag, draft release notes grouped by area, verify their PR references and links, and deliver them to the routine's output targets (set `output_targets` before creating it).",
+            routine: json!({
+                "name": "Release notes",
+                "schedule": { "interval_seconds": { "seconds": 604_800 } },
+                "entrypoint": "mission.default",
+                "args": {
+                    "prompt": "Draft the release notes for this repository. 1. Call release_changes (pass `since` to start after a specific tag). 2. Write notes with one section per area, breaking changes first, linking each item to its #PR reference when the commit has one; do not invent references. 3. Call release_notes_verify with the draft and the same range, and fix every reported reference or link until it passes. 4. Write the notes to RELEASE_NOTES.md. 5. Reply with the final notes only.",
+                    "success_criteria": [
+                        "Every area with changes has a section",
+                        { "type": "file_exists", "path": "RELEASE_NOTES.md" }
+                    ]
+                },
+                "allowed_tools": ["release_changes", "release_notes_verify", "read", "write"],
+                "requires_approval": false,
+                "external_integrations_allowed": false,
+            }),
+        },
+    ]
+}
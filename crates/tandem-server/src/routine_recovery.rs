@@ -0,0 +1,321 @@
+//! Startup reconciliation of routine runs a crash left in `running`.
+//!
+//! A run is interrupted when it was running as loaded from disk and its
+//! session has no active run in this process. The `routine_recovery` config
+//! block decides what happens to it:
+//!
+//! - `fail` (default): the run is marked failed.
+//! - `requeue`: the run is queued again and starts over in a new session.
+//! - `resume`: the run is queued again in its old session when that session
+//!   still has a turn pending (the last message is the prompt, or a tool call
+//!   without a result); otherwise it is requeued.
+//!
+//! A run already recovered `max_attempts` times is failed instead, so a run
+//! that takes the engine down is not retried forever. Each decision is
+//! appended to the run's `recoveries`, written to its `detail` and recorded
+//! in the routine history.
+
+use serde::{Deserialize, Serialize};
+use tandem_types::{EngineEvent, MessagePart, MessageRole, Session};
+
+use crate::{now_ms, AppState, RoutineHistoryEvent, RoutineRunRecord, RoutineRunStatus};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryPolicy {
+    #[default]
+    Fail,
+    Requeue,
+    Resume,
+}
+
+fn default_max_attempts() -> u32 {
+    3
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutineRecoveryConfig {
+    #[serde(default)]
+    pub policy: RecoveryPolicy,
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+}
+
+impl Default for RoutineRecoveryConfig {
+    fn default() -> Self {
+        Self {
+            policy: RecoveryPolicy::default(),
+            max_attempts: default_max_attempts(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryAction {
+    Failed,
+    Requeued,
+    Resumed,
+}
+
+impl RecoveryAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Failed => "failed",
+            Self::Requeued => "requeued",
+            Self::Resumed => "resumed",
+        }
+    }
+}
+
+/// One recovery decision taken for a run at startup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecovery {
+    pub action: RecoveryAction,
+    pub at_ms: u64,
+    pub reason: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+/// Prefix for the prompt of a resumed run, so the model picks up the work
+/// already in the session instead of starting over.
+pub(crate) const RESUME_NOTE: &str = "This routine run was interrupted by an engine restart. \
+The conversation above shows the progress made so far; continue from where it stopped and \
+finish the task.";
+
+/// Whether the session's last turn was cut off before it finished.
+pub(crate) fn session_has_pending_turn(session: &Session) -> bool {
+    session.messages.last().is_some_and(|message| {
+        matches!(message.role, MessageRole::User)
+            || message.parts.iter().any(|part| {
+                matches!(
+                    part,
+                    MessagePart::ToolInvocation {
+                        result: None,
+                        error: None,
+                        ..
+                    }
+                )
+            })
+    })
+}
+
+pub(crate) fn decide_recovery(
+    config: &RoutineRecoveryConfig,
+    run: &RoutineRunRecord,
+    session: Option<&Session>,
+) -> (RecoveryAction, String) {
+    let attempts = run.recoveries.len() as u32;
+    if config.policy != RecoveryPolicy::Fail && attempts >= config.max_attempts {
+        return (
+            RecoveryAction::Failed,
+            format!("interrupted again after {attempts} recoveries"),
+        );
+    }
+    match config.policy {
+        RecoveryPolicy::Fail => (
+            RecoveryAction::Failed,
+            "interrupted by an engine restart".to_string(),
+        ),
+        RecoveryPolicy::Requeue => (
+            RecoveryAction::Requeued,
+            "interrupted by an engine restart; starting over".to_string(),
+        ),
+        RecoveryPolicy::Resume => match session {
+            Some(session) if session_has_pending_turn(session) => (
+                RecoveryAction::Resumed,
+                "interrupted by an engine restart; resuming in its session".to_string(),
+            ),
+            Some(_) => (
+                RecoveryAction::Requeued,
+                "interrupted by an engine restart with no pending turn; starting over".to_string(),
+            ),
+            None => (
+                RecoveryAction::Requeued,
+                "interrupted by an engine restart and its session is gone; starting over"
+                    .to_string(),
+            ),
+        },
+    }
+}
+
+impl AppState {
+    /// Resolve routine runs a previous process left `running`. Only runs last
+    /// updated before `loaded_at_ms` are looked at, so a run the executor
+    /// claims in the meantime is left alone.
+    pub async fn recover_interrupted_routine_runs(
+        &self,
+        loaded_at_ms: u64,
+    ) -> Vec<RoutineRunRecord> {
+        let config = self.routine_recovery_config().await;
+        let candidates = self
+            .routine_runs
+            .read()
+            .await
+            .values()
+            .filter(|run| {
+                run.status == RoutineRunStatus::Running && run.updated_at_ms < loaded_at_ms
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut recovered = Vec::new();
+        for run in candidates {
+            let session = match run.session_id.as_deref() {
+                Some(session_id) if self.run_registry.get(session_id).await.is_some() => {
+                    continue;
+                }
+                Some(session_id) => self.storage.get_session(session_id).await,
+                None => None,
+            };
+            let (action, reason) = decide_recovery(&config, &run, session.as_ref());
+            match self
+                .apply_routine_recovery(&run.run_id, action, &reason)
+                .await
+            {
+                Ok(Some(updated)) => recovered.push(updated),
+                Ok(None) => {}
+                Err(error) => {
+                    tracing::warn!("failed to recover routine run {}: {error}", run.run_id);
+                }
+            }
+        }
+        recovered
+    }
+
+    async fn apply_routine_recovery(
+        &self,
+        run_id: &str,
+        action: RecoveryAction,
+        reason: &str,
+    ) -> anyhow::Result<Option<RoutineRunRecord>> {
+        let now = now_ms();
+        let mut guard = self.routine_runs.write().await;
+        let Some(row) = guard.get_mut(run_id) else {
+            return Ok(None);
+        };
+        let previous = row.clone();
+        row.recoveries.push(RunRecovery {
+            action,
+            at_ms: now,
+            reason: reason.to_string(),
+            session_id: row.session_id.clone(),
+        });
+        row.detail = Some(format!("recovered after restart: {reason}"));
+        row.updated_at_ms = now;
+        match action {
+            RecoveryAction::Failed => {
+                row.status = RoutineRunStatus::Failed;
+                row.finished_at_ms = Some(now);
+            }
+            RecoveryAction::Requeued | RecoveryAction::Resumed => {
+                row.status = RoutineRunStatus::Queued;
+                row.started_at_ms = None;
+                row.resume_session_id = match action {
+                    RecoveryAction::Resumed => row.session_id.clone(),
+                    _ => None,
+                };
+            }
+        }
+        let updated = row.clone();
+        drop(guard);
+        self.persist_run_change(previous).await?;
+        if action == RecoveryAction::Failed {
+            self.notify_routine_run_finished(&updated).await;
+        }
+
+        self.append_routine_history(RoutineHistoryEvent {
+            routine_id: updated.routine_id.clone(),
+            trigger_type: updated.trigger_type.clone(),
+            run_count: updated.run_count,
+            fired_at_ms: now,
+            status: format!("recovery_{}", action.as_str()),
+            detail: Some(reason.to_string()),
+        })
+        .await;
+        self.event_bus.publish(EngineEvent::new(
+            "routine.run.recovered",
+            serde_json::json!({
+                "runID": updated.run_id,
+                "routineID": updated.routine_id,
+                "sessionID": updated.session_id,
+                "action": action,
+                "reason": reason,
+            }),
+        ));
+        Ok(Some(updated))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tandem_types::Message;
+
+    fn running_run(recoveries: usize) -> RoutineRunRecord {
+        let mut run: RoutineRunRecord = serde_json::from_value(json!({
+            "run_id": "run-1",
+            "routine_id": "routine-1",
+            "trigger_type": "scheduled",
+            "run_count": 1,
+            "status": "running",
+            "created_at_ms": 1,
+            "updated_at_ms": 1,
+            "requires_approval": false,
+            "entrypoint": "mission.default",
+            "session_id": "session-1",
+        }))
+        .expect("run");
+        run.recoveries = (0..recoveries)
+            .map(|_| RunRecovery {
+                action: RecoveryAction::Resumed,
+                at_ms: 1,
+                reason: "earlier".to_string(),
+                session_id: None,
+            })
+            .collect();
+        run
+    }
+
+    #[test]
+    fn resume_needs_a_pending_turn_and_gives_up_after_max_attempts() {
+        let config = RoutineRecoveryConfig {
+            policy: RecoveryPolicy::Resume,
+            max_attempts: 2,
+        };
+        let mut session = Session::new(None, None);
+        session.messages.push(Message::new(
+            MessageRole::User,
+            vec![MessagePart::Text {
+                text: "run the report".to_string(),
+            }],
+        ));
+        assert!(session_has_pending_turn(&session));
+        let (action, _) = decide_recovery(&config, &running_run(0), Some(&session));
+        assert_eq!(action, RecoveryAction::Resumed);
+
+        session.messages.push(Message::new(
+            MessageRole::Assistant,
+            vec![MessagePart::Text {
+                text: "done".to_string(),
+            }],
+        ));
+        assert!(!session_has_pending_turn(&session));
+        let (action, _) = decide_recovery(&config, &running_run(0), Some(&session));
+        assert_eq!(action, RecoveryAction::Requeued);
+        let (action, _) = decide_recovery(&config, &running_run(0), None);
+        assert_eq!(action, RecoveryAction::Requeued);
+
+        let (action, reason) = decide_recovery(&config, &running_run(2), None);
+        assert_eq!(action, RecoveryAction::Failed);
+        assert!(reason.contains("2 recoveries"));
+
+        let (action, _) = decide_recovery(
+            &RoutineRecoveryConfig::default(),
+            &running_run(0),
+            Some(&session),
+        );
+        assert_eq!(action, RecoveryAction::Failed);
+    }
+}
@@ -0,0 +1,279 @@
+//! `{{variable}}` interpolation for routine entrypoints and args, resolved
+//! when a run starts executing.
+//!
+//! Variables are written as `{{name}}` (surrounding whitespace is ignored).
+//! Unresolved variables are left verbatim unless the routine uses
+//! [`RoutineTemplateMode::Strict`], in which case the run fails instead.
+//! `{{calc: EXPR}}` evaluates `EXPR` with the deterministic `calc` evaluator;
+//! other variables can be used by name inside it.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tandem_tools::calc::CalcValue;
+
+/// Environment variables exposed as `{{env.NAME}}` must carry this prefix,
+/// so routines cannot read arbitrary host secrets.
+pub const ROUTINE_ENV_PREFIX: &str = "TANDEM_ROUTINE_";
+const RESOURCE_PREFIX: &str = "resource:";
+const CALC_PREFIX: &str = "calc:";
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoutineTemplateMode {
+    /// Leave unresolved variables in place.
+    #[default]
+    Lenient,
+    /// Fail the run when any variable cannot be resolved.
+    Strict,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateVariableDoc {
+    pub name: &'static str,
+    pub description: &'static str,
+}
+
+/// Variables available to routine templates.
+pub const TEMPLATE_VARIABLE_CATALOG: &[TemplateVariableDoc] = &[
+    TemplateVariableDoc {
+        name: "date",
+        description: "Current UTC date (YYYY-MM-DD)",
+    },
+    TemplateVariableDoc {
+        name: "time",
+        description: "Current UTC time (HH:MM:SS)",
+    },
+    TemplateVariableDoc {
+        name: "datetime",
+        description: "Current UTC timestamp (RFC 3339)",
+    },
+    TemplateVariableDoc {
+        name: "timestamp_ms",
+        description: "Current time in milliseconds since the Unix epoch",
+    },
+    TemplateVariableDoc {
+        name: "workspace_root",
+        description: "Workspace root the run executes in",
+    },
+    TemplateVariableDoc {
+        name: "routine.id",
+        description: "Routine ID",
+    },
+    TemplateVariableDoc {
+        name: "routine.name",
+        description: "Routine display name",
+    },
+    TemplateVariableDoc {
+        name: "run.id",
+        description: "ID of the run being executed",
+    },
+    TemplateVariableDoc {
+        name: "run.trigger_type",
+        description: "What fired the run (scheduled, manual, ...)",
+    },
+    TemplateVariableDoc {
+        name: "run.count",
+        description: "Run count of the trigger",
+    },
+    TemplateVariableDoc {
+        name: "last_run.id",
+        description: "ID of the routine's previous finished run",
+    },
+    TemplateVariableDoc {
+        name: "last_run.status",
+        description: "Status of the previous finished run",
+    },
+    TemplateVariableDoc {
+        name: "last_run.finished_at_ms",
+        description: "When the previous run finished",
+    },
+    TemplateVariableDoc {
+        name: "last_run.detail",
+        description: "Status detail of the previous run",
+    },
+    TemplateVariableDoc {
+        name: "env.NAME",
+        description: "Value of the TANDEM_ROUTINE_NAME environment variable",
+    },
+    TemplateVariableDoc {
+        name: "resource:KEY",
+        description: "Value of the shared resource KEY (strings verbatim, otherwise JSON)",
+    },
+    TemplateVariableDoc {
+        name: "calc: EXPR",
+        description:
+            "Result of a calc expression over the other variables, e.g. {{calc: date(date) + 7 d}}",
+    },
+];
+
+/// Resolved values for a single run.
+#[derive(Debug, Clone, Default)]
+pub struct RoutineTemplateContext {
+    pub vars: HashMap<String, String>,
+    pub resources: HashMap<String, Value>,
+}
+
+impl RoutineTemplateContext {
+    fn resolve(&self, name: &str) -> Option<String> {
+        if let Some(key) = name.strip_prefix(RESOURCE_PREFIX) {
+            return self.resources.get(key.trim()).map(|value| match value {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            });
+        }
+        if let Some(expr) = name.strip_prefix(CALC_PREFIX) {
+            let lookup = |var: &str| self.vars.get(var).map(|v| CalcValue::from_text(v));
+            return tandem_tools::calc::evaluate_with(expr, &lookup)
+                .ok()
+                .map(|value| value.to_string());
+        }
+        if let Some(env_name) = name.strip_prefix("env.") {
+            return std::env::var(format!("{ROUTINE_ENV_PREFIX}{env_name}")).ok();
+        }
+        self.vars.get(name).cloned()
+    }
+}
+
+/// Iterate the `{{...}}` placeholders of `text` as `(start, end, name)`.
+fn placeholders(text: &str) -> Vec<(usize, usize, &str)> {
+    let mut out = Vec::new();
+    let mut cursor = 0;
+    while let Some(open) = text[cursor..].find("{{") {
+        let start = cursor + open;
+        let Some(close) = text[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + close + 2;
+        out.push((start, end, text[start + 2..end - 2].trim()));
+        cursor = end;
+    }
+    out
+}
+
+fn collect_names<'a>(value: &'a Value, out: &mut Vec<&'a str>) {
+    match value {
+        Value::String(text) => out.extend(placeholders(text).into_iter().map(|(_, _, n)| n)),
+        Value::Array(items) => items.iter().for_each(|item| collect_names(item, out)),
+        Value::Object(map) => map.values().for_each(|item| collect_names(item, out)),
+        _ => {}
+    }
+}
+
+/// Shared-resource keys referenced through `{{resource:KEY}}`.
+pub fn referenced_resource_keys(values: &[&Value]) -> Vec<String> {
+    let mut names = Vec::new();
+    for value in values {
+        collect_names(value, &mut names);
+    }
+    let mut keys = names
+        .into_iter()
+        .filter_map(|name| name.strip_prefix(RESOURCE_PREFIX))
+        .map(|key| key.trim().to_string())
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+    keys
+}
+
+pub fn render_template_str(
+    text: &str,
+    ctx: &RoutineTemplateContext,
+    unresolved: &mut Vec<String>,
+) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, end, name) in placeholders(text) {
+        out.push_str(&text[last..start]);
+        match ctx.resolve(name) {
+            Some(value) => out.push_str(&value),
+            None => {
+                if !unresolved.iter().any(|n| n == name) {
+                    unresolved.push(name.to_string());
+                }
+                out.push_str(&text[start..end]);
+            }
+        }
+        last = end;
+    }
+    out.push_str(&text[last..]);
+    out
+}
+
+/// Render every string inside `value`. Names that could not be resolved are
+/// appended to `unresolved`.
+pub fn render_template_value(
+    value: &Value,
+    ctx: &RoutineTemplateContext,
+    unresolved: &mut Vec<String>,
+) -> Value {
+    match value {
+        Value::String(text) => Value::String(render_template_str(text, ctx, unresolved)),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| render_template_value(item, ctx, unresolved))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), render_template_value(v, ctx, unresolved)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn renders_nested_args_and_reports_unknown_variables() {
+        let mut ctx = RoutineTemplateContext::default();
+        ctx.vars
+            .insert("date".to_string(), "2026-01-02".to_string());
+        ctx.vars
+            .insert("last_run.status".to_string(), "failed".to_string());
+        ctx.resources
+            .insert("project/x/goal".to_string(), json!("ship v2"));
+        ctx.resources
+            .insert("project/x/limits".to_string(), json!({"max": 3}));
+        let args = json!({
+            "prompt": "Report for {{ date }} (last: {{last_run.status}}) goal={{resource:project/x/goal}}",
+            "limits": ["{{resource:project/x/limits}}", 7],
+            "note": "{{unknown}} and {{ unknown }}",
+        });
+        assert_eq!(
+            referenced_resource_keys(&[&args]),
+            vec!["project/x/goal", "project/x/limits"]
+        );
+        let mut unresolved = Vec::new();
+        let rendered = render_template_value(&args, &ctx, &mut unresolved);
+        assert_eq!(
+            rendered["prompt"],
+            json!("Report for 2026-01-02 (last: failed) goal=ship v2")
+        );
+        assert_eq!(rendered["limits"], json!(["{\"max\":3}", 7]));
+        assert_eq!(rendered["note"], json!("{{unknown}} and {{ unknown }}"));
+        assert_eq!(unresolved, vec!["unknown"]);
+    }
+
+    #[test]
+    fn evaluates_calc_expressions_over_variables() {
+        let mut ctx = RoutineTemplateContext::default();
+        ctx.vars
+            .insert("date".to_string(), "2026-10-17".to_string());
+        ctx.vars.insert("run.count".to_string(), "9".to_string());
+        let mut unresolved = Vec::new();
+        let rendered = render_template_str(
+            "Due {{calc: date(date) + 2 wk}}, run #{{ calc: run.count + 1 }}, {{calc: 1 / 0}}",
+            &ctx,
+            &mut unresolved,
+        );
+        assert_eq!(rendered, "Due 2026-10-31, run #10, {{calc: 1 / 0}}");
+        assert_eq!(unresolved, vec!["calc: 1 / 0"]);
+    }
+}
@@ -0,0 +1,411 @@
+//! Run lifecycle hooks: shell commands or plugin callbacks executed before a
+//! run starts, after it succeeds, and after it fails.
+//!
+//! Hooks come from the `run_hooks` config block. Global hooks run first, then
+//! hooks for the session's workspace, then hooks for the run's agent profile.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::process::Command;
+
+const DEFAULT_HOOK_TIMEOUT_MS: u64 = 60_000;
+const MAX_HOOK_OUTPUT_CHARS: usize = 4_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookPhase {
+    PreRun,
+    PostRun,
+    OnFailure,
+}
+
+impl HookPhase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::PreRun => "pre_run",
+            Self::PostRun => "post_run",
+            Self::OnFailure => "on_failure",
+        }
+    }
+}
+
+/// What a failing hook does to the run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookFailurePolicy {
+    /// Recorded on the run only.
+    Ignore,
+    /// Recorded and published as `session.run.hook.failed`.
+    #[default]
+    Warn,
+    /// A failing `pre_run` hook aborts the run; a failing `post_run` hook
+    /// marks the run as failed.
+    Fail,
+}
+
+/// A single hook: exactly one of `command` (shell) or `url` (plugin callback,
+/// receives a JSON POST).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunHook {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub on_error: HookFailurePolicy,
+}
+
+impl RunHook {
+    fn label(&self) -> String {
+        self.name
+            .clone()
+            .or_else(|| self.command.clone())
+            .or_else(|| self.url.clone())
+            .unwrap_or_else(|| "hook".to_string())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunHookSet {
+    #[serde(default)]
+    pub pre_run: Vec<RunHook>,
+    #[serde(default)]
+    pub post_run: Vec<RunHook>,
+    #[serde(default)]
+    pub on_failure: Vec<RunHook>,
+}
+
+impl RunHookSet {
+    fn phase(&self, phase: HookPhase) -> &[RunHook] {
+        match phase {
+            HookPhase::PreRun => &self.pre_run,
+            HookPhase::PostRun => &self.post_run,
+            HookPhase::OnFailure => &self.on_failure,
+        }
+    }
+}
+
+/// `run_hooks` config block: global hooks plus per-workspace (keyed by
+/// workspace root) and per-agent-profile sets.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunHooksConfig {
+    #[serde(flatten)]
+    pub default: RunHookSet,
+    #[serde(default)]
+    pub workspaces: HashMap<String, RunHookSet>,
+    #[serde(default)]
+    pub profiles: HashMap<String, RunHookSet>,
+}
+
+impl RunHooksConfig {
+    /// Hooks for `phase` in execution order: global, workspace, profile.
+    pub fn hooks_for(
+        &self,
+        phase: HookPhase,
+        workspace_root: Option<&str>,
+        agent_profile: Option<&str>,
+    ) -> Vec<RunHook> {
+        let mut out = self.default.phase(phase).to_vec();
+        if let Some(root) = workspace_root {
+            let root = root.trim_end_matches(['/', '\\']);
+            if let Some(set) = self
+                .workspaces
+                .iter()
+                .find(|(key, _)| key.trim_end_matches(['/', '\\']) == root)
+                .map(|(_, set)| set)
+            {
+                out.extend_from_slice(set.phase(phase));
+            }
+        }
+        if let Some(set) = agent_profile.and_then(|profile| self.profiles.get(profile)) {
+            out.extend_from_slice(set.phase(phase));
+        }
+        out
+    }
+}
+
+/// Result of one hook, attached to the run.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunHookOutcome {
+    pub phase: HookPhase,
+    pub hook: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    pub output: String,
+    pub duration_ms: u64,
+    pub on_error: HookFailurePolicy,
+}
+
+impl RunHookOutcome {
+    /// True when this failure should abort or fail the run.
+    pub fn is_fatal(&self) -> bool {
+        !self.ok && self.on_error == HookFailurePolicy::Fail
+    }
+}
+
+/// Context passed to hooks, as `TANDEM_*` env vars for commands and as the
+/// JSON body for callbacks.
+#[derive(Debug, Clone)]
+pub struct HookRunContext<'a> {
+    pub session_id: &'a str,
+    pub run_id: &'a str,
+    pub workspace_root: Option<&'a str>,
+    pub agent_profile: Option<&'a str>,
+    pub status: Option<&'a str>,
+    pub error: Option<&'a str>,
+}
+
+fn truncate_output(text: &str) -> String {
+    let trimmed = text.trim();
+    if trimmed.chars().count() <= MAX_HOOK_OUTPUT_CHARS {
+        return trimmed.to_string();
+    }
+    let tail = trimmed
+        .chars()
+        .rev()
+        .take(MAX_HOOK_OUTPUT_CHARS)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect::<String>();
+    format!("...{tail}")
+}
+
+/// How a shell command run by [`run_shell_command`] ended.
+pub(crate) enum ShellOutcome {
+    /// The command exited; `output` is stdout followed by stderr.
+    Exited {
+        success: bool,
+        code: Option<i32>,
+        output: String,
+    },
+    SpawnFailed(std::io::Error),
+    TimedOut,
+}
+
+/// Run `command` through the platform shell in `cwd` (when it is a
+/// directory) with extra `envs`, killing it after `timeout`. Shared by run
+/// hooks and routine success checks.
+pub(crate) async fn run_shell_command(
+    command: &str,
+    cwd: Option<&str>,
+    envs: &[(&str, &str)],
+    timeout: Duration,
+) -> ShellOutcome {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("powershell");
+        cmd.args(["-NoProfile", "-Command", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    };
+    if let Some(root) = cwd.filter(|root| Path::new(root).is_dir()) {
+        cmd.current_dir(root);
+    }
+    cmd.envs(envs.iter().copied()).kill_on_drop(true);
+    match tokio::time::timeout(timeout, cmd.output()).await {
+        Ok(Ok(output)) => {
+            let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if !stderr.trim().is_empty() {
+                if !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+                text.push_str(&stderr);
+            }
+            ShellOutcome::Exited {
+                success: output.status.success(),
+                code: output.status.code(),
+                output: text,
+            }
+        }
+        Ok(Err(err)) => ShellOutcome::SpawnFailed(err),
+        Err(_) => ShellOutcome::TimedOut,
+    }
+}
+
+async fn run_command_hook(
+    command: &str,
+    ctx: &HookRunContext<'_>,
+    phase: HookPhase,
+    timeout: Duration,
+) -> (bool, Option<i32>, String) {
+    let envs = [
+        ("TANDEM_HOOK_PHASE", phase.as_str()),
+        ("TANDEM_SESSION_ID", ctx.session_id),
+        ("TANDEM_RUN_ID", ctx.run_id),
+        ("TANDEM_RUN_STATUS", ctx.status.unwrap_or_default()),
+        ("TANDEM_RUN_ERROR", ctx.error.unwrap_or_default()),
+    ];
+    match run_shell_command(command, ctx.workspace_root, &envs, timeout).await {
+        ShellOutcome::Exited {
+            success,
+            code,
+            output,
+        } => (success, code, output),
+        ShellOutcome::SpawnFailed(err) => (false, None, format!("failed to spawn hook: {err}")),
+        ShellOutcome::TimedOut => (
+            false,
+            None,
+            format!("hook timed out after {}ms", timeout.as_millis()),
+        ),
+    }
+}
+
+async fn run_callback_hook(
+    url: &str,
+    ctx: &HookRunContext<'_>,
+    phase: HookPhase,
+    timeout: Duration,
+) -> (bool, Option<i32>, String) {
+    let body = json!({
+        "phase": phase,
+        "sessionID": ctx.session_id,
+        "runID": ctx.run_id,
+        "workspaceRoot": ctx.workspace_root,
+        "agentProfile": ctx.agent_profile,
+        "status": ctx.status,
+        "error": ctx.error,
+    });
+    let client = reqwest::Client::new();
+    match client.post(url).timeout(timeout).json(&body).send().await {
+        Ok(resp) => {
+            let status = resp.status();
+            let text = resp.text().await.unwrap_or_default();
+            (status.is_success(), Some(status.as_u16() as i32), text)
+        }
+        Err(err) => (false, None, format!("callback failed: {err}")),
+    }
+}
+
+/// Run `hooks` sequentially. A fatal failure stops the remaining hooks of
+/// the phase.
+pub async fn run_hooks(
+    phase: HookPhase,
+    hooks: &[RunHook],
+    ctx: &HookRunContext<'_>,
+) -> Vec<RunHookOutcome> {
+    let mut outcomes = Vec::with_capacity(hooks.len());
+    for hook in hooks {
+        let started = Instant::now();
+        let timeout =
+            Duration::from_millis(hook.timeout_ms.unwrap_or(DEFAULT_HOOK_TIMEOUT_MS).max(1));
+        let (ok, exit_code, output) = match (hook.command.as_deref(), hook.url.as_deref()) {
+            (Some(command), _) if !command.trim().is_empty() => {
+                run_command_hook(command, ctx, phase, timeout).await
+            }
+            (_, Some(url)) if !url.trim().is_empty() => {
+                run_callback_hook(url, ctx, phase, timeout).await
+            }
+            _ => (
+                false,
+                None,
+                "hook has neither `command` nor `url`".to_string(),
+            ),
+        };
+        let outcome = RunHookOutcome {
+            phase,
+            hook: hook.label(),
+            ok,
+            exit_code,
+            output: truncate_output(&output),
+            duration_ms: started.elapsed().as_millis() as u64,
+            on_error: hook.on_error,
+        };
+        let fatal = outcome.is_fatal();
+        outcomes.push(outcome);
+        if fatal {
+            break;
+        }
+    }
+    outcomes
+}
+
+/// Event payload for one hook outcome.
+pub fn outcome_event_payload(session_id: &str, run_id: &str, outcome: &RunHookOutcome) -> Value {
+    json!({
+        "sessionID": session_id,
+        "runID": run_id,
+        "phase": outcome.phase,
+        "hook": outcome.hook,
+        "ok": outcome.ok,
+        "exitCode": outcome.exit_code,
+        "output": outcome.output,
+        "durationMs": outcome.duration_ms,
+        "onError": outcome.on_error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx() -> HookRunContext<'static> {
+        HookRunContext {
+            session_id: "s1",
+            run_id: "r1",
+            workspace_root: None,
+            agent_profile: None,
+            status: Some("completed"),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn hooks_are_ordered_global_workspace_profile() {
+        let config: RunHooksConfig = serde_json::from_value(json!({
+            "post_run": [{"command": "echo global"}],
+            "workspaces": {"/ws/": {"post_run": [{"command": "cargo fmt"}]}},
+            "profiles": {"reviewer": {"post_run": [{"url": "http://localhost/hook"}]}},
+        }))
+        .expect("config");
+        let hooks = config.hooks_for(HookPhase::PostRun, Some("/ws"), Some("reviewer"));
+        let labels = hooks.iter().map(RunHook::label).collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            vec!["echo global", "cargo fmt", "http://localhost/hook"]
+        );
+        assert!(config
+            .hooks_for(HookPhase::PreRun, Some("/ws"), Some("reviewer"))
+            .is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn fatal_failure_stops_remaining_hooks() {
+        let hooks = vec![
+            RunHook {
+                command: Some("echo \"$TANDEM_HOOK_PHASE $TANDEM_RUN_ID\"".to_string()),
+                ..RunHook::default()
+            },
+            RunHook {
+                command: Some("echo nope >&2; exit 3".to_string()),
+                on_error: HookFailurePolicy::Fail,
+                ..RunHook::default()
+            },
+            RunHook {
+                command: Some("echo unreachable".to_string()),
+                ..RunHook::default()
+            },
+        ];
+        let outcomes = run_hooks(HookPhase::PreRun, &hooks, &ctx()).await;
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes[0].ok);
+        assert_eq!(outcomes[0].output, "pre_run r1");
+        assert!(outcomes[1].is_fatal());
+        assert_eq!(outcomes[1].exit_code, Some(3));
+        assert_eq!(outcomes[1].output, "nope");
+    }
+}
@@ -0,0 +1,458 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tandem_types::EngineEvent;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::RwLock;
+
+/// One state transition of a run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RunTimelineEntry {
+    pub seq: u64,
+    pub at_ms: u64,
+    pub kind: String,
+    pub label: String,
+    #[serde(rename = "sessionID")]
+    pub session_id: String,
+    /// Pointers to the payload behind the transition (message/part IDs, the
+    /// source event type) rather than the payload itself.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payload_ref: Option<Value>,
+}
+
+/// A start/end pair derived from the timeline, for Gantt-style rendering.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RunTimelineSpan {
+    pub kind: String,
+    pub label: String,
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+struct ActiveTimeline {
+    run_id: String,
+    next_seq: u64,
+}
+
+/// Records per-run state transitions from the event bus into
+/// `run_timelines/{run_id}.jsonl`.
+#[derive(Clone)]
+pub struct RunTimelineRecorder {
+    dir: PathBuf,
+    active: Arc<RwLock<HashMap<String, ActiveTimeline>>>,
+}
+
+fn str_prop<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(|v| v.as_str())
+}
+
+/// Session an engine event belongs to, read from `sessionID` or its part's.
+pub fn event_session_id(event: &EngineEvent) -> Option<String> {
+    str_prop(&event.properties, "sessionID")
+        .or_else(|| {
+            event
+                .properties
+                .get("part")
+                .and_then(|p| str_prop(p, "sessionID"))
+        })
+        .map(ToString::to_string)
+}
+
+/// Map an engine event to `(kind, label, payload_ref)`; `None` if it is not a
+/// run state transition.
+fn transition_for(event: &EngineEvent) -> Option<(String, String, Option<Value>)> {
+    let props = &event.properties;
+    match event.event_type.as_str() {
+        "session.run.started" => Some(("run.started".to_string(), "run started".to_string(), None)),
+        "provider.call.started" | "provider.call.finished" => {
+            let iteration = props.get("iteration").and_then(|v| v.as_u64()).unwrap_or(0);
+            let kind = if event.event_type.ends_with("started") {
+                "model_turn.started"
+            } else {
+                "model_turn.finished"
+            };
+            Some((
+                kind.to_string(),
+                format!("model turn {iteration}"),
+                Some(serde_json::json!({
+                    "event": event.event_type,
+                    "messageID": props.get("messageID"),
+                    "providerID": props.get("providerID"),
+                    "modelID": props.get("modelID"),
+                })),
+            ))
+        }
+        "message.part.updated" => {
+            if props.get("toolCallDelta").is_some() {
+                return None;
+            }
+            let part = props.get("part")?;
+            if str_prop(part, "type") != Some("tool") {
+                return None;
+            }
+            let tool = str_prop(part, "tool").unwrap_or("tool");
+            let kind = match str_prop(part, "state")? {
+                "running" => "tool.started",
+                "pending" => "tool.awaiting_permission",
+                "completed" => "tool.finished",
+                "failed" | "denied" => "tool.failed",
+                _ => return None,
+            };
+            Some((
+                kind.to_string(),
+                format!("tool {tool}"),
+                Some(serde_json::json!({
+                    "event": event.event_type,
+                    "messageID": part.get("messageID"),
+                    "partID": part.get("id"),
+                    "tool": tool,
+                })),
+            ))
+        }
+        "session.run.hook" => {
+            let phase = str_prop(props, "phase").unwrap_or("hook");
+            let hook = str_prop(props, "hook").unwrap_or("hook");
+            Some((
+                format!("hook.{phase}"),
+                format!("hook {hook}"),
+                Some(serde_json::json!({
+                    "event": event.event_type,
+                    "ok": props.get("ok"),
+                    "exitCode": props.get("exitCode"),
+                    "durationMs": props.get("durationMs"),
+                })),
+            ))
+        }
+        "session.run.skills_selected" => {
+            let skills = props
+                .pointer("/selection/skills")
+                .and_then(|v| v.as_array())
+                .map(|rows| {
+                    rows.iter()
+                        .map(|row| serde_json::json!({ "name": row.get("name"), "mode": row.get("mode") }))
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            Some((
+                "skills.selected".to_string(),
+                format!("{} skill(s) loaded", skills.len()),
+                Some(serde_json::json!({
+                    "event": event.event_type,
+                    "skills": skills,
+                    "usedTokens": props.pointer("/selection/usedTokens"),
+                    "budgetTokens": props.pointer("/selection/budgetTokens"),
+                })),
+            ))
+        }
+        "tool.command_policy" => {
+            let outcome = str_prop(props, "outcome").unwrap_or("blocked");
+            Some((
+                format!("command_policy.{outcome}"),
+                format!("command {outcome} by policy"),
+                Some(serde_json::json!({
+                    "event": event.event_type,
+                    "messageID": props.get("messageID"),
+                    "command": props.get("command"),
+                    "rule": props.pointer("/decision/rule"),
+                    "reason": props.pointer("/decision/reason"),
+                })),
+            ))
+        }
+        "session.run.finished" => {
+            let status = str_prop(props, "status").unwrap_or("finished");
+            Some((
+                "run.finished".to_string(),
+                format!("run {status}"),
+                Some(serde_json::json!({ "status": status, "error": props.get("error") })),
+            ))
+        }
+        _ => None,
+    }
+}
+
+impl RunTimelineRecorder {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            active: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn set_dir(&mut self, dir: PathBuf) {
+        self.dir = dir;
+    }
+
+    fn path_for(&self, run_id: &str) -> PathBuf {
+        self.dir.join(format!("{run_id}.jsonl"))
+    }
+
+    /// Record `event` against the session's active run, if it is a transition.
+    pub async fn handle_event(&self, event: &EngineEvent, at_ms: u64) -> anyhow::Result<()> {
+        let Some(session_id) = event_session_id(event) else {
+            return Ok(());
+        };
+        let Some((kind, label, payload_ref)) = transition_for(event) else {
+            return Ok(());
+        };
+        let (run_id, seq) = {
+            let mut active = self.active.write().await;
+            if kind == "run.started" {
+                let Some(run_id) = str_prop(&event.properties, "runID") else {
+                    return Ok(());
+                };
+                active.insert(
+                    session_id.clone(),
+                    ActiveTimeline {
+                        run_id: run_id.to_string(),
+                        next_seq: 0,
+                    },
+                );
+            }
+            let Some(timeline) = active.get_mut(&session_id) else {
+                return Ok(());
+            };
+            if kind == "run.finished"
+                && str_prop(&event.properties, "runID").is_some_and(|id| id != timeline.run_id)
+            {
+                return Ok(());
+            }
+            let seq = timeline.next_seq;
+            timeline.next_seq += 1;
+            let run_id = timeline.run_id.clone();
+            if kind == "run.finished" {
+                active.remove(&session_id);
+            }
+            (run_id, seq)
+        };
+        let entry = RunTimelineEntry {
+            seq,
+            at_ms,
+            kind,
+            label,
+            session_id,
+            payload_ref,
+        };
+        self.append(&run_id, &entry).await
+    }
+
+    async fn append(&self, run_id: &str, entry: &RunTimelineEntry) -> anyhow::Result<()> {
+        fs::create_dir_all(&self.dir).await?;
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path_for(run_id))
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// Ordered transitions for `run_id`, or `None` if nothing was recorded.
+    pub async fn load(&self, run_id: &str) -> Option<Vec<RunTimelineEntry>> {
+        if run_id.is_empty() || run_id.contains(['/', '\\']) || run_id.contains("..") {
+            return None;
+        }
+        read_entries(&self.path_for(run_id)).await
+    }
+}
+
+async fn read_entries(path: &Path) -> Option<Vec<RunTimelineEntry>> {
+    let raw = fs::read_to_string(path).await.ok()?;
+    let mut entries = raw
+        .lines()
+        .filter_map(|line| serde_json::from_str::<RunTimelineEntry>(line).ok())
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|entry| entry.seq);
+    Some(entries)
+}
+
+/// Pair `*.started` transitions with their matching end. Spans still open at
+/// the end of the timeline close at the last recorded timestamp.
+pub fn timeline_spans(entries: &[RunTimelineEntry]) -> Vec<RunTimelineSpan> {
+    let span_key = |entry: &RunTimelineEntry| -> String {
+        let part = entry
+            .payload_ref
+            .as_ref()
+            .and_then(|r| r.get("partID"))
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        format!("{}|{part}", entry.label)
+    };
+    let last_ms = entries.last().map(|e| e.at_ms).unwrap_or(0);
+    let mut open: Vec<(String, &RunTimelineEntry)> = Vec::new();
+    let mut spans = Vec::new();
+    let mut close = |start: &RunTimelineEntry, end_ms: u64, kind: &str, status: Option<String>| {
+        spans.push(RunTimelineSpan {
+            kind: kind.to_string(),
+            label: start.label.clone(),
+            start_ms: start.at_ms,
+            end_ms,
+            duration_ms: end_ms.saturating_sub(start.at_ms),
+            status,
+        });
+    };
+    for entry in entries {
+        let (family, phase) = entry.kind.rsplit_once('.').unwrap_or((&entry.kind, ""));
+        match phase {
+            "started" => open.push((span_key(entry), entry)),
+            "finished" | "failed" => {
+                let key = span_key(entry);
+                if let Some(idx) = open
+                    .iter()
+                    .rposition(|(k, start)| *k == key && start.kind.starts_with(family))
+                {
+                    let (_, start) = open.remove(idx);
+                    close(start, entry.at_ms, family, Some(phase.to_string()));
+                } else if family == "run" {
+                    if let Some(idx) = open.iter().position(|(_, s)| s.kind == "run.started") {
+                        let (_, start) = open.remove(idx);
+                        let status = entry
+                            .payload_ref
+                            .as_ref()
+                            .and_then(|r| r.get("status"))
+                            .and_then(|v| v.as_str())
+                            .map(ToString::to_string);
+                        close(start, entry.at_ms, family, status);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    for (_, start) in open {
+        let family = start.kind.trim_end_matches(".started").to_string();
+        close(start, last_ms, &family, None);
+    }
+    spans.sort_by_key(|span| span.start_ms);
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn recorder() -> RunTimelineRecorder {
+        RunTimelineRecorder::new(
+            std::env::temp_dir().join(format!("tandem-timeline-test-{}", uuid::Uuid::new_v4())),
+        )
+    }
+
+    fn tool_part(state: &str) -> EngineEvent {
+        EngineEvent::new(
+            "message.part.updated",
+            json!({
+                "part": {
+                    "id": "part-1",
+                    "sessionID": "s1",
+                    "messageID": "m1",
+                    "type": "tool",
+                    "tool": "read",
+                    "state": state,
+                }
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn records_ordered_transitions_for_active_run() {
+        let recorder = recorder();
+        let events = [
+            EngineEvent::new(
+                "session.run.started",
+                json!({"sessionID": "s1", "runID": "r1"}),
+            ),
+            EngineEvent::new(
+                "session.run.skills_selected",
+                json!({
+                    "sessionID": "s1",
+                    "runID": "r1",
+                    "selection": {
+                        "budgetTokens": 2000,
+                        "usedTokens": 420,
+                        "skills": [{ "name": "release-notes", "mode": "full", "score": 0.9, "tokens": 400 }]
+                    }
+                }),
+            ),
+            EngineEvent::new(
+                "provider.call.started",
+                json!({"sessionID": "s1", "iteration": 1}),
+            ),
+            EngineEvent::new(
+                "provider.call.finished",
+                json!({"sessionID": "s1", "iteration": 1}),
+            ),
+            tool_part("running"),
+            tool_part("completed"),
+            EngineEvent::new(
+                "tool.command_policy",
+                json!({
+                    "sessionID": "s1",
+                    "messageID": "m1",
+                    "command": "rm -rf /",
+                    "outcome": "blocked",
+                    "decision": { "action": "deny", "rule": "rm -r /" }
+                }),
+            ),
+            EngineEvent::new(
+                "session.run.finished",
+                json!({"sessionID": "s1", "runID": "r1", "status": "completed"}),
+            ),
+            tool_part("running"),
+        ];
+        for (i, event) in events.iter().enumerate() {
+            recorder
+                .handle_event(event, 1_000 + i as u64 * 10)
+                .await
+                .expect("record");
+        }
+        let entries = recorder.load("r1").await.expect("timeline");
+        let kinds = entries.iter().map(|e| e.kind.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![
+                "run.started",
+                "skills.selected",
+                "model_turn.started",
+                "model_turn.finished",
+                "tool.started",
+                "tool.finished",
+                "command_policy.blocked",
+                "run.finished",
+            ]
+        );
+
+        assert_eq!(
+            entries[1]
+                .payload_ref
+                .as_ref()
+                .map(|r| r["skills"][0]["mode"].clone()),
+            Some(json!("full"))
+        );
+
+        let spans = timeline_spans(&entries);
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans[0].kind, "run");
+        // Seven events, 10ms apart, from run start to run finish.
+        assert_eq!(spans[0].duration_ms, 70);
+        assert_eq!(spans[0].status.as_deref(), Some("completed"));
+        assert_eq!(spans[2].label, "tool read");
+        assert_eq!(spans[2].duration_ms, 10);
+    }
+
+    #[tokio::test]
+    async fn rejects_path_like_run_ids() {
+        assert!(recorder().load("../secrets").await.is_none());
+    }
+}
@@ -0,0 +1,276 @@
+//! Expiring links to routine run output, for results too sensitive to post
+//! to a chat platform.
+//!
+//! A `channel://` output target with `secure=true` gets a short notice and a
+//! link instead of the report or its file artifacts. The link points at
+//! `{web_ui.public_url}{web_ui_prefix}/share/{token}` and is served by the
+//! Web UI, so it needs the Web UI enabled.
+//!
+//! Each link is bound to the tenant of the run's routine and, optionally, to
+//! one OIDC user. When OIDC is configured the viewer must be signed in with a
+//! matching role or identity; a user-bound link cannot be opened without
+//! OIDC. Only a hash of each token is kept, in `secure_links.json`, so links
+//! survive a restart and stop working when they expire.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::oidc::{OidcSession, UiRole};
+use crate::state_file::JsonStateFile;
+use crate::{now_ms, AppState, WebUiConfig};
+
+pub const DEFAULT_SECURE_LINK_TTL_MS: u64 = 24 * 60 * 60 * 1000;
+pub const MIN_SECURE_LINK_TTL_MS: u64 = 60 * 1000;
+pub const MAX_SECURE_LINK_TTL_MS: u64 = 7 * 24 * 60 * 60 * 1000;
+
+/// What a link opens: a run's final report, or one of its artifacts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecureLink {
+    #[serde(rename = "runID")]
+    pub run_id: String,
+    #[serde(
+        rename = "artifactID",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub artifact_id: Option<String>,
+    /// Tenant of the run's routine; only that tenant (or an admin) may open
+    /// the link.
+    #[serde(rename = "tenantID", default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    /// OIDC user (subject or email) the link is restricted to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    pub created_at_ms: u64,
+    pub expires_at_ms: u64,
+}
+
+impl SecureLink {
+    /// Whether a viewer signed in as `session` (`None` without OIDC) may
+    /// open the link.
+    pub fn allows(&self, session: Option<&OidcSession>) -> bool {
+        let Some(session) = session else {
+            return self.user.is_none();
+        };
+        let role_ok = match &session.role {
+            UiRole::Admin | UiRole::Viewer => true,
+            UiRole::Tenant(tenant_id) => self.tenant_id.as_deref() == Some(tenant_id.as_str()),
+        };
+        let user_ok = self
+            .user
+            .as_deref()
+            .is_none_or(|user| session.subject == user || session.email.as_deref() == Some(user));
+        role_ok && user_ok
+    }
+}
+
+/// Who a new link is for.
+#[derive(Debug, Clone, Default)]
+pub struct SecureLinkScope {
+    pub tenant_id: Option<String>,
+    pub user: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct SecureLinks {
+    file: JsonStateFile,
+    /// Keyed by the SHA-256 of the token.
+    links: Arc<RwLock<HashMap<String, SecureLink>>>,
+}
+
+fn token_hash(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+impl SecureLinks {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            file: JsonStateFile::new(path),
+            links: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.file.set_path(path);
+    }
+
+    pub async fn load(&self) -> anyhow::Result<()> {
+        if let Some(parsed) = self.file.load::<HashMap<String, SecureLink>>().await? {
+            *self.links.write().await = parsed;
+        }
+        Ok(())
+    }
+
+    async fn persist(&self, links: &HashMap<String, SecureLink>) -> anyhow::Result<()> {
+        self.file.save(links).await
+    }
+
+    /// Issue a token for `run_id` (and `artifact_id`) valid for `ttl_ms`,
+    /// clamped to one minute..seven days.
+    pub async fn issue(
+        &self,
+        run_id: &str,
+        artifact_id: Option<&str>,
+        scope: SecureLinkScope,
+        ttl_ms: u64,
+        now: u64,
+    ) -> anyhow::Result<(String, SecureLink)> {
+        let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+        let link = SecureLink {
+            run_id: run_id.to_string(),
+            artifact_id: artifact_id.map(str::to_string),
+            tenant_id: scope.tenant_id,
+            user: scope.user,
+            created_at_ms: now,
+            expires_at_ms: now + ttl_ms.clamp(MIN_SECURE_LINK_TTL_MS, MAX_SECURE_LINK_TTL_MS),
+        };
+        let mut links = self.links.write().await;
+        links.retain(|_, link| link.expires_at_ms > now);
+        links.insert(token_hash(&token), link.clone());
+        self.persist(&links).await?;
+        Ok((token, link))
+    }
+
+    pub async fn resolve(&self, token: &str, now: u64) -> Option<SecureLink> {
+        let key = token_hash(token.trim());
+        let mut links = self.links.write().await;
+        match links.get(&key) {
+            Some(link) if link.expires_at_ms > now => Some(link.clone()),
+            Some(_) => {
+                links.remove(&key);
+                if let Err(err) = self.persist(&links).await {
+                    tracing::warn!("failed to persist secure links: {err:#}");
+                }
+                None
+            }
+            None => None,
+        }
+    }
+}
+
+impl AppState {
+    /// Path of the Web UI page that opens `token`.
+    pub fn secure_link_path(&self, token: &str) -> String {
+        let prefix = self.web_ui_prefix();
+        format!("{}/share/{token}", prefix.trim_end_matches('/'))
+    }
+
+    /// Absolute URL of the page that opens `token`, on `web_ui.public_url`
+    /// when set and the server's own address otherwise.
+    pub async fn secure_link_url(&self, token: &str) -> String {
+        let web_ui: WebUiConfig = self.config_section("web_ui").await;
+        let base = web_ui
+            .public_url
+            .filter(|url| !url.trim().is_empty())
+            .unwrap_or_else(|| self.server_base_url());
+        format!(
+            "{}{}",
+            base.trim().trim_end_matches('/'),
+            self.secure_link_path(token)
+        )
+    }
+
+    pub async fn create_secure_link(
+        &self,
+        run_id: &str,
+        artifact_id: Option<&str>,
+        scope: SecureLinkScope,
+        ttl_ms: Option<u64>,
+    ) -> anyhow::Result<(String, SecureLink)> {
+        self.secure_links
+            .issue(
+                run_id,
+                artifact_id,
+                scope,
+                ttl_ms.unwrap_or(DEFAULT_SECURE_LINK_TTL_MS),
+                now_ms(),
+            )
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store() -> SecureLinks {
+        SecureLinks::new(
+            std::env::temp_dir()
+                .join(format!("tandem-secure-links-{}", Uuid::new_v4()))
+                .join("secure_links.json"),
+        )
+    }
+
+    #[tokio::test]
+    async fn links_resolve_until_they_expire() {
+        let links = store();
+        let (token, link) = links
+            .issue(
+                "run-1",
+                Some("artifact-1"),
+                SecureLinkScope::default(),
+                0,
+                1_000,
+            )
+            .await
+            .expect("issue");
+        assert_eq!(link.expires_at_ms, 1_000 + MIN_SECURE_LINK_TTL_MS);
+        assert_eq!(links.resolve(&token, 2_000).await, Some(link.clone()));
+        assert_eq!(links.resolve("not-a-token", 2_000).await, None);
+
+        // Links survive a restart; only token hashes are stored.
+        let reloaded = SecureLinks::new(links.file.path().to_path_buf());
+        reloaded.load().await.expect("load");
+        assert_eq!(reloaded.resolve(&token, 2_000).await, Some(link.clone()));
+        let raw = std::fs::read_to_string(links.file.path()).expect("links file");
+        assert!(!raw.contains(&token));
+
+        assert_eq!(links.resolve(&token, link.expires_at_ms).await, None);
+        // Expired links are dropped, not just hidden.
+        assert!(links.links.read().await.is_empty());
+
+        let (_, long) = links
+            .issue("run-1", None, SecureLinkScope::default(), u64::MAX, 0)
+            .await
+            .expect("issue");
+        assert_eq!(long.expires_at_ms, MAX_SECURE_LINK_TTL_MS);
+    }
+
+    #[test]
+    fn links_only_open_for_their_tenant_and_user() {
+        let session = |role: UiRole| {
+            let mut session = OidcSession::for_test(role);
+            session.subject = "sub-1".to_string();
+            session.email = Some("ana@example.com".to_string());
+            session
+        };
+        let link = |tenant_id: Option<&str>, user: Option<&str>| SecureLink {
+            run_id: "run-1".to_string(),
+            artifact_id: None,
+            tenant_id: tenant_id.map(str::to_string),
+            user: user.map(str::to_string),
+            created_at_ms: 0,
+            expires_at_ms: 1,
+        };
+        let acme = UiRole::Tenant("acme".to_string());
+        let other = UiRole::Tenant("other".to_string());
+
+        assert!(link(None, None).allows(None));
+        assert!(link(Some("acme"), None).allows(Some(&session(acme.clone()))));
+        assert!(link(Some("acme"), None).allows(Some(&session(UiRole::Admin))));
+        assert!(!link(Some("acme"), None).allows(Some(&session(other))));
+        assert!(!link(None, None).allows(Some(&session(acme.clone()))));
+
+        assert!(link(None, Some("ana@example.com")).allows(Some(&session(UiRole::Admin))));
+        assert!(link(Some("acme"), Some("sub-1")).allows(Some(&session(acme))));
+        assert!(!link(None, Some("bob@example.com")).allows(Some(&session(UiRole::Admin))));
+        assert!(!link(None, Some("ana@example.com")).allows(None));
+    }
+}
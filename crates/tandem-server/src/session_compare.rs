@@ -0,0 +1,382 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tandem_types::{Message, MessagePart, MessageRole, Session};
+
+/// Tools whose invocations change the file named by their `path` argument.
+const FILE_CHANGE_TOOLS: &[&str] = &["write", "edit", "apply_patch", "multiedit"];
+const TURN_TEXT_PREVIEW_CHARS: usize = 280;
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BranchRelationship {
+    /// `base` was forked from `other`.
+    Child,
+    /// `other` was forked from `base`.
+    Parent,
+    /// Both were forked from the same session.
+    Siblings,
+    Unrelated,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchInfo {
+    #[serde(rename = "sessionID")]
+    pub session_id: String,
+    pub title: String,
+    pub message_count: usize,
+    #[serde(rename = "parentID", skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BranchToolCall {
+    pub tool: String,
+    /// Compact rendering of the arguments, for display and matching.
+    pub args: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchTurn {
+    #[serde(rename = "messageID")]
+    pub message_id: String,
+    pub role: MessageRole,
+    pub created_at_ms: i64,
+    pub text: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<BranchToolCall>,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCallComparison {
+    pub only_in_base: Vec<BranchToolCall>,
+    pub only_in_other: Vec<BranchToolCall>,
+    pub in_both: Vec<BranchToolCall>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeStatus {
+    OnlyBase,
+    OnlyOther,
+    Same,
+    Differs,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchFileChange {
+    pub path: String,
+    pub status: FileChangeStatus,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub base_tools: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub other_tools: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedPrefix {
+    pub message_count: usize,
+    #[serde(rename = "lastMessageID", skip_serializing_if = "Option::is_none")]
+    pub last_message_id: Option<String>,
+}
+
+/// Structured comparison of two branches of a conversation.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionBranchComparison {
+    pub base: BranchInfo,
+    pub other: BranchInfo,
+    pub relationship: BranchRelationship,
+    pub shared_prefix: SharedPrefix,
+    pub base_turns: Vec<BranchTurn>,
+    pub other_turns: Vec<BranchTurn>,
+    pub tool_calls: ToolCallComparison,
+    pub file_changes: Vec<BranchFileChange>,
+    pub summary: String,
+}
+
+fn relationship(base: &BranchInfo, other: &BranchInfo) -> BranchRelationship {
+    if base.parent_id.as_deref() == Some(other.session_id.as_str()) {
+        BranchRelationship::Child
+    } else if other.parent_id.as_deref() == Some(base.session_id.as_str()) {
+        BranchRelationship::Parent
+    } else if base.parent_id.is_some() && base.parent_id == other.parent_id {
+        BranchRelationship::Siblings
+    } else {
+        BranchRelationship::Unrelated
+    }
+}
+
+fn compact_args(args: &Value) -> String {
+    let rendered = serde_json::to_string(args).unwrap_or_default();
+    if rendered.chars().count() > 200 {
+        let head = rendered.chars().take(200).collect::<String>();
+        format!("{head}…")
+    } else {
+        rendered
+    }
+}
+
+fn turn_for(message: &Message) -> BranchTurn {
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+    for part in &message.parts {
+        match part {
+            MessagePart::Text { text: t } => {
+                if !text.is_empty() {
+                    text.push('\n');
+                }
+                text.push_str(t);
+            }
+            MessagePart::Reasoning { .. } => {}
+            MessagePart::ToolInvocation { tool, args, .. } => tool_calls.push(BranchToolCall {
+                tool: tool.clone(),
+                args: compact_args(args),
+            }),
+        }
+    }
+    if text.chars().count() > TURN_TEXT_PREVIEW_CHARS {
+        text = format!(
+            "{}…",
+            text.chars()
+                .take(TURN_TEXT_PREVIEW_CHARS)
+                .collect::<String>()
+        );
+    }
+    BranchTurn {
+        message_id: message.id.clone(),
+        role: message.role.clone(),
+        created_at_ms: message.created_at.timestamp_millis(),
+        text,
+        tool_calls,
+    }
+}
+
+/// Last write per path in the given messages: `path -> (tools, fingerprint)`.
+fn file_changes(messages: &[Message]) -> BTreeMap<String, (Vec<String>, String)> {
+    let mut out: BTreeMap<String, (Vec<String>, String)> = BTreeMap::new();
+    for message in messages {
+        for part in &message.parts {
+            let MessagePart::ToolInvocation {
+                tool, args, error, ..
+            } = part
+            else {
+                continue;
+            };
+            if error.is_some() || !FILE_CHANGE_TOOLS.contains(&tool.as_str()) {
+                continue;
+            }
+            let Some(path) = args
+                .get("path")
+                .or_else(|| args.get("filePath"))
+                .and_then(Value::as_str)
+            else {
+                continue;
+            };
+            let mut hasher = Sha256::new();
+            hasher.update(serde_json::to_vec(args).unwrap_or_default());
+            let entry = out.entry(path.to_string()).or_default();
+            if !entry.0.contains(tool) {
+                entry.0.push(tool.clone());
+            }
+            entry.1 = format!("{:x}", hasher.finalize());
+        }
+    }
+    out
+}
+
+fn compare_tool_calls(base: &[BranchTurn], other: &[BranchTurn]) -> ToolCallComparison {
+    let count = |turns: &[BranchTurn]| {
+        let mut counts: HashMap<BranchToolCall, usize> = HashMap::new();
+        for call in turns.iter().flat_map(|t| t.tool_calls.iter()) {
+            *counts.entry(call.clone()).or_default() += 1;
+        }
+        counts
+    };
+    let (base_counts, other_counts) = (count(base), count(other));
+    let mut result = ToolCallComparison::default();
+    for (call, n) in &base_counts {
+        let m = other_counts.get(call).copied().unwrap_or(0);
+        for _ in 0..(*n).min(m) {
+            result.in_both.push(call.clone());
+        }
+        for _ in m..*n {
+            result.only_in_base.push(call.clone());
+        }
+    }
+    for (call, m) in &other_counts {
+        let n = base_counts.get(call).copied().unwrap_or(0);
+        for _ in n..*m {
+            result.only_in_other.push(call.clone());
+        }
+    }
+    result.in_both.sort();
+    result.only_in_base.sort();
+    result.only_in_other.sort();
+    result
+}
+
+/// Compare two sessions that share history (typically a session and its
+/// fork). Messages match by ID, which forks preserve.
+pub fn compare_session_branches(
+    base: &Session,
+    base_parent: Option<String>,
+    other: &Session,
+    other_parent: Option<String>,
+) -> SessionBranchComparison {
+    let base_info = BranchInfo {
+        session_id: base.id.clone(),
+        title: base.title.clone(),
+        message_count: base.messages.len(),
+        parent_id: base_parent,
+    };
+    let other_info = BranchInfo {
+        session_id: other.id.clone(),
+        title: other.title.clone(),
+        message_count: other.messages.len(),
+        parent_id: other_parent,
+    };
+    let shared = base
+        .messages
+        .iter()
+        .zip(other.messages.iter())
+        .take_while(|(a, b)| a.id == b.id)
+        .count();
+    let base_rest = &base.messages[shared..];
+    let other_rest = &other.messages[shared..];
+    let base_turns = base_rest.iter().map(turn_for).collect::<Vec<_>>();
+    let other_turns = other_rest.iter().map(turn_for).collect::<Vec<_>>();
+    let tool_calls = compare_tool_calls(&base_turns, &other_turns);
+
+    let base_files = file_changes(base_rest);
+    let other_files = file_changes(other_rest);
+    let mut paths = base_files
+        .keys()
+        .chain(other_files.keys())
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+    let file_changes = paths
+        .into_iter()
+        .map(|path| {
+            let (b, o) = (base_files.get(path), other_files.get(path));
+            let status = match (b, o) {
+                (Some(b), Some(o)) if b.1 == o.1 => FileChangeStatus::Same,
+                (Some(_), Some(_)) => FileChangeStatus::Differs,
+                (Some(_), None) => FileChangeStatus::OnlyBase,
+                _ => FileChangeStatus::OnlyOther,
+            };
+            BranchFileChange {
+                path: path.clone(),
+                status,
+                base_tools: b.map(|b| b.0.clone()).unwrap_or_default(),
+                other_tools: o.map(|o| o.0.clone()).unwrap_or_default(),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let differing_files = file_changes
+        .iter()
+        .filter(|f| f.status != FileChangeStatus::Same)
+        .count();
+    let summary = if base_turns.is_empty() && other_turns.is_empty() {
+        format!("branches are identical ({shared} shared messages)")
+    } else {
+        format!(
+            "{shared} shared messages; {} vs {} divergent turns, {} vs {} unique tool calls, {differing_files} files differ",
+            base_turns.len(),
+            other_turns.len(),
+            tool_calls.only_in_base.len(),
+            tool_calls.only_in_other.len(),
+        )
+    };
+
+    SessionBranchComparison {
+        relationship: relationship(&base_info, &other_info),
+        base: base_info,
+        other: other_info,
+        shared_prefix: SharedPrefix {
+            message_count: shared,
+            last_message_id: shared
+                .checked_sub(1)
+                .map(|idx| base.messages[idx].id.clone()),
+        },
+        base_turns,
+        other_turns,
+        tool_calls,
+        file_changes,
+        summary,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn text(role: MessageRole, text: &str) -> Message {
+        Message::new(
+            role,
+            vec![MessagePart::Text {
+                text: text.to_string(),
+            }],
+        )
+    }
+
+    fn write(path: &str, content: &str) -> Message {
+        Message::new(
+            MessageRole::Assistant,
+            vec![MessagePart::ToolInvocation {
+                tool: "write".to_string(),
+                args: json!({ "path": path, "content": content }),
+                result: None,
+                error: None,
+            }],
+        )
+    }
+
+    #[test]
+    fn compares_forked_branches() {
+        let mut base = Session::new(Some("plan".to_string()), None);
+        base.messages = vec![
+            text(MessageRole::User, "draft a plan"),
+            text(MessageRole::Assistant, "here is a plan"),
+        ];
+        let mut fork = base.clone();
+        fork.id = "fork-1".to_string();
+
+        base.messages.push(write("plan.md", "v1"));
+        base.messages.push(write("notes.md", "same"));
+        fork.messages.push(write("plan.md", "v2"));
+        fork.messages.push(write("notes.md", "same"));
+        fork.messages
+            .push(text(MessageRole::User, "shorter please"));
+
+        let comparison = compare_session_branches(&base, None, &fork, Some(base.id.clone()));
+        assert_eq!(comparison.relationship, BranchRelationship::Parent);
+        assert_eq!(comparison.shared_prefix.message_count, 2);
+        assert_eq!(comparison.base_turns.len(), 2);
+        assert_eq!(comparison.other_turns.len(), 3);
+        assert_eq!(comparison.tool_calls.in_both.len(), 1);
+        assert_eq!(comparison.tool_calls.only_in_base.len(), 1);
+        let statuses = comparison
+            .file_changes
+            .iter()
+            .map(|f| (f.path.as_str(), f.status))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            vec![
+                ("notes.md", FileChangeStatus::Same),
+                ("plan.md", FileChangeStatus::Differs)
+            ]
+        );
+    }
+}
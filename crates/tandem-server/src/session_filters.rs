@@ -0,0 +1,329 @@
+//! Tag/time/text filters over the session list, and named saved filters.
+//!
+//! A filter's `since`/`until` are kept as written (`7d`, epoch ms) so a saved
+//! "last week" filter stays relative to when it is run. Saved filters are
+//! persisted to `session_filters.json`.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tandem_types::{normalize_session_tag, Session};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::state_file::JsonStateFile;
+
+pub const MAX_FILTER_NAME_CHARS: usize = 120;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SessionFilter {
+    /// Case-insensitive substring of the title or directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub q: Option<String>,
+    /// Tags a session must all carry.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Lower bound on the session's last update: a duration back from now
+    /// (`30m`, `6h`, `7d`, `2w`) or epoch milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+    /// Upper bound on the session's last update, in the same forms.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived: Option<bool>,
+}
+
+/// A filter with `since`/`until` resolved against a point in time.
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedSessionFilter {
+    pub q: Option<String>,
+    pub tags: Vec<String>,
+    pub since_ms: Option<u64>,
+    pub until_ms: Option<u64>,
+    pub archived: Option<bool>,
+}
+
+impl SessionFilter {
+    /// Parse a comma-separated tag list as given in a query string.
+    pub fn parse_tags(raw: &str) -> Vec<String> {
+        raw.split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Layer `other` on top of `self`: tags are combined, every other field
+    /// set in `other` wins.
+    pub fn merged_with(&self, other: &SessionFilter) -> SessionFilter {
+        let mut tags = self.tags.clone();
+        for tag in &other.tags {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
+        SessionFilter {
+            q: other.q.clone().or_else(|| self.q.clone()),
+            tags,
+            since: other.since.clone().or_else(|| self.since.clone()),
+            until: other.until.clone().or_else(|| self.until.clone()),
+            archived: other.archived.or(self.archived),
+        }
+    }
+
+    /// Normalize tags and resolve relative times; errors name the bad field.
+    pub fn resolve(&self, now_ms: u64) -> Result<ResolvedSessionFilter, String> {
+        let mut tags = Vec::new();
+        for raw in &self.tags {
+            let tag = normalize_session_tag(raw).ok_or_else(|| format!("invalid tag `{raw}`"))?;
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        let time = |field: &str, raw: &Option<String>| -> Result<Option<u64>, String> {
+            match raw.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+                Some(value) => tandem_runtime::parse_since_ms(value, now_ms)
+                    .map(Some)
+                    .ok_or_else(|| format!("invalid {field} `{value}`")),
+                None => Ok(None),
+            }
+        };
+        Ok(ResolvedSessionFilter {
+            q: self
+                .q
+                .as_deref()
+                .map(str::trim)
+                .filter(|q| !q.is_empty())
+                .map(str::to_lowercase),
+            tags,
+            since_ms: time("since", &self.since)?,
+            until_ms: time("until", &self.until)?,
+            archived: self.archived,
+        })
+    }
+}
+
+impl ResolvedSessionFilter {
+    /// Whether `session` passes the text, tag and time conditions. `archived`
+    /// lives in session metadata and is checked by the caller.
+    pub fn matches(&self, session: &Session) -> bool {
+        if let Some(q) = self.q.as_deref() {
+            if !session.title.to_lowercase().contains(q)
+                && !session.directory.to_lowercase().contains(q)
+            {
+                return false;
+            }
+        }
+        if !self.tags.iter().all(|tag| session.tags.contains(tag)) {
+            return false;
+        }
+        let updated_ms = session.time.updated.timestamp_millis().max(0) as u64;
+        if self.since_ms.is_some_and(|since| updated_ms < since) {
+            return false;
+        }
+        if self.until_ms.is_some_and(|until| updated_ms > until) {
+            return false;
+        }
+        true
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SavedSessionFilter {
+    pub filter_id: String,
+    pub name: String,
+    pub filter: SessionFilter,
+    /// Tenant that saved the filter; `None` outside tenant isolation mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>,
+    pub created_at_ms: u64,
+}
+
+#[derive(Clone)]
+pub struct SessionFilterStore {
+    file: JsonStateFile,
+    filters: Arc<RwLock<Vec<SavedSessionFilter>>>,
+}
+
+impl SessionFilterStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            file: JsonStateFile::new(path),
+            filters: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.file.set_path(path);
+    }
+
+    /// A file that does not parse is an error, and is then never
+    /// overwritten; see [`JsonStateFile`].
+    pub async fn load(&self) -> anyhow::Result<()> {
+        if let Some(parsed) = self.file.load::<Vec<SavedSessionFilter>>().await? {
+            *self.filters.write().await = parsed;
+        }
+        Ok(())
+    }
+
+    pub async fn persist(&self) -> anyhow::Result<()> {
+        let guard = self.filters.read().await;
+        self.file.save(&*guard).await
+    }
+
+    pub async fn list(&self) -> Vec<SavedSessionFilter> {
+        self.filters.read().await.clone()
+    }
+
+    pub async fn get(&self, filter_id: &str) -> Option<SavedSessionFilter> {
+        self.filters
+            .read()
+            .await
+            .iter()
+            .find(|f| f.filter_id == filter_id)
+            .cloned()
+    }
+
+    /// Save a filter under `name`, replacing an existing filter with the same
+    /// name for the same tenant.
+    pub async fn save(
+        &self,
+        name: &str,
+        filter: SessionFilter,
+        tenant_id: Option<String>,
+        now_ms: u64,
+    ) -> anyhow::Result<SavedSessionFilter> {
+        let name = name
+            .trim()
+            .chars()
+            .take(MAX_FILTER_NAME_CHARS)
+            .collect::<String>();
+        let saved = {
+            let mut guard = self.filters.write().await;
+            match guard
+                .iter_mut()
+                .find(|f| f.name == name && f.tenant_id == tenant_id)
+            {
+                Some(existing) => {
+                    existing.filter = filter;
+                    existing.clone()
+                }
+                None => {
+                    let saved = SavedSessionFilter {
+                        filter_id: format!("sf-{}", Uuid::new_v4().simple()),
+                        name,
+                        filter,
+                        tenant_id,
+                        created_at_ms: now_ms,
+                    };
+                    guard.push(saved.clone());
+                    saved
+                }
+            }
+        };
+        self.persist().await?;
+        Ok(saved)
+    }
+
+    pub async fn delete(&self, filter_id: &str) -> anyhow::Result<bool> {
+        let removed = {
+            let mut guard = self.filters.write().await;
+            let before = guard.len();
+            guard.retain(|f| f.filter_id != filter_id);
+            guard.len() != before
+        };
+        if removed {
+            self.persist().await?;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn session(title: &str, tags: &[&str], age: Duration) -> Session {
+        let mut session = Session::new(Some(title.to_string()), Some("/repo".to_string()));
+        session.tags = tags.iter().map(|t| t.to_string()).collect();
+        session.time.updated = Utc::now() - age;
+        session
+    }
+
+    #[test]
+    fn filter_requires_every_tag_and_respects_time_window() {
+        let filter = SessionFilter {
+            tags: vec!["Agent:Release-Bot".to_string(), "run:failed".to_string()],
+            since: Some("7d".to_string()),
+            ..SessionFilter::default()
+        }
+        .resolve(Utc::now().timestamp_millis() as u64)
+        .expect("resolve");
+
+        assert!(filter.matches(&session(
+            "nightly",
+            &["routine", "agent:release-bot", "run:failed"],
+            Duration::days(2)
+        )));
+        assert!(!filter.matches(&session(
+            "nightly",
+            &["agent:release-bot", "run:completed"],
+            Duration::days(2)
+        )));
+        assert!(!filter.matches(&session(
+            "old",
+            &["agent:release-bot", "run:failed"],
+            Duration::days(9)
+        )));
+    }
+
+    #[test]
+    fn query_params_layer_over_saved_filter() {
+        let saved = SessionFilter {
+            tags: vec!["routine".to_string()],
+            since: Some("7d".to_string()),
+            ..SessionFilter::default()
+        };
+        let merged = saved.merged_with(&SessionFilter {
+            tags: SessionFilter::parse_tags("run:failed, routine"),
+            since: Some("1d".to_string()),
+            ..SessionFilter::default()
+        });
+        assert_eq!(merged.tags, vec!["routine", "run:failed"]);
+        assert_eq!(merged.since.as_deref(), Some("1d"));
+        assert!(SessionFilter {
+            since: Some("soon".to_string()),
+            ..SessionFilter::default()
+        }
+        .resolve(0)
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn corrupt_filter_file_is_not_replaced() {
+        let path = std::env::temp_dir()
+            .join(format!("tandem-filters-{}", Uuid::new_v4()))
+            .join("session_filters.json");
+        let store = SessionFilterStore::new(path.clone());
+        store
+            .save("nightly", SessionFilter::default(), None, 1)
+            .await
+            .expect("save");
+
+        std::fs::write(&path, "[{\"filter_id\":").expect("truncate");
+        let reloaded = SessionFilterStore::new(path.clone());
+        assert!(reloaded.load().await.is_err());
+        assert!(reloaded
+            .save("weekly", SessionFilter::default(), None, 2)
+            .await
+            .is_err());
+        assert_eq!(
+            std::fs::read_to_string(&path).expect("read"),
+            "[{\"filter_id\":"
+        );
+        let _ = std::fs::remove_dir_all(path.parent().expect("dir"));
+    }
+}
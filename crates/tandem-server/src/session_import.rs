@@ -0,0 +1,831 @@
+//! Importing transcripts from other agent CLIs as Tandem sessions.
+//!
+//! `POST /session/import` takes the raw session file of another tool and
+//! converts it into a regular session, so the history stays searchable and
+//! can be consolidated into memory like any other conversation. Supported
+//! formats:
+//!
+//! - `claude_code`: the JSONL files under `~/.claude/projects/<project>/`.
+//! - `codex`: Codex CLI rollout JSONL (`~/.codex/sessions/...`), both the
+//!   `session_meta`/`response_item` envelope and the older bare item lines.
+//! - `aider`: `.aider.chat.history.md`.
+//!
+//! Tool calls are mapped onto Tandem's built-in tools where an equivalent
+//! exists (`Bash` → `bash`, `Read` → `read`, ...) with their arguments
+//! renamed to match; other tools keep their original name and arguments.
+//! Tool results are attached to the call they answer.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tandem_types::{Message, MessagePart, MessageRole};
+
+/// Largest transcript accepted in one import.
+pub const MAX_IMPORT_BYTES: usize = 20 * 1024 * 1024;
+const TITLE_CHARS: usize = 80;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    #[default]
+    Auto,
+    ClaudeCode,
+    Codex,
+    Aider,
+}
+
+impl ImportFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::ClaudeCode => "claude_code",
+            Self::Codex => "codex",
+            Self::Aider => "aider",
+        }
+    }
+
+    fn filename(self) -> &'static str {
+        match self {
+            Self::Aider => "aider.chat.history.md",
+            Self::Codex => "codex-rollout.jsonl",
+            _ => "claude-code-session.jsonl",
+        }
+    }
+
+    /// MIME type of the original transcript when it is kept as an attachment.
+    pub fn mime(self) -> &'static str {
+        match self {
+            Self::Aider => "text/markdown",
+            _ => "application/x-ndjson",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionImportInput {
+    #[serde(default)]
+    pub format: ImportFormat,
+    /// The transcript file, verbatim.
+    pub content: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Overrides the working directory recorded in the transcript.
+    #[serde(default)]
+    pub workspace_root: Option<String>,
+    /// Name for the attached original; defaults to one per format.
+    #[serde(default)]
+    pub filename: Option<String>,
+    /// Keep the original transcript as a session attachment (default true).
+    #[serde(default = "default_true")]
+    pub attach_original: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl SessionImportInput {
+    pub fn attachment_name(&self, format: ImportFormat) -> String {
+        self.filename
+            .as_deref()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .unwrap_or(format.filename())
+            .to_string()
+    }
+}
+
+/// A transcript converted to Tandem messages.
+#[derive(Debug, Clone)]
+pub struct ImportedTranscript {
+    pub format: ImportFormat,
+    pub title: Option<String>,
+    pub directory: Option<String>,
+    /// The other tool's id for the session, when recorded.
+    pub source_id: Option<String>,
+    pub messages: Vec<Message>,
+    pub tool_calls: usize,
+    /// Tool names with no Tandem equivalent, kept as-is.
+    pub unmapped_tools: Vec<String>,
+    /// Lines that could not be read and were left out.
+    pub skipped_lines: usize,
+}
+
+impl ImportedTranscript {
+    fn new(format: ImportFormat) -> Self {
+        Self {
+            format,
+            title: None,
+            directory: None,
+            source_id: None,
+            messages: Vec::new(),
+            tool_calls: 0,
+            unmapped_tools: Vec::new(),
+            skipped_lines: 0,
+        }
+    }
+
+    /// The given title, the transcript's own, or the first user message.
+    pub fn session_title(&self, requested: Option<&str>) -> String {
+        let first_user = || {
+            self.messages
+                .iter()
+                .filter(|m| matches!(m.role, MessageRole::User))
+                .flat_map(|m| m.parts.iter())
+                .find_map(|part| match part {
+                    MessagePart::Text { text } => text.lines().find(|l| !l.trim().is_empty()),
+                    _ => None,
+                })
+                .map(|line| crate::clip(line.trim(), TITLE_CHARS))
+        };
+        requested
+            .map(str::trim)
+            .filter(|title| !title.is_empty())
+            .map(str::to_string)
+            .or_else(|| self.title.clone())
+            .or_else(first_user)
+            .unwrap_or_else(|| format!("Imported {} session", self.format.as_str()))
+    }
+
+    fn push(&mut self, role: MessageRole, parts: Vec<MessagePart>, at: Option<DateTime<Utc>>) {
+        if parts.is_empty() {
+            return;
+        }
+        let mut message = Message::new(role, parts);
+        if let Some(at) = at {
+            message.created_at = at;
+        }
+        message.metadata = Some(json!({ "sessionImport": { "format": self.format.as_str() } }));
+        self.messages.push(message);
+    }
+
+    /// Append parts to the trailing assistant message, or start one.
+    fn push_assistant_parts(&mut self, parts: Vec<MessagePart>, at: Option<DateTime<Utc>>) {
+        match self.messages.last_mut() {
+            Some(last) if matches!(last.role, MessageRole::Assistant) => last.parts.extend(parts),
+            _ => self.push(MessageRole::Assistant, parts, at),
+        }
+    }
+
+    fn tool_call(
+        &mut self,
+        name: &str,
+        args: Value,
+        mapping: fn(&str) -> Option<&'static str>,
+    ) -> MessagePart {
+        self.tool_calls += 1;
+        let (tool, args) = match mapping(name) {
+            Some(tool) => (tool.to_string(), map_tool_args(tool, args)),
+            None => {
+                if !self.unmapped_tools.iter().any(|t| t == name) {
+                    self.unmapped_tools.push(name.to_string());
+                }
+                (name.to_string(), args)
+            }
+        };
+        MessagePart::ToolInvocation {
+            tool,
+            args,
+            result: None,
+            error: None,
+        }
+    }
+
+    /// Set the result of the call at `at` (message index, part index).
+    fn resolve_tool(&mut self, at: (usize, usize), output: Value, error: Option<String>) {
+        let part = self
+            .messages
+            .get_mut(at.0)
+            .and_then(|message| message.parts.get_mut(at.1));
+        if let Some(MessagePart::ToolInvocation {
+            result, error: err, ..
+        }) = part
+        {
+            *result = Some(output);
+            *err = error;
+        }
+    }
+
+    /// Position of the last part of the last message.
+    fn last_part(&self) -> Option<(usize, usize)> {
+        let index = self.messages.len().checked_sub(1)?;
+        let part = self.messages[index].parts.len().checked_sub(1)?;
+        Some((index, part))
+    }
+}
+
+/// Guess the format from the content.
+pub fn detect_format(content: &str) -> Option<ImportFormat> {
+    let trimmed = content.trim_start();
+    if trimmed.starts_with("# aider chat started at") || trimmed.starts_with("#### ") {
+        return Some(ImportFormat::Aider);
+    }
+    for line in trimmed.lines().take(20) {
+        let Ok(value) = serde_json::from_str::<Value>(line) else {
+            continue;
+        };
+        let kind = value.get("type").and_then(Value::as_str).unwrap_or("");
+        if matches!(
+            kind,
+            "session_meta" | "response_item" | "turn_context" | "event_msg"
+        ) || (value.get("instructions").is_some() && value.get("id").is_some())
+        {
+            return Some(ImportFormat::Codex);
+        }
+        if value.get("sessionId").is_some() || value.get("parentUuid").is_some() {
+            return Some(ImportFormat::ClaudeCode);
+        }
+        if matches!(
+            kind,
+            "message" | "function_call" | "function_call_output" | "reasoning"
+        ) {
+            return Some(ImportFormat::Codex);
+        }
+    }
+    None
+}
+
+/// Convert `content` in `format` (or the detected format for `auto`).
+pub fn parse_transcript(format: ImportFormat, content: &str) -> Result<ImportedTranscript, String> {
+    if content.trim().is_empty() {
+        return Err("transcript is empty".to_string());
+    }
+    if content.len() > MAX_IMPORT_BYTES {
+        return Err(format!(
+            "transcript is {} bytes; at most {MAX_IMPORT_BYTES} are accepted",
+            content.len()
+        ));
+    }
+    let format = match format {
+        ImportFormat::Auto => detect_format(content)
+            .ok_or_else(|| "could not detect the transcript format".to_string())?,
+        format => format,
+    };
+    let transcript = match format {
+        ImportFormat::ClaudeCode => parse_claude_code(content),
+        ImportFormat::Codex => parse_codex(content),
+        _ => parse_aider(content),
+    };
+    if transcript.messages.is_empty() {
+        return Err(format!(
+            "no messages found in the {} transcript",
+            format.as_str()
+        ));
+    }
+    Ok(transcript)
+}
+
+fn parse_claude_code(content: &str) -> ImportedTranscript {
+    let mut out = ImportedTranscript::new(ImportFormat::ClaudeCode);
+    let mut pending: HashMap<String, (usize, usize)> = HashMap::new();
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        let Ok(entry) = serde_json::from_str::<Value>(line) else {
+            out.skipped_lines += 1;
+            continue;
+        };
+        let text = |key: &str| entry.get(key).and_then(Value::as_str).map(str::to_string);
+        if out.source_id.is_none() {
+            out.source_id = text("sessionId");
+        }
+        if out.directory.is_none() {
+            out.directory = text("cwd");
+        }
+        let kind = text("type").unwrap_or_default();
+        if kind == "summary" {
+            out.title = out.title.take().or_else(|| text("summary"));
+            continue;
+        }
+        if !matches!(kind.as_str(), "user" | "assistant")
+            || entry.get("isMeta").and_then(Value::as_bool) == Some(true)
+        {
+            continue;
+        }
+        let at = text("timestamp").and_then(|ts| parse_timestamp(&ts));
+        let message = entry.get("message").cloned().unwrap_or(Value::Null);
+        let blocks = match message.get("content") {
+            Some(Value::String(text)) => vec![json!({ "type": "text", "text": text })],
+            Some(Value::Array(blocks)) => blocks.clone(),
+            _ => Vec::new(),
+        };
+        if kind == "user" {
+            let mut parts = Vec::new();
+            for block in &blocks {
+                match block.get("type").and_then(Value::as_str) {
+                    Some("text") => push_text(&mut parts, block.get("text")),
+                    Some("tool_result") => {
+                        let id = block.get("tool_use_id").and_then(Value::as_str);
+                        let Some(at) = id.and_then(|id| pending.remove(id)) else {
+                            continue;
+                        };
+                        let output = block_text(block.get("content"));
+                        let error = (block.get("is_error").and_then(Value::as_bool) == Some(true))
+                            .then(|| output.clone());
+                        out.resolve_tool(at, Value::String(output), error);
+                    }
+                    _ => {}
+                }
+            }
+            out.push(MessageRole::User, parts, at);
+            continue;
+        }
+        // Responses are written one content block per line, and tool results
+        // come back as user lines; all of it until the next real user message
+        // becomes one assistant message, as in a Tandem run.
+        for block in &blocks {
+            let part = match block.get("type").and_then(Value::as_str) {
+                Some("text") => {
+                    let mut parts = Vec::new();
+                    push_text(&mut parts, block.get("text"));
+                    parts.pop()
+                }
+                Some("thinking") => block
+                    .get("thinking")
+                    .and_then(Value::as_str)
+                    .filter(|t| !t.trim().is_empty())
+                    .map(|t| MessagePart::Reasoning {
+                        text: t.to_string(),
+                    }),
+                Some("tool_use") => {
+                    let name = block.get("name").and_then(Value::as_str).unwrap_or("tool");
+                    let input = block.get("input").cloned().unwrap_or_else(|| json!({}));
+                    let part = out.tool_call(name, input, claude_tool);
+                    out.push_assistant_parts(vec![part], at);
+                    if let (Some(id), Some(pos)) =
+                        (block.get("id").and_then(Value::as_str), out.last_part())
+                    {
+                        pending.insert(id.to_string(), pos);
+                    }
+                    None
+                }
+                _ => None,
+            };
+            if let Some(part) = part {
+                out.push_assistant_parts(vec![part], at);
+            }
+        }
+    }
+    out
+}
+
+fn parse_codex(content: &str) -> ImportedTranscript {
+    let mut out = ImportedTranscript::new(ImportFormat::Codex);
+    let mut pending: HashMap<String, (usize, usize)> = HashMap::new();
+    for line in content.lines().filter(|l| !l.trim().is_empty()) {
+        let Ok(entry) = serde_json::from_str::<Value>(line) else {
+            out.skipped_lines += 1;
+            continue;
+        };
+        let at = entry
+            .get("timestamp")
+            .and_then(Value::as_str)
+            .and_then(parse_timestamp);
+        let item = match entry.get("type").and_then(Value::as_str) {
+            Some("session_meta") => {
+                let payload = entry.get("payload").unwrap_or(&Value::Null);
+                let text = |key: &str| payload.get(key).and_then(Value::as_str).map(str::to_string);
+                out.source_id = out.source_id.take().or_else(|| text("id"));
+                out.directory = out.directory.take().or_else(|| text("cwd"));
+                continue;
+            }
+            Some("response_item") => entry.get("payload").cloned().unwrap_or(Value::Null),
+            Some(_) => entry,
+            // Older rollouts start with a bare `{id, timestamp, instructions}` header.
+            None => {
+                if out.source_id.is_none() {
+                    out.source_id = entry.get("id").and_then(Value::as_str).map(str::to_string);
+                }
+                continue;
+            }
+        };
+        match item.get("type").and_then(Value::as_str) {
+            Some("message") => {
+                let role = match item.get("role").and_then(Value::as_str) {
+                    Some("user") => MessageRole::User,
+                    Some("assistant") => MessageRole::Assistant,
+                    _ => continue,
+                };
+                let mut parts = Vec::new();
+                for block in item
+                    .get("content")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    let text = block.get("text").and_then(Value::as_str).unwrap_or("");
+                    // Codex injects its environment and AGENTS.md as user messages.
+                    let injected = ["<environment_context>", "<user_instructions>"]
+                        .iter()
+                        .any(|tag| text.trim_start().starts_with(tag));
+                    if !injected {
+                        push_text(&mut parts, block.get("text"));
+                    }
+                }
+                if matches!(role, MessageRole::Assistant) {
+                    out.push_assistant_parts(parts, at);
+                } else {
+                    out.push(role, parts, at);
+                }
+            }
+            Some("reasoning") => {
+                let summary = item
+                    .get("summary")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|s| s.get("text").and_then(Value::as_str))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                if !summary.trim().is_empty() {
+                    out.push_assistant_parts(vec![MessagePart::Reasoning { text: summary }], at);
+                }
+            }
+            Some(kind @ ("function_call" | "custom_tool_call" | "local_shell_call")) => {
+                let (name, args) = match kind {
+                    "local_shell_call" => (
+                        "shell".to_string(),
+                        json!({ "command": item.pointer("/action/command").cloned().unwrap_or(Value::Null) }),
+                    ),
+                    "custom_tool_call" => (
+                        item.get("name")
+                            .and_then(Value::as_str)
+                            .unwrap_or("tool")
+                            .to_string(),
+                        json!({ "input": item.get("input").cloned().unwrap_or(Value::Null) }),
+                    ),
+                    _ => {
+                        let raw = item
+                            .get("arguments")
+                            .and_then(Value::as_str)
+                            .unwrap_or("{}");
+                        (
+                            item.get("name")
+                                .and_then(Value::as_str)
+                                .unwrap_or("tool")
+                                .to_string(),
+                            serde_json::from_str(raw).unwrap_or_else(|_| json!({ "input": raw })),
+                        )
+                    }
+                };
+                let (name, args) = codex_args(name, args);
+                let part = out.tool_call(&name, args, codex_tool);
+                out.push_assistant_parts(vec![part], at);
+                let call_id = item
+                    .get("call_id")
+                    .or_else(|| item.get("id"))
+                    .and_then(Value::as_str);
+                if let (Some(id), Some(pos)) = (call_id, out.last_part()) {
+                    pending.insert(id.to_string(), pos);
+                }
+            }
+            Some("function_call_output" | "custom_tool_call_output") => {
+                let Some(at) = item
+                    .get("call_id")
+                    .and_then(Value::as_str)
+                    .and_then(|id| pending.remove(id))
+                else {
+                    continue;
+                };
+                let (output, error) = codex_output(item.get("output"));
+                out.resolve_tool(at, Value::String(output), error);
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Codex wraps shell output as `{"output": ..., "metadata": {"exit_code": ..}}`.
+fn codex_output(raw: Option<&Value>) -> (String, Option<String>) {
+    let text = match raw {
+        Some(Value::String(text)) => text.clone(),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    };
+    let Ok(wrapped) = serde_json::from_str::<Value>(&text) else {
+        return (text, None);
+    };
+    let Some(output) = wrapped.get("output").and_then(Value::as_str) else {
+        return (text, None);
+    };
+    let error = wrapped
+        .pointer("/metadata/exit_code")
+        .and_then(Value::as_i64)
+        .filter(|code| *code != 0)
+        .map(|code| format!("exit code {code}"));
+    (output.to_string(), error)
+}
+
+/// Shell calls carry an argv; Tandem's `bash` takes one command string, and
+/// patches arrive as the shell call `["apply_patch", "<patch>"]`.
+fn codex_args(name: String, args: Value) -> (String, Value) {
+    if !matches!(name.as_str(), "shell" | "container.exec") {
+        return (name, args);
+    }
+    let argv = args
+        .get("command")
+        .and_then(Value::as_array)
+        .map(|argv| {
+            argv.iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if let [tool, patch] = argv.as_slice() {
+        if tool == "apply_patch" {
+            return (tool.clone(), json!({ "patchText": patch }));
+        }
+    }
+    let command = match argv.as_slice() {
+        [shell, flag, script]
+            if matches!(shell.as_str(), "bash" | "sh" | "zsh")
+                && matches!(flag.as_str(), "-c" | "-lc") =>
+        {
+            script.clone()
+        }
+        _ => argv.join(" "),
+    };
+    let mut mapped = Map::new();
+    mapped.insert("command".to_string(), Value::String(command));
+    if let Some(workdir) = args.get("workdir") {
+        mapped.insert("workdir".to_string(), workdir.clone());
+    }
+    (name, Value::Object(mapped))
+}
+
+fn parse_aider(content: &str) -> ImportedTranscript {
+    let mut out = ImportedTranscript::new(ImportFormat::Aider);
+    let mut user: Vec<&str> = Vec::new();
+    let mut assistant: Vec<&str> = Vec::new();
+    let mut started: Option<DateTime<Utc>> = None;
+
+    fn flush(
+        out: &mut ImportedTranscript,
+        lines: &mut Vec<&str>,
+        role: MessageRole,
+        at: Option<DateTime<Utc>>,
+    ) {
+        let text = lines.join("\n");
+        lines.clear();
+        let mut parts = Vec::new();
+        push_text(&mut parts, Some(&Value::String(text)));
+        if matches!(role, MessageRole::Assistant) {
+            out.push_assistant_parts(parts, at);
+        } else {
+            out.push(role, parts, at);
+        }
+    }
+
+    for line in content.lines() {
+        if let Some(stamp) = line.strip_prefix("# aider chat started at ") {
+            flush(&mut out, &mut user, MessageRole::User, started);
+            flush(&mut out, &mut assistant, MessageRole::Assistant, started);
+            started = chrono::NaiveDateTime::parse_from_str(stamp.trim(), "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .map(|naive| naive.and_utc());
+            continue;
+        }
+        if let Some(text) = line.strip_prefix("####") {
+            flush(&mut out, &mut assistant, MessageRole::Assistant, started);
+            user.push(text.strip_prefix(' ').unwrap_or(text));
+            continue;
+        }
+        flush(&mut out, &mut user, MessageRole::User, started);
+        // `> ` lines are aider's own console output; only edits and commands
+        // it ran are worth keeping.
+        if let Some(note) = line.strip_prefix('>') {
+            let note = note.trim();
+            let call = if let Some(path) = note.strip_prefix("Applied edit to ") {
+                Some(("edit", json!({ "path": path.trim() })))
+            } else {
+                note.strip_prefix("Running ")
+                    .map(|command| ("bash", json!({ "command": command.trim() })))
+            };
+            if let Some((tool, args)) = call {
+                flush(&mut out, &mut assistant, MessageRole::Assistant, started);
+                out.tool_calls += 1;
+                out.push_assistant_parts(
+                    vec![MessagePart::ToolInvocation {
+                        tool: tool.to_string(),
+                        args,
+                        result: None,
+                        error: None,
+                    }],
+                    started,
+                );
+            }
+            continue;
+        }
+        assistant.push(line);
+    }
+    flush(&mut out, &mut user, MessageRole::User, started);
+    flush(&mut out, &mut assistant, MessageRole::Assistant, started);
+    out
+}
+
+fn claude_tool(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "Bash" => "bash",
+        "Read" => "read",
+        "Write" => "write",
+        "Edit" | "MultiEdit" => "edit",
+        "Glob" => "glob",
+        "Grep" => "grep",
+        "WebFetch" => "webfetch",
+        "WebSearch" => "websearch",
+        "TodoWrite" => "todo_write",
+        "Task" => "task",
+        _ => return None,
+    })
+}
+
+fn codex_tool(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "shell" | "container.exec" => "bash",
+        "apply_patch" => "apply_patch",
+        "update_plan" => "todo_write",
+        _ => return None,
+    })
+}
+
+/// Rename the other tools' argument keys to the Tandem tool's.
+fn map_tool_args(tool: &str, args: Value) -> Value {
+    let Value::Object(mut map) = args else {
+        return args;
+    };
+    let renames: &[(&str, &str)] = match tool {
+        "read" | "write" | "glob" | "grep" => &[("file_path", "path")],
+        "edit" => &[
+            ("file_path", "path"),
+            ("old_string", "old"),
+            ("new_string", "new"),
+        ],
+        "apply_patch" => &[("input", "patchText")],
+        _ => &[],
+    };
+    for (from, to) in renames {
+        if let Some(value) = map.remove(*from) {
+            map.entry(to.to_string()).or_insert(value);
+        }
+    }
+    Value::Object(map)
+}
+
+fn push_text(parts: &mut Vec<MessagePart>, text: Option<&Value>) {
+    if let Some(text) = text.and_then(Value::as_str).map(str::trim) {
+        if !text.is_empty() {
+            parts.push(MessagePart::Text {
+                text: text.to_string(),
+            });
+        }
+    }
+}
+
+/// Tool result content: a string or a list of text blocks.
+fn block_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|b| b.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Some(other) => other.to_string(),
+        None => String::new(),
+    }
+}
+
+fn parse_timestamp(raw: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(raw)
+        .ok()
+        .map(|at| at.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool_parts(transcript: &ImportedTranscript) -> Vec<(String, Value, Option<Value>)> {
+        transcript
+            .messages
+            .iter()
+            .flat_map(|m| m.parts.iter())
+            .filter_map(|part| match part {
+                MessagePart::ToolInvocation {
+                    tool, args, result, ..
+                } => Some((tool.clone(), args.clone(), result.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn claude_code_tool_calls_are_mapped_and_resolved() {
+        let content = [
+            json!({"type":"summary","summary":"Fix the flaky test"}),
+            json!({"type":"user","sessionId":"abc","cwd":"/work/app","timestamp":"2025-05-01T10:00:00Z",
+                   "message":{"role":"user","content":"why does the test fail?"}}),
+            json!({"type":"assistant","sessionId":"abc","message":{"id":"m1","role":"assistant",
+                   "content":[{"type":"text","text":"Let me look."}]}}),
+            json!({"type":"assistant","sessionId":"abc","message":{"id":"m1","role":"assistant",
+                   "content":[{"type":"tool_use","id":"t1","name":"Read","input":{"file_path":"src/lib.rs"}}]}}),
+            json!({"type":"user","sessionId":"abc","message":{"role":"user",
+                   "content":[{"type":"tool_result","tool_use_id":"t1","content":"fn main() {}"}]}}),
+            json!({"type":"assistant","sessionId":"abc","message":{"id":"m2","role":"assistant",
+                   "content":[{"type":"tool_use","id":"t2","name":"mcp__jira__search","input":{"q":"x"}}]}}),
+        ]
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+        assert_eq!(detect_format(&content), Some(ImportFormat::ClaudeCode));
+        let transcript = parse_transcript(ImportFormat::Auto, &content).expect("transcript");
+        assert_eq!(transcript.source_id.as_deref(), Some("abc"));
+        assert_eq!(transcript.directory.as_deref(), Some("/work/app"));
+        assert_eq!(transcript.session_title(None), "Fix the flaky test");
+        assert_eq!(transcript.messages.len(), 2);
+        let tools = tool_parts(&transcript);
+        assert_eq!(tools[0].0, "read");
+        assert_eq!(tools[0].1, json!({"path": "src/lib.rs"}));
+        assert_eq!(tools[0].2, Some(json!("fn main() {}")));
+        assert_eq!(transcript.unmapped_tools, vec!["mcp__jira__search"]);
+    }
+
+    #[test]
+    fn codex_rollouts_map_shell_calls_and_skip_injected_context() {
+        let content = [
+            json!({"timestamp":"2025-06-01T09:00:00Z","type":"session_meta","payload":{"id":"r1","cwd":"/repo"}}),
+            json!({"type":"response_item","payload":{"type":"message","role":"user",
+                   "content":[{"type":"input_text","text":"<environment_context>cwd</environment_context>"}]}}),
+            json!({"type":"response_item","payload":{"type":"message","role":"user",
+                   "content":[{"type":"input_text","text":"run the tests"}]}}),
+            json!({"type":"response_item","payload":{"type":"function_call","name":"shell","call_id":"c1",
+                   "arguments":"{\"command\":[\"bash\",\"-lc\",\"cargo test\"],\"workdir\":\"/repo\"}"}}),
+            json!({"type":"response_item","payload":{"type":"function_call_output","call_id":"c1",
+                   "output":"{\"output\":\"1 failed\",\"metadata\":{\"exit_code\":101}}"}}),
+            json!({"type":"response_item","payload":{"type":"message","role":"assistant",
+                   "content":[{"type":"output_text","text":"One test fails."}]}}),
+        ]
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+        assert_eq!(detect_format(&content), Some(ImportFormat::Codex));
+        let transcript = parse_transcript(ImportFormat::Codex, &content).expect("transcript");
+        assert_eq!(transcript.source_id.as_deref(), Some("r1"));
+        assert_eq!(transcript.messages.len(), 2);
+        assert_eq!(transcript.session_title(None), "run the tests");
+        let MessagePart::ToolInvocation {
+            tool,
+            args,
+            result,
+            error,
+        } = &transcript.messages[1].parts[0]
+        else {
+            panic!("expected a tool call");
+        };
+        assert_eq!(tool, "bash");
+        assert_eq!(args, &json!({"command": "cargo test", "workdir": "/repo"}));
+        assert_eq!(result, &Some(json!("1 failed")));
+        assert_eq!(error.as_deref(), Some("exit code 101"));
+    }
+
+    #[test]
+    fn aider_history_splits_turns_and_keeps_edits() {
+        let content = "# aider chat started at 2025-03-04 12:30:00\n\n\
+                       > Add src/app.py to the chat? (Y)es/(N)o [Yes]: y\n\n\
+                       #### add a --verbose flag\n\n\
+                       I'll add the flag.\n\n\
+                       > Applied edit to src/app.py\n\
+                       > Commit 1a2b3c feat: add --verbose\n\n\
+                       #### thanks\n";
+        assert_eq!(detect_format(content), Some(ImportFormat::Aider));
+        let transcript = parse_transcript(ImportFormat::Auto, content).expect("transcript");
+        let roles = transcript
+            .messages
+            .iter()
+            .map(|m| &m.role)
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            roles.as_slice(),
+            [MessageRole::User, MessageRole::Assistant, MessageRole::User]
+        ));
+        assert_eq!(
+            tool_parts(&transcript),
+            vec![("edit".to_string(), json!({"path": "src/app.py"}), None)]
+        );
+        assert_eq!(
+            transcript.messages[0].created_at.to_rfc3339(),
+            "2025-03-04T12:30:00+00:00"
+        );
+    }
+
+    #[test]
+    fn rejects_empty_and_unrecognized_content() {
+        assert!(parse_transcript(ImportFormat::Auto, "  ").is_err());
+        assert!(parse_transcript(ImportFormat::Auto, "just some notes").is_err());
+        assert!(parse_transcript(ImportFormat::ClaudeCode, "{\"type\":\"other\"}").is_err());
+    }
+}
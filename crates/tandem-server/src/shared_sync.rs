@@ -0,0 +1,1122 @@
+//! Org-wide sync of skills, routines and agent profiles from a git repo.
+//!
+//! When the `shared_sync` config block is enabled, the engine fetches
+//! `repo` (read-only; nothing is ever pushed) every `interval_secs` and
+//! applies its contents:
+//!
+//! ```text
+//! skills/<name>/SKILL.md   -> global skills directory
+//! agents/<name>.md         -> agent profiles (.tandem/agent)
+//! routines/<id>.json       -> routines, as POST /routines bodies
+//! ```
+//!
+//! Every applied item is recorded in `shared-sync/manifest.json` under the
+//! state directory with the hash of what was written. On the next sync an
+//! item whose local copy no longer matches that hash has drifted: it was
+//! edited or deleted locally, or (without a manifest entry) it already
+//! existed before the sync managed it. `conflict_policy` decides whether
+//! drifted items keep their local version (`keep_local`, the default) or are
+//! replaced by upstream (`overwrite`). Items removed upstream are removed
+//! locally unless they drifted. Drift is reported by `GET
+//! /global/shared-sync/drift` without changing anything.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tandem_types::EngineEvent;
+use tokio::process::Command;
+use tokio::sync::Mutex;
+
+use crate::item_names::valid_item_name;
+use crate::object_store::sha256_hex;
+use crate::{now_ms, AppState, RoutineSpec};
+
+const MANIFEST_FILE: &str = "manifest.json";
+const CHECKOUT_DIR: &str = "checkout";
+/// Fields of a routine that belong to this engine, not to its definition.
+const LOCAL_ROUTINE_FIELDS: [&str; 6] = [
+    "status",
+    "next_fire_at_ms",
+    "last_fired_at_ms",
+    "creator_type",
+    "creator_id",
+    "tenant_id",
+];
+
+/// `shared_sync` config block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedSyncConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Git URL or local path of the shared repository.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    /// Branch to follow; the remote's default branch when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    #[serde(default)]
+    pub conflict_policy: SyncConflictPolicy,
+    /// Overrides the global skills directory as the target for `skills/`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skills_dir: Option<PathBuf>,
+    /// Overrides `.tandem/agent` as the target for `agents/`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agents_dir: Option<PathBuf>,
+}
+
+impl Default for SharedSyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            repo: None,
+            branch: None,
+            interval_secs: default_interval_secs(),
+            conflict_policy: SyncConflictPolicy::default(),
+            skills_dir: None,
+            agents_dir: None,
+        }
+    }
+}
+
+fn default_interval_secs() -> u64 {
+    60 * 60
+}
+
+impl SharedSyncConfig {
+    /// The repository to sync, `None` when sync is disabled.
+    pub fn repo(&self) -> Result<Option<&str>, String> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        let repo = self
+            .repo
+            .as_deref()
+            .map(str::trim)
+            .filter(|repo| !repo.is_empty())
+            .ok_or_else(|| "shared_sync needs a `repo`".to_string())?;
+        if repo.starts_with('-') {
+            return Err("shared_sync `repo` must be a URL or path".to_string());
+        }
+        if self.branch.as_deref().is_some_and(|b| !valid_branch(b)) {
+            return Err("shared_sync `branch` must be a branch name".to_string());
+        }
+        Ok(Some(repo))
+    }
+}
+
+/// What happens to an item that drifted from what the sync last applied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncConflictPolicy {
+    #[default]
+    KeepLocal,
+    Overwrite,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncItemKind {
+    Skill,
+    Agent,
+    Routine,
+}
+
+impl SyncItemKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Skill => "skill",
+            Self::Agent => "agent",
+            Self::Routine => "routine",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncAction {
+    Create,
+    Update,
+    Unchanged,
+    /// Removed upstream and unchanged locally.
+    Remove,
+    /// Drifted; the local version is kept.
+    KeepLocal,
+    /// Drifted; replaced by the upstream version.
+    Overwrite,
+    /// Removed both upstream and locally; only the manifest entry goes.
+    Forget,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncDrift {
+    LocallyModified,
+    LocallyDeleted,
+    /// Existed locally before the sync managed it.
+    Unmanaged,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPlanItem {
+    pub kind: SyncItemKind,
+    pub name: String,
+    pub action: SyncAction,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drift: Option<SyncDrift>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedSyncReport {
+    pub commit: String,
+    /// False for a drift check, which changes nothing.
+    pub applied: bool,
+    pub items: Vec<SyncPlanItem>,
+    pub created: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub drifted: usize,
+    /// Upstream files that could not be read or applied.
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedSyncStatus {
+    pub enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_sync_at_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_success_at_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    pub syncs: u64,
+    pub managed: usize,
+    pub drifted: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ManifestEntry {
+    kind: SyncItemKind,
+    name: String,
+    upstream_sha256: String,
+    /// Hash of the local copy right after it was written.
+    applied_sha256: String,
+    applied_at_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SyncManifest {
+    #[serde(default)]
+    commit: Option<String>,
+    #[serde(default)]
+    entries: Vec<ManifestEntry>,
+}
+
+/// Shared sync state; one sync or drift check runs at a time.
+#[derive(Clone, Default)]
+pub struct SharedSyncHandle {
+    inner: Arc<Mutex<SharedSyncStatus>>,
+}
+
+impl SharedSyncHandle {
+    pub async fn status(&self) -> SharedSyncStatus {
+        self.inner.lock().await.clone()
+    }
+}
+
+/// Decide what to do with one item from its upstream hash, the hash of its
+/// local copy and its manifest entry.
+fn plan_item(
+    upstream: Option<&str>,
+    local: Option<&str>,
+    entry: Option<&ManifestEntry>,
+    policy: SyncConflictPolicy,
+) -> (SyncAction, Option<SyncDrift>) {
+    let drift = match (entry, local) {
+        (None, Some(local)) if upstream != Some(local) => Some(SyncDrift::Unmanaged),
+        (Some(_), None) => Some(SyncDrift::LocallyDeleted),
+        (Some(entry), Some(local)) if local != entry.applied_sha256 => {
+            Some(SyncDrift::LocallyModified)
+        }
+        _ => None,
+    };
+    let action = match (upstream, drift) {
+        (Some(_), Some(_)) => match policy {
+            SyncConflictPolicy::KeepLocal => SyncAction::KeepLocal,
+            SyncConflictPolicy::Overwrite => SyncAction::Overwrite,
+        },
+        (Some(upstream), None) => match (entry, local) {
+            (None, None) => SyncAction::Create,
+            (None, Some(_)) => SyncAction::Unchanged,
+            (Some(entry), _) if entry.upstream_sha256 == upstream => SyncAction::Unchanged,
+            (Some(_), _) => SyncAction::Update,
+        },
+        (None, _) if local.is_none() => SyncAction::Forget,
+        (None, None) => SyncAction::Remove,
+        (None, Some(_)) => match policy {
+            SyncConflictPolicy::KeepLocal => SyncAction::KeepLocal,
+            SyncConflictPolicy::Overwrite => SyncAction::Remove,
+        },
+    };
+    (action, drift)
+}
+
+/// One skill, agent profile or routine found in the checkout.
+#[derive(Debug, Clone)]
+struct UpstreamItem {
+    kind: SyncItemKind,
+    name: String,
+    sha256: String,
+    source: PathBuf,
+    routine: Option<RoutineSpec>,
+}
+
+/// Local targets for skills and agent profiles.
+#[derive(Debug, Clone)]
+struct SyncTargets {
+    skills_dir: PathBuf,
+    agents_dir: PathBuf,
+}
+
+impl SyncTargets {
+    fn path(&self, kind: SyncItemKind, name: &str) -> Option<PathBuf> {
+        match kind {
+            SyncItemKind::Skill => Some(self.skills_dir.join(name)),
+            SyncItemKind::Agent => Some(self.agents_dir.join(format!("{name}.md"))),
+            SyncItemKind::Routine => None,
+        }
+    }
+}
+
+/// A branch or tag name git would accept, which can't be read as an option.
+fn valid_branch(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(['-', '/', '.'])
+        && !name.ends_with(['/', '.'])
+        && !name.ends_with(".lock")
+        && !name.contains("..")
+        && !name.contains("//")
+        && !name.contains("@{")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'))
+}
+
+/// Whether `path` itself is a symlink. The checkout is untrusted, so links
+/// are never followed out of it, and local targets are never written
+/// through one.
+fn is_symlink(path: &Path) -> bool {
+    std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_symlink())
+}
+
+/// Hash of a routine's definition, ignoring engine-local fields.
+fn routine_sha256(routine: &RoutineSpec) -> String {
+    let mut value = serde_json::to_value(routine).unwrap_or_default();
+    if let Some(map) = value.as_object_mut() {
+        for field in LOCAL_ROUTINE_FIELDS {
+            map.remove(field);
+        }
+    }
+    sha256_hex(value.to_string().as_bytes())
+}
+
+/// Build a routine from a `routines/<id>.json` file, filling the same
+/// defaults as `POST /routines`.
+fn routine_from_upstream(file_stem: &str, value: Value) -> Result<RoutineSpec, String> {
+    let Value::Object(mut map) = value else {
+        return Err("routine file must be a JSON object".to_string());
+    };
+    for field in LOCAL_ROUTINE_FIELDS {
+        map.remove(field);
+    }
+    map.entry("routine_id").or_insert_with(|| json!(file_stem));
+    map.insert("status".to_string(), json!("active"));
+    map.insert("creator_type".to_string(), json!("shared_sync"));
+    map.insert("creator_id".to_string(), json!("shared_sync"));
+    map.entry("timezone").or_insert_with(|| json!("UTC"));
+    map.entry("misfire_policy")
+        .or_insert_with(|| json!({ "type": "run_once" }));
+    map.entry("args").or_insert_with(|| json!({}));
+    map.entry("requires_approval").or_insert(json!(true));
+    map.entry("external_integrations_allowed")
+        .or_insert(json!(false));
+    let routine: RoutineSpec =
+        serde_json::from_value(Value::Object(map)).map_err(|err| err.to_string())?;
+    if !valid_item_name(&routine.routine_id) {
+        return Err(format!("invalid routine_id `{}`", routine.routine_id));
+    }
+    Ok(routine)
+}
+
+/// Sorted `(relative path, absolute path)` of every regular file under
+/// `dir`; symlinks are skipped.
+fn dir_files(dir: &Path) -> Vec<(String, PathBuf)> {
+    if is_symlink(dir) {
+        return Vec::new();
+    }
+    let mut files = ignore::WalkBuilder::new(dir)
+        .standard_filters(false)
+        .follow_links(false)
+        .build()
+        .flatten()
+        .filter(|entry| {
+            !entry.path_is_symlink() && entry.file_type().is_some_and(|ft| ft.is_file())
+        })
+        .filter_map(|entry| {
+            let relative = entry
+                .path()
+                .strip_prefix(dir)
+                .ok()?
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            Some((relative, entry.into_path()))
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+/// Hash over the relative paths and contents of every file in `dir`.
+fn dir_sha256(dir: &Path) -> Option<String> {
+    if !dir.is_dir() || is_symlink(dir) {
+        return None;
+    }
+    let mut listing = String::new();
+    for (relative, path) in dir_files(dir) {
+        let bytes = std::fs::read(path).ok()?;
+        listing.push_str(&format!("{relative}\0{}\n", sha256_hex(&bytes)));
+    }
+    Some(sha256_hex(listing.as_bytes()))
+}
+
+fn file_sha256(path: &Path) -> Option<String> {
+    if is_symlink(path) {
+        return None;
+    }
+    std::fs::read(path).ok().map(|bytes| sha256_hex(&bytes))
+}
+
+fn local_sha256(kind: SyncItemKind, path: &Path) -> Option<String> {
+    match kind {
+        SyncItemKind::Skill => dir_sha256(path),
+        _ => file_sha256(path),
+    }
+}
+
+/// Skills, agent profiles and routines in a checkout, plus the files that
+/// could not be read.
+fn read_checkout(checkout: &Path) -> (Vec<UpstreamItem>, Vec<String>) {
+    let mut items = Vec::new();
+    let mut errors = Vec::new();
+    let entries = |dir: &str| {
+        let mut paths = std::fs::read_dir(checkout.join(dir))
+            .map(|rd| rd.flatten().map(|e| e.path()).collect::<Vec<_>>())
+            .unwrap_or_default();
+        paths.sort();
+        paths
+    };
+    let stem = |path: &Path| {
+        path.file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default()
+    };
+
+    for path in entries("skills") {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if !path.join("SKILL.md").is_file() || !valid_item_name(&name) {
+            continue;
+        }
+        if let Some(sha256) = dir_sha256(&path) {
+            items.push(UpstreamItem {
+                kind: SyncItemKind::Skill,
+                name,
+                sha256,
+                source: path,
+                routine: None,
+            });
+        }
+    }
+    for path in entries("agents") {
+        let name = stem(&path);
+        if path.extension().and_then(|e| e.to_str()) != Some("md") || !valid_item_name(&name) {
+            continue;
+        }
+        if let Some(sha256) = file_sha256(&path) {
+            items.push(UpstreamItem {
+                kind: SyncItemKind::Agent,
+                name,
+                sha256,
+                source: path,
+                routine: None,
+            });
+        }
+    }
+    for path in entries("routines") {
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|raw| serde_json::from_str::<Value>(&raw).map_err(|err| err.to_string()))
+            .and_then(|value| routine_from_upstream(&stem(&path), value));
+        match parsed {
+            Ok(routine) => items.push(UpstreamItem {
+                kind: SyncItemKind::Routine,
+                name: routine.routine_id.clone(),
+                sha256: routine_sha256(&routine),
+                source: path,
+                routine: Some(routine),
+            }),
+            Err(err) => errors.push(format!(
+                "routines/{}: {err}",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            )),
+        }
+    }
+    (items, errors)
+}
+
+fn copy_dir(src: &Path, dst: &Path) -> std::io::Result<()> {
+    if is_symlink(dst) {
+        std::fs::remove_file(dst)?;
+    } else if dst.exists() {
+        std::fs::remove_dir_all(dst)?;
+    }
+    for (relative, path) in dir_files(src) {
+        let target = dst.join(relative);
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(path, target)?;
+    }
+    Ok(())
+}
+
+async fn git(dir: Option<&Path>, args: &[&str]) -> anyhow::Result<String> {
+    let mut command = Command::new("git");
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command
+        .args(args)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .await
+        .context("failed to run git")?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Clone or fast-forward the read-only checkout; returns the commit.
+async fn fetch_checkout(
+    repo: &str,
+    branch: Option<&str>,
+    checkout: &Path,
+) -> anyhow::Result<String> {
+    if repo.starts_with('-') || branch.is_some_and(|branch| !valid_branch(branch)) {
+        bail!("invalid shared_sync repo or branch");
+    }
+    let origin = if checkout.join(".git").exists() {
+        git(Some(checkout), &["remote", "get-url", "origin"])
+            .await
+            .ok()
+    } else {
+        None
+    };
+    if origin.as_deref() == Some(repo) {
+        git(
+            Some(checkout),
+            &[
+                "fetch",
+                "--depth",
+                "1",
+                "--",
+                "origin",
+                branch.unwrap_or("HEAD"),
+            ],
+        )
+        .await?;
+        git(Some(checkout), &["reset", "--hard", "FETCH_HEAD"]).await?;
+        git(Some(checkout), &["clean", "-fdx"]).await?;
+    } else {
+        if checkout.exists() {
+            tokio::fs::remove_dir_all(checkout).await?;
+        }
+        if let Some(parent) = checkout.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let checkout_arg = checkout.to_string_lossy().to_string();
+        let mut args = vec!["clone".to_string(), "--depth".to_string(), "1".to_string()];
+        if let Some(branch) = branch {
+            args.push(format!("--branch={branch}"));
+        }
+        args.extend(["--".to_string(), repo.to_string(), checkout_arg]);
+        git(None, &args.iter().map(String::as_str).collect::<Vec<_>>()).await?;
+    }
+    git(Some(checkout), &["rev-parse", "HEAD"]).await
+}
+
+/// An item of the plan with what is needed to apply it.
+struct PlannedItem {
+    item: SyncPlanItem,
+    upstream: Option<UpstreamItem>,
+    local_sha256: Option<String>,
+}
+
+impl AppState {
+    /// Effective `shared_sync` config block.
+    pub async fn shared_sync_config(&self) -> SharedSyncConfig {
+        self.config_section("shared_sync").await
+    }
+
+    fn shared_sync_dir(&self) -> PathBuf {
+        self.state_dir.join("shared-sync")
+    }
+
+    fn shared_sync_targets(&self, config: &SharedSyncConfig) -> SyncTargets {
+        SyncTargets {
+            skills_dir: config.skills_dir.clone().unwrap_or_else(|| {
+                tandem_skills::SkillService::for_workspace(None)
+                    .global_write_root()
+                    .to_path_buf()
+            }),
+            agents_dir: config
+                .agents_dir
+                .clone()
+                .unwrap_or_else(|| self.agents.agents_dir().to_path_buf()),
+        }
+    }
+
+    async fn load_sync_manifest(&self) -> SyncManifest {
+        match tokio::fs::read(self.shared_sync_dir().join(MANIFEST_FILE)).await {
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+            Err(_) => SyncManifest::default(),
+        }
+    }
+
+    async fn save_sync_manifest(&self, manifest: &SyncManifest) -> anyhow::Result<()> {
+        let dir = self.shared_sync_dir();
+        tokio::fs::create_dir_all(&dir).await?;
+        let path = dir.join(MANIFEST_FILE);
+        let tmp = path.with_extension("partial");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(manifest)?).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(())
+    }
+
+    /// Compare the checkout with local items and the manifest.
+    async fn plan_shared_sync(
+        &self,
+        config: &SharedSyncConfig,
+        targets: &SyncTargets,
+        manifest: &SyncManifest,
+    ) -> anyhow::Result<(Vec<PlannedItem>, Vec<String>)> {
+        let checkout = self.shared_sync_dir().join(CHECKOUT_DIR);
+        let (upstream, errors) =
+            tokio::task::spawn_blocking(move || read_checkout(&checkout)).await?;
+        let mut keys = upstream
+            .iter()
+            .map(|item| (item.kind, item.name.clone()))
+            .collect::<Vec<_>>();
+        for entry in &manifest.entries {
+            if !keys.contains(&(entry.kind, entry.name.clone())) {
+                keys.push((entry.kind, entry.name.clone()));
+            }
+        }
+
+        let paths = keys
+            .iter()
+            .map(|(kind, name)| (*kind, targets.path(*kind, name)))
+            .collect::<Vec<_>>();
+        let file_hashes = tokio::task::spawn_blocking(move || {
+            paths
+                .into_iter()
+                .map(|(kind, path)| path.and_then(|path| local_sha256(kind, &path)))
+                .collect::<Vec<_>>()
+        })
+        .await?;
+
+        let mut planned = Vec::new();
+        for ((kind, name), file_hash) in keys.into_iter().zip(file_hashes) {
+            let local = match kind {
+                SyncItemKind::Routine => self.get_routine(&name).await.map(|r| routine_sha256(&r)),
+                _ => file_hash,
+            };
+            let upstream_item = upstream
+                .iter()
+                .find(|item| item.kind == kind && item.name == name)
+                .cloned();
+            let entry = manifest
+                .entries
+                .iter()
+                .find(|entry| entry.kind == kind && entry.name == name);
+            let (action, drift) = plan_item(
+                upstream_item.as_ref().map(|item| item.sha256.as_str()),
+                local.as_deref(),
+                entry,
+                config.conflict_policy,
+            );
+            planned.push(PlannedItem {
+                item: SyncPlanItem {
+                    kind,
+                    name,
+                    action,
+                    drift,
+                    error: None,
+                },
+                upstream: upstream_item,
+                local_sha256: local,
+            });
+        }
+        Ok((planned, errors))
+    }
+
+    /// Write one upstream item; returns the hash of the local copy.
+    async fn apply_sync_item(
+        &self,
+        targets: &SyncTargets,
+        upstream: &UpstreamItem,
+    ) -> Result<String, String> {
+        if let Some(routine) = &upstream.routine {
+            let mut routine = routine.clone();
+            if let Some(local) = self.get_routine(&routine.routine_id).await {
+                routine.status = local.status;
+                routine.next_fire_at_ms = local.next_fire_at_ms;
+                routine.last_fired_at_ms = local.last_fired_at_ms;
+                routine.tenant_id = local.tenant_id;
+            }
+            let stored = self
+                .put_routine(routine)
+                .await
+                .map_err(|err| format!("{err:?}"))?;
+            return Ok(routine_sha256(&stored));
+        }
+        let target = targets
+            .path(upstream.kind, &upstream.name)
+            .ok_or_else(|| "no target".to_string())?;
+        let source = upstream.source.clone();
+        let kind = upstream.kind;
+        tokio::task::spawn_blocking(move || {
+            if let Some(parent) = target.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            match kind {
+                SyncItemKind::Skill => copy_dir(&source, &target)?,
+                _ => {
+                    if is_symlink(&source) {
+                        return Err(std::io::Error::other("upstream file is a symlink"));
+                    }
+                    if is_symlink(&target) {
+                        std::fs::remove_file(&target)?;
+                    }
+                    std::fs::copy(&source, &target)?;
+                }
+            }
+            Ok::<_, std::io::Error>(local_sha256(kind, &target).unwrap_or_default())
+        })
+        .await
+        .map_err(|err| err.to_string())?
+        .map_err(|err| err.to_string())
+    }
+
+    async fn remove_sync_item(
+        &self,
+        targets: &SyncTargets,
+        kind: SyncItemKind,
+        name: &str,
+    ) -> Result<(), String> {
+        match targets.path(kind, name) {
+            None => self
+                .delete_routine(name)
+                .await
+                .map(|_| ())
+                .map_err(|err| format!("{err:?}")),
+            Some(path) if kind == SyncItemKind::Skill => tokio::fs::remove_dir_all(path)
+                .await
+                .map_err(|err| err.to_string()),
+            Some(path) => tokio::fs::remove_file(path)
+                .await
+                .map_err(|err| err.to_string()),
+        }
+    }
+
+    /// Fetch the shared repo and apply it. `Ok(None)` when sync is disabled.
+    pub async fn run_shared_sync(&self) -> anyhow::Result<Option<SharedSyncReport>> {
+        let config = self.shared_sync_config().await;
+        let mut status = self.shared_sync.inner.lock().await;
+        let repo = match config.repo() {
+            Ok(Some(repo)) => repo.to_string(),
+            Ok(None) => {
+                status.enabled = false;
+                return Ok(None);
+            }
+            Err(error) => {
+                status.enabled = false;
+                status.last_error = Some(error.clone());
+                bail!(error);
+            }
+        };
+        status.enabled = true;
+        status.last_sync_at_ms = Some(now_ms());
+        let result = self.sync_locked(&config, &repo).await;
+        match &result {
+            Ok(report) => {
+                status.commit = Some(report.commit.clone());
+                status.last_success_at_ms = status.last_sync_at_ms;
+                status.last_error = None;
+                status.syncs += 1;
+                status.managed = self.load_sync_manifest().await.entries.len();
+                status.drifted = report.drifted;
+                if report.created + report.updated + report.removed > 0 || report.drifted > 0 {
+                    self.event_bus.publish(EngineEvent::new(
+                        "shared_sync.applied",
+                        json!({
+                            "commit": report.commit,
+                            "created": report.created,
+                            "updated": report.updated,
+                            "removed": report.removed,
+                            "drifted": report.drifted,
+                        }),
+                    ));
+                }
+            }
+            Err(err) => {
+                status.last_error = Some(format!("{err:#}"));
+                self.event_bus.publish(EngineEvent::new(
+                    "shared_sync.failed",
+                    json!({ "error": format!("{err:#}") }),
+                ));
+            }
+        }
+        result.map(Some)
+    }
+
+    async fn sync_locked(
+        &self,
+        config: &SharedSyncConfig,
+        repo: &str,
+    ) -> anyhow::Result<SharedSyncReport> {
+        let checkout = self.shared_sync_dir().join(CHECKOUT_DIR);
+        let commit = fetch_checkout(repo, config.branch.as_deref(), &checkout).await?;
+        let targets = self.shared_sync_targets(config);
+        let manifest = self.load_sync_manifest().await;
+        let (planned, errors) = self.plan_shared_sync(config, &targets, &manifest).await?;
+
+        let mut entries = manifest
+            .entries
+            .iter()
+            .map(|entry| ((entry.kind, entry.name.clone()), entry.clone()))
+            .collect::<HashMap<_, _>>();
+        let mut report = SharedSyncReport {
+            commit: commit.clone(),
+            applied: true,
+            errors,
+            ..SharedSyncReport::default()
+        };
+        let mut agents_changed = false;
+        for mut planned_item in planned {
+            let item = &mut planned_item.item;
+            let key = (item.kind, item.name.clone());
+            let outcome = match (item.action, &planned_item.upstream) {
+                (SyncAction::Create | SyncAction::Update | SyncAction::Overwrite, Some(up)) => {
+                    self.apply_sync_item(&targets, up).await.map(|applied| {
+                        entries.insert(
+                            key,
+                            ManifestEntry {
+                                kind: up.kind,
+                                name: up.name.clone(),
+                                upstream_sha256: up.sha256.clone(),
+                                applied_sha256: applied,
+                                applied_at_ms: now_ms(),
+                            },
+                        );
+                        true
+                    })
+                }
+                (SyncAction::Unchanged, Some(up)) => {
+                    // Adopt an identical local copy the sync did not write.
+                    entries.entry(key).or_insert_with(|| ManifestEntry {
+                        kind: up.kind,
+                        name: up.name.clone(),
+                        upstream_sha256: up.sha256.clone(),
+                        applied_sha256: planned_item.local_sha256.clone().unwrap_or_default(),
+                        applied_at_ms: now_ms(),
+                    });
+                    Ok(false)
+                }
+                (SyncAction::Remove, _) => self
+                    .remove_sync_item(&targets, item.kind, &item.name)
+                    .await
+                    .map(|_| {
+                        entries.remove(&key);
+                        true
+                    }),
+                // Kept local changes of an item removed upstream become
+                // unmanaged.
+                (SyncAction::KeepLocal | SyncAction::Forget, None) => {
+                    entries.remove(&key);
+                    Ok(false)
+                }
+                _ => Ok(false),
+            };
+            match outcome {
+                Ok(changed) => {
+                    agents_changed |= changed && item.kind == SyncItemKind::Agent;
+                    match item.action {
+                        SyncAction::Create => report.created += 1,
+                        SyncAction::Update | SyncAction::Overwrite => report.updated += 1,
+                        SyncAction::Remove => report.removed += 1,
+                        _ => {}
+                    }
+                }
+                Err(error) => {
+                    report
+                        .errors
+                        .push(format!("{} {}: {error}", item.kind.as_str(), item.name));
+                    item.error = Some(error);
+                }
+            }
+            if item.drift.is_some() {
+                report.drifted += 1;
+            }
+            report.items.push(planned_item.item);
+        }
+        if agents_changed {
+            self.agents.reload().await?;
+        }
+
+        let mut entries = entries.into_values().collect::<Vec<_>>();
+        entries.sort_by(|a, b| (a.kind.as_str(), &a.name).cmp(&(b.kind.as_str(), &b.name)));
+        self.save_sync_manifest(&SyncManifest {
+            commit: Some(commit),
+            entries,
+        })
+        .await?;
+        Ok(report)
+    }
+
+    /// Compare local items with the last fetched checkout without changing
+    /// anything. `Ok(None)` when sync is disabled.
+    pub async fn shared_sync_drift(&self) -> anyhow::Result<Option<SharedSyncReport>> {
+        let config = self.shared_sync_config().await;
+        if config.repo().map_err(anyhow::Error::msg)?.is_none() {
+            return Ok(None);
+        }
+        let _guard = self.shared_sync.inner.lock().await;
+        let manifest = self.load_sync_manifest().await;
+        let Some(commit) = manifest.commit.clone() else {
+            bail!("the shared repository has not been synced yet");
+        };
+        let targets = self.shared_sync_targets(&config);
+        let (planned, errors) = self.plan_shared_sync(&config, &targets, &manifest).await?;
+        let items = planned
+            .into_iter()
+            .map(|planned| planned.item)
+            .collect::<Vec<_>>();
+        Ok(Some(SharedSyncReport {
+            commit,
+            applied: false,
+            drifted: items.iter().filter(|item| item.drift.is_some()).count(),
+            items,
+            errors,
+            ..SharedSyncReport::default()
+        }))
+    }
+}
+
+/// Background task: sync the shared repository every `interval_secs`.
+pub async fn run_shared_sync_loop(state: AppState) {
+    // Initial delay so startup is not impacted.
+    tokio::time::sleep(Duration::from_secs(45)).await;
+    loop {
+        if let Err(err) = state.run_shared_sync().await {
+            tracing::warn!("shared sync failed: {err:#}");
+        }
+        let interval = state.shared_sync_config().await.interval_secs.max(60);
+        tokio::time::sleep(Duration::from_secs(interval)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(upstream: &str, applied: &str) -> ManifestEntry {
+        ManifestEntry {
+            kind: SyncItemKind::Skill,
+            name: "lint".to_string(),
+            upstream_sha256: upstream.to_string(),
+            applied_sha256: applied.to_string(),
+            applied_at_ms: 0,
+        }
+    }
+
+    #[test]
+    fn plan_follows_upstream_until_local_copies_drift() {
+        use SyncAction::*;
+        let keep = SyncConflictPolicy::KeepLocal;
+        let overwrite = SyncConflictPolicy::Overwrite;
+        let synced = entry("u1", "l1");
+
+        assert_eq!(plan_item(Some("u1"), None, None, keep), (Create, None));
+        assert_eq!(
+            plan_item(Some("u1"), Some("u1"), None, keep),
+            (Unchanged, None)
+        );
+        assert_eq!(
+            plan_item(Some("u1"), Some("l1"), Some(&synced), keep),
+            (Unchanged, None)
+        );
+        assert_eq!(
+            plan_item(Some("u2"), Some("l1"), Some(&synced), keep),
+            (Update, None)
+        );
+        assert_eq!(
+            plan_item(Some("u2"), Some("edited"), Some(&synced), keep),
+            (KeepLocal, Some(SyncDrift::LocallyModified))
+        );
+        assert_eq!(
+            plan_item(Some("u2"), Some("edited"), Some(&synced), overwrite),
+            (Overwrite, Some(SyncDrift::LocallyModified))
+        );
+        assert_eq!(
+            plan_item(Some("u1"), None, Some(&synced), keep),
+            (KeepLocal, Some(SyncDrift::LocallyDeleted))
+        );
+        assert_eq!(
+            plan_item(Some("u1"), Some("mine"), None, keep),
+            (KeepLocal, Some(SyncDrift::Unmanaged))
+        );
+
+        // Removed upstream.
+        assert_eq!(
+            plan_item(None, Some("l1"), Some(&synced), keep),
+            (Remove, None)
+        );
+        assert_eq!(
+            plan_item(None, Some("edited"), Some(&synced), keep),
+            (KeepLocal, Some(SyncDrift::LocallyModified))
+        );
+        assert_eq!(
+            plan_item(None, Some("edited"), Some(&synced), overwrite),
+            (Remove, Some(SyncDrift::LocallyModified))
+        );
+        assert_eq!(
+            plan_item(None, None, Some(&synced), keep),
+            (Forget, Some(SyncDrift::LocallyDeleted))
+        );
+    }
+
+    #[test]
+    fn routine_files_get_create_defaults_and_ignore_local_fields() {
+        let routine = routine_from_upstream(
+            "nightly-digest",
+            json!({
+                "name": "Nightly digest",
+                "schedule": { "interval_seconds": { "seconds": 86_400 } },
+                "entrypoint": "mission.default",
+                "status": "paused",
+                "tenant_id": "acme",
+            }),
+        )
+        .expect("routine");
+        assert_eq!(routine.routine_id, "nightly-digest");
+        assert_eq!(routine.creator_type, "shared_sync");
+        assert!(routine.requires_approval);
+        assert_eq!(routine.tenant_id, None);
+
+        let mut local = routine.clone();
+        local.status = crate::RoutineStatus::Paused;
+        local.next_fire_at_ms = Some(42);
+        assert_eq!(routine_sha256(&local), routine_sha256(&routine));
+        local.entrypoint = "mission.other".to_string();
+        assert_ne!(routine_sha256(&local), routine_sha256(&routine));
+
+        assert!(routine_from_upstream("x", json!([])).is_err());
+        assert!(routine_from_upstream("x", json!({ "routine_id": "../x" })).is_err());
+        assert!(!valid_item_name("../etc"));
+    }
+
+    #[test]
+    fn branches_that_read_as_options_or_bad_refs_are_rejected() {
+        for branch in ["main", "release/1.2", "feature_x-2"] {
+            assert!(valid_branch(branch), "{branch}");
+        }
+        for branch in [
+            "",
+            "-upload-pack=touch /tmp/x",
+            "--help",
+            "a..b",
+            "a//b",
+            "x.lock",
+            "HEAD@{1}",
+            "a b",
+        ] {
+            assert!(!valid_branch(branch), "{branch}");
+        }
+        let config = SharedSyncConfig {
+            enabled: true,
+            repo: Some("https://example.com/shared.git".to_string()),
+            branch: Some("--upload-pack=evil".to_string()),
+            ..Default::default()
+        };
+        assert!(config.repo().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn checkout_symlinks_are_not_followed() {
+        let root = std::env::temp_dir().join(format!("shared-sync-{}", uuid::Uuid::new_v4()));
+        let outside = root.join("outside");
+        let checkout = root.join("checkout");
+        std::fs::create_dir_all(&outside).expect("outside");
+        std::fs::write(outside.join("secret"), "secret").expect("secret");
+        std::fs::write(outside.join("SKILL.md"), "# outside").expect("outside skill");
+        let skill = checkout.join("skills").join("lint");
+        std::fs::create_dir_all(&skill).expect("skill");
+        std::fs::write(skill.join("SKILL.md"), "# lint").expect("skill md");
+        std::os::unix::fs::symlink(outside.join("secret"), skill.join("leak")).expect("link");
+        let agents = checkout.join("agents");
+        std::fs::create_dir_all(&agents).expect("agents");
+        std::os::unix::fs::symlink(outside.join("secret"), agents.join("spy.md")).expect("link");
+        std::os::unix::fs::symlink(&outside, checkout.join("skills").join("linked")).expect("link");
+
+        let files = dir_files(&skill)
+            .into_iter()
+            .map(|(relative, _)| relative)
+            .collect::<Vec<_>>();
+        assert_eq!(files, vec!["SKILL.md".to_string()]);
+        assert!(file_sha256(&agents.join("spy.md")).is_none());
+        let (items, _) = read_checkout(&checkout);
+        let names = items
+            .iter()
+            .map(|item| item.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["lint"]);
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,464 @@
+//! Connected `/event` SSE clients.
+//!
+//! Every connection registers a client with its own filter, which can be
+//! changed through `PUT /event/clients/{id}/filter` without reconnecting. The
+//! forwarding task drops clients that stop reading (or keep falling behind the
+//! bus) and reports them with an `sse.client.dropped` event instead of letting
+//! them lag the broadcast. A client is unregistered as soon as it disconnects.
+//! Admins see every client; other callers only see and update their own.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tandem_core::EventBus;
+use tandem_types::EngineEvent;
+
+use crate::event_coalesce::{StreamConsumer, StreamDropReason};
+use crate::oidc::UiRole;
+use crate::quotas::AuthenticatedUser;
+use crate::tenants::TenantScope;
+
+const DEFAULT_STALL_MS: u64 = 15_000;
+const DEFAULT_MAX_LAG: u64 = 512;
+const DEFAULT_RETRY_MS: u64 = 3_000;
+const DEFAULT_KEEPALIVE_SECS: u64 = 10;
+const MAX_FILTER_TYPES: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct SseSettings {
+    /// How long a full client buffer may go unread before the client is dropped.
+    pub stall_ms: u64,
+    /// Events a client may miss from the bus in a row, with no delivery in
+    /// between, before it is dropped.
+    pub max_lag: u64,
+    /// Reconnection delay suggested to clients through the SSE `retry:` field.
+    pub retry_ms: u64,
+    pub keepalive_secs: u64,
+}
+
+impl Default for SseSettings {
+    fn default() -> Self {
+        Self {
+            stall_ms: DEFAULT_STALL_MS,
+            max_lag: DEFAULT_MAX_LAG,
+            retry_ms: DEFAULT_RETRY_MS,
+            keepalive_secs: DEFAULT_KEEPALIVE_SECS,
+        }
+    }
+}
+
+impl SseSettings {
+    /// Settings from `TANDEM_SSE_STALL_MS`, `TANDEM_SSE_MAX_LAG`,
+    /// `TANDEM_SSE_RETRY_MS` and `TANDEM_SSE_KEEPALIVE_SECS`.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let parse = |key: &str, default: u64| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(default)
+        };
+        Self {
+            stall_ms: parse("TANDEM_SSE_STALL_MS", defaults.stall_ms),
+            max_lag: parse("TANDEM_SSE_MAX_LAG", defaults.max_lag),
+            retry_ms: parse("TANDEM_SSE_RETRY_MS", defaults.retry_ms),
+            keepalive_secs: parse("TANDEM_SSE_KEEPALIVE_SECS", defaults.keepalive_secs),
+        }
+    }
+}
+
+/// Which bus events a client receives.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SseFilter {
+    #[serde(rename = "sessionID", default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(rename = "runID", default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    /// Event types to deliver; a trailing `*` matches a prefix
+    /// (`session.*`). Empty means all types.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub types: Vec<String>,
+}
+
+impl SseFilter {
+    /// Parse a comma-separated `types` query value.
+    pub fn parse_types(raw: Option<&str>) -> Vec<String> {
+        raw.unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .take(MAX_FILTER_TYPES)
+            .map(str::to_string)
+            .collect()
+    }
+
+    pub fn matches(&self, event: &EngineEvent) -> bool {
+        if !self.types.is_empty()
+            && !self
+                .types
+                .iter()
+                .any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => event.event_type.starts_with(prefix),
+                    None => event.event_type == *pattern,
+                })
+        {
+            return false;
+        }
+        if self.session_id.is_none() && self.run_id.is_none() {
+            return true;
+        }
+        let event_session = event
+            .properties
+            .get("sessionID")
+            .or_else(|| event.properties.get("sessionId"))
+            .or_else(|| event.properties.get("id"))
+            .and_then(|v| v.as_str());
+        if let Some(session_id) = self.session_id.as_deref() {
+            if event_session != Some(session_id) {
+                return false;
+            }
+        }
+        if let Some(run_id) = self.run_id.as_deref() {
+            let event_run = event
+                .properties
+                .get("runID")
+                .or_else(|| event.properties.get("run_id"))
+                .and_then(|v| v.as_str());
+            if let Some(value) = event_run {
+                return value == run_id;
+            }
+            return self.session_id.is_some() && event_session.is_some();
+        }
+        true
+    }
+}
+
+/// Extractor for who is connecting to or inspecting SSE clients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SseCaller {
+    /// Admin-token callers, OIDC admins and unauthenticated single-user setups.
+    Admin,
+    /// A signed-in user (`user:<subject>`) or a tenant (`tenant:<id>`).
+    Owner(String),
+}
+
+impl SseCaller {
+    fn may_see(&self, owner: Option<&str>) -> bool {
+        match self {
+            Self::Admin => true,
+            Self::Owner(caller) => owner == Some(caller.as_str()),
+        }
+    }
+
+    fn owner(&self) -> Option<String> {
+        match self {
+            Self::Admin => None,
+            Self::Owner(owner) => Some(owner.clone()),
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for SseCaller {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(scope) = parts.extensions.get::<TenantScope>() {
+            return Ok(Self::Owner(format!("tenant:{}", scope.tenant_id)));
+        }
+        if parts.extensions.get::<UiRole>() == Some(&UiRole::Admin) {
+            return Ok(Self::Admin);
+        }
+        if let Some(AuthenticatedUser(subject)) = parts.extensions.get::<AuthenticatedUser>() {
+            return Ok(Self::Owner(format!("user:{subject}")));
+        }
+        Ok(Self::Admin)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SseClientInfo {
+    pub client_id: String,
+    pub filter: SseFilter,
+    pub connected_at_ms: u64,
+    pub delivered: u64,
+    pub lagged: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_delivered_at_ms: Option<u64>,
+}
+
+pub struct SseClient {
+    client_id: String,
+    owner: Option<String>,
+    filter: RwLock<SseFilter>,
+    connected_at_ms: u64,
+    delivered: AtomicU64,
+    lagged: AtomicU64,
+    /// Events missed since the last delivery; reset by every delivery.
+    lag_streak: AtomicU64,
+    last_delivered_at_ms: AtomicU64,
+    settings: SseSettings,
+    registry: SseClientRegistry,
+    event_bus: EventBus,
+}
+
+impl SseClient {
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    pub fn filter(&self) -> SseFilter {
+        self.filter.read().map(|f| f.clone()).unwrap_or_default()
+    }
+
+    pub fn info(&self) -> SseClientInfo {
+        let last = self.last_delivered_at_ms.load(Ordering::Relaxed);
+        SseClientInfo {
+            client_id: self.client_id.clone(),
+            filter: self.filter(),
+            connected_at_ms: self.connected_at_ms,
+            delivered: self.delivered.load(Ordering::Relaxed),
+            lagged: self.lagged.load(Ordering::Relaxed),
+            last_delivered_at_ms: (last > 0).then_some(last),
+        }
+    }
+}
+
+impl StreamConsumer for SseClient {
+    fn accepts(&self, event: &EngineEvent) -> bool {
+        self.filter.read().map(|f| f.matches(event)).unwrap_or(true)
+    }
+
+    fn stall_timeout(&self) -> Option<Duration> {
+        Some(Duration::from_millis(self.settings.stall_ms))
+    }
+
+    fn on_lagged(&self, skipped: u64) -> bool {
+        self.lagged.fetch_add(skipped, Ordering::Relaxed);
+        let streak = self.lag_streak.fetch_add(skipped, Ordering::Relaxed) + skipped;
+        streak > self.settings.max_lag
+    }
+
+    fn on_delivered(&self) {
+        self.lag_streak.store(0, Ordering::Relaxed);
+        self.delivered.fetch_add(1, Ordering::Relaxed);
+        self.last_delivered_at_ms
+            .store(crate::now_ms(), Ordering::Relaxed);
+    }
+
+    fn on_finished(&self, dropped: Option<StreamDropReason>) {
+        self.registry.remove(&self.client_id);
+        let Some(reason) = dropped else {
+            return;
+        };
+        let (reason_code, detail) = match reason {
+            StreamDropReason::Stalled { waited_ms } => ("stalled", json!({"waitedMs": waited_ms})),
+            StreamDropReason::Lagged { skipped } => ("lagged", json!({"skipped": skipped})),
+        };
+        tracing::warn!(
+            client_id = %self.client_id,
+            reason = reason_code,
+            delivered = self.delivered.load(Ordering::Relaxed),
+            lagged = self.lagged.load(Ordering::Relaxed),
+            "dropping slow SSE client"
+        );
+        self.event_bus.publish(EngineEvent::new(
+            "sse.client.dropped",
+            json!({
+                "clientID": self.client_id,
+                "reason": reason_code,
+                "detail": detail,
+                "delivered": self.delivered.load(Ordering::Relaxed),
+                "lagged": self.lagged.load(Ordering::Relaxed),
+                "connectedAtMs": self.connected_at_ms,
+            }),
+        ));
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct SseClientRegistry {
+    clients: Arc<RwLock<HashMap<String, Arc<SseClient>>>>,
+}
+
+impl SseClientRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(
+        &self,
+        caller: &SseCaller,
+        filter: SseFilter,
+        settings: SseSettings,
+        event_bus: EventBus,
+    ) -> Arc<SseClient> {
+        let client = Arc::new(SseClient {
+            client_id: format!("sse-{}", uuid::Uuid::new_v4().simple()),
+            owner: caller.owner(),
+            filter: RwLock::new(filter),
+            connected_at_ms: crate::now_ms(),
+            delivered: AtomicU64::new(0),
+            lagged: AtomicU64::new(0),
+            lag_streak: AtomicU64::new(0),
+            last_delivered_at_ms: AtomicU64::new(0),
+            settings,
+            registry: self.clone(),
+            event_bus,
+        });
+        if let Ok(mut clients) = self.clients.write() {
+            clients.insert(client.client_id.clone(), client.clone());
+        }
+        client
+    }
+
+    pub fn remove(&self, client_id: &str) {
+        if let Ok(mut clients) = self.clients.write() {
+            clients.remove(client_id);
+        }
+    }
+
+    /// Clients `caller` may see, oldest connection first.
+    pub fn list(&self, caller: &SseCaller) -> Vec<SseClientInfo> {
+        let mut rows = self
+            .clients
+            .read()
+            .map(|clients| {
+                clients
+                    .values()
+                    .filter(|c| caller.may_see(c.owner.as_deref()))
+                    .map(|c| c.info())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        rows.sort_by_key(|row| row.connected_at_ms);
+        rows
+    }
+
+    /// Replace a connected client's filter. Returns the updated client info,
+    /// or `None` if no such client is connected or `caller` may not see it.
+    pub fn update_filter(
+        &self,
+        caller: &SseCaller,
+        client_id: &str,
+        filter: SseFilter,
+    ) -> Option<SseClientInfo> {
+        let client = self
+            .clients
+            .read()
+            .ok()?
+            .get(client_id)
+            .filter(|c| caller.may_see(c.owner.as_deref()))
+            .cloned()?;
+        if let Ok(mut current) = client.filter.write() {
+            *current = filter;
+        }
+        Some(client.info())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_by_type_pattern_session_and_run() {
+        let event =
+            |event_type: &str, props: serde_json::Value| EngineEvent::new(event_type, props);
+        let filter = SseFilter {
+            session_id: Some("s1".to_string()),
+            run_id: None,
+            types: SseFilter::parse_types(Some("session.*, message.part.updated")),
+        };
+        assert!(filter.matches(&event("session.updated", json!({"sessionID": "s1"}))));
+        assert!(filter.matches(&event("message.part.updated", json!({"sessionID": "s1"}))));
+        assert!(!filter.matches(&event("message.updated", json!({"sessionID": "s1"}))));
+        assert!(!filter.matches(&event("session.updated", json!({"sessionID": "s2"}))));
+
+        let run = SseFilter {
+            session_id: Some("s1".to_string()),
+            run_id: Some("r1".to_string()),
+            types: Vec::new(),
+        };
+        assert!(!run.matches(&event(
+            "session.run.started",
+            json!({"sessionID": "s1", "runID": "r2"})
+        )));
+        assert!(run.matches(&event("session.updated", json!({"sessionID": "s1"}))));
+    }
+
+    #[tokio::test]
+    async fn filters_update_in_place_and_lag_drops_the_client() {
+        let registry = SseClientRegistry::new();
+        let bus = EventBus::new();
+        let mut rx = bus.subscribe();
+        let client = registry.register(
+            &SseCaller::Admin,
+            SseFilter::default(),
+            SseSettings {
+                max_lag: 10,
+                ..SseSettings::default()
+            },
+            bus.clone(),
+        );
+        let updated = registry
+            .update_filter(
+                &SseCaller::Admin,
+                client.client_id(),
+                SseFilter {
+                    types: vec!["run.*".to_string()],
+                    ..SseFilter::default()
+                },
+            )
+            .expect("client");
+        assert_eq!(updated.filter.types, vec!["run.*".to_string()]);
+        assert!(!client.accepts(&EngineEvent::new("session.updated", json!({}))));
+
+        assert!(!client.on_lagged(5));
+        client.on_delivered();
+        assert!(!client.on_lagged(6), "a delivery resets the lag streak");
+        assert!(client.on_lagged(5));
+        assert_eq!(client.info().lagged, 16);
+        client.on_finished(Some(StreamDropReason::Lagged { skipped: 5 }));
+        assert!(registry.list(&SseCaller::Admin).is_empty());
+        let dropped = rx.recv().await.expect("event");
+        assert_eq!(dropped.event_type, "sse.client.dropped");
+        assert_eq!(dropped.properties["reason"], json!("lagged"));
+    }
+
+    #[test]
+    fn callers_only_see_their_own_clients() {
+        let registry = SseClientRegistry::new();
+        let bus = EventBus::new();
+        let alice = SseCaller::Owner("user:alice".to_string());
+        let bob = SseCaller::Owner("tenant:bob".to_string());
+        let register = |caller: &SseCaller| {
+            registry.register(
+                caller,
+                SseFilter::default(),
+                SseSettings::default(),
+                bus.clone(),
+            )
+        };
+        let own = register(&alice);
+        let _other = register(&bob);
+        let _admin = register(&SseCaller::Admin);
+
+        let visible = registry.list(&alice);
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].client_id, own.client_id());
+        assert_eq!(registry.list(&SseCaller::Admin).len(), 3);
+        assert!(registry
+            .update_filter(&bob, own.client_id(), SseFilter::default())
+            .is_none());
+        assert!(registry
+            .update_filter(&alice, own.client_id(), SseFilter::default())
+            .is_some());
+    }
+}
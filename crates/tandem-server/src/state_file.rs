@@ -0,0 +1,124 @@
+//! JSON state files that survive crashes and never fail open.
+//!
+//! Server stores keep their state in memory and write it out whole. Writes go
+//! to a sibling temp file that is then renamed over the target, so a crash
+//! mid-write leaves the previous file intact. A file that exists but does not
+//! parse is an error rather than an empty store, and the store then refuses
+//! to overwrite it so the data can still be recovered by hand.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+#[derive(Debug, Clone)]
+pub struct JsonStateFile {
+    path: PathBuf,
+    /// Why the file on disk could not be parsed, if it could not.
+    load_error: Arc<Mutex<Option<String>>>,
+}
+
+impl JsonStateFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            load_error: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.path = path;
+        self.set_load_error(None);
+    }
+
+    /// The parsed file, or `None` if it does not exist yet.
+    pub async fn load<T: DeserializeOwned>(&self) -> anyhow::Result<Option<T>> {
+        let raw = match tokio::fs::read_to_string(&self.path).await {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        match serde_json::from_str(&raw) {
+            Ok(parsed) => {
+                self.set_load_error(None);
+                Ok(Some(parsed))
+            }
+            Err(err) => {
+                self.set_load_error(Some(err.to_string()));
+                anyhow::bail!("invalid state file {}: {err}", self.path.display())
+            }
+        }
+    }
+
+    /// Replace the file with `value`.
+    pub async fn save<T: Serialize + ?Sized>(&self, value: &T) -> anyhow::Result<()> {
+        if let Some(err) = self.load_error.lock().expect("load error lock").clone() {
+            anyhow::bail!(
+                "refusing to overwrite unparseable state file {}: {err}",
+                self.path.display()
+            );
+        }
+        write_atomic(&self.path, &serde_json::to_vec_pretty(value)?).await?;
+        Ok(())
+    }
+
+    fn set_load_error(&self, err: Option<String>) {
+        *self.load_error.lock().expect("load error lock") = err;
+    }
+}
+
+/// Replace `path` with `bytes` via a uniquely named sibling temp file, so
+/// concurrent writers never rename each other's half-written data.
+pub async fn write_atomic(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "state".to_string());
+    let seq = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    let tmp = path.with_file_name(format!(".{name}.{}-{seq}.partial", std::process::id()));
+    let result = async {
+        tokio::fs::write(&tmp, bytes).await?;
+        tokio::fs::rename(&tmp, path).await
+    }
+    .await;
+    if result.is_err() {
+        let _ = tokio::fs::remove_file(&tmp).await;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn unparseable_files_fail_to_load_and_are_not_overwritten() {
+        let dir = std::env::temp_dir().join(format!("tandem-state-file-{}", uuid::Uuid::new_v4()));
+        let file = JsonStateFile::new(dir.join("store.json"));
+        assert_eq!(file.load::<Vec<u32>>().await.expect("missing"), None);
+
+        file.save(&vec![1u32, 2]).await.expect("save");
+        assert_eq!(
+            file.load::<Vec<u32>>().await.expect("load"),
+            Some(vec![1, 2])
+        );
+        let leftovers = std::fs::read_dir(&dir).expect("dir").count();
+        assert_eq!(leftovers, 1);
+
+        std::fs::write(file.path(), "[1, 2").expect("truncate");
+        assert!(file.load::<Vec<u32>>().await.is_err());
+        assert!(file.save(&Vec::<u32>::new()).await.is_err());
+        assert_eq!(std::fs::read_to_string(file.path()).expect("read"), "[1, 2");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::path::Path;
+
+use serde::Serialize;
+
+/// Number of samples retained for growth-rate trends.
+const MAX_SAMPLES: usize = 288;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StoreStats {
+    pub count: u64,
+    pub bytes: u64,
+}
+
+impl StoreStats {
+    pub fn new(count: u64, bytes: u64) -> Self {
+        Self { count, bytes }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreTrend {
+    pub window_ms: u64,
+    pub count_delta: i64,
+    pub bytes_delta: i64,
+    pub count_per_hour: f64,
+    pub bytes_per_hour: f64,
+}
+
+#[derive(Debug, Clone)]
+struct StorageSample {
+    at_ms: u64,
+    stores: BTreeMap<String, StoreStats>,
+}
+
+/// Rolling history of storage snapshots used to derive per-store growth rates.
+#[derive(Debug, Default)]
+pub struct StorageStatsHistory {
+    samples: VecDeque<StorageSample>,
+}
+
+impl StorageStatsHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn record(&mut self, at_ms: u64, stores: &BTreeMap<String, StoreStats>) {
+        if self.samples.back().is_some_and(|last| last.at_ms > at_ms) {
+            return;
+        }
+        self.samples.push_back(StorageSample {
+            at_ms,
+            stores: stores.clone(),
+        });
+        while self.samples.len() > MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Growth between the oldest retained sample and the newest one. Stores
+    /// without an earlier sample (or with no elapsed time) are omitted.
+    pub fn trends(&self) -> BTreeMap<String, StoreTrend> {
+        let mut out = BTreeMap::new();
+        let (Some(first), Some(last)) = (self.samples.front(), self.samples.back()) else {
+            return out;
+        };
+        let window_ms = last.at_ms.saturating_sub(first.at_ms);
+        if window_ms == 0 {
+            return out;
+        }
+        let hours = window_ms as f64 / 3_600_000.0;
+        for (name, current) in &last.stores {
+            let Some(previous) = first.stores.get(name) else {
+                continue;
+            };
+            let count_delta = current.count as i64 - previous.count as i64;
+            let bytes_delta = current.bytes as i64 - previous.bytes as i64;
+            out.insert(
+                name.clone(),
+                StoreTrend {
+                    window_ms,
+                    count_delta,
+                    bytes_delta,
+                    count_per_hour: count_delta as f64 / hours,
+                    bytes_per_hour: bytes_delta as f64 / hours,
+                },
+            );
+        }
+        out
+    }
+}
+
+pub fn file_size_bytes(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|meta| meta.len()).unwrap_or(0)
+}
+
+/// Recursive size of a directory tree, counting regular files only.
+pub fn dir_size_bytes(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    let mut total = 0u64;
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if file_type.is_dir() {
+            total = total.saturating_add(dir_size_bytes(&entry.path()));
+        } else if file_type.is_file() {
+            total = total.saturating_add(entry.metadata().map(|meta| meta.len()).unwrap_or(0));
+        }
+    }
+    total
+}
+
+pub fn dir_file_count(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+        .count() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stores(count: u64, bytes: u64) -> BTreeMap<String, StoreStats> {
+        BTreeMap::from([("sessions".to_string(), StoreStats::new(count, bytes))])
+    }
+
+    #[test]
+    fn trends_report_growth_per_hour() {
+        let mut history = StorageStatsHistory::new();
+        history.record(0, &stores(10, 1_000));
+        history.record(1_800_000, &stores(15, 3_000));
+        let trends = history.trends();
+        let sessions = trends.get("sessions").expect("sessions trend");
+        assert_eq!(sessions.count_delta, 5);
+        assert_eq!(sessions.bytes_delta, 2_000);
+        assert_eq!(sessions.count_per_hour, 10.0);
+        assert_eq!(sessions.bytes_per_hour, 4_000.0);
+    }
+
+    #[test]
+    fn single_sample_has_no_trend() {
+        let mut history = StorageStatsHistory::new();
+        history.record(5, &stores(1, 1));
+        assert!(history.trends().is_empty());
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut history = StorageStatsHistory::new();
+        for i in 0..(MAX_SAMPLES as u64 + 10) {
+            history.record(i, &stores(i, i));
+        }
+        assert_eq!(history.len(), MAX_SAMPLES);
+    }
+}
@@ -0,0 +1,550 @@
+//! Machine-checkable routine success criteria.
+//!
+//! `success_criteria` in routine args may mix free-text criteria, which are
+//! only shown to the model, with assertion objects that the server evaluates
+//! after the run finishes:
+//!
+//! ```json
+//! [
+//!   "Summary covers every open incident",
+//!   { "type": "file_exists", "path": "reports/daily.md" },
+//!   { "type": "command", "command": "cargo test -q", "expect_exit": 0 },
+//!   { "type": "artifact_matches", "pattern": "(?m)^## Summary", "artifact": "daily.md" },
+//!   { "type": "url_ok", "url": "https://status.example.com/health" }
+//! ]
+//! ```
+//!
+//! A run whose model turn succeeded is only marked `completed` when every
+//! assertion passes; otherwise it is `failed` with the failing checks in its
+//! detail, whatever the model reported. Each assertion may carry a
+//! `description` used in the prompt and in results.
+//!
+//! `command` checks run on the host, so only routines without a tenant may
+//! use them. `url_ok` checks refuse loopback, private and link-local targets.
+
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::routine_diff::{artifact_file_path, ArtifactContent};
+use crate::run_hooks::{run_shell_command, ShellOutcome};
+use crate::{AppState, RoutineRunRecord};
+
+const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 120;
+const MAX_COMMAND_TIMEOUT_SECS: u64 = 1_800;
+const URL_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_DETAIL_CHARS: usize = 500;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CriterionCheck {
+    /// A file exists at `path`, relative to the workspace root.
+    FileExists { path: String },
+    /// A shell command, run in the workspace root, exits with `expect_exit`.
+    Command {
+        command: String,
+        #[serde(default)]
+        expect_exit: i32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        timeout_secs: Option<u64>,
+    },
+    /// `pattern` matches the content of a run artifact. `artifact` narrows
+    /// the candidates by label, kind or URI suffix; any artifact otherwise.
+    ArtifactMatches {
+        pattern: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        artifact: Option<String>,
+    },
+    /// A GET to `url` answers `status`, or any 2xx when unset.
+    UrlOk {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<u16>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SuccessCriterion {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(flatten)]
+    pub check: CriterionCheck,
+}
+
+impl SuccessCriterion {
+    /// Text shown to the model and in results.
+    pub fn label(&self) -> String {
+        if let Some(description) = self.description.as_deref().map(str::trim) {
+            if !description.is_empty() {
+                return description.to_string();
+            }
+        }
+        match &self.check {
+            CriterionCheck::FileExists { path } => format!("file `{path}` exists"),
+            CriterionCheck::Command {
+                command,
+                expect_exit,
+                ..
+            } => format!("`{command}` exits with {expect_exit}"),
+            CriterionCheck::ArtifactMatches { pattern, artifact } => match artifact {
+                Some(artifact) => format!("artifact `{artifact}` matches /{pattern}/"),
+                None => format!("an artifact matches /{pattern}/"),
+            },
+            CriterionCheck::UrlOk { url, status } => match status {
+                Some(status) => format!("{url} returns {status}"),
+                None => format!("{url} returns 2xx"),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CriterionResult {
+    pub criterion: String,
+    pub passed: bool,
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+/// Assertion objects in `args.success_criteria`; free-text entries are
+/// skipped. Fails on the first object that is not a valid assertion.
+pub fn checkable_criteria(args: &Value) -> Result<Vec<SuccessCriterion>, String> {
+    let Some(rows) = args.get("success_criteria").and_then(|v| v.as_array()) else {
+        return Ok(Vec::new());
+    };
+    let mut out = Vec::new();
+    for (index, row) in rows.iter().enumerate() {
+        if !row.is_object() {
+            continue;
+        }
+        let criterion = serde_json::from_value::<SuccessCriterion>(row.clone())
+            .map_err(|err| format!("success_criteria[{index}]: {err}"))?;
+        validate(&criterion).map_err(|err| format!("success_criteria[{index}]: {err}"))?;
+        out.push(criterion);
+    }
+    Ok(out)
+}
+
+/// Reject `command` checks for tenant-owned routines; they would run the
+/// tenant's shell commands on the host.
+pub fn check_commands_allowed(
+    criteria: &[SuccessCriterion],
+    tenant_id: Option<&str>,
+) -> Result<(), String> {
+    if tenant_id.is_none() {
+        return Ok(());
+    }
+    match criteria
+        .iter()
+        .position(|c| matches!(c.check, CriterionCheck::Command { .. }))
+    {
+        Some(index) => Err(format!(
+            "success_criteria[{index}]: command checks are only available to admin routines"
+        )),
+        None => Ok(()),
+    }
+}
+
+fn validate(criterion: &SuccessCriterion) -> Result<(), String> {
+    match &criterion.check {
+        CriterionCheck::FileExists { path } if path.trim().is_empty() => {
+            Err("path is required".to_string())
+        }
+        CriterionCheck::Command { command, .. } if command.trim().is_empty() => {
+            Err("command is required".to_string())
+        }
+        CriterionCheck::ArtifactMatches { pattern, .. } => regex::Regex::new(pattern)
+            .map(|_| ())
+            .map_err(|err| format!("invalid pattern: {err}")),
+        CriterionCheck::UrlOk { url, .. }
+            if !(url.starts_with("http://") || url.starts_with("https://")) =>
+        {
+            Err("url must be http(s)".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+async fn check_command(
+    command: &str,
+    expect_exit: i32,
+    timeout: Duration,
+    workspace_root: &str,
+) -> (bool, String) {
+    match run_shell_command(command, Some(workspace_root), &[], timeout).await {
+        ShellOutcome::Exited { code, output, .. } => {
+            let passed = code == Some(expect_exit);
+            let code = code.map_or_else(|| "signal".to_string(), |c| c.to_string());
+            (
+                passed,
+                crate::clip(format!("exit {code}; {output}").trim(), MAX_DETAIL_CHARS),
+            )
+        }
+        ShellOutcome::SpawnFailed(err) => (false, format!("failed to spawn: {err}")),
+        ShellOutcome::TimedOut => (false, format!("timed out after {}s", timeout.as_secs())),
+    }
+}
+
+/// Whether `ip` is reachable on the public internet, i.e. not loopback,
+/// private, link-local (including cloud metadata), CGNAT or unspecified.
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// Resolve the host of `url` and return the address to connect to, refusing
+/// hosts that resolve to any non-public address.
+async fn public_target(url: &reqwest::Url) -> Result<SocketAddr, String> {
+    let host = url.host_str().ok_or("url has no host")?;
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let port = url.port_or_known_default().ok_or("url has no port")?;
+    let addrs = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|err| format!("cannot resolve {host}: {err}"))?
+        .collect::<Vec<_>>();
+    if addrs.is_empty() {
+        return Err(format!("cannot resolve {host}"));
+    }
+    if let Some(blocked) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        return Err(format!(
+            "{host} resolves to non-public address {}",
+            blocked.ip()
+        ));
+    }
+    Ok(addrs[0])
+}
+
+async fn check_url(url: &str, status: Option<u16>) -> (bool, String) {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return (false, "invalid url".to_string());
+    };
+    let target = match public_target(&parsed).await {
+        Ok(target) => target,
+        Err(err) => {
+            return (
+                false,
+                crate::clip(&format!("refused: {err}"), MAX_DETAIL_CHARS),
+            )
+        }
+    };
+    // Pin the checked address and skip redirects so neither DNS rebinding
+    // nor a redirect can reach an internal host.
+    let mut builder = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(URL_TIMEOUT);
+    if let Some(domain) = parsed.domain() {
+        builder = builder.resolve(domain, target);
+    }
+    let client = match builder.build() {
+        Ok(client) => client,
+        Err(err) => {
+            return (
+                false,
+                crate::clip(&format!("request failed: {err}"), MAX_DETAIL_CHARS),
+            )
+        }
+    };
+    match client.get(parsed).send().await {
+        Ok(resp) => {
+            let got = resp.status();
+            let passed = match status {
+                Some(expected) => got.as_u16() == expected,
+                None => got.is_success(),
+            };
+            (passed, format!("HTTP {}", got.as_u16()))
+        }
+        Err(err) => (
+            false,
+            crate::clip(&format!("request failed: {err}"), MAX_DETAIL_CHARS),
+        ),
+    }
+}
+
+fn artifact_selected(selector: Option<&str>, run_artifact: &crate::RoutineRunArtifact) -> bool {
+    let Some(selector) = selector.map(str::trim).filter(|s| !s.is_empty()) else {
+        return true;
+    };
+    run_artifact.label.as_deref() == Some(selector)
+        || run_artifact.kind == selector
+        || run_artifact.uri.ends_with(selector)
+}
+
+impl AppState {
+    async fn check_artifact_matches(
+        &self,
+        run: &RoutineRunRecord,
+        pattern: &str,
+        selector: Option<&str>,
+    ) -> (bool, String) {
+        let Ok(re) = regex::Regex::new(pattern) else {
+            return (false, "invalid pattern".to_string());
+        };
+        let Some(run) = self.get_routine_run(&run.run_id).await else {
+            return (false, "run not found".to_string());
+        };
+        let mut checked = 0;
+        for artifact in run
+            .artifacts
+            .iter()
+            .filter(|a| artifact_selected(selector, a))
+        {
+            let ArtifactContent::Bytes(bytes) = self
+                .routine_run_artifact_content(&run.run_id, artifact)
+                .await
+            else {
+                continue;
+            };
+            checked += 1;
+            if re.is_match(&String::from_utf8_lossy(&bytes)) {
+                return (true, format!("matched artifact {}", artifact.uri));
+            }
+        }
+        if checked == 0 {
+            (false, "no readable artifact".to_string())
+        } else {
+            (false, format!("no match in {checked} artifact(s)"))
+        }
+    }
+
+    async fn evaluate_criterion(
+        &self,
+        run: &RoutineRunRecord,
+        criterion: &SuccessCriterion,
+        workspace_root: &str,
+        tenant_id: Option<&str>,
+    ) -> CriterionResult {
+        let started = Instant::now();
+        let (passed, detail) = match &criterion.check {
+            CriterionCheck::FileExists { path } => {
+                match artifact_file_path(path, &PathBuf::from(workspace_root)) {
+                    Some(found) if found.is_file() => (true, format!("found {}", found.display())),
+                    _ => (false, format!("`{path}` not found in the workspace")),
+                }
+            }
+            CriterionCheck::Command {
+                command,
+                expect_exit,
+                timeout_secs,
+            } if tenant_id.is_none() => {
+                let secs = timeout_secs
+                    .unwrap_or(DEFAULT_COMMAND_TIMEOUT_SECS)
+                    .clamp(1, MAX_COMMAND_TIMEOUT_SECS);
+                check_command(
+                    command,
+                    *expect_exit,
+                    Duration::from_secs(secs),
+                    workspace_root,
+                )
+                .await
+            }
+            CriterionCheck::Command { .. } => (
+                false,
+                "command checks are only available to admin routines".to_string(),
+            ),
+            CriterionCheck::ArtifactMatches { pattern, artifact } => {
+                self.check_artifact_matches(run, pattern, artifact.as_deref())
+                    .await
+            }
+            CriterionCheck::UrlOk { url, status } => check_url(url, *status).await,
+        };
+        CriterionResult {
+            criterion: criterion.label(),
+            passed,
+            detail,
+            duration_ms: started.elapsed().as_millis() as u64,
+        }
+    }
+
+    /// Evaluate every assertion in the run's `success_criteria`, in order.
+    pub async fn evaluate_success_criteria(
+        &self,
+        run: &RoutineRunRecord,
+        workspace_root: &str,
+    ) -> Vec<CriterionResult> {
+        let criteria = match checkable_criteria(&run.args) {
+            Ok(criteria) => criteria,
+            Err(err) => {
+                return vec![CriterionResult {
+                    criterion: "success_criteria".to_string(),
+                    passed: false,
+                    detail: err,
+                    duration_ms: 0,
+                }]
+            }
+        };
+        let tenant_id = self
+            .get_routine(&run.routine_id)
+            .await
+            .and_then(|routine| routine.tenant_id);
+        let mut results = Vec::with_capacity(criteria.len());
+        for criterion in &criteria {
+            results.push(
+                self.evaluate_criterion(run, criterion, workspace_root, tenant_id.as_deref())
+                    .await,
+            );
+        }
+        results
+    }
+}
+
+/// Run detail for failed criteria, e.g. `success criteria not met: file
+/// `out.md` exists (not found)`.
+pub fn failure_detail(results: &[CriterionResult]) -> Option<String> {
+    let failed = results
+        .iter()
+        .filter(|r| !r.passed)
+        .map(|r| format!("{} ({})", r.criterion, r.detail))
+        .collect::<Vec<_>>();
+    (!failed.is_empty()).then(|| format!("success criteria not met: {}", failed.join("; ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn free_text_is_skipped_and_objects_are_parsed() {
+        let args = json!({
+            "success_criteria": [
+                "Reads well",
+                { "type": "file_exists", "path": "out.md" },
+                { "type": "command", "command": "true", "description": "tests pass" },
+                { "type": "url_ok", "url": "https://example.com", "status": 204 }
+            ]
+        });
+        let criteria = checkable_criteria(&args).expect("criteria");
+        assert_eq!(criteria.len(), 3);
+        assert_eq!(criteria[0].label(), "file `out.md` exists");
+        assert_eq!(criteria[1].label(), "tests pass");
+        assert_eq!(
+            criteria[1].check,
+            CriterionCheck::Command {
+                command: "true".to_string(),
+                expect_exit: 0,
+                timeout_secs: None,
+            }
+        );
+        assert_eq!(criteria[2].label(), "https://example.com returns 204");
+    }
+
+    #[test]
+    fn invalid_assertions_are_rejected() {
+        for bad in [
+            json!({ "type": "file_exists", "path": " " }),
+            json!({ "type": "artifact_matches", "pattern": "(" }),
+            json!({ "type": "url_ok", "url": "file:///etc/passwd" }),
+            json!({ "type": "ping" }),
+        ] {
+            let args = json!({ "success_criteria": [bad] });
+            let err = checkable_criteria(&args).expect_err("invalid");
+            assert!(err.starts_with("success_criteria[0]"), "{err}");
+        }
+    }
+
+    #[test]
+    fn tenant_routines_may_not_use_command_checks() {
+        let args = json!({
+            "success_criteria": [
+                { "type": "file_exists", "path": "out.md" },
+                { "type": "command", "command": "true" }
+            ]
+        });
+        let criteria = checkable_criteria(&args).expect("criteria");
+        assert!(check_commands_allowed(&criteria, None).is_ok());
+        let err = check_commands_allowed(&criteria, Some("acme")).expect_err("refused");
+        assert!(err.starts_with("success_criteria[1]"), "{err}");
+        assert!(check_commands_allowed(&criteria[..1], Some("acme")).is_ok());
+    }
+
+    #[test]
+    fn internal_addresses_are_not_public() {
+        for blocked in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(blocked.parse().expect("ip")), "{blocked}");
+        }
+        assert!(is_public_ip("93.184.216.34".parse().expect("ip")));
+        assert!(is_public_ip("2606:4700::1111".parse().expect("ip")));
+    }
+
+    #[tokio::test]
+    async fn url_checks_refuse_internal_targets() {
+        let (passed, detail) = check_url("http://127.0.0.1:9/health", None).await;
+        assert!(!passed);
+        assert!(detail.starts_with("refused:"), "{detail}");
+        let (passed, _) = check_url("http://localhost/", None).await;
+        assert!(!passed);
+    }
+
+    #[test]
+    fn failure_detail_lists_failed_checks_only() {
+        let results = vec![
+            CriterionResult {
+                criterion: "a".to_string(),
+                passed: true,
+                detail: "ok".to_string(),
+                duration_ms: 1,
+            },
+            CriterionResult {
+                criterion: "b".to_string(),
+                passed: false,
+                detail: "exit 1".to_string(),
+                duration_ms: 1,
+            },
+        ];
+        assert_eq!(
+            failure_detail(&results).as_deref(),
+            Some("success criteria not met: b (exit 1)")
+        );
+        assert_eq!(failure_detail(&results[..1]), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn command_check_compares_exit_code() {
+        let root = std::env::temp_dir().display().to_string();
+        let (passed, _) = check_command("exit 3", 3, Duration::from_secs(5), &root).await;
+        assert!(passed);
+        let (passed, detail) = check_command("exit 1", 0, Duration::from_secs(5), &root).await;
+        assert!(!passed);
+        assert!(detail.starts_with("exit 1"));
+    }
+}
@@ -0,0 +1,307 @@
+//! Tenant isolation for shared servers.
+//!
+//! Each tenant gets its own API token. Requests made with a tenant token are
+//! tagged with a [`TenantScope`] and only see that tenant's data: sessions
+//! (stored as the session's `project_id`), routines, memory partitions whose
+//! `org_id` is the tenant id, and shared resources under `tenants/<id>/`.
+//! Requests made with the server's admin token are unscoped. Tenants are
+//! persisted to `tenants.json`; only token hashes are stored.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::Method;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::object_store::sha256_hex;
+use crate::state_file::JsonStateFile;
+
+/// Request extension set by the auth gate for tenant-token requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TenantScope {
+    pub tenant_id: String,
+}
+
+/// Extractor for the caller's tenant; `None` for admin or single-tenant
+/// requests.
+#[derive(Debug, Clone, Default)]
+pub struct CallerTenant(pub Option<String>);
+
+impl CallerTenant {
+    /// Whether the caller may see data tagged with `tenant_id`.
+    pub fn can_access(&self, tenant_id: Option<&str>) -> bool {
+        match self.0.as_deref() {
+            None => true,
+            Some(own) => tenant_id == Some(own),
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for CallerTenant {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(
+            parts
+                .extensions
+                .get::<TenantScope>()
+                .map(|scope| scope.tenant_id.clone()),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TenantRecord {
+    pub tenant_id: String,
+    pub name: String,
+    pub token_sha256: String,
+    pub created_at_ms: u64,
+}
+
+fn hash_token(token: &str) -> String {
+    sha256_hex(token.as_bytes())
+}
+
+/// Shared-resource key prefix owned by `tenant_id`.
+pub fn resource_namespace(tenant_id: &str) -> String {
+    format!("tenants/{tenant_id}/")
+}
+
+pub fn is_valid_tenant_id(tenant_id: &str) -> bool {
+    !tenant_id.is_empty()
+        && tenant_id.len() <= 64
+        && tenant_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Whether a tenant token may call `method path`. Tenants are refused by
+/// default; only routes whose handlers scope data to the caller's tenant are
+/// listed, and path-addressed objects are checked for ownership separately.
+/// Host-level surfaces (terminals, files, tools, MCP, skills, workspace,
+/// provider auth) and server administration stay admin-only.
+pub fn is_tenant_route(method: &Method, path: &str) -> bool {
+    let segments = path
+        .trim_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    let segments = match segments.as_slice() {
+        ["api", rest @ ..] => rest,
+        all => all,
+    };
+    let read_only = *method == Method::GET || *method == Method::HEAD;
+    match segments {
+        ["global", "health" | "event"] | ["event"] | ["capabilities"] | ["doc"] => true,
+        ["i18n", "catalog"] => true,
+        ["session"] | ["session", "status" | "import"] | ["session", "filters", ..] => true,
+        // Shell commands and workspace overrides act on the host.
+        ["session", _, "shell" | "workspace", ..] => false,
+        ["session" | "sessions", _, ..] => true,
+        ["routines" | "automations", "events"] => false,
+        ["routines" | "automations", ..] => true,
+        ["resource", "events"] => false,
+        ["resource", ..] => true,
+        ["permission" | "question", ..] => true,
+        ["memory"] | ["memory", "search" | "put" | "promote"] => true,
+        ["memory", id] => !matches!(
+            *id,
+            "audit" | "vector-index" | "embeddings" | "redaction-profiles" | "chunks"
+        ),
+        ["exports", _] => true,
+        ["config"] | ["project"] | ["quota"] | ["agent"] | ["command"] | ["tool"] => read_only,
+        ["provider"] | ["providers"] | ["provider", "presets" | "health"] => read_only,
+        _ => false,
+    }
+}
+
+#[derive(Clone)]
+pub struct TenantRegistry {
+    file: JsonStateFile,
+    tenants: Arc<RwLock<Vec<TenantRecord>>>,
+}
+
+impl TenantRegistry {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            file: JsonStateFile::new(path),
+            tenants: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    pub fn set_path(&mut self, path: PathBuf) {
+        self.file.set_path(path);
+    }
+
+    /// An unreadable file must not silently turn isolation off, so a file
+    /// that does not parse is an error.
+    pub async fn load(&self) -> anyhow::Result<()> {
+        if let Some(parsed) = self.file.load::<Vec<TenantRecord>>().await? {
+            *self.tenants.write().await = parsed;
+        }
+        Ok(())
+    }
+
+    async fn persist(&self) -> anyhow::Result<()> {
+        let guard = self.tenants.read().await;
+        self.file.save(&*guard).await
+    }
+
+    /// Isolation mode is on as soon as one tenant exists.
+    pub async fn is_enabled(&self) -> bool {
+        !self.tenants.read().await.is_empty()
+    }
+
+    pub async fn list(&self) -> Vec<TenantRecord> {
+        self.tenants.read().await.clone()
+    }
+
+    /// Tenant owning `token`, if any.
+    pub async fn tenant_for_token(&self, token: &str) -> Option<String> {
+        let hashed = hash_token(token);
+        self.tenants
+            .read()
+            .await
+            .iter()
+            .find(|tenant| tenant.token_sha256 == hashed)
+            .map(|tenant| tenant.tenant_id.clone())
+    }
+
+    /// Create a tenant, or rotate its token if it exists. Returns the record
+    /// and the plaintext token, which is not stored.
+    pub async fn upsert(
+        &self,
+        tenant_id: &str,
+        name: Option<String>,
+        now_ms: u64,
+    ) -> anyhow::Result<(TenantRecord, String)> {
+        let token = format!("tk_tenant_{}", uuid::Uuid::new_v4().simple());
+        let record = {
+            let mut guard = self.tenants.write().await;
+            let record = TenantRecord {
+                tenant_id: tenant_id.to_string(),
+                name: name.unwrap_or_else(|| tenant_id.to_string()),
+                token_sha256: hash_token(&token),
+                created_at_ms: now_ms,
+            };
+            match guard.iter_mut().find(|t| t.tenant_id == tenant_id) {
+                Some(existing) => {
+                    existing.name = record.name.clone();
+                    existing.token_sha256 = record.token_sha256.clone();
+                    existing.clone()
+                }
+                None => {
+                    guard.push(record.clone());
+                    record
+                }
+            }
+        };
+        self.persist().await?;
+        Ok((record, token))
+    }
+
+    pub async fn remove(&self, tenant_id: &str) -> anyhow::Result<bool> {
+        let removed = {
+            let mut guard = self.tenants.write().await;
+            let before = guard.len();
+            guard.retain(|t| t.tenant_id != tenant_id);
+            guard.len() != before
+        };
+        if removed {
+            self.persist().await?;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn tokens_resolve_to_tenants_and_rotate() {
+        let path = std::env::temp_dir().join(format!("tenants-{}.json", uuid::Uuid::new_v4()));
+        let registry = TenantRegistry::new(path.clone());
+        assert!(!registry.is_enabled().await);
+        let (record, token) = registry
+            .upsert("team-a", None, 1)
+            .await
+            .expect("create tenant");
+        assert_eq!(record.name, "team-a");
+        assert_ne!(record.token_sha256, token);
+        assert_eq!(
+            registry.tenant_for_token(&token).await.as_deref(),
+            Some("team-a")
+        );
+
+        let (_, rotated) = registry.upsert("team-a", None, 2).await.expect("rotate");
+        assert!(registry.tenant_for_token(&token).await.is_none());
+
+        let reloaded = TenantRegistry::new(path.clone());
+        reloaded.load().await.expect("load");
+        assert_eq!(
+            reloaded.tenant_for_token(&rotated).await.as_deref(),
+            Some("team-a")
+        );
+        assert!(reloaded.remove("team-a").await.expect("remove"));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn corrupt_tenants_file_fails_to_load() {
+        let path = std::env::temp_dir().join(format!("tenants-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "{not json").expect("write");
+        let registry = TenantRegistry::new(path.clone());
+        assert!(registry.load().await.is_err());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn tenant_routes_are_an_allowlist() {
+        for (method, path) in [
+            (Method::GET, "/global/health"),
+            (Method::GET, "/session"),
+            (Method::POST, "/session/abc/prompt_async"),
+            (Method::GET, "/api/session/abc"),
+            (Method::GET, "/routines/r1/history"),
+            (Method::GET, "/resource/tenants/a/x"),
+            (Method::POST, "/memory/search"),
+            (Method::DELETE, "/memory/m1"),
+            (Method::GET, "/config"),
+        ] {
+            assert!(is_tenant_route(&method, path), "{method} {path}");
+        }
+        for (method, path) in [
+            (Method::GET, "/admin/usage"),
+            (Method::POST, "/jobs"),
+            (Method::GET, "/resources/snapshots"),
+            (Method::PATCH, "/config"),
+            (Method::POST, "/global/dispose"),
+            (Method::GET, "/pty"),
+            (Method::POST, "/tool/execute"),
+            (Method::GET, "/provider/auth"),
+            (Method::POST, "/instance/dispose"),
+            (Method::POST, "/worktree/reset"),
+            (Method::GET, "/file/content"),
+            (Method::GET, "/find/symbol"),
+            (Method::GET, "/workspace/tree"),
+            (Method::POST, "/workspace/onboarding/apply"),
+            (Method::GET, "/mcp/tools"),
+            (Method::GET, "/skills"),
+            (Method::GET, "/config/providers"),
+            (Method::GET, "/log"),
+            (Method::GET, "/vcs"),
+            (Method::POST, "/provider/prewarm"),
+            (Method::GET, "/pipelines"),
+            (Method::GET, "/memory/audit"),
+            (Method::POST, "/session/abc/shell"),
+            (Method::GET, "/routines/events"),
+            (Method::GET, "/jobsite"),
+        ] {
+            assert!(!is_tenant_route(&method, path), "{method} {path}");
+        }
+    }
+}
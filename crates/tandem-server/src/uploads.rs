@@ -0,0 +1,227 @@
+/// Incremental `multipart/form-data` parser used by the attachment upload
+/// endpoint. Only the first part that carries a `filename` is surfaced; its
+/// body is emitted in chunks so uploads never need to be buffered whole.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MultipartEvent {
+    FileStart {
+        filename: String,
+        content_type: Option<String>,
+    },
+    Data(Vec<u8>),
+    FileEnd,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScanState {
+    Preamble,
+    Headers,
+    Body { capture: bool },
+    Done,
+}
+
+const MAX_PART_HEADER_BYTES: usize = 16 * 1024;
+
+pub struct MultipartScanner {
+    delimiter: Vec<u8>,
+    buf: Vec<u8>,
+    state: ScanState,
+    file_seen: bool,
+}
+
+/// Boundary parameter of a `multipart/form-data` content type.
+pub fn multipart_boundary(content_type: &str) -> Option<String> {
+    let (mime, params) = content_type.split_once(';')?;
+    if !mime.trim().eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+    params.split(';').find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("boundary")
+            .then(|| value.trim().trim_matches('"').to_string())
+            .filter(|b| !b.is_empty())
+    })
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn header_param(value: &str, name: &str) -> Option<String> {
+    value.split(';').skip(1).find_map(|param| {
+        let (key, value) = param.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().trim_matches('"').to_string())
+    })
+}
+
+impl MultipartScanner {
+    pub fn new(boundary: &str) -> Self {
+        Self {
+            delimiter: format!("\r\n--{boundary}").into_bytes(),
+            // A leading CRLF lets the first delimiter match like every other.
+            buf: b"\r\n".to_vec(),
+            state: ScanState::Preamble,
+            file_seen: false,
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == ScanState::Done
+    }
+
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<MultipartEvent>, String> {
+        self.buf.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        loop {
+            match self.state {
+                ScanState::Done => {
+                    self.buf.clear();
+                    break;
+                }
+                ScanState::Preamble => {
+                    let Some(pos) = find(&self.buf, &self.delimiter) else {
+                        let keep = self.delimiter.len().min(self.buf.len());
+                        self.buf.drain(..self.buf.len() - keep);
+                        break;
+                    };
+                    if !self.after_delimiter(pos)? {
+                        break;
+                    }
+                }
+                ScanState::Headers => {
+                    let Some(end) = find(&self.buf, b"\r\n\r\n") else {
+                        if self.buf.len() > MAX_PART_HEADER_BYTES {
+                            return Err("multipart part headers too large".to_string());
+                        }
+                        break;
+                    };
+                    let headers = String::from_utf8_lossy(&self.buf[..end]).to_string();
+                    self.buf.drain(..end + 4);
+                    let mut filename = None;
+                    let mut content_type = None;
+                    for line in headers.lines() {
+                        let Some((name, value)) = line.split_once(':') else {
+                            continue;
+                        };
+                        if name.trim().eq_ignore_ascii_case("content-disposition") {
+                            filename = header_param(value, "filename");
+                        } else if name.trim().eq_ignore_ascii_case("content-type") {
+                            content_type = Some(value.trim().to_string());
+                        }
+                    }
+                    let capture = !self.file_seen && filename.is_some();
+                    if let (true, Some(filename)) = (capture, filename) {
+                        self.file_seen = true;
+                        events.push(MultipartEvent::FileStart {
+                            filename,
+                            content_type,
+                        });
+                    }
+                    self.state = ScanState::Body { capture };
+                }
+                ScanState::Body { capture } => match find(&self.buf, &self.delimiter) {
+                    Some(pos) => {
+                        if capture {
+                            if pos > 0 {
+                                events.push(MultipartEvent::Data(self.buf[..pos].to_vec()));
+                            }
+                            events.push(MultipartEvent::FileEnd);
+                        }
+                        if !self.after_delimiter(pos)? {
+                            // Keep the delimiter so it is re-examined once the
+                            // trailing bytes arrive; body data was already sent.
+                            self.buf.drain(..pos);
+                            self.state = ScanState::Preamble;
+                            break;
+                        }
+                    }
+                    None => {
+                        // Hold back enough bytes to catch a split delimiter.
+                        let keep = (self.delimiter.len() - 1).min(self.buf.len());
+                        let emit = self.buf.len() - keep;
+                        if capture && emit > 0 {
+                            events.push(MultipartEvent::Data(self.buf[..emit].to_vec()));
+                        }
+                        self.buf.drain(..emit);
+                        break;
+                    }
+                },
+            }
+        }
+        Ok(events)
+    }
+
+    /// Consume the delimiter at `pos` plus its line ending. Returns false when
+    /// more input is needed to tell a part boundary from the closing one.
+    fn after_delimiter(&mut self, pos: usize) -> Result<bool, String> {
+        let after = pos + self.delimiter.len();
+        if self.buf.len() < after + 2 {
+            return Ok(false);
+        }
+        match &self.buf[after..after + 2] {
+            b"--" => {
+                self.state = ScanState::Done;
+                self.buf.clear();
+            }
+            b"\r\n" => {
+                self.state = ScanState::Headers;
+                self.buf.drain(..after + 2);
+            }
+            _ => return Err("malformed multipart delimiter".to_string()),
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_file_part_across_arbitrary_chunk_splits() {
+        let body = concat!(
+            "--XyZ\r\n",
+            "Content-Disposition: form-data; name=\"note\"\r\n\r\n",
+            "ignored field\r\n",
+            "--XyZ\r\n",
+            "Content-Disposition: form-data; name=\"file\"; filename=\"report.csv\"\r\n",
+            "Content-Type: text/csv\r\n\r\n",
+            "a,b\r\n1,2\r\n-XyZ\r\n",
+            "\r\n--XyZ--\r\n"
+        )
+        .as_bytes();
+        assert_eq!(
+            multipart_boundary("multipart/form-data; boundary=\"XyZ\""),
+            Some("XyZ".to_string())
+        );
+        for chunk_size in [1, 3, 7, body.len()] {
+            let mut scanner = MultipartScanner::new("XyZ");
+            let mut events = Vec::new();
+            for chunk in body.chunks(chunk_size) {
+                events.extend(scanner.push(chunk).expect("push"));
+            }
+            assert!(scanner.is_done());
+            assert_eq!(
+                events.first(),
+                Some(&MultipartEvent::FileStart {
+                    filename: "report.csv".to_string(),
+                    content_type: Some("text/csv".to_string()),
+                })
+            );
+            assert_eq!(events.last(), Some(&MultipartEvent::FileEnd));
+            let data = events
+                .iter()
+                .filter_map(|e| match e {
+                    MultipartEvent::Data(bytes) => Some(bytes.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .concat();
+            assert_eq!(data, b"a,b\r\n1,2\r\n-XyZ\r\n");
+        }
+    }
+}
@@ -0,0 +1,105 @@
+//! `workspace_changes`: structured "what changed in the repo since X" for
+//! agents, answered from the periodic workspace snapshots instead of making
+//! the model diff through bash.
+
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tandem_tools::Tool;
+use tandem_types::{ToolResult, ToolSchema};
+
+use crate::{now_ms, AppState};
+
+pub const WORKSPACE_CHANGES_TOOL: &str = "workspace_changes";
+const DEFAULT_SINCE: &str = "24h";
+const DEFAULT_LIMIT: usize = 200;
+
+pub struct WorkspaceChangesTool {
+    state: AppState,
+}
+
+impl WorkspaceChangesTool {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+}
+
+#[async_trait]
+impl Tool for WorkspaceChangesTool {
+    fn schema(&self) -> ToolSchema {
+        ToolSchema {
+            name: WORKSPACE_CHANGES_TOOL.to_string(),
+            description: "List files added, modified or deleted in the workspace since a point in time, based on periodic file-hash snapshots. Use instead of running git or diff commands to see what changed while you were away.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "since": { "type": "string", "description": "Duration back from now (30m, 6h, 1d, 2w) or epoch milliseconds; defaults to 24h" },
+                    "limit": { "type": "integer", "description": "Maximum number of changed paths to list" }
+                }
+            }),
+        }
+    }
+
+    async fn execute(&self, args: Value) -> anyhow::Result<ToolResult> {
+        let since_raw = args
+            .get("since")
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .unwrap_or(DEFAULT_SINCE);
+        let since_ms = tandem_runtime::parse_since_ms(since_raw, now_ms())
+            .ok_or_else(|| anyhow::anyhow!("invalid since `{since_raw}`"))?;
+        let limit = args
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .map(|v| v.max(1) as usize)
+            .unwrap_or(DEFAULT_LIMIT);
+        let root = match args.get("__workspace_root").and_then(|v| v.as_str()) {
+            Some(root) if !root.trim().is_empty() => PathBuf::from(root),
+            _ => PathBuf::from(self.state.workspace_index.snapshot().await.root),
+        };
+        let Some(mut changes) = self
+            .state
+            .workspace_snapshots
+            .changes_since(&root, since_ms)
+            .await
+        else {
+            let baseline = self.state.workspace_snapshots.capture(&root).await?;
+            return Ok(ToolResult {
+                output: "No earlier workspace snapshot exists yet; took a baseline now. Ask again later to see changes.".to_string(),
+                metadata: json!({ "baselineSnapshotID": baseline.snapshot_id }),
+            });
+        };
+        let mut output = format!(
+            "Changes since {} (baseline snapshot {}): {} added, {} modified, {} deleted.",
+            chrono::DateTime::from_timestamp_millis(changes.since_ms as i64)
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_else(|| changes.since_ms.to_string()),
+            changes.baseline_snapshot_id,
+            changes.added,
+            changes.modified,
+            changes.deleted,
+        );
+        let total = changes.changes.len();
+        for change in changes.changes.iter().take(limit) {
+            let marker = match change.change {
+                tandem_runtime::FileChangeKind::Added => "A",
+                tandem_runtime::FileChangeKind::Modified => "M",
+                tandem_runtime::FileChangeKind::Deleted => "D",
+            };
+            output.push_str(&format!("\n{marker} {}", change.path));
+        }
+        if total > limit {
+            output.push_str(&format!("\n... {} more", total - limit));
+        }
+        changes.changes.truncate(limit);
+        Ok(ToolResult {
+            output,
+            metadata: json!({
+                "changes": changes,
+                "truncated": total > limit,
+            }),
+        })
+    }
+}
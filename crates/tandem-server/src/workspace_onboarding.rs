@@ -0,0 +1,894 @@
+//! Workspace onboarding: inspect a workspace (languages, build tools, test
+//! and lint commands, CI config) and propose starter agent profiles, a
+//! project skill, verification hooks and routines as one reviewable plan.
+//!
+//! `POST /workspace/onboarding/analyze` returns the plan. Clients may edit or
+//! drop any part of it and send it back to `POST /workspace/onboarding/apply`,
+//! which writes the agents and skill into the workspace's `.tandem` dir,
+//! merges the hooks into `run_hooks.workspaces.<root>` (only with
+//! `approve_hooks`) and creates the routines. Existing agents, skills,
+//! hooks and routines are kept unless the apply asks to overwrite them.
+//! Both endpoints only accept the server workspace or one of its git
+//! worktrees, and apply is admin-only.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tandem_skills::{SkillLocation, SkillService};
+use tandem_types::EngineEvent;
+
+use crate::run_hooks::{HookFailurePolicy, RunHook, RunHookSet};
+use crate::{AppState, RoutineSpec};
+
+const MAX_SCAN_DEPTH: usize = 8;
+const MAX_SCAN_FILES: usize = 20_000;
+const VERIFY_HOOK_TIMEOUT_MS: u64 = 600_000;
+const SKIPPED_DIRS: &[&str] = &[
+    "node_modules",
+    "target",
+    "dist",
+    "build",
+    "vendor",
+    "venv",
+    "__pycache__",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LanguageStat {
+    pub language: String,
+    pub files: usize,
+}
+
+/// A build tool found at the workspace root and the commands it implies.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Toolchain {
+    pub tool: String,
+    pub manifest: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lint: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CiConfig {
+    pub provider: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkspaceProfile {
+    pub root: String,
+    /// Most files first.
+    pub languages: Vec<LanguageStat>,
+    pub toolchains: Vec<Toolchain>,
+    pub ci: Vec<CiConfig>,
+    pub files_scanned: usize,
+    /// The scan stopped at [`MAX_SCAN_FILES`].
+    pub truncated: bool,
+}
+
+impl WorkspaceProfile {
+    /// Lint commands, then test commands, in toolchain order.
+    pub fn verify_commands(&self) -> Vec<String> {
+        let lint = self.toolchains.iter().filter_map(|t| t.lint.clone());
+        let test = self.toolchains.iter().filter_map(|t| t.test.clone());
+        let mut out = Vec::new();
+        for command in lint.chain(test) {
+            if !out.contains(&command) {
+                out.push(command);
+            }
+        }
+        out
+    }
+
+    fn test_commands(&self) -> Vec<String> {
+        self.toolchains
+            .iter()
+            .filter_map(|t| t.test.clone())
+            .collect()
+    }
+
+    /// "this Rust/TypeScript repository", naming up to three languages.
+    fn describe(&self) -> String {
+        let names = self
+            .languages
+            .iter()
+            .take(3)
+            .map(|l| l.language.as_str())
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            "this repository".to_string()
+        } else {
+            format!("this {} repository", names.join("/"))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProposedAgent {
+    pub name: String,
+    pub description: String,
+    /// Agent profile markdown, written to `.tandem/agent/<name>.md`.
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProposedSkill {
+    pub name: String,
+    pub description: String,
+    /// `SKILL.md` content, installed as a project skill.
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OnboardingPlan {
+    pub workspace_root: String,
+    #[serde(default)]
+    pub profile: WorkspaceProfile,
+    #[serde(default)]
+    pub agents: Vec<ProposedAgent>,
+    #[serde(default)]
+    pub skills: Vec<ProposedSkill>,
+    /// Hooks for `run_hooks.workspaces.<workspace_root>`.
+    #[serde(default)]
+    pub hooks: RunHookSet,
+    /// `POST /routines` bodies.
+    #[serde(default)]
+    pub routines: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OnboardingApplyReport {
+    pub agents: Vec<String>,
+    pub skills: Vec<String>,
+    pub hooks: usize,
+    pub routines: Vec<String>,
+    /// Items left alone because they already exist.
+    pub skipped: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+fn language_for_extension(ext: &str) -> Option<&'static str> {
+    Some(match ext {
+        "rs" => "Rust",
+        "ts" | "tsx" | "mts" | "cts" => "TypeScript",
+        "js" | "jsx" | "mjs" | "cjs" => "JavaScript",
+        "py" => "Python",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "cs" => "C#",
+        "c" | "h" => "C",
+        "cc" | "cpp" | "cxx" | "hpp" => "C++",
+        "swift" => "Swift",
+        "scala" => "Scala",
+        "ex" | "exs" => "Elixir",
+        "dart" => "Dart",
+        "sh" | "bash" => "Shell",
+        _ => return None,
+    })
+}
+
+/// File counts per language, skipping hidden and dependency/build dirs.
+fn count_languages(root: &Path) -> (Vec<LanguageStat>, usize, bool) {
+    let mut counts: BTreeMap<&'static str, usize> = BTreeMap::new();
+    let mut scanned = 0usize;
+    let mut stack = vec![(root.to_path_buf(), 0usize)];
+    while let Some((dir, depth)) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if depth + 1 < MAX_SCAN_DEPTH && !SKIPPED_DIRS.contains(&name.as_str()) {
+                    stack.push((entry.path(), depth + 1));
+                }
+                continue;
+            }
+            if !file_type.is_file() {
+                continue;
+            }
+            if scanned >= MAX_SCAN_FILES {
+                return (sorted_languages(counts), scanned, true);
+            }
+            scanned += 1;
+            let language = Path::new(&name)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(|ext| language_for_extension(&ext.to_ascii_lowercase()));
+            if let Some(language) = language {
+                *counts.entry(language).or_default() += 1;
+            }
+        }
+    }
+    (sorted_languages(counts), scanned, false)
+}
+
+fn sorted_languages(counts: BTreeMap<&'static str, usize>) -> Vec<LanguageStat> {
+    let mut languages = counts
+        .into_iter()
+        .map(|(language, files)| LanguageStat {
+            language: language.to_string(),
+            files,
+        })
+        .collect::<Vec<_>>();
+    languages.sort_by(|a, b| b.files.cmp(&a.files).then(a.language.cmp(&b.language)));
+    languages
+}
+
+fn read(root: &Path, name: &str) -> Option<String> {
+    std::fs::read_to_string(root.join(name)).ok()
+}
+
+fn has_make_target(makefile: &str, target: &str) -> bool {
+    makefile.lines().any(|line| {
+        line.strip_prefix(target)
+            .is_some_and(|rest| rest.starts_with(':'))
+    })
+}
+
+fn detect_toolchains(root: &Path) -> Vec<Toolchain> {
+    let exists = |name: &str| root.join(name).exists();
+    let mut out = Vec::new();
+
+    if let Some(manifest) = read(root, "Cargo.toml") {
+        let all = if manifest.contains("[workspace]") {
+            " --workspace"
+        } else {
+            ""
+        };
+        out.push(Toolchain {
+            tool: "cargo".to_string(),
+            manifest: "Cargo.toml".to_string(),
+            build: Some(format!("cargo build{all}")),
+            test: Some(format!("cargo test{all}")),
+            lint: Some(format!("cargo clippy{all} --all-targets -- -D warnings")),
+        });
+    }
+
+    if let Some(manifest) = read(root, "package.json") {
+        let scripts = serde_json::from_str::<Value>(&manifest)
+            .ok()
+            .and_then(|v| v.get("scripts").cloned())
+            .unwrap_or_default();
+        let script = |name: &str| scripts.get(name).and_then(Value::as_str).is_some();
+        let pm = if exists("pnpm-lock.yaml") {
+            "pnpm"
+        } else if exists("yarn.lock") {
+            "yarn"
+        } else if exists("bun.lockb") || exists("bun.lock") {
+            "bun"
+        } else {
+            "npm"
+        };
+        // `npm init` writes a placeholder test script that always fails.
+        let real_test = scripts
+            .get("test")
+            .and_then(Value::as_str)
+            .is_some_and(|test| !test.contains("no test specified"));
+        out.push(Toolchain {
+            tool: pm.to_string(),
+            manifest: "package.json".to_string(),
+            build: script("build").then(|| format!("{pm} run build")),
+            test: real_test.then(|| format!("{pm} run test")),
+            lint: script("lint").then(|| format!("{pm} run lint")),
+        });
+    }
+
+    if exists("go.mod") {
+        out.push(Toolchain {
+            tool: "go".to_string(),
+            manifest: "go.mod".to_string(),
+            build: Some("go build ./...".to_string()),
+            test: Some("go test ./...".to_string()),
+            lint: Some("go vet ./...".to_string()),
+        });
+    }
+
+    let python_manifest = ["pyproject.toml", "setup.py", "requirements.txt"]
+        .into_iter()
+        .find(|name| exists(name));
+    if let Some(manifest) = python_manifest {
+        let pyproject = read(root, "pyproject.toml").unwrap_or_default();
+        let runner = if exists("poetry.lock") {
+            "poetry run "
+        } else if exists("uv.lock") {
+            "uv run "
+        } else {
+            ""
+        };
+        let pytest = pyproject.contains("pytest")
+            || exists("pytest.ini")
+            || exists("conftest.py")
+            || exists("tests");
+        let ruff = pyproject.contains("[tool.ruff") || exists("ruff.toml");
+        out.push(Toolchain {
+            tool: "python".to_string(),
+            manifest: manifest.to_string(),
+            build: None,
+            test: pytest.then(|| format!("{runner}pytest")),
+            lint: ruff.then(|| format!("{runner}ruff check .")),
+        });
+    }
+
+    if exists("pom.xml") {
+        let mvn = if exists("mvnw") { "./mvnw" } else { "mvn" };
+        out.push(Toolchain {
+            tool: "maven".to_string(),
+            manifest: "pom.xml".to_string(),
+            build: Some(format!("{mvn} -q compile")),
+            test: Some(format!("{mvn} -q test")),
+            lint: None,
+        });
+    }
+
+    let gradle_manifest = ["build.gradle.kts", "build.gradle"]
+        .into_iter()
+        .find(|name| exists(name));
+    if let Some(manifest) = gradle_manifest {
+        let gradle = if exists("gradlew") {
+            "./gradlew"
+        } else {
+            "gradle"
+        };
+        out.push(Toolchain {
+            tool: "gradle".to_string(),
+            manifest: manifest.to_string(),
+            build: Some(format!("{gradle} assemble")),
+            test: Some(format!("{gradle} test")),
+            lint: None,
+        });
+    }
+
+    if exists("Gemfile") {
+        let test = if exists("spec") {
+            Some("bundle exec rspec".to_string())
+        } else if exists("Rakefile") {
+            Some("bundle exec rake test".to_string())
+        } else {
+            None
+        };
+        out.push(Toolchain {
+            tool: "bundler".to_string(),
+            manifest: "Gemfile".to_string(),
+            build: None,
+            test,
+            lint: exists(".rubocop.yml").then(|| "bundle exec rubocop".to_string()),
+        });
+    }
+
+    if let Some(makefile) = read(root, "Makefile") {
+        let target = |name: &str| has_make_target(&makefile, name).then(|| format!("make {name}"));
+        let make = Toolchain {
+            tool: "make".to_string(),
+            manifest: "Makefile".to_string(),
+            build: target("build"),
+            test: target("test"),
+            lint: target("lint"),
+        };
+        // Only worth listing when it adds commands of its own.
+        if make.build.is_some() || make.test.is_some() || make.lint.is_some() {
+            out.push(make);
+        }
+    }
+    out
+}
+
+fn detect_ci(root: &Path) -> Vec<CiConfig> {
+    let mut out = Vec::new();
+    let workflows = root.join(".github").join("workflows");
+    if let Ok(entries) = std::fs::read_dir(&workflows) {
+        let mut files = entries
+            .flatten()
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name.ends_with(".yml") || name.ends_with(".yaml"))
+            .collect::<Vec<_>>();
+        files.sort();
+        out.extend(files.into_iter().map(|name| CiConfig {
+            provider: "github_actions".to_string(),
+            path: format!(".github/workflows/{name}"),
+        }));
+    }
+    for (provider, path) in [
+        ("gitlab", ".gitlab-ci.yml"),
+        ("circleci", ".circleci/config.yml"),
+        ("jenkins", "Jenkinsfile"),
+        ("azure_pipelines", "azure-pipelines.yml"),
+        ("buildkite", ".buildkite/pipeline.yml"),
+        ("travis", ".travis.yml"),
+    ] {
+        if root.join(path).is_file() {
+            out.push(CiConfig {
+                provider: provider.to_string(),
+                path: path.to_string(),
+            });
+        }
+    }
+    out
+}
+
+/// Inspect `root`. Blocking; call it from `spawn_blocking`.
+pub fn analyze_workspace(root: &Path) -> WorkspaceProfile {
+    let (languages, files_scanned, truncated) = count_languages(root);
+    WorkspaceProfile {
+        root: root.to_string_lossy().to_string(),
+        languages,
+        toolchains: detect_toolchains(root),
+        ci: detect_ci(root),
+        files_scanned,
+        truncated,
+    }
+}
+
+/// Lowercase `[a-z0-9-]` name for the workspace, used in skill and routine
+/// ids.
+fn workspace_slug(root: &str) -> String {
+    let base = Path::new(root)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    let mut slug = String::new();
+    for c in base.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(40);
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        "workspace".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+fn code_list(commands: &[String]) -> String {
+    commands
+        .iter()
+        .map(|command| format!("`{command}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn agent_markdown(name: &str, tools: &[&str], skill: &str, body: &str) -> String {
+    format!(
+        "---\nname: {name}\nmode: subagent\ntools: [{}]\nskills: [{skill}]\n---\n{body}\n",
+        tools.join(", ")
+    )
+}
+
+fn workflow_skill(profile: &WorkspaceProfile, name: &str) -> ProposedSkill {
+    let title = Path::new(&profile.root)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "workspace".to_string());
+    let description = format!("Build, test and lint commands and CI layout for {title}");
+    let mut body = format!("# {title} workflow\n\n");
+    if !profile.languages.is_empty() {
+        let languages = profile
+            .languages
+            .iter()
+            .map(|l| format!("{} ({} files)", l.language, l.files))
+            .collect::<Vec<_>>()
+            .join(", ");
+        body.push_str(&format!("Languages: {languages}.\n\n"));
+    }
+    if !profile.toolchains.is_empty() {
+        body.push_str("## Commands\n\n");
+        for toolchain in &profile.toolchains {
+            let commands = [
+                ("build", &toolchain.build),
+                ("test", &toolchain.test),
+                ("lint", &toolchain.lint),
+            ]
+            .into_iter()
+            .filter_map(|(kind, command)| command.as_ref().map(|c| format!("{kind} `{c}`")))
+            .collect::<Vec<_>>();
+            body.push_str(&format!(
+                "- {} ({}): {}\n",
+                toolchain.tool,
+                toolchain.manifest,
+                if commands.is_empty() {
+                    "no commands detected".to_string()
+                } else {
+                    commands.join(", ")
+                }
+            ));
+        }
+        body.push('\n');
+    }
+    if !profile.ci.is_empty() {
+        body.push_str("## CI\n\n");
+        for ci in &profile.ci {
+            body.push_str(&format!("- {}: {}\n", ci.provider, ci.path));
+        }
+        body.push('\n');
+    }
+    body.push_str(
+        "Run the lint and test commands before reporting a change as done, and keep them in step with what CI runs.\n",
+    );
+    ProposedSkill {
+        name: name.to_string(),
+        content: format!("---\nname: {name}\ndescription: {description}\n---\n{body}"),
+        description,
+    }
+}
+
+/// The starter plan for an analyzed workspace.
+pub fn propose_plan(profile: WorkspaceProfile) -> OnboardingPlan {
+    let slug = workspace_slug(&profile.root);
+    let skill = workflow_skill(&profile, &format!("{slug}-workflow"));
+    let repository = profile.describe();
+    let verify = profile.verify_commands();
+    let tests = profile.test_commands();
+
+    let mut agents = vec![ProposedAgent {
+        name: "reviewer".to_string(),
+        description: "Reviews changes against the project's conventions; read-only".to_string(),
+        content: agent_markdown(
+            "reviewer",
+            &["read", "glob", "grep", "codesearch", "lsp"],
+            &skill.name,
+            &format!(
+                "You review changes in {repository}. Read the diff and the code around it, check it against the conventions and commands in the `{}` skill, and report concrete issues with file and line. Do not edit files.",
+                skill.name
+            ),
+        ),
+    }];
+    if !tests.is_empty() {
+        agents.push(ProposedAgent {
+            name: "tester".to_string(),
+            description: "Runs the test suite and fixes failures".to_string(),
+            content: agent_markdown(
+                "tester",
+                &["bash", "read", "glob", "grep", "edit"],
+                &skill.name,
+                &format!(
+                    "You keep the test suite green. Run {}; for each failure find the cause, fix the code or the test, and rerun until everything passes. Report what you changed and why.",
+                    code_list(&tests)
+                ),
+            ),
+        });
+    }
+    if !profile.ci.is_empty() {
+        let paths = profile
+            .ci
+            .iter()
+            .map(|ci| ci.path.clone())
+            .collect::<Vec<_>>();
+        agents.push(ProposedAgent {
+            name: "ci-triage".to_string(),
+            description: "Diagnoses CI failures from the pipeline definitions".to_string(),
+            content: agent_markdown(
+                "ci-triage",
+                &["bash", "read", "glob", "grep"],
+                &skill.name,
+                &format!(
+                    "You diagnose CI failures. The pipelines are defined in {}. Find the failing job, reproduce it locally with the matching command from the `{}` skill, and report the root cause and the smallest fix. Do not edit the pipeline files unless asked.",
+                    code_list(&paths),
+                    skill.name
+                ),
+            ),
+        });
+    }
+
+    let hooks = RunHookSet {
+        post_run: verify
+            .iter()
+            .map(|command| RunHook {
+                name: Some(format!("verify: {command}")),
+                command: Some(command.clone()),
+                timeout_ms: Some(VERIFY_HOOK_TIMEOUT_MS),
+                on_error: HookFailurePolicy::Warn,
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+
+    let mut routines = Vec::new();
+    if !verify.is_empty() {
+        routines.push(json!({
+            "routine_id": format!("{slug}-nightly-verify"),
+            "name": "Nightly verification",
+            "schedule": { "interval_seconds": { "seconds": 86_400 } },
+            "entrypoint": "mission.default",
+            "args": {
+                "prompt": format!(
+                    "Run the verification commands for this workspace: {}. Report each failing command with the relevant error lines and a likely cause. Do not modify files.",
+                    code_list(&verify)
+                ),
+            },
+            "allowed_tools": ["bash", "read", "glob", "grep"],
+            "requires_approval": false,
+            "external_integrations_allowed": false,
+        }));
+    }
+    if let Some(preset) = crate::routine_presets::routine_presets()
+        .into_iter()
+        .find(|preset| preset.id == "secrets-scan-daily")
+    {
+        let mut routine = preset.routine;
+        routine["routine_id"] = json!(format!("{slug}-secrets-scan"));
+        routines.push(routine);
+    }
+
+    OnboardingPlan {
+        workspace_root: profile.root.clone(),
+        profile,
+        agents,
+        skills: vec![skill],
+        hooks,
+        routines,
+    }
+}
+
+/// Build a routine from a plan entry, filling the same defaults as
+/// `POST /routines`.
+fn routine_from_plan(value: &Value) -> Result<RoutineSpec, String> {
+    let Value::Object(mut map) = value.clone() else {
+        return Err("routine must be a JSON object".to_string());
+    };
+    let routine_id = map
+        .get("routine_id")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| "routine is missing routine_id".to_string())?;
+    if !crate::item_names::valid_item_name(&routine_id) {
+        return Err(format!("invalid routine_id `{routine_id}`"));
+    }
+    map.insert("status".to_string(), json!("active"));
+    map.insert("creator_type".to_string(), json!("onboarding"));
+    map.insert("creator_id".to_string(), json!("onboarding"));
+    map.insert("tenant_id".to_string(), Value::Null);
+    map.entry("timezone").or_insert_with(|| json!("UTC"));
+    map.entry("misfire_policy")
+        .or_insert_with(|| json!({ "type": "run_once" }));
+    map.entry("args").or_insert_with(|| json!({}));
+    map.entry("requires_approval").or_insert(json!(true));
+    map.entry("external_integrations_allowed")
+        .or_insert(json!(false));
+    serde_json::from_value(Value::Object(map))
+        .map_err(|err| format!("routine `{routine_id}`: {err}"))
+}
+
+impl AppState {
+    /// Apply an onboarding plan for `plan.workspace_root`. Items that fail
+    /// are reported and the rest still apply. Hooks run shell commands on
+    /// every run in the workspace, so they are only stored when
+    /// `approve_hooks` is set; otherwise they are reported as skipped.
+    pub async fn apply_onboarding_plan(
+        &self,
+        plan: &OnboardingPlan,
+        overwrite: bool,
+        approve_hooks: bool,
+    ) -> OnboardingApplyReport {
+        let root = Path::new(&plan.workspace_root);
+        let mut report = OnboardingApplyReport::default();
+
+        let agents_dir = root.join(".tandem").join("agent");
+        for agent in &plan.agents {
+            let label = format!("agent:{}", agent.name);
+            if !crate::item_names::valid_item_name(&agent.name) {
+                report.errors.push(format!("{label}: invalid name"));
+                continue;
+            }
+            if !agent.content.trim_start().starts_with("---") {
+                report
+                    .errors
+                    .push(format!("{label}: content needs a frontmatter block"));
+                continue;
+            }
+            let path = agents_dir.join(format!("{}.md", agent.name));
+            if path.exists() && !overwrite {
+                report.skipped.push(label);
+                continue;
+            }
+            let written = async {
+                tokio::fs::create_dir_all(&agents_dir).await?;
+                tokio::fs::write(&path, &agent.content).await
+            }
+            .await;
+            match written {
+                Ok(()) => report.agents.push(agent.name.clone()),
+                Err(err) => report.errors.push(format!("{label}: {err}")),
+            }
+        }
+        if !report.agents.is_empty() {
+            if let Err(err) = self.agents.reload().await {
+                report.errors.push(format!("agent reload: {err}"));
+            }
+        }
+
+        let skills = SkillService::for_workspace(Some(root.to_path_buf()));
+        for skill in &plan.skills {
+            let label = format!("skill:{}", skill.name);
+            let existing = root
+                .join(".tandem")
+                .join("skill")
+                .join(&skill.name)
+                .join("SKILL.md");
+            if existing.exists() && !overwrite {
+                report.skipped.push(label);
+                continue;
+            }
+            match skills.import_skill_from_content(&skill.content, SkillLocation::Project) {
+                Ok(info) => report.skills.push(info.name),
+                Err(err) => report.errors.push(format!("{label}: {err}")),
+            }
+        }
+
+        let hook_count =
+            plan.hooks.pre_run.len() + plan.hooks.post_run.len() + plan.hooks.on_failure.len();
+        if hook_count > 0 && !approve_hooks {
+            report.skipped.push("hooks:unapproved".to_string());
+        } else if hook_count > 0 {
+            let existing = self.run_hooks_config().await;
+            if existing.workspaces.contains_key(&plan.workspace_root) && !overwrite {
+                report.skipped.push("hooks".to_string());
+            } else {
+                let mut workspaces = serde_json::Map::new();
+                workspaces.insert(plan.workspace_root.clone(), json!(plan.hooks));
+                let patch = json!({ "run_hooks": { "workspaces": workspaces } });
+                match self.config.patch_project(patch).await {
+                    Ok(_) => report.hooks = hook_count,
+                    Err(err) => report.errors.push(format!("hooks: {err}")),
+                }
+            }
+        }
+
+        for value in &plan.routines {
+            let routine = match routine_from_plan(value) {
+                Ok(routine) => routine,
+                Err(err) => {
+                    report.errors.push(err);
+                    continue;
+                }
+            };
+            let label = format!("routine:{}", routine.routine_id);
+            let mut routine = routine;
+            if let Some(existing) = self.get_routine(&routine.routine_id).await {
+                if !overwrite {
+                    report.skipped.push(label);
+                    continue;
+                }
+                routine.status = existing.status;
+                routine.tenant_id = existing.tenant_id;
+            }
+            match self.put_routine(routine).await {
+                Ok(stored) => {
+                    self.event_bus.publish(EngineEvent::new(
+                        "routine.created",
+                        json!({
+                            "routineID": stored.routine_id,
+                            "name": stored.name,
+                            "entrypoint": stored.entrypoint,
+                        }),
+                    ));
+                    report.routines.push(stored.routine_id);
+                }
+                Err(err) => report.errors.push(format!("{label}: {err:?}")),
+            }
+        }
+
+        self.event_bus.publish(EngineEvent::new(
+            "workspace.onboarding.applied",
+            json!({
+                "workspaceRoot": plan.workspace_root,
+                "agents": report.agents,
+                "skills": report.skills,
+                "hooks": report.hooks,
+                "routines": report.routines,
+                "skipped": report.skipped.len(),
+                "errors": report.errors.len(),
+            }),
+        ));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn analyzes_a_mixed_workspace_and_proposes_a_plan() {
+        let root = std::env::temp_dir().join(format!("Demo App-{}", uuid::Uuid::new_v4()));
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write("Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\n");
+        write("crates/core/src/lib.rs", "");
+        write("crates/core/src/main.rs", "");
+        write(
+            "package.json",
+            r#"{"scripts": {"test": "vitest", "lint": "eslint ."}}"#,
+        );
+        write("pnpm-lock.yaml", "");
+        write("web/app.ts", "");
+        write("node_modules/left-pad/index.js", "");
+        write("target/debug/build.rs", "");
+        write(".github/workflows/ci.yml", "on: push\n");
+        write("Makefile", "release:\n\techo release\n");
+
+        let profile = analyze_workspace(&root);
+        assert_eq!(
+            profile.languages,
+            vec![
+                LanguageStat {
+                    language: "Rust".to_string(),
+                    files: 2
+                },
+                LanguageStat {
+                    language: "TypeScript".to_string(),
+                    files: 1
+                },
+            ]
+        );
+        let tools = profile
+            .toolchains
+            .iter()
+            .map(|t| t.tool.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(tools, vec!["cargo", "pnpm"]);
+        assert_eq!(profile.toolchains[1].build, None);
+        assert_eq!(
+            profile.verify_commands(),
+            vec![
+                "cargo clippy --workspace --all-targets -- -D warnings",
+                "pnpm run lint",
+                "cargo test --workspace",
+                "pnpm run test",
+            ]
+        );
+        assert_eq!(profile.ci[0].path, ".github/workflows/ci.yml");
+
+        let plan = propose_plan(profile);
+        let slug = workspace_slug(&plan.workspace_root);
+        assert!(slug.starts_with("demo-app-"));
+        let agents = plan
+            .agents
+            .iter()
+            .map(|a| a.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(agents, vec!["reviewer", "tester", "ci-triage"]);
+        assert!(plan.agents[1].content.contains("`cargo test --workspace`"));
+        assert_eq!(plan.skills[0].name, format!("{slug}-workflow"));
+        assert!(plan.skills[0].content.contains("- pnpm (package.json)"));
+        assert_eq!(plan.hooks.post_run.len(), 4);
+        let routine_ids = plan
+            .routines
+            .iter()
+            .map(|r| r["routine_id"].as_str().unwrap_or_default().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            routine_ids,
+            vec![
+                format!("{slug}-nightly-verify"),
+                format!("{slug}-secrets-scan")
+            ]
+        );
+        for routine in &plan.routines {
+            routine_from_plan(routine).expect("routine parses");
+        }
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
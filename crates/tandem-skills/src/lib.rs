@@ -4,6 +4,13 @@ use std::fs;
 use std::io::Read;
 use std::path::{Path, PathBuf};
 
+pub mod relevance;
+
+pub use relevance::{
+    rank_skills, select_skills, RankedSkill, SelectedSkill, SkillLoadMode, SkillRankInput,
+    SkillSelection,
+};
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum SkillLocation {
@@ -180,6 +187,11 @@ impl SkillService {
         }
     }
 
+    /// Directory global skills are installed into.
+    pub fn global_write_root(&self) -> &Path {
+        &self.global_write_root
+    }
+
     pub fn list_skills(&self) -> Result<Vec<SkillInfo>, String> {
         let mut out = Vec::new();
         let mut seen_names = HashSet::new();
@@ -0,0 +1,408 @@
+//! Ranking installed skills against a prompt and fitting them into a token
+//! budget.
+//!
+//! Each skill is scored from three signals:
+//!
+//! - trigger match: 1.0 when one of its `triggers` appears in the prompt as a
+//!   phrase, otherwise a partial score for the share of trigger words present;
+//! - similarity: cosine similarity between the prompt and the skill's name,
+//!   description, tags and triggers (term vectors unless the caller supplies
+//!   embedding similarities);
+//! - recency: how recently the skill was last loaded, halving every week.
+//!
+//! Only skills with a trigger match or enough similarity are candidates;
+//! recency breaks ties between them but never makes a skill relevant on its
+//! own. [`select_skills`] then loads the best candidates in full and lists the
+//! rest as one-line summaries, within the budget.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::SkillInfo;
+
+const TRIGGER_WEIGHT: f32 = 0.6;
+const SIMILARITY_WEIGHT: f32 = 0.3;
+const RECENCY_WEIGHT: f32 = 0.1;
+/// Partial trigger matches score at most this much.
+const PARTIAL_TRIGGER_SCALE: f32 = 0.6;
+/// Similarity below this does not make a skill a candidate.
+pub const MIN_SIMILARITY: f32 = 0.2;
+const RECENCY_HALF_LIFE_MS: f64 = 7.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+
+const STOPWORDS: &[&str] = &[
+    "and", "are", "but", "can", "for", "from", "have", "how", "into", "not", "that", "the", "then",
+    "this", "use", "using", "what", "when", "with", "you", "your",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedSkill {
+    pub name: String,
+    pub score: f32,
+    pub trigger: f32,
+    pub similarity: f32,
+    pub recency: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SkillRankInput<'a> {
+    pub prompt: &'a str,
+    /// Unix ms each skill was last loaded, by name.
+    pub last_used_ms: Option<&'a HashMap<String, u64>>,
+    /// Precomputed prompt/skill embedding similarities (0..1), by name. Skills
+    /// missing from the map fall back to term-vector similarity.
+    pub embedding_similarity: Option<&'a HashMap<String, f32>>,
+    pub now_ms: u64,
+}
+
+fn terms(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() >= 3)
+        .map(str::to_lowercase)
+        .filter(|t| !STOPWORDS.contains(&t.as_str()))
+        .collect()
+}
+
+fn term_vector(text: &str) -> HashMap<String, f32> {
+    let mut out = HashMap::new();
+    for term in terms(text) {
+        *out.entry(term).or_insert(0.0) += 1.0;
+    }
+    out
+}
+
+fn cosine(a: &HashMap<String, f32>, b: &HashMap<String, f32>) -> f32 {
+    let dot = a
+        .iter()
+        .filter_map(|(k, v)| b.get(k).map(|w| v * w))
+        .sum::<f32>();
+    let norm = |m: &HashMap<String, f32>| m.values().map(|v| v * v).sum::<f32>().sqrt();
+    let denom = norm(a) * norm(b);
+    if denom == 0.0 {
+        0.0
+    } else {
+        dot / denom
+    }
+}
+
+/// Strongest match of any trigger in `prompt`.
+pub fn trigger_strength(triggers: &[String], prompt: &str) -> f32 {
+    let prompt_lower = prompt.to_lowercase();
+    let prompt_terms = terms(prompt);
+    triggers
+        .iter()
+        .map(|trigger| {
+            let trigger = trigger.trim().to_lowercase();
+            if trigger.is_empty() {
+                return 0.0;
+            }
+            if prompt_lower.contains(&trigger) {
+                return 1.0;
+            }
+            let words = terms(&trigger);
+            if words.is_empty() {
+                return 0.0;
+            }
+            let hits = words.iter().filter(|w| prompt_terms.contains(w)).count();
+            PARTIAL_TRIGGER_SCALE * hits as f32 / words.len() as f32
+        })
+        .fold(0.0, f32::max)
+}
+
+fn skill_text(skill: &SkillInfo) -> String {
+    format!(
+        "{} {} {} {}",
+        skill.name.replace(['-', '_'], " "),
+        skill.description,
+        skill.tags.join(" "),
+        skill.triggers.join(" ")
+    )
+}
+
+fn recency(last_used_ms: Option<u64>, now_ms: u64) -> f32 {
+    let Some(last) = last_used_ms else {
+        return 0.0;
+    };
+    let age = now_ms.saturating_sub(last) as f64;
+    0.5f64.powf(age / RECENCY_HALF_LIFE_MS) as f32
+}
+
+/// Candidate skills for `input.prompt`, best first.
+pub fn rank_skills(skills: &[SkillInfo], input: &SkillRankInput<'_>) -> Vec<RankedSkill> {
+    let prompt_vector = term_vector(input.prompt);
+    let mut ranked = skills
+        .iter()
+        .filter(|skill| skill.parse_error.is_none())
+        .filter_map(|skill| {
+            let trigger = trigger_strength(&skill.triggers, input.prompt);
+            let similarity = input
+                .embedding_similarity
+                .and_then(|m| m.get(&skill.name).copied())
+                .unwrap_or_else(|| cosine(&prompt_vector, &term_vector(&skill_text(skill))))
+                .clamp(0.0, 1.0);
+            if trigger == 0.0 && similarity < MIN_SIMILARITY {
+                return None;
+            }
+            let recency = recency(
+                input.last_used_ms.and_then(|m| m.get(&skill.name).copied()),
+                input.now_ms,
+            );
+            Some(RankedSkill {
+                name: skill.name.clone(),
+                score: TRIGGER_WEIGHT * trigger
+                    + SIMILARITY_WEIGHT * similarity
+                    + RECENCY_WEIGHT * recency,
+                trigger,
+                similarity,
+                recency,
+            })
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.name.cmp(&b.name)));
+    ranked
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SkillLoadMode {
+    Full,
+    Summary,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectedSkill {
+    pub name: String,
+    pub mode: SkillLoadMode,
+    pub score: f32,
+    pub tokens: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkillSelection {
+    pub budget_tokens: usize,
+    pub used_tokens: usize,
+    pub skills: Vec<SelectedSkill>,
+    /// Candidates that did not fit even as a summary.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub omitted: Vec<String>,
+}
+
+impl SkillSelection {
+    pub fn is_empty(&self) -> bool {
+        self.skills.is_empty()
+    }
+}
+
+/// Rough token count (~4 chars per token).
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+pub fn summary_line(skill: &SkillInfo) -> String {
+    format!("- {}: {}", skill.name, skill.description.trim())
+}
+
+/// Fit `ranked` into `budget_tokens`. Every candidate that fits gets a
+/// summary line first; then, best first, up to `max_full` are upgraded to
+/// their full content (from `load`) while the budget allows. Returns the
+/// selection and the rendered context block (empty when nothing fits).
+pub fn select_skills(
+    ranked: &[RankedSkill],
+    skills: &[SkillInfo],
+    budget_tokens: usize,
+    max_full: usize,
+    mut load: impl FnMut(&str) -> Option<String>,
+) -> (SkillSelection, String) {
+    let by_name = skills
+        .iter()
+        .map(|s| (s.name.as_str(), s))
+        .collect::<HashMap<_, _>>();
+    let mut selection = SkillSelection {
+        budget_tokens,
+        ..SkillSelection::default()
+    };
+    let mut summaries = Vec::new();
+    for candidate in ranked {
+        let Some(skill) = by_name.get(candidate.name.as_str()) else {
+            continue;
+        };
+        let line = summary_line(skill);
+        let tokens = estimate_tokens(&line);
+        if selection.used_tokens + tokens > budget_tokens {
+            selection.omitted.push(candidate.name.clone());
+            continue;
+        }
+        selection.used_tokens += tokens;
+        selection.skills.push(SelectedSkill {
+            name: candidate.name.clone(),
+            mode: SkillLoadMode::Summary,
+            score: candidate.score,
+            tokens,
+        });
+        summaries.push(line);
+    }
+
+    let mut full = HashMap::new();
+    for index in 0..selection.skills.len() {
+        if full.len() >= max_full {
+            break;
+        }
+        let Some(content) = load(&selection.skills[index].name) else {
+            continue;
+        };
+        let tokens = estimate_tokens(&content);
+        let summary_tokens = selection.skills[index].tokens;
+        if selection.used_tokens - summary_tokens + tokens > budget_tokens {
+            continue;
+        }
+        selection.used_tokens = selection.used_tokens - summary_tokens + tokens;
+        let entry = &mut selection.skills[index];
+        entry.mode = SkillLoadMode::Full;
+        entry.tokens = tokens;
+        full.insert(index, content);
+    }
+
+    if selection.skills.is_empty() {
+        return (selection, String::new());
+    }
+    let mut lines = vec!["<relevant_skills>".to_string()];
+    for (index, skill) in selection.skills.iter().enumerate() {
+        if let Some(content) = full.get(&index) {
+            lines.push(format!("<skill_content name=\"{}\">", skill.name));
+            lines.push(content.trim().to_string());
+            lines.push("</skill_content>".to_string());
+        }
+    }
+    let summarized = selection
+        .skills
+        .iter()
+        .zip(summaries)
+        .filter(|(s, _)| s.mode == SkillLoadMode::Summary)
+        .map(|(_, line)| line)
+        .collect::<Vec<_>>();
+    if !summarized.is_empty() {
+        lines.push("Other relevant skills (load one with the `skill` tool if needed):".to_string());
+        lines.extend(summarized);
+    }
+    lines.push("</relevant_skills>".to_string());
+    (selection, lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SkillLocation;
+
+    fn skill(name: &str, description: &str, triggers: &[&str]) -> SkillInfo {
+        SkillInfo {
+            name: name.to_string(),
+            description: description.to_string(),
+            location: SkillLocation::Project,
+            path: format!("/skills/{name}"),
+            version: None,
+            author: None,
+            tags: Vec::new(),
+            requires: Vec::new(),
+            compatibility: None,
+            triggers: triggers.iter().map(|t| t.to_string()).collect(),
+            parse_error: None,
+        }
+    }
+
+    fn catalog() -> Vec<SkillInfo> {
+        vec![
+            skill(
+                "release-notes",
+                "Draft release notes from merged pull requests",
+                &["release notes", "changelog"],
+            ),
+            skill(
+                "db-migrations",
+                "Write and review database schema migrations",
+                &["migration"],
+            ),
+            skill("weather", "Fetch a weather forecast", &[]),
+        ]
+    }
+
+    #[test]
+    fn triggers_outrank_similarity_and_unrelated_skills_are_dropped() {
+        let skills = catalog();
+        let ranked = rank_skills(
+            &skills,
+            &SkillRankInput {
+                prompt: "Write the release notes and check the schema migrations",
+                ..SkillRankInput::default()
+            },
+        );
+        let mut names = ranked.iter().map(|r| r.name.as_str()).collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["db-migrations", "release-notes"]);
+        assert!(ranked.iter().all(|r| r.trigger == 1.0));
+    }
+
+    #[test]
+    fn recency_breaks_ties_but_is_not_relevance() {
+        let skills = vec![
+            skill("alpha", "lint rust code", &["lint"]),
+            skill("beta", "lint python code", &["lint"]),
+            skill("gamma", "unrelated", &[]),
+        ];
+        let now = 10 * RECENCY_HALF_LIFE_MS as u64;
+        let last_used = HashMap::from([
+            ("beta".to_string(), now - 1_000),
+            ("gamma".to_string(), now),
+        ]);
+        let ranked = rank_skills(
+            &skills,
+            &SkillRankInput {
+                prompt: "please lint this",
+                last_used_ms: Some(&last_used),
+                now_ms: now,
+                ..SkillRankInput::default()
+            },
+        );
+        let names = ranked.iter().map(|r| r.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["beta", "alpha"]);
+    }
+
+    #[test]
+    fn partial_trigger_match_scores_lower() {
+        let triggers = vec!["deploy staging cluster".to_string()];
+        assert_eq!(
+            trigger_strength(&triggers, "Deploy staging cluster now"),
+            1.0
+        );
+        let partial = trigger_strength(&triggers, "deploy to the cluster");
+        assert!(partial > 0.0 && partial < 1.0);
+        assert_eq!(trigger_strength(&triggers, "write docs"), 0.0);
+    }
+
+    #[test]
+    fn selection_loads_best_in_full_and_summarizes_the_rest_within_budget() {
+        let skills = catalog();
+        let ranked = rank_skills(
+            &skills,
+            &SkillRankInput {
+                prompt: "Write the release notes and check the schema migrations",
+                ..SkillRankInput::default()
+            },
+        );
+        let body = "x".repeat(400);
+        let (selection, rendered) = select_skills(&ranked, &skills, 150, 3, |_| Some(body.clone()));
+        let (best, next) = (&selection.skills[0], &selection.skills[1]);
+        assert_eq!(best.mode, SkillLoadMode::Full);
+        assert_eq!(next.mode, SkillLoadMode::Summary);
+        assert!(selection.used_tokens <= 150);
+        assert!(rendered.contains(&format!("<skill_content name=\"{}\">", best.name)));
+        assert!(rendered.contains(&format!("- {}: ", next.name)));
+
+        let (selection, rendered) = select_skills(&ranked, &skills, 5, 3, |_| Some(body.clone()));
+        assert!(selection.is_empty());
+        assert_eq!(selection.omitted.len(), 2);
+        assert!(rendered.is_empty());
+    }
+}
@@ -0,0 +1,1192 @@
+//! Deterministic expression evaluator behind the `calc` tool and the
+//! `{{calc: ...}}` routine template variable.
+//!
+//! Expressions cover numbers, quantities with units (`3 km + 200 m`,
+//! `90 min to h`), UTC dates (`date("2026-03-01") + 30 d`), booleans and
+//! strings. There is no I/O and no clock: the same expression always gives
+//! the same result, so "today" has to be passed in (routines have `{{date}}`).
+//! Numbers are `f64`; results are shown rounded to 12 significant digits so
+//! `0.1 + 0.2` prints as `0.3`.
+
+use std::fmt;
+
+/// Longest expression accepted.
+pub const MAX_EXPRESSION_CHARS: usize = 4_000;
+const MAX_DEPTH: usize = 64;
+const SECONDS_PER_DAY: i64 = 86_400;
+/// Dates are limited to years -999999..=999999.
+const MAX_YEAR: i64 = 999_999;
+/// Furthest a time may be from the epoch either way; keeps the difference of
+/// two times well inside `i64`.
+const MAX_TIME_SECONDS: i64 = (MAX_YEAR + 1) * 366 * SECONDS_PER_DAY;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dimension {
+    Length,
+    Mass,
+    Time,
+    Data,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Unit {
+    /// Shown in results.
+    pub symbol: &'static str,
+    pub dimension: Dimension,
+    /// Size of one unit in the dimension's base unit (m, kg, s, byte).
+    pub factor: f64,
+    aliases: &'static [&'static str],
+}
+
+macro_rules! unit {
+    ($symbol:literal, $dimension:ident, $factor:expr, [$($alias:literal),*]) => {
+        Unit {
+            symbol: $symbol,
+            dimension: Dimension::$dimension,
+            factor: $factor,
+            aliases: &[$symbol, $($alias),*],
+        }
+    };
+}
+
+static UNITS: &[Unit] = &[
+    unit!("ms", Time, 0.001, ["millisecond", "milliseconds"]),
+    unit!("s", Time, 1.0, ["sec", "secs", "second", "seconds"]),
+    unit!("min", Time, 60.0, ["mins", "minute", "minutes"]),
+    unit!("h", Time, 3_600.0, ["hr", "hrs", "hour", "hours"]),
+    unit!("d", Time, 86_400.0, ["day", "days"]),
+    unit!("wk", Time, 604_800.0, ["week", "weeks"]),
+    unit!("mm", Length, 0.001, []),
+    unit!("cm", Length, 0.01, []),
+    unit!("m", Length, 1.0, ["meter", "meters", "metre", "metres"]),
+    unit!("km", Length, 1_000.0, []),
+    unit!("in", Length, 0.0254, ["inch", "inches"]),
+    unit!("ft", Length, 0.3048, ["foot", "feet"]),
+    unit!("yd", Length, 0.9144, ["yard", "yards"]),
+    unit!("mi", Length, 1_609.344, ["mile", "miles"]),
+    unit!("mg", Mass, 0.000_001, []),
+    unit!("g", Mass, 0.001, ["gram", "grams"]),
+    unit!("kg", Mass, 1.0, []),
+    unit!("t", Mass, 1_000.0, ["tonne", "tonnes"]),
+    unit!("oz", Mass, 0.028_349_523_125, []),
+    unit!("lb", Mass, 0.453_592_37, ["lbs", "pound", "pounds"]),
+    unit!("B", Data, 1.0, ["byte", "bytes"]),
+    unit!("KB", Data, 1e3, ["kB"]),
+    unit!("MB", Data, 1e6, []),
+    unit!("GB", Data, 1e9, []),
+    unit!("TB", Data, 1e12, []),
+    unit!("KiB", Data, 1_024.0, []),
+    unit!("MiB", Data, 1_048_576.0, []),
+    unit!("GiB", Data, 1_073_741_824.0, []),
+    unit!("TiB", Data, 1_099_511_627_776.0, []),
+];
+
+fn find_unit(name: &str) -> Option<&'static Unit> {
+    UNITS.iter().find(|unit| unit.aliases.contains(&name))
+}
+
+fn unit_named(symbol: &str) -> &'static Unit {
+    find_unit(symbol).expect("built-in unit")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CalcValue {
+    Number(f64),
+    /// `value` is in the dimension's base unit and shown in `unit`.
+    Quantity {
+        value: f64,
+        unit: &'static Unit,
+    },
+    /// Seconds since the Unix epoch, UTC. Date-only values print as
+    /// `YYYY-MM-DD`.
+    Time {
+        seconds: i64,
+        date_only: bool,
+    },
+    Bool(bool),
+    Text(String),
+}
+
+impl CalcValue {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Number(_) => "number",
+            Self::Quantity { .. } => "quantity",
+            Self::Time {
+                date_only: true, ..
+            } => "date",
+            Self::Time { .. } => "datetime",
+            Self::Bool(_) => "bool",
+            Self::Text(_) => "text",
+        }
+    }
+
+    /// Number, or a quantity's magnitude in its display unit.
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Self::Number(n) => Some(*n),
+            Self::Quantity { value, unit } => Some(value / unit.factor),
+            _ => None,
+        }
+    }
+
+    /// Template variables are strings; numeric ones are used as numbers.
+    pub fn from_text(text: &str) -> Self {
+        match text.trim().parse::<f64>() {
+            Ok(n) if n.is_finite() => Self::Number(n),
+            _ => Self::Text(text.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for CalcValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(n) => f.write_str(&format_number(*n)),
+            Self::Quantity { value, unit } => {
+                write!(f, "{} {}", format_number(value / unit.factor), unit.symbol)
+            }
+            Self::Time { seconds, date_only } => {
+                let days = seconds.div_euclid(SECONDS_PER_DAY);
+                let (y, m, d) = civil_from_days(days);
+                if *date_only {
+                    write!(f, "{y:04}-{m:02}-{d:02}")
+                } else {
+                    let secs = seconds.rem_euclid(SECONDS_PER_DAY);
+                    write!(
+                        f,
+                        "{y:04}-{m:02}-{d:02}T{:02}:{:02}:{:02}Z",
+                        secs / 3_600,
+                        secs % 3_600 / 60,
+                        secs % 60
+                    )
+                }
+            }
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Text(text) => f.write_str(text),
+        }
+    }
+}
+
+/// Shortest decimal form after rounding to 12 significant digits.
+fn format_number(n: f64) -> String {
+    if n == 0.0 {
+        return "0".to_string();
+    }
+    let rounded: f64 = format!("{n:.11e}").parse().unwrap_or(n);
+    if rounded.fract() == 0.0 && rounded.abs() < 1e15 {
+        format!("{}", rounded as i64)
+    } else {
+        format!("{rounded}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalcError(pub String);
+
+impl fmt::Display for CalcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CalcError {}
+
+fn err<T>(message: impl Into<String>) -> Result<T, CalcError> {
+    Err(CalcError(message.into()))
+}
+
+/// Evaluate `expr` without variables.
+pub fn evaluate(expr: &str) -> Result<CalcValue, CalcError> {
+    evaluate_with(expr, &|_| None)
+}
+
+/// Evaluate `expr`, resolving identifiers that are not constants or units
+/// through `vars`.
+pub fn evaluate_with(
+    expr: &str,
+    vars: &dyn Fn(&str) -> Option<CalcValue>,
+) -> Result<CalcValue, CalcError> {
+    if expr.chars().count() > MAX_EXPRESSION_CHARS {
+        return err(format!(
+            "expression is longer than {MAX_EXPRESSION_CHARS} characters"
+        ));
+    }
+    let tokens = tokenize(expr)?;
+    if tokens.is_empty() {
+        return err("empty expression");
+    }
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        depth: 0,
+    };
+    let ast = parser.expr()?;
+    if let Some(token) = parser.tokens.get(parser.pos) {
+        return err(format!("unexpected {}", token.describe()));
+    }
+    eval(&ast, vars, 0)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Self::Num(n) => format!("number {}", format_number(*n)),
+            Self::Str(_) => "string".to_string(),
+            Self::Ident(name) => format!("`{name}`"),
+            Self::Op(op) => format!("`{op}`"),
+            Self::LParen => "`(`".to_string(),
+            Self::RParen => "`)`".to_string(),
+            Self::Comma => "`,`".to_string(),
+        }
+    }
+}
+
+const OPERATORS: [&str; 13] = [
+    "==", "!=", "<=", ">=", "+", "-", "*", "/", "%", "^", "<", ">", "!",
+];
+
+fn tokenize(expr: &str) -> Result<Vec<Token>, CalcError> {
+    let chars = expr.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_ascii_digit() || chars[i] == '.' || chars[i] == '_')
+            {
+                i += 1;
+            }
+            // Exponent only when digits follow, so `2e` stays `2 e`.
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let sign = usize::from(matches!(chars.get(i + 1), Some('+' | '-')));
+                if chars.get(i + 1 + sign).is_some_and(char::is_ascii_digit) {
+                    i += 1 + sign;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text = chars[start..i]
+                .iter()
+                .filter(|c| **c != '_')
+                .collect::<String>();
+            let n = text
+                .parse::<f64>()
+                .map_err(|_| CalcError(format!("invalid number `{text}`")))?;
+            tokens.push(Token::Num(n));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if c == '"' || c == '\'' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return err("unterminated string"),
+                    Some(&q) if q == c => break,
+                    Some('\\') => {
+                        match chars.get(i + 1) {
+                            Some('n') => text.push('\n'),
+                            Some('t') => text.push('\t'),
+                            Some(&other) => text.push(other),
+                            None => return err("unterminated string"),
+                        }
+                        i += 1;
+                    }
+                    Some(&other) => text.push(other),
+                }
+                i += 1;
+            }
+            i += 1;
+            tokens.push(Token::Str(text));
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == ',' {
+            tokens.push(Token::Comma);
+            i += 1;
+        } else {
+            let rest = chars[i..chars.len().min(i + 2)].iter().collect::<String>();
+            let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) else {
+                return err(format!("unexpected character `{c}`"));
+            };
+            tokens.push(Token::Op(op));
+            i += op.len();
+        }
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Value(CalcValue),
+    Var(String),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    WithUnit(Box<Expr>, &'static Unit),
+    Convert(Box<Expr>, &'static Unit),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat_op(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.pos += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Ident(name)) if name == word);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expr(&mut self) -> Result<Expr, CalcError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return err("expression is nested too deeply");
+        }
+        let mut left = self.or()?;
+        while self.eat_word("to") {
+            let unit = match self.next() {
+                Some(Token::Ident(name)) => {
+                    find_unit(&name).ok_or_else(|| CalcError(format!("unknown unit `{name}`")))?
+                }
+                _ => return err("expected a unit after `to`"),
+            };
+            left = Expr::Convert(Box::new(left), unit);
+        }
+        self.depth -= 1;
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr, CalcError> {
+        let mut left = self.and()?;
+        while self.eat_word("or") {
+            left = Expr::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, CalcError> {
+        let mut left = self.comparison()?;
+        while self.eat_word("and") {
+            left = Expr::And(Box::new(left), Box::new(self.comparison()?));
+        }
+        Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, CalcError> {
+        let left = self.additive()?;
+        match self.eat_op(&["==", "!=", "<", "<=", ">", ">="]) {
+            Some(op) => Ok(Expr::Binary(op, Box::new(left), Box::new(self.additive()?))),
+            None => Ok(left),
+        }
+    }
+
+    fn additive(&mut self) -> Result<Expr, CalcError> {
+        let mut left = self.multiplicative()?;
+        while let Some(op) = self.eat_op(&["+", "-"]) {
+            left = Expr::Binary(op, Box::new(left), Box::new(self.multiplicative()?));
+        }
+        Ok(left)
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, CalcError> {
+        let mut left = self.unary()?;
+        while let Some(op) = self.eat_op(&["*", "/", "%"]) {
+            left = Expr::Binary(op, Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, CalcError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return err("expression is nested too deeply");
+        }
+        let out = if let Some(op) = self.eat_op(&["-", "+", "!"]) {
+            Expr::Unary(op, Box::new(self.unary()?))
+        } else if self.eat_word("not") {
+            Expr::Unary("!", Box::new(self.unary()?))
+        } else {
+            self.power()?
+        };
+        self.depth -= 1;
+        Ok(out)
+    }
+
+    fn power(&mut self) -> Result<Expr, CalcError> {
+        let base = self.with_unit()?;
+        if self.eat_op(&["^"]).is_some() {
+            return Ok(Expr::Binary("^", Box::new(base), Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    /// A unit right after a value: `5 km`, `(1 + 2) h`.
+    fn with_unit(&mut self) -> Result<Expr, CalcError> {
+        let value = self.primary()?;
+        if let Some(Token::Ident(name)) = self.peek() {
+            let called = matches!(self.tokens.get(self.pos + 1), Some(Token::LParen));
+            if let (Some(unit), false) = (find_unit(name), called) {
+                self.pos += 1;
+                return Ok(Expr::WithUnit(Box::new(value), unit));
+            }
+        }
+        Ok(value)
+    }
+
+    fn primary(&mut self) -> Result<Expr, CalcError> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Expr::Value(CalcValue::Number(n))),
+            Some(Token::Str(text)) => Ok(Expr::Value(CalcValue::Text(text))),
+            Some(Token::LParen) => {
+                let inner = self.expr()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(inner),
+                    _ => err("missing `)`"),
+                }
+            }
+            Some(Token::Ident(name)) => {
+                if !matches!(self.peek(), Some(Token::LParen)) {
+                    return Ok(Expr::Var(name));
+                }
+                self.pos += 1;
+                let mut args = Vec::new();
+                if matches!(self.peek(), Some(Token::RParen)) {
+                    self.pos += 1;
+                    return Ok(Expr::Call(name, args));
+                }
+                loop {
+                    args.push(self.expr()?);
+                    match self.next() {
+                        Some(Token::Comma) => {}
+                        Some(Token::RParen) => break,
+                        _ => return err(format!("missing `)` after arguments of {name}")),
+                    }
+                }
+                Ok(Expr::Call(name, args))
+            }
+            Some(token) => err(format!("unexpected {}", token.describe())),
+            None => err("unexpected end of expression"),
+        }
+    }
+}
+
+fn eval(
+    expr: &Expr,
+    vars: &dyn Fn(&str) -> Option<CalcValue>,
+    depth: usize,
+) -> Result<CalcValue, CalcError> {
+    if depth > MAX_DEPTH * 2 {
+        return err("expression is nested too deeply");
+    }
+    let eval_inner = |inner: &Expr| eval(inner, vars, depth + 1);
+    let value = match expr {
+        Expr::Value(value) => value.clone(),
+        Expr::Var(name) => match name.as_str() {
+            "pi" => CalcValue::Number(std::f64::consts::PI),
+            "e" => CalcValue::Number(std::f64::consts::E),
+            "true" => CalcValue::Bool(true),
+            "false" => CalcValue::Bool(false),
+            _ => vars(name).ok_or_else(|| CalcError(format!("unknown name `{name}`")))?,
+        },
+        Expr::Unary(op, inner) => match (*op, eval_inner(inner)?) {
+            ("-", CalcValue::Number(n)) => CalcValue::Number(-n),
+            ("-", CalcValue::Quantity { value, unit }) => CalcValue::Quantity {
+                value: -value,
+                unit,
+            },
+            ("+", value @ (CalcValue::Number(_) | CalcValue::Quantity { .. })) => value,
+            ("!", CalcValue::Bool(b)) => CalcValue::Bool(!b),
+            (op, value) => return err(format!("cannot apply `{op}` to {}", value.type_name())),
+        },
+        Expr::And(left, right) => {
+            CalcValue::Bool(as_bool(eval_inner(left)?)? && as_bool(eval_inner(right)?)?)
+        }
+        Expr::Or(left, right) => {
+            CalcValue::Bool(as_bool(eval_inner(left)?)? || as_bool(eval_inner(right)?)?)
+        }
+        Expr::Binary(op, left, right) => binary(op, eval_inner(left)?, eval_inner(right)?)?,
+        Expr::WithUnit(inner, unit) => match eval_inner(inner)? {
+            CalcValue::Number(n) => CalcValue::Quantity {
+                value: n * unit.factor,
+                unit,
+            },
+            other => return err(format!("cannot give {} a unit", other.type_name())),
+        },
+        Expr::Convert(inner, unit) => match eval_inner(inner)? {
+            CalcValue::Quantity { value, unit: from } if from.dimension == unit.dimension => {
+                CalcValue::Quantity { value, unit }
+            }
+            CalcValue::Quantity { unit: from, .. } => {
+                return err(format!("cannot convert {} to {}", from.symbol, unit.symbol))
+            }
+            other => {
+                return err(format!(
+                    "cannot convert {} to {}",
+                    other.type_name(),
+                    unit.symbol
+                ))
+            }
+        },
+        Expr::Call(name, args) if name == "if" => {
+            let [cond, then, otherwise] = args.as_slice() else {
+                return err("if takes 3 arguments");
+            };
+            if as_bool(eval_inner(cond)?)? {
+                eval_inner(then)?
+            } else {
+                eval_inner(otherwise)?
+            }
+        }
+        Expr::Call(name, args) => {
+            let args = args.iter().map(eval_inner).collect::<Result<Vec<_>, _>>()?;
+            call(name, args)?
+        }
+    };
+    match value {
+        CalcValue::Number(n) | CalcValue::Quantity { value: n, .. } if !n.is_finite() => {
+            err("result is not a finite number")
+        }
+        value => Ok(value),
+    }
+}
+
+fn as_bool(value: CalcValue) -> Result<bool, CalcError> {
+    match value {
+        CalcValue::Bool(b) => Ok(b),
+        other => err(format!("expected a bool, got {}", other.type_name())),
+    }
+}
+
+fn time_quantity(seconds: f64, date_only: bool) -> CalcValue {
+    let unit = if date_only || seconds.abs() >= SECONDS_PER_DAY as f64 {
+        "d"
+    } else if seconds.abs() >= 3_600.0 {
+        "h"
+    } else if seconds.abs() >= 60.0 {
+        "min"
+    } else {
+        "s"
+    };
+    CalcValue::Quantity {
+        value: seconds,
+        unit: unit_named(unit),
+    }
+}
+
+/// A time value, or an error when `seconds` overflowed or left the
+/// supported range.
+fn checked_time(seconds: Option<i64>, date_only: bool) -> Result<CalcValue, CalcError> {
+    match seconds.filter(|seconds| seconds.abs() <= MAX_TIME_SECONDS) {
+        Some(seconds) => Ok(CalcValue::Time { seconds, date_only }),
+        None => err("date is out of range"),
+    }
+}
+
+fn shift_time(seconds: i64, date_only: bool, delta: f64) -> Result<CalcValue, CalcError> {
+    let delta = delta.round();
+    if !delta.is_finite() || delta.abs() > (2 * MAX_TIME_SECONDS) as f64 {
+        return err("date is out of range");
+    }
+    let delta = delta as i64;
+    checked_time(
+        seconds.checked_add(delta),
+        date_only && delta % SECONDS_PER_DAY == 0,
+    )
+}
+
+fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0)
+}
+
+fn binary(op: &str, left: CalcValue, right: CalcValue) -> Result<CalcValue, CalcError> {
+    use CalcValue::*;
+    if matches!(op, "==" | "!=" | "<" | "<=" | ">" | ">=") {
+        return compare(op, &left, &right);
+    }
+    let mismatch = |left: &CalcValue, right: &CalcValue| {
+        err(format!(
+            "cannot apply `{op}` to {} and {}",
+            left.type_name(),
+            right.type_name()
+        ))
+    };
+    Ok(match (op, left, right) {
+        ("+", Text(a), b) => Text(format!("{a}{b}")),
+        ("+", a, Text(b)) => Text(format!("{a}{b}")),
+        (_, Number(a), Number(b)) => Number(match op {
+            "+" => a + b,
+            "-" => a - b,
+            "*" => a * b,
+            "/" if b == 0.0 => return err("division by zero"),
+            "/" => a / b,
+            "%" if b == 0.0 => return err("division by zero"),
+            "%" => a % b,
+            _ => a.powf(b),
+        }),
+        (
+            "+" | "-" | "/" | "%",
+            Quantity { value: a, unit },
+            Quantity {
+                value: b,
+                unit: other,
+            },
+        ) if unit.dimension == other.dimension => match op {
+            "+" => Quantity { value: a + b, unit },
+            "-" => Quantity { value: a - b, unit },
+            _ if b == 0.0 => return err("division by zero"),
+            "/" => Number(a / b),
+            _ => Quantity { value: a % b, unit },
+        },
+        ("*", Quantity { value, unit }, Number(n)) | ("*", Number(n), Quantity { value, unit }) => {
+            Quantity {
+                value: value * n,
+                unit,
+            }
+        }
+        ("/", Quantity { value, unit }, Number(n)) => {
+            if n == 0.0 {
+                return err("division by zero");
+            }
+            Quantity {
+                value: value / n,
+                unit,
+            }
+        }
+        ("+", Time { seconds, date_only }, Quantity { value, unit })
+        | ("+", Quantity { value, unit }, Time { seconds, date_only })
+            if unit.dimension == Dimension::Time =>
+        {
+            shift_time(seconds, date_only, value)?
+        }
+        ("-", Time { seconds, date_only }, Quantity { value, unit })
+            if unit.dimension == Dimension::Time =>
+        {
+            shift_time(seconds, date_only, -value)?
+        }
+        (
+            "-",
+            Time {
+                seconds: a,
+                date_only: a_date,
+            },
+            Time {
+                seconds: b,
+                date_only: b_date,
+            },
+        ) => time_quantity((a - b) as f64, a_date && b_date),
+        (_, left, right) => return mismatch(&left, &right),
+    })
+}
+
+fn compare(op: &str, left: &CalcValue, right: &CalcValue) -> Result<CalcValue, CalcError> {
+    use std::cmp::Ordering;
+    use CalcValue::*;
+    let ordering = match (left, right) {
+        (Number(a), Number(b)) => {
+            if approx_eq(*a, *b) {
+                Ordering::Equal
+            } else {
+                a.total_cmp(b)
+            }
+        }
+        (
+            Quantity { value: a, unit },
+            Quantity {
+                value: b,
+                unit: other,
+            },
+        ) if unit.dimension == other.dimension => {
+            if approx_eq(*a, *b) {
+                Ordering::Equal
+            } else {
+                a.total_cmp(b)
+            }
+        }
+        (Time { seconds: a, .. }, Time { seconds: b, .. }) => a.cmp(b),
+        (Text(a), Text(b)) => a.cmp(b),
+        (Bool(a), Bool(b)) if matches!(op, "==" | "!=") => a.cmp(b),
+        _ => {
+            return err(format!(
+                "cannot compare {} and {}",
+                left.type_name(),
+                right.type_name()
+            ))
+        }
+    };
+    Ok(Bool(match op {
+        "==" => ordering == Ordering::Equal,
+        "!=" => ordering != Ordering::Equal,
+        "<" => ordering == Ordering::Less,
+        "<=" => ordering != Ordering::Greater,
+        ">" => ordering == Ordering::Greater,
+        _ => ordering != Ordering::Less,
+    }))
+}
+
+fn number_arg(name: &str, value: &CalcValue) -> Result<f64, CalcError> {
+    match value {
+        CalcValue::Number(n) => Ok(*n),
+        other => err(format!(
+            "{name} expects a number, got {}",
+            other.type_name()
+        )),
+    }
+}
+
+fn text_arg<'a>(name: &str, value: &'a CalcValue) -> Result<&'a str, CalcError> {
+    match value {
+        CalcValue::Text(text) => Ok(text),
+        other => err(format!("{name} expects text, got {}", other.type_name())),
+    }
+}
+
+fn time_arg(name: &str, value: &CalcValue) -> Result<(i64, bool), CalcError> {
+    match value {
+        CalcValue::Time { seconds, date_only } => Ok((*seconds, *date_only)),
+        CalcValue::Text(text) => match parse_time(text) {
+            Some(CalcValue::Time { seconds, date_only }) => Ok((seconds, date_only)),
+            _ => err(format!("{name}: `{text}` is not a date")),
+        },
+        other => err(format!("{name} expects a date, got {}", other.type_name())),
+    }
+}
+
+/// Apply `f` to a number, or to a quantity in its display unit.
+fn map_magnitude(
+    name: &str,
+    value: &CalcValue,
+    f: impl Fn(f64) -> f64,
+) -> Result<CalcValue, CalcError> {
+    match value {
+        CalcValue::Number(n) => Ok(CalcValue::Number(f(*n))),
+        CalcValue::Quantity { value, unit } => Ok(CalcValue::Quantity {
+            value: f(value / unit.factor) * unit.factor,
+            unit,
+        }),
+        other => err(format!(
+            "{name} expects a number, got {}",
+            other.type_name()
+        )),
+    }
+}
+
+fn call(name: &str, args: Vec<CalcValue>) -> Result<CalcValue, CalcError> {
+    use CalcValue::*;
+    let arity = |min: usize, max: usize| {
+        if args.len() < min || args.len() > max {
+            let expected = if min == max {
+                min.to_string()
+            } else {
+                format!("{min}-{max}")
+            };
+            return err(format!("{name} takes {expected} argument(s)"));
+        }
+        Ok(())
+    };
+    let numbers = || {
+        if args.is_empty() {
+            return err(format!("{name} needs at least one argument"));
+        }
+        args.iter()
+            .map(|arg| number_arg(name, arg))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    Ok(match name {
+        "abs" => {
+            arity(1, 1)?;
+            map_magnitude(name, &args[0], f64::abs)?
+        }
+        "floor" => {
+            arity(1, 1)?;
+            map_magnitude(name, &args[0], f64::floor)?
+        }
+        "ceil" => {
+            arity(1, 1)?;
+            map_magnitude(name, &args[0], f64::ceil)?
+        }
+        "round" => {
+            arity(1, 2)?;
+            let digits = match args.get(1) {
+                Some(arg) => number_arg(name, arg)?.clamp(-15.0, 15.0) as i32,
+                None => 0,
+            };
+            let scale = 10f64.powi(digits);
+            map_magnitude(name, &args[0], |n| (n * scale).round() / scale)?
+        }
+        "sqrt" | "ln" | "log10" | "exp" => {
+            arity(1, 1)?;
+            let n = number_arg(name, &args[0])?;
+            if matches!(name, "sqrt" | "ln" | "log10") && (n < 0.0 || (n == 0.0 && name != "sqrt"))
+            {
+                return err(format!("{name} is undefined for {}", format_number(n)));
+            }
+            Number(match name {
+                "sqrt" => n.sqrt(),
+                "ln" => n.ln(),
+                "log10" => n.log10(),
+                _ => n.exp(),
+            })
+        }
+        "pow" => {
+            arity(2, 2)?;
+            Number(number_arg(name, &args[0])?.powf(number_arg(name, &args[1])?))
+        }
+        "min" => Number(numbers()?.into_iter().fold(f64::INFINITY, f64::min)),
+        "max" => Number(numbers()?.into_iter().fold(f64::NEG_INFINITY, f64::max)),
+        "sum" => Number(numbers()?.into_iter().sum()),
+        "avg" => {
+            let values = numbers()?;
+            Number(values.iter().sum::<f64>() / values.len() as f64)
+        }
+        "number" => {
+            arity(1, 1)?;
+            match &args[0] {
+                Text(text) => match CalcValue::from_text(text) {
+                    number @ Number(_) => number,
+                    _ => return err(format!("`{text}` is not a number")),
+                },
+                other => Number(other.as_number().ok_or_else(|| {
+                    CalcError(format!("cannot make a number of {}", other.type_name()))
+                })?),
+            }
+        }
+        "text" => {
+            arity(1, 1)?;
+            Text(args[0].to_string())
+        }
+        "len" => {
+            arity(1, 1)?;
+            Number(text_arg(name, &args[0])?.chars().count() as f64)
+        }
+        "upper" | "lower" | "trim" => {
+            arity(1, 1)?;
+            let text = text_arg(name, &args[0])?;
+            Text(match name {
+                "upper" => text.to_uppercase(),
+                "lower" => text.to_lowercase(),
+                _ => text.trim().to_string(),
+            })
+        }
+        "concat" => Text(args.iter().map(ToString::to_string).collect()),
+        "contains" => {
+            arity(2, 2)?;
+            Bool(text_arg(name, &args[0])?.contains(text_arg(name, &args[1])?))
+        }
+        "replace" => {
+            arity(3, 3)?;
+            Text(
+                text_arg(name, &args[0])?
+                    .replace(text_arg(name, &args[1])?, text_arg(name, &args[2])?),
+            )
+        }
+        "substr" => {
+            arity(2, 3)?;
+            let text = text_arg(name, &args[0])?;
+            let start = number_arg(name, &args[1])?.max(0.0) as usize;
+            let len = match args.get(2) {
+                Some(arg) => number_arg(name, arg)?.max(0.0) as usize,
+                None => usize::MAX,
+            };
+            Text(text.chars().skip(start).take(len).collect())
+        }
+        "date" | "datetime" => {
+            arity(1, 1)?;
+            let (seconds, _) = time_arg(name, &args[0])?;
+            if name == "date" {
+                Time {
+                    seconds: seconds.div_euclid(SECONDS_PER_DAY) * SECONDS_PER_DAY,
+                    date_only: true,
+                }
+            } else {
+                Time {
+                    seconds,
+                    date_only: false,
+                }
+            }
+        }
+        "add_months" => {
+            arity(2, 2)?;
+            let (seconds, date_only) = time_arg(name, &args[0])?;
+            let months = number_arg(name, &args[1])?;
+            if months.fract() != 0.0 || months.abs() > 120_000.0 {
+                return err("add_months expects a whole number of months");
+            }
+            let days = seconds.div_euclid(SECONDS_PER_DAY);
+            let (y, m, d) = civil_from_days(days);
+            let index = y * 12 + (m - 1) + months as i64;
+            let (y, m) = (index.div_euclid(12), index.rem_euclid(12) + 1);
+            if y.abs() > MAX_YEAR {
+                return err("date is out of range");
+            }
+            let d = d.min(days_in_month(y, m));
+            let seconds = days_from_civil(y, m, d)
+                .checked_mul(SECONDS_PER_DAY)
+                .and_then(|day| day.checked_add(seconds.rem_euclid(SECONDS_PER_DAY)));
+            checked_time(seconds, date_only)?
+        }
+        "days_between" => {
+            arity(2, 2)?;
+            let (a, _) = time_arg(name, &args[0])?;
+            let (b, _) = time_arg(name, &args[1])?;
+            Number((b - a) as f64 / SECONDS_PER_DAY as f64)
+        }
+        "weekday" => {
+            arity(1, 1)?;
+            let (seconds, _) = time_arg(name, &args[0])?;
+            const NAMES: [&str; 7] = [
+                "Sunday",
+                "Monday",
+                "Tuesday",
+                "Wednesday",
+                "Thursday",
+                "Friday",
+                "Saturday",
+            ];
+            let day = (seconds.div_euclid(SECONDS_PER_DAY) + 4).rem_euclid(7);
+            Text(NAMES[day as usize].to_string())
+        }
+        "year" | "month" | "day" => {
+            arity(1, 1)?;
+            let (seconds, _) = time_arg(name, &args[0])?;
+            let (y, m, d) = civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+            Number(match name {
+                "year" => y,
+                "month" => m,
+                _ => d,
+            } as f64)
+        }
+        _ => return err(format!("unknown function `{name}`")),
+    })
+}
+
+/// `YYYY-MM-DD`, or an RFC 3339-style timestamp (`T` or space separator,
+/// optional seconds, `Z` or `±HH:MM` offset, UTC when omitted).
+fn parse_time(text: &str) -> Option<CalcValue> {
+    let text = text.trim();
+    let (date, rest) = match text.find(['T', ' ']) {
+        Some(at) => (&text[..at], Some(&text[at + 1..])),
+        None => (text, None),
+    };
+    let mut parts = date.splitn(3, '-');
+    let y = parts.next()?.parse::<i64>().ok()?;
+    let m = parts.next()?.parse::<i64>().ok()?;
+    let d = parts.next()?.parse::<i64>().ok()?;
+    if y.abs() > MAX_YEAR || !(1..=12).contains(&m) || d < 1 || d > days_in_month(y, m) {
+        return None;
+    }
+    let days = days_from_civil(y, m, d);
+    let Some(rest) = rest else {
+        return checked_time(days.checked_mul(SECONDS_PER_DAY), true).ok();
+    };
+    let (clock, offset) = if let Some(clock) = rest.strip_suffix(['Z', 'z']) {
+        (clock, 0)
+    } else if let Some(at) = rest.rfind(['+', '-']) {
+        let (sign, zone) = (
+            if &rest[at..at + 1] == "-" { -1 } else { 1 },
+            &rest[at + 1..],
+        );
+        let (h, mi) = zone.split_once(':')?;
+        let (h, mi) = (h.parse::<i64>().ok()?, mi.parse::<i64>().ok()?);
+        if !(0..=23).contains(&h) || !(0..=59).contains(&mi) {
+            return None;
+        }
+        (&rest[..at], sign * (h * 3_600 + mi * 60))
+    } else {
+        (rest, 0)
+    };
+    let clock = clock.split('.').next()?;
+    let mut fields = clock.split(':');
+    let h = fields.next()?.parse::<i64>().ok()?;
+    let mi = fields.next()?.parse::<i64>().ok()?;
+    let s = fields.next().map_or(Some(0), |s| s.parse::<i64>().ok())?;
+    if h > 23 || mi > 59 || s > 60 || fields.next().is_some() {
+        return None;
+    }
+    let seconds = days
+        .checked_mul(SECONDS_PER_DAY)
+        .and_then(|day| day.checked_add(h * 3_600 + mi * 60 + s))
+        .and_then(|seconds| seconds.checked_sub(offset));
+    checked_time(seconds, false).ok()
+}
+
+fn is_leap(y: i64) -> bool {
+    (y % 4 == 0 && y % 100 != 0) || y % 400 == 0
+}
+
+fn days_in_month(y: i64, m: i64) -> i64 {
+    match m {
+        2 if is_leap(y) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + i64::from(m <= 2);
+    (y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calc(expr: &str) -> String {
+        evaluate(expr)
+            .unwrap_or_else(|e| panic!("{expr}: {e}"))
+            .to_string()
+    }
+
+    #[test]
+    fn evaluates_numbers_units_dates_and_text() {
+        assert_eq!(calc("0.1 + 0.2"), "0.3");
+        assert_eq!(calc("2 + 3 * 4 ^ 2 / 8"), "8");
+        assert_eq!(calc("-2 ^ 2"), "-4");
+        assert_eq!(calc("1_250_000 * 0.07"), "87500");
+        assert_eq!(calc("round(10 / 3, 2)"), "3.33");
+        assert_eq!(calc("max(3, 9, 4) - min(3, 9, 4)"), "6");
+
+        assert_eq!(calc("3 km + 200 m"), "3.2 km");
+        assert_eq!(calc("90 min to h"), "1.5 h");
+        assert_eq!(calc("5 GiB / 512 MiB"), "10");
+        assert_eq!(calc("12 in to cm"), "30.48 cm");
+        assert_eq!(calc("2 lb > 900 g"), "true");
+
+        assert_eq!(calc("date(\"2024-01-31\") + 30 days"), "2024-03-01");
+        assert_eq!(calc("add_months(\"2024-01-31\", 1)"), "2024-02-29");
+        assert_eq!(calc("date(\"2026-12-25\") - date(\"2026-10-17\")"), "69 d");
+        assert_eq!(calc("weekday(\"2026-10-17\")"), "Saturday");
+        assert_eq!(
+            calc("datetime(\"2026-03-01T23:30:00+02:00\") + 45 min"),
+            "2026-03-01T22:15:00Z"
+        );
+        assert_eq!(calc("days_between(\"2024-02-01\", \"2024-03-01\")"), "29");
+
+        assert_eq!(calc("upper(trim('  ok ')) + \"!\""), "OK!");
+        assert_eq!(calc("substr(\"release-notes\", 8)"), "notes");
+        assert_eq!(
+            calc("if(len(\"abc\") == 3 and not false, 'yes', 1 / 0)"),
+            "yes"
+        );
+
+        let vars = |name: &str| match name {
+            "run.count" => Some(CalcValue::from_text("41")),
+            "date" => Some(CalcValue::from_text("2026-10-17")),
+            _ => None,
+        };
+        assert_eq!(
+            evaluate_with("run.count + 1", &vars)
+                .expect("vars")
+                .to_string(),
+            "42"
+        );
+        assert_eq!(
+            evaluate_with("date(date) + 2 wk", &vars)
+                .expect("vars")
+                .to_string(),
+            "2026-10-31"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for (expr, message) in [
+            ("1 / 0", "division by zero"),
+            ("3 km + 2 kg", "cannot apply `+`"),
+            ("5 to km", "cannot convert number"),
+            ("2 +", "unexpected end"),
+            ("(1 + 2", "missing `)`"),
+            ("open(\"/etc/passwd\")", "unknown function `open`"),
+            ("secret", "unknown name `secret`"),
+            ("date(\"2026-02-30\")", "is not a date"),
+            ("10 ^ 400", "not a finite number"),
+            ("date(\"2026-01-01\") + 1e300 s", "out of range"),
+            ("date(\"2026-01-01\") - 1e18 d", "out of range"),
+            ("add_months(\"999999-12-01\", 1)", "out of range"),
+            ("date(\"99999999999-01-01\")", "is not a date"),
+            (
+                "date(\"2026-01-01T00:00+99999999999999:00\")",
+                "is not a date",
+            ),
+        ] {
+            let error = evaluate(expr).expect_err(expr).to_string();
+            assert!(error.contains(message), "{expr}: {error}");
+        }
+        let nested = format!("{}1{}", "(".repeat(200), ")".repeat(200));
+        assert!(evaluate(&nested).is_err());
+    }
+}
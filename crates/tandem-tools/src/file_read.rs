@@ -0,0 +1,377 @@
+use std::io::ErrorKind;
+use std::path::Path;
+
+use serde_json::{json, Value};
+use tokio::fs;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, BufReader};
+
+use tandem_types::ToolResult;
+
+use crate::file_write::content_hash;
+
+/// Files larger than this are refused unless a range is requested.
+pub(crate) const DEFAULT_MAX_READ_BYTES: u64 = 25 * 1024 * 1024;
+/// Whole-file reads above this size carry a warning suggesting ranges.
+const LARGE_FILE_WARN_BYTES: u64 = 1024 * 1024;
+const DEFAULT_LINE_LIMIT: u64 = 2_000;
+const DEFAULT_BYTE_LIMIT: u64 = 64 * 1024;
+const SNIFF_BYTES: usize = 8 * 1024;
+const HEXDUMP_DEFAULT_BYTES: u64 = 512;
+const HEXDUMP_MAX_BYTES: u64 = 4 * 1024;
+
+/// Portion of a file requested by the model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReadRange {
+    Whole,
+    /// 1-based first line and number of lines.
+    Lines {
+        start: u64,
+        limit: u64,
+    },
+    Bytes {
+        offset: u64,
+        limit: u64,
+    },
+}
+
+impl ReadRange {
+    pub(crate) fn from_args(args: &Value) -> Self {
+        let byte_offset = args.get("byte_offset").and_then(Value::as_u64);
+        let byte_limit = args.get("byte_limit").and_then(Value::as_u64);
+        if byte_offset.is_some() || byte_limit.is_some() {
+            return Self::Bytes {
+                offset: byte_offset.unwrap_or(0),
+                limit: byte_limit.unwrap_or(DEFAULT_BYTE_LIMIT).max(1),
+            };
+        }
+        let offset = args.get("offset").and_then(Value::as_u64);
+        let limit = args.get("limit").and_then(Value::as_u64);
+        if offset.is_some() || limit.is_some() {
+            return Self::Lines {
+                start: offset.unwrap_or(1).max(1),
+                limit: limit.unwrap_or(DEFAULT_LINE_LIMIT).max(1),
+            };
+        }
+        Self::Whole
+    }
+}
+
+/// Structured failure for a read, keyed by `reason` so models can tell a
+/// missing file from an unreadable or oversized one.
+pub(crate) fn read_error_result(
+    reason: &str,
+    message: String,
+    path: &str,
+    resolved: &Path,
+    extra: Value,
+) -> ToolResult {
+    let mut metadata = json!({
+        "ok": false,
+        "reason": reason,
+        "path": path,
+        "resolved_path": resolved.to_string_lossy(),
+    });
+    if let (Some(obj), Value::Object(extra)) = (metadata.as_object_mut(), extra) {
+        obj.extend(extra);
+    }
+    ToolResult {
+        output: format!("read failed ({reason}): {message}"),
+        metadata,
+    }
+}
+
+pub(crate) fn io_error_reason(err: &std::io::Error) -> &'static str {
+    match err.kind() {
+        ErrorKind::NotFound => "path_not_found",
+        ErrorKind::PermissionDenied => "permission_denied",
+        _ => "io_error",
+    }
+}
+
+pub(crate) fn io_error_result(err: &std::io::Error, path: &str, resolved: &Path) -> ToolResult {
+    read_error_result(
+        io_error_reason(err),
+        err.to_string(),
+        path,
+        resolved,
+        json!({ "error": err.to_string() }),
+    )
+}
+
+/// NUL bytes or invalid UTF-8 (other than a sequence cut at the sniff
+/// boundary) mark a file as binary.
+pub(crate) fn looks_binary(sample: &[u8]) -> bool {
+    if sample.contains(&0) {
+        return true;
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => false,
+        Err(err) => err.error_len().is_some(),
+    }
+}
+
+pub(crate) fn hexdump(bytes: &[u8], base_offset: u64) -> String {
+    let mut out = String::new();
+    for (row, chunk) in bytes.chunks(16).enumerate() {
+        let hex = chunk
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ascii = chunk
+            .iter()
+            .map(|b| {
+                if b.is_ascii_graphic() || *b == b' ' {
+                    *b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        out.push_str(&format!(
+            "{:08x}  {hex:<47}  |{ascii}|\n",
+            base_offset + (row as u64) * 16
+        ));
+    }
+    out
+}
+
+async fn read_bytes_at(path: &Path, offset: u64, limit: u64) -> std::io::Result<Vec<u8>> {
+    let mut file = fs::File::open(path).await?;
+    file.seek(std::io::SeekFrom::Start(offset)).await?;
+    let mut buf = Vec::new();
+    file.take(limit).read_to_end(&mut buf).await?;
+    Ok(buf)
+}
+
+/// Read a text or binary file honoring `range`. Only the requested portion is
+/// held in memory for ranged reads.
+pub(crate) async fn read_file_range(
+    path: &str,
+    resolved: &Path,
+    size: u64,
+    range: ReadRange,
+    max_size: u64,
+) -> ToolResult {
+    let sniff = match read_bytes_at(resolved, 0, SNIFF_BYTES as u64).await {
+        Ok(bytes) => bytes,
+        Err(err) => return io_error_result(&err, path, resolved),
+    };
+    let resolved_str = resolved.to_string_lossy().to_string();
+
+    if looks_binary(&sniff) {
+        let (offset, limit) = match range {
+            ReadRange::Bytes { offset, limit } => (offset, limit.min(HEXDUMP_MAX_BYTES)),
+            _ => (0, HEXDUMP_DEFAULT_BYTES),
+        };
+        let bytes = match read_bytes_at(resolved, offset, limit).await {
+            Ok(bytes) => bytes,
+            Err(err) => return io_error_result(&err, path, resolved),
+        };
+        let end = offset + bytes.len() as u64;
+        return ToolResult {
+            output: format!(
+                "binary file ({size} bytes); hexdump of bytes {offset}..{end} (use byte_offset/byte_limit for more):\n{}",
+                hexdump(&bytes, offset)
+            ),
+            metadata: json!({
+                "path": resolved_str,
+                "type": "binary",
+                "size": size,
+                "byte_offset": offset,
+                "bytes_read": bytes.len(),
+            }),
+        };
+    }
+
+    match range {
+        ReadRange::Whole => {
+            if size > max_size {
+                return read_error_result(
+                    "too_large",
+                    format!(
+                        "file is {size} bytes (limit {max_size}); read it in ranges with offset/limit (lines) or byte_offset/byte_limit"
+                    ),
+                    path,
+                    resolved,
+                    json!({ "size": size, "max_size": max_size }),
+                );
+            }
+            let bytes = match fs::read(resolved).await {
+                Ok(bytes) => bytes,
+                Err(err) => return io_error_result(&err, path, resolved),
+            };
+            let mut metadata = json!({
+                "path": resolved_str,
+                "type": "text",
+                "size": size,
+                "sha256": content_hash(&bytes),
+            });
+            let mut output = match String::from_utf8(bytes) {
+                Ok(text) => text,
+                Err(err) => {
+                    metadata["lossy"] = json!(true);
+                    String::from_utf8_lossy(err.as_bytes()).into_owned()
+                }
+            };
+            if size > LARGE_FILE_WARN_BYTES {
+                let warning = format!(
+                    "file is {size} bytes; prefer offset/limit to read only the lines you need"
+                );
+                output.push_str(&format!("\n\n[warning: {warning}]"));
+                metadata["warning"] = json!(warning);
+            }
+            ToolResult { output, metadata }
+        }
+        ReadRange::Lines { start, limit } => {
+            let file = match fs::File::open(resolved).await {
+                Ok(file) => file,
+                Err(err) => return io_error_result(&err, path, resolved),
+            };
+            let mut reader = BufReader::new(file);
+            let mut line = Vec::new();
+            let mut line_no = 0u64;
+            let mut output = String::new();
+            let mut returned = 0u64;
+            loop {
+                line.clear();
+                match reader.read_until(b'\n', &mut line).await {
+                    Ok(0) => break,
+                    Ok(_) => {}
+                    Err(err) => return io_error_result(&err, path, resolved),
+                }
+                line_no += 1;
+                if line_no >= start && returned < limit {
+                    output.push_str(&String::from_utf8_lossy(&line));
+                    returned += 1;
+                }
+            }
+            let end_line = if returned == 0 {
+                None
+            } else {
+                Some(start + returned - 1)
+            };
+            ToolResult {
+                output,
+                metadata: json!({
+                    "path": resolved_str,
+                    "type": "text",
+                    "size": size,
+                    "start_line": start,
+                    "end_line": end_line,
+                    "total_lines": line_no,
+                    "has_more": end_line.is_some_and(|end| end < line_no),
+                }),
+            }
+        }
+        ReadRange::Bytes { offset, limit } => {
+            let limit = limit.min(max_size);
+            let bytes = match read_bytes_at(resolved, offset, limit).await {
+                Ok(bytes) => bytes,
+                Err(err) => return io_error_result(&err, path, resolved),
+            };
+            let end = offset + bytes.len() as u64;
+            ToolResult {
+                output: String::from_utf8_lossy(&bytes).into_owned(),
+                metadata: json!({
+                    "path": resolved_str,
+                    "type": "text",
+                    "size": size,
+                    "byte_offset": offset,
+                    "bytes_read": bytes.len(),
+                    "has_more": end < size,
+                }),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str, contents: &[u8]) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("tandem-file-read-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).expect("dir");
+        let path = dir.join(name);
+        std::fs::write(&path, contents).expect("write");
+        path
+    }
+
+    #[test]
+    fn range_prefers_bytes_over_lines() {
+        assert_eq!(ReadRange::from_args(&json!({})), ReadRange::Whole);
+        assert_eq!(
+            ReadRange::from_args(&json!({"offset": 10})),
+            ReadRange::Lines {
+                start: 10,
+                limit: DEFAULT_LINE_LIMIT
+            }
+        );
+        assert_eq!(
+            ReadRange::from_args(&json!({"offset": 3, "byte_limit": 16})),
+            ReadRange::Bytes {
+                offset: 0,
+                limit: 16
+            }
+        );
+    }
+
+    #[test]
+    fn binary_sniff_tolerates_split_utf8_at_boundary() {
+        assert!(looks_binary(b"PK\x03\x04\x00\x00"));
+        assert!(!looks_binary("héllo".as_bytes()));
+        let cut = &"é".as_bytes()[..1];
+        assert!(!looks_binary(cut));
+        assert!(looks_binary(b"\xff\xfe plain"));
+    }
+
+    #[tokio::test]
+    async fn line_range_reports_position_and_remaining() {
+        let path = temp_file("lines.txt", b"one\ntwo\nthree\nfour\n");
+        let result = read_file_range(
+            "lines.txt",
+            &path,
+            19,
+            ReadRange::Lines { start: 2, limit: 2 },
+            DEFAULT_MAX_READ_BYTES,
+        )
+        .await;
+        assert_eq!(result.output, "two\nthree\n");
+        assert_eq!(result.metadata["end_line"], json!(3));
+        assert_eq!(result.metadata["total_lines"], json!(4));
+        assert_eq!(result.metadata["has_more"], json!(true));
+    }
+
+    #[tokio::test]
+    async fn binary_and_oversized_files_are_reported_explicitly() {
+        let path = temp_file("blob.bin", &[0u8, 1, 2, 0x41, 0xff]);
+        let result = read_file_range(
+            "blob.bin",
+            &path,
+            5,
+            ReadRange::Whole,
+            DEFAULT_MAX_READ_BYTES,
+        )
+        .await;
+        assert_eq!(result.metadata["type"], json!("binary"));
+        assert!(result.output.contains("00000000  00 01 02 41 ff"));
+
+        let path = temp_file("big.txt", b"0123456789");
+        let result = read_file_range("big.txt", &path, 10, ReadRange::Whole, 4).await;
+        assert_eq!(result.metadata["reason"], json!("too_large"));
+        let ranged = read_file_range(
+            "big.txt",
+            &path,
+            10,
+            ReadRange::Bytes {
+                offset: 2,
+                limit: 3,
+            },
+            4,
+        )
+        .await;
+        assert_eq!(ranged.output, "234");
+    }
+}
@@ -0,0 +1,324 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use tandem_types::ToolResult;
+
+use crate::file_read::io_error_reason;
+
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Hex SHA-256 of file bytes, as reported by `read` and checked by
+/// `if_match_hash`.
+pub(crate) fn content_hash(bytes: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(bytes);
+    format!("{:x}", hasher.finalize())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WriteOptions {
+    pub create_dirs: bool,
+    pub backup: bool,
+    pub preserve_format: bool,
+    pub if_match_hash: Option<String>,
+}
+
+impl WriteOptions {
+    pub(crate) fn from_args(args: &Value) -> Self {
+        let flag =
+            |key: &str, default: bool| args.get(key).and_then(Value::as_bool).unwrap_or(default);
+        Self {
+            create_dirs: flag("create_dirs", true),
+            backup: flag("backup", false),
+            preserve_format: flag("preserve_format", true),
+            if_match_hash: args
+                .get("if_match_hash")
+                .and_then(Value::as_str)
+                .map(|s| s.trim().to_ascii_lowercase())
+                .filter(|s| !s.is_empty()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TextEncoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl TextEncoding {
+    fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+            Self::Utf8Bom
+        } else if bytes.starts_with(&[0xFF, 0xFE]) {
+            Self::Utf16Le
+        } else if bytes.starts_with(&[0xFE, 0xFF]) {
+            Self::Utf16Be
+        } else {
+            Self::Utf8
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Utf8 => "utf-8",
+            Self::Utf8Bom => "utf-8-bom",
+            Self::Utf16Le => "utf-16le",
+            Self::Utf16Be => "utf-16be",
+        }
+    }
+
+    fn encode(self, text: &str) -> Vec<u8> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        match self {
+            Self::Utf8 => text.as_bytes().to_vec(),
+            Self::Utf8Bom => [&[0xEF, 0xBB, 0xBF][..], text.as_bytes()].concat(),
+            Self::Utf16Le => std::iter::once(0xFEFFu16)
+                .chain(text.encode_utf16())
+                .flat_map(u16::to_le_bytes)
+                .collect(),
+            Self::Utf16Be => std::iter::once(0xFEFFu16)
+                .chain(text.encode_utf16())
+                .flat_map(u16::to_be_bytes)
+                .collect(),
+        }
+    }
+}
+
+fn uses_crlf(bytes: &[u8], encoding: TextEncoding) -> bool {
+    let crlf = match encoding {
+        TextEncoding::Utf16Le => bytes.windows(4).filter(|w| w == b"\r\0\n\0").count(),
+        TextEncoding::Utf16Be => bytes.windows(4).filter(|w| w == b"\0\r\0\n").count(),
+        _ => bytes.windows(2).filter(|w| w == b"\r\n").count(),
+    };
+    let lf = match encoding {
+        TextEncoding::Utf16Le => bytes.windows(2).filter(|w| w == b"\n\0").count(),
+        TextEncoding::Utf16Be => bytes.windows(2).filter(|w| w == b"\0\n").count(),
+        _ => bytes.iter().filter(|b| **b == b'\n').count(),
+    };
+    crlf > 0 && crlf * 2 >= lf
+}
+
+/// Encode `content` the way the file it replaces was stored: same BOM/UTF-16
+/// encoding and CRLF line endings when the original used them.
+fn encode_like_existing(content: &str, existing: Option<&[u8]>) -> (Vec<u8>, TextEncoding, bool) {
+    let Some(existing) = existing else {
+        return (content.as_bytes().to_vec(), TextEncoding::Utf8, false);
+    };
+    let encoding = TextEncoding::detect(existing);
+    let crlf = uses_crlf(existing, encoding) && !content.contains("\r\n");
+    let text = if crlf {
+        content.replace('\n', "\r\n")
+    } else {
+        content.to_string()
+    };
+    (encoding.encode(&text), encoding, crlf)
+}
+
+fn write_error(reason: &str, message: String, path: &str, extra: Value) -> ToolResult {
+    let mut metadata = json!({ "ok": false, "reason": reason, "path": path });
+    if let (Some(obj), Value::Object(extra)) = (metadata.as_object_mut(), extra) {
+        obj.extend(extra);
+    }
+    ToolResult {
+        output: format!("write failed ({reason}): {message}"),
+        metadata,
+    }
+}
+
+fn temp_path_for(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "file".to_string());
+    let seq = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    path.with_file_name(format!(".{name}.tandem-{}-{seq}.tmp", std::process::id()))
+}
+
+/// Write via a sibling temp file and rename so readers never observe a
+/// partially written file. Existing permissions are carried over.
+pub(crate) async fn atomic_write(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    let tmp = temp_path_for(path);
+    let result = async {
+        let mut file = fs::File::create(&tmp).await?;
+        file.write_all(bytes).await?;
+        file.sync_all().await?;
+        drop(file);
+        if let Ok(meta) = fs::metadata(path).await {
+            fs::set_permissions(&tmp, meta.permissions()).await?;
+        }
+        fs::rename(&tmp, path).await
+    }
+    .await;
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp).await;
+    }
+    result
+}
+
+pub(crate) async fn write_file(
+    path: &str,
+    resolved: &Path,
+    content: &str,
+    options: &WriteOptions,
+) -> ToolResult {
+    let existing = match fs::read(resolved).await {
+        Ok(bytes) => Some(bytes),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => {
+            return write_error(
+                io_error_reason(&err),
+                err.to_string(),
+                path,
+                json!({ "error": err.to_string() }),
+            )
+        }
+    };
+    let current_hash = existing.as_deref().map(content_hash);
+    if let Some(expected) = options.if_match_hash.as_deref() {
+        if current_hash.as_deref() != Some(expected) {
+            return write_error(
+                "hash_mismatch",
+                "file changed since it was last read; read it again before writing".to_string(),
+                path,
+                json!({ "expected_sha256": expected, "current_sha256": current_hash }),
+            );
+        }
+    }
+
+    if let Some(parent) = resolved.parent().filter(|p| !p.as_os_str().is_empty()) {
+        if fs::metadata(parent).await.is_err() {
+            if !options.create_dirs {
+                return write_error(
+                    "parent_missing",
+                    format!(
+                        "directory `{}` does not exist (set create_dirs=true)",
+                        parent.to_string_lossy()
+                    ),
+                    path,
+                    json!({}),
+                );
+            }
+            if let Err(err) = fs::create_dir_all(parent).await {
+                return write_error(
+                    io_error_reason(&err),
+                    err.to_string(),
+                    path,
+                    json!({ "error": err.to_string() }),
+                );
+            }
+        }
+    }
+
+    let mut backup_path = None;
+    if options.backup && existing.is_some() {
+        let mut name = resolved.as_os_str().to_owned();
+        name.push(".bak");
+        let target = PathBuf::from(name);
+        if let Err(err) = fs::copy(resolved, &target).await {
+            return write_error(
+                io_error_reason(&err),
+                format!("backup failed: {err}"),
+                path,
+                json!({ "error": err.to_string() }),
+            );
+        }
+        backup_path = Some(target);
+    }
+
+    let (bytes, encoding, crlf) = if options.preserve_format {
+        encode_like_existing(content, existing.as_deref())
+    } else {
+        (content.as_bytes().to_vec(), TextEncoding::Utf8, false)
+    };
+    if let Err(err) = atomic_write(resolved, &bytes).await {
+        return write_error(
+            io_error_reason(&err),
+            err.to_string(),
+            path,
+            json!({ "error": err.to_string() }),
+        );
+    }
+    ToolResult {
+        output: "ok".to_string(),
+        metadata: json!({
+            "path": resolved.to_string_lossy(),
+            "created": existing.is_none(),
+            "bytes": bytes.len(),
+            "sha256": content_hash(&bytes),
+            "previous_sha256": current_hash,
+            "encoding": encoding.label(),
+            "line_endings": if crlf { "crlf" } else { "lf" },
+            "backup_path": backup_path.map(|p| p.to_string_lossy().to_string()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preserves_crlf_and_bom_of_existing_file() {
+        let existing = b"\xEF\xBB\xBFa\r\nb\r\n";
+        let (bytes, encoding, crlf) = encode_like_existing("x\ny\n", Some(existing));
+        assert_eq!(bytes, b"\xEF\xBB\xBFx\r\ny\r\n");
+        assert_eq!(encoding, TextEncoding::Utf8Bom);
+        assert!(crlf);
+
+        let (bytes, _, crlf) = encode_like_existing("x\ny\n", Some(b"a\nb\n"));
+        assert_eq!(bytes, b"x\ny\n");
+        assert!(!crlf);
+    }
+
+    #[test]
+    fn re_encodes_utf16_files() {
+        let existing = TextEncoding::Utf16Le.encode("a\r\nb");
+        let (bytes, encoding, crlf) = encode_like_existing("hi\n", Some(&existing));
+        assert_eq!(encoding, TextEncoding::Utf16Le);
+        assert!(crlf);
+        assert_eq!(
+            bytes,
+            vec![0xFF, 0xFE, b'h', 0, b'i', 0, b'\r', 0, b'\n', 0]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_stale_hash_and_keeps_backup() {
+        let dir = std::env::temp_dir().join(format!("tandem-file-write-{}", std::process::id()));
+        let path = dir.join("nested").join("a.txt");
+        let missing_parent = WriteOptions {
+            create_dirs: false,
+            ..WriteOptions::from_args(&json!({}))
+        };
+        let result = write_file("a.txt", &path, "one", &missing_parent).await;
+        assert_eq!(result.metadata["reason"], json!("parent_missing"));
+
+        let result = write_file("a.txt", &path, "one", &WriteOptions::from_args(&json!({}))).await;
+        assert_eq!(result.metadata["created"], json!(true));
+        let first_hash = content_hash(b"one");
+        assert_eq!(result.metadata["sha256"], json!(first_hash));
+
+        let stale = WriteOptions::from_args(&json!({ "if_match_hash": "deadbeef" }));
+        let result = write_file("a.txt", &path, "two", &stale).await;
+        assert_eq!(result.metadata["reason"], json!("hash_mismatch"));
+
+        let fresh =
+            WriteOptions::from_args(&json!({ "if_match_hash": first_hash, "backup": true }));
+        let result = write_file("a.txt", &path, "two", &fresh).await;
+        assert_eq!(result.output, "ok");
+        assert_eq!(std::fs::read_to_string(&path).expect("read"), "two");
+        let mut backup = path.clone().into_os_string();
+        backup.push(".bak");
+        assert_eq!(std::fs::read_to_string(backup).expect("backup"), "one");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
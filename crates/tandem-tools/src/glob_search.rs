@@ -0,0 +1,255 @@
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::Serialize;
+use serde_json::Value;
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1_000;
+/// Upper bound on matches examined per call so huge trees stay responsive.
+const MAX_SCANNED_MATCHES: usize = 50_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GlobFileType {
+    Any,
+    File,
+    Dir,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GlobSort {
+    Path,
+    Modified,
+    Size,
+}
+
+/// Filters, ordering and paging for a `glob` call.
+#[derive(Debug, Clone)]
+pub(crate) struct GlobQuery {
+    pub exclude: Vec<glob::Pattern>,
+    pub file_type: GlobFileType,
+    pub extensions: Vec<String>,
+    pub modified_after_ms: Option<u64>,
+    pub modified_before_ms: Option<u64>,
+    pub sort: GlobSort,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::String(s)) => vec![s.clone()],
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|v| v.as_str().map(ToString::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl GlobQuery {
+    pub(crate) fn from_args(args: &Value) -> Result<Self, String> {
+        let exclude = string_list(args.get("exclude"))
+            .iter()
+            .map(|p| glob::Pattern::new(p).map_err(|err| format!("invalid exclude `{p}`: {err}")))
+            .collect::<Result<Vec<_>, _>>()?;
+        let file_type = match args.get("type").and_then(Value::as_str) {
+            None | Some("any") => GlobFileType::Any,
+            Some("file") => GlobFileType::File,
+            Some("dir") | Some("directory") => GlobFileType::Dir,
+            Some(other) => return Err(format!("unknown type `{other}` (use file, dir or any)")),
+        };
+        let sort = match args.get("sort").and_then(Value::as_str) {
+            None | Some("path") => GlobSort::Path,
+            Some("mtime") | Some("modified") => GlobSort::Modified,
+            Some("size") => GlobSort::Size,
+            Some(other) => return Err(format!("unknown sort `{other}` (use path, mtime or size)")),
+        };
+        Ok(Self {
+            exclude,
+            file_type,
+            extensions: string_list(args.get("extensions"))
+                .into_iter()
+                .map(|ext| ext.trim_start_matches('.').to_ascii_lowercase())
+                .collect(),
+            modified_after_ms: args.get("modified_after_ms").and_then(Value::as_u64),
+            modified_before_ms: args.get("modified_before_ms").and_then(Value::as_u64),
+            sort,
+            offset: args.get("offset").and_then(Value::as_u64).unwrap_or(0) as usize,
+            limit: args
+                .get("limit")
+                .and_then(Value::as_u64)
+                .map(|v| (v as usize).clamp(1, MAX_PAGE_SIZE))
+                .unwrap_or(DEFAULT_PAGE_SIZE),
+        })
+    }
+
+    fn accepts(&self, relative: &Path, entry: &GlobEntry) -> bool {
+        if self
+            .exclude
+            .iter()
+            .any(|pattern| pattern.matches_path(relative))
+        {
+            return false;
+        }
+        match self.file_type {
+            GlobFileType::File if entry.is_dir => return false,
+            GlobFileType::Dir if !entry.is_dir => return false,
+            _ => {}
+        }
+        if !self.extensions.is_empty() {
+            let ext = relative
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(str::to_ascii_lowercase)
+                .unwrap_or_default();
+            if !self.extensions.contains(&ext) {
+                return false;
+            }
+        }
+        if let Some(after) = self.modified_after_ms {
+            if entry.modified_ms.is_none_or(|m| m < after) {
+                return false;
+            }
+        }
+        if let Some(before) = self.modified_before_ms {
+            if entry.modified_ms.is_none_or(|m| m > before) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct GlobEntry {
+    pub path: String,
+    pub is_dir: bool,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified_ms: Option<u64>,
+}
+
+impl GlobEntry {
+    fn from_path(path: &Path) -> Self {
+        let meta = std::fs::metadata(path).ok();
+        Self {
+            path: path.display().to_string(),
+            is_dir: meta.as_ref().is_some_and(|m| m.is_dir()),
+            size: meta.as_ref().map(|m| m.len()).unwrap_or(0),
+            modified_ms: meta
+                .and_then(|m| m.modified().ok())
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_millis() as u64),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct GlobPage {
+    pub entries: Vec<GlobEntry>,
+    pub total: usize,
+    /// True when `MAX_SCANNED_MATCHES` cut the scan short.
+    pub scan_truncated: bool,
+    pub next_offset: Option<usize>,
+}
+
+/// Match `candidates` against the query, then sort and page them. `base` is
+/// the anchor directory used for exclude patterns.
+pub(crate) fn collect_page(
+    candidates: impl Iterator<Item = PathBuf>,
+    base: &Path,
+    query: &GlobQuery,
+) -> GlobPage {
+    let mut entries = Vec::new();
+    let mut scan_truncated = false;
+    for path in candidates {
+        if entries.len() >= MAX_SCANNED_MATCHES {
+            scan_truncated = true;
+            break;
+        }
+        let relative = path.strip_prefix(base).unwrap_or(&path).to_path_buf();
+        let entry = GlobEntry::from_path(&path);
+        if query.accepts(&relative, &entry) {
+            entries.push(entry);
+        }
+    }
+    match query.sort {
+        GlobSort::Path => entries.sort_by(|a, b| a.path.cmp(&b.path)),
+        GlobSort::Modified => entries.sort_by(|a, b| {
+            b.modified_ms
+                .cmp(&a.modified_ms)
+                .then_with(|| a.path.cmp(&b.path))
+        }),
+        GlobSort::Size => {
+            entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)))
+        }
+    }
+    let total = entries.len();
+    let page = entries
+        .into_iter()
+        .skip(query.offset)
+        .take(query.limit)
+        .collect::<Vec<_>>();
+    let end = query.offset + page.len();
+    GlobPage {
+        entries: page,
+        total,
+        scan_truncated,
+        next_offset: (end < total).then_some(end),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn filters_sorts_and_pages_matches() {
+        let base = std::env::temp_dir().join(format!("tandem-glob-search-{}", std::process::id()));
+        std::fs::create_dir_all(base.join("src")).expect("dir");
+        std::fs::create_dir_all(base.join("target")).expect("dir");
+        for (name, body) in [
+            ("src/a.rs", "a"),
+            ("src/b.rs", "bbbb"),
+            ("src/c.md", "c"),
+            ("target/d.rs", "d"),
+        ] {
+            std::fs::write(base.join(name), body).expect("write");
+        }
+        let candidates = || {
+            ["src/a.rs", "src/b.rs", "src/c.md", "target/d.rs", "src"]
+                .into_iter()
+                .map(|p| base.join(p))
+        };
+
+        let query = GlobQuery::from_args(&json!({
+            "exclude": ["target/**"],
+            "type": "file",
+            "extensions": [".RS"],
+            "sort": "size",
+            "limit": 1
+        }))
+        .expect("query");
+        let page = collect_page(candidates(), &base, &query);
+        assert_eq!(page.total, 2);
+        assert_eq!(page.entries.len(), 1);
+        assert!(page.entries[0].path.ends_with("b.rs"));
+        assert_eq!(page.entries[0].size, 4);
+        assert_eq!(page.next_offset, Some(1));
+
+        let query = GlobQuery::from_args(&json!({ "type": "dir" })).expect("query");
+        let page = collect_page(candidates(), &base, &query);
+        assert_eq!(page.total, 1);
+        assert!(page.entries[0].is_dir);
+
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        assert!(GlobQuery::from_args(&json!({ "type": "socket" })).is_err());
+        assert!(GlobQuery::from_args(&json!({ "exclude": ["[unclosed"] })).is_err());
+    }
+}
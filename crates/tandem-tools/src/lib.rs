@@ -2,12 +2,12 @@ use std::collections::{hash_map::DefaultHasher, HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::anyhow;
 use async_trait::async_trait;
-use ignore::WalkBuilder;
 use regex::Regex;
 use serde_json::{json, Value};
 use tandem_skills::SkillService;
@@ -27,7 +27,34 @@ use tandem_agent_teams::{
 };
 use tandem_memory::types::{MemorySearchResult, MemoryTier};
 use tandem_memory::MemoryManager;
-use tandem_types::{ToolResult, ToolSchema};
+use tandem_runtime::WorkspaceIgnore;
+use tandem_types::{PathStyle, ToolResult, ToolSchema};
+
+pub mod calc;
+mod command_policy;
+mod file_read;
+mod file_write;
+mod glob_search;
+mod path_style;
+mod release_notes;
+mod secrets_scan;
+mod timeouts;
+mod web_cache;
+mod workspace_packages;
+
+use calc::CalcValue;
+pub use command_policy::{
+    builtin_rules, CommandAction, CommandDecision, CommandPolicyConfig, CommandRule,
+};
+use file_read::{io_error_result, read_file_range, ReadRange, DEFAULT_MAX_READ_BYTES};
+use file_write::{write_file, WriteOptions};
+use glob_search::{collect_page, GlobQuery};
+use path_style::{path_style_from_args, styled_path_within, StyledPath};
+pub use secrets_scan::file_has_secrets;
+use secrets_scan::{PrecommitMode, Severity};
+pub use timeouts::{is_timeout_result, ToolTimeoutConfig, ToolTimeoutProfile};
+pub use web_cache::{webfetch_cache, WebFetchCache, WebFetchCacheEntry, WebFetchCacheStats};
+use workspace_packages::{resolve_search_scope, SearchScope};
 
 #[async_trait]
 pub trait Tool: Send + Sync {
@@ -45,6 +72,8 @@ pub trait Tool: Send + Sync {
 #[derive(Clone)]
 pub struct ToolRegistry {
     tools: Arc<RwLock<HashMap<String, Arc<dyn Tool>>>>,
+    timeouts: Arc<RwLock<ToolTimeoutConfig>>,
+    command_policy: Arc<RwLock<CommandPolicyConfig>>,
 }
 
 impl ToolRegistry {
@@ -56,6 +85,13 @@ impl ToolRegistry {
         map.insert("edit".to_string(), Arc::new(EditTool));
         map.insert("glob".to_string(), Arc::new(GlobTool));
         map.insert("grep".to_string(), Arc::new(GrepTool));
+        map.insert("secrets_scan".to_string(), Arc::new(SecretsScanTool));
+        map.insert("calc".to_string(), Arc::new(CalcTool));
+        map.insert("release_changes".to_string(), Arc::new(ReleaseChangesTool));
+        map.insert(
+            "release_notes_verify".to_string(),
+            Arc::new(ReleaseNotesVerifyTool),
+        );
         map.insert("webfetch".to_string(), Arc::new(WebFetchTool));
         map.insert("webfetch_html".to_string(), Arc::new(WebFetchHtmlTool));
         map.insert("mcp_debug".to_string(), Arc::new(McpDebugTool));
@@ -68,6 +104,7 @@ impl ToolRegistry {
         map.insert("task".to_string(), Arc::new(TaskTool));
         map.insert("question".to_string(), Arc::new(QuestionTool));
         map.insert("spawn_agent".to_string(), Arc::new(SpawnAgentTool));
+        map.insert("tool_result_read".to_string(), Arc::new(ToolResultReadTool));
         map.insert("skill".to_string(), Arc::new(SkillTool));
         map.insert("memory_store".to_string(), Arc::new(MemoryStoreTool));
         map.insert("memory_list".to_string(), Arc::new(MemoryListTool));
@@ -82,6 +119,8 @@ impl ToolRegistry {
         map.insert("sendmessage".to_string(), Arc::new(SendMessageCompatTool));
         Self {
             tools: Arc::new(RwLock::new(map)),
+            timeouts: Arc::new(RwLock::new(ToolTimeoutConfig::default())),
+            command_policy: Arc::new(RwLock::new(CommandPolicyConfig::default())),
         }
     }
 
@@ -117,18 +156,11 @@ impl ToolRegistry {
         removed
     }
 
+    /// Run `name` without an approval step: shell commands the command
+    /// policy denies or asks about are refused.
     pub async fn execute(&self, name: &str, args: Value) -> anyhow::Result<ToolResult> {
-        let tool = {
-            let tools = self.tools.read().await;
-            resolve_registered_tool(&tools, name)
-        };
-        let Some(tool) = tool else {
-            return Ok(ToolResult {
-                output: format!("Unknown tool: {name}"),
-                metadata: json!({}),
-            });
-        };
-        tool.execute(args).await
+        self.execute_for_profile(name, args, CancellationToken::new(), None)
+            .await
     }
 
     pub async fn execute_with_cancel(
@@ -136,6 +168,89 @@ impl ToolRegistry {
         name: &str,
         args: Value,
         cancel: CancellationToken,
+    ) -> anyhow::Result<ToolResult> {
+        self.execute_for_profile(name, args, cancel, None).await
+    }
+
+    /// Run `name` within the timeout configured for it and agent `profile`.
+    /// When the budget runs out the tool's cancellation token is fired and a
+    /// result tagged `outcome: "timeout"` is returned instead of an error.
+    pub async fn execute_for_profile(
+        &self,
+        name: &str,
+        args: Value,
+        cancel: CancellationToken,
+        profile: Option<&str>,
+    ) -> anyhow::Result<ToolResult> {
+        if let Some(blocked) = self
+            .command_policy_block(name, &args, CommandAction::Ask)
+            .await
+        {
+            return Ok(blocked);
+        }
+        self.run_tool(name, args, cancel, profile).await
+    }
+
+    /// Like [`Self::execute_for_profile`] for callers that already had the
+    /// user approve commands the policy asks about; denied commands are still
+    /// refused.
+    pub async fn execute_approved(
+        &self,
+        name: &str,
+        args: Value,
+        cancel: CancellationToken,
+        profile: Option<&str>,
+    ) -> anyhow::Result<ToolResult> {
+        if let Some(blocked) = self
+            .command_policy_block(name, &args, CommandAction::Deny)
+            .await
+        {
+            return Ok(blocked);
+        }
+        self.run_tool(name, args, cancel, profile).await
+    }
+
+    /// Result refusing a shell call whose command the policy decides at
+    /// `threshold` or stricter.
+    async fn command_policy_block(
+        &self,
+        name: &str,
+        args: &Value,
+        threshold: CommandAction,
+    ) -> Option<ToolResult> {
+        if canonical_tool_name(name) != "bash" {
+            return None;
+        }
+        let command = args["command"].as_str().unwrap_or("").trim();
+        if command.is_empty() {
+            return None;
+        }
+        let decision = self.evaluate_command(command).await;
+        if decision.action < threshold {
+            return None;
+        }
+        let output = match decision.action {
+            CommandAction::Ask => format!(
+                "Command needs approval under the command policy: {}.",
+                decision.reason.as_deref().unwrap_or("matched an ask rule")
+            ),
+            _ => format!(
+                "Command blocked by the command policy: {}.",
+                decision.reason.as_deref().unwrap_or("matched a deny rule")
+            ),
+        };
+        Some(ToolResult {
+            output,
+            metadata: json!({"outcome": "blocked", "decision": decision}),
+        })
+    }
+
+    async fn run_tool(
+        &self,
+        name: &str,
+        args: Value,
+        cancel: CancellationToken,
+        profile: Option<&str>,
     ) -> anyhow::Result<ToolResult> {
         let tool = {
             let tools = self.tools.read().await;
@@ -147,7 +262,38 @@ impl ToolRegistry {
                 metadata: json!({}),
             });
         };
-        tool.execute_with_cancel(args, cancel).await
+        let Some(limit) = self.timeout_for(name, profile).await else {
+            return tool.execute_with_cancel(args, cancel).await;
+        };
+        let token = cancel.child_token();
+        let run = tool.execute_with_cancel(args, token.clone());
+        tokio::pin!(run);
+        tokio::select! {
+            result = &mut run => result,
+            _ = tokio::time::sleep(limit) => {
+                token.cancel();
+                let _ = tokio::time::timeout(timeouts::CANCEL_GRACE, &mut run).await;
+                tracing::warn!("tool `{name}` timed out after {}ms", limit.as_millis());
+                Ok(timeouts::timeout_result(name, limit))
+            }
+        }
+    }
+
+    pub async fn set_timeouts(&self, config: ToolTimeoutConfig) {
+        *self.timeouts.write().await = config;
+    }
+
+    pub async fn timeout_for(&self, name: &str, profile: Option<&str>) -> Option<Duration> {
+        self.timeouts.read().await.resolve(name, profile)
+    }
+
+    pub async fn set_command_policy(&self, config: CommandPolicyConfig) {
+        *self.command_policy.write().await = config;
+    }
+
+    /// Decision of the shell command policy for the command line `command`.
+    pub async fn evaluate_command(&self, command: &str) -> CommandDecision {
+        self.command_policy.read().await.evaluate(command)
     }
 }
 
@@ -406,19 +552,29 @@ fn normalize_existing_or_lexical(path: &Path) -> PathBuf {
         .unwrap_or_else(|_| normalize_path_for_compare(path))
 }
 
-fn is_within_workspace_root(path: &Path, workspace_root: &Path) -> bool {
+fn is_within_workspace_root(path: &Path, workspace_root: &Path, style: PathStyle) -> bool {
     // First compare lexical-normalized paths so non-existent target files under symlinked
     // workspace roots still pass containment checks.
-    let candidate_lexical = normalize_path_for_compare(path);
-    let root_lexical = normalize_path_for_compare(workspace_root);
-    if candidate_lexical.starts_with(&root_lexical) {
-        return true;
+    if style == PathStyle::Windows {
+        // Drive letters, separators, verbatim prefixes and case all vary on Windows.
+        if styled_path_within(path, workspace_root, style) {
+            return true;
+        }
+    } else {
+        let candidate_lexical = normalize_path_for_compare(path);
+        let root_lexical = normalize_path_for_compare(workspace_root);
+        if candidate_lexical.starts_with(&root_lexical) {
+            return true;
+        }
     }
 
     // Fallback to canonical comparison when available (best for existing paths and symlink
     // resolution consistency).
     let candidate = normalize_existing_or_lexical(path);
     let root = normalize_existing_or_lexical(workspace_root);
+    if style == PathStyle::Windows {
+        return styled_path_within(&candidate, &root, style);
+    }
     candidate.starts_with(root)
 }
 
@@ -427,10 +583,11 @@ fn resolve_tool_path(path: &str, args: &Value) -> Option<PathBuf> {
     if trimmed.is_empty() {
         return None;
     }
+    let style = path_style_from_args(args);
     if trimmed == "." || trimmed == "./" || trimmed == ".\\" {
         let cwd = effective_cwd_from_args(args);
         if let Some(workspace_root) = workspace_root_from_args(args) {
-            if !is_within_workspace_root(&cwd, &workspace_root) {
+            if !is_within_workspace_root(&cwd, &workspace_root, style) {
                 return None;
             }
         }
@@ -439,23 +596,21 @@ fn resolve_tool_path(path: &str, args: &Value) -> Option<PathBuf> {
     if is_root_only_path_token(trimmed) || is_malformed_tool_path_token(trimmed) {
         return None;
     }
-    let raw = Path::new(trimmed);
-    if !raw.is_absolute()
-        && raw
-            .components()
-            .any(|c| matches!(c, std::path::Component::ParentDir))
-    {
+    let raw = StyledPath::parse(trimmed, style);
+    if raw.is_ambiguous() || (!raw.is_absolute() && raw.had_parent_dir) {
         return None;
     }
 
     let resolved = if raw.is_absolute() {
         raw.to_path_buf()
     } else {
-        effective_cwd_from_args(args).join(raw)
+        StyledPath::from_path(&effective_cwd_from_args(args), style)
+            .join(&raw)
+            .to_path_buf()
     };
 
     if let Some(workspace_root) = workspace_root_from_args(args) {
-        if !is_within_workspace_root(&resolved, &workspace_root) {
+        if !is_within_workspace_root(&resolved, &workspace_root, style) {
             return None;
         }
     } else if raw.is_absolute() {
@@ -487,6 +642,7 @@ fn resolve_read_path_fallback(path: &str, args: &Value) -> Option<PathBuf> {
         return None;
     }
 
+    let style = path_style_from_args(args);
     let workspace_root = workspace_root_from_args(args);
     let effective_cwd = effective_cwd_from_args(args);
     let mut search_roots = vec![effective_cwd.clone()];
@@ -497,21 +653,26 @@ fn resolve_read_path_fallback(path: &str, args: &Value) -> Option<PathBuf> {
     }
 
     let token_lower = token.to_lowercase();
+    let rules = workspace_ignore(args);
     for root in search_roots {
         if let Some(workspace_root) = workspace_root.as_ref() {
-            if !is_within_workspace_root(&root, workspace_root) {
+            if !is_within_workspace_root(&root, workspace_root, style) {
                 continue;
             }
         }
 
         let mut matches = Vec::new();
-        for entry in WalkBuilder::new(&root).build().flatten() {
+        for entry in rules
+            .path_walker(std::slice::from_ref(&root))
+            .build()
+            .flatten()
+        {
             if !entry.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
                 continue;
             }
             let candidate = entry.path();
             if let Some(workspace_root) = workspace_root.as_ref() {
-                if !is_within_workspace_root(candidate, workspace_root) {
+                if !is_within_workspace_root(candidate, workspace_root, style) {
                     continue;
                 }
             }
@@ -546,7 +707,7 @@ fn sandbox_path_denied_result(path: &str, args: &Value) -> ToolResult {
         .map(PathBuf::from)
         .and_then(|name| {
             if let Some(root) = workspace_root.as_ref() {
-                if is_within_workspace_root(&effective_cwd, root) {
+                if is_within_workspace_root(&effective_cwd, root, path_style_from_args(args)) {
                     Some(effective_cwd.join(name))
                 } else {
                     Some(root.join(name))
@@ -692,6 +853,13 @@ impl Tool for BashTool {
         } = shell;
         let effective_cwd = effective_cwd_from_args(&args);
         command.current_dir(&effective_cwd);
+        let precommit = precommit_secrets_findings(cmd, &effective_cwd).await;
+        if let Some(blocked) = precommit
+            .as_ref()
+            .and_then(PrecommitFindings::blocked_result)
+        {
+            return Ok(blocked);
+        }
         if let Some(env) = args.get("env").and_then(|v| v.as_object()) {
             for (k, v) in env {
                 if let Some(value) = v.as_str() {
@@ -720,10 +888,11 @@ impl Tool for BashTool {
                 );
             }
         }
-        Ok(ToolResult {
-            output: String::from_utf8_lossy(&output.stdout).to_string(),
-            metadata,
-        })
+        let mut output = String::from_utf8_lossy(&output.stdout).to_string();
+        if let Some(findings) = precommit.as_ref() {
+            findings.annotate(&mut output, &mut metadata);
+        }
+        Ok(ToolResult { output, metadata })
     }
 
     async fn execute_with_cancel(
@@ -750,6 +919,13 @@ impl Tool for BashTool {
         } = shell;
         let effective_cwd = effective_cwd_from_args(&args);
         command.current_dir(&effective_cwd);
+        let precommit = precommit_secrets_findings(cmd, &effective_cwd).await;
+        if let Some(blocked) = precommit
+            .as_ref()
+            .and_then(PrecommitFindings::blocked_result)
+        {
+            return Ok(blocked);
+        }
         if let Some(env) = args.get("env").and_then(|v| v.as_object()) {
             for (k, v) in env {
                 if let Some(value) = v.as_str() {
@@ -807,15 +983,74 @@ impl Tool for BashTool {
                 );
             }
         }
-        Ok(ToolResult {
-            output: if stdout.is_empty() {
-                format!("command exited: {}", status)
-            } else {
-                stdout
-            },
-            metadata,
+        let mut output = if stdout.is_empty() {
+            format!("command exited: {}", status)
+        } else {
+            stdout
+        };
+        if let Some(findings) = precommit.as_ref() {
+            findings.annotate(&mut output, &mut metadata);
+        }
+        Ok(ToolResult { output, metadata })
+    }
+}
+
+/// Secrets found in what a `git commit` run through `bash` is about to record.
+struct PrecommitFindings {
+    mode: PrecommitMode,
+    report: secrets_scan::ScanReport,
+}
+
+async fn precommit_secrets_findings(cmd: &str, cwd: &Path) -> Option<PrecommitFindings> {
+    if !secrets_scan::is_git_commit_command(cmd) {
+        return None;
+    }
+    let mode = PrecommitMode::from_env();
+    if mode == PrecommitMode::Off {
+        return None;
+    }
+    let include_worktree = secrets_scan::stages_worktree(cmd);
+    let cwd = cwd.to_path_buf();
+    let report =
+        tokio::task::spawn_blocking(move || secrets_scan::precommit_scan(&cwd, include_worktree))
+            .await
+            .ok()
+            .flatten()?;
+    Some(PrecommitFindings { mode, report })
+}
+
+impl PrecommitFindings {
+    fn metadata(&self, blocked: bool) -> Value {
+        json!({
+            "mode": if self.mode == PrecommitMode::Block { "block" } else { "advisory" },
+            "blocked": blocked,
+            "count": self.report.findings.len(),
+            "findings": self.report.findings,
         })
     }
+
+    fn blocked_result(&self) -> Option<ToolResult> {
+        if self.mode != PrecommitMode::Block {
+            return None;
+        }
+        Some(ToolResult {
+            output: format!(
+                "git commit blocked: the files being committed contain possible secrets. Remove them (or mark intentional fixtures with `secrets-scan:allow`) and retry.\n{}",
+                self.report.render()
+            ),
+            metadata: json!({ "secrets_precommit": self.metadata(true) }),
+        })
+    }
+
+    fn annotate(&self, output: &mut String, metadata: &mut Value) {
+        output.push_str(&format!(
+            "\n\nWarning: the committed files contain possible secrets. Rotate any real credential and rewrite the commit before pushing.\n{}",
+            self.report.render()
+        ));
+        if let Some(obj) = metadata.as_object_mut() {
+            obj.insert("secrets_precommit".to_string(), self.metadata(false));
+        }
+    }
 }
 
 struct ShellExecutionPlan {
@@ -1055,7 +1290,7 @@ impl Tool for ReadTool {
     fn schema(&self) -> ToolSchema {
         ToolSchema {
             name: "read".to_string(),
-            description: "Read file contents. Supports text files and documents (PDF, DOCX, PPTX, XLSX, RTF).".to_string(),
+            description: "Read file contents. Supports text files and documents (PDF, DOCX, PPTX, XLSX, RTF). Use offset/limit (lines) or byte_offset/byte_limit to read large files in ranges; binary files return a hexdump preview.".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -1063,6 +1298,22 @@ impl Tool for ReadTool {
                         "type": "string",
                         "description": "Path to file"
                     },
+                    "offset": {
+                        "type": "integer",
+                        "description": "1-based line to start reading from"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Number of lines to read (default: 2000 when offset is set)"
+                    },
+                    "byte_offset": {
+                        "type": "integer",
+                        "description": "Byte position to start reading from"
+                    },
+                    "byte_limit": {
+                        "type": "integer",
+                        "description": "Number of bytes to read (default: 64KB)"
+                    },
                     "max_size": {
                         "type": "integer",
                         "description": "Max file size in bytes (default: 25MB)"
@@ -1089,29 +1340,10 @@ impl Tool for ReadTool {
                     path_buf = recovered;
                     match fs::metadata(&path_buf).await {
                         Ok(meta) => meta,
-                        Err(e) => {
-                            return Ok(ToolResult {
-                                output: format!("read failed: {}", e),
-                                metadata: json!({
-                                    "ok": false,
-                                    "reason": "path_not_found",
-                                    "path": path,
-                                    "resolved_path": path_buf.to_string_lossy(),
-                                    "error": e.to_string()
-                                }),
-                            });
-                        }
+                        Err(e) => return Ok(io_error_result(&e, path, &path_buf)),
                     }
                 } else {
-                    return Ok(ToolResult {
-                        output: format!("read failed: {}", first_err),
-                        metadata: json!({
-                            "ok": false,
-                            "reason": "path_not_found",
-                            "path": path,
-                            "error": first_err.to_string()
-                        }),
-                    });
+                    return Ok(io_error_result(&first_err, path, &path_buf));
                 }
             }
         };
@@ -1167,24 +1399,15 @@ impl Tool for ReadTool {
         }
 
         // Fallback to text reading
-        let data = match fs::read_to_string(&path_buf).await {
-            Ok(data) => data,
-            Err(e) => {
-                return Ok(ToolResult {
-                    output: format!("read failed: {}", e),
-                    metadata: json!({
-                        "ok": false,
-                        "reason": "read_text_failed",
-                        "path": path_buf.to_string_lossy(),
-                        "error": e.to_string()
-                    }),
-                });
-            }
-        };
-        Ok(ToolResult {
-            output: data,
-            metadata: json!({"path": path_buf.to_string_lossy(), "type": "text"}),
-        })
+        let max_size = args["max_size"].as_u64().unwrap_or(DEFAULT_MAX_READ_BYTES);
+        Ok(read_file_range(
+            path,
+            &path_buf,
+            metadata.len(),
+            ReadRange::from_args(&args),
+            max_size,
+        )
+        .await)
     }
 }
 
@@ -1194,13 +1417,17 @@ impl Tool for WriteTool {
     fn schema(&self) -> ToolSchema {
         ToolSchema {
             name: "write".to_string(),
-            description: "Write file contents".to_string(),
+            description: "Write file contents atomically. Preserves the existing file's line endings and encoding.".to_string(),
             input_schema: json!({
                 "type":"object",
                 "properties":{
                     "path":{"type":"string"},
                     "content":{"type":"string"},
-                    "allow_empty":{"type":"boolean"}
+                    "allow_empty":{"type":"boolean"},
+                    "create_dirs":{"type":"boolean","description":"Create missing parent directories (default: true)"},
+                    "backup":{"type":"boolean","description":"Copy the existing file to `<path>.bak` first"},
+                    "preserve_format":{"type":"boolean","description":"Keep the existing file's CRLF line endings and BOM/UTF-16 encoding (default: true)"},
+                    "if_match_hash":{"type":"string","description":"sha256 from a previous read; the write is refused if the file changed since"}
                 },
                 "required":["path", "content"]
             }),
@@ -1228,16 +1455,8 @@ impl Tool for WriteTool {
                 metadata: json!({"ok": false, "reason": "empty_content", "path": path}),
             });
         }
-        if let Some(parent) = path_buf.parent() {
-            if !parent.as_os_str().is_empty() {
-                fs::create_dir_all(parent).await?;
-            }
-        }
-        fs::write(&path_buf, content).await?;
-        Ok(ToolResult {
-            output: "ok".to_string(),
-            metadata: json!({"path": path_buf.to_string_lossy()}),
-        })
+        let options = WriteOptions::from_args(&args);
+        Ok(write_file(path, &path_buf, content, &options).await)
     }
 }
 
@@ -1282,102 +1501,581 @@ impl Tool for GlobTool {
     fn schema(&self) -> ToolSchema {
         ToolSchema {
             name: "glob".to_string(),
-            description: "Find files by glob".to_string(),
-            input_schema: json!({"type":"object","properties":{"pattern":{"type":"string"}}}),
+            description: "Find files by glob, relative to the session workspace. Supports excludes, type/extension/mtime filters, sorting and pagination.".to_string(),
+            input_schema: json!({
+                "type":"object",
+                "properties":{
+                    "pattern":{"type":"string"},
+                    "path":{"type":"string","description":"Directory to anchor relative patterns (default: session working directory)"},
+                    "scope":{"type":"string","description":"Workspace package name(s) or package directories (comma-separated) to match relative patterns inside, or \"*\" for everything. Defaults to packages with recently touched files for `**/` patterns in monorepos."},
+                    "exclude":{"type":"array","items":{"type":"string"},"description":"Glob patterns, relative to the anchor, to drop"},
+                    "type":{"type":"string","enum":["any","file","dir"]},
+                    "extensions":{"type":"array","items":{"type":"string"}},
+                    "modified_after_ms":{"type":"integer"},
+                    "modified_before_ms":{"type":"integer"},
+                    "sort":{"type":"string","enum":["path","mtime","size"]},
+                    "offset":{"type":"integer"},
+                    "limit":{"type":"integer","description":"Page size (default: 100, max: 1000)"}
+                }
+            }),
+        }
+    }
+    async fn execute(&self, args: Value) -> anyhow::Result<ToolResult> {
+        let pattern = args["pattern"].as_str().unwrap_or("*");
+        if pattern.contains("..") {
+            return Ok(ToolResult {
+                output: "pattern denied by sandbox policy".to_string(),
+                metadata: json!({"pattern": pattern}),
+            });
+        }
+        if is_malformed_tool_pattern_token(pattern) {
+            return Ok(ToolResult {
+                output: "pattern denied by sandbox policy".to_string(),
+                metadata: json!({"pattern": pattern}),
+            });
+        }
+        let query = match GlobQuery::from_args(&args) {
+            Ok(query) => query,
+            Err(err) => {
+                return Ok(ToolResult {
+                    output: format!("glob failed: {err}"),
+                    metadata: json!({"ok": false, "reason": "invalid_arguments", "pattern": pattern}),
+                })
+            }
+        };
+        let style = path_style_from_args(&args);
+        let workspace_root = workspace_root_from_args(&args);
+        let effective_cwd = effective_cwd_from_args(&args);
+        // Relative patterns are anchored inside the workspace, never the process CWD.
+        let anchor = match args.get("path").and_then(|v| v.as_str()) {
+            Some(path) if !path.trim().is_empty() => match resolve_walk_root(path, &args) {
+                Some(anchor) => anchor,
+                None => return Ok(sandbox_path_denied_result(path, &args)),
+            },
+            _ => match workspace_root.as_ref() {
+                Some(root) if !is_within_workspace_root(&effective_cwd, root, style) => {
+                    root.clone()
+                }
+                _ => effective_cwd.clone(),
+            },
+        };
+        let absolute = StyledPath::parse(pattern, style).is_absolute();
+        // Excluded directories are searched only when the call points into one.
+        let rules = workspace_ignore(&args);
+        let explicit_excluded = rules.excludes(&anchor, true);
+        // Only "match anywhere" patterns are narrowed to recently touched
+        // packages by default; a path-shaped pattern says where to look.
+        let matches_anywhere = pattern.starts_with("**/") || !pattern.contains('/');
+        let scope = if absolute {
+            None
+        } else {
+            match search_scope(&args, !has_explicit_path(&args) && matches_anywhere) {
+                Ok(scope) => scope,
+                Err(err) => return Ok(scope_error_result(err)),
+            }
+        };
+        let collect = |anchors: &[PathBuf]| -> anyhow::Result<_> {
+            let mut candidates = Vec::new();
+            for base in anchors {
+                let scoped_pattern = if absolute {
+                    pattern.to_string()
+                } else {
+                    base.join(pattern).to_string_lossy().to_string()
+                };
+                candidates.extend((glob::glob(&scoped_pattern)?).flatten().filter(|path| {
+                    !is_discovery_ignored_path(path)
+                        && (explicit_excluded || !rules.excludes(path, path.is_dir()))
+                        && workspace_root
+                            .as_ref()
+                            .is_none_or(|root| is_within_workspace_root(path, root, style))
+                }));
+            }
+            Ok(collect_page(candidates.into_iter(), &anchor, &query))
+        };
+        let anchors = scope
+            .as_ref()
+            .map(SearchScope::roots)
+            .unwrap_or_else(|| vec![anchor.clone()]);
+        let mut page = collect(&anchors)?;
+        let widened = page.total == 0 && scope.as_ref().is_some_and(|scope| scope.inferred);
+        if widened {
+            page = collect(std::slice::from_ref(&anchor))?;
+        }
+        let mut output = page
+            .entries
+            .iter()
+            .map(|entry| entry.path.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if let Some(next) = page.next_offset {
+            output.push_str(&format!(
+                "\n... {} more (use offset={next})",
+                page.total - next
+            ));
+        }
+        if let Some(note) = scope_note(scope.as_ref(), widened) {
+            output.push_str(&format!("\n{note}"));
+        }
+        Ok(ToolResult {
+            output,
+            metadata: json!({
+                "count": page.entries.len(),
+                "total": page.total,
+                "offset": query.offset,
+                "next_offset": page.next_offset,
+                "scan_truncated": page.scan_truncated,
+                "entries": page.entries,
+                "anchor": anchor,
+                "effective_cwd": effective_cwd,
+                "workspace_root": workspace_root,
+                "scope": scope_metadata(scope.as_ref(), widened)
+            }),
+        })
+    }
+}
+
+fn is_discovery_ignored_path(path: &Path) -> bool {
+    path.components()
+        .any(|component| component.as_os_str() == ".tandem")
+}
+
+/// Package scope for a search call; see [`resolve_search_scope`]. Packages
+/// are detected at the workspace root.
+fn search_scope(args: &Value, infer: bool) -> Result<Option<SearchScope>, String> {
+    let workspace_root =
+        workspace_root_from_args(args).unwrap_or_else(|| effective_cwd_from_args(args));
+    resolve_search_scope(args, &workspace_root, infer)
+}
+
+/// `workspace_ignore` rules for the workspace the call runs in.
+fn workspace_ignore(args: &Value) -> Arc<WorkspaceIgnore> {
+    let root = workspace_root_from_args(args).unwrap_or_else(|| effective_cwd_from_args(args));
+    WorkspaceIgnore::for_root(&root)
+}
+
+fn has_explicit_path(args: &Value) -> bool {
+    args["path"]
+        .as_str()
+        .map(str::trim)
+        .is_some_and(|p| !p.is_empty() && p != "." && p != "./")
+}
+
+/// Tell the model which packages were searched when it did not ask for a
+/// scope, so it knows how to widen the search.
+fn scope_note(scope: Option<&SearchScope>, widened: bool) -> Option<String> {
+    let scope = scope.filter(|scope| scope.inferred)?;
+    let names = scope.names().join(", ");
+    Some(if widened {
+        format!("(no matches in recently touched packages {names}; searched the whole workspace)")
+    } else {
+        format!("(searched recently touched packages {names}; pass scope=\"*\" to search the whole workspace)")
+    })
+}
+
+fn scope_metadata(scope: Option<&SearchScope>, widened: bool) -> Value {
+    json!({
+        "packages": scope.map(SearchScope::names),
+        "inferred": scope.is_some_and(|scope| scope.inferred),
+        "widened": widened,
+    })
+}
+
+fn scope_error_result(err: String) -> ToolResult {
+    ToolResult {
+        output: err,
+        metadata: json!({"count": 0, "ok": false, "reason": "unknown_scope"}),
+    }
+}
+
+const SCOPE_SCHEMA_DESCRIPTION: &str = "Workspace package name(s) or package directories to search (comma-separated), or \"*\" for everything. Defaults to packages with recently touched files in monorepos.";
+
+struct GrepTool;
+#[async_trait]
+impl Tool for GrepTool {
+    fn schema(&self) -> ToolSchema {
+        ToolSchema {
+            name: "grep".to_string(),
+            description: "Regex search in files".to_string(),
+            input_schema: json!({"type":"object","properties":{"pattern":{"type":"string"},"path":{"type":"string"},"scope":{"type":"string","description":SCOPE_SCHEMA_DESCRIPTION}}}),
+        }
+    }
+    async fn execute(&self, args: Value) -> anyhow::Result<ToolResult> {
+        let pattern = args["pattern"].as_str().unwrap_or("");
+        let root = args["path"].as_str().unwrap_or(".");
+        let Some(root_path) = resolve_walk_root(root, &args) else {
+            return Ok(sandbox_path_denied_result(root, &args));
+        };
+        let regex = Regex::new(pattern)?;
+        let scope = match search_scope(&args, !has_explicit_path(&args)) {
+            Ok(scope) => scope,
+            Err(err) => return Ok(scope_error_result(err)),
+        };
+        let roots = scope
+            .as_ref()
+            .map(SearchScope::roots)
+            .unwrap_or_else(|| vec![root_path.clone()]);
+        let rules = workspace_ignore(&args);
+        let mut out = grep_roots(&regex, &roots, &rules).await;
+        let widened = out.is_empty() && scope.as_ref().is_some_and(|scope| scope.inferred);
+        if widened {
+            out = grep_roots(&regex, std::slice::from_ref(&root_path), &rules).await;
+        }
+        let mut output = out.join("\n");
+        if let Some(note) = scope_note(scope.as_ref(), widened) {
+            output.push_str(&format!("\n{note}"));
+        }
+        Ok(ToolResult {
+            output,
+            metadata: json!({
+                "count": out.len(),
+                "path": root_path.to_string_lossy(),
+                "scope": scope_metadata(scope.as_ref(), widened),
+            }),
+        })
+    }
+}
+
+async fn grep_roots(regex: &Regex, roots: &[PathBuf], rules: &Arc<WorkspaceIgnore>) -> Vec<String> {
+    let mut out = Vec::new();
+    for entry in rules.walker(roots).build().flatten() {
+        if !entry.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
+            continue;
+        }
+        let path = entry.path();
+        if is_discovery_ignored_path(path) {
+            continue;
+        }
+        if let Ok(content) = fs::read_to_string(path).await {
+            for (idx, line) in content.lines().enumerate() {
+                if regex.is_match(line) {
+                    out.push(format!("{}:{}:{}", path.display(), idx + 1, line));
+                    if out.len() >= 100 {
+                        break;
+                    }
+                }
+            }
+        }
+        if out.len() >= 100 {
+            break;
+        }
+    }
+    out
+}
+
+struct SecretsScanTool;
+#[async_trait]
+impl Tool for SecretsScanTool {
+    fn schema(&self) -> ToolSchema {
+        ToolSchema {
+            name: "secrets_scan".to_string(),
+            description: "Scan workspace files for credentials (API keys, tokens, private keys). Findings are redacted. mode: all (default), changed (modified/untracked) or staged.".to_string(),
+            input_schema: json!({
+                "type":"object",
+                "properties":{
+                    "path":{"type":"string"},
+                    "mode":{"type":"string","enum":["all","changed","staged"]},
+                    "min_severity":{"type":"string","enum":["low","medium","high","critical"]},
+                    "max_findings":{"type":"integer"}
+                }
+            }),
+        }
+    }
+    async fn execute(&self, args: Value) -> anyhow::Result<ToolResult> {
+        let root = args["path"].as_str().unwrap_or(".");
+        let Some(root_path) = resolve_walk_root(root, &args) else {
+            return Ok(sandbox_path_denied_result(root, &args));
+        };
+        let mode = args["mode"].as_str().unwrap_or("all").trim().to_string();
+        let min_severity = match args["min_severity"].as_str() {
+            None => Severity::Low,
+            Some(raw) => match Severity::parse(raw) {
+                Some(severity) => severity,
+                None => {
+                    return Ok(ToolResult {
+                        output: format!(
+                            "unknown min_severity `{raw}` (use low, medium, high or critical)"
+                        ),
+                        metadata: json!({"ok": false}),
+                    })
+                }
+            },
+        };
+        let max_findings = args["max_findings"]
+            .as_u64()
+            .map(|v| v.clamp(1, 1_000) as usize)
+            .unwrap_or(secrets_scan::DEFAULT_MAX_FINDINGS);
+        let files = match mode.as_str() {
+            "all" => None,
+            "changed" | "staged" => {
+                match secrets_scan::git_listed_files(&root_path, mode == "staged") {
+                    Ok(files) => Some(files),
+                    Err(err) => {
+                        return Ok(ToolResult {
+                            output: format!(
+                                "secrets_scan mode `{mode}` needs a git checkout: {err}"
+                            ),
+                            metadata: json!({"ok": false, "mode": mode}),
+                        })
+                    }
+                }
+            }
+            other => {
+                return Ok(ToolResult {
+                    output: format!("unknown mode `{other}` (use all, changed or staged)"),
+                    metadata: json!({"ok": false}),
+                })
+            }
+        };
+        let scan_root = root_path.clone();
+        let report = tokio::task::spawn_blocking(move || {
+            secrets_scan::scan_paths(&scan_root, files, min_severity, max_findings)
+        })
+        .await?;
+        Ok(ToolResult {
+            output: report.render(),
+            metadata: json!({
+                "ok": true,
+                "path": root_path.to_string_lossy(),
+                "mode": mode,
+                "count": report.findings.len(),
+                "highest_severity": report.highest_severity().map(Severity::as_str),
+                "counts": {
+                    "critical": report.count(Severity::Critical),
+                    "high": report.count(Severity::High),
+                    "medium": report.count(Severity::Medium),
+                    "low": report.count(Severity::Low),
+                },
+                "files_scanned": report.files_scanned,
+                "files_skipped": report.files_skipped,
+                "truncated": report.truncated,
+                "findings": report.findings,
+            }),
+        })
+    }
+}
+
+/// Commits of the release range `since..until` (see [`release_notes`]).
+async fn release_range_changes(
+    args: &Value,
+    tool: &str,
+) -> Result<release_notes::ReleaseChanges, ToolResult> {
+    let root = args["path"].as_str().unwrap_or(".");
+    let Some(root_path) = resolve_walk_root(root, args) else {
+        return Err(sandbox_path_denied_result(root, args));
+    };
+    let since = args["since"].as_str().map(str::to_string);
+    let until = args["until"]
+        .as_str()
+        .map(str::trim)
+        .filter(|until| !until.is_empty())
+        .unwrap_or("HEAD")
+        .to_string();
+    let max_commits = args["max_commits"]
+        .as_u64()
+        .map(|v| v.clamp(1, 5_000) as usize)
+        .unwrap_or(release_notes::DEFAULT_MAX_COMMITS);
+    let result = tokio::task::spawn_blocking(move || {
+        release_notes::collect_changes(&root_path, since.as_deref(), &until, max_commits)
+    })
+    .await;
+    match result {
+        Ok(Ok(changes)) => Ok(changes),
+        Ok(Err(err)) => Err(ToolResult {
+            output: format!("{tool} needs a git checkout with the requested range: {err}"),
+            metadata: json!({"ok": false}),
+        }),
+        Err(err) => Err(ToolResult {
+            output: format!("{tool} failed: {err}"),
+            metadata: json!({"ok": false}),
+        }),
+    }
+}
+
+struct ReleaseChangesTool;
+#[async_trait]
+impl Tool for ReleaseChangesTool {
+    fn schema(&self) -> ToolSchema {
+        ToolSchema {
+            name: "release_changes".to_string(),
+            description: "List the non-merge git commits of a release, grouped by area (conventional-commit scope or directory), with kind, breaking flag and #PR references. since defaults to the latest tag reachable from until (default HEAD).".to_string(),
+            input_schema: json!({
+                "type":"object",
+                "properties":{
+                    "path":{"type":"string"},
+                    "since":{"type":"string","description":"Tag or revision the release starts after"},
+                    "until":{"type":"string"},
+                    "max_commits":{"type":"integer"}
+                }
+            }),
+        }
+    }
+    async fn execute(&self, args: Value) -> anyhow::Result<ToolResult> {
+        let changes = match release_range_changes(&args, "release_changes").await {
+            Ok(changes) => changes,
+            Err(result) => return Ok(result),
+        };
+        let areas = changes
+            .areas
+            .iter()
+            .map(|(area, commits)| (area.clone(), json!(commits.len())))
+            .collect::<serde_json::Map<_, _>>();
+        Ok(ToolResult {
+            output: changes.render(),
+            metadata: json!({
+                "ok": true,
+                "since": changes.since,
+                "until": changes.until,
+                "count": changes.commits.len(),
+                "truncated": changes.truncated,
+                "areas": areas,
+                "pr_refs": changes.pr_refs(),
+                "commits": changes.commits,
+            }),
+        })
+    }
+}
+
+struct ReleaseNotesVerifyTool;
+#[async_trait]
+impl Tool for ReleaseNotesVerifyTool {
+    fn schema(&self) -> ToolSchema {
+        ToolSchema {
+            name: "release_notes_verify".to_string(),
+            description: "Check drafted release notes: #PR references must appear in a commit of the release range (same since/until as release_changes) and links must answer (check_links, default true).".to_string(),
+            input_schema: json!({
+                "type":"object",
+                "properties":{
+                    "notes":{"type":"string"},
+                    "path":{"type":"string"},
+                    "since":{"type":"string"},
+                    "until":{"type":"string"},
+                    "check_links":{"type":"boolean"}
+                },
+                "required":["notes"]
+            }),
         }
     }
     async fn execute(&self, args: Value) -> anyhow::Result<ToolResult> {
-        let pattern = args["pattern"].as_str().unwrap_or("*");
-        if pattern.contains("..") {
-            return Ok(ToolResult {
-                output: "pattern denied by sandbox policy".to_string(),
-                metadata: json!({"pattern": pattern}),
-            });
-        }
-        if is_malformed_tool_pattern_token(pattern) {
+        let notes = args["notes"].as_str().unwrap_or("");
+        if notes.trim().is_empty() {
             return Ok(ToolResult {
-                output: "pattern denied by sandbox policy".to_string(),
-                metadata: json!({"pattern": pattern}),
+                output: "notes is required".to_string(),
+                metadata: json!({"ok": false}),
             });
         }
-        let workspace_root = workspace_root_from_args(&args);
-        let effective_cwd = effective_cwd_from_args(&args);
-        let scoped_pattern = if Path::new(pattern).is_absolute() {
-            pattern.to_string()
+        let changes = match release_range_changes(&args, "release_notes_verify").await {
+            Ok(changes) => changes,
+            Err(result) => return Ok(result),
+        };
+        let unknown_refs = release_notes::unknown_pr_refs(notes, &changes.pr_refs());
+        let links = release_notes::links(notes);
+        let link_checks = if args["check_links"].as_bool().unwrap_or(true) {
+            release_notes::check_links(&links).await?
         } else {
-            effective_cwd.join(pattern).to_string_lossy().to_string()
+            Vec::new()
         };
-        let mut files = Vec::new();
-        for path in (glob::glob(&scoped_pattern)?).flatten() {
-            if is_discovery_ignored_path(&path) {
-                continue;
-            }
-            if let Some(root) = workspace_root.as_ref() {
-                if !is_within_workspace_root(&path, root) {
-                    continue;
-                }
-            }
-            files.push(path.display().to_string());
-            if files.len() >= 100 {
-                break;
-            }
+        let broken = link_checks
+            .iter()
+            .filter(|check| !check.ok)
+            .collect::<Vec<_>>();
+
+        let mut output = Vec::new();
+        if !unknown_refs.is_empty() {
+            output.push(format!(
+                "PR references not found in {} commit(s) of the release: {}",
+                changes.commits.len(),
+                unknown_refs
+                    .iter()
+                    .map(|r| format!("#{r}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        for check in &broken {
+            let reason = match (check.status, check.error.as_deref()) {
+                (Some(status), _) => format!("HTTP {status}"),
+                (None, Some(error)) => error.to_string(),
+                (None, None) => "no response".to_string(),
+            };
+            output.push(format!("Broken link {}: {reason}", check.url));
+        }
+        let passed = output.is_empty();
+        if passed {
+            output.push(format!(
+                "Release notes check passed ({} PR reference(s), {} link(s) checked).",
+                release_notes::pr_refs(notes).len(),
+                link_checks.len()
+            ));
         }
         Ok(ToolResult {
-            output: files.join("\n"),
-            metadata: json!({"count": files.len(), "effective_cwd": effective_cwd, "workspace_root": workspace_root}),
+            output: output.join("\n"),
+            metadata: json!({
+                "ok": true,
+                "passed": passed,
+                "unknown_pr_refs": unknown_refs,
+                "links": link_checks,
+                "since": changes.since,
+                "until": changes.until,
+            }),
         })
     }
 }
 
-fn is_discovery_ignored_path(path: &Path) -> bool {
-    path.components()
-        .any(|component| component.as_os_str() == ".tandem")
-}
-
-struct GrepTool;
+struct CalcTool;
 #[async_trait]
-impl Tool for GrepTool {
+impl Tool for CalcTool {
     fn schema(&self) -> ToolSchema {
         ToolSchema {
-            name: "grep".to_string(),
-            description: "Regex search in files".to_string(),
-            input_schema: json!({"type":"object","properties":{"pattern":{"type":"string"},"path":{"type":"string"}}}),
+            name: "calc".to_string(),
+            description: "Evaluate an arithmetic, unit or date expression exactly instead of computing it yourself. Supports + - * / % ^, comparisons, and/or/not, units with `to` conversion (3 km + 200 m, 90 min to h, 5 GiB / 512 MiB), UTC dates (date(\"2026-03-01\") + 30 d, add_months, days_between, weekday), text functions (len, upper, lower, trim, concat, replace, substr, contains) and round, floor, ceil, sqrt, pow, ln, log10, exp, min, max, sum, avg, if. No I/O and no clock: pass today's date as a value.".to_string(),
+            input_schema: json!({
+                "type":"object",
+                "properties":{
+                    "expression":{"type":"string"},
+                    "vars":{"type":"object","description":"Values for names used in the expression; numeric strings count as numbers"}
+                },
+                "required":["expression"]
+            }),
         }
     }
     async fn execute(&self, args: Value) -> anyhow::Result<ToolResult> {
-        let pattern = args["pattern"].as_str().unwrap_or("");
-        let root = args["path"].as_str().unwrap_or(".");
-        let Some(root_path) = resolve_walk_root(root, &args) else {
-            return Ok(sandbox_path_denied_result(root, &args));
+        let expression = args["expression"].as_str().unwrap_or("").trim();
+        if expression.is_empty() {
+            return Ok(ToolResult {
+                output: "expression is required".to_string(),
+                metadata: json!({"ok": false}),
+            });
+        }
+        let vars = args["vars"].as_object().cloned().unwrap_or_default();
+        let lookup = |name: &str| match vars.get(name)? {
+            Value::Number(n) => n.as_f64().map(CalcValue::Number),
+            Value::Bool(b) => Some(CalcValue::Bool(*b)),
+            Value::String(s) => Some(CalcValue::from_text(s)),
+            _ => None,
         };
-        let regex = Regex::new(pattern)?;
-        let mut out = Vec::new();
-        for entry in WalkBuilder::new(&root_path).build().flatten() {
-            if !entry.file_type().map(|ft| ft.is_file()).unwrap_or(false) {
-                continue;
-            }
-            let path = entry.path();
-            if is_discovery_ignored_path(path) {
-                continue;
-            }
-            if let Ok(content) = fs::read_to_string(path).await {
-                for (idx, line) in content.lines().enumerate() {
-                    if regex.is_match(line) {
-                        out.push(format!("{}:{}:{}", path.display(), idx + 1, line));
-                        if out.len() >= 100 {
-                            break;
-                        }
-                    }
-                }
-            }
-            if out.len() >= 100 {
-                break;
+        match calc::evaluate_with(expression, &lookup) {
+            Ok(value) => {
+                let json_value = match &value {
+                    CalcValue::Bool(b) => json!(b),
+                    other => other
+                        .as_number()
+                        .map_or_else(|| json!(other.to_string()), |n| json!(n)),
+                };
+                Ok(ToolResult {
+                    output: format!("{expression} = {value}"),
+                    metadata: json!({
+                        "ok": true,
+                        "result": value.to_string(),
+                        "type": value.type_name(),
+                        "value": json_value,
+                    }),
+                })
             }
+            Err(err) => Ok(ToolResult {
+                output: format!("calc error: {err}"),
+                metadata: json!({"ok": false, "error": err.to_string()}),
+            }),
         }
-        Ok(ToolResult {
-            output: out.join("\n"),
-            metadata: json!({"count": out.len(), "path": root_path.to_string_lossy()}),
-        })
     }
 }
 
@@ -1396,7 +2094,8 @@ impl Tool for WebFetchTool {
                     "return":{"type":"string"},
                     "max_bytes":{"type":"integer"},
                     "timeout_ms":{"type":"integer"},
-                    "max_redirects":{"type":"integer"}
+                    "max_redirects":{"type":"integer"},
+                    "no_cache":{"type":"boolean","description":"Skip the response cache and fetch fresh"}
                 }
             }),
         }
@@ -1419,7 +2118,9 @@ impl Tool for WebFetchTool {
         let max_redirects = args["max_redirects"].as_u64().unwrap_or(5).min(20) as usize;
 
         let started = std::time::Instant::now();
-        let fetched = fetch_url_with_limits(url, timeout_ms, max_bytes, max_redirects).await?;
+        let use_cache = !args["no_cache"].as_bool().unwrap_or(false);
+        let fetched =
+            fetch_url_with_limits(url, timeout_ms, max_bytes, max_redirects, use_cache).await?;
         let raw = String::from_utf8_lossy(&fetched.buffer).to_string();
 
         let cleaned = strip_html_noise(&raw);
@@ -1472,7 +2173,8 @@ impl Tool for WebFetchTool {
                 "markdown_chars": markdown_chars,
                 "reduction_pct": reduction_pct,
                 "elapsed_ms": started.elapsed().as_millis(),
-                "truncated": fetched.truncated
+                "truncated": fetched.truncated,
+                "cache": fetched.cache
             }
         });
 
@@ -1482,7 +2184,8 @@ impl Tool for WebFetchTool {
                 "url": url,
                 "final_url": fetched.final_url,
                 "content_type": fetched.content_type,
-                "truncated": fetched.truncated
+                "truncated": fetched.truncated,
+                "cache": fetched.cache
             }),
         })
     }
@@ -1501,7 +2204,8 @@ impl Tool for WebFetchHtmlTool {
                     "url":{"type":"string"},
                     "max_bytes":{"type":"integer"},
                     "timeout_ms":{"type":"integer"},
-                    "max_redirects":{"type":"integer"}
+                    "max_redirects":{"type":"integer"},
+                    "no_cache":{"type":"boolean","description":"Skip the response cache and fetch fresh"}
                 }
             }),
         }
@@ -1522,7 +2226,9 @@ impl Tool for WebFetchHtmlTool {
         let max_redirects = args["max_redirects"].as_u64().unwrap_or(5).min(20) as usize;
 
         let started = std::time::Instant::now();
-        let fetched = fetch_url_with_limits(url, timeout_ms, max_bytes, max_redirects).await?;
+        let use_cache = !args["no_cache"].as_bool().unwrap_or(false);
+        let fetched =
+            fetch_url_with_limits(url, timeout_ms, max_bytes, max_redirects, use_cache).await?;
         let output = String::from_utf8_lossy(&fetched.buffer).to_string();
 
         Ok(ToolResult {
@@ -1533,7 +2239,8 @@ impl Tool for WebFetchHtmlTool {
                 "content_type": fetched.content_type,
                 "truncated": fetched.truncated,
                 "bytes_in": fetched.buffer.len(),
-                "elapsed_ms": started.elapsed().as_millis()
+                "elapsed_ms": started.elapsed().as_millis(),
+                "cache": fetched.cache
             }),
         })
     }
@@ -1544,6 +2251,27 @@ struct FetchedResponse {
     content_type: String,
     buffer: Vec<u8>,
     truncated: bool,
+    /// `hit`, `revalidated`, `miss` or `bypass`; see [`web_cache`].
+    cache: &'static str,
+}
+
+impl FetchedResponse {
+    fn from_cached(cached: web_cache::CachedResponse, cache: &'static str) -> Self {
+        Self {
+            final_url: cached.final_url,
+            content_type: cached.content_type,
+            buffer: cached.body,
+            truncated: cached.truncated,
+            cache,
+        }
+    }
+}
+
+fn header_string(headers: &reqwest::header::HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(ToString::to_string)
 }
 
 async fn fetch_url_with_limits(
@@ -1551,27 +2279,49 @@ async fn fetch_url_with_limits(
     timeout_ms: u64,
     max_bytes: usize,
     max_redirects: usize,
+    use_cache: bool,
 ) -> anyhow::Result<FetchedResponse> {
+    let cache = web_cache::webfetch_cache();
+    let (lookup, cached) = if use_cache {
+        cache.lookup(url, max_bytes)
+    } else {
+        (web_cache::CacheLookup::Miss, None)
+    };
+    if lookup == web_cache::CacheLookup::Fresh {
+        if let Some(cached) = cached {
+            return Ok(FetchedResponse::from_cached(cached, "hit"));
+        }
+    }
+
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_millis(timeout_ms))
         .redirect(reqwest::redirect::Policy::limited(max_redirects))
         .build()?;
 
-    let res = client
-        .get(url)
-        .header(
-            "Accept",
-            "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
-        )
-        .send()
-        .await?;
+    let mut req = client.get(url).header(
+        "Accept",
+        "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+    );
+    if let (web_cache::CacheLookup::Revalidate, Some(cached)) = (lookup, cached.as_ref()) {
+        if let Some(etag) = &cached.etag {
+            req = req.header("If-None-Match", etag);
+        }
+        if let Some(last_modified) = &cached.last_modified {
+            req = req.header("If-Modified-Since", last_modified);
+        }
+    }
+    let res = req.send().await?;
+    let cache_control = header_string(res.headers(), "cache-control");
+    if res.status() == reqwest::StatusCode::NOT_MODIFIED {
+        if let Some(refreshed) = cache.revalidated(url, cache_control.as_deref(), max_bytes) {
+            return Ok(FetchedResponse::from_cached(refreshed, "revalidated"));
+        }
+    }
     let final_url = res.url().to_string();
-    let content_type = res
-        .headers()
-        .get("content-type")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("")
-        .to_string();
+    let status = res.status();
+    let content_type = header_string(res.headers(), "content-type").unwrap_or_default();
+    let etag = header_string(res.headers(), "etag");
+    let last_modified = header_string(res.headers(), "last-modified");
 
     let mut stream = res.bytes_stream();
     let mut buffer: Vec<u8> = Vec::new();
@@ -1587,11 +2337,24 @@ async fn fetch_url_with_limits(
         buffer.extend_from_slice(&chunk);
     }
 
+    if use_cache && status.is_success() {
+        cache.store(
+            url,
+            &final_url,
+            &content_type,
+            &buffer,
+            truncated,
+            etag,
+            last_modified,
+            cache_control.as_deref(),
+        );
+    }
     Ok(FetchedResponse {
         final_url,
         content_type,
         buffer,
         truncated,
+        cache: if use_cache { "miss" } else { "bypass" },
     })
 }
 
@@ -1812,6 +2575,11 @@ impl Tool for WebSearchTool {
         } else {
             Some(stable_hash(&query))
         };
+        // Set by the engine when this call repeats one it recently made.
+        let loop_guard_triggered = args
+            .get("__loop_guard_triggered")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
         if query.is_empty() {
             tracing::warn!("WebSearchTool missing query. Args: {}", args);
             return Ok(ToolResult {
@@ -1821,7 +2589,7 @@ impl Tool for WebSearchTool {
                     "error": "missing_query",
                     "query_source": query_source,
                     "query_hash": query_hash,
-                    "loop_guard_triggered": false
+                    "loop_guard_triggered": loop_guard_triggered
                 }),
             });
         }
@@ -1912,7 +2680,7 @@ impl Tool for WebSearchTool {
                                                     "query": query,
                                                     "query_source": query_source,
                                                     "query_hash": query_hash,
-                                                    "loop_guard_triggered": false
+                                                    "loop_guard_triggered": loop_guard_triggered
                                                 }),
                                             });
                                         }
@@ -1935,7 +2703,7 @@ impl Tool for WebSearchTool {
                             "error": "timeout",
                             "query_source": query_source,
                             "query_hash": query_hash,
-                            "loop_guard_triggered": false
+                            "loop_guard_triggered": loop_guard_triggered
                         }),
                     });
                 }
@@ -1948,7 +2716,7 @@ impl Tool for WebSearchTool {
                 "query": query,
                 "query_source": query_source,
                 "query_hash": query_hash,
-                "loop_guard_triggered": false
+                "loop_guard_triggered": loop_guard_triggered
             }),
         })
     }
@@ -2075,7 +2843,7 @@ impl Tool for CodeSearchTool {
         ToolSchema {
             name: "codesearch".to_string(),
             description: "Search code in workspace files".to_string(),
-            input_schema: json!({"type":"object","properties":{"query":{"type":"string"},"path":{"type":"string"},"limit":{"type":"integer"}}}),
+            input_schema: json!({"type":"object","properties":{"query":{"type":"string"},"path":{"type":"string"},"scope":{"type":"string","description":SCOPE_SCHEMA_DESCRIPTION},"limit":{"type":"integer"}}}),
         }
     }
     async fn execute(&self, args: Value) -> anyhow::Result<ToolResult> {
@@ -2094,39 +2862,71 @@ impl Tool for CodeSearchTool {
             .as_u64()
             .map(|v| v.clamp(1, 200) as usize)
             .unwrap_or(50);
-        let mut hits = Vec::new();
+        let scope = match search_scope(&args, !has_explicit_path(&args)) {
+            Ok(scope) => scope,
+            Err(err) => return Ok(scope_error_result(err)),
+        };
+        let roots = scope
+            .as_ref()
+            .map(SearchScope::roots)
+            .unwrap_or_else(|| vec![root_path.clone()]);
         let lower = query.to_lowercase();
-        for entry in WalkBuilder::new(&root_path).build().flatten() {
-            if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
-                continue;
-            }
-            let path = entry.path();
-            let ext = path.extension().and_then(|v| v.to_str()).unwrap_or("");
-            if !matches!(
-                ext,
-                "rs" | "ts" | "tsx" | "js" | "jsx" | "py" | "md" | "toml" | "json"
-            ) {
-                continue;
-            }
-            if let Ok(content) = fs::read_to_string(path).await {
-                for (idx, line) in content.lines().enumerate() {
-                    if line.to_lowercase().contains(&lower) {
-                        hits.push(format!("{}:{}:{}", path.display(), idx + 1, line.trim()));
-                        if hits.len() >= limit {
-                            break;
-                        }
+        let rules = workspace_ignore(&args);
+        let mut hits = codesearch_roots(&lower, &roots, limit, &rules).await;
+        let widened = hits.is_empty() && scope.as_ref().is_some_and(|scope| scope.inferred);
+        if widened {
+            hits = codesearch_roots(&lower, std::slice::from_ref(&root_path), limit, &rules).await;
+        }
+        let mut output = hits.join("\n");
+        if let Some(note) = scope_note(scope.as_ref(), widened) {
+            output.push_str(&format!("\n{note}"));
+        }
+        Ok(ToolResult {
+            output,
+            metadata: json!({
+                "count": hits.len(),
+                "query": query,
+                "path": root_path.to_string_lossy(),
+                "scope": scope_metadata(scope.as_ref(), widened),
+            }),
+        })
+    }
+}
+
+async fn codesearch_roots(
+    lower: &str,
+    roots: &[PathBuf],
+    limit: usize,
+    rules: &Arc<WorkspaceIgnore>,
+) -> Vec<String> {
+    let mut hits = Vec::new();
+    for entry in rules.walker(roots).build().flatten() {
+        if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
+            continue;
+        }
+        let path = entry.path();
+        let ext = path.extension().and_then(|v| v.to_str()).unwrap_or("");
+        if !matches!(
+            ext,
+            "rs" | "ts" | "tsx" | "js" | "jsx" | "py" | "md" | "toml" | "json"
+        ) {
+            continue;
+        }
+        if let Ok(content) = fs::read_to_string(path).await {
+            for (idx, line) in content.lines().enumerate() {
+                if line.to_lowercase().contains(lower) {
+                    hits.push(format!("{}:{}:{}", path.display(), idx + 1, line.trim()));
+                    if hits.len() >= limit {
+                        break;
                     }
                 }
             }
-            if hits.len() >= limit {
-                break;
-            }
         }
-        Ok(ToolResult {
-            output: hits.join("\n"),
-            metadata: json!({"count": hits.len(), "query": query, "path": root_path.to_string_lossy()}),
-        })
+        if hits.len() >= limit {
+            break;
+        }
     }
+    hits
 }
 
 struct TodoWriteTool;
@@ -2448,6 +3248,37 @@ fn normalize_question_choice(choice: &Value) -> Option<Value> {
     }))
 }
 
+struct ToolResultReadTool;
+#[async_trait]
+impl Tool for ToolResultReadTool {
+    fn schema(&self) -> ToolSchema {
+        ToolSchema {
+            name: "tool_result_read".to_string(),
+            description: "Read exact lines of an oversized tool result that was replaced by a summary. Use the handle from the summary; ranges are 1-based and inclusive."
+                .to_string(),
+            input_schema: json!({
+                "type":"object",
+                "properties":{
+                    "handle":{"type":"string"},
+                    "startLine":{"type":"integer","minimum":1},
+                    "endLine":{"type":"integer","minimum":1}
+                },
+                "required":["handle"]
+            }),
+        }
+    }
+
+    async fn execute(&self, _args: Value) -> anyhow::Result<ToolResult> {
+        Ok(ToolResult {
+            output: "tool_result_read must be executed through the engine runtime.".to_string(),
+            metadata: json!({
+                "ok": false,
+                "code": "TOOL_RESULT_STORE_UNAVAILABLE"
+            }),
+        })
+    }
+}
+
 struct SpawnAgentTool;
 #[async_trait]
 impl Tool for SpawnAgentTool {
@@ -2462,7 +3293,7 @@ impl Tool for SpawnAgentTool {
                     "missionID":{"type":"string"},
                     "parentInstanceID":{"type":"string"},
                     "templateID":{"type":"string"},
-                    "role":{"type":"string","enum":["orchestrator","delegator","worker","watcher","reviewer","tester","committer"]},
+                    "role":{"type":"string","enum":["orchestrator","delegator","worker","watcher","reviewer","tester","committer","observer"]},
                     "source":{"type":"string","enum":["tool_call"]},
                     "justification":{"type":"string"},
                     "budgetOverride":{"type":"object"}
@@ -3260,7 +4091,7 @@ impl Tool for MemorySearchTool {
     fn schema(&self) -> ToolSchema {
         ToolSchema {
             name: "memory_search".to_string(),
-            description: "Search tandem memory across session/project/global tiers. Global scope is opt-in via allow_global=true (or TANDEM_ENABLE_GLOBAL_MEMORY=1).".to_string(),
+            description: "Search tandem memory across session/project/global tiers. Global scope is opt-in via allow_global=true, the global_memory feature flag or TANDEM_ENABLE_GLOBAL_MEMORY=1.".to_string(),
             input_schema: json!({
                 "type":"object",
                 "properties":{
@@ -3478,6 +4309,7 @@ impl Tool for MemorySearchTool {
                     "similarity": item.similarity,
                     "content": item.chunk.content,
                     "created_at": item.chunk.created_at,
+                    "provenance": item.chunk.provenance,
                 })
             })
             .collect::<Vec<_>>();
@@ -3506,7 +4338,7 @@ impl Tool for MemoryStoreTool {
     fn schema(&self) -> ToolSchema {
         ToolSchema {
             name: "memory_store".to_string(),
-            description: "Store memory chunks in session/project/global tiers. Global writes are opt-in via allow_global=true (or TANDEM_ENABLE_GLOBAL_MEMORY=1).".to_string(),
+            description: "Store memory chunks in session/project/global tiers. Global writes are opt-in via allow_global=true, the global_memory feature flag or TANDEM_ENABLE_GLOBAL_MEMORY=1.".to_string(),
             input_schema: json!({
                 "type":"object",
                 "properties":{
@@ -3638,6 +4470,7 @@ impl Tool for MemoryStoreTool {
             source_size: None,
             source_hash: None,
             metadata,
+            provenance: memory_provenance_from_args(&args, "memory_store"),
         };
         let chunk_ids = manager.store_message(request).await?;
 
@@ -3779,6 +4612,7 @@ impl Tool for MemoryListTool {
                     "content": chunk.content,
                     "created_at": chunk.created_at,
                     "metadata": chunk.metadata,
+                    "provenance": chunk.provenance,
                 })
             })
             .collect::<Vec<_>>();
@@ -3799,6 +4633,35 @@ impl Tool for MemoryListTool {
     }
 }
 
+/// Where a memory write came from, taken from the ids the engine injects into
+/// tool args.
+fn memory_provenance_from_args(
+    args: &Value,
+    tool_name: &str,
+) -> Option<tandem_memory::types::MemoryProvenance> {
+    let injected = |key: &str| {
+        args.get(key)
+            .and_then(|v| v.as_str())
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(ToString::to_string)
+    };
+    let provenance = tandem_memory::types::MemoryProvenance {
+        session_id: injected("__session_id"),
+        message_id: injected("__message_id"),
+        run_id: injected("__run_id"),
+        tool_call_id: injected("__tool_call_id"),
+        tool_name: None,
+    };
+    if provenance.is_empty() {
+        return None;
+    }
+    Some(tandem_memory::types::MemoryProvenance {
+        tool_name: Some(tool_name.to_string()),
+        ..provenance
+    })
+}
+
 fn resolve_memory_db_path(args: &Value) -> PathBuf {
     if let Some(path) = args
         .get("db_path")
@@ -3826,11 +4689,20 @@ fn resolve_memory_db_path(args: &Value) -> PathBuf {
     PathBuf::from("memory.sqlite")
 }
 
+static GLOBAL_MEMORY_FLAG: AtomicBool = AtomicBool::new(false);
+
+/// Let memory tools use the global tier without `allow_global`; set from the
+/// server's `global_memory` feature flag.
+pub fn set_global_memory_flag(enabled: bool) {
+    GLOBAL_MEMORY_FLAG.store(enabled, Ordering::Relaxed);
+}
+
 fn global_memory_enabled(args: &Value) -> bool {
     if args
         .get("allow_global")
         .and_then(|v| v.as_bool())
         .unwrap_or(false)
+        || GLOBAL_MEMORY_FLAG.load(Ordering::Relaxed)
     {
         return true;
     }
@@ -4200,7 +5072,11 @@ async fn list_symbols(query: &str, root: &Path) -> String {
     let rust_fn = Regex::new(r"^\s*(pub\s+)?(async\s+)?fn\s+([A-Za-z_][A-Za-z0-9_]*)")
         .unwrap_or_else(|_| Regex::new("$^").expect("regex"));
     let mut out = Vec::new();
-    for entry in WalkBuilder::new(root).build().flatten() {
+    for entry in WorkspaceIgnore::for_root(root)
+        .walker(&[root.to_path_buf()])
+        .build()
+        .flatten()
+    {
         if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
             continue;
         }
@@ -4279,6 +5155,126 @@ mod tests {
         );
     }
 
+    struct SlowTool {
+        cancelled: Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    #[async_trait]
+    impl Tool for SlowTool {
+        fn schema(&self) -> ToolSchema {
+            ToolSchema {
+                name: "slow".to_string(),
+                description: "sleeps until cancelled".to_string(),
+                input_schema: json!({"type":"object"}),
+            }
+        }
+
+        async fn execute(&self, args: Value) -> anyhow::Result<ToolResult> {
+            self.execute_with_cancel(args, CancellationToken::new())
+                .await
+        }
+
+        async fn execute_with_cancel(
+            &self,
+            _args: Value,
+            cancel: CancellationToken,
+        ) -> anyhow::Result<ToolResult> {
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(30)) => {}
+                _ = cancel.cancelled() => {
+                    self.cancelled.store(true, Ordering::SeqCst);
+                }
+            }
+            Ok(ToolResult {
+                output: "done".to_string(),
+                metadata: json!({}),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn registry_cancels_tools_that_exceed_their_profile_timeout() {
+        let registry = ToolRegistry::new();
+        let cancelled = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        registry
+            .register_tool(
+                "slow".to_string(),
+                Arc::new(SlowTool {
+                    cancelled: cancelled.clone(),
+                }),
+            )
+            .await;
+        registry
+            .set_timeouts(
+                serde_json::from_value(json!({
+                    "tools": { "slow": 60000 },
+                    "profiles": { "explore": { "tools": { "slow": 50 } } }
+                }))
+                .expect("timeouts"),
+            )
+            .await;
+        let result = registry
+            .execute_for_profile("slow", json!({}), CancellationToken::new(), Some("explore"))
+            .await
+            .expect("result");
+        assert!(is_timeout_result(&result));
+        assert_eq!(result.metadata["timeoutMs"], 50);
+        assert!(cancelled.load(Ordering::SeqCst));
+        assert_eq!(
+            registry.timeout_for("slow", Some("build")).await,
+            Some(Duration::from_secs(60))
+        );
+    }
+
+    #[tokio::test]
+    async fn registry_enforces_the_command_policy() {
+        let registry = ToolRegistry::new();
+        registry
+            .set_command_policy(
+                serde_json::from_value(json!({
+                    "rules": [
+                        { "pattern": "echo denied-marker", "action": "deny", "reason": "test" },
+                        { "pattern": "echo ask-marker", "action": "ask" }
+                    ]
+                }))
+                .expect("policy"),
+            )
+            .await;
+        let denied = registry
+            .execute("shell", json!({"command": "echo denied-marker"}))
+            .await
+            .expect("result");
+        assert_eq!(denied.metadata["outcome"], "blocked");
+        assert!(denied
+            .output
+            .contains("blocked by the command policy: test"));
+        let unapproved = registry
+            .execute("bash", json!({"command": "echo ask-marker"}))
+            .await
+            .expect("result");
+        assert_eq!(unapproved.metadata["outcome"], "blocked");
+        let approved_but_denied = registry
+            .execute_approved(
+                "bash",
+                json!({"command": "echo denied-marker"}),
+                CancellationToken::new(),
+                None,
+            )
+            .await
+            .expect("result");
+        assert_eq!(approved_but_denied.metadata["outcome"], "blocked");
+        let approved = registry
+            .execute_approved(
+                "bash",
+                json!({"command": "echo ask-marker"}),
+                CancellationToken::new(),
+                None,
+            )
+            .await
+            .expect("result");
+        assert!(approved.metadata.get("outcome").is_none());
+    }
+
     #[test]
     fn websearch_query_extraction_accepts_aliases_and_nested_shapes() {
         let direct = json!({"query":"meaning of life"});
@@ -4467,6 +5463,29 @@ mod tests {
         assert!(resolve_tool_path("/etc/passwd", &args).is_none());
     }
 
+    #[test]
+    fn path_policy_normalizes_windows_style_paths_on_any_host() {
+        let args = json!({
+            "__workspace_root": r"C:\Work\Repo",
+            "__effective_cwd": r"C:\Work\Repo\docs",
+            "__path_style": "windows"
+        });
+        assert_eq!(
+            resolve_tool_path("guide/intro.md", &args),
+            Some(PathBuf::from(r"C:\Work\Repo\docs\guide\intro.md"))
+        );
+        assert_eq!(
+            resolve_tool_path(r"c:/work/repo/README.md", &args),
+            Some(PathBuf::from(r"C:\work\repo\README.md"))
+        );
+        assert!(resolve_tool_path(r"\\?\C:\Work\Repo\src\lib.rs", &args).is_some());
+        assert!(resolve_tool_path(r"C:\Work\Repo2\secret.txt", &args).is_none());
+        assert!(resolve_tool_path(r"D:secret.txt", &args).is_none());
+        assert!(resolve_tool_path(r"\Windows\system32", &args).is_none());
+        assert!(resolve_tool_path(r"\\fileserver\share\x.txt", &args).is_none());
+        assert!(resolve_tool_path(r"..\Repo2\secret.txt", &args).is_none());
+    }
+
     #[test]
     fn read_fallback_resolves_unique_suffix_filename() {
         let root =
@@ -4486,6 +5505,62 @@ mod tests {
         let _ = std::fs::remove_dir_all(&root);
     }
 
+    #[tokio::test]
+    async fn read_tool_distinguishes_missing_files_and_reads_line_ranges() {
+        let root =
+            std::env::temp_dir().join(format!("tandem-read-range-{}", uuid_like(now_ms_u64())));
+        std::fs::create_dir_all(&root).expect("create root");
+        std::fs::write(root.join("notes.txt"), "a\nb\nc\n").expect("write test file");
+        let args = |extra: Value| {
+            let mut args = json!({
+                "__workspace_root": root.to_string_lossy().to_string(),
+                "__effective_cwd": root.to_string_lossy().to_string()
+            });
+            args.as_object_mut()
+                .expect("object")
+                .extend(extra.as_object().cloned().unwrap_or_default());
+            args
+        };
+
+        let missing = ReadTool
+            .execute(args(json!({"path": "missing.txt"})))
+            .await
+            .expect("read result");
+        assert_eq!(missing.metadata["reason"], json!("path_not_found"));
+        assert!(missing.output.starts_with("read failed (path_not_found)"));
+
+        let ranged = ReadTool
+            .execute(args(json!({"path": "notes.txt", "offset": 2, "limit": 1})))
+            .await
+            .expect("read result");
+        assert_eq!(ranged.output, "b\n");
+        assert_eq!(ranged.metadata["total_lines"], json!(3));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[tokio::test]
+    async fn grep_skips_build_dirs_and_binaries_without_gitignore() {
+        let root =
+            std::env::temp_dir().join(format!("tandem-grep-ignore-{}", uuid_like(now_ms_u64())));
+        std::fs::create_dir_all(root.join("node_modules/dep")).expect("create dirs");
+        std::fs::create_dir_all(root.join("src")).expect("create dirs");
+        std::fs::write(root.join("src/lib.rs"), "needle\n").expect("write test file");
+        std::fs::write(root.join("src/data.dat"), b"needle\n\0").expect("write test file");
+        std::fs::write(root.join("node_modules/dep/index.js"), "needle\n").expect("write");
+        let args = json!({
+            "pattern": "needle",
+            "__workspace_root": root.to_string_lossy().to_string(),
+            "__effective_cwd": root.to_string_lossy().to_string()
+        });
+
+        let result = GrepTool.execute(args).await.expect("grep result");
+        assert_eq!(result.metadata["count"], json!(1));
+        assert!(result.output.contains("lib.rs:1:needle"));
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
     #[tokio::test]
     async fn write_tool_rejects_empty_content_by_default() {
         let tool = WriteTool;
@@ -4501,6 +5576,31 @@ mod tests {
         assert!(!Path::new("target/write_guard_test.txt").exists());
     }
 
+    #[tokio::test]
+    async fn calc_tool_uses_vars_and_reports_errors() {
+        let registry = ToolRegistry::new();
+        let result = registry
+            .execute(
+                "calc",
+                json!({"expression":"price * qty to kg", "vars":{"price":"2.5 ","qty":4}}),
+            )
+            .await
+            .expect("calc should return ToolResult");
+        assert_eq!(result.metadata["ok"], json!(false));
+        assert!(result.output.contains("cannot convert number to kg"));
+
+        let result = registry
+            .execute(
+                "calc",
+                json!({"expression":"round(price * qty * 1.2, 2)", "vars":{"price":"2.5 ","qty":4}}),
+            )
+            .await
+            .expect("calc should return ToolResult");
+        assert_eq!(result.output, "round(price * qty * 1.2, 2) = 12");
+        assert_eq!(result.metadata["type"], json!("number"));
+        assert_eq!(result.metadata["value"], json!(12.0));
+    }
+
     #[tokio::test]
     async fn registry_resolves_default_api_namespaced_tool() {
         let registry = ToolRegistry::new();
@@ -4625,7 +5725,11 @@ async fn find_symbol_references(symbol: &str, root: &Path) -> String {
         return "invalid symbol".to_string();
     };
     let mut refs = Vec::new();
-    for entry in WalkBuilder::new(root).build().flatten() {
+    for entry in WorkspaceIgnore::for_root(root)
+        .walker(&[root.to_path_buf()])
+        .build()
+        .flatten()
+    {
         if !entry.file_type().map(|t| t.is_file()).unwrap_or(false) {
             continue;
         }
@@ -0,0 +1,252 @@
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+use tandem_types::PathStyle;
+
+/// Path style for a tool call: the engine injects `__path_style` from the host
+/// runtime context; direct callers fall back to the compile-time host.
+pub(crate) fn path_style_from_args(args: &Value) -> PathStyle {
+    match args.get("__path_style").and_then(|v| v.as_str()) {
+        Some("windows") => PathStyle::Windows,
+        Some("posix") => PathStyle::Posix,
+        _ => host_path_style(),
+    }
+}
+
+pub(crate) fn host_path_style() -> PathStyle {
+    if cfg!(windows) {
+        PathStyle::Windows
+    } else {
+        PathStyle::Posix
+    }
+}
+
+/// Lexical view of a path under a given style. Windows paths keep their
+/// drive (`C:`) or UNC (`\\server\share`) prefix separately from components;
+/// verbatim prefixes (`\\?\`) are dropped so they compare equal to the plain
+/// form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StyledPath {
+    style: PathStyle,
+    prefix: Option<String>,
+    rooted: bool,
+    components: Vec<String>,
+    /// Whether a `..` component appeared anywhere in the input.
+    pub(crate) had_parent_dir: bool,
+}
+
+impl StyledPath {
+    pub(crate) fn parse(raw: &str, style: PathStyle) -> Self {
+        match style {
+            PathStyle::Posix => Self::parse_posix(raw),
+            PathStyle::Windows => Self::parse_windows(raw),
+        }
+    }
+
+    pub(crate) fn from_path(path: &Path, style: PathStyle) -> Self {
+        Self::parse(&path.to_string_lossy(), style)
+    }
+
+    fn parse_posix(raw: &str) -> Self {
+        let mut path = Self {
+            style: PathStyle::Posix,
+            prefix: None,
+            rooted: raw.starts_with('/'),
+            components: Vec::new(),
+            had_parent_dir: false,
+        };
+        path.push_components(raw.split('/'));
+        path
+    }
+
+    fn parse_windows(raw: &str) -> Self {
+        let mut rest = raw.replace('/', "\\");
+        for (verbatim, replacement) in [("\\\\?\\UNC\\", "\\\\"), ("\\\\?\\", ""), ("\\\\.\\", "")]
+        {
+            if rest.len() >= verbatim.len() && rest[..verbatim.len()].eq_ignore_ascii_case(verbatim)
+            {
+                rest = format!("{replacement}{}", &rest[verbatim.len()..]);
+                break;
+            }
+        }
+        // MSYS/Git Bash style `/c/Users/...` means `C:\Users\...`.
+        let bytes = rest.as_bytes();
+        if bytes.len() >= 2
+            && bytes[0] == b'\\'
+            && bytes[1].is_ascii_alphabetic()
+            && (bytes.len() == 2 || bytes[2] == b'\\')
+            && !rest.starts_with("\\\\")
+        {
+            rest = format!("{}:{}", &rest[1..2], &rest[2..]);
+        }
+
+        let mut path = Self {
+            style: PathStyle::Windows,
+            prefix: None,
+            rooted: false,
+            components: Vec::new(),
+            had_parent_dir: false,
+        };
+        let body = if let Some(unc) = rest.strip_prefix("\\\\") {
+            let mut parts = unc.splitn(3, '\\');
+            let server = parts.next().unwrap_or_default();
+            let share = parts.next().unwrap_or_default();
+            path.prefix = Some(format!("\\\\{server}\\{share}"));
+            path.rooted = true;
+            parts.next().unwrap_or_default().to_string()
+        } else {
+            let bytes = rest.as_bytes();
+            if bytes.len() >= 2 && bytes[1] == b':' && bytes[0].is_ascii_alphabetic() {
+                path.prefix = Some(format!("{}:", (bytes[0] as char).to_ascii_uppercase()));
+                path.rooted = bytes.get(2) == Some(&b'\\');
+                rest[2..].to_string()
+            } else {
+                path.rooted = rest.starts_with('\\');
+                rest
+            }
+        };
+        path.push_components(body.split('\\'));
+        path
+    }
+
+    fn push_components<'a>(&mut self, parts: impl Iterator<Item = &'a str>) {
+        for part in parts {
+            match part {
+                "" | "." => {}
+                ".." => {
+                    self.had_parent_dir = true;
+                    if self.components.last().is_some_and(|c| c != "..") {
+                        self.components.pop();
+                    } else if !self.rooted {
+                        self.components.push("..".to_string());
+                    }
+                }
+                other => self.components.push(other.to_string()),
+            }
+        }
+    }
+
+    /// Fully qualified: rooted, and on Windows anchored to a drive or share.
+    pub(crate) fn is_absolute(&self) -> bool {
+        match self.style {
+            PathStyle::Posix => self.rooted,
+            PathStyle::Windows => self.rooted && self.prefix.is_some(),
+        }
+    }
+
+    /// Windows paths like `\foo` (current drive) or `C:foo` (drive-relative)
+    /// depend on process state and are neither absolute nor safely relative.
+    pub(crate) fn is_ambiguous(&self) -> bool {
+        self.style == PathStyle::Windows && (self.rooted != self.prefix.is_some())
+    }
+
+    /// Append a relative path's components to this one.
+    pub(crate) fn join(&self, relative: &StyledPath) -> StyledPath {
+        let mut joined = self.clone();
+        joined.had_parent_dir |= relative.had_parent_dir;
+        joined.push_components(relative.components.iter().map(String::as_str));
+        joined
+    }
+
+    pub(crate) fn render(&self) -> String {
+        let sep = match self.style {
+            PathStyle::Posix => "/",
+            PathStyle::Windows => "\\",
+        };
+        let mut out = self.prefix.clone().unwrap_or_default();
+        // A UNC prefix (`\\server\share`) already ends at the share root.
+        let is_unc = out.starts_with("\\\\");
+        if self.rooted && (!is_unc || !self.components.is_empty()) {
+            out.push_str(sep);
+        }
+        out.push_str(&self.components.join(sep));
+        out
+    }
+
+    pub(crate) fn to_path_buf(&self) -> PathBuf {
+        PathBuf::from(self.render())
+    }
+
+    fn key(&self) -> (Option<String>, bool, Vec<String>) {
+        match self.style {
+            PathStyle::Posix => (None, self.rooted, self.components.clone()),
+            // NTFS and SMB shares are case-insensitive by default.
+            PathStyle::Windows => (
+                self.prefix.as_ref().map(|p| p.to_lowercase()),
+                self.rooted,
+                self.components.iter().map(|c| c.to_lowercase()).collect(),
+            ),
+        }
+    }
+
+    pub(crate) fn starts_with(&self, root: &StyledPath) -> bool {
+        let (prefix, rooted, components) = self.key();
+        let (root_prefix, root_rooted, root_components) = root.key();
+        prefix == root_prefix && rooted == root_rooted && components.starts_with(&root_components)
+    }
+}
+
+/// Style-aware containment check used by the sandbox.
+pub(crate) fn styled_path_within(path: &Path, root: &Path, style: PathStyle) -> bool {
+    StyledPath::from_path(path, style).starts_with(&StyledPath::from_path(root, style))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn win(raw: &str) -> StyledPath {
+        StyledPath::parse(raw, PathStyle::Windows)
+    }
+
+    #[test]
+    fn windows_forms_normalize_to_the_same_path() {
+        let plain = win(r"C:\Work\Repo\src\main.rs");
+        assert_eq!(plain.render(), r"C:\Work\Repo\src\main.rs");
+        for raw in [
+            "c:/Work/Repo/src/main.rs",
+            r"\\?\C:\Work\Repo\src\main.rs",
+            "/c/Work/Repo/src/main.rs",
+            r"C:\Work\Repo\.\src\..\src\main.rs",
+        ] {
+            assert_eq!(win(raw).render(), plain.render(), "{raw}");
+        }
+        assert!(plain.is_absolute());
+    }
+
+    #[test]
+    fn unc_paths_keep_server_and_share() {
+        let unc = win(r"\\?\UNC\fileserver\team\docs\a.txt");
+        assert_eq!(unc.render(), r"\\fileserver\team\docs\a.txt");
+        assert!(unc.is_absolute());
+        assert!(unc.starts_with(&win(r"\\FILESERVER\Team")));
+        assert!(!unc.starts_with(&win(r"\\fileserver\other")));
+    }
+
+    #[test]
+    fn windows_containment_is_case_insensitive() {
+        let root = win(r"C:\Work\Repo");
+        assert!(win(r"c:\work\repo\README.md").starts_with(&root));
+        assert!(!win(r"C:\Work\Repo2\README.md").starts_with(&root));
+        assert!(!win(r"D:\Work\Repo\README.md").starts_with(&root));
+        assert!(!StyledPath::parse("/work/repo/a", PathStyle::Posix)
+            .starts_with(&StyledPath::parse("/Work/Repo", PathStyle::Posix)));
+    }
+
+    #[test]
+    fn drive_relative_and_current_drive_paths_are_ambiguous() {
+        assert!(win("C:secrets.txt").is_ambiguous());
+        assert!(win(r"\Windows\system32").is_ambiguous());
+        assert!(!win(r"src\main.rs").is_ambiguous());
+        assert!(!StyledPath::parse("/etc", PathStyle::Posix).is_ambiguous());
+    }
+
+    #[test]
+    fn join_resolves_relative_components() {
+        let joined = win(r"C:\Work\Repo").join(&win("src/lib.rs"));
+        assert_eq!(joined.render(), r"C:\Work\Repo\src\lib.rs");
+        let escaped = win(r"C:\Work\Repo").join(&win(r"..\Other"));
+        assert!(escaped.had_parent_dir);
+        assert!(!escaped.starts_with(&win(r"C:\Work\Repo")));
+    }
+}
@@ -0,0 +1,367 @@
+//! Release-notes primitives: the commits of a release, grouped by area, and
+//! checks for a drafted changelog.
+//!
+//! Backs the `release_changes` and `release_notes_verify` tools used by the
+//! server's `release-notes` routine preset. `release_changes` lists the
+//! non-merge commits between a tag (the latest one reachable from `until`
+//! by default) and `until`, with their conventional-commit kind, area and
+//! `#123` PR references. An area is a conventional-commit scope when there
+//! is one, otherwise the directory most of the commit's files live in.
+//! `release_notes_verify` flags PR references in a draft that no commit in
+//! the range mentions, and links that do not answer.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use regex::Regex;
+use serde::Serialize;
+
+pub(crate) const DEFAULT_MAX_COMMITS: usize = 500;
+const MAX_CHECKED_LINKS: usize = 50;
+const LINK_TIMEOUT: Duration = Duration::from_secs(10);
+/// Top-level directories that hold one package per subdirectory; their
+/// areas are named after the package (`crates/tandem-tools`).
+const PACKAGE_DIRS: &[&str] = &["crates", "packages", "apps", "libs", "services", "modules"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct ReleaseCommit {
+    pub sha: String,
+    pub author: String,
+    pub date: String,
+    pub subject: String,
+    /// Conventional-commit type (`feat`, `fix`, ...), or `other`.
+    pub kind: String,
+    pub area: String,
+    pub breaking: bool,
+    pub pr_refs: Vec<u32>,
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct ReleaseChanges {
+    /// Start of the range; `None` when the repository has no tag and the
+    /// whole history up to `until` is included.
+    pub since: Option<String>,
+    pub until: String,
+    pub commits: Vec<ReleaseCommit>,
+    /// Commit indexes by area.
+    pub areas: BTreeMap<String, Vec<usize>>,
+    pub truncated: bool,
+}
+
+fn conventional_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^(?P<kind>[a-zA-Z]+)(?:\((?P<scope>[^)]+)\))?(?P<bang>!)?:\s").expect("regex")
+    })
+}
+
+fn pr_ref_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"(?:^|[^\w&/])#(\d{1,7})\b").expect("regex"))
+}
+
+fn link_re() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r#"https?://[^\s<>()\[\]"'`]+"#).expect("regex"))
+}
+
+pub(crate) fn pr_refs(text: &str) -> Vec<u32> {
+    let mut refs = pr_ref_re()
+        .captures_iter(text)
+        .filter_map(|caps| caps[1].parse().ok())
+        .collect::<Vec<u32>>();
+    refs.sort_unstable();
+    refs.dedup();
+    refs
+}
+
+fn file_area(path: &str) -> Option<String> {
+    let mut parts = path.split('/');
+    let first = parts.next()?;
+    let second = parts.next()?;
+    if PACKAGE_DIRS.contains(&first) && parts.next().is_some() {
+        Some(format!("{first}/{second}"))
+    } else {
+        Some(first.to_string())
+    }
+}
+
+/// The directory most of `files` live in; `root` for top-level files.
+fn dominant_area(files: &[String]) -> String {
+    let mut counts = BTreeMap::<String, usize>::new();
+    for file in files {
+        let area = file_area(file).unwrap_or_else(|| "root".to_string());
+        *counts.entry(area).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))
+        .map(|(area, _)| area)
+        .unwrap_or_else(|| "misc".to_string())
+}
+
+/// Kind, area and whether the commit is marked breaking.
+fn classify(subject: &str, files: &[String]) -> (String, String, bool) {
+    let Some(caps) = conventional_re().captures(subject) else {
+        return ("other".to_string(), dominant_area(files), false);
+    };
+    let area = caps
+        .name("scope")
+        .map(|scope| scope.as_str().trim().to_string())
+        .unwrap_or_else(|| dominant_area(files));
+    (
+        caps["kind"].to_ascii_lowercase(),
+        area,
+        caps.name("bang").is_some(),
+    )
+}
+
+fn git(root: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = std::process::Command::new("git")
+        .current_dir(root)
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.first().copied().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// The latest tag reachable from `until`, if any.
+pub(crate) fn latest_tag(root: &Path, until: &str) -> Option<String> {
+    git(root, &["describe", "--tags", "--abbrev=0", until])
+        .ok()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+}
+
+pub(crate) fn collect_changes(
+    root: &Path,
+    since: Option<&str>,
+    until: &str,
+    max_commits: usize,
+) -> anyhow::Result<ReleaseChanges> {
+    let since = match since.map(str::trim).filter(|s| !s.is_empty()) {
+        Some(since) => Some(since.to_string()),
+        None => latest_tag(root, until),
+    };
+    let range = match &since {
+        Some(since) => format!("{since}..{until}"),
+        None => until.to_string(),
+    };
+    let limit = format!("--max-count={}", max_commits + 1);
+    let log = git(
+        root,
+        &[
+            "log",
+            "--no-merges",
+            &limit,
+            "--format=%x1e%H%x1f%an%x1f%aI%x1f%s",
+            "--name-only",
+            &range,
+        ],
+    )?;
+    let mut commits = Vec::new();
+    for record in log.split('\u{1e}').filter(|r| !r.trim().is_empty()) {
+        let mut lines = record.lines();
+        let Some(header) = lines.next() else {
+            continue;
+        };
+        let fields = header.split('\u{1f}').collect::<Vec<_>>();
+        let [sha, author, date, subject] = fields[..] else {
+            continue;
+        };
+        let files = lines
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let (kind, area, breaking) = classify(subject, &files);
+        commits.push(ReleaseCommit {
+            sha: sha.to_string(),
+            author: author.to_string(),
+            date: date.to_string(),
+            subject: subject.to_string(),
+            kind,
+            area,
+            breaking,
+            pr_refs: pr_refs(subject),
+            files,
+        });
+    }
+    let truncated = commits.len() > max_commits;
+    commits.truncate(max_commits);
+    let mut areas = BTreeMap::<String, Vec<usize>>::new();
+    for (idx, commit) in commits.iter().enumerate() {
+        areas.entry(commit.area.clone()).or_default().push(idx);
+    }
+    Ok(ReleaseChanges {
+        since,
+        until: until.to_string(),
+        commits,
+        areas,
+        truncated,
+    })
+}
+
+impl ReleaseChanges {
+    pub(crate) fn pr_refs(&self) -> Vec<u32> {
+        let mut refs = self
+            .commits
+            .iter()
+            .flat_map(|c| c.pr_refs.iter().copied())
+            .collect::<Vec<_>>();
+        refs.sort_unstable();
+        refs.dedup();
+        refs
+    }
+
+    pub(crate) fn render(&self) -> String {
+        let mut out = format!(
+            "{} commit(s) in {}..{}\n",
+            self.commits.len(),
+            self.since.as_deref().unwrap_or("(start)"),
+            self.until
+        );
+        for (area, indexes) in &self.areas {
+            out.push_str(&format!("\n## {area}\n"));
+            for commit in indexes.iter().map(|idx| &self.commits[*idx]) {
+                let breaking = if commit.breaking { " [breaking]" } else { "" };
+                out.push_str(&format!(
+                    "- {} {} ({}){breaking}\n",
+                    &commit.sha[..commit.sha.len().min(8)],
+                    commit.subject,
+                    commit.kind
+                ));
+            }
+        }
+        if self.truncated {
+            out.push_str("\n(truncated; raise max_commits for the full range)\n");
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct LinkCheck {
+    pub url: String,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// PR references in `notes` that no commit of the release mentions.
+pub(crate) fn unknown_pr_refs(notes: &str, known: &[u32]) -> Vec<u32> {
+    pr_refs(notes)
+        .into_iter()
+        .filter(|r| !known.contains(r))
+        .collect()
+}
+
+pub(crate) fn links(notes: &str) -> Vec<String> {
+    let mut links = Vec::new();
+    for found in link_re().find_iter(notes) {
+        let url = found.as_str().trim_end_matches(['.', ',', ';', ':']);
+        if !links.iter().any(|link| link == url) {
+            links.push(url.to_string());
+        }
+    }
+    links.truncate(MAX_CHECKED_LINKS);
+    links
+}
+
+async fn check_link(client: &reqwest::Client, url: &str) -> LinkCheck {
+    let mut result = client.head(url).send().await;
+    if matches!(&result, Ok(resp) if resp.status() == reqwest::StatusCode::METHOD_NOT_ALLOWED) {
+        result = client.get(url).send().await;
+    }
+    match result {
+        Ok(resp) => LinkCheck {
+            url: url.to_string(),
+            ok: resp.status().is_success(),
+            status: Some(resp.status().as_u16()),
+            error: None,
+        },
+        Err(err) => LinkCheck {
+            url: url.to_string(),
+            ok: false,
+            status: None,
+            error: Some(err.to_string()),
+        },
+    }
+}
+
+pub(crate) async fn check_links(urls: &[String]) -> anyhow::Result<Vec<LinkCheck>> {
+    let client = reqwest::Client::builder()
+        .timeout(LINK_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::limited(5))
+        .build()?;
+    Ok(futures_util::future::join_all(urls.iter().map(|url| check_link(&client, url))).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git_ok(dir: &Path, args: &[&str]) {
+        git(dir, args).expect("git");
+    }
+
+    fn commit(dir: &Path, file: &str, message: &str) {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+        std::fs::write(&path, message).expect("write");
+        git_ok(dir, &["add", "-A"]);
+        git_ok(dir, &["commit", "-q", "-m", message]);
+    }
+
+    #[test]
+    fn changes_since_latest_tag_are_grouped_by_area() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path();
+        git_ok(root, &["init", "-q"]);
+        git_ok(root, &["config", "user.email", "dev@example.com"]);
+        git_ok(root, &["config", "user.name", "Dev"]);
+        commit(root, "README.md", "initial");
+        git_ok(root, &["tag", "v0.1.0"]);
+        commit(
+            root,
+            "crates/core/src/lib.rs",
+            "feat(engine)!: new loop (#12)",
+        );
+        commit(
+            root,
+            "crates/tools/src/lib.rs",
+            "fix: handle empty args (#13)",
+        );
+        commit(root, "docs/guide.md", "Update guide");
+
+        let changes = collect_changes(root, None, "HEAD", DEFAULT_MAX_COMMITS).expect("changes");
+        assert_eq!(changes.since.as_deref(), Some("v0.1.0"));
+        assert_eq!(changes.commits.len(), 3);
+        assert_eq!(
+            changes.areas.keys().collect::<Vec<_>>(),
+            vec!["crates/tools", "docs", "engine"]
+        );
+        let feat = &changes.commits[changes.areas["engine"][0]];
+        assert!(feat.breaking && feat.kind == "feat");
+        assert_eq!(changes.pr_refs(), vec![12, 13]);
+
+        let limited = collect_changes(root, Some("v0.1.0"), "HEAD", 2).expect("changes");
+        assert!(limited.truncated && limited.commits.len() == 2);
+
+        let notes = "- New loop (#12)\n- Fix args (#13, #99) see https://example.com/x.\n";
+        assert_eq!(unknown_pr_refs(notes, &changes.pr_refs()), vec![99]);
+        assert_eq!(links(notes), vec!["https://example.com/x".to_string()]);
+        assert!(pr_refs("color: &#123; or a/#5").is_empty());
+    }
+}
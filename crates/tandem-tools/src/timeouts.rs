@@ -0,0 +1,156 @@
+//! Per-tool execution budgets.
+//!
+//! Timeouts come from the `tool_timeouts` config block:
+//!
+//! ```json
+//! {
+//!   "default_ms": 120000,
+//!   "tools": { "bash": 600000, "webfetch": 30000, "lsp": 5000 },
+//!   "profiles": { "explore": { "default_ms": 60000, "tools": { "bash": 30000 } } }
+//! }
+//! ```
+//!
+//! Profiles are agent names. The most specific setting wins: the profile's
+//! entry for the tool, then the global entry for the tool, then the
+//! profile's default, then the global default. `0` means no timeout. Tool
+//! names are matched after alias resolution, so `shell` uses the `bash`
+//! budget.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tandem_types::ToolResult;
+
+use crate::canonical_tool_name;
+
+/// How long a timed-out tool gets to react to cancellation before its
+/// future is dropped.
+pub(crate) const CANCEL_GRACE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolTimeoutProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_ms: Option<u64>,
+    #[serde(default)]
+    pub tools: HashMap<String, u64>,
+}
+
+impl ToolTimeoutProfile {
+    fn tool_ms(&self, canonical: &str) -> Option<u64> {
+        tool_ms(&self.tools, canonical)
+    }
+}
+
+fn tool_ms(tools: &HashMap<String, u64>, canonical: &str) -> Option<u64> {
+    tools
+        .iter()
+        .find(|(name, _)| canonical_tool_name(name) == canonical)
+        .map(|(_, ms)| *ms)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolTimeoutConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_ms: Option<u64>,
+    #[serde(default)]
+    pub tools: HashMap<String, u64>,
+    #[serde(default)]
+    pub profiles: HashMap<String, ToolTimeoutProfile>,
+}
+
+impl ToolTimeoutConfig {
+    /// Budget for `tool` when run by agent `profile`; `None` means unbounded.
+    pub fn resolve(&self, tool: &str, profile: Option<&str>) -> Option<Duration> {
+        let canonical = canonical_tool_name(tool);
+        let profile = profile.and_then(|name| self.profiles.get(name));
+        let ms = profile
+            .and_then(|p| p.tool_ms(&canonical))
+            .or_else(|| tool_ms(&self.tools, &canonical))
+            .or_else(|| profile.and_then(|p| p.default_ms))
+            .or(self.default_ms)?;
+        (ms > 0).then(|| Duration::from_millis(ms))
+    }
+}
+
+/// Result returned in place of the tool's own when it exceeds its budget.
+/// It is an `Ok` result so callers can tell a timeout from a tool error by
+/// `metadata.outcome == "timeout"`.
+pub(crate) fn timeout_result(tool: &str, limit: Duration) -> ToolResult {
+    let ms = limit.as_millis() as u64;
+    ToolResult {
+        output: format!(
+            "Tool `{tool}` timed out after {} and was cancelled.",
+            format_limit(limit)
+        ),
+        metadata: json!({
+            "outcome": "timeout",
+            "tool": canonical_tool_name(tool),
+            "timeoutMs": ms,
+        }),
+    }
+}
+
+/// Whether `result` is a timeout produced by the registry.
+pub fn is_timeout_result(result: &ToolResult) -> bool {
+    result.metadata.get("outcome").and_then(|v| v.as_str()) == Some("timeout")
+}
+
+fn format_limit(limit: Duration) -> String {
+    let ms = limit.as_millis();
+    if ms.is_multiple_of(1000) {
+        format!("{}s", ms / 1000)
+    } else {
+        format!("{ms}ms")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ToolTimeoutConfig {
+        serde_json::from_value(json!({
+            "default_ms": 120000,
+            "tools": { "bash": 600000, "webfetch": 30000, "lsp": 0 },
+            "profiles": {
+                "explore": { "default_ms": 60000, "tools": { "shell": 30000 } }
+            }
+        }))
+        .expect("config")
+    }
+
+    #[test]
+    fn most_specific_timeout_wins() {
+        let cfg = config();
+        assert_eq!(cfg.resolve("bash", None), Some(Duration::from_secs(600)));
+        assert_eq!(
+            cfg.resolve("run_command", Some("explore")),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            cfg.resolve("webfetch", Some("explore")),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            cfg.resolve("read", Some("explore")),
+            Some(Duration::from_secs(60))
+        );
+        assert_eq!(
+            cfg.resolve("read", Some("build")),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(cfg.resolve("lsp", None), None);
+        assert_eq!(ToolTimeoutConfig::default().resolve("bash", None), None);
+    }
+
+    #[test]
+    fn timeout_result_is_tagged() {
+        let result = timeout_result("shell", Duration::from_millis(1500));
+        assert!(is_timeout_result(&result));
+        assert_eq!(result.metadata["tool"], "bash");
+        assert_eq!(result.metadata["timeoutMs"], 1500);
+        assert!(result.output.contains("1500ms"));
+    }
+}
@@ -0,0 +1,441 @@
+//! In-process HTTP cache for `webfetch` and `webfetch_html`.
+//!
+//! Responses are kept per URL for a TTL (`TANDEM_WEBFETCH_CACHE_TTL_SECS`,
+//! default 5 minutes) with per-domain overrides from
+//! `TANDEM_WEBFETCH_CACHE_DOMAIN_TTLS` (`docs.rs=3600,example.com=0`; a
+//! domain entry also covers its subdomains, `0` disables caching). Once an
+//! entry is stale it is revalidated with `If-None-Match` / `If-Modified-Since`
+//! when the origin sent an `ETag` or `Last-Modified`, so a `304` costs no
+//! body transfer. `Cache-Control: no-store` responses are never cached.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use serde::Serialize;
+
+const DEFAULT_TTL_SECS: u64 = 300;
+const MAX_ENTRIES: usize = 256;
+const MAX_TOTAL_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
+pub(crate) struct CachedResponse {
+    pub final_url: String,
+    pub content_type: String,
+    pub body: Vec<u8>,
+    pub truncated: bool,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    fetched_at_ms: u64,
+    expires_at_ms: u64,
+    hits: u64,
+    revalidations: u64,
+}
+
+/// How a fetch was served, reported in tool metadata as `cache`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CacheLookup {
+    /// Fresh entry; serve without a request.
+    Fresh,
+    /// Stale entry with validators; send a conditional request.
+    Revalidate,
+    Miss,
+}
+
+/// Entry metadata for the inspection endpoint (no bodies).
+#[derive(Debug, Clone, Serialize)]
+pub struct WebFetchCacheEntry {
+    pub url: String,
+    pub final_url: String,
+    pub content_type: String,
+    pub bytes: usize,
+    pub truncated: bool,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub fetched_at_ms: u64,
+    pub expires_at_ms: u64,
+    pub fresh: bool,
+    pub hits: u64,
+    pub revalidations: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WebFetchCacheStats {
+    pub entries: usize,
+    pub bytes: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub revalidated: u64,
+    pub default_ttl_secs: u64,
+    pub domain_ttl_secs: HashMap<String, u64>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    entries: HashMap<String, CachedResponse>,
+    hits: u64,
+    misses: u64,
+    revalidated: u64,
+}
+
+pub struct WebFetchCache {
+    default_ttl_secs: u64,
+    domain_ttl_secs: HashMap<String, u64>,
+    state: Mutex<CacheState>,
+}
+
+/// Process-wide cache shared by the fetch tools.
+pub fn webfetch_cache() -> &'static WebFetchCache {
+    static CACHE: OnceLock<WebFetchCache> = OnceLock::new();
+    CACHE.get_or_init(WebFetchCache::from_env)
+}
+
+/// Parse `domain=secs` pairs separated by commas.
+fn parse_domain_ttls(raw: &str) -> HashMap<String, u64> {
+    raw.split(',')
+        .filter_map(|pair| {
+            let (domain, secs) = pair.split_once('=')?;
+            let domain = domain.trim().trim_start_matches('.').to_ascii_lowercase();
+            let secs = secs.trim().parse::<u64>().ok()?;
+            (!domain.is_empty()).then_some((domain, secs))
+        })
+        .collect()
+}
+
+fn url_host(url: &str) -> Option<String> {
+    reqwest::Url::parse(url)
+        .ok()?
+        .host_str()
+        .map(|host| host.to_ascii_lowercase())
+}
+
+/// `(no_store, max_age)` from a `Cache-Control` header. `no-cache` is
+/// treated as `max-age=0`, i.e. always revalidate.
+fn cache_control_directives(value: Option<&str>) -> (bool, Option<u64>) {
+    let Some(value) = value else {
+        return (false, None);
+    };
+    let mut no_store = false;
+    let mut max_age = None;
+    for directive in value.split(',').map(|d| d.trim().to_ascii_lowercase()) {
+        if directive == "no-store" || directive == "private" {
+            no_store = true;
+        } else if directive == "no-cache" {
+            max_age = Some(0);
+        } else if let Some(secs) = directive.strip_prefix("max-age=") {
+            if max_age.is_none() {
+                max_age = secs.trim_matches('"').parse::<u64>().ok();
+            }
+        }
+    }
+    (no_store, max_age)
+}
+
+impl WebFetchCache {
+    pub fn new(default_ttl_secs: u64, domain_ttl_secs: HashMap<String, u64>) -> Self {
+        Self {
+            default_ttl_secs,
+            domain_ttl_secs,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub fn from_env() -> Self {
+        let default_ttl_secs = std::env::var("TANDEM_WEBFETCH_CACHE_TTL_SECS")
+            .ok()
+            .and_then(|v| v.trim().parse::<u64>().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+        let domain_ttl_secs = std::env::var("TANDEM_WEBFETCH_CACHE_DOMAIN_TTLS")
+            .map(|raw| parse_domain_ttls(&raw))
+            .unwrap_or_default();
+        Self::new(default_ttl_secs, domain_ttl_secs)
+    }
+
+    /// The most specific domain override matching `url`'s host.
+    fn domain_ttl_secs_for(&self, url: &str) -> Option<u64> {
+        let host = url_host(url)?;
+        self.domain_ttl_secs
+            .iter()
+            .filter(|(domain, _)| {
+                host == **domain
+                    || host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|rest| rest.ends_with('.'))
+            })
+            .max_by_key(|(domain, _)| domain.len())
+            .map(|(_, secs)| *secs)
+    }
+
+    /// TTL for `url`: its domain override, else the default.
+    pub fn ttl_secs_for(&self, url: &str) -> u64 {
+        self.domain_ttl_secs_for(url)
+            .unwrap_or(self.default_ttl_secs)
+    }
+
+    fn enabled_for(&self, url: &str) -> bool {
+        self.ttl_secs_for(url) > 0
+    }
+
+    /// Look up `url`. Entries truncated below `max_bytes` cannot satisfy a
+    /// larger request and count as a miss.
+    pub(crate) fn lookup(
+        &self,
+        url: &str,
+        max_bytes: usize,
+    ) -> (CacheLookup, Option<CachedResponse>) {
+        if !self.enabled_for(url) {
+            return (CacheLookup::Miss, None);
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = crate::now_ms_u64();
+        let lookup = match state.entries.get_mut(url) {
+            Some(entry) if entry.truncated && entry.body.len() < max_bytes => None,
+            Some(entry) if entry.expires_at_ms > now => {
+                entry.hits += 1;
+                Some((CacheLookup::Fresh, entry.clone()))
+            }
+            Some(entry) if entry.etag.is_some() || entry.last_modified.is_some() => {
+                Some((CacheLookup::Revalidate, entry.clone()))
+            }
+            _ => None,
+        };
+        match lookup {
+            Some((CacheLookup::Fresh, entry)) => {
+                state.hits += 1;
+                (CacheLookup::Fresh, Some(truncate_to(entry, max_bytes)))
+            }
+            Some((kind, entry)) => (kind, Some(entry)),
+            None => {
+                state.misses += 1;
+                (CacheLookup::Miss, None)
+            }
+        }
+    }
+
+    /// Record a `304 Not Modified` for `url`, extending its freshness.
+    pub(crate) fn revalidated(
+        &self,
+        url: &str,
+        cache_control: Option<&str>,
+        max_bytes: usize,
+    ) -> Option<CachedResponse> {
+        let ttl_ms = self.effective_ttl_ms(url, cache_control)?;
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.revalidated += 1;
+        let entry = state.entries.get_mut(url)?;
+        entry.expires_at_ms = crate::now_ms_u64() + ttl_ms;
+        entry.revalidations += 1;
+        Some(truncate_to(entry.clone(), max_bytes))
+    }
+
+    /// Freshness lifetime for a response, or `None` when it must not be
+    /// cached. Domain overrides win; otherwise a shorter origin `max-age`
+    /// is respected.
+    fn effective_ttl_ms(&self, url: &str, cache_control: Option<&str>) -> Option<u64> {
+        let (no_store, max_age) = cache_control_directives(cache_control);
+        if no_store || !self.enabled_for(url) {
+            return None;
+        }
+        let ttl_secs = match self.domain_ttl_secs_for(url) {
+            Some(secs) => secs,
+            None => max_age.map_or(self.default_ttl_secs, |age| age.min(self.default_ttl_secs)),
+        };
+        Some(ttl_secs * 1_000)
+    }
+
+    /// Store a full response for `url` unless caching is disabled for it.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn store(
+        &self,
+        url: &str,
+        final_url: &str,
+        content_type: &str,
+        body: &[u8],
+        truncated: bool,
+        etag: Option<String>,
+        last_modified: Option<String>,
+        cache_control: Option<&str>,
+    ) {
+        let Some(ttl_ms) = self.effective_ttl_ms(url, cache_control) else {
+            return;
+        };
+        if ttl_ms == 0 && etag.is_none() && last_modified.is_none() {
+            return;
+        }
+        let now = crate::now_ms_u64();
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.entries.insert(
+            url.to_string(),
+            CachedResponse {
+                final_url: final_url.to_string(),
+                content_type: content_type.to_string(),
+                body: body.to_vec(),
+                truncated,
+                etag,
+                last_modified,
+                fetched_at_ms: now,
+                expires_at_ms: now + ttl_ms,
+                hits: 0,
+                revalidations: 0,
+            },
+        );
+        let mut total = state.entries.values().map(|e| e.body.len()).sum::<usize>();
+        while state.entries.len() > MAX_ENTRIES || total > MAX_TOTAL_BYTES {
+            let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.fetched_at_ms)
+                .map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            if let Some(removed) = state.entries.remove(&oldest) {
+                total = total.saturating_sub(removed.body.len());
+            }
+        }
+    }
+
+    pub fn entries(&self) -> Vec<WebFetchCacheEntry> {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = crate::now_ms_u64();
+        let mut entries = state
+            .entries
+            .iter()
+            .map(|(url, e)| WebFetchCacheEntry {
+                url: url.clone(),
+                final_url: e.final_url.clone(),
+                content_type: e.content_type.clone(),
+                bytes: e.body.len(),
+                truncated: e.truncated,
+                etag: e.etag.clone(),
+                last_modified: e.last_modified.clone(),
+                fetched_at_ms: e.fetched_at_ms,
+                expires_at_ms: e.expires_at_ms,
+                fresh: e.expires_at_ms > now,
+                hits: e.hits,
+                revalidations: e.revalidations,
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.fetched_at_ms));
+        entries
+    }
+
+    pub fn stats(&self) -> WebFetchCacheStats {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        WebFetchCacheStats {
+            entries: state.entries.len(),
+            bytes: state.entries.values().map(|e| e.body.len()).sum(),
+            hits: state.hits,
+            misses: state.misses,
+            revalidated: state.revalidated,
+            default_ttl_secs: self.default_ttl_secs,
+            domain_ttl_secs: self.domain_ttl_secs.clone(),
+        }
+    }
+
+    /// Drop every entry, or only the one for `url`. Returns how many went.
+    pub fn clear(&self, url: Option<&str>) -> usize {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match url {
+            Some(url) => usize::from(state.entries.remove(url).is_some()),
+            None => {
+                let removed = state.entries.len();
+                state.entries.clear();
+                removed
+            }
+        }
+    }
+}
+
+fn truncate_to(mut entry: CachedResponse, max_bytes: usize) -> CachedResponse {
+    if entry.body.len() > max_bytes {
+        entry.body.truncate(max_bytes);
+        entry.truncated = true;
+    }
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache() -> WebFetchCache {
+        WebFetchCache::new(60, parse_domain_ttls("docs.rs=3600, nocache.example.com=0"))
+    }
+
+    #[test]
+    fn domain_overrides_cover_subdomains() {
+        let cache = cache();
+        assert_eq!(cache.ttl_secs_for("https://docs.rs/serde"), 3600);
+        assert_eq!(cache.ttl_secs_for("https://api.docs.rs/x"), 3600);
+        assert_eq!(cache.ttl_secs_for("https://notdocs.rs/x"), 60);
+        assert_eq!(cache.ttl_secs_for("https://nocache.example.com/"), 0);
+    }
+
+    #[test]
+    fn serves_fresh_entries_and_revalidates_stale_ones() {
+        let cache = cache();
+        let url = "https://example.org/page";
+        assert_eq!(cache.lookup(url, 1_000).0, CacheLookup::Miss);
+        cache.store(
+            url,
+            url,
+            "text/html",
+            b"<p>hello</p>",
+            false,
+            Some("\"v1\"".to_string()),
+            None,
+            None,
+        );
+        let (kind, hit) = cache.lookup(url, 5);
+        assert_eq!(kind, CacheLookup::Fresh);
+        let hit = hit.expect("entry");
+        assert_eq!(hit.body, b"<p>he");
+        assert!(hit.truncated);
+
+        // origin said max-age=0: stale immediately, but has an ETag.
+        cache.store(
+            url,
+            url,
+            "text/html",
+            b"<p>hello</p>",
+            false,
+            Some("\"v1\"".to_string()),
+            None,
+            Some("max-age=0"),
+        );
+        assert_eq!(cache.lookup(url, 1_000).0, CacheLookup::Revalidate);
+        let refreshed = cache
+            .revalidated(url, None, 1_000)
+            .expect("revalidated entry");
+        assert_eq!(refreshed.body, b"<p>hello</p>");
+        assert_eq!(cache.lookup(url, 1_000).0, CacheLookup::Fresh);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.revalidated), (2, 1, 1));
+    }
+
+    #[test]
+    fn no_store_and_disabled_domains_are_not_cached() {
+        let cache = cache();
+        cache.store(
+            "https://example.org/private",
+            "https://example.org/private",
+            "text/html",
+            b"secret",
+            false,
+            None,
+            None,
+            Some("private, no-store"),
+        );
+        cache.store(
+            "https://nocache.example.com/",
+            "https://nocache.example.com/",
+            "text/html",
+            b"x",
+            false,
+            Some("\"e\"".to_string()),
+            None,
+            None,
+        );
+        assert!(cache.entries().is_empty());
+    }
+}
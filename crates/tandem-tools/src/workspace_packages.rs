@@ -0,0 +1,476 @@
+//! Package detection for monorepos, used to scope `glob`, `grep` and
+//! `codesearch`.
+//!
+//! Packages come from Cargo workspaces (`[workspace] members`), pnpm/npm/yarn
+//! workspaces (`pnpm-workspace.yaml`, `package.json` `workspaces`) and
+//! `go.work` `use` directives at the workspace root. Detection only reads
+//! manifests, and results are cached per root for a short while.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::Value;
+
+const CACHE_TTL: Duration = Duration::from_secs(30);
+/// How many package names an unknown-scope error lists.
+const MAX_LISTED_PACKAGES: usize = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PackageKind {
+    Cargo,
+    Node,
+    Go,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub(crate) struct WorkspacePackage {
+    pub name: String,
+    pub kind: PackageKind,
+    pub root: PathBuf,
+}
+
+fn read(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path).ok()
+}
+
+/// Quoted strings inside the `[...]` array assigned to `key` in `section`.
+/// Enough TOML for `[workspace] members = [...]`, which may span lines.
+fn toml_string_array(text: &str, section: &str, key: &str) -> Vec<String> {
+    let mut in_section = false;
+    let mut collecting = false;
+    let mut buf = String::new();
+    for line in text.lines() {
+        let trimmed = line.split('#').next().unwrap_or("").trim();
+        if !collecting && trimmed.starts_with('[') && !trimmed.starts_with("[[") {
+            in_section = trimmed == format!("[{section}]");
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        if !collecting {
+            let Some((lhs, rhs)) = trimmed.split_once('=') else {
+                continue;
+            };
+            if lhs.trim() != key {
+                continue;
+            }
+            collecting = true;
+            buf.push_str(rhs);
+        } else {
+            buf.push_str(trimmed);
+        }
+        if buf.contains(']') {
+            break;
+        }
+    }
+    buf.split('"')
+        .skip(1)
+        .step_by(2)
+        .map(ToString::to_string)
+        .collect()
+}
+
+/// `name = "..."` in `section`.
+fn toml_string(text: &str, section: &str, key: &str) -> Option<String> {
+    let mut in_section = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_section = trimmed == format!("[{section}]");
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        let Some((lhs, rhs)) = trimmed.split_once('=') else {
+            continue;
+        };
+        if lhs.trim() == key {
+            return rhs.split('"').nth(1).map(ToString::to_string);
+        }
+    }
+    None
+}
+
+/// Expand workspace member globs relative to `root`, keeping directories.
+fn expand_members(root: &Path, patterns: &[String], excludes: &[String]) -> Vec<PathBuf> {
+    let excluded = excludes
+        .iter()
+        .map(|e| root.join(e.trim_start_matches("./")))
+        .collect::<Vec<_>>();
+    let mut dirs = Vec::new();
+    for pattern in patterns {
+        let pattern = pattern.trim().trim_start_matches("./");
+        if pattern.is_empty() || pattern.starts_with('!') || pattern.contains("..") {
+            continue;
+        }
+        let Ok(paths) = glob::glob(&root.join(pattern).to_string_lossy()) else {
+            continue;
+        };
+        for path in paths.flatten() {
+            if path.is_dir() && !excluded.contains(&path) && !dirs.contains(&path) {
+                dirs.push(path);
+            }
+        }
+    }
+    dirs
+}
+
+fn cargo_packages(root: &Path) -> Vec<WorkspacePackage> {
+    let Some(manifest) = read(&root.join("Cargo.toml")) else {
+        return Vec::new();
+    };
+    let members = toml_string_array(&manifest, "workspace", "members");
+    let excludes = toml_string_array(&manifest, "workspace", "exclude");
+    expand_members(root, &members, &excludes)
+        .into_iter()
+        .filter_map(|dir| {
+            let name = toml_string(&read(&dir.join("Cargo.toml"))?, "package", "name")?;
+            Some(WorkspacePackage {
+                name,
+                kind: PackageKind::Cargo,
+                root: dir,
+            })
+        })
+        .collect()
+}
+
+fn pnpm_workspace_patterns(text: &str) -> Vec<String> {
+    let mut patterns = Vec::new();
+    let mut in_packages = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if !line.starts_with(' ') && !line.starts_with('-') && !trimmed.is_empty() {
+            in_packages = trimmed.starts_with("packages:");
+            continue;
+        }
+        if in_packages {
+            if let Some(item) = trimmed.strip_prefix('-') {
+                patterns.push(
+                    item.trim()
+                        .trim_matches(|c| c == '\'' || c == '"')
+                        .to_string(),
+                );
+            }
+        }
+    }
+    patterns
+}
+
+fn node_packages(root: &Path) -> Vec<WorkspacePackage> {
+    let mut patterns = read(&root.join("pnpm-workspace.yaml"))
+        .map(|text| pnpm_workspace_patterns(&text))
+        .unwrap_or_default();
+    if patterns.is_empty() {
+        let manifest = read(&root.join("package.json"))
+            .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+            .unwrap_or(Value::Null);
+        let workspaces = match manifest.get("workspaces") {
+            Some(Value::Object(obj)) => obj.get("packages").cloned().unwrap_or(Value::Null),
+            Some(other) => other.clone(),
+            None => Value::Null,
+        };
+        patterns = workspaces
+            .as_array()
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|v| v.as_str().map(ToString::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+    }
+    expand_members(root, &patterns, &[])
+        .into_iter()
+        .filter_map(|dir| {
+            let manifest = read(&dir.join("package.json"))?;
+            let name = serde_json::from_str::<Value>(&manifest)
+                .ok()?
+                .get("name")?
+                .as_str()?
+                .to_string();
+            Some(WorkspacePackage {
+                name,
+                kind: PackageKind::Node,
+                root: dir,
+            })
+        })
+        .collect()
+}
+
+fn go_work_dirs(text: &str) -> Vec<String> {
+    let mut dirs = Vec::new();
+    let mut in_block = false;
+    for line in text.lines() {
+        let trimmed = line.split("//").next().unwrap_or("").trim();
+        if in_block {
+            if trimmed == ")" {
+                in_block = false;
+            } else if !trimmed.is_empty() {
+                dirs.push(trimmed.to_string());
+            }
+        } else if let Some(rest) = trimmed.strip_prefix("use") {
+            let rest = rest.trim();
+            if rest == "(" {
+                in_block = true;
+            } else if !rest.is_empty() {
+                dirs.push(rest.to_string());
+            }
+        }
+    }
+    dirs
+}
+
+fn go_packages(root: &Path) -> Vec<WorkspacePackage> {
+    let Some(work) = read(&root.join("go.work")) else {
+        return Vec::new();
+    };
+    go_work_dirs(&work)
+        .into_iter()
+        .filter_map(|dir| {
+            let dir = root.join(dir.trim_matches('"').trim_start_matches("./"));
+            let module = read(&dir.join("go.mod"))?
+                .lines()
+                .find_map(|line| line.trim().strip_prefix("module ").map(str::trim))
+                .map(ToString::to_string)?;
+            // Agents refer to Go modules by their last path segment.
+            let name = module.rsplit('/').next().unwrap_or(&module).to_string();
+            Some(WorkspacePackage {
+                name,
+                kind: PackageKind::Go,
+                root: dir,
+            })
+        })
+        .collect()
+}
+
+pub(crate) fn detect_packages(root: &Path) -> Vec<WorkspacePackage> {
+    let mut packages = cargo_packages(root);
+    packages.extend(node_packages(root));
+    packages.extend(go_packages(root));
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    packages
+}
+
+type PackageCache = Mutex<HashMap<PathBuf, (Instant, Vec<WorkspacePackage>)>>;
+
+fn cached_packages(root: &Path) -> Vec<WorkspacePackage> {
+    static CACHE: OnceLock<PackageCache> = OnceLock::new();
+    let cache = CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some((at, packages)) = cache.lock().ok().and_then(|c| c.get(root).cloned()) {
+        if at.elapsed() < CACHE_TTL {
+            return packages;
+        }
+    }
+    let packages = detect_packages(root);
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(root.to_path_buf(), (Instant::now(), packages.clone()));
+    }
+    packages
+}
+
+/// The innermost package containing `path`.
+fn package_for<'a>(packages: &'a [WorkspacePackage], path: &Path) -> Option<&'a WorkspacePackage> {
+    packages
+        .iter()
+        .filter(|pkg| path.starts_with(&pkg.root))
+        .max_by_key(|pkg| pkg.root.components().count())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SearchScope {
+    pub packages: Vec<WorkspacePackage>,
+    /// True when the scope was inferred from recently touched files rather
+    /// than requested; callers widen the search if it finds nothing.
+    pub inferred: bool,
+}
+
+impl SearchScope {
+    pub(crate) fn roots(&self) -> Vec<PathBuf> {
+        self.packages.iter().map(|pkg| pkg.root.clone()).collect()
+    }
+
+    pub(crate) fn names(&self) -> Vec<String> {
+        self.packages.iter().map(|pkg| pkg.name.clone()).collect()
+    }
+}
+
+fn scope_tokens(args: &Value) -> Vec<String> {
+    match args.get("scope") {
+        Some(Value::String(s)) => s
+            .split(',')
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Resolve the `scope` argument (package names or package-relative paths,
+/// `*` for everything) against the packages under `workspace_root`. Without
+/// a `scope`, and when `infer` is set, the packages holding the session's
+/// recently touched files (`__recent_paths`) are used instead.
+pub(crate) fn resolve_search_scope(
+    args: &Value,
+    workspace_root: &Path,
+    infer: bool,
+) -> Result<Option<SearchScope>, String> {
+    let tokens = scope_tokens(args);
+    if tokens.iter().any(|t| t == "*" || t == "all") {
+        return Ok(None);
+    }
+    let has_recent = args
+        .get("__recent_paths")
+        .and_then(Value::as_array)
+        .is_some_and(|paths| !paths.is_empty());
+    if tokens.is_empty() && !(infer && has_recent) {
+        return Ok(None);
+    }
+    let packages = cached_packages(workspace_root);
+    if !tokens.is_empty() {
+        let mut selected = Vec::new();
+        for token in &tokens {
+            let as_path = workspace_root.join(token.trim_start_matches("./"));
+            let found = packages
+                .iter()
+                .find(|pkg| pkg.name == *token || pkg.root == as_path);
+            match found {
+                Some(pkg) if !selected.contains(pkg) => selected.push(pkg.clone()),
+                Some(_) => {}
+                None => {
+                    let known = packages
+                        .iter()
+                        .take(MAX_LISTED_PACKAGES)
+                        .map(|pkg| pkg.name.as_str())
+                        .collect::<Vec<_>>();
+                    return Err(if known.is_empty() {
+                        format!("unknown scope `{token}`: no workspace packages detected")
+                    } else {
+                        format!("unknown scope `{token}`; packages: {}", known.join(", "))
+                    });
+                }
+            }
+        }
+        return Ok(Some(SearchScope {
+            packages: selected,
+            inferred: false,
+        }));
+    }
+    if packages.len() < 2 {
+        return Ok(None);
+    }
+    let mut selected: Vec<WorkspacePackage> = Vec::new();
+    let recent = args
+        .get("__recent_paths")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str);
+    for path in recent {
+        let path = workspace_root.join(path);
+        if let Some(pkg) = package_for(&packages, &path) {
+            if !selected.contains(pkg) {
+                selected.push(pkg.clone());
+            }
+        }
+    }
+    Ok((!selected.is_empty()).then_some(SearchScope {
+        packages: selected,
+        inferred: true,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write(path: &Path, text: &str) {
+        std::fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+        std::fs::write(path, text).expect("write");
+    }
+
+    fn monorepo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path();
+        write(
+            &root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\n  \"crates/*\", # all crates\n]\nexclude = [\"crates/skip\"]\n",
+        );
+        write(
+            &root.join("crates/core/Cargo.toml"),
+            "[package]\nname = \"app-core\"\n",
+        );
+        write(
+            &root.join("crates/cli/Cargo.toml"),
+            "[package]\nname = \"app-cli\"\n",
+        );
+        write(
+            &root.join("crates/skip/Cargo.toml"),
+            "[package]\nname = \"skipped\"\n",
+        );
+        write(
+            &root.join("pnpm-workspace.yaml"),
+            "packages:\n  - 'web/*'\n",
+        );
+        write(&root.join("web/ui/package.json"), r#"{"name":"@app/ui"}"#);
+        write(&root.join("go.work"), "go 1.22\n\nuse (\n\t./svc\n)\n");
+        write(&root.join("svc/go.mod"), "module example.com/app/svc\n");
+        dir
+    }
+
+    #[test]
+    fn detects_cargo_pnpm_and_go_packages() {
+        let dir = monorepo();
+        let names = detect_packages(dir.path())
+            .into_iter()
+            .map(|pkg| pkg.name)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["@app/ui", "app-cli", "app-core", "svc"]);
+    }
+
+    #[test]
+    fn explicit_scope_and_inferred_scope() {
+        let dir = monorepo();
+        let root = dir.path();
+        let scope = resolve_search_scope(&json!({"scope": "app-core,web/ui"}), root, true)
+            .expect("scope")
+            .expect("scoped");
+        assert_eq!(scope.names(), vec!["app-core", "@app/ui"]);
+        assert!(!scope.inferred);
+
+        let err = resolve_search_scope(&json!({"scope": "nope"}), root, true).unwrap_err();
+        assert!(err.contains("app-cli"));
+
+        let inferred = resolve_search_scope(
+            &json!({"__recent_paths": ["crates/cli/src/main.rs", "README.md"]}),
+            root,
+            true,
+        )
+        .expect("scope")
+        .expect("inferred");
+        assert_eq!(inferred.names(), vec!["app-cli"]);
+        assert!(inferred.inferred);
+
+        assert_eq!(
+            resolve_search_scope(&json!({"__recent_paths": ["crates/cli/x.rs"]}), root, false),
+            Ok(None)
+        );
+        assert_eq!(
+            resolve_search_scope(&json!({"scope": "*"}), root, true),
+            Ok(None)
+        );
+    }
+}
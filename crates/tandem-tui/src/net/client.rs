@@ -893,6 +893,9 @@ impl EngineClient {
             model: None,
             provider: None,
             permission: Some(default_tui_permission_rules()),
+            tags: None,
+            incognito: None,
+            generation_preset: None,
         };
 
         let resp = self.client.post(&url).json(&req).send().await?;
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Request/response header carrying the correlation id of an HTTP request.
+pub const CORRELATION_ID_HEADER: &str = "x-tandem-correlation-id";
+/// Generic request id header, accepted and echoed with the same value as
+/// [`CORRELATION_ID_HEADER`].
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Standard body of every error response returned by the HTTP API.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ErrorEnvelope {
+    /// Stable, machine-readable error code (e.g. `NOT_FOUND`, `QUOTA_EXCEEDED`).
+    pub code: String,
+    /// Human-readable description. Older responses named this `error`.
+    #[serde(alias = "error")]
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+    /// Whether repeating the same request later may succeed.
+    #[serde(default)]
+    pub retryable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub correlation_id: Option<String>,
+}
+
+impl ErrorEnvelope {
+    pub fn new(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            message: message.into(),
+            details: None,
+            retryable: false,
+            correlation_id: None,
+        }
+    }
+
+    /// Envelope for a bare HTTP status, using the default code and
+    /// retryability for that status.
+    pub fn from_status(status: u16, message: impl Into<String>) -> Self {
+        Self::new(default_error_code(status), message).with_retryable(is_retryable_status(status))
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn with_retryable(mut self, retryable: bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    pub fn with_correlation_id(mut self, correlation_id: impl Into<String>) -> Self {
+        self.correlation_id = Some(correlation_id.into());
+        self
+    }
+}
+
+/// Code used when a handler fails without naming one.
+pub fn default_error_code(status: u16) -> &'static str {
+    match status {
+        400 => "BAD_REQUEST",
+        401 => "AUTH_REQUIRED",
+        403 => "FORBIDDEN",
+        404 => "NOT_FOUND",
+        405 => "METHOD_NOT_ALLOWED",
+        408 => "REQUEST_TIMEOUT",
+        409 => "CONFLICT",
+        413 => "PAYLOAD_TOO_LARGE",
+        415 => "UNSUPPORTED_MEDIA_TYPE",
+        422 => "UNPROCESSABLE_ENTITY",
+        429 => "RATE_LIMITED",
+        501 => "NOT_IMPLEMENTED",
+        502 => "BAD_GATEWAY",
+        503 => "SERVICE_UNAVAILABLE",
+        504 => "GATEWAY_TIMEOUT",
+        _ if status < 500 => "REQUEST_FAILED",
+        _ => "INTERNAL_ERROR",
+    }
+}
+
+/// Statuses that describe a transient condition rather than a bad request.
+pub fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 425 | 429 | 502 | 503 | 504)
+}
@@ -7,6 +7,15 @@ pub struct EngineEvent {
     pub event_type: String,
     #[serde(default)]
     pub properties: Value,
+    /// Correlation id of the HTTP request the event was emitted for. Kept
+    /// beside `properties`, where `requestID` already names permission and
+    /// question requests, so the per-event property contracts stay unchanged.
+    #[serde(
+        rename = "correlationID",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub correlation_id: Option<String>,
 }
 
 impl EngineEvent {
@@ -14,6 +23,7 @@ impl EngineEvent {
         Self {
             event_type: event_type.into(),
             properties,
+            correlation_id: None,
         }
     }
 }
@@ -1,3 +1,4 @@
+pub mod error;
 pub mod event;
 pub mod message;
 pub mod provider;
@@ -5,6 +6,7 @@ pub mod runtime;
 pub mod session;
 pub mod tool;
 
+pub use error::*;
 pub use event::*;
 pub use message::*;
 pub use provider::*;
@@ -19,6 +19,9 @@ pub struct Message {
     #[serde(default)]
     pub parts: Vec<MessagePart>,
     pub created_at: DateTime<Utc>,
+    /// Engine annotations, e.g. `stitched` when a dropped stream was resumed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
 }
 
 impl Message {
@@ -28,6 +31,7 @@ impl Message {
             role,
             parts,
             created_at: Utc::now(),
+            metadata: None,
         }
     }
 }
@@ -60,4 +64,10 @@ pub enum MessagePartInput {
         filename: Option<String>,
         url: String,
     },
+    /// Reference to an uploaded attachment in the artifact store.
+    Artifact {
+        artifact_id: String,
+        mime: String,
+        filename: Option<String>,
+    },
 }
@@ -33,12 +33,30 @@ pub struct Session {
     pub time: SessionTime,
     pub model: Option<ModelSpec>,
     pub provider: Option<String>,
+    /// Named generation preset for this session's turns; a request's own
+    /// preset wins.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation_preset: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub environment: Option<HostRuntimeContext>,
+    /// Normalized labels, set by users or automatically (`agent:<id>`,
+    /// `routine:<id>`, `channel:<name>`, `run:<status>`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Incognito sessions are never written to memory, consolidated, captured
+    /// for analytics or kept in the response cache. They leave no run
+    /// timeline, their oversized tool outputs are not stored, and the audit
+    /// chain keeps only a hash of their event content.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub incognito: bool,
     #[serde(default)]
     pub messages: Vec<Message>,
 }
 
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
 impl Session {
     pub fn new(title: Option<String>, directory: Option<String>) -> Self {
         let now = Utc::now();
@@ -61,12 +79,31 @@ impl Session {
             },
             model: None,
             provider: None,
+            generation_preset: None,
             environment: None,
+            tags: Vec::new(),
+            incognito: false,
             messages: Vec::new(),
         }
     }
 }
 
+const MAX_SESSION_TAG_CHARS: usize = 64;
+/// Upper bound on tags kept per session.
+pub const MAX_SESSION_TAGS: usize = 32;
+
+/// Lowercase and trim a tag; `None` if it is empty, too long or contains
+/// characters outside `[a-z0-9:_./-]`.
+pub fn normalize_session_tag(raw: &str) -> Option<String> {
+    let tag = raw.trim().to_ascii_lowercase();
+    let valid = !tag.is_empty()
+        && tag.len() <= MAX_SESSION_TAG_CHARS
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '_' | '.' | '/' | '-'));
+    valid.then_some(tag)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateSessionRequest {
     pub parent_id: Option<String>,
@@ -76,6 +113,12 @@ pub struct CreateSessionRequest {
     pub model: Option<ModelSpec>,
     pub provider: Option<String>,
     pub permission: Option<Vec<serde_json::Value>>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub incognito: Option<bool>,
+    #[serde(default)]
+    pub generation_preset: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,6 +127,14 @@ pub struct SendMessageRequest {
     pub parts: Vec<crate::MessagePartInput>,
     pub model: Option<ModelSpec>,
     pub agent: Option<String>,
+    /// Extra system-prompt instructions for this request only, e.g. the
+    /// response language a channel user prefers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system: Option<String>,
+    /// Generation preset for this request; overrides the session's and the
+    /// agent's.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,7 +51,10 @@ impl From<Session> for WireSession {
             }),
             model: value.model.map(Into::into),
             provider: value.provider,
+            generation_preset: value.generation_preset,
             environment: value.environment,
+            tags: value.tags,
+            incognito: value.incognito,
             messages: value
                 .messages
                 .into_iter()
@@ -76,6 +79,7 @@ impl WireSessionMessage {
             model: None,
             deleted: None,
             reverted: None,
+            metadata: msg.metadata.clone(),
         };
 
         let parts = msg.parts.iter().map(message_part_to_value).collect();
@@ -51,12 +51,26 @@ pub struct WireSession {
     pub model: Option<WireModelSpec>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub provider: Option<String>,
+    #[serde(
+        rename = "generationPreset",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub generation_preset: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub environment: Option<HostRuntimeContext>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub incognito: bool,
     #[serde(default)]
     pub messages: Vec<WireSessionMessage>,
 }
 
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct WireSessionTime {
     pub created: u64,
@@ -87,6 +101,8 @@ pub struct WireMessageInfo {
     pub deleted: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reverted: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
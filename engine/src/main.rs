@@ -16,6 +16,8 @@ use tandem_observability::{
     canonical_logs_dir_from_root, emit_event, init_process_logging, ObservabilityEvent, ProcessKind,
 };
 use tandem_runtime::{LspManager, McpRegistry, PtyManager, WorkspaceIndex};
+use tandem_server::doctor::{run_doctor, CheckStatus, DoctorOptions};
+use tandem_server::replication::{restore_from_replica, verify_replica, ReplicationConfig};
 use tandem_server::{detect_host_runtime_context, serve, AppState, RuntimeState};
 use tandem_tools::ToolRegistry;
 use tokio::sync::RwLock;
@@ -46,6 +48,7 @@ const ENGINE_CLI_EXAMPLES: &str = r#"Examples:
   tandem-engine tool --json @payload.json
   cat payload.json | tandem-engine tool --json -
   tandem-engine providers
+  tandem-engine doctor
 "#;
 
 const STATUS_EXAMPLES: &str = r#"Examples:
@@ -88,10 +91,28 @@ const TOOL_EXAMPLES: &str = r#"Examples:
   - - (read JSON from stdin)
 "#;
 
+const DOCTOR_EXAMPLES: &str = r#"Examples:
+  tandem-engine doctor
+  tandem-engine doctor --offline
+  tandem-engine doctor --state-dir .tandem-test --port 39731
+
+Prints a JSON report; exits non-zero when any check fails.
+"#;
+
 const TOKEN_EXAMPLES: &str = r#"Examples:
   tandem-engine token generate
 "#;
 
+const REPLICA_EXAMPLES: &str = r#"Examples:
+  tandem-engine replica verify
+  tandem-engine replica restore --state-dir /var/lib/tandem
+  tandem-engine replica restore --config standby.json --overwrite
+
+The replica location comes from the `replication` config block. Stop the
+engine before restoring; files are only replaced once every object in the
+replica has passed its checksum.
+"#;
+
 #[derive(Parser, Debug)]
 #[command(name = "tandem-engine")]
 #[command(version)]
@@ -251,11 +272,50 @@ enum Command {
     },
     #[command(about = "List supported provider IDs for --provider.")]
     Providers,
+    #[command(
+        about = "Check binaries, provider connectivity, ports, state dirs, clock skew and memory DB health."
+    )]
+    #[command(after_help = DOCTOR_EXAMPLES)]
+    Doctor {
+        #[arg(
+            long,
+            env = "TANDEM_ENGINE_HOST",
+            alias = "host",
+            default_value = DEFAULT_ENGINE_HOST,
+            help = "Hostname the engine would bind."
+        )]
+        hostname: String,
+        #[arg(
+            long,
+            env = "TANDEM_ENGINE_PORT",
+            default_value_t = DEFAULT_ENGINE_PORT,
+            help = "Port that should be free for the engine."
+        )]
+        port: u16,
+        #[arg(
+            long,
+            help = "Engine state directory. If omitted, uses TANDEM_STATE_DIR or the shared Tandem path."
+        )]
+        state_dir: Option<String>,
+        #[arg(long, help = "Path to config JSON override.")]
+        config: Option<String>,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Skip provider connectivity and clock skew checks."
+        )]
+        offline: bool,
+    },
     #[command(about = "API token utilities.")]
     Token {
         #[command(subcommand)]
         action: TokenCommand,
     },
+    #[command(about = "Check or restore the state replica configured under `replication`.")]
+    Replica {
+        #[command(subcommand)]
+        action: ReplicaCommand,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -265,6 +325,38 @@ enum TokenCommand {
     Generate,
 }
 
+#[derive(Subcommand, Debug)]
+enum ReplicaCommand {
+    #[command(about = "Download every replica object and check it against the manifest.")]
+    #[command(after_help = REPLICA_EXAMPLES)]
+    Verify {
+        #[arg(
+            long,
+            help = "Engine state directory. If omitted, uses TANDEM_STATE_DIR or the shared Tandem path."
+        )]
+        state_dir: Option<String>,
+        #[arg(long, help = "Path to config JSON override.")]
+        config: Option<String>,
+    },
+    #[command(about = "Restore the state directory from the replica (engine must be stopped).")]
+    #[command(after_help = REPLICA_EXAMPLES)]
+    Restore {
+        #[arg(
+            long,
+            help = "Engine state directory. If omitted, uses TANDEM_STATE_DIR or the shared Tandem path."
+        )]
+        state_dir: Option<String>,
+        #[arg(long, help = "Path to config JSON override.")]
+        config: Option<String>,
+        #[arg(
+            long,
+            default_value_t = false,
+            help = "Replace state files that already exist."
+        )]
+        overwrite: bool,
+    },
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
@@ -519,17 +611,87 @@ async fn main() -> anyhow::Result<()> {
                 println!("  - {provider}");
             }
         }
+        Command::Doctor {
+            hostname,
+            port,
+            state_dir,
+            config,
+            offline,
+        } => {
+            let state_dir = resolve_state_dir(state_dir);
+            configure_memory_db_path_env(&state_dir);
+            let config_path = config
+                .map(PathBuf::from)
+                .unwrap_or_else(|| state_dir.join("config.json"));
+            let config = ConfigStore::new(config_path, None).await?;
+            let providers = ProviderRegistry::new(config.get().await.into());
+            let options = DoctorOptions {
+                state_dirs: vec![state_dir.clone()],
+                memory_db_path: std::env::var_os("TANDEM_MEMORY_DB_PATH").map(PathBuf::from),
+                provider_endpoints: providers.endpoints().await,
+                ports: vec![(hostname, port)],
+                network: !offline,
+            };
+            let report = run_doctor(options).await;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            if report.status == CheckStatus::Fail {
+                anyhow::bail!("doctor found {} failing check(s)", report.summary.fail);
+            }
+        }
         Command::Token { action } => match action {
             TokenCommand::Generate => {
                 let token = format!("tk_{}", Uuid::new_v4().simple());
                 println!("{token}");
             }
         },
+        Command::Replica { action } => match action {
+            ReplicaCommand::Verify { state_dir, config } => {
+                let state_dir = resolve_state_dir(state_dir);
+                let target = load_replica_target(&state_dir, config).await?;
+                let report = verify_replica(&target).await?;
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                if !report.problems.is_empty() {
+                    anyhow::bail!("replica has {} damaged file(s)", report.problems.len());
+                }
+            }
+            ReplicaCommand::Restore {
+                state_dir,
+                config,
+                overwrite,
+            } => {
+                let state_dir = resolve_state_dir(state_dir);
+                let target = load_replica_target(&state_dir, config).await?;
+                let report = restore_from_replica(&target, &state_dir, overwrite).await?;
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        },
     }
 
     Ok(())
 }
 
+async fn load_replica_target(
+    state_dir: &Path,
+    config: Option<String>,
+) -> anyhow::Result<tandem_server::replication::ReplicaTarget> {
+    let config_path = config
+        .map(PathBuf::from)
+        .unwrap_or_else(|| state_dir.join("config.json"));
+    let config = ConfigStore::new(config_path, None).await?;
+    let effective = config.get_effective_value().await;
+    let replication: ReplicationConfig = effective
+        .get("replication")
+        .cloned()
+        .map(serde_json::from_value)
+        .transpose()
+        .context("invalid `replication` config")?
+        .unwrap_or_default();
+    replication
+        .target()
+        .map_err(anyhow::Error::msg)?
+        .ok_or_else(|| anyhow::anyhow!("replication is not enabled in config"))
+}
+
 fn build_cli_overrides(
     api_key: Option<String>,
     provider: Option<String>,
@@ -792,6 +954,7 @@ async fn build_runtime(
     let phase_start = Instant::now();
     let event_bus = EventBus::new();
     let providers = ProviderRegistry::new(config.get().await.into());
+    providers.spawn_prewarm_if_enabled();
     let plugins = PluginRegistry::new(".").await?;
     let agents = AgentRegistry::new(".").await?;
     let tools = ToolRegistry::new();
@@ -6,8 +6,8 @@ use crate::keystore::{validate_api_key, validate_key_type, ApiKeyType, SecureKey
 use crate::logs::{self, LogFileInfo};
 use crate::memory::indexer::{index_workspace, IndexingStats};
 use crate::memory::types::{
-    ClearFileIndexResult, EmbeddingHealth, MemoryRetrievalMeta, MemoryStats, MemoryTier,
-    ProjectMemoryStats, StoreMessageRequest,
+    ClearFileIndexResult, EmbeddingHealth, MemoryProvenance, MemoryRetrievalMeta, MemoryStats,
+    MemoryTier, ProjectMemoryStats, StoreMessageRequest,
 };
 use crate::modes::{ModeDefinition, ModeResolution, ModeScope, ResolvedMode};
 use crate::orchestrator::{
@@ -3466,6 +3466,10 @@ async fn store_user_message_in_memory(
         "role": "user",
         "source_kind": "chat_turn"
     });
+    let provenance = MemoryProvenance {
+        session_id: Some(session_id.to_string()),
+        ..Default::default()
+    };
 
     let session_req = StoreMessageRequest {
         content: content.to_string(),
@@ -3478,6 +3482,7 @@ async fn store_user_message_in_memory(
         source_size: None,
         source_hash: None,
         metadata: Some(base_metadata.clone()),
+        provenance: Some(provenance.clone()),
     };
     let mut session_chunks_stored = 0usize;
     let mut project_chunks_stored = 0usize;
@@ -3521,6 +3526,7 @@ async fn store_user_message_in_memory(
             source_size: None,
             source_hash: None,
             metadata: Some(base_metadata),
+            provenance: Some(provenance),
         };
         match manager.store_message(project_req).await {
             Ok(ids) => {
@@ -303,6 +303,7 @@ async fn index_workspace_impl(
                 "path": relative_path,
                 "filename": path.file_name().and_then(|n| n.to_str()).unwrap_or(""),
             })),
+            provenance: None,
         };
 
         match memory_manager.store_message(request).await {
@@ -523,6 +524,7 @@ mod tests {
             source_size: None,
             source_hash: None,
             metadata: None,
+            provenance: None,
         };
         manager.store_message(req).await.unwrap();
 
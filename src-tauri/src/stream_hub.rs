@@ -1,5 +1,5 @@
 use crate::error::Result;
-use crate::memory::types::{MemoryTier, StoreMessageRequest};
+use crate::memory::types::{MemoryProvenance, MemoryTier, StoreMessageRequest};
 use crate::sidecar::{SidecarManager, StreamEvent};
 use futures::StreamExt;
 use serde::Serialize;
@@ -1368,6 +1368,11 @@ async fn persist_assistant_message_memory(
         "source_kind": "chat_turn",
         "message_id": message_id
     });
+    let provenance = MemoryProvenance {
+        session_id: Some(session_id.to_string()),
+        message_id: Some(message_id.to_string()),
+        ..Default::default()
+    };
 
     let session_req = StoreMessageRequest {
         content: content.to_string(),
@@ -1380,6 +1385,7 @@ async fn persist_assistant_message_memory(
         source_size: None,
         source_hash: None,
         metadata: Some(metadata.clone()),
+        provenance: Some(provenance.clone()),
     };
     let mut session_chunks_stored = 0usize;
     let mut project_chunks_stored = 0usize;
@@ -1424,6 +1430,7 @@ async fn persist_assistant_message_memory(
             source_size: None,
             source_hash: None,
             metadata: Some(metadata),
+            provenance: Some(provenance),
         };
         match manager.store_message(project_req).await {
             Ok(ids) => {